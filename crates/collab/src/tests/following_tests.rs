@@ -382,6 +382,20 @@ async fn test_basic_following(
     executor.run_until_parked();
     editor_b1.update(cx_b, |editor, cx| assert_eq!(editor.text(cx), "TWO"));
 
+    // Folds made by client A are mirrored by client B.
+    editor_a1.update(cx_a, |editor, cx| editor.fold_ranges([1..2], false, cx));
+    executor.run_until_parked();
+    editor_b1.update(cx_b, |editor, cx| {
+        assert_eq!(editor.display_text(cx), "T⋯O");
+    });
+    editor_a1.update(cx_a, |editor, cx| {
+        editor.unfold_ranges([0..3], true, false, cx)
+    });
+    executor.run_until_parked();
+    editor_b1.update(cx_b, |editor, cx| {
+        assert_eq!(editor.display_text(cx), "TWO");
+    });
+
     editor_a1.update(cx_a, |editor, cx| {
         editor.change_selections(None, cx, |s| s.select_ranges([3..3]));
         editor.set_scroll_position(point(0., 100.), cx);
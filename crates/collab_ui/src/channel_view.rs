@@ -498,6 +498,8 @@ impl FollowableItem for ChannelView {
                                 scroll_top_anchor: state.scroll_top_anchor,
                                 scroll_x: state.scroll_x,
                                 scroll_y: state.scroll_y,
+                                folds: state.folds,
+                                folds_changed: true,
                                 ..Default::default()
                             }),
                             cx,
@@ -0,0 +1,203 @@
+//! Measures how long the editor takes to lay out a frame in which only the cursor moved, which
+//! reuses the layouts of the rows from the previous frame, compared to a frame following an edit
+//! that inserts a line at the top of the buffer, after which every visible row is laid out again.
+//! It also measures scrolling down a row per frame, along with how often the rows scrolled into
+//! view were already shaped, for tuning the `overscan_rows` setting, and moving hundreds of
+//! cursors at once. Each scenario is measured with the layouts kept between frames, again with
+//! every frame laid out from scratch, and again without merging the quads that continue each
+//! other, such as the cursors of a column selection.
+//!
+//! Run with `cargo bench -p editor --bench editor_layout`.
+
+use editor::{
+    actions::{MoveDown, MoveLeft, MoveRight, MoveToBeginning, MoveUp, Newline},
+    Editor, EditorElement, EditorMode, EditorStyle, LineLayoutStats,
+};
+use gpui::{point, px, size, AvailableSpace, IntoElement, TestAppContext, VisualTestContext};
+use language::Point;
+use multi_buffer::MultiBuffer;
+use project::Project;
+use settings::SettingsStore;
+use std::time::{Duration, Instant};
+
+const LINE_COUNT: usize = 10_000;
+const FRAME_COUNT: u32 = 500;
+const CURSOR_COUNT: u32 = 300;
+
+fn main() {
+    gpui::run_test(
+        1,
+        0,
+        &mut |dispatcher, _| {
+            let mut cx = TestAppContext::new(dispatcher, Some("editor_layout"));
+            init(&mut cx);
+
+            let text = (0..LINE_COUNT)
+                .map(|ix| format!("    let value_{ix} = compute({ix}, \"some text\");\n"))
+                .collect::<String>();
+            let (editor, cx) = cx.add_window_view(|cx| {
+                let buffer = MultiBuffer::build_simple(&text, cx);
+                Editor::new(EditorMode::Full, buffer, None, cx)
+            });
+
+            let cached = measure_scenarios(&editor, cx);
+            editor.update(cx, |editor, cx| editor.set_layout_caching(false, cx));
+            let uncached = measure_scenarios(&editor, cx);
+            editor.update(cx, |editor, cx| {
+                editor.set_layout_caching(true, cx);
+                editor.set_quad_merging(false, cx);
+            });
+            let unmerged = measure_scenarios(&editor, cx);
+
+            println!(
+                "{:<12} {:>14} {:>14} {:>14}",
+                "per frame", "cached", "uncached", "unmerged"
+            );
+            for (name, timings) in [
+                (
+                    "cursor only",
+                    [
+                        cached.cursor_only,
+                        uncached.cursor_only,
+                        unmerged.cursor_only,
+                    ],
+                ),
+                (
+                    "scrolling",
+                    [cached.scrolling, uncached.scrolling, unmerged.scrolling],
+                ),
+                (
+                    "many cursors",
+                    [
+                        cached.many_cursors,
+                        uncached.many_cursors,
+                        unmerged.many_cursors,
+                    ],
+                ),
+                (
+                    "after edit",
+                    [cached.after_edit, uncached.after_edit, unmerged.after_edit],
+                ),
+            ] {
+                let [cached, uncached, unmerged] = timings.map(|total| total / FRAME_COUNT);
+                println!(
+                    "{name:<12} {:>14} {:>14} {:>14}",
+                    format!("{cached:?}"),
+                    format!("{uncached:?}"),
+                    format!("{unmerged:?}"),
+                );
+            }
+            println!(
+                "{:.1}% of lines scrolled into view were already shaped",
+                cached.scrolling_stats.hit_rate() * 100.
+            );
+        },
+        None,
+    );
+}
+
+fn init(cx: &mut TestAppContext) {
+    cx.update(|cx| {
+        let store = SettingsStore::test(cx);
+        cx.set_global(store);
+        theme::init(theme::LoadThemes::JustBase, cx);
+        release_channel::init("0.0.0", cx);
+        client::init_settings(cx);
+        language::init(cx);
+        Project::init_settings(cx);
+        workspace::init_settings(cx);
+        editor::init(cx);
+    });
+}
+
+/// The total time spent drawing the frames of each scenario.
+struct Timings {
+    cursor_only: Duration,
+    scrolling: Duration,
+    scrolling_stats: LineLayoutStats,
+    many_cursors: Duration,
+    after_edit: Duration,
+}
+
+fn measure_scenarios(editor: &gpui::View<Editor>, cx: &mut VisualTestContext) -> Timings {
+    editor.update(cx, |editor, cx| {
+        editor.set_scroll_position(point(0., 0.), cx);
+        editor.move_to_beginning(&MoveToBeginning, cx);
+    });
+    draw(editor, cx);
+    let cursor_only = measure(editor, cx, |editor, ix, cx| {
+        if ix % 2 == 0 {
+            editor.move_down(&MoveDown, cx);
+        } else {
+            editor.move_up(&MoveUp, cx);
+        }
+    });
+    let stats = editor.update(cx, |editor, _| editor.line_layout_stats());
+    let scrolling = measure(editor, cx, |editor, ix, cx| {
+        editor.set_scroll_position(point(0., ix as f32 + 1.), cx);
+    });
+    let scrolling_stats = editor.update(cx, |editor, _| editor.line_layout_stats());
+    let scrolling_stats = LineLayoutStats {
+        hits: scrolling_stats.hits - stats.hits,
+        misses: scrolling_stats.misses - stats.misses,
+        stale: scrolling_stats.stale - stats.stale,
+        overscanned: scrolling_stats.overscanned - stats.overscanned,
+        prefetched: scrolling_stats.prefetched - stats.prefetched,
+    };
+    // Ten cursors on each of the first rows, which are all visible.
+    editor.update(cx, |editor, cx| {
+        editor.set_scroll_position(point(0., 0.), cx);
+        editor.change_selections(None, cx, |selections| {
+            selections.select_ranges((0..CURSOR_COUNT).map(|ix| {
+                let position = Point::new(ix / 10, 4 + ix % 10 * 4);
+                position..position
+            }))
+        });
+    });
+    let many_cursors = measure(editor, cx, |editor, ix, cx| {
+        if ix % 2 == 0 {
+            editor.move_right(&MoveRight, cx);
+        } else {
+            editor.move_left(&MoveLeft, cx);
+        }
+    });
+    let after_edit = measure(editor, cx, |editor, _, cx| {
+        editor.move_to_beginning(&MoveToBeginning, cx);
+        editor.newline(&Newline, cx);
+    });
+    Timings {
+        cursor_only,
+        scrolling,
+        scrolling_stats,
+        many_cursors,
+        after_edit,
+    }
+}
+
+/// Returns the total time spent drawing the editor after each update.
+fn measure(
+    editor: &gpui::View<Editor>,
+    cx: &mut VisualTestContext,
+    update: impl Fn(&mut Editor, u32, &mut gpui::ViewContext<Editor>),
+) -> Duration {
+    let mut total = Duration::ZERO;
+    for ix in 0..FRAME_COUNT {
+        editor.update(cx, |editor, cx| update(editor, ix, cx));
+        let start = Instant::now();
+        draw(editor, cx);
+        total += start.elapsed();
+    }
+    total
+}
+
+fn draw(editor: &gpui::View<Editor>, cx: &mut VisualTestContext) {
+    let element = EditorElement::new(editor, EditorStyle::default());
+    cx.draw(
+        point(px(0.), px(0.)),
+        size(
+            AvailableSpace::Definite(px(800.)),
+            AvailableSpace::Definite(px(600.)),
+        ),
+        |_| element.into_any_element(),
+    );
+}
@@ -1,99 +1,208 @@
 //! This module contains all actions supported by [`Editor`].
 use super::*;
 
-#[derive(PartialEq, Clone, Deserialize, Default)]
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
 pub struct SelectNext {
     #[serde(default)]
     pub replace_newest: bool,
 }
 
-#[derive(PartialEq, Clone, Deserialize, Default)]
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
 pub struct SelectPrevious {
     #[serde(default)]
     pub replace_newest: bool,
 }
 
-#[derive(PartialEq, Clone, Deserialize, Default)]
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
 pub struct SelectToBeginningOfLine {
     #[serde(default)]
     pub(super) stop_at_soft_wraps: bool,
 }
 
-#[derive(PartialEq, Clone, Deserialize, Default)]
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
 pub struct MovePageUp {
     #[serde(default)]
     pub(super) center_cursor: bool,
 }
 
-#[derive(PartialEq, Clone, Deserialize, Default)]
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
 pub struct MovePageDown {
     #[serde(default)]
     pub(super) center_cursor: bool,
 }
 
-#[derive(PartialEq, Clone, Deserialize, Default)]
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
 pub struct SelectToEndOfLine {
     #[serde(default)]
     pub(super) stop_at_soft_wraps: bool,
 }
 
-#[derive(PartialEq, Clone, Deserialize, Default)]
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
 pub struct ToggleCodeActions {
     #[serde(default)]
     pub deployed_from_indicator: bool,
 }
 
-#[derive(PartialEq, Clone, Deserialize, Default)]
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
 pub struct ConfirmCompletion {
     #[serde(default)]
     pub item_ix: Option<usize>,
 }
 
-#[derive(PartialEq, Clone, Deserialize, Default)]
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
 pub struct ConfirmCodeAction {
     #[serde(default)]
     pub item_ix: Option<usize>,
 }
 
-#[derive(PartialEq, Clone, Deserialize, Default)]
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
+pub struct ConfirmClipboardHistoryEntry {
+    #[serde(default)]
+    pub item_ix: Option<usize>,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
 pub struct ToggleComments {
     #[serde(default)]
     pub advance_downwards: bool,
 }
 
-#[derive(PartialEq, Clone, Deserialize, Default)]
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
 pub struct FoldAt {
     pub buffer_row: u32,
 }
 
-#[derive(PartialEq, Clone, Deserialize, Default)]
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
 pub struct UnfoldAt {
     pub buffer_row: u32,
 }
 
-#[derive(PartialEq, Clone, Deserialize, Default)]
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
 pub struct MoveUpByLines {
     #[serde(default)]
     pub(super) lines: u32,
 }
 
-#[derive(PartialEq, Clone, Deserialize, Default)]
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
 pub struct MoveDownByLines {
     #[serde(default)]
     pub(super) lines: u32,
 }
-#[derive(PartialEq, Clone, Deserialize, Default)]
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
 pub struct SelectUpByLines {
     #[serde(default)]
     pub(super) lines: u32,
 }
 
-#[derive(PartialEq, Clone, Deserialize, Default)]
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
 pub struct SelectDownByLines {
     #[serde(default)]
     pub(super) lines: u32,
 }
 
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
+pub struct ExpandExcerptsUp {
+    #[serde(default)]
+    pub(super) lines: u32,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
+pub struct ExpandExcerptsDown {
+    #[serde(default)]
+    pub(super) lines: u32,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
+pub struct ToggleScrollLock {
+    /// Whether the locked editors scroll to the same fraction of their content rather than the
+    /// same row.
+    #[serde(default)]
+    pub proportional: bool,
+}
+
+#[derive(PartialEq, Clone, Copy, Deserialize, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ScreenshotFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
+pub struct ScreenshotSelection {
+    #[serde(default)]
+    pub format: ScreenshotFormat,
+    #[serde(default)]
+    pub window_chrome: bool,
+    #[serde(default)]
+    pub padding: Option<f32>,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
+pub struct StopRecordingMacro {
+    /// The name to save the macro under, so that it can be replayed in later sessions.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
+pub struct ReplayMacro {
+    /// The name of a saved macro to replay instead of the last recorded one.
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub count: Option<usize>,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
+pub struct UniqueLines {
+    /// Whether to only remove the lines that are identical to the line before them.
+    #[serde(default)]
+    pub adjacent: bool,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
+pub struct JoinLinesWith {
+    /// The text to separate the joined lines with. Defaults to `, `.
+    #[serde(default)]
+    pub separator: Option<String>,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
+pub struct SurroundWith {
+    /// The opening bracket, quote or HTML tag to surround selections with. When omitted, it is
+    /// asked for in a prompt.
+    #[serde(default)]
+    pub open: Option<String>,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
+pub struct CreateRestorePoint {
+    /// The name to list the restore point under in the edit history.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
+pub struct AddCursorsAtColumn {
+    /// Whether to pad lines shorter than the column with spaces, so that every cursor is placed at
+    /// the column.
+    #[serde(default)]
+    pub pad_short_lines: bool,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
+pub struct SetMark {
+    /// The name of the mark. Uppercase marks are global to the workspace, so that they can be
+    /// jumped to from any file.
+    pub name: char,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Serialize, Default)]
+pub struct GoToMark {
+    pub name: char,
+}
+
 impl_actions!(
     editor,
     [
@@ -106,28 +215,51 @@ impl_actions!(
         ToggleCodeActions,
         ConfirmCompletion,
         ConfirmCodeAction,
+        ConfirmClipboardHistoryEntry,
         ToggleComments,
         FoldAt,
         UnfoldAt,
         MoveUpByLines,
         MoveDownByLines,
         SelectUpByLines,
-        SelectDownByLines
+        SelectDownByLines,
+        ScreenshotSelection,
+        ExpandExcerptsUp,
+        ExpandExcerptsDown,
+        ToggleScrollLock,
+        StopRecordingMacro,
+        ReplayMacro,
+        UniqueLines,
+        SurroundWith,
+        JoinLinesWith,
+        CreateRestorePoint,
+        AddCursorsAtColumn,
+        SetMark,
+        GoToMark
     ]
 );
 
 gpui::actions!(
     editor,
     [
+        AcceptCopilotSuggestionLine,
+        AcceptCopilotSuggestionWord,
+        AddCursorsToLineEnds,
+        AddCursorsToLineStarts,
         AddSelectionAbove,
         AddSelectionBelow,
+        ApplyAllReplacePreviews,
+        ApplyReplacePreview,
         Backspace,
         Cancel,
         ConfirmRename,
+        ConfirmSurround,
         ContextMenuFirst,
         ContextMenuLast,
         ContextMenuNext,
         ContextMenuPrev,
+        ConvertIndentationToSpaces,
+        ConvertIndentationToTabs,
         ConvertToKebabCase,
         ConvertToLowerCamelCase,
         ConvertToLowerCase,
@@ -140,18 +272,25 @@ gpui::actions!(
         CopyPath,
         CopyPermalinkToLine,
         CopyRelativePath,
+        CopyWithFormatting,
         Cut,
         CutToEndOfLine,
+        CycleEditLocations,
+        DecreaseFontSize,
         Delete,
         DeleteLine,
+        DeleteSurrounding,
         DeleteToBeginningOfLine,
         DeleteToEndOfLine,
         DeleteToNextSubwordEnd,
         DeleteToNextWordEnd,
         DeleteToPreviousSubwordStart,
         DeleteToPreviousWordStart,
+        DismissExcerpt,
         DisplayCursorNames,
         DuplicateLine,
+        DuplicateLineUp,
+        DuplicateSelection,
         ExpandMacroRecursively,
         FindAllReferences,
         Fold,
@@ -161,14 +300,20 @@ gpui::actions!(
         GoToDefinitionSplit,
         GoToDiagnostic,
         GoToHunk,
+        GoToLastEdit,
+        GoToNextExcerpt,
         GoToPrevDiagnostic,
+        GoToPrevExcerpt,
         GoToPrevHunk,
+        GoToPrevTodo,
+        GoToTodo,
         GoToTypeDefinition,
         GoToTypeDefinitionSplit,
         OpenUrl,
         HalfPageDown,
         HalfPageUp,
         Hover,
+        IncreaseFontSize,
         Indent,
         JoinLines,
         LineDown,
@@ -178,6 +323,8 @@ gpui::actions!(
         MoveLineDown,
         MoveLineUp,
         MoveRight,
+        MoveSelectionDown,
+        MoveSelectionUp,
         MoveToBeginning,
         MoveToBeginningOfLine,
         MoveToEnclosingBracket,
@@ -193,30 +340,53 @@ gpui::actions!(
         Newline,
         NewlineAbove,
         NewlineBelow,
+        NextBookmark,
+        NextHoverTab,
         NextScreen,
         OpenExcerpts,
         OpenPermalinkToLine,
+        OpenSideBySideDiff,
         Outdent,
         PageDown,
         PageUp,
         Paste,
+        PasteFromHistory,
+        PasteWithoutFormatting,
+        PrevBookmark,
+        PreviousHoverTab,
+        Print,
         Redo,
         RedoSelection,
+        Reflow,
+        Reindent,
         Rename,
+        ResetFontSize,
+        RestartCodeCellKernel,
         RestartLanguageServer,
         RevealInFinder,
         ReverseLines,
+        RunCodeCell,
         ScrollCursorBottom,
         ScrollCursorCenter,
+        ScrollCursorLeft,
+        ScrollCursorRight,
         ScrollCursorTop,
         SelectAll,
+        SelectAllInExcerpt,
         SelectAllMatches,
+        SelectAllOccurrencesOfEachCursor,
+        SelectArgument,
         SelectDown,
+        SelectEnclosingFunction,
+        SelectEnclosingType,
         SelectLargerSyntaxNode,
         SelectLeft,
         SelectLine,
         SelectRight,
         SelectSmallerSyntaxNode,
+        SelectStringContents,
+        SelectSyntaxNodeSiblingNext,
+        SelectSyntaxNodeSiblingPrev,
         SelectToBeginning,
         SelectToEnd,
         SelectToEndOfParagraph,
@@ -229,15 +399,29 @@ gpui::actions!(
         ShowCharacterPalette,
         ShowCompletions,
         ShuffleLines,
+        SortLinesByColumn,
         SortLinesCaseInsensitive,
         SortLinesCaseSensitive,
+        SortLinesNumerically,
         SplitSelectionIntoLines,
+        StartRecordingMacro,
         Tab,
         TabPrev,
+        ToggleBookmark,
+        ToggleCenteredLayout,
+        ToggleEditHistory,
+        ToggleImagePreview,
         ToggleInlayHints,
+        ToggleInlineDiagnostics,
+        ToggleInlineDiff,
+        ToggleLigatures,
+        ToggleReadOnly,
+        ToggleReviewMode,
         ToggleSoftWrap,
         Transpose,
         Undo,
+        UndoInSelection,
+        UndoLastSelectionAddition,
         UndoSelection,
         UnfoldLines,
         UniqueLinesCaseSensitive,
@@ -1,5 +1,5 @@
 use crate::EditorSettings;
-use gpui::ModelContext;
+use gpui::{AppContext, ModelContext};
 use settings::Settings;
 use settings::SettingsStore;
 use smol::Timer;
@@ -15,15 +15,16 @@ pub struct BlinkManager {
 }
 
 impl BlinkManager {
-    pub fn new(blink_interval: Duration, cx: &mut ModelContext<Self>) -> Self {
+    pub fn new(cx: &mut ModelContext<Self>) -> Self {
         // Make sure we blink the cursors if the setting is re-enabled
         cx.observe_global::<SettingsStore>(move |this, cx| {
+            this.blink_interval = Self::blink_interval_setting(cx);
             this.blink_cursors(this.blink_epoch, cx)
         })
         .detach();
 
         Self {
-            blink_interval,
+            blink_interval: Self::blink_interval_setting(cx),
 
             blink_epoch: 0,
             blinking_paused: false,
@@ -32,6 +33,10 @@ impl BlinkManager {
         }
     }
 
+    fn blink_interval_setting(cx: &AppContext) -> Duration {
+        Duration::from_millis(EditorSettings::get_global(cx).cursor_blink_interval.max(1))
+    }
+
     fn next_blink_epoch(&mut self) -> usize {
         self.blink_epoch += 1;
         self.blink_epoch
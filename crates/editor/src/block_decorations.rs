@@ -0,0 +1,311 @@
+use crate::{
+    display_map::{
+        BlockContext, BlockDisposition, BlockId, BlockProperties, BlockStyle, RenderBlock,
+    },
+    Anchor, Editor,
+};
+use collections::{HashMap, HashSet};
+use gpui::{div, InteractiveElement, IntoElement, MouseButton, ParentElement, Styled, ViewContext};
+use std::{rc::Rc, sync::Arc};
+
+pub type BlockDecorationCallback =
+    Rc<dyn Fn(BlockDecorationEvent, &mut Editor, &mut ViewContext<Editor>)>;
+
+/// An interactive widget shown between the lines of the editor, such as a form, a button or an
+/// image, which moves along with the text it's anchored to.
+pub struct BlockDecoration {
+    pub position: Anchor,
+    pub disposition: BlockDisposition,
+    pub style: BlockStyle,
+    /// The number of lines spanned by the decoration, until it's first laid out if it fits its
+    /// content.
+    pub height: u8,
+    /// Whether the decoration is resized to the number of lines its element needs whenever it's
+    /// laid out, such as for output whose size isn't known upfront.
+    pub fit_content: bool,
+    pub render: RenderBlock,
+    /// Called after the decoration was resized, hidden, shown or removed.
+    pub on_event: Option<BlockDecorationCallback>,
+}
+
+/// A change in the lifecycle of a [`BlockDecoration`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockDecorationEvent {
+    /// The decoration was resized to fit its content, and now spans the given number of lines.
+    Resized { height: u8 },
+    /// The text the decoration is anchored to was deleted, so it's hidden until the deletion is
+    /// undone. Decorations that only make sense along with their text, like the preview of a
+    /// link, remove themselves instead, so that they don't linger while hidden.
+    Hidden,
+    /// The decoration is shown again after the deletion of its text was undone.
+    Shown,
+    /// The decoration was removed.
+    Removed,
+}
+
+struct BlockDecorationState {
+    position: Anchor,
+    fit_content: bool,
+    hidden: bool,
+    on_event: Option<BlockDecorationCallback>,
+}
+
+/// The decorations shown in the editor, by the id of the block showing them.
+#[derive(Default)]
+pub(crate) struct BlockDecorations {
+    decorations: HashMap<BlockId, BlockDecorationState>,
+}
+
+impl BlockDecorations {
+    /// Whether the given block shows a decoration that's resized to fit its content.
+    pub fn fits_content(&self, block_id: BlockId) -> bool {
+        self.decorations
+            .get(&block_id)
+            .map_or(false, |decoration| decoration.fit_content)
+    }
+}
+
+impl Editor {
+    /// Shows the given decorations between the lines of the editor, returning the ids of the
+    /// blocks showing them.
+    pub fn insert_block_decorations(
+        &mut self,
+        decorations: impl IntoIterator<Item = BlockDecoration>,
+        cx: &mut ViewContext<Self>,
+    ) -> Vec<BlockId> {
+        let mut states = Vec::new();
+        let blocks = decorations
+            .into_iter()
+            .map(|decoration| {
+                states.push(BlockDecorationState {
+                    position: decoration.position,
+                    fit_content: decoration.fit_content,
+                    hidden: false,
+                    on_event: decoration.on_event,
+                });
+                let render = decoration.render;
+                BlockProperties {
+                    position: decoration.position,
+                    height: decoration.height.max(1),
+                    style: decoration.style,
+                    render: Arc::new(move |cx: &mut BlockContext| {
+                        // Clicks within the decoration are handled by its element, rather than
+                        // moving the cursor to the row next to it.
+                        div()
+                            .size_full()
+                            .on_mouse_down(MouseButton::Left, |_, cx| cx.prevent_default())
+                            .on_mouse_down(MouseButton::Middle, |_, cx| cx.prevent_default())
+                            .on_mouse_down(MouseButton::Right, |_, cx| cx.prevent_default())
+                            .child(render(cx))
+                            .into_any_element()
+                    }),
+                    disposition: decoration.disposition,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let block_ids = self.insert_blocks(blocks, None, cx);
+        self.block_decorations
+            .decorations
+            .extend(block_ids.iter().copied().zip(states));
+        cx.notify();
+        block_ids
+    }
+
+    /// Removes the given decorations from the editor.
+    pub fn remove_block_decorations(
+        &mut self,
+        block_ids: HashSet<BlockId>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let removed = block_ids
+            .iter()
+            .filter_map(|block_id| self.block_decorations.decorations.remove(block_id))
+            .collect::<Vec<_>>();
+        self.remove_blocks(block_ids, None, cx);
+        for decoration in removed {
+            notify_block_decoration(&decoration, BlockDecorationEvent::Removed, cx);
+        }
+        cx.notify();
+    }
+
+    /// Resizes the decorations that fit their content to the number of lines measured for them.
+    pub(crate) fn resize_block_decorations(
+        &mut self,
+        heights: HashMap<BlockId, u8>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let heights = heights
+            .into_iter()
+            .filter(|(block_id, _)| self.block_decorations.decorations.contains_key(block_id))
+            .collect::<HashMap<_, _>>();
+        if heights.is_empty() {
+            return;
+        }
+
+        self.resize_blocks(heights.clone(), None, cx);
+        for (block_id, height) in heights {
+            if let Some(decoration) = self.block_decorations.decorations.get(&block_id) {
+                notify_block_decoration(decoration, BlockDecorationEvent::Resized { height }, cx);
+            }
+        }
+    }
+
+    /// Hides the decorations whose text was deleted, and shows the ones whose deletion was
+    /// undone, keeping their block ids.
+    pub(crate) fn refresh_block_decoration_visibility(&mut self, cx: &mut ViewContext<Self>) {
+        if self.block_decorations.decorations.is_empty() {
+            return;
+        }
+
+        let buffer = self.buffer.read(cx).snapshot(cx);
+        let mut hidden = HashSet::default();
+        let mut shown = HashSet::default();
+        for (block_id, decoration) in &mut self.block_decorations.decorations {
+            let valid = decoration.position.is_valid(&buffer);
+            if decoration.hidden == valid {
+                decoration.hidden = !valid;
+                if valid {
+                    shown.insert(*block_id);
+                } else {
+                    hidden.insert(*block_id);
+                }
+            }
+        }
+        if hidden.is_empty() && shown.is_empty() {
+            return;
+        }
+
+        self.display_map.update(cx, |display_map, cx| {
+            display_map.hide_blocks(hidden.clone(), cx);
+            display_map.show_blocks(shown.clone(), cx);
+        });
+        let events = hidden
+            .into_iter()
+            .map(|block_id| (block_id, BlockDecorationEvent::Hidden))
+            .chain(
+                shown
+                    .into_iter()
+                    .map(|block_id| (block_id, BlockDecorationEvent::Shown)),
+            );
+        for (block_id, event) in events {
+            if let Some(decoration) = self.block_decorations.decorations.get(&block_id) {
+                notify_block_decoration(decoration, event, cx);
+            }
+        }
+        cx.notify();
+    }
+}
+
+/// Calls the decoration's callback once the editor is done handling the change, so that the
+/// callback can update the editor.
+fn notify_block_decoration(
+    decoration: &BlockDecorationState,
+    event: BlockDecorationEvent,
+    cx: &mut ViewContext<Editor>,
+) {
+    if let Some(on_event) = decoration.on_event.clone() {
+        cx.defer(move |editor, cx| on_event(event, editor, cx));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+    use language::Point;
+    use std::cell::RefCell;
+
+    #[gpui::test]
+    async fn test_block_decoration_lifecycle(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state("ˇone\ntwo\nthree");
+        let events = Rc::new(RefCell::new(Vec::new()));
+
+        let block_ids = cx.update_editor(|editor, cx| {
+            let buffer = editor.buffer.read(cx).snapshot(cx);
+            let decoration = |row| BlockDecoration {
+                position: buffer.anchor_after(Point::new(row, 0)),
+                disposition: BlockDisposition::Below,
+                style: BlockStyle::Flex,
+                height: 1,
+                fit_content: true,
+                render: Arc::new(|_| div().into_any_element()),
+                on_event: Some(Rc::new({
+                    let events = events.clone();
+                    move |event, _, _| events.borrow_mut().push((row, event))
+                })),
+            };
+            editor.insert_block_decorations([decoration(0), decoration(1)], cx)
+        });
+
+        // Decorations that fit their content are told when they're resized.
+        cx.update_editor(|editor, cx| {
+            assert!(editor.block_decorations.fits_content(block_ids[0]));
+            editor.resize_block_decorations(HashMap::from_iter([(block_ids[0], 3)]), cx);
+            let snapshot = editor.snapshot(cx);
+            assert_eq!(snapshot.display_snapshot.max_point().row(), 6);
+        });
+        cx.run_until_parked();
+        assert_eq!(
+            events.borrow_mut().drain(..).collect::<Vec<_>>(),
+            [(0, BlockDecorationEvent::Resized { height: 3 })]
+        );
+
+        // Deleting the text a decoration is anchored to hides it, and undoing the deletion shows
+        // it again.
+        cx.update_editor(|editor, cx| {
+            editor
+                .buffer
+                .update(cx, |buffer, cx| buffer.finalize_last_transaction(cx));
+            editor.edit([(Point::new(1, 0)..Point::new(2, 0), "")], cx);
+        });
+        cx.run_until_parked();
+        assert_eq!(
+            events.borrow_mut().drain(..).collect::<Vec<_>>(),
+            [(1, BlockDecorationEvent::Hidden)]
+        );
+        cx.update_editor(|editor, cx| {
+            let snapshot = editor.snapshot(cx);
+            assert_eq!(snapshot.display_snapshot.max_point().row(), 4);
+            editor.undo(&Default::default(), cx);
+        });
+        cx.run_until_parked();
+        assert_eq!(
+            events.borrow_mut().drain(..).collect::<Vec<_>>(),
+            [(1, BlockDecorationEvent::Shown)]
+        );
+        cx.update_editor(|editor, cx| {
+            let snapshot = editor.snapshot(cx);
+            assert_eq!(snapshot.display_snapshot.max_point().row(), 6);
+        });
+
+        // Removing decorations forgets them, whether they're hidden or not.
+        cx.update_editor(|editor, cx| {
+            editor
+                .buffer
+                .update(cx, |buffer, cx| buffer.finalize_last_transaction(cx));
+            editor.edit([(Point::new(1, 0)..Point::new(2, 0), "")], cx);
+        });
+        cx.run_until_parked();
+        events.borrow_mut().clear();
+        cx.update_editor(|editor, cx| {
+            editor.remove_block_decorations(HashSet::from_iter(block_ids.iter().copied()), cx);
+            assert!(!editor.block_decorations.fits_content(block_ids[1]));
+            editor.undo(&Default::default(), cx);
+            let snapshot = editor.snapshot(cx);
+            assert_eq!(snapshot.display_snapshot.max_point().row(), 2);
+        });
+        cx.run_until_parked();
+        let mut events = events.borrow_mut().drain(..).collect::<Vec<_>>();
+        events.sort_by_key(|(row, _)| *row);
+        assert_eq!(
+            events,
+            [
+                (0, BlockDecorationEvent::Removed),
+                (1, BlockDecorationEvent::Removed)
+            ]
+        );
+    }
+}
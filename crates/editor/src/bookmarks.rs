@@ -0,0 +1,267 @@
+use crate::{
+    display_map::ToDisplayPoint, persistence::DB, scroll::Autoscroll, Direction, DisplayPoint,
+    Editor, EditorSnapshot, NextBookmark, PrevBookmark, ToggleBookmark,
+};
+use collections::HashMap;
+use gpui::{AppContext, EntityId, Global, Model, Subscription, ViewContext, WeakModel};
+use language::{Buffer, Point, ToOffset, ToPoint};
+use multi_buffer::ToPoint as _;
+use std::{ops::Range, path::PathBuf};
+use util::ResultExt;
+use workspace::WorkspaceId;
+
+/// The bookmarks of every buffer, shared by all the editors showing the buffer so that they
+/// neither diverge nor overwrite each other's saved bookmarks.
+#[derive(Default)]
+struct Bookmarks {
+    buffers: HashMap<EntityId, BufferBookmarks>,
+}
+
+impl Global for Bookmarks {}
+
+struct BufferBookmarks {
+    buffer: WeakModel<Buffer>,
+    /// Sorted by their position in the buffer.
+    anchors: Vec<language::Anchor>,
+}
+
+/// Notifies the editor whenever the bookmarks of any buffer change, finding its scrollbar markers
+/// again.
+pub(crate) fn observe_bookmarks(cx: &mut ViewContext<Editor>) -> Subscription {
+    cx.observe_global::<Bookmarks>(|editor, cx| {
+        editor.scrollbar_marker_cache.invalidate();
+        cx.notify()
+    })
+}
+
+fn buffer_bookmarks<'a>(buffer: &Model<Buffer>, cx: &'a AppContext) -> &'a [language::Anchor] {
+    cx.try_global::<Bookmarks>()
+        .and_then(|bookmarks| bookmarks.buffers.get(&buffer.entity_id()))
+        .map_or(&[], |bookmarks| bookmarks.anchors.as_slice())
+}
+
+fn set_buffer_bookmarks(
+    buffer: &Model<Buffer>,
+    mut anchors: Vec<language::Anchor>,
+    cx: &mut AppContext,
+) {
+    let snapshot = buffer.read(cx).snapshot();
+    anchors.sort_by(|a, b| a.cmp(b, &snapshot));
+    let bookmarks = cx.default_global::<Bookmarks>();
+    bookmarks
+        .buffers
+        .retain(|_, bookmarks| bookmarks.buffer.upgrade().is_some());
+    bookmarks.buffers.insert(
+        buffer.entity_id(),
+        BufferBookmarks {
+            buffer: buffer.downgrade(),
+            anchors,
+        },
+    );
+}
+
+impl Editor {
+    /// Adds a bookmark to the lines of the selections' heads, or removes their bookmarks if they
+    /// all have one.
+    pub fn toggle_bookmark(&mut self, _: &ToggleBookmark, cx: &mut ViewContext<Self>) {
+        let mut rows_by_buffer = HashMap::<EntityId, (Model<Buffer>, Vec<u32>)>::default();
+        for selection in self.selections.all::<Point>(cx) {
+            let Some((buffer, anchor)) = self
+                .buffer
+                .read(cx)
+                .text_anchor_for_position(selection.head(), cx)
+            else {
+                continue;
+            };
+            let row = anchor.to_point(&buffer.read(cx).snapshot()).row;
+            rows_by_buffer
+                .entry(buffer.entity_id())
+                .or_insert_with(|| (buffer, Vec::new()))
+                .1
+                .push(row);
+        }
+
+        let all_bookmarked = rows_by_buffer.values().all(|(buffer, rows)| {
+            let bookmarked_rows = bookmark_rows(buffer, cx);
+            rows.iter().all(|row| bookmarked_rows.contains(row))
+        });
+        for (buffer, rows) in rows_by_buffer.into_values() {
+            let snapshot = buffer.read(cx).snapshot();
+            let mut anchors = buffer_bookmarks(&buffer, cx).to_vec();
+            if all_bookmarked {
+                anchors.retain(|anchor| !rows.contains(&anchor.to_point(&snapshot).row));
+            } else {
+                let bookmarked_rows = bookmark_rows(&buffer, cx);
+                for row in rows {
+                    if !bookmarked_rows.contains(&row) {
+                        anchors.push(snapshot.anchor_after(Point::new(row, 0)));
+                    }
+                }
+            }
+            set_buffer_bookmarks(&buffer, anchors, cx);
+        }
+        self.save_bookmarks(cx);
+    }
+
+    pub fn next_bookmark(&mut self, _: &NextBookmark, cx: &mut ViewContext<Self>) {
+        self.go_to_bookmark_in_direction(Direction::Next, cx);
+    }
+
+    pub fn prev_bookmark(&mut self, _: &PrevBookmark, cx: &mut ViewContext<Self>) {
+        self.go_to_bookmark_in_direction(Direction::Prev, cx);
+    }
+
+    fn go_to_bookmark_in_direction(&mut self, direction: Direction, cx: &mut ViewContext<Self>) {
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let max_point = snapshot.max_point();
+        let rows = self.bookmark_rows_in_range(Point::zero()..max_point, cx);
+        let head = self.selections.newest::<Point>(cx).head().row;
+        let target = match direction {
+            Direction::Next => rows.iter().find(|row| **row > head).or(rows.first()),
+            Direction::Prev => rows.iter().rev().find(|row| **row < head).or(rows.last()),
+        };
+        if let Some(row) = target.copied() {
+            let point = Point::new(row, 0);
+            self.change_selections(Some(Autoscroll::fit()), cx, |s| {
+                s.select_ranges([point..point])
+            });
+        }
+    }
+
+    /// Returns the sorted display rows in the given range that show bookmarked lines.
+    pub(crate) fn bookmark_display_rows(
+        &self,
+        rows: Range<u32>,
+        snapshot: &EditorSnapshot,
+        cx: &AppContext,
+    ) -> Vec<u32> {
+        let start = DisplayPoint::new(rows.start, 0).to_point(&snapshot.display_snapshot);
+        let end = DisplayPoint::new(rows.end, 0).to_point(&snapshot.display_snapshot);
+        let mut display_rows = self
+            .bookmark_rows_in_range(start..end, cx)
+            .into_iter()
+            .map(|row| {
+                Point::new(row, 0)
+                    .to_display_point(&snapshot.display_snapshot)
+                    .row()
+            })
+            .filter(|row| rows.contains(row))
+            .collect::<Vec<_>>();
+        display_rows.dedup();
+        display_rows
+    }
+
+    /// Whether any of the editor's buffers has bookmarks.
+    pub(crate) fn has_bookmarks(&self, cx: &AppContext) -> bool {
+        self.buffer
+            .read(cx)
+            .all_buffers()
+            .iter()
+            .any(|buffer| !buffer_bookmarks(buffer, cx).is_empty())
+    }
+
+    /// Returns the sorted multi-buffer rows of the bookmarks in the given range, only looking at
+    /// the parts of the excerpts within it.
+    fn bookmark_rows_in_range(&self, range: Range<Point>, cx: &AppContext) -> Vec<u32> {
+        let multi_buffer = self.buffer.read(cx);
+        let snapshot = multi_buffer.snapshot(cx);
+        let mut rows = Vec::new();
+        for (buffer, range, excerpt_id) in multi_buffer.range_to_buffer_ranges(range, cx) {
+            let bookmarks = buffer_bookmarks(&buffer, cx);
+            let buffer = buffer.read(cx);
+            let first_ix =
+                bookmarks.partition_point(|anchor| anchor.to_offset(buffer) < range.start);
+            for anchor in &bookmarks[first_ix..] {
+                if anchor.to_offset(buffer) > range.end {
+                    break;
+                }
+                rows.push(
+                    snapshot
+                        .anchor_in_excerpt(excerpt_id, *anchor)
+                        .to_point(&snapshot)
+                        .row,
+                );
+            }
+        }
+        rows.sort_unstable();
+        rows.dedup();
+        rows
+    }
+
+    /// Restores the bookmarks that were saved for the editor's file in the given workspace,
+    /// unless another editor already restored them, in which case they may have moved with
+    /// unsaved edits.
+    pub(crate) fn read_bookmarks_from_db(
+        &mut self,
+        workspace_id: WorkspaceId,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(buffer) = self.buffer.read(cx).as_singleton() else {
+            return;
+        };
+        let is_restored = |buffer: &Model<Buffer>, cx: &AppContext| {
+            cx.try_global::<Bookmarks>().map_or(false, |bookmarks| {
+                bookmarks.buffers.contains_key(&buffer.entity_id())
+            })
+        };
+        if is_restored(&buffer, cx) {
+            return;
+        }
+        let Some(path) = self.bookmarks_path(cx) else {
+            return;
+        };
+        cx.spawn(|this, mut cx| async move {
+            let rows = DB.get_bookmarks(workspace_id, path).await?;
+            this.update(&mut cx, |_, cx| {
+                if is_restored(&buffer, cx) {
+                    return;
+                }
+                let snapshot = buffer.read(cx).snapshot();
+                let anchors = rows
+                    .into_iter()
+                    .filter(|row| *row <= snapshot.max_point().row)
+                    .map(|row| snapshot.anchor_after(Point::new(row, 0)))
+                    .collect();
+                set_buffer_bookmarks(&buffer, anchors, cx);
+                cx.notify();
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    /// Saves the rows of the bookmarks, so that they are restored when the file is reopened.
+    pub(crate) fn save_bookmarks(&self, cx: &mut ViewContext<Self>) {
+        let Some((_, workspace_id)) = self.workspace.as_ref() else {
+            return;
+        };
+        let Some(buffer) = self.buffer.read(cx).as_singleton() else {
+            return;
+        };
+        let Some(path) = self.bookmarks_path(cx) else {
+            return;
+        };
+        let workspace_id = *workspace_id;
+        let rows = bookmark_rows(&buffer, cx);
+        cx.background_executor()
+            .spawn(async move { DB.save_bookmarks(workspace_id, path, rows).await.log_err() })
+            .detach();
+    }
+
+    /// Bookmarks are persisted for editors showing a single local file.
+    fn bookmarks_path(&self, cx: &ViewContext<Self>) -> Option<PathBuf> {
+        let buffer = self.buffer.read(cx).as_singleton()?;
+        let file = buffer.read(cx).file()?.as_local()?;
+        Some(file.abs_path(cx))
+    }
+}
+
+/// Returns the sorted rows of the bookmarks in the given buffer.
+fn bookmark_rows(buffer: &Model<Buffer>, cx: &AppContext) -> Vec<u32> {
+    let snapshot = buffer.read(cx).snapshot();
+    let mut rows = buffer_bookmarks(buffer, cx)
+        .iter()
+        .map(|anchor| anchor.to_point(&snapshot).row)
+        .collect::<Vec<_>>();
+    rows.dedup();
+    rows
+}
@@ -0,0 +1,262 @@
+use crate::{
+    display_map::{DisplayTextSnapshot, ToDisplayPoint},
+    DisplayPoint, EditorSnapshot,
+};
+use gpui::Hsla;
+use multi_buffer::MultiBufferSnapshot;
+use std::{cmp::Reverse, ops::Range};
+use theme::PlayerColors;
+
+/// A pair of matching brackets, and the number of bracket pairs enclosing it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct BracketPair {
+    pub open: Range<usize>,
+    pub close: Range<usize>,
+    pub depth: usize,
+}
+
+/// The color of a bracket that is being laid out.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct BracketColor {
+    /// The bracket's display row, relative to the first row being laid out.
+    pub row: u32,
+    pub columns: Range<usize>,
+    pub color: Hsla,
+}
+
+/// Returns the pairs of brackets overlapping the given range, ordered by the start of their
+/// opening bracket.
+///
+/// A pair's depth counts the pairs enclosing it in its buffer, including those extending past
+/// the excerpt containing it, so that it doesn't depend on how the buffer is excerpted.
+pub(crate) fn bracket_pairs(buffer: &MultiBufferSnapshot, range: Range<usize>) -> Vec<BracketPair> {
+    let mut pairs = Vec::new();
+    for (excerpt_buffer, context, excerpt_offset) in buffer.excerpts_in_range(range.clone()) {
+        let start = context.start + range.start.saturating_sub(excerpt_offset);
+        let end = (context.start + range.end.saturating_sub(excerpt_offset)).min(context.end);
+        let is_bracket = |range: &Range<usize>| {
+            range.len() == 1
+                && matches!(
+                    excerpt_buffer.chars_at(range.start).next(),
+                    Some('(' | ')' | '[' | ']' | '{' | '}' | '<' | '>')
+                )
+        };
+        let buffer_pairs = assign_depths(
+            excerpt_buffer
+                .bracket_ranges(start..end)
+                .filter(|(open, close)| is_bracket(open) && is_bracket(close))
+                .collect(),
+        );
+
+        let to_excerpt_offsets = |range: Range<usize>| {
+            range.start - context.start + excerpt_offset..range.end - context.start + excerpt_offset
+        };
+        pairs.extend(
+            buffer_pairs
+                .into_iter()
+                .filter(|pair| pair.open.start >= context.start && pair.close.end <= context.end)
+                .map(|pair| BracketPair {
+                    open: to_excerpt_offsets(pair.open),
+                    close: to_excerpt_offsets(pair.close),
+                    depth: pair.depth,
+                }),
+        );
+    }
+    pairs
+}
+
+fn assign_depths(mut pairs: Vec<(Range<usize>, Range<usize>)>) -> Vec<BracketPair> {
+    pairs.sort_by_key(|(open, close)| (open.start, Reverse(close.end)));
+    pairs.dedup();
+
+    let mut enclosing_ends = Vec::new();
+    pairs
+        .into_iter()
+        .map(|(open, close)| {
+            while enclosing_ends
+                .last()
+                .map_or(false, |end| *end <= open.start)
+            {
+                enclosing_ends.pop();
+            }
+            let depth = enclosing_ends.len();
+            enclosing_ends.push(close.end);
+            BracketPair { open, close, depth }
+        })
+        .collect()
+}
+
+/// Returns the color of each bracket in the given display rows, rotating through the players'
+/// colors with the depth of the bracket's pair.
+pub(crate) fn bracket_colors(
+    pairs: &[BracketPair],
+    rows: Range<u32>,
+    snapshot: &EditorSnapshot,
+    players: &PlayerColors,
+) -> Vec<BracketColor> {
+    bracket_colors_at(pairs, rows, players, |offset| {
+        let point = offset.to_display_point(&snapshot.display_snapshot);
+        (point.row(), point.column())
+    })
+}
+
+/// Like [`bracket_colors`], for the given rows of a display snapshot's text without its blocks.
+pub(crate) fn text_bracket_colors(
+    pairs: &[BracketPair],
+    rows: Range<u32>,
+    snapshot: &DisplayTextSnapshot,
+    players: &PlayerColors,
+) -> Vec<BracketColor> {
+    bracket_colors_at(pairs, rows, players, |offset| {
+        snapshot.offset_to_point(offset)
+    })
+}
+
+/// Returns the color of each bracket in the given rows, given the row and column of a buffer
+/// offset.
+fn bracket_colors_at(
+    pairs: &[BracketPair],
+    rows: Range<u32>,
+    players: &PlayerColors,
+    point_for_offset: impl Fn(usize) -> (u32, u32),
+) -> Vec<BracketColor> {
+    let mut colors = Vec::new();
+    for pair in pairs {
+        let color = bracket_pair_color(pair, players);
+        for range in [&pair.open, &pair.close] {
+            let (start_row, start_column) = point_for_offset(range.start);
+            let (end_row, end_column) = point_for_offset(range.end);
+            if start_row == end_row && start_column < end_column && rows.contains(&start_row) {
+                colors.push(BracketColor {
+                    row: start_row - rows.start,
+                    columns: start_column as usize..end_column as usize,
+                    color,
+                });
+            }
+        }
+    }
+    colors.sort_by_key(|color| (color.row, color.columns.start));
+    colors
+}
+
+pub(crate) fn bracket_pair_color(pair: &BracketPair, players: &PlayerColors) -> Hsla {
+    players.color_for_participant(pair.depth as u32).cursor
+}
+
+/// Returns the ranges to underline to connect the innermost pair of brackets containing the
+/// given offset.
+pub(crate) fn cursor_bracket_pair_underlines(
+    pairs: &[BracketPair],
+    cursor: usize,
+    snapshot: &EditorSnapshot,
+) -> Option<(&BracketPair, Vec<Range<DisplayPoint>>)> {
+    let pair = pairs
+        .iter()
+        .filter(|pair| (pair.open.start..=pair.close.end).contains(&cursor))
+        .max_by_key(|pair| pair.depth)?;
+    let open = pair.open.start.to_display_point(&snapshot.display_snapshot)
+        ..pair.open.end.to_display_point(&snapshot.display_snapshot);
+    let close = pair
+        .close
+        .start
+        .to_display_point(&snapshot.display_snapshot)
+        ..pair.close.end.to_display_point(&snapshot.display_snapshot);
+    let underlines = if open.start.row() == close.end.row() {
+        vec![open.start..close.end]
+    } else {
+        vec![open, close]
+    };
+    Some((pair, underlines))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor_tests::init_test;
+    use gpui::{Context as _, TestAppContext};
+    use language::{Buffer, Capability, Language, LanguageConfig};
+    use multi_buffer::{ExcerptRange, MultiBuffer};
+    use std::sync::Arc;
+    use text::{BufferId, Point};
+
+    #[test]
+    fn test_assign_depths() {
+        // a(b[c]{d(e)})(f)
+        let pairs = assign_depths(vec![
+            (13..14, 15..16),
+            (1..2, 12..13),
+            (3..4, 5..6),
+            (6..7, 11..12),
+            (8..9, 10..11),
+            (3..4, 5..6),
+        ]);
+        assert_eq!(
+            pairs
+                .iter()
+                .map(|pair| (pair.open.start, pair.depth))
+                .collect::<Vec<_>>(),
+            vec![(1, 0), (3, 1), (6, 1), (8, 2), (13, 0)]
+        );
+    }
+
+    #[gpui::test]
+    async fn test_bracket_pair_depths_across_excerpts(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let language = Arc::new(
+            Language::new(
+                LanguageConfig::default(),
+                Some(tree_sitter_rust::language()),
+            )
+            .with_brackets_query(
+                r#"
+                    ("(" @open ")" @close)
+                    ("{" @open "}" @close)
+                    "#,
+            )
+            .unwrap(),
+        );
+        let buffer = cx.new_model(|cx| {
+            Buffer::new(
+                0,
+                BufferId::new(cx.entity_id().as_u64()).unwrap(),
+                "fn f() {\n    g(h(1));\n}\n",
+            )
+            .with_language(language, cx)
+        });
+        cx.condition(&buffer, |buffer, _| !buffer.is_parsing())
+            .await;
+
+        // The second excerpt starts inside the function's body.
+        let multibuffer = cx.new_model(|cx| {
+            let mut multibuffer = MultiBuffer::new(0, Capability::ReadWrite);
+            multibuffer.push_excerpts(
+                buffer.clone(),
+                [
+                    ExcerptRange {
+                        context: Point::new(0, 0)..Point::new(3, 0),
+                        primary: None,
+                    },
+                    ExcerptRange {
+                        context: Point::new(1, 0)..Point::new(2, 0),
+                        primary: None,
+                    },
+                ],
+                cx,
+            );
+            multibuffer
+        });
+        let snapshot = cx.update(|cx| multibuffer.read(cx).snapshot(cx));
+        let pairs = bracket_pairs(&snapshot, 0..snapshot.len());
+        assert_eq!(
+            pairs
+                .iter()
+                .map(|pair| (
+                    snapshot.text_for_range(pair.open.clone()).collect(),
+                    pair.depth
+                ))
+                .collect::<Vec<(String, _)>>(),
+            [("(", 0), ("{", 0), ("(", 1), ("(", 2), ("(", 1), ("(", 2)]
+                .map(|(bracket, depth)| (bracket.to_string(), depth))
+        );
+    }
+}
@@ -0,0 +1,211 @@
+use crate::{scroll::Autoscroll, Anchor, Editor, EditorSettings};
+use gpui::{AnyElement, ViewContext};
+use language::OutlineItem;
+use multi_buffer::{ExcerptId, MultiBufferSnapshot};
+use settings::Settings;
+use std::sync::Arc;
+use ui::{popover_menu, prelude::*, ContextMenu};
+
+/// The outline of the buffer shown in the breadcrumb bar, which is kept until the buffer is
+/// edited or reparsed rather than being recomputed on every render.
+#[derive(Default)]
+pub(crate) struct BreadcrumbOutline {
+    cached: Option<(
+        (ExcerptId, clock::Global, usize),
+        Arc<[OutlineItem<Anchor>]>,
+    )>,
+}
+
+impl BreadcrumbOutline {
+    /// Returns the outline of the multibuffer if it has a single buffer, computing it unless the
+    /// buffer is unchanged since it was last computed.
+    fn items(&mut self, snapshot: &MultiBufferSnapshot) -> Arc<[OutlineItem<Anchor>]> {
+        let Some((excerpt_id, _, buffer)) = snapshot.as_singleton() else {
+            return Vec::new().into();
+        };
+        let key = (*excerpt_id, buffer.version().clone(), buffer.parse_count());
+        if let Some((cached_key, items)) = &self.cached {
+            if *cached_key == key {
+                return items.clone();
+            }
+        }
+
+        let items: Arc<[_]> = snapshot
+            .outline(None)
+            .map_or_else(Vec::new, |outline| outline.items)
+            .into();
+        self.cached = Some((key, items.clone()));
+        items
+    }
+}
+
+struct Crumb {
+    text: SharedString,
+    siblings: Vec<(SharedString, Anchor)>,
+}
+
+/// Renders the file path and the chain of symbols enclosing the newest cursor.
+/// Clicking a symbol opens a menu listing its siblings in the outline.
+pub(crate) fn render_breadcrumb_bar(
+    editor: &mut Editor,
+    cx: &mut ViewContext<Editor>,
+) -> Option<AnyElement> {
+    if !EditorSettings::get_global(cx).breadcrumb_bar {
+        return None;
+    }
+
+    let syntax_theme = cx.theme().syntax().clone();
+    let cursor = editor.selections.newest_anchor().head();
+    let multibuffer = editor.buffer().read(cx);
+    let snapshot = multibuffer.snapshot(cx);
+    let (buffer_id, symbols) = multibuffer.symbols_containing(cursor, Some(&syntax_theme), cx)?;
+    let path = multibuffer
+        .buffer(buffer_id)?
+        .read(cx)
+        .snapshot()
+        .resolve_file_path(cx, false)
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_else(|| "untitled".to_string());
+
+    let outline = editor.breadcrumb_outline.items(&snapshot);
+    let crumbs = symbols
+        .into_iter()
+        .map(|symbol| {
+            let siblings = outline
+                .iter()
+                .position(|item| {
+                    item.range.start.cmp(&symbol.range.start, &snapshot).is_eq()
+                        && item.range.end.cmp(&symbol.range.end, &snapshot).is_eq()
+                })
+                .map(|ix| {
+                    sibling_indices(&outline, ix)
+                        .into_iter()
+                        .map(|ix| (outline[ix].text.clone().into(), outline[ix].range.start))
+                        .collect()
+                })
+                .unwrap_or_default();
+            Crumb {
+                text: symbol.text.into(),
+                siblings,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let editor_handle = cx.view().downgrade();
+    Some(
+        h_flex()
+            .id("breadcrumb-bar")
+            .w_full()
+            .px_2()
+            .gap_0p5()
+            .overflow_x_hidden()
+            .bg(cx.theme().colors().editor_background)
+            .border_b_1()
+            .border_color(cx.theme().colors().border_variant)
+            .child(Label::new(path).size(LabelSize::Small).color(Color::Muted))
+            .children(crumbs.into_iter().enumerate().map(|(ix, crumb)| {
+                let editor_handle = editor_handle.clone();
+                h_flex()
+                    .gap_0p5()
+                    .child(
+                        Icon::new(IconName::ChevronRight)
+                            .size(IconSize::Small)
+                            .color(Color::Muted),
+                    )
+                    .child(
+                        popover_menu(("breadcrumb", ix))
+                            .trigger(
+                                Button::new(("breadcrumb-trigger", ix), crumb.text)
+                                    .style(ButtonStyle::Subtle)
+                                    .label_size(LabelSize::Small),
+                            )
+                            .menu(move |cx| {
+                                let siblings = crumb.siblings.clone();
+                                let editor_handle = editor_handle.clone();
+                                Some(ContextMenu::build(cx, move |mut menu, _| {
+                                    for (text, position) in siblings {
+                                        let editor_handle = editor_handle.clone();
+                                        menu = menu.entry(text, None, move |cx| {
+                                            editor_handle
+                                                .update(cx, |editor, cx| {
+                                                    editor.change_selections(
+                                                        Some(Autoscroll::center()),
+                                                        cx,
+                                                        |s| {
+                                                            s.select_anchor_ranges([
+                                                                position..position
+                                                            ])
+                                                        },
+                                                    );
+                                                    editor.focus(cx);
+                                                })
+                                                .ok();
+                                        });
+                                    }
+                                    menu
+                                }))
+                            }),
+                    )
+            }))
+            .into_any_element(),
+    )
+}
+
+/// Returns the indices of the outline items that share a parent and depth with the item at `ix`,
+/// including the item itself.
+pub(crate) fn sibling_indices<T>(items: &[OutlineItem<T>], ix: usize) -> Vec<usize> {
+    let depth = items[ix].depth;
+    let start = items[..ix]
+        .iter()
+        .rposition(|item| item.depth < depth)
+        .map_or(0, |parent_ix| parent_ix + 1);
+    let end = items[ix..]
+        .iter()
+        .position(|item| item.depth < depth)
+        .map_or(items.len(), |offset| ix + offset);
+    (start..end)
+        .filter(|&sibling_ix| items[sibling_ix].depth == depth)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor_tests::init_test;
+    use multi_buffer::MultiBuffer;
+
+    #[gpui::test]
+    fn test_breadcrumb_outline_cache(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let buffer = cx.update(|cx| MultiBuffer::build_simple("fn a() {}\n", cx));
+        let mut outline = BreadcrumbOutline::default();
+
+        let snapshot = cx.update(|cx| buffer.read(cx).snapshot(cx));
+        let items = outline.items(&snapshot);
+        assert!(Arc::ptr_eq(&items, &outline.items(&snapshot)));
+
+        // The outline is computed again once the buffer is edited.
+        buffer.update(cx, |buffer, cx| buffer.edit([(0..0, "\n")], None, cx));
+        let snapshot = cx.update(|cx| buffer.read(cx).snapshot(cx));
+        assert!(!Arc::ptr_eq(&items, &outline.items(&snapshot)));
+    }
+
+    #[test]
+    fn test_sibling_indices() {
+        let items = [0, 1, 1, 2, 1, 0, 1]
+            .into_iter()
+            .map(|depth| OutlineItem {
+                depth,
+                range: 0..0,
+                text: String::new(),
+                highlight_ranges: Vec::new(),
+                name_ranges: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(sibling_indices(&items, 0), vec![0, 5]);
+        assert_eq!(sibling_indices(&items, 2), vec![1, 2, 4]);
+        assert_eq!(sibling_indices(&items, 3), vec![3]);
+        assert_eq!(sibling_indices(&items, 6), vec![6]);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{
+    display_map::ToDisplayPoint, DisplayPoint, Editor, EditorMode, EditorSettings, EditorSnapshot,
+};
+use gpui::{AppContext, ViewContext};
+use language::ToOffset;
+use settings::Settings;
+use std::ops::Range;
+
+impl Editor {
+    /// Whether the breakpoints set in the project are shown in a lane of the gutter, and toggled
+    /// by clicking it.
+    pub(crate) fn breakpoints_enabled(&self, cx: &AppContext) -> bool {
+        self.mode == EditorMode::Full
+            && self.project.is_some()
+            && EditorSettings::get_global(cx).breakpoints
+    }
+
+    /// Toggles the breakpoint on the buffer line shown at the given display row.
+    pub(crate) fn toggle_breakpoint_for_display_row(
+        &mut self,
+        display_row: u32,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if !self.breakpoints_enabled(cx) {
+            return;
+        }
+        let Some(project) = self.project.clone() else {
+            return;
+        };
+        let snapshot = self.snapshot(cx);
+        let point = DisplayPoint::new(display_row, 0).to_point(&snapshot.display_snapshot);
+        let Some((buffer, offset, _)) = self.buffer.read(cx).point_to_buffer_offset(point, cx)
+        else {
+            return;
+        };
+        let row = buffer.read(cx).offset_to_point(offset).row;
+        project.update(cx, |project, cx| {
+            project.toggle_breakpoint(&buffer, row, cx)
+        });
+    }
+
+    /// Returns the sorted display rows in the given range that show lines with breakpoints.
+    pub(crate) fn breakpoint_display_rows(
+        &self,
+        rows: Range<u32>,
+        snapshot: &EditorSnapshot,
+        cx: &AppContext,
+    ) -> Vec<u32> {
+        let Some(project) = self
+            .project
+            .as_ref()
+            .filter(|_| self.breakpoints_enabled(cx))
+        else {
+            return Vec::new();
+        };
+        let project = project.read(cx);
+        let start = DisplayPoint::new(rows.start, 0).to_point(&snapshot.display_snapshot);
+        let end = DisplayPoint::new(rows.end, 0).to_point(&snapshot.display_snapshot);
+
+        // Only the breakpoints in the parts of the excerpts that are shown are looked at.
+        let mut display_rows = Vec::new();
+        for (buffer, range, excerpt_id) in
+            self.buffer.read(cx).range_to_buffer_ranges(start..end, cx)
+        {
+            let buffer = buffer.read(cx);
+            let breakpoints = project.breakpoints_for_buffer(buffer, cx);
+            let first_ix = breakpoints
+                .partition_point(|breakpoint| breakpoint.position.to_offset(buffer) < range.start);
+            for breakpoint in &breakpoints[first_ix..] {
+                if breakpoint.position.to_offset(buffer) > range.end {
+                    break;
+                }
+                let row = snapshot
+                    .buffer_snapshot
+                    .anchor_in_excerpt(excerpt_id, breakpoint.position)
+                    .to_display_point(&snapshot.display_snapshot)
+                    .row();
+                if rows.contains(&row) {
+                    display_rows.push(row);
+                }
+            }
+        }
+        display_rows.sort_unstable();
+        display_rows.dedup();
+        display_rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+    use indoc::indoc;
+    use settings::SettingsStore;
+
+    #[gpui::test]
+    async fn test_breakpoints(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state(indoc! {"
+            ˇone
+            two
+            three
+            four
+        "});
+
+        // Breakpoints are neither shown nor toggled unless they are enabled.
+        cx.update_editor(|editor, cx| {
+            editor.toggle_breakpoint_for_display_row(1, cx);
+            let snapshot = editor.snapshot(cx);
+            assert!(editor
+                .breakpoint_display_rows(0..4, &snapshot, cx)
+                .is_empty());
+        });
+        let project = cx.update_editor(|editor, _| editor.project.clone().unwrap());
+        project.read_with(&cx.cx, |project, cx| {
+            assert!(project.breakpoints(cx).is_empty())
+        });
+
+        cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|settings, cx| {
+                settings.update_user_settings::<EditorSettings>(cx, |settings| {
+                    settings.breakpoints = Some(true);
+                });
+            });
+        });
+        cx.update_editor(|editor, cx| {
+            editor.toggle_breakpoint_for_display_row(1, cx);
+            editor.toggle_breakpoint_for_display_row(3, cx);
+            let snapshot = editor.snapshot(cx);
+            assert_eq!(editor.breakpoint_display_rows(0..4, &snapshot, cx), [1, 3]);
+            assert_eq!(editor.breakpoint_display_rows(2..4, &snapshot, cx), [3]);
+        });
+
+        // Breakpoints move with the lines they are set on.
+        cx.set_selections_state(indoc! {"
+            ˇone
+            two
+            three
+            four
+        "});
+        cx.update_editor(|editor, cx| {
+            editor.insert("zero\n", cx);
+            let snapshot = editor.snapshot(cx);
+            assert_eq!(editor.breakpoint_display_rows(0..5, &snapshot, cx), [2, 4]);
+            editor.toggle_breakpoint_for_display_row(2, cx);
+            let snapshot = editor.snapshot(cx);
+            assert_eq!(editor.breakpoint_display_rows(0..5, &snapshot, cx), [4]);
+        });
+
+        // The gutter gains a lane for them while they are enabled.
+        cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|settings, cx| {
+                settings.update_user_settings::<EditorSettings>(cx, |settings| {
+                    settings.breakpoints = Some(false);
+                });
+            });
+        });
+        cx.update_editor(|editor, cx| {
+            let snapshot = editor.snapshot(cx);
+            assert!(editor
+                .breakpoint_display_rows(0..5, &snapshot, cx)
+                .is_empty());
+            assert!(!snapshot.show_breakpoints);
+        });
+        project.read_with(&cx.cx, |project, cx| {
+            assert_eq!(project.breakpoints(cx).len(), 1)
+        });
+    }
+}
@@ -0,0 +1,251 @@
+use crate::{ConfirmClipboardHistoryEntry, ContextMenu, Editor, Paste, PasteFromHistory};
+use collections::VecDeque;
+use gpui::{
+    uniform_list, AnyElement, AppContext, ClipboardItem, Global, MouseButton, Pixels, SharedString,
+    UniformListScrollHandle, ViewContext,
+};
+use std::sync::Arc;
+use ui::prelude::*;
+
+/// The number of cuts and copies kept in the clipboard history.
+const MAX_CLIPBOARD_HISTORY_LEN: usize = 20;
+
+/// The number of characters of an entry shown in the clipboard history menu.
+const MAX_ENTRY_LABEL_LEN: usize = 80;
+
+/// The most recent cuts and copies made in editors, newest first.
+#[derive(Default)]
+struct ClipboardHistory(VecDeque<ClipboardItem>);
+
+impl Global for ClipboardHistory {}
+
+/// Writes the item to the clipboard, and records it at the front of the clipboard history.
+pub(crate) fn write_to_clipboard(item: ClipboardItem, cx: &mut AppContext) {
+    let history = &mut cx.default_global::<ClipboardHistory>().0;
+    history.retain(|entry| entry.text() != item.text());
+    history.push_front(item.clone());
+    history.truncate(MAX_CLIPBOARD_HISTORY_LEN);
+    cx.write_to_clipboard(item);
+}
+
+pub(crate) struct ClipboardHistoryMenu {
+    entries: Arc<[ClipboardItem]>,
+    selected_item: usize,
+    scroll_handle: UniformListScrollHandle,
+}
+
+impl ClipboardHistoryMenu {
+    pub(crate) fn select_first(&mut self, cx: &mut ViewContext<Editor>) {
+        self.selected_item = 0;
+        self.scroll_handle.scroll_to_item(self.selected_item);
+        cx.notify();
+    }
+
+    pub(crate) fn select_prev(&mut self, cx: &mut ViewContext<Editor>) {
+        if self.selected_item > 0 {
+            self.selected_item -= 1;
+        } else {
+            self.selected_item = self.entries.len() - 1;
+        }
+        self.scroll_handle.scroll_to_item(self.selected_item);
+        cx.notify();
+    }
+
+    pub(crate) fn select_next(&mut self, cx: &mut ViewContext<Editor>) {
+        if self.selected_item + 1 < self.entries.len() {
+            self.selected_item += 1;
+        } else {
+            self.selected_item = 0;
+        }
+        self.scroll_handle.scroll_to_item(self.selected_item);
+        cx.notify();
+    }
+
+    pub(crate) fn select_last(&mut self, cx: &mut ViewContext<Editor>) {
+        self.selected_item = self.entries.len() - 1;
+        self.scroll_handle.scroll_to_item(self.selected_item);
+        cx.notify();
+    }
+
+    pub(crate) fn visible(&self) -> bool {
+        !self.entries.is_empty()
+    }
+
+    pub(crate) fn render(&self, max_height: Pixels, cx: &mut ViewContext<Editor>) -> AnyElement {
+        let labels = self
+            .entries
+            .iter()
+            .map(|entry| entry_label(entry.text()))
+            .collect::<Arc<[_]>>();
+        let selected_item = self.selected_item;
+        let longest_label = labels
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, label)| label.chars().count())
+            .map(|(ix, _)| ix);
+
+        uniform_list(
+            cx.view().clone(),
+            "clipboard_history_menu",
+            labels.len(),
+            move |_this, range, cx| {
+                labels[range.clone()]
+                    .iter()
+                    .enumerate()
+                    .map(|(ix, label)| {
+                        let item_ix = range.start + ix;
+                        let selected = selected_item == item_ix;
+                        let colors = cx.theme().colors();
+                        div()
+                            .px_2()
+                            .text_color(colors.text)
+                            .when(selected, |style| {
+                                style
+                                    .bg(colors.element_active)
+                                    .text_color(colors.text_accent)
+                            })
+                            .hover(|style| {
+                                style
+                                    .bg(colors.element_hover)
+                                    .text_color(colors.text_accent)
+                            })
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(move |editor, _, cx| {
+                                    cx.stop_propagation();
+                                    editor.confirm_clipboard_history_entry(
+                                        &ConfirmClipboardHistoryEntry {
+                                            item_ix: Some(item_ix),
+                                        },
+                                        cx,
+                                    );
+                                }),
+                            )
+                            .child(label.clone())
+                    })
+                    .collect()
+            },
+        )
+        .elevation_1(cx)
+        .px_2()
+        .py_1()
+        .max_h(max_height)
+        .track_scroll(self.scroll_handle.clone())
+        .with_width_from_item(longest_label)
+        .into_any_element()
+    }
+}
+
+/// Returns the first non-blank line of an entry, truncated, followed by an ellipsis if the entry
+/// has more text.
+fn entry_label(text: &str) -> SharedString {
+    let text = text.trim();
+    let first_line = text.lines().next().unwrap_or_default();
+    let mut label = first_line
+        .chars()
+        .take(MAX_ENTRY_LABEL_LEN)
+        .collect::<String>();
+    if label.len() < text.len() {
+        label.push('…');
+    }
+    label.into()
+}
+
+impl Editor {
+    /// Shows a menu of the recent cuts and copies, to paste one of them.
+    pub fn paste_from_history(&mut self, _: &PasteFromHistory, cx: &mut ViewContext<Self>) {
+        if self.read_only(cx) {
+            return;
+        }
+        let entries = cx
+            .try_global::<ClipboardHistory>()
+            .map(|history| history.0.iter().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        if entries.is_empty() {
+            return;
+        }
+
+        self.discard_copilot_suggestion(cx);
+        *self.context_menu.write() = Some(ContextMenu::ClipboardHistory(ClipboardHistoryMenu {
+            entries: entries.into(),
+            selected_item: 0,
+            scroll_handle: UniformListScrollHandle::new(),
+        }));
+        cx.notify();
+    }
+
+    /// Pastes the selected entry of the clipboard history menu, which becomes the clipboard's
+    /// content.
+    pub fn confirm_clipboard_history_entry(
+        &mut self,
+        action: &ConfirmClipboardHistoryEntry,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(ContextMenu::ClipboardHistory(menu)) = self.hide_context_menu(cx) else {
+            return;
+        };
+        let Some(item) = menu
+            .entries
+            .get(action.item_ix.unwrap_or(menu.selected_item))
+            .cloned()
+        else {
+            return;
+        };
+        write_to_clipboard(item, cx);
+        self.paste(&Paste, cx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        editor_tests::init_test, test::editor_test_context::EditorTestContext, ContextMenuNext,
+        Copy, Cut,
+    };
+
+    #[gpui::test]
+    async fn test_paste_from_history(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state("«oneˇ» two three");
+        cx.update_editor(|e, cx| e.copy(&Copy, cx));
+        cx.set_state("one «twoˇ» three");
+        cx.update_editor(|e, cx| e.cut(&Cut, cx));
+        cx.set_state("one «oneˇ» three");
+        cx.update_editor(|e, cx| e.copy(&Copy, cx));
+
+        // Entries are listed newest first, without duplicates.
+        cx.set_state("ˇ");
+        cx.update_editor(|e, cx| {
+            e.paste_from_history(&PasteFromHistory, cx);
+            let context_menu = e.context_menu.read();
+            let Some(ContextMenu::ClipboardHistory(menu)) = context_menu.as_ref() else {
+                panic!("expected the clipboard history menu to be shown");
+            };
+            let texts = menu.entries.iter().map(|entry| entry.text().as_str());
+            assert_eq!(texts.collect::<Vec<_>>(), ["one", "two"]);
+        });
+
+        cx.update_editor(|e, cx| {
+            e.context_menu_next(&ContextMenuNext, cx);
+            e.confirm_clipboard_history_entry(&ConfirmClipboardHistoryEntry::default(), cx);
+        });
+        cx.assert_editor_state("twoˇ");
+        assert_eq!(
+            cx.read_from_clipboard().map(|item| item.text().clone()),
+            Some("two".into())
+        );
+    }
+
+    #[test]
+    fn test_entry_label() {
+        assert_eq!(entry_label("  one  ").as_ref(), "one");
+        assert_eq!(entry_label("\n    fn a() {\n    }\n").as_ref(), "fn a() {…");
+        assert_eq!(
+            entry_label(&"a".repeat(100)).as_ref(),
+            format!("{}…", "a".repeat(80))
+        );
+    }
+}
@@ -0,0 +1,864 @@
+use crate::{
+    display_map::{BlockContext, BlockDisposition, BlockId, BlockStyle},
+    Anchor, BlockDecoration, BlockDecorationEvent, DisplayPoint, Editor, EditorMode,
+    EditorSnapshot, RestartCodeCellKernel, RunCodeCell,
+};
+use anyhow::{Context as _, Result};
+use collections::{HashMap, HashSet, VecDeque};
+use futures::{
+    channel::mpsc, io::BufReader, stream, AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, Future,
+    Stream, StreamExt,
+};
+use gpui::{
+    AppContext, ClickEvent, Context as _, EntityId, EventEmitter, Global, IntoElement, Model,
+    ModelContext, Render, StyledText, Subscription, Task, View, ViewContext, VisualContext,
+    WeakModel,
+};
+use language::{BufferSnapshot, Point};
+use multi_buffer::ToPoint;
+use std::{
+    io,
+    ops::Range,
+    path::PathBuf,
+    process::{ExitStatus, Stdio},
+    rc::Rc,
+    sync::Arc,
+};
+use ui::{prelude::*, IconButton, Label, Tooltip};
+
+/// The number of lines at the end of a cell's output that are kept.
+const MAX_OUTPUT_LINES: usize = 100;
+
+/// The text of the comments that mark the start of a code cell, after the comment prefix.
+const CELL_MARKER: &str = "%%";
+
+/// The line written to a kernel after the code of each cell, which the kernel prints once it
+/// finished running the cell.
+const CELL_END: &str = "\u{1e}";
+
+/// The code cells run in the editor, along with their output.
+#[derive(Default)]
+pub(crate) struct CodeCells {
+    kernel: Option<(Model<CodeCellKernel>, Subscription)>,
+    runs: Vec<CodeCellRun>,
+}
+
+struct CodeCellRun {
+    /// The start of the cell's marker.
+    marker: Anchor,
+    output: View<CodeCellOutput>,
+    /// The block showing the cell's output.
+    block_id: BlockId,
+    /// The id of the cell's latest run in the kernel, if it was sent to one.
+    run_id: Option<usize>,
+}
+
+/// The kernels running the code cells of each buffer, which are shared by the buffer's editors.
+#[derive(Default)]
+struct CodeCellKernels(HashMap<EntityId, WeakModel<CodeCellKernel>>);
+
+impl Global for CodeCellKernels {}
+
+/// A long-lived process running the code cells of a buffer one after the other, so that the
+/// cells share the state of the program, like the kernel of a notebook.
+pub(crate) struct CodeCellKernel {
+    input: mpsc::UnboundedSender<String>,
+    /// The ids of the runs sent to the process that didn't finish yet, oldest first.
+    running: VecDeque<usize>,
+    next_run_id: usize,
+    exited: bool,
+    _io: Task<()>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum CodeCellKernelEvent {
+    Output(usize, String),
+    Finished(usize, Result<(), SharedString>),
+}
+
+impl EventEmitter<CodeCellKernelEvent> for CodeCellKernel {}
+
+impl CodeCellKernel {
+    /// Starts the given program, which reads the code of the cells from its standard input.
+    fn spawn(
+        command: &[String],
+        working_directory: Option<PathBuf>,
+        cx: &mut AppContext,
+    ) -> Result<Model<Self>> {
+        let (program, args) = command.split_first().context("The cell command is empty")?;
+        let mut process = smol::process::Command::new(program);
+        process
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(working_directory) = working_directory {
+            process.current_dir(working_directory);
+        }
+        let mut child = process
+            .spawn()
+            .with_context(|| format!("Failed to run `{program}`"))?;
+
+        let mut stdin = child
+            .stdin
+            .take()
+            .context("Failed to open the standard input")?;
+        let stdout = BufReader::new(child.stdout.take().context("Failed to read the output")?);
+        let stderr = BufReader::new(child.stderr.take().context("Failed to read the errors")?);
+        let (input, mut code) = mpsc::unbounded::<String>();
+        cx.background_executor()
+            .spawn(async move {
+                while let Some(code) = code.next().await {
+                    if stdin.write_all(code.as_bytes()).await.is_err()
+                        || stdin.flush().await.is_err()
+                    {
+                        break;
+                    }
+                }
+            })
+            .detach();
+
+        // The two pipes aren't ordered with respect to each other, so the kernel is expected to
+        // print its errors to its standard output, and only errors printed before it starts
+        // reading cells, like those of a missing program, end up here.
+        let lines = stream::select(lossy_lines(stdout), lossy_lines(stderr));
+        let status = async move { Ok(child.status().await?) };
+        Ok(cx.new_model(|cx| Self::new(input, lines, status, cx)))
+    }
+
+    /// Creates a kernel that writes the code of the cells to the given input, and reads their
+    /// output from the given lines until the process exits with the given status.
+    fn new(
+        input: mpsc::UnboundedSender<String>,
+        lines: impl Stream<Item = io::Result<String>> + 'static,
+        status: impl Future<Output = Result<ExitStatus>> + 'static,
+        cx: &mut ModelContext<Self>,
+    ) -> Self {
+        let io = cx.spawn(|this, mut cx| async move {
+            let mut lines = Box::pin(lines);
+            while let Some(line) = lines.next().await {
+                let result = match line {
+                    Ok(line) => this.update(&mut cx, |kernel, cx| kernel.push_line(line, cx)),
+                    Err(error) => {
+                        let message = format!("Failed to read the output: {error}").into();
+                        this.update(&mut cx, |kernel, cx| kernel.exit(message, cx))
+                            .ok();
+                        return;
+                    }
+                };
+                if result.is_err() {
+                    return;
+                }
+            }
+            let message: SharedString = match status.await {
+                Ok(status) => match status.code() {
+                    Some(code) => format!("Exited with code {code}").into(),
+                    None => "Terminated".into(),
+                },
+                Err(error) => format!("{error:#}").into(),
+            };
+            this.update(&mut cx, |kernel, cx| kernel.exit(message, cx))
+                .ok();
+        });
+        Self {
+            input,
+            running: VecDeque::new(),
+            next_run_id: 0,
+            exited: false,
+            _io: io,
+        }
+    }
+
+    /// Sends the code of a cell to the process, returning the id of the run.
+    fn run(&mut self, code: &str) -> Result<usize> {
+        let mut input = code.trim_end_matches('\n').to_string();
+        input.push('\n');
+        input.push_str(CELL_END);
+        input.push('\n');
+        self.input
+            .unbounded_send(input)
+            .ok()
+            .context("The kernel isn't running")?;
+        let run_id = self.next_run_id;
+        self.next_run_id += 1;
+        self.running.push_back(run_id);
+        Ok(run_id)
+    }
+
+    fn push_line(&mut self, line: String, cx: &mut ModelContext<Self>) {
+        let Some(&run_id) = self.running.front() else {
+            return;
+        };
+        if line == CELL_END {
+            self.running.pop_front();
+            cx.emit(CodeCellKernelEvent::Finished(run_id, Ok(())));
+        } else {
+            cx.emit(CodeCellKernelEvent::Output(run_id, line));
+        }
+    }
+
+    /// Stops the process, failing the runs that didn't finish with the given message.
+    fn exit(&mut self, message: SharedString, cx: &mut ModelContext<Self>) {
+        self.exited = true;
+        self._io = Task::ready(());
+        self.input.close_channel();
+        for run_id in self.running.drain(..) {
+            cx.emit(CodeCellKernelEvent::Finished(run_id, Err(message.clone())));
+        }
+    }
+}
+
+/// Returns the lines of the given reader, replacing invalid UTF-8 rather than failing on it.
+fn lossy_lines(
+    reader: impl AsyncBufRead + Unpin + 'static,
+) -> impl Stream<Item = io::Result<String>> {
+    stream::unfold(reader, |mut reader| async move {
+        let mut line = Vec::new();
+        match reader.read_until(b'\n', &mut line).await {
+            Ok(0) => None,
+            Ok(_) => {
+                if line.ends_with(b"\n") {
+                    line.pop();
+                    if line.ends_with(b"\r") {
+                        line.pop();
+                    }
+                }
+                Some((Ok(String::from_utf8_lossy(&line).into_owned()), reader))
+            }
+            Err(error) => Some((Err(error), reader)),
+        }
+    })
+}
+
+/// The output of a code cell, which streams in while the cell runs.
+pub struct CodeCellOutput {
+    lines: VecDeque<String>,
+    status: CodeCellStatus,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum CodeCellStatus {
+    Running,
+    Done,
+    Failed(SharedString),
+}
+
+impl CodeCellOutput {
+    fn new() -> Self {
+        Self {
+            lines: VecDeque::new(),
+            status: CodeCellStatus::Running,
+        }
+    }
+
+    fn restart(&mut self, cx: &mut ViewContext<Self>) {
+        self.lines.clear();
+        self.status = CodeCellStatus::Running;
+        cx.notify();
+    }
+
+    fn push_line(&mut self, line: String, cx: &mut ViewContext<Self>) {
+        if self.lines.len() == MAX_OUTPUT_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+        cx.notify();
+    }
+
+    fn finish(&mut self, result: Result<(), SharedString>, cx: &mut ViewContext<Self>) {
+        self.status = match result {
+            Ok(()) => CodeCellStatus::Done,
+            Err(error) => CodeCellStatus::Failed(error),
+        };
+        cx.notify();
+    }
+}
+
+impl Render for CodeCellOutput {
+    fn render(&mut self, _: &mut ViewContext<Self>) -> impl IntoElement {
+        let (status, color): (SharedString, Color) = match &self.status {
+            CodeCellStatus::Running => ("Running…".into(), Color::Muted),
+            CodeCellStatus::Done => ("Done".into(), Color::Muted),
+            CodeCellStatus::Failed(error) => (error.clone(), Color::Error),
+        };
+        let text = self.lines.iter().map(String::as_str).collect::<Vec<_>>();
+
+        v_flex()
+            .gap_1()
+            .when(!text.is_empty(), |this| {
+                this.child(StyledText::new(text.join("\n")))
+            })
+            .child(Label::new(status).size(LabelSize::Small).color(color))
+    }
+}
+
+/// Whether the given line is a comment that marks the start of a code cell, such as `# %%`.
+fn is_cell_marker(line: &str, comment_prefixes: &[Arc<str>]) -> bool {
+    let line = line.trim_start();
+    comment_prefixes.iter().any(|prefix| {
+        line.strip_prefix(prefix.trim_end())
+            .map_or(false, |comment| {
+                comment.trim_start().starts_with(CELL_MARKER)
+            })
+    })
+}
+
+/// Returns the command that runs the buffer's code cells, along with the prefixes of the comments
+/// that mark them.
+fn cell_language(buffer: &BufferSnapshot) -> Option<(Vec<String>, Vec<Arc<str>>)> {
+    let language = buffer.language()?;
+    let command = language.cell_command()?.to_vec();
+    let comment_prefixes = language
+        .default_scope()
+        .line_comment_prefixes()
+        .cloned()
+        .unwrap_or_default();
+    Some((command, comment_prefixes))
+}
+
+fn line(buffer: &BufferSnapshot, row: u32) -> String {
+    buffer
+        .text_for_range(Point::new(row, 0)..Point::new(row, buffer.line_len(row)))
+        .collect()
+}
+
+/// Returns the rows of the code cell containing the given row, starting with its marker unless
+/// it's the code before the first marker.
+fn cell_rows(buffer: &BufferSnapshot, row: u32, comment_prefixes: &[Arc<str>]) -> Range<u32> {
+    let is_marker = |row| is_cell_marker(&line(buffer, row), comment_prefixes);
+    let start = (0..=row).rev().find(|row| is_marker(*row)).unwrap_or(0);
+    let end = (row + 1..=buffer.max_point().row)
+        .find(|row| is_marker(*row))
+        .unwrap_or(buffer.max_point().row + 1);
+    start..end
+}
+
+impl Editor {
+    /// Returns the display rows in the given range that show the markers of code cells.
+    pub(crate) fn code_cell_marker_rows(
+        &self,
+        rows: Range<u32>,
+        snapshot: &EditorSnapshot,
+    ) -> Vec<u32> {
+        if self.mode != EditorMode::Full {
+            return Vec::new();
+        }
+        let Some((_, _, buffer)) = snapshot.buffer_snapshot.as_singleton() else {
+            return Vec::new();
+        };
+        let Some((_, comment_prefixes)) = cell_language(buffer) else {
+            return Vec::new();
+        };
+
+        snapshot
+            .buffer_rows(rows.start)
+            .take(rows.len())
+            .enumerate()
+            .filter_map(|(ix, buffer_row)| {
+                // Rows that continue wrapped lines don't have a buffer row.
+                let buffer_row = buffer_row?;
+                is_cell_marker(&line(buffer, buffer_row), &comment_prefixes)
+                    .then_some(rows.start + ix as u32)
+            })
+            .collect()
+    }
+
+    /// Renders the buttons next to the markers of the code cells on the given display rows,
+    /// which run the cells.
+    pub(crate) fn render_code_cell_indicators(
+        &self,
+        marker_rows: &[u32],
+        snapshot: &EditorSnapshot,
+        cx: &mut ViewContext<Self>,
+    ) -> Vec<(u32, IconButton)> {
+        marker_rows
+            .iter()
+            .map(|&row| {
+                let buffer_row = DisplayPoint::new(row, 0)
+                    .to_point(&snapshot.display_snapshot)
+                    .row;
+                let button = IconButton::new(("run-code-cell", row as usize), IconName::Play)
+                    .icon_size(IconSize::XSmall)
+                    .icon_color(Color::Muted)
+                    .tooltip(|cx| Tooltip::for_action("Run cell", &RunCodeCell, cx))
+                    .on_click(cx.listener(move |editor, _: &ClickEvent, cx| {
+                        editor.run_code_cell_at_row(buffer_row, cx);
+                    }));
+                (row, button)
+            })
+            .collect()
+    }
+
+    /// Runs the code cell containing the newest cursor in the buffer's kernel, streaming its
+    /// output into a block below the cell.
+    pub fn run_code_cell(&mut self, _: &RunCodeCell, cx: &mut ViewContext<Self>) {
+        let row = self.selections.newest::<Point>(cx).head().row;
+        self.run_code_cell_at_row(row, cx);
+    }
+
+    /// Stops the process running the code cells of the buffer, so that the next cell that runs
+    /// starts a new one.
+    pub fn restart_code_cell_kernel(
+        &mut self,
+        _: &RestartCodeCellKernel,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(buffer) = self.buffer.read(cx).as_singleton() else {
+            return;
+        };
+        let kernel = cx
+            .default_global::<CodeCellKernels>()
+            .0
+            .remove(&buffer.entity_id())
+            .and_then(|kernel| kernel.upgrade());
+        if let Some(kernel) = kernel {
+            kernel.update(cx, |kernel, cx| kernel.exit("Restarted".into(), cx));
+        }
+    }
+
+    fn run_code_cell_at_row(&mut self, row: u32, cx: &mut ViewContext<Self>) {
+        let multi_buffer = self.buffer.read(cx);
+        let Some(buffer) = multi_buffer.as_singleton() else {
+            return;
+        };
+        let working_directory = buffer.read(cx).file().and_then(|file| {
+            let path = file.as_local()?.abs_path(cx);
+            Some(path.parent()?.to_path_buf())
+        });
+        let snapshot = multi_buffer.snapshot(cx);
+        let buffer_id = buffer.entity_id();
+        let buffer = buffer.read(cx).snapshot();
+        let Some((command, comment_prefixes)) = cell_language(&buffer) else {
+            return;
+        };
+
+        let rows = cell_rows(&buffer, row, &comment_prefixes);
+        let code_start = if is_cell_marker(&line(&buffer, rows.start), &comment_prefixes) {
+            rows.start + 1
+        } else {
+            rows.start
+        };
+        let last_row = rows.end - 1;
+        let code = buffer
+            .text_for_range(
+                Point::new(code_start, 0)..Point::new(last_row, buffer.line_len(last_row)),
+            )
+            .collect::<String>();
+        if code.trim().is_empty() {
+            return;
+        }
+
+        let existing_run = self
+            .code_cells
+            .runs
+            .iter()
+            .position(|run| run.marker.to_point(&snapshot).row == rows.start);
+        let run_ix = if let Some(ix) = existing_run {
+            self.code_cells.runs[ix]
+                .output
+                .update(cx, |output, cx| output.restart(cx));
+            ix
+        } else {
+            let output = cx.new_view(|_| CodeCellOutput::new());
+            let block_id = self.insert_code_cell_output(
+                snapshot.anchor_before(Point::new(last_row, buffer.line_len(last_row))),
+                output.clone(),
+                cx,
+            );
+            self.code_cells.runs.push(CodeCellRun {
+                marker: snapshot.anchor_before(Point::new(rows.start, 0)),
+                output,
+                block_id,
+                run_id: None,
+            });
+            self.code_cells.runs.len() - 1
+        };
+
+        let run_id = self
+            .code_cell_kernel(buffer_id, &command, working_directory, cx)
+            .and_then(|kernel| kernel.update(cx, |kernel, _| kernel.run(&code)));
+        match run_id {
+            Ok(run_id) => self.code_cells.runs[run_ix].run_id = Some(run_id),
+            Err(error) => {
+                let error = format!("{error:#}").into();
+                let run = &mut self.code_cells.runs[run_ix];
+                run.run_id = None;
+                run.output
+                    .update(cx, |output, cx| output.finish(Err(error), cx));
+            }
+        }
+    }
+
+    /// Returns the kernel running the code cells of the given buffer, starting one if the
+    /// buffer's kernel exited or none of its editors ran a cell yet.
+    fn code_cell_kernel(
+        &mut self,
+        buffer_id: EntityId,
+        command: &[String],
+        working_directory: Option<PathBuf>,
+        cx: &mut ViewContext<Self>,
+    ) -> Result<Model<CodeCellKernel>> {
+        let kernel = cx
+            .default_global::<CodeCellKernels>()
+            .0
+            .get(&buffer_id)
+            .and_then(|kernel| kernel.upgrade())
+            .filter(|kernel| !kernel.read(cx).exited);
+        let kernel = match kernel {
+            Some(kernel) => kernel,
+            None => {
+                let kernel = CodeCellKernel::spawn(command, working_directory, cx)?;
+                cx.default_global::<CodeCellKernels>()
+                    .0
+                    .insert(buffer_id, kernel.downgrade());
+                kernel
+            }
+        };
+
+        let is_subscribed = self
+            .code_cells
+            .kernel
+            .as_ref()
+            .map_or(false, |(subscribed, _)| *subscribed == kernel);
+        if !is_subscribed {
+            let subscription = cx.subscribe(&kernel, |editor, kernel, event, cx| {
+                editor.handle_code_cell_kernel_event(kernel, event, cx)
+            });
+            self.code_cells.kernel = Some((kernel.clone(), subscription));
+            // The ids of the runs are only unique within a kernel, so the runs sent to the
+            // previous one are forgotten.
+            for run in &mut self.code_cells.runs {
+                run.run_id = None;
+            }
+        }
+        Ok(kernel)
+    }
+
+    fn handle_code_cell_kernel_event(
+        &mut self,
+        kernel: Model<CodeCellKernel>,
+        event: &CodeCellKernelEvent,
+        cx: &mut ViewContext<Self>,
+    ) {
+        // Events of a previous kernel may still be delivered after switching to a new one.
+        let is_current_kernel = self
+            .code_cells
+            .kernel
+            .as_ref()
+            .map_or(false, |(current, _)| *current == kernel);
+        if !is_current_kernel {
+            return;
+        }
+        let (CodeCellKernelEvent::Output(run_id, _) | CodeCellKernelEvent::Finished(run_id, _)) =
+            event;
+        // Runs that were started again since are ignored.
+        let Some(run) = self
+            .code_cells
+            .runs
+            .iter()
+            .find(|run| run.run_id == Some(*run_id))
+        else {
+            return;
+        };
+        run.output.update(cx, |output, cx| match event {
+            CodeCellKernelEvent::Output(_, line) => output.push_line(line.clone(), cx),
+            CodeCellKernelEvent::Finished(_, result) => output.finish(result.clone(), cx),
+        });
+    }
+
+    fn insert_code_cell_output(
+        &mut self,
+        position: Anchor,
+        output: View<CodeCellOutput>,
+        cx: &mut ViewContext<Self>,
+    ) -> BlockId {
+        let output_id = output.entity_id();
+        let decoration = BlockDecoration {
+            position,
+            disposition: BlockDisposition::Below,
+            style: BlockStyle::Sticky,
+            height: 1,
+            fit_content: true,
+            render: Arc::new(move |cx: &mut BlockContext| {
+                let text_style = &cx.editor_style.text;
+                div()
+                    .pl(cx.gutter_width)
+                    .py_1()
+                    .font(text_style.font_family.clone())
+                    .text_size(text_style.font_size)
+                    .child(output.clone())
+                    .into_any_element()
+            }),
+            // Deleting the cell removes its output.
+            on_event: Some(Rc::new(
+                move |event: BlockDecorationEvent,
+                      editor: &mut Editor,
+                      cx: &mut ViewContext<Editor>| match event {
+                    BlockDecorationEvent::Hidden => editor.remove_code_cell_output(output_id, cx),
+                    BlockDecorationEvent::Removed => editor.remove_code_cell_run(output_id),
+                    BlockDecorationEvent::Resized { .. } | BlockDecorationEvent::Shown => {}
+                },
+            )),
+        };
+        self.insert_block_decorations([decoration], cx)[0]
+    }
+
+    fn remove_code_cell_output(&mut self, output_id: EntityId, cx: &mut ViewContext<Self>) {
+        let block_ids = self
+            .code_cells
+            .runs
+            .iter()
+            .filter(|run| run.output.entity_id() == output_id)
+            .map(|run| run.block_id)
+            .collect::<HashSet<_>>();
+        self.remove_block_decorations(block_ids, cx);
+    }
+
+    fn remove_code_cell_run(&mut self, output_id: EntityId) {
+        self.code_cells
+            .runs
+            .retain(|run| run.output.entity_id() != output_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+    use futures::future;
+    use language::{Buffer, Language, LanguageConfig};
+    use text::BufferId;
+
+    fn python_cells() -> Arc<Language> {
+        Arc::new(Language::new(
+            LanguageConfig {
+                line_comments: vec!["# ".into()],
+                cell_command: Some(vec!["python3".into(), "-u".into(), "kernel.py".into()]),
+                ..Default::default()
+            },
+            None,
+        ))
+    }
+
+    #[test]
+    fn test_is_cell_marker() {
+        let prefixes = [Arc::from("# ")];
+        assert!(is_cell_marker("# %%", &prefixes));
+        assert!(is_cell_marker("  #%% [markdown]", &prefixes));
+        assert!(!is_cell_marker("# %", &prefixes));
+        assert!(!is_cell_marker("x = 1  # %%", &prefixes));
+    }
+
+    #[gpui::test]
+    fn test_cell_rows(cx: &mut gpui::AppContext) {
+        let text = "import os\n# %%\nx = 1\n\n# %% second\nprint(x)\n";
+        let buffer = cx.new_model(|cx| {
+            Buffer::new(0, BufferId::new(cx.entity_id().as_u64()).unwrap(), text)
+                .with_language(python_cells(), cx)
+        });
+        let buffer = buffer.read(cx).snapshot();
+        let (command, prefixes) = cell_language(&buffer).unwrap();
+        assert_eq!(command, ["python3", "-u", "kernel.py"]);
+        assert_eq!(cell_rows(&buffer, 0, &prefixes), 0..1);
+        assert_eq!(cell_rows(&buffer, 1, &prefixes), 1..4);
+        assert_eq!(cell_rows(&buffer, 3, &prefixes), 1..4);
+        assert_eq!(cell_rows(&buffer, 6, &prefixes), 4..7);
+    }
+
+    #[gpui::test]
+    async fn test_code_cell_markers(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.update_buffer(|buffer, cx| buffer.set_language(Some(python_cells()), cx));
+        cx.set_state("ˇ# %%\nx = 1\n# %%\nprint(x)\n");
+        cx.update_editor(|editor, cx| {
+            let snapshot = editor.snapshot(cx);
+            assert_eq!(editor.code_cell_marker_rows(0..5, &snapshot), [0, 2]);
+            assert_eq!(editor.code_cell_marker_rows(1..5, &snapshot), [2]);
+        });
+    }
+
+    /// Starts a kernel for the editor's buffer whose input and output are channels rather than
+    /// the pipes of a process.
+    fn fake_kernel(
+        cx: &mut EditorTestContext,
+    ) -> (
+        Model<CodeCellKernel>,
+        mpsc::UnboundedReceiver<String>,
+        mpsc::UnboundedSender<io::Result<String>>,
+    ) {
+        let buffer = cx.update_editor(|editor, cx| editor.buffer.read(cx).as_singleton().unwrap());
+        let (input, code) = mpsc::unbounded();
+        let (output, lines) = mpsc::unbounded();
+        let kernel = cx.update(|cx| {
+            let kernel =
+                cx.new_model(|cx| CodeCellKernel::new(input, lines, future::pending(), cx));
+            cx.default_global::<CodeCellKernels>()
+                .0
+                .insert(buffer.entity_id(), kernel.downgrade());
+            kernel
+        });
+        (kernel, code, output)
+    }
+
+    /// Returns the output lines and status of each cell that ran.
+    fn outputs(cx: &mut EditorTestContext) -> Vec<(Vec<String>, CodeCellStatus)> {
+        cx.update_editor(|editor, cx| {
+            editor
+                .code_cells
+                .runs
+                .iter()
+                .map(|run| {
+                    let output = run.output.read(cx);
+                    (
+                        output.lines.iter().cloned().collect::<Vec<_>>(),
+                        output.status.clone(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+    }
+
+    #[gpui::test]
+    async fn test_running_code_cells(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.update_buffer(|buffer, cx| buffer.set_language(Some(python_cells()), cx));
+        cx.set_state("# %%\nˇx = 1\nprint(x)\n# %%\nprint(x + 1)\n");
+
+        let (kernel, mut code, output) = fake_kernel(&mut cx);
+
+        // The cell's code is sent to the buffer's kernel, and its output streams into a block.
+        cx.dispatch_action(RunCodeCell);
+        cx.run_until_parked();
+        assert_eq!(
+            code.try_next().unwrap().unwrap(),
+            "x = 1\nprint(x)\n\u{1e}\n"
+        );
+        output.unbounded_send(Ok("1".into())).unwrap();
+        cx.run_until_parked();
+        assert_eq!(
+            outputs(&mut cx),
+            [(vec!["1".into()], CodeCellStatus::Running)]
+        );
+        output.unbounded_send(Ok(CELL_END.into())).unwrap();
+        cx.run_until_parked();
+        assert_eq!(outputs(&mut cx), [(vec!["1".into()], CodeCellStatus::Done)]);
+
+        // Running the cell again restarts its output in the same block, in the same kernel.
+        cx.dispatch_action(RunCodeCell);
+        cx.run_until_parked();
+        assert_eq!(
+            code.try_next().unwrap().unwrap(),
+            "x = 1\nprint(x)\n\u{1e}\n"
+        );
+        assert_eq!(outputs(&mut cx), [(vec![], CodeCellStatus::Running)]);
+        output.unbounded_send(Ok("1".into())).unwrap();
+        output.unbounded_send(Ok(CELL_END.into())).unwrap();
+        cx.run_until_parked();
+        assert_eq!(outputs(&mut cx), [(vec!["1".into()], CodeCellStatus::Done)]);
+
+        // Restarting the kernel fails the cells that are still running, and the next cell runs
+        // in a new kernel.
+        cx.set_selections_state("# %%\nx = 1\nprint(x)\n# %%\nˇprint(x + 1)\n");
+        cx.dispatch_action(RunCodeCell);
+        cx.run_until_parked();
+        assert_eq!(code.try_next().unwrap().unwrap(), "print(x + 1)\n\u{1e}\n");
+        cx.dispatch_action(RestartCodeCellKernel);
+        cx.run_until_parked();
+        assert!(cx.update(|cx| kernel.read(cx).exited));
+        assert_eq!(
+            outputs(&mut cx),
+            [
+                (vec!["1".into()], CodeCellStatus::Done),
+                (vec![], CodeCellStatus::Failed("Restarted".into()))
+            ]
+        );
+
+        let (new_kernel, mut new_code, new_output) = fake_kernel(&mut cx);
+        cx.dispatch_action(RunCodeCell);
+        cx.run_until_parked();
+        assert_eq!(
+            new_code.try_next().unwrap().unwrap(),
+            "print(x + 1)\n\u{1e}\n"
+        );
+        assert_eq!(
+            outputs(&mut cx),
+            [
+                (vec!["1".into()], CodeCellStatus::Done),
+                (vec![], CodeCellStatus::Running)
+            ]
+        );
+
+        // Failing to read the output stops the kernel instead of waiting for it to exit.
+        let error = io::Error::new(io::ErrorKind::InvalidData, "invalid data");
+        new_output.unbounded_send(Err(error)).unwrap();
+        cx.run_until_parked();
+        assert_eq!(
+            outputs(&mut cx),
+            [
+                (vec!["1".into()], CodeCellStatus::Done),
+                (
+                    vec![],
+                    CodeCellStatus::Failed("Failed to read the output: invalid data".into())
+                )
+            ]
+        );
+        assert!(cx.update(|cx| new_kernel.read(cx).exited));
+
+        // Deleting a cell removes its output.
+        cx.update_editor(|editor, cx| {
+            editor.edit([(Point::new(3, 0)..Point::new(5, 0), "")], cx);
+        });
+        cx.run_until_parked();
+        assert_eq!(outputs(&mut cx), [(vec!["1".into()], CodeCellStatus::Done)]);
+    }
+
+    #[gpui::test]
+    async fn test_code_cell_runs_across_kernels(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.update_buffer(|buffer, cx| buffer.set_language(Some(python_cells()), cx));
+        cx.set_state("# %%\nˇx = 1\n# %%\nprint(x)\n");
+
+        let (kernel, _code, output) = fake_kernel(&mut cx);
+        cx.dispatch_action(RunCodeCell);
+        cx.run_until_parked();
+        output.unbounded_send(Ok(CELL_END.into())).unwrap();
+        cx.run_until_parked();
+        cx.dispatch_action(RestartCodeCellKernel);
+        cx.run_until_parked();
+
+        // The runs of a new kernel get the ids of the previous kernel's runs again, without their
+        // output ending up in the blocks of the cells that ran before.
+        let (_new_kernel, _new_code, new_output) = fake_kernel(&mut cx);
+        cx.set_selections_state("# %%\nx = 1\n# %%\nˇprint(x)\n");
+        cx.dispatch_action(RunCodeCell);
+        cx.run_until_parked();
+        new_output.unbounded_send(Ok("1".into())).unwrap();
+        cx.run_until_parked();
+        assert_eq!(
+            outputs(&mut cx),
+            [
+                (vec![], CodeCellStatus::Done),
+                (vec!["1".into()], CodeCellStatus::Running)
+            ]
+        );
+
+        // Events of the previous kernel are ignored.
+        cx.update(|cx| {
+            kernel.update(cx, |_, cx| {
+                cx.emit(CodeCellKernelEvent::Output(0, "stale".into()))
+            })
+        });
+        cx.run_until_parked();
+        assert_eq!(
+            outputs(&mut cx),
+            [
+                (vec![], CodeCellStatus::Done),
+                (vec!["1".into()], CodeCellStatus::Running)
+            ]
+        );
+    }
+}
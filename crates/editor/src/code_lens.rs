@@ -0,0 +1,328 @@
+use crate::{
+    display_map::{BlockContext, BlockDisposition, BlockProperties, BlockStyle, RenderBlock},
+    Editor, EditorMode, EditorSettings,
+};
+use collections::HashSet;
+use gpui::{Model, ViewContext};
+use language::{Buffer, Point, ToPoint};
+use project::CodeLens;
+use settings::Settings;
+use std::{sync::Arc, time::Duration};
+use ui::prelude::*;
+use util::ResultExt;
+
+const CODE_LENS_DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(500);
+
+impl Editor {
+    /// Requests the code lenses of the buffer from its language server, after a delay if
+    /// `debounce` is true so that consecutive edits are handled once, and shows them in blocks
+    /// above the lines they annotate.
+    pub(crate) fn refresh_code_lens(&mut self, debounce: bool, cx: &mut ViewContext<Self>) {
+        if self.mode != EditorMode::Full || !EditorSettings::get_global(cx).code_lens {
+            self.code_lens_task = None;
+            self.show_code_lens(None, Vec::new(), cx);
+            return;
+        }
+
+        let Some(project) = self.project.clone() else {
+            return;
+        };
+        let Some(buffer) = self.buffer.read(cx).as_singleton() else {
+            return;
+        };
+        self.code_lens_task = Some(cx.spawn(|this, mut cx| async move {
+            if debounce {
+                cx.background_executor()
+                    .timer(CODE_LENS_DEBOUNCE_TIMEOUT)
+                    .await;
+            }
+
+            let lenses = project
+                .update(&mut cx, |project, cx| project.code_lens(&buffer, cx))
+                .ok()?
+                .await
+                .log_err()?;
+            this.update(&mut cx, |this, cx| {
+                this.show_code_lens(Some(buffer), lenses, cx)
+            })
+            .ok()
+        }));
+    }
+
+    /// Replaces the blocks showing code lenses with blocks showing the given ones.
+    fn show_code_lens(
+        &mut self,
+        buffer: Option<Model<Buffer>>,
+        lenses: Vec<CodeLens>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if !self.code_lens_blocks.is_empty() {
+            let blocks = HashSet::from_iter(self.code_lens_blocks.drain(..));
+            self.remove_blocks(blocks, None, cx);
+        }
+        let Some(buffer) = buffer else {
+            return;
+        };
+
+        // Lenses are grouped by the line they start on, and shown above its first
+        // non-whitespace character.
+        let buffer_snapshot = buffer.read(cx).snapshot();
+        let mut rows = Vec::<(u32, Vec<CodeLens>)>::new();
+        for lens in lenses {
+            if lens.title().is_none() {
+                continue;
+            }
+            let row = lens.range.start.to_point(&buffer_snapshot).row;
+            match rows.iter_mut().find(|(lens_row, _)| *lens_row == row) {
+                Some((_, row_lenses)) => row_lenses.push(lens),
+                None => rows.push((row, vec![lens])),
+            }
+        }
+
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let blocks = rows.into_iter().map(|(row, lenses)| {
+            let indent = buffer_snapshot.indent_size_for_line(row);
+            BlockProperties {
+                position: snapshot.anchor_after(Point::new(row, indent.len)),
+                height: 1,
+                style: BlockStyle::Flexible,
+                render: render_code_lens(buffer.clone(), lenses),
+                disposition: BlockDisposition::Above,
+            }
+        });
+        self.code_lens_blocks = self.insert_blocks(blocks, None, cx);
+    }
+
+    /// Runs the command of a code lens, and opens the buffers it edited.
+    fn execute_code_lens(
+        &mut self,
+        buffer: &Model<Buffer>,
+        lens: CodeLens,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let (Some(project), Some(workspace)) = (self.project.clone(), self.workspace()) else {
+            return;
+        };
+        let title = lens.title().unwrap_or_default().to_string();
+        let execute = project.update(cx, |project, cx| {
+            project.execute_code_lens(buffer, lens, cx)
+        });
+        let workspace = workspace.downgrade();
+        cx.spawn(|editor, cx| async move {
+            let project_transaction = execute.await?;
+            Self::open_project_transaction(&editor, workspace, project_transaction, title, cx).await
+        })
+        .detach_and_log_err(cx);
+    }
+}
+
+fn render_code_lens(buffer: Model<Buffer>, lenses: Vec<CodeLens>) -> RenderBlock {
+    Arc::new(move |cx: &mut BlockContext| {
+        let view = cx.view.clone();
+        let mut row = h_flex().id(cx.block_id).size_full().pl(cx.anchor_x).gap_1();
+        for (ix, lens) in lenses.iter().enumerate() {
+            if ix > 0 {
+                row = row.child(Label::new("|").size(LabelSize::Small).color(Color::Muted));
+            }
+            let buffer = buffer.clone();
+            let lens = lens.clone();
+            row = row.child(
+                Button::new(
+                    ("code-lens", ix),
+                    lens.title().unwrap_or_default().to_string(),
+                )
+                .label_size(LabelSize::Small)
+                .color(Color::Muted)
+                .on_click(cx.listener_for(&view, move |editor, _, cx| {
+                    editor.execute_code_lens(&buffer, lens.clone(), cx)
+                })),
+            );
+        }
+        row.into_any_element()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_lsp_test_context::EditorLspTestContext};
+    use indoc::indoc;
+    use parking_lot::Mutex;
+    use serde_json::json;
+    use settings::SettingsStore;
+
+    #[gpui::test]
+    async fn test_code_lens(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                code_lens_provider: Some(lsp::CodeLensOptions {
+                    resolve_provider: Some(false),
+                }),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+        cx.set_state(indoc! {"
+            fn main() {
+                ˇa();
+            }
+            fn a() {}
+        "});
+
+        cx.handle_request::<lsp::request::CodeLensRequest, _, _>(|_, _, _| async move {
+            let lens = |row, title: Option<&str>| lsp::CodeLens {
+                range: lsp::Range::new(lsp::Position::new(row, 0), lsp::Position::new(row, 2)),
+                command: title.map(|title| lsp::Command {
+                    title: title.to_string(),
+                    command: "run".to_string(),
+                    arguments: None,
+                }),
+                data: None,
+            };
+            Ok(Some(vec![
+                lens(0, Some("Run")),
+                lens(3, Some("1 reference")),
+                lens(0, Some("Debug")),
+                // Lenses without a command aren't shown.
+                lens(1, None),
+            ]))
+        });
+        _ = cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|settings, cx| {
+                settings.update_user_settings::<EditorSettings>(cx, |settings| {
+                    settings.code_lens = Some(true);
+                });
+            })
+        });
+        cx.run_until_parked();
+
+        // Each line with lenses has a block above it.
+        cx.update_editor(|editor, cx| {
+            assert_eq!(editor.code_lens_blocks.len(), 2);
+            assert_eq!(
+                editor.display_text(cx),
+                "\nfn main() {\n    a();\n}\n\nfn a() {}\n"
+            );
+        });
+
+        _ = cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|settings, cx| {
+                settings.update_user_settings::<EditorSettings>(cx, |settings| {
+                    settings.code_lens = Some(false);
+                });
+            })
+        });
+        cx.update_editor(|editor, cx| {
+            assert!(editor.code_lens_blocks.is_empty());
+            assert_eq!(
+                editor.display_text(cx),
+                "fn main() {\n    a();\n}\nfn a() {}\n"
+            );
+        });
+    }
+
+    #[gpui::test]
+    async fn test_code_lens_resolve_and_execute(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                code_lens_provider: Some(lsp::CodeLensOptions {
+                    resolve_provider: Some(true),
+                }),
+                execute_command_provider: Some(lsp::ExecuteCommandOptions {
+                    commands: vec!["run".to_string()],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+        cx.set_state(indoc! {"
+            fn main() {
+                ˇa();
+            }
+            fn a() {}
+        "});
+
+        let command = |name: &str| lsp::Command {
+            title: name.to_string(),
+            command: name.to_string(),
+            arguments: None,
+        };
+        cx.handle_request::<lsp::request::CodeLensRequest, _, _>(|_, _, _| async move {
+            let lens = |row, data| lsp::CodeLens {
+                range: lsp::Range::new(lsp::Position::new(row, 0), lsp::Position::new(row, 2)),
+                command: None,
+                data: Some(data),
+            };
+            Ok(Some(vec![lens(0, json!(0)), lens(3, json!(1))]))
+        });
+        cx.handle_request::<lsp::request::CodeLensResolve, _, _>(move |_, lens, _| async move {
+            if lens.data == Some(json!(0)) {
+                Ok(lsp::CodeLens {
+                    command: Some(command("run")),
+                    ..lens
+                })
+            } else {
+                Err(anyhow::anyhow!("can't resolve the lens"))
+            }
+        });
+        _ = cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|settings, cx| {
+                settings.update_user_settings::<EditorSettings>(cx, |settings| {
+                    settings.code_lens = Some(true);
+                });
+            })
+        });
+        cx.run_until_parked();
+
+        // A lens that fails to resolve doesn't hide the others.
+        cx.update_editor(|editor, cx| {
+            assert_eq!(editor.code_lens_blocks.len(), 1);
+            assert_eq!(
+                editor.display_text(cx),
+                "\nfn main() {\n    a();\n}\nfn a() {}\n"
+            );
+        });
+
+        // Only the commands the server supports are sent to it.
+        let executed_commands = Arc::new(Mutex::new(Vec::new()));
+        cx.handle_request::<lsp::request::ExecuteCommand, _, _>({
+            let executed_commands = executed_commands.clone();
+            move |_, params, _| {
+                executed_commands.lock().push(params.command);
+                async move { Ok(None) }
+            }
+        });
+        let (project, buffer) = cx.update_editor(|editor, cx| {
+            (
+                editor.project.clone().unwrap(),
+                editor.buffer().read(cx).as_singleton().unwrap(),
+            )
+        });
+        let server_id = cx.lsp.server.server_id();
+        for (name, succeeds) in [("run", true), ("debug", false)] {
+            let lens = CodeLens {
+                server_id,
+                range: language::Anchor::MIN..language::Anchor::MIN,
+                lsp_lens: lsp::CodeLens {
+                    range: lsp::Range::default(),
+                    command: Some(command(name)),
+                    data: None,
+                },
+            };
+            let execute = cx.update(|cx| {
+                project.update(cx, |project, cx| {
+                    project.execute_code_lens(&buffer, lens, cx)
+                })
+            });
+            assert_eq!(execute.await.is_ok(), succeeds);
+        }
+        assert_eq!(*executed_commands.lock(), ["run".to_string()]);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{
+    diff_view::DiffView,
+    display_map::{BlockContext, BlockDisposition, BlockProperties, BlockStyle, RenderBlock},
+    Anchor, Editor, EditorMode,
+};
+use collections::HashSet;
+use gpui::ViewContext;
+use std::sync::Arc;
+use ui::{prelude::*, Tooltip};
+use workspace::{item::Item as _, SplitDirection};
+
+/// Shows a banner above the first line while the buffer's file has changed on disk and the buffer
+/// has unsaved edits, and removes it once the conflict is resolved.
+pub(crate) fn refresh_conflict_banner(editor: &mut Editor, cx: &mut ViewContext<Editor>) {
+    let has_conflict = editor.mode == EditorMode::Full
+        && editor
+            .buffer
+            .read(cx)
+            .as_singleton()
+            .map_or(false, |buffer| buffer.read(cx).has_conflict());
+
+    match (has_conflict, editor.conflict_banner) {
+        (true, None) => {
+            let block_ids = editor.insert_blocks(
+                [BlockProperties {
+                    position: Anchor::min(),
+                    height: 2,
+                    style: BlockStyle::Sticky,
+                    render: render_conflict_banner(),
+                    disposition: BlockDisposition::Above,
+                }],
+                None,
+                cx,
+            );
+            editor.conflict_banner = block_ids.into_iter().next();
+        }
+        (false, Some(block_id)) => {
+            editor.conflict_banner = None;
+            editor.remove_blocks(HashSet::from_iter([block_id]), None, cx);
+        }
+        _ => {}
+    }
+}
+
+fn render_conflict_banner() -> RenderBlock {
+    Arc::new(|cx: &mut BlockContext| {
+        let view = cx.view.clone();
+        let status = cx.theme().status();
+        h_flex()
+            .id(cx.block_id)
+            .size_full()
+            .pl(cx.gutter_width)
+            .pr_2()
+            .gap_2()
+            .bg(status.warning_background)
+            .border_b_1()
+            .border_color(status.warning_border)
+            .child(
+                Icon::new(IconName::ExclamationTriangle)
+                    .size(IconSize::Small)
+                    .color(Color::Warning),
+            )
+            .child(
+                Label::new("This file has changed on disk since you started editing it.")
+                    .size(LabelSize::Small),
+            )
+            .child(div().flex_1())
+            .child(
+                Button::new("conflict-reload", "Reload")
+                    .label_size(LabelSize::Small)
+                    .tooltip(|cx| Tooltip::text("Discard your changes and reload the file", cx))
+                    .on_click(cx.listener_for(&view, |editor, _, cx| {
+                        if let Some(project) = editor.project.clone() {
+                            editor.reload(project, cx).detach_and_log_err(cx);
+                        }
+                    })),
+            )
+            .child(
+                Button::new("conflict-keep-mine", "Keep Mine")
+                    .label_size(LabelSize::Small)
+                    .tooltip(|cx| Tooltip::text("Overwrite the file with your changes", cx))
+                    .on_click(cx.listener_for(&view, |editor, _, cx| {
+                        if let Some(project) = editor.project.clone() {
+                            editor.save(project, cx).detach_and_log_err(cx);
+                        }
+                    })),
+            )
+            .child(
+                Button::new("conflict-compare", "Compare")
+                    .label_size(LabelSize::Small)
+                    .tooltip(|cx| Tooltip::text("Compare your changes with the file on disk", cx))
+                    .on_click(cx.listener_for(&view, |editor, _, cx| {
+                        compare_with_file_on_disk(editor, cx)
+                    })),
+            )
+            .into_any_element()
+    })
+}
+
+/// Opens the contents of the buffer's file on disk side by side with the buffer in a split to
+/// the right, where the conflicting changes can be reviewed and resolved.
+fn compare_with_file_on_disk(editor: &mut Editor, cx: &mut ViewContext<Editor>) {
+    let (Some(project), Some(workspace)) = (editor.project.clone(), editor.workspace()) else {
+        return;
+    };
+    let Some(buffer) = editor.buffer.read(cx).as_singleton() else {
+        return;
+    };
+    let Some(file) = buffer.read(cx).file().and_then(|file| file.as_local()) else {
+        return;
+    };
+    let load_file = file.load(cx);
+    let title = format!("{} (on disk)", file.file_name(cx).to_string_lossy());
+    let language = buffer.read(cx).language().cloned();
+
+    cx.spawn(|_, mut cx| async move {
+        let file_text = load_file.await?;
+        workspace.update(&mut cx, |workspace, cx| {
+            let file_buffer = project.update(cx, |project, cx| {
+                project.create_buffer(&file_text, language, cx)
+            })?;
+            let diff_view = cx
+                .new_view(|cx| DiffView::new(file_buffer, buffer, title.into(), Some(project), cx));
+            workspace.split_item(SplitDirection::Right, Box::new(diff_view), cx);
+            anyhow::Ok(())
+        })?
+    })
+    .detach_and_log_err(cx);
+}
@@ -0,0 +1,114 @@
+use gpui::{point, Pixels, Point};
+use std::time::{Duration, Instant};
+
+/// How long the cursor takes to glide to a new position.
+const CURSOR_ANIMATION_DURATION: Duration = Duration::from_millis(80);
+
+/// The number of rows beyond which the cursor jumps to its new position instead of gliding.
+const MAX_ANIMATED_ROWS: f32 = 8.;
+
+/// Interpolates the position of the newest cursor between the positions it's painted at, so that
+/// it glides instead of jumping when it moves.
+#[derive(Default)]
+pub(crate) struct CursorAnimation {
+    transition: Option<Transition>,
+}
+
+#[derive(Clone, Copy)]
+struct Transition {
+    from: Point<Pixels>,
+    to: Point<Pixels>,
+    started_at: Instant,
+}
+
+impl Transition {
+    fn position_at(&self, now: Instant) -> Point<Pixels> {
+        let elapsed = now.saturating_duration_since(self.started_at);
+        let progress =
+            (elapsed.as_secs_f32() / CURSOR_ANIMATION_DURATION.as_secs_f32()).clamp(0., 1.);
+        let eased = 1. - (1. - progress).powi(3);
+        point(
+            self.from.x + (self.to.x - self.from.x) * eased,
+            self.from.y + (self.to.y - self.from.y) * eased,
+        )
+    }
+
+    fn is_finished_at(&self, now: Instant) -> bool {
+        self.from == self.to
+            || now.saturating_duration_since(self.started_at) >= CURSOR_ANIMATION_DURATION
+    }
+}
+
+impl CursorAnimation {
+    /// Returns the position at which to paint a cursor moving to `target`, and whether it is
+    /// still moving. Positions are relative to the start of the text, ignoring scrolling.
+    pub fn position(
+        &mut self,
+        target: Point<Pixels>,
+        line_height: Pixels,
+        now: Instant,
+    ) -> (Point<Pixels>, bool) {
+        let transition = match self.transition {
+            Some(transition) if transition.to == target => transition,
+            Some(transition)
+                if (transition.to.y - target.y).abs() <= line_height * MAX_ANIMATED_ROWS =>
+            {
+                Transition {
+                    from: transition.position_at(now),
+                    to: target,
+                    started_at: now,
+                }
+            }
+            _ => Transition {
+                from: target,
+                to: target,
+                started_at: now,
+            },
+        };
+        self.transition = Some(transition);
+        (transition.position_at(now), !transition.is_finished_at(now))
+    }
+
+    pub fn reset(&mut self) {
+        self.transition = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::px;
+
+    #[test]
+    fn test_cursor_animation() {
+        let line_height = px(20.);
+        let start = Instant::now();
+        let mut animation = CursorAnimation::default();
+
+        // The first position is painted as is.
+        let (position, moving) = animation.position(point(px(10.), px(0.)), line_height, start);
+        assert_eq!(position, point(px(10.), px(0.)));
+        assert!(!moving);
+
+        // Moving the cursor starts from the previous position and ends at the new one.
+        let (position, moving) = animation.position(point(px(50.), px(20.)), line_height, start);
+        assert_eq!(position, point(px(10.), px(0.)));
+        assert!(moving);
+
+        let halfway = start + CURSOR_ANIMATION_DURATION / 2;
+        let (position, moving) = animation.position(point(px(50.), px(20.)), line_height, halfway);
+        assert!(position.x > px(10.) && position.x < px(50.));
+        assert!(position.y > px(0.) && position.y < px(20.));
+        assert!(moving);
+
+        let end = start + CURSOR_ANIMATION_DURATION;
+        let (position, moving) = animation.position(point(px(50.), px(20.)), line_height, end);
+        assert_eq!(position, point(px(50.), px(20.)));
+        assert!(!moving);
+
+        // Moving the cursor far away jumps there.
+        let (position, moving) = animation.position(point(px(0.), px(1000.)), line_height, end);
+        assert_eq!(position, point(px(0.), px(1000.)));
+        assert!(!moving);
+    }
+}
@@ -0,0 +1,139 @@
+use gpui::{Font, Pixels, SharedString};
+use std::{ops::Range, time::Duration};
+
+/// How long a collaborator's name is shown above their cursor after it last moved.
+pub(crate) const NAME_TAG_VISIBLE_FOR: Duration = Duration::from_secs(3);
+
+/// How long the name tag then takes to fade out.
+pub(crate) const NAME_TAG_FADE_OUT: Duration = Duration::from_millis(400);
+
+/// The width of a collaborator's name as it was last shaped, so that it's only shaped again when
+/// the name or its font changes.
+pub(crate) struct NameTag {
+    name: SharedString,
+    font: Font,
+    font_size: Pixels,
+    width: Pixels,
+}
+
+impl NameTag {
+    /// Returns the width of the given name, calling `shape` to measure it only when the cached
+    /// tag wasn't measured for the same name and font.
+    pub fn width(
+        cached: &mut Option<Self>,
+        name: &SharedString,
+        font: &Font,
+        font_size: Pixels,
+        shape: impl FnOnce() -> Pixels,
+    ) -> Pixels {
+        match cached {
+            Some(tag) if tag.name == *name && tag.font == *font && tag.font_size == font_size => {
+                tag.width
+            }
+            _ => {
+                let width = shape();
+                *cached = Some(NameTag {
+                    name: name.clone(),
+                    font: font.clone(),
+                    font_size,
+                    width,
+                });
+                width
+            }
+        }
+    }
+}
+
+/// Returns the opacity of the name tag of a collaborator whose cursor didn't move for the given
+/// duration.
+pub(crate) fn name_tag_opacity(idle: Duration) -> f32 {
+    match idle.checked_sub(NAME_TAG_VISIBLE_FOR) {
+        None => 1.,
+        Some(fading_for) => {
+            (1. - fading_for.as_secs_f32() / NAME_TAG_FADE_OUT.as_secs_f32()).max(0.)
+        }
+    }
+}
+
+/// Returns how many tags up the name tag spanning the given horizontal range on a row needs to
+/// be moved so that it doesn't cover the tags placed before it, and places it.
+pub(crate) fn stack_name_tag(
+    placed: &mut Vec<(u32, Range<Pixels>, usize)>,
+    row: u32,
+    x_range: Range<Pixels>,
+) -> usize {
+    let mut level = 0;
+    while placed
+        .iter()
+        .any(|(placed_row, placed_range, placed_level)| {
+            *placed_row == row
+                && *placed_level == level
+                && placed_range.start < x_range.end
+                && x_range.start < placed_range.end
+        })
+    {
+        level += 1;
+    }
+    placed.push((row, x_range, level));
+    level
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::px;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_cursor_name_tag_opacity() {
+        assert_eq!(name_tag_opacity(Duration::ZERO), 1.);
+        assert_eq!(name_tag_opacity(NAME_TAG_VISIBLE_FOR), 1.);
+
+        // The name tag fades out once the cursor stays still.
+        let opacity = name_tag_opacity(NAME_TAG_VISIBLE_FOR + NAME_TAG_FADE_OUT / 2);
+        assert!(opacity > 0. && opacity < 1.);
+        assert_eq!(
+            name_tag_opacity(NAME_TAG_VISIBLE_FOR + NAME_TAG_FADE_OUT),
+            0.
+        );
+    }
+
+    #[test]
+    fn test_cursor_name_tag_width() {
+        let name = SharedString::from("user");
+        let font = gpui::font("Helvetica");
+        let mut cached = None;
+
+        let shaped = Cell::new(0);
+        let shape = |width| {
+            shaped.set(shaped.get() + 1);
+            px(width)
+        };
+        assert_eq!(
+            NameTag::width(&mut cached, &name, &font, px(10.), || shape(30.)),
+            px(30.)
+        );
+        assert_eq!(
+            NameTag::width(&mut cached, &name, &font, px(10.), || shape(30.)),
+            px(30.)
+        );
+        assert_eq!(shaped.get(), 1);
+
+        // The name is measured again once its size changes.
+        assert_eq!(
+            NameTag::width(&mut cached, &name, &font, px(12.), || shape(36.)),
+            px(36.)
+        );
+        assert_eq!(shaped.get(), 2);
+    }
+
+    #[test]
+    fn test_stack_name_tag() {
+        let mut placed = Vec::new();
+        assert_eq!(stack_name_tag(&mut placed, 0, px(0.)..px(50.)), 0);
+        assert_eq!(stack_name_tag(&mut placed, 0, px(20.)..px(70.)), 1);
+        assert_eq!(stack_name_tag(&mut placed, 0, px(40.)..px(90.)), 2);
+        assert_eq!(stack_name_tag(&mut placed, 0, px(60.)..px(110.)), 0);
+        assert_eq!(stack_name_tag(&mut placed, 1, px(0.)..px(50.)), 0);
+    }
+}
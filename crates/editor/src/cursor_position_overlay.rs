@@ -0,0 +1,123 @@
+use crate::{Editor, EditorMode, EditorSettings};
+use gpui::{AnyElement, ViewContext};
+use language::{Point, Selection};
+use multi_buffer::MultiBufferSnapshot;
+use settings::Settings;
+use std::time::Duration;
+use ui::prelude::*;
+
+/// How long the overlay stays visible after the selection stops changing.
+const CURSOR_POSITION_OVERLAY_VISIBLE_FOR: Duration = Duration::from_millis(1000);
+
+/// Shows the overlay while the newest selection is non-empty and hides it once the selection
+/// has been stable for [`CURSOR_POSITION_OVERLAY_VISIBLE_FOR`].
+pub(crate) fn refresh_cursor_position_overlay(editor: &mut Editor, cx: &mut ViewContext<Editor>) {
+    let enabled = editor.mode == EditorMode::Full
+        && EditorSettings::get_global(cx).cursor_position_overlay
+        && !editor.selections.newest::<usize>(cx).is_empty();
+    if !enabled {
+        editor.show_cursor_position_overlay = false;
+        editor.hide_cursor_position_overlay_task = None;
+        return;
+    }
+
+    editor.show_cursor_position_overlay = true;
+    editor.hide_cursor_position_overlay_task = Some(cx.spawn(|this, mut cx| async move {
+        cx.background_executor()
+            .timer(CURSOR_POSITION_OVERLAY_VISIBLE_FOR)
+            .await;
+        this.update(&mut cx, |this, cx| {
+            this.show_cursor_position_overlay = false;
+            cx.notify();
+        })
+        .ok();
+    }));
+}
+
+pub(crate) fn render_cursor_position_overlay(
+    editor: &Editor,
+    cx: &mut ViewContext<Editor>,
+) -> Option<AnyElement> {
+    if !editor.show_cursor_position_overlay {
+        return None;
+    }
+
+    let snapshot = editor.buffer.read(cx).snapshot(cx);
+    let selection = editor.selections.newest::<usize>(cx);
+    let colors = cx.theme().colors();
+    Some(
+        div()
+            .px_1()
+            .rounded_md()
+            .border_1()
+            .border_color(colors.border_variant)
+            .bg(colors.elevated_surface_background)
+            .child(
+                Label::new(cursor_position_summary(&snapshot, &selection))
+                    .size(LabelSize::XSmall)
+                    .color(Color::Muted),
+            )
+            .into_any_element(),
+    )
+}
+
+/// Describes the head of the selection as a 1-based line and column, followed by the length of
+/// the selection and the byte offset of the head.
+pub(crate) fn cursor_position_summary(
+    snapshot: &MultiBufferSnapshot,
+    selection: &Selection<usize>,
+) -> String {
+    let head = snapshot.offset_to_point(selection.head());
+    let column = snapshot
+        .text_for_range(Point::new(head.row, 0)..head)
+        .flat_map(|chunk| chunk.chars())
+        .count();
+    let mut summary = format!("{}:{}", head.row + 1, column + 1);
+    if !selection.is_empty() {
+        let chars = snapshot
+            .text_for_range(selection.range())
+            .flat_map(|chunk| chunk.chars())
+            .count();
+        let rows = snapshot.offset_to_point(selection.end).row
+            - snapshot.offset_to_point(selection.start).row
+            + 1;
+        let lines = if rows == 1 { "line" } else { "lines" };
+        summary.push_str(&format!(" · {chars} chars, {rows} {lines}"));
+    }
+    summary.push_str(&format!(" · byte {}", selection.head()));
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::AppContext;
+    use language::SelectionGoal;
+    use multi_buffer::MultiBuffer;
+
+    #[gpui::test]
+    fn test_cursor_position_summary(cx: &mut AppContext) {
+        let buffer = MultiBuffer::build_simple("fn main() {\n    ümlaut\n}\n", cx);
+        let snapshot = buffer.read(cx).snapshot(cx);
+        let selection = |start: usize, end: usize, reversed: bool| Selection {
+            id: 0,
+            start,
+            end,
+            reversed,
+            goal: SelectionGoal::None,
+        };
+
+        assert_eq!(
+            cursor_position_summary(&snapshot, &selection(3, 3, false)),
+            "1:4 · byte 3"
+        );
+        assert_eq!(
+            cursor_position_summary(&snapshot, &selection(16, 20, false)),
+            "2:8 · 3 chars, 1 line · byte 20"
+        );
+        assert_eq!(
+            cursor_position_summary(&snapshot, &selection(3, 20, true)),
+            "1:4 · 16 chars, 2 lines · byte 3"
+        );
+    }
+}
@@ -0,0 +1,795 @@
+use crate::{
+    display_map::{
+        BlockContext, BlockDisposition, BlockId, BlockProperties, BlockStyle, DisplayPoint,
+        DisplaySnapshot, ToDisplayPoint,
+    },
+    scroll::Autoscroll,
+    Bias, Direction, Editor, EditorEvent, OpenSideBySideDiff,
+};
+use anyhow::Result;
+use collections::HashSet;
+use git::diff::{BufferDiff, DiffHunk};
+use gpui::{
+    actions, point, AnyElement, AppContext, EntityId, EventEmitter, FocusHandle, FocusableView,
+    HighlightStyle, Model, Subscription, Task, View, ViewContext,
+};
+use language::{language_settings::SoftWrap, Buffer, Point};
+use project::Project;
+use std::{ops::Range, path::PathBuf, sync::Arc};
+use ui::prelude::*;
+use util::ResultExt;
+use workspace::{
+    item::{Item, ItemEvent},
+    SplitDirection,
+};
+
+actions!(diff_view, [GoToNextHunk, GoToPreviousHunk]);
+
+/// An old and a new version of a text, shown side by side in two editors whose changed lines are
+/// aligned by padding the shorter side of each hunk, and which scroll together. The old version
+/// is read-only, while the new one can be edited and saved.
+pub struct DiffView {
+    /// Whether the old version is replaced with the new version's git diff base whenever it
+    /// changes.
+    follows_diff_base: bool,
+    old_buffer: Model<Buffer>,
+    new_buffer: Model<Buffer>,
+    old_editor: View<Editor>,
+    new_editor: View<Editor>,
+    title: SharedString,
+    hunks: Vec<AlignedHunk>,
+    old_padding: HashSet<BlockId>,
+    new_padding: HashSet<BlockId>,
+    /// The editor whose next scroll event was caused by scrolling it along with the other one.
+    scrolled_along: Option<EntityId>,
+    _update_diff: Task<()>,
+    _subscriptions: Vec<Subscription>,
+}
+
+/// The rows a hunk spans in the old and new versions of the text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct AlignedHunk {
+    old_rows: Range<u32>,
+    new_rows: Range<u32>,
+}
+
+impl DiffView {
+    pub fn new(
+        old_buffer: Model<Buffer>,
+        new_buffer: Model<Buffer>,
+        title: SharedString,
+        project: Option<Model<Project>>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let old_editor = cx.new_view(|cx| {
+            let mut editor = Editor::for_buffer(old_buffer.clone(), project.clone(), cx);
+            editor.set_read_only(true);
+            editor.set_soft_wrap_mode(SoftWrap::None, cx);
+            editor
+        });
+        let new_editor = cx.new_view(|cx| {
+            let mut editor = Editor::for_buffer(new_buffer.clone(), project, cx);
+            editor.set_soft_wrap_mode(SoftWrap::None, cx);
+            editor
+        });
+        let subscriptions = vec![
+            cx.subscribe(&old_editor, Self::on_editor_event),
+            cx.subscribe(&new_editor, Self::on_editor_event),
+            cx.subscribe(&old_buffer, Self::on_buffer_event),
+            cx.subscribe(&new_buffer, Self::on_buffer_event),
+        ];
+
+        let mut this = Self {
+            follows_diff_base: false,
+            old_buffer,
+            new_buffer,
+            old_editor,
+            new_editor,
+            title,
+            hunks: Vec::new(),
+            old_padding: HashSet::default(),
+            new_padding: HashSet::default(),
+            scrolled_along: None,
+            _update_diff: Task::ready(()),
+            _subscriptions: subscriptions,
+        };
+        this.update_diff(cx);
+        this
+    }
+
+    /// Shows the new buffer's git diff base as the old version of the text, keeping it up to date
+    /// as the diff base changes.
+    pub fn follow_diff_base(&mut self, cx: &mut ViewContext<Self>) {
+        self.follows_diff_base = true;
+        self.update_old_text_from_diff_base(cx);
+    }
+
+    fn update_old_text_from_diff_base(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(diff_base) = self.new_buffer.read(cx).diff_base().map(str::to_string) else {
+            return;
+        };
+        self.old_buffer.update(cx, |buffer, cx| {
+            if buffer.text() != diff_base {
+                buffer.set_text(diff_base, cx);
+            }
+        });
+    }
+
+    /// Returns the editor showing the new version of the text.
+    pub fn new_editor(&self) -> &View<Editor> {
+        &self.new_editor
+    }
+
+    /// Returns the editor showing the old version of the text.
+    pub fn old_editor(&self) -> &View<Editor> {
+        &self.old_editor
+    }
+
+    fn on_editor_event(
+        &mut self,
+        editor: View<Editor>,
+        event: &EditorEvent,
+        cx: &mut ViewContext<Self>,
+    ) {
+        match event {
+            EditorEvent::ScrollPositionChanged { .. } => {
+                if self.scrolled_along == Some(editor.entity_id()) {
+                    self.scrolled_along = None;
+                } else {
+                    self.sync_scroll_position(&editor, cx);
+                }
+            }
+            EditorEvent::Saved | EditorEvent::DirtyChanged | EditorEvent::TitleChanged
+                if editor == self.new_editor =>
+            {
+                cx.emit(event.clone())
+            }
+            _ => {}
+        }
+    }
+
+    fn on_buffer_event(
+        &mut self,
+        buffer: Model<Buffer>,
+        event: &language::Event,
+        cx: &mut ViewContext<Self>,
+    ) {
+        match event {
+            language::Event::Edited => self.update_diff(cx),
+            language::Event::DiffBaseChanged
+                if self.follows_diff_base && buffer == self.new_buffer =>
+            {
+                self.update_old_text_from_diff_base(cx)
+            }
+            _ => {}
+        }
+    }
+
+    /// Scrolls the other editor so that the lines shown next to the given editor's top row are
+    /// the ones corresponding to it. The rows are mapped through the buffers rather than the
+    /// display, so that folds, inlays and blocks on either side don't break the alignment.
+    fn sync_scroll_position(&mut self, source: &View<Editor>, cx: &mut ViewContext<Self>) {
+        let from_old = *source == self.old_editor;
+        let target = if from_old {
+            self.new_editor.clone()
+        } else {
+            self.old_editor.clone()
+        };
+        let hunks = &self.hunks;
+        let (x, target_row, offset) = source.update(cx, |editor, cx| {
+            let position = editor.scroll_position(cx);
+            let snapshot = editor.snapshot(cx).display_snapshot;
+            let top_row = DisplayPoint::new(position.y.max(0.) as u32, 0)
+                .to_point(&snapshot)
+                .row;
+            let (source_row, target_row) = corresponding_rows(hunks, top_row, from_old);
+            let offset = position.y - display_row(&snapshot, source_row);
+            (position.x, target_row, offset)
+        });
+
+        let changed = target.update(cx, |editor, cx| {
+            let snapshot = editor.snapshot(cx).display_snapshot;
+            let position = point(x, display_row(&snapshot, target_row) + offset);
+            let current = editor.scroll_position(cx);
+            let changed = (current.x - position.x).abs() > f32::EPSILON
+                || (current.y - position.y).abs() > f32::EPSILON;
+            if changed {
+                editor.set_scroll_position(position, cx);
+            }
+            changed
+        });
+        // The target emits a scroll event of its own, which mustn't scroll the source back.
+        if changed {
+            self.scrolled_along = Some(target.entity_id());
+        }
+    }
+
+    /// Diffs the two versions of the text in the background, and aligns their rows once done.
+    fn update_diff(&mut self, cx: &mut ViewContext<Self>) {
+        let old_snapshot = self.old_buffer.read(cx).text_snapshot();
+        let new_snapshot = self.new_buffer.read(cx).text_snapshot();
+        self._update_diff = cx.spawn(|this, mut cx| async move {
+            let hunks = cx
+                .background_executor()
+                .spawn(async move {
+                    let mut diff = BufferDiff::new();
+                    diff.update(&old_snapshot.text(), &new_snapshot).await;
+                    let rows = 0..new_snapshot.max_point().row + 1;
+                    aligned_hunks(diff.hunks_in_row_range(rows, &new_snapshot), |offset| {
+                        old_snapshot.offset_to_point(offset)
+                    })
+                })
+                .await;
+            this.update(&mut cx, |this, cx| this.set_hunks(hunks, cx))
+                .ok();
+        });
+    }
+
+    fn set_hunks(&mut self, hunks: Vec<AlignedHunk>, cx: &mut ViewContext<Self>) {
+        let status = cx.theme().status();
+        let deleted_style = HighlightStyle {
+            background_color: Some(status.deleted_background),
+            ..Default::default()
+        };
+        let created_style = HighlightStyle {
+            background_color: Some(status.created_background),
+            ..Default::default()
+        };
+
+        let mut old_padding = Vec::new();
+        let mut new_padding = Vec::new();
+        for hunk in &hunks {
+            let old_len = hunk.old_rows.len() as u32;
+            let new_len = hunk.new_rows.len() as u32;
+            if new_len > old_len {
+                old_padding.push((hunk.old_rows.end, new_len - old_len));
+            } else if old_len > new_len {
+                new_padding.push((hunk.new_rows.end, old_len - new_len));
+            }
+        }
+
+        let old_rows = hunks.iter().map(|hunk| hunk.old_rows.clone()).collect();
+        self.old_padding = align_editor(
+            &self.old_editor,
+            &self.old_padding,
+            old_padding,
+            old_rows,
+            deleted_style,
+            cx,
+        );
+        let new_rows = hunks.iter().map(|hunk| hunk.new_rows.clone()).collect();
+        self.new_padding = align_editor(
+            &self.new_editor,
+            &self.new_padding,
+            new_padding,
+            new_rows,
+            created_style,
+            cx,
+        );
+        self.hunks = hunks;
+
+        // The rows above the new editor's scroll position may have been padded differently.
+        let new_editor = self.new_editor.clone();
+        self.sync_scroll_position(&new_editor, cx);
+        cx.notify();
+    }
+
+    fn go_to_next_hunk(&mut self, _: &GoToNextHunk, cx: &mut ViewContext<Self>) {
+        self.go_to_hunk(Direction::Next, cx);
+    }
+
+    fn go_to_previous_hunk(&mut self, _: &GoToPreviousHunk, cx: &mut ViewContext<Self>) {
+        self.go_to_hunk(Direction::Prev, cx);
+    }
+
+    /// Moves the cursors of both editors to the start of the next or previous hunk, wrapping
+    /// around at the ends of the text.
+    fn go_to_hunk(&mut self, direction: Direction, cx: &mut ViewContext<Self>) {
+        let row = self.new_editor.update(cx, |editor, cx| {
+            editor.selections.newest::<Point>(cx).head().row
+        });
+        let hunk = match direction {
+            Direction::Next => self
+                .hunks
+                .iter()
+                .find(|hunk| hunk.new_rows.start > row)
+                .or_else(|| self.hunks.first()),
+            Direction::Prev => self
+                .hunks
+                .iter()
+                .rev()
+                .find(|hunk| hunk.new_rows.start < row)
+                .or_else(|| self.hunks.last()),
+        };
+        let Some(hunk) = hunk.cloned() else {
+            return;
+        };
+
+        // Only the new editor autoscrolls, and the old one follows it.
+        self.old_editor.update(cx, |editor, cx| {
+            let point = editor
+                .buffer()
+                .read(cx)
+                .snapshot(cx)
+                .clip_point(Point::new(hunk.old_rows.start, 0), Bias::Left);
+            editor.change_selections(None, cx, |s| s.select_ranges([point..point]));
+        });
+        self.new_editor.update(cx, |editor, cx| {
+            let point = editor
+                .buffer()
+                .read(cx)
+                .snapshot(cx)
+                .clip_point(Point::new(hunk.new_rows.start, 0), Bias::Left);
+            editor.change_selections(Some(Autoscroll::center()), cx, |s| {
+                s.select_ranges([point..point])
+            });
+        });
+    }
+}
+
+/// Replaces the blocks padding one side of the diff, and highlights the rows of its hunks.
+fn align_editor(
+    editor: &View<Editor>,
+    old_blocks: &HashSet<BlockId>,
+    padding: Vec<(u32, u32)>,
+    hunk_rows: Vec<Range<u32>>,
+    style: HighlightStyle,
+    cx: &mut ViewContext<DiffView>,
+) -> HashSet<BlockId> {
+    editor.update(cx, |editor, cx| {
+        editor.remove_blocks(old_blocks.clone(), None, cx);
+        let snapshot = editor.buffer().read(cx).snapshot(cx);
+        let max_point = snapshot.max_point();
+        let mut blocks = Vec::new();
+        for (row, mut height) in padding {
+            // Padding after the last line is shown below it.
+            let (position, disposition) = if row > max_point.row {
+                (snapshot.anchor_after(max_point), BlockDisposition::Below)
+            } else {
+                let point = Point::new(row, 0);
+                (snapshot.anchor_before(point), BlockDisposition::Above)
+            };
+            while height > 0 {
+                let block_height = height.min(u8::MAX as u32);
+                height -= block_height;
+                blocks.push(BlockProperties {
+                    position,
+                    height: block_height as u8,
+                    style: BlockStyle::Flex,
+                    render: Arc::new(render_padding),
+                    disposition,
+                });
+            }
+        }
+        let highlights = hunk_rows
+            .into_iter()
+            .filter(|rows| !rows.is_empty())
+            .map(|rows| {
+                let end = Point::new(rows.end, 0).min(max_point);
+                snapshot.anchor_after(Point::new(rows.start, 0))..snapshot.anchor_before(end)
+            })
+            .collect::<Vec<_>>();
+        editor.highlight_text::<DiffView>(highlights, style, cx);
+        editor.insert_blocks(blocks, None, cx).into_iter().collect()
+    })
+}
+
+fn render_padding(cx: &mut BlockContext) -> AnyElement {
+    div()
+        .size_full()
+        .bg(cx.theme().colors().editor_subheader_background)
+        .into_any_element()
+}
+
+/// Converts the hunks of the diff between the two versions to the rows they span in each of
+/// them, given a way to convert offsets in the old version to points.
+fn aligned_hunks(
+    hunks: impl Iterator<Item = DiffHunk<u32>>,
+    old_offset_to_point: impl Fn(usize) -> Point,
+) -> Vec<AlignedHunk> {
+    // The number of rows the new version has gained over the old one before the current hunk.
+    let mut divergence = 0i64;
+    hunks
+        .map(|hunk| {
+            let old_rows = if hunk.diff_base_byte_range.is_empty() {
+                // Hunks that only add lines don't have a position in the old version, so it's
+                // derived from the rows added and removed by the hunks before them.
+                let row = (hunk.buffer_range.start as i64 - divergence) as u32;
+                row..row
+            } else {
+                let start = old_offset_to_point(hunk.diff_base_byte_range.start);
+                let end = old_offset_to_point(hunk.diff_base_byte_range.end);
+                // A hunk ending within the last line, which has no trailing newline, spans it.
+                let end_row = if end.column > 0 { end.row + 1 } else { end.row };
+                start.row..end_row
+            };
+            divergence += hunk.buffer_range.len() as i64 - old_rows.len() as i64;
+            AlignedHunk {
+                old_rows,
+                new_rows: hunk.buffer_range,
+            }
+        })
+        .collect()
+}
+
+/// Returns a row of the given version of the text that is aligned with a row of the other
+/// version, along with that row, such that the given row is at a fixed number of display rows
+/// below the first. Rows outside of hunks correspond to each other directly, while rows inside of
+/// a hunk are measured from its start, since the shorter side of the hunk is padded.
+fn corresponding_rows(hunks: &[AlignedHunk], row: u32, from_old: bool) -> (u32, u32) {
+    let mut divergence = 0i64;
+    for hunk in hunks {
+        let (source, target) = if from_old {
+            (&hunk.old_rows, &hunk.new_rows)
+        } else {
+            (&hunk.new_rows, &hunk.old_rows)
+        };
+        if row < source.start {
+            break;
+        }
+        if row < source.end {
+            return (source.start, target.start);
+        }
+        divergence = target.end as i64 - source.end as i64;
+    }
+    (row, (row as i64 + divergence).max(0) as u32)
+}
+
+fn display_row(snapshot: &DisplaySnapshot, row: u32) -> f32 {
+    let point = snapshot
+        .buffer_snapshot
+        .clip_point(Point::new(row, 0), Bias::Left);
+    point.to_display_point(snapshot).row() as f32
+}
+
+impl EventEmitter<EditorEvent> for DiffView {}
+
+impl FocusableView for DiffView {
+    fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
+        self.new_editor.focus_handle(cx)
+    }
+}
+
+impl Item for DiffView {
+    type Event = EditorEvent;
+
+    fn tab_content(&self, _: Option<usize>, selected: bool, _: &WindowContext) -> AnyElement {
+        h_flex()
+            .gap_2()
+            .child(Icon::new(IconName::FileGit).color(if selected {
+                Color::Default
+            } else {
+                Color::Muted
+            }))
+            .child(Label::new(self.title.clone()).color(if selected {
+                Color::Default
+            } else {
+                Color::Muted
+            }))
+            .into_any_element()
+    }
+
+    fn telemetry_event_text(&self) -> Option<&'static str> {
+        Some("side by side diff")
+    }
+
+    fn is_dirty(&self, cx: &AppContext) -> bool {
+        self.new_editor.read(cx).is_dirty(cx)
+    }
+
+    fn has_conflict(&self, cx: &AppContext) -> bool {
+        self.new_editor.read(cx).has_conflict(cx)
+    }
+
+    fn can_save(&self, cx: &AppContext) -> bool {
+        self.new_editor.read(cx).can_save(cx)
+    }
+
+    fn save(&mut self, project: Model<Project>, cx: &mut ViewContext<Self>) -> Task<Result<()>> {
+        self.new_editor
+            .update(cx, |editor, cx| editor.save(project, cx))
+    }
+
+    fn save_as(
+        &mut self,
+        project: Model<Project>,
+        abs_path: PathBuf,
+        cx: &mut ViewContext<Self>,
+    ) -> Task<Result<()>> {
+        self.new_editor
+            .update(cx, |editor, cx| editor.save_as(project, abs_path, cx))
+    }
+
+    fn reload(&mut self, project: Model<Project>, cx: &mut ViewContext<Self>) -> Task<Result<()>> {
+        self.new_editor
+            .update(cx, |editor, cx| editor.reload(project, cx))
+    }
+
+    fn for_each_project_item(
+        &self,
+        cx: &AppContext,
+        f: &mut dyn FnMut(EntityId, &dyn project::Item),
+    ) {
+        self.new_editor.read(cx).for_each_project_item(cx, f)
+    }
+
+    fn is_singleton(&self, cx: &AppContext) -> bool {
+        self.new_editor.read(cx).is_singleton(cx)
+    }
+
+    fn to_item_events(event: &EditorEvent, f: impl FnMut(ItemEvent)) {
+        Editor::to_item_events(event, f)
+    }
+}
+
+impl Render for DiffView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        h_flex()
+            .key_context("DiffView")
+            .on_action(cx.listener(Self::go_to_next_hunk))
+            .on_action(cx.listener(Self::go_to_previous_hunk))
+            .size_full()
+            .child(div().flex_1().h_full().child(self.old_editor.clone()))
+            .child(div().w_px().h_full().bg(cx.theme().colors().border))
+            .child(div().flex_1().h_full().child(self.new_editor.clone()))
+    }
+}
+
+impl Editor {
+    /// Opens the buffer's text side by side with its git diff base in a split to the right.
+    pub fn open_side_by_side_diff(&mut self, _: &OpenSideBySideDiff, cx: &mut ViewContext<Self>) {
+        let (Some(project), Some(workspace)) = (self.project.clone(), self.workspace()) else {
+            return;
+        };
+        let Some(buffer) = self.buffer.read(cx).as_singleton() else {
+            return;
+        };
+        let Some(diff_base) = buffer.read(cx).diff_base().map(str::to_string) else {
+            return;
+        };
+        let language = buffer.read(cx).language().cloned();
+        let name = buffer
+            .read(cx)
+            .file()
+            .map(|file| file.file_name(cx).to_string_lossy().into_owned())
+            .unwrap_or_else(|| "untitled".to_string());
+
+        workspace
+            .update(cx, |workspace, cx| {
+                let old_buffer = project.update(cx, |project, cx| {
+                    project.create_buffer(&diff_base, language, cx)
+                })?;
+                let diff_view = cx.new_view(|cx| {
+                    let mut diff_view = DiffView::new(
+                        old_buffer,
+                        buffer,
+                        format!("{name} (diff)").into(),
+                        Some(project),
+                        cx,
+                    );
+                    diff_view.follow_diff_base(cx);
+                    diff_view
+                });
+                workspace.split_item(SplitDirection::Right, Box::new(diff_view), cx);
+                anyhow::Ok(())
+            })
+            .log_err();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor_tests::init_test;
+    use text::BufferId;
+    use unindent::Unindent;
+
+    #[gpui::test]
+    async fn test_side_by_side_diff(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let buffer = |text: String, cx: &mut gpui::TestAppContext| {
+            cx.new_model(|cx| Buffer::new(0, BufferId::new(cx.entity_id().as_u64()).unwrap(), text))
+        };
+        let old_buffer = buffer(
+            "
+            one
+            two
+            three
+            four
+            five
+            "
+            .unindent(),
+            cx,
+        );
+        let new_buffer = buffer(
+            "
+            one
+            TWO
+            TWO AND A HALF
+            three
+            five
+            six
+            "
+            .unindent(),
+            cx,
+        );
+        let (view, cx) = cx.add_window_view(|cx| {
+            DiffView::new(old_buffer, new_buffer.clone(), "test".into(), None, cx)
+        });
+        cx.run_until_parked();
+
+        // Each side is padded so that the lines following a hunk stay aligned.
+        let max_rows = |view: &View<DiffView>, cx: &mut gpui::VisualTestContext| {
+            view.update(cx, |view, cx| {
+                let old_row = view.old_editor.update(cx, |editor, cx| {
+                    editor.snapshot(cx).display_snapshot.max_point().row()
+                });
+                let new_row = view.new_editor.update(cx, |editor, cx| {
+                    editor.snapshot(cx).display_snapshot.max_point().row()
+                });
+                (old_row, new_row)
+            })
+        };
+        view.update(cx, |view, _| {
+            assert_eq!(
+                view.hunks,
+                [
+                    AlignedHunk {
+                        old_rows: 1..2,
+                        new_rows: 1..3,
+                    },
+                    AlignedHunk {
+                        old_rows: 3..4,
+                        new_rows: 4..4,
+                    },
+                    AlignedHunk {
+                        old_rows: 5..5,
+                        new_rows: 5..6,
+                    },
+                ]
+            );
+        });
+        assert_eq!(max_rows(&view, cx), (7, 7));
+
+        // Hunks are navigated in both directions, wrapping around.
+        let cursor_rows = |view: &View<DiffView>, cx: &mut gpui::VisualTestContext| {
+            view.update(cx, |view, cx| {
+                let row = |editor: &View<Editor>, cx: &mut ViewContext<DiffView>| {
+                    editor.update(cx, |editor, cx| {
+                        editor.selections.newest::<Point>(cx).head().row
+                    })
+                };
+                (row(&view.old_editor, cx), row(&view.new_editor, cx))
+            })
+        };
+        view.update(cx, |view, cx| view.go_to_next_hunk(&GoToNextHunk, cx));
+        assert_eq!(cursor_rows(&view, cx), (1, 1));
+        view.update(cx, |view, cx| view.go_to_next_hunk(&GoToNextHunk, cx));
+        assert_eq!(cursor_rows(&view, cx), (3, 4));
+        view.update(cx, |view, cx| view.go_to_next_hunk(&GoToNextHunk, cx));
+        assert_eq!(cursor_rows(&view, cx), (5, 5));
+        view.update(cx, |view, cx| view.go_to_next_hunk(&GoToNextHunk, cx));
+        assert_eq!(cursor_rows(&view, cx), (1, 1));
+        view.update(cx, |view, cx| {
+            view.go_to_previous_hunk(&GoToPreviousHunk, cx)
+        });
+        assert_eq!(cursor_rows(&view, cx), (5, 5));
+
+        // Editing the new version aligns the rows again.
+        new_buffer.update(cx, |buffer, cx| buffer.edit([(4..23, "two\n")], None, cx));
+        cx.run_until_parked();
+        view.update(cx, |view, _| {
+            assert_eq!(
+                view.hunks,
+                [
+                    AlignedHunk {
+                        old_rows: 3..4,
+                        new_rows: 3..3,
+                    },
+                    AlignedHunk {
+                        old_rows: 5..5,
+                        new_rows: 4..5,
+                    },
+                ]
+            );
+        });
+        assert_eq!(max_rows(&view, cx), (6, 6));
+    }
+
+    #[gpui::test]
+    async fn test_side_by_side_diff_scrolling(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let old_text = (0..20)
+            .map(|row| format!("line {row}\n"))
+            .collect::<String>();
+        let new_text = old_text.replace("line 2\n", "");
+        let old_buffer = cx.new_model(|cx| {
+            Buffer::new(0, BufferId::new(cx.entity_id().as_u64()).unwrap(), old_text)
+        });
+        let new_buffer = cx.new_model(|cx| {
+            Buffer::new(0, BufferId::new(cx.entity_id().as_u64()).unwrap(), new_text)
+        });
+        let (view, cx) =
+            cx.add_window_view(|cx| DiffView::new(old_buffer, new_buffer, "test".into(), None, cx));
+        cx.run_until_parked();
+
+        // Folds in one of the editors don't break the alignment of the rows shown next to each
+        // other, and the editor that was scrolled along doesn't scroll the other one back.
+        let top_row = |editor: &View<Editor>, cx: &mut gpui::VisualTestContext| {
+            editor.update(cx, |editor, cx| {
+                let position = editor.scroll_position(cx);
+                let snapshot = editor.snapshot(cx).display_snapshot;
+                DisplayPoint::new(position.y as u32, 0)
+                    .to_point(&snapshot)
+                    .row
+            })
+        };
+        let (old_editor, new_editor) = view.update(cx, |view, _| {
+            (view.old_editor.clone(), view.new_editor.clone())
+        });
+        new_editor.update(cx, |editor, cx| {
+            editor.fold_ranges([Point::new(10, 0)..Point::new(14, 0)], false, cx)
+        });
+        old_editor.update(cx, |editor, cx| {
+            editor.set_scroll_position(point(0., 16.), cx)
+        });
+        cx.run_until_parked();
+        assert_eq!(top_row(&old_editor, cx), 16);
+        assert_eq!(top_row(&new_editor, cx), 15);
+
+        new_editor.update(cx, |editor, cx| {
+            editor.set_scroll_position(point(0., 1.), cx)
+        });
+        cx.run_until_parked();
+        assert_eq!(top_row(&new_editor, cx), 1);
+        assert_eq!(top_row(&old_editor, cx), 1);
+    }
+
+    #[gpui::test]
+    async fn test_side_by_side_diff_follows_diff_base(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let old_buffer =
+            cx.new_model(|cx| Buffer::new(0, BufferId::new(cx.entity_id().as_u64()).unwrap(), ""));
+        let new_buffer = cx.new_model(|cx| {
+            let mut buffer = Buffer::new(
+                0,
+                BufferId::new(cx.entity_id().as_u64()).unwrap(),
+                "one\ntwo\n",
+            );
+            buffer.set_diff_base(Some("one\n".to_string()), cx);
+            buffer
+        });
+        let (view, cx) = cx.add_window_view(|cx| {
+            let mut view = DiffView::new(
+                old_buffer.clone(),
+                new_buffer.clone(),
+                "test".into(),
+                None,
+                cx,
+            );
+            view.follow_diff_base(cx);
+            view
+        });
+        cx.run_until_parked();
+        assert_eq!(old_buffer.read_with(cx, |buffer, _| buffer.text()), "one\n");
+        view.update(cx, |view, _| {
+            assert_eq!(
+                view.hunks,
+                [AlignedHunk {
+                    old_rows: 1..1,
+                    new_rows: 1..2,
+                }]
+            )
+        });
+
+        new_buffer.update(cx, |buffer, cx| {
+            buffer.set_diff_base(Some("one\ntwo\n".to_string()), cx)
+        });
+        cx.run_until_parked();
+        assert_eq!(
+            old_buffer.read_with(cx, |buffer, _| buffer.text()),
+            "one\ntwo\n"
+        );
+        view.update(cx, |view, _| assert!(view.hunks.is_empty()));
+    }
+}
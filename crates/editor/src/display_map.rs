@@ -31,12 +31,12 @@ use crate::{
 pub use block_map::{BlockMap, BlockPoint};
 use collections::{BTreeMap, HashMap, HashSet};
 use fold_map::FoldMap;
-use gpui::{Font, HighlightStyle, Hsla, LineLayout, Model, ModelContext, Pixels, UnderlineStyle};
+use gpui::{Font, HighlightStyle, Hsla, LineLayout, Model, ModelContext, Pixels, WrapIndent};
 use inlay_map::InlayMap;
 use language::{
-    language_settings::language_settings, OffsetUtf16, Point, Subscription as BufferSubscription,
+    language_settings::language_settings, Chunk, OffsetUtf16, Point,
+    Subscription as BufferSubscription,
 };
-use lsp::DiagnosticSeverity;
 use std::{any::TypeId, borrow::Cow, fmt::Debug, num::NonZeroU32, ops::Range, sync::Arc};
 use sum_tree::{Bias, TreeMap};
 use tab_map::TabMap;
@@ -89,6 +89,8 @@ pub struct DisplayMap {
     text_highlights: TextHighlights,
     /// Regions of inlays that should be highlighted.
     inlay_highlights: InlayHighlights,
+    /// Incremented whenever the text or inlay highlights change.
+    highlights_version: usize,
     pub clip_at_line_ends: bool,
 }
 
@@ -121,6 +123,7 @@ impl DisplayMap {
             block_map,
             text_highlights: Default::default(),
             inlay_highlights: Default::default(),
+            highlights_version: 0,
             clip_at_line_ends: false,
         }
     }
@@ -159,6 +162,11 @@ impl DisplayMap {
         );
     }
 
+    /// Returns the ranges of the folds, as of the last time the map was synced with the buffer.
+    pub fn fold_ranges(&self) -> Vec<Range<Anchor>> {
+        self.fold_map.fold_ranges()
+    }
+
     pub fn fold<T: ToOffset>(
         &mut self,
         ranges: impl IntoIterator<Item = Range<T>>,
@@ -228,6 +236,48 @@ impl DisplayMap {
         self.block_map.replace(styles);
     }
 
+    pub fn resize_blocks(&mut self, heights: HashMap<BlockId, u8>, cx: &mut ModelContext<Self>) {
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let edits = self.buffer_subscription.consume().into_inner();
+        let tab_size = Self::tab_size(&self.buffer, cx);
+        let (snapshot, edits) = self.inlay_map.sync(snapshot, edits);
+        let (snapshot, edits) = self.fold_map.read(snapshot, edits);
+        let (snapshot, edits) = self.tab_map.sync(snapshot, edits, tab_size);
+        let (snapshot, edits) = self
+            .wrap_map
+            .update(cx, |map, cx| map.sync(snapshot, edits, cx));
+        let mut block_map = self.block_map.write(snapshot, edits);
+        block_map.resize(heights);
+    }
+
+    pub fn hide_blocks(&mut self, ids: HashSet<BlockId>, cx: &mut ModelContext<Self>) {
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let edits = self.buffer_subscription.consume().into_inner();
+        let tab_size = Self::tab_size(&self.buffer, cx);
+        let (snapshot, edits) = self.inlay_map.sync(snapshot, edits);
+        let (snapshot, edits) = self.fold_map.read(snapshot, edits);
+        let (snapshot, edits) = self.tab_map.sync(snapshot, edits, tab_size);
+        let (snapshot, edits) = self
+            .wrap_map
+            .update(cx, |map, cx| map.sync(snapshot, edits, cx));
+        let mut block_map = self.block_map.write(snapshot, edits);
+        block_map.hide(ids);
+    }
+
+    pub fn show_blocks(&mut self, ids: HashSet<BlockId>, cx: &mut ModelContext<Self>) {
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let edits = self.buffer_subscription.consume().into_inner();
+        let tab_size = Self::tab_size(&self.buffer, cx);
+        let (snapshot, edits) = self.inlay_map.sync(snapshot, edits);
+        let (snapshot, edits) = self.fold_map.read(snapshot, edits);
+        let (snapshot, edits) = self.tab_map.sync(snapshot, edits, tab_size);
+        let (snapshot, edits) = self
+            .wrap_map
+            .update(cx, |map, cx| map.sync(snapshot, edits, cx));
+        let mut block_map = self.block_map.write(snapshot, edits);
+        block_map.show(ids);
+    }
+
     pub fn remove_blocks(&mut self, ids: HashSet<BlockId>, cx: &mut ModelContext<Self>) {
         let snapshot = self.buffer.read(cx).snapshot(cx);
         let edits = self.buffer_subscription.consume().into_inner();
@@ -250,6 +300,7 @@ impl DisplayMap {
     ) {
         self.text_highlights
             .insert(Some(type_id), Arc::new((style, ranges)));
+        self.highlights_version += 1;
     }
 
     pub(crate) fn highlight_inlays(
@@ -264,6 +315,7 @@ impl DisplayMap {
                 .or_default()
                 .insert(highlight.inlay, (style, highlight));
         }
+        self.highlights_version += 1;
     }
 
     pub fn text_highlights(&self, type_id: TypeId) -> Option<(HighlightStyle, &[Range<Anchor>])> {
@@ -273,9 +325,18 @@ impl DisplayMap {
     pub fn clear_highlights(&mut self, type_id: TypeId) -> bool {
         let mut cleared = self.text_highlights.remove(&Some(type_id)).is_some();
         cleared |= self.inlay_highlights.remove(&type_id).is_some();
+        if cleared {
+            self.highlights_version += 1;
+        }
         cleared
     }
 
+    /// The number of times the text or inlay highlights changed, which changes the styles of the
+    /// text without changing its rows.
+    pub fn highlights_version(&self) -> usize {
+        self.highlights_version
+    }
+
     pub fn set_font(&self, font: Font, font_size: Pixels, cx: &mut ModelContext<Self>) -> bool {
         self.wrap_map
             .update(cx, |map, cx| map.set_font_with_size(font, font_size, cx))
@@ -290,6 +351,18 @@ impl DisplayMap {
             .update(cx, |map, cx| map.set_wrap_width(width, cx))
     }
 
+    pub fn set_wrap_indent(&self, indent: WrapIndent, cx: &mut ModelContext<Self>) -> bool {
+        self.wrap_map
+            .update(cx, |map, cx| map.set_wrap_indent(indent, cx))
+    }
+
+    /// Returns the display rows that changed since the last call, extending to the end of the
+    /// map when rows were inserted or removed, so that layout work can be limited to them.
+    pub fn take_dirty_rows(&mut self, cx: &mut ModelContext<Self>) -> Option<Range<u32>> {
+        self.snapshot(cx);
+        self.block_map.take_dirty_rows()
+    }
+
     pub(crate) fn current_inlays(&self) -> impl Iterator<Item = &Inlay> {
         self.inlay_map.current_inlays()
     }
@@ -349,6 +422,7 @@ pub struct HighlightedChunk<'a> {
     pub chunk: &'a str,
     pub style: Option<HighlightStyle>,
     pub is_tab: bool,
+    pub is_suggestion: bool,
 }
 
 pub struct DisplaySnapshot {
@@ -545,50 +619,32 @@ impl DisplaySnapshot {
             Some(editor_style.inlays_style),
             Some(editor_style.suggestions_style),
         )
-        .map(|chunk| {
-            let mut highlight_style = chunk
-                .syntax_highlight_id
-                .and_then(|id| id.style(&editor_style.syntax));
-
-            if let Some(chunk_highlight) = chunk.highlight_style {
-                if let Some(highlight_style) = highlight_style.as_mut() {
-                    highlight_style.highlight(chunk_highlight);
-                } else {
-                    highlight_style = Some(chunk_highlight);
-                }
-            }
-
-            let mut diagnostic_highlight = HighlightStyle::default();
-
-            if chunk.is_unnecessary {
-                diagnostic_highlight.fade_out = Some(UNNECESSARY_CODE_FADE);
-            }
+        .map(|chunk| highlighted_chunk(chunk, editor_style))
+    }
 
-            if let Some(severity) = chunk.diagnostic_severity {
-                // Omit underlines for HINT/INFO diagnostics on 'unnecessary' code.
-                if severity <= DiagnosticSeverity::WARNING || !chunk.is_unnecessary {
-                    let diagnostic_color =
-                        super::diagnostic_style(severity, true, &editor_style.status);
-                    diagnostic_highlight.underline = Some(UnderlineStyle {
-                        color: Some(diagnostic_color),
-                        thickness: 1.0.into(),
-                        wavy: true,
-                    });
-                }
-            }
+    /// Returns the text of the snapshot without its blocks, which can be moved to a background
+    /// thread to lay out rows there.
+    pub(crate) fn text_snapshot(&self) -> DisplayTextSnapshot {
+        DisplayTextSnapshot {
+            wrap_snapshot: self.wrap_snapshot.clone(),
+            text_highlights: self.text_highlights.clone(),
+            inlay_highlights: self.inlay_highlights.clone(),
+        }
+    }
 
-            if let Some(highlight_style) = highlight_style.as_mut() {
-                highlight_style.highlight(diagnostic_highlight);
+    /// Returns the rows of the snapshot's text, without its blocks, shown in the given display
+    /// rows.
+    pub(crate) fn text_rows(&self, display_rows: Range<u32>) -> Range<u32> {
+        let text_row = |display_row: u32| {
+            if display_row > self.max_point().row() {
+                self.wrap_snapshot.max_point().row() + 1
             } else {
-                highlight_style = Some(diagnostic_highlight);
-            }
-
-            HighlightedChunk {
-                chunk: chunk.text,
-                style: highlight_style,
-                is_tab: chunk.is_tab,
+                self.block_snapshot
+                    .to_wrap_point(BlockPoint::new(display_row, 0))
+                    .row()
             }
-        })
+        };
+        text_row(display_rows.start)..text_row(display_rows.end)
     }
 
     pub fn layout_row(
@@ -859,6 +915,26 @@ impl DisplaySnapshot {
         }
     }
 
+    pub fn syntax_fold_for_line(&self, buffer_row: u32) -> Option<FoldStatus> {
+        if self.is_line_folded(buffer_row) {
+            Some(FoldStatus::Folded)
+        } else if self.syntax_foldable_range(buffer_row).is_some() {
+            Some(FoldStatus::Foldable)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the range to fold to collapse the syntax node that starts on the given row, if
+    /// the buffer is a singleton and the row isn't folded already.
+    pub fn syntax_foldable_range(&self, buffer_row: u32) -> Option<Range<Point>> {
+        if self.is_line_folded(buffer_row) {
+            return None;
+        }
+        let (_, _, buffer) = self.buffer_snapshot.as_singleton()?;
+        buffer.syntax_fold_range(buffer_row)
+    }
+
     pub fn is_foldable(self: &Self, buffer_row: u32) -> bool {
         let max_row = self.buffer_snapshot.max_buffer_row();
         if buffer_row >= max_row {
@@ -925,6 +1001,95 @@ impl DisplaySnapshot {
     }
 }
 
+/// The text of a [`DisplaySnapshot`] without its blocks, whose rows are the display rows that
+/// aren't blocks. Unlike the display snapshot it can be moved to a background thread, to lay out
+/// rows there.
+#[derive(Clone)]
+pub(crate) struct DisplayTextSnapshot {
+    wrap_snapshot: wrap_map::WrapSnapshot,
+    text_highlights: TextHighlights,
+    inlay_highlights: InlayHighlights,
+}
+
+impl DisplayTextSnapshot {
+    pub fn buffer_snapshot(&self) -> &MultiBufferSnapshot {
+        self.wrap_snapshot.buffer_snapshot()
+    }
+
+    /// Returns the buffer offset of the start of the given row of the text.
+    pub fn offset_for_row(&self, row: u32) -> usize {
+        if row > self.wrap_snapshot.max_point().row() {
+            return self.buffer_snapshot().len();
+        }
+        self.wrap_snapshot
+            .to_point(wrap_map::WrapPoint::new(row, 0), Bias::Left)
+            .to_offset(self.buffer_snapshot())
+    }
+
+    /// Returns the row and column of the given buffer offset in the text.
+    pub fn offset_to_point(&self, offset: usize) -> (u32, u32) {
+        let point = offset.to_point(self.buffer_snapshot());
+        let wrap_point = self.wrap_snapshot.make_wrap_point(point, Bias::Left);
+        (wrap_point.row(), wrap_point.column())
+    }
+
+    /// Like [`DisplaySnapshot::highlighted_chunks`], for the given rows of the text.
+    pub fn highlighted_chunks<'a>(
+        &'a self,
+        rows: Range<u32>,
+        language_aware: bool,
+        editor_style: &'a EditorStyle,
+    ) -> impl Iterator<Item = HighlightedChunk<'a>> {
+        self.wrap_snapshot
+            .chunks(
+                rows,
+                language_aware,
+                Highlights {
+                    text_highlights: Some(&self.text_highlights),
+                    inlay_highlights: Some(&self.inlay_highlights),
+                    inlay_highlight_style: Some(editor_style.inlays_style),
+                    suggestion_highlight_style: Some(editor_style.suggestions_style),
+                },
+            )
+            .map(|chunk| highlighted_chunk(chunk, editor_style))
+    }
+}
+
+/// Styles a chunk of text with its syntax highlighting and the highlights it's in.
+fn highlighted_chunk<'a>(chunk: Chunk<'a>, editor_style: &EditorStyle) -> HighlightedChunk<'a> {
+    let mut highlight_style = chunk
+        .syntax_highlight_id
+        .and_then(|id| id.style(&editor_style.syntax));
+
+    if let Some(chunk_highlight) = chunk.highlight_style {
+        if let Some(highlight_style) = highlight_style.as_mut() {
+            highlight_style.highlight(chunk_highlight);
+        } else {
+            highlight_style = Some(chunk_highlight);
+        }
+    }
+
+    // Diagnostics are underlined in a separate pass when painting the editor.
+    let mut diagnostic_highlight = HighlightStyle::default();
+
+    if chunk.is_unnecessary {
+        diagnostic_highlight.fade_out = Some(UNNECESSARY_CODE_FADE);
+    }
+
+    if let Some(highlight_style) = highlight_style.as_mut() {
+        highlight_style.highlight(diagnostic_highlight);
+    } else {
+        highlight_style = Some(diagnostic_highlight);
+    }
+
+    HighlightedChunk {
+        chunk: chunk.text,
+        style: highlight_style,
+        is_tab: chunk.is_tab,
+        is_suggestion: chunk.is_suggestion,
+    }
+}
+
 #[derive(Copy, Clone, Default, Eq, Ord, PartialOrd, PartialEq)]
 pub struct DisplayPoint(BlockPoint);
 
@@ -1399,7 +1564,24 @@ pub mod tests {
             assert_eq!(
                 snapshot.text_chunks(1).collect::<String>(),
                 "three \nfour five\nsix and \nseven \neight"
-            )
+            );
+
+            // Re-wrap on wrap indent changes
+            map.update(cx, |map, cx| {
+                map.set_wrap_indent(
+                    WrapIndent {
+                        match_line: true,
+                        extra: 2,
+                    },
+                    cx,
+                )
+            });
+
+            let snapshot = map.update(cx, |map, cx| map.snapshot(cx));
+            let text = snapshot.text_chunks(0).collect::<String>();
+            for (line, buffer_row) in text.lines().zip(snapshot.buffer_rows(0)) {
+                assert_eq!(line.starts_with("  "), buffer_row.is_none(), "{text:?}");
+            }
         });
     }
 
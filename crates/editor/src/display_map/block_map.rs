@@ -29,7 +29,12 @@ pub struct BlockMap {
     next_block_id: AtomicUsize,
     wrap_snapshot: RefCell<WrapSnapshot>,
     blocks: Vec<Arc<Block>>,
+    /// The blocks that aren't shown until they're shown again, by their ids.
+    hidden_blocks: HashMap<BlockId, Arc<Block>>,
     transforms: RefCell<SumTree<Transform>>,
+    /// The display rows whose contents changed since they were last taken, extending to the end of
+    /// the display map if rows were inserted or removed.
+    dirty_rows: RefCell<Option<Range<u32>>>,
     buffer_header_height: u8,
     excerpt_header_height: u8,
 }
@@ -181,8 +186,10 @@ impl BlockMap {
         let map = Self {
             next_block_id: AtomicUsize::new(0),
             blocks: Vec::new(),
+            hidden_blocks: HashMap::default(),
             transforms: RefCell::new(SumTree::from_item(Transform::isomorphic(row_count), &())),
             wrap_snapshot: RefCell::new(wrap_snapshot.clone()),
+            dirty_rows: RefCell::new(None),
             buffer_header_height,
             excerpt_header_height,
         };
@@ -211,6 +218,11 @@ impl BlockMap {
         BlockMapWriter(self)
     }
 
+    /// Returns the display rows that changed since the last call, if any.
+    pub fn take_dirty_rows(&self) -> Option<Range<u32>> {
+        self.dirty_rows.borrow_mut().take()
+    }
+
     fn sync(&self, wrap_snapshot: &WrapSnapshot, mut edits: Patch<u32>) {
         let buffer = wrap_snapshot.buffer_snapshot();
 
@@ -238,6 +250,8 @@ impl BlockMap {
 
         let mut transforms = self.transforms.borrow_mut();
         let mut new_transforms = SumTree::new();
+        let mut dirty_rows: Option<Range<u32>> = None;
+        let mut rows_shifted = false;
         let old_row_count = transforms.summary().input_rows;
         let new_row_count = wrap_snapshot.max_point().row() + 1;
         let mut cursor = transforms.cursor::<WrapRow>();
@@ -272,6 +286,7 @@ impl BlockMap {
             // Preserve any portion of an old transform that precedes this edit.
             let extent_before_edit = old_start.0 - cursor.start().0;
             push_isomorphic(&mut new_transforms, extent_before_edit);
+            let dirty_start = new_transforms.summary().output_rows;
 
             // Skip over any old transforms that intersect this edit.
             let mut old_end = WrapRow(edit.old.end);
@@ -368,14 +383,22 @@ impl BlockMap {
                         (position.row(), TransformBlock::Custom(block.clone()))
                     }),
             );
+            let mut prev_header_row = None;
             blocks_in_edit.extend(
                 buffer
                     .excerpt_boundaries_in_range((start_bound, end_bound))
-                    .map(|excerpt_boundary| {
-                        (
-                            wrap_snapshot
-                                .make_wrap_point(Point::new(excerpt_boundary.row, 0), Bias::Left)
-                                .row(),
+                    .filter_map(|excerpt_boundary| {
+                        let row = wrap_snapshot
+                            .make_wrap_point(Point::new(excerpt_boundary.row, 0), Bias::Left)
+                            .row();
+                        // When a fold spans consecutive excerpts from the same buffer, such as
+                        // when collapsing a file group, only the file's header is kept.
+                        if !excerpt_boundary.starts_new_buffer && prev_header_row == Some(row) {
+                            return None;
+                        }
+                        prev_header_row = Some(row);
+                        Some((
+                            row,
                             TransformBlock::ExcerptHeader {
                                 id: excerpt_boundary.id,
                                 buffer: excerpt_boundary.buffer,
@@ -387,7 +410,7 @@ impl BlockMap {
                                 },
                                 starts_new_buffer: excerpt_boundary.starts_new_buffer,
                             },
-                        )
+                        ))
                     }),
             );
 
@@ -426,6 +449,15 @@ impl BlockMap {
             let extent_after_last_block = new_end.0 - new_transforms.summary().input_rows;
             push_isomorphic(&mut new_transforms, extent_after_last_block);
 
+            // Rows after an edit that changes the number of rows, or that touches blocks, may
+            // have moved, so they are all considered dirty.
+            let dirty_end = new_transforms.summary().output_rows;
+            rows_shifted |= old_end.0 - old_start.0 != new_end.0 - new_start.0
+                || dirty_end - dirty_start != new_end.0 - new_start.0;
+            dirty_rows = Some(dirty_rows.map_or(dirty_start..dirty_end, |rows| {
+                rows.start.min(dirty_start)..rows.end.max(dirty_end)
+            }));
+
             // Preserve any portion of the old transform after this edit.
             let extent_after_edit = cursor.start().0 - old_end.0;
             push_isomorphic(&mut new_transforms, extent_after_edit);
@@ -438,7 +470,20 @@ impl BlockMap {
         );
 
         drop(cursor);
+        if new_transforms.summary().output_rows != transforms.summary().output_rows {
+            rows_shifted = true;
+        }
         *transforms = new_transforms;
+
+        if let Some(mut rows) = dirty_rows {
+            if rows_shifted {
+                rows.end = u32::MAX;
+            }
+            let mut dirty_rows = self.dirty_rows.borrow_mut();
+            *dirty_rows = Some(dirty_rows.as_ref().map_or(rows.clone(), |dirty_rows| {
+                dirty_rows.start.min(rows.start)..dirty_rows.end.max(rows.end)
+            }));
+        }
     }
 
     pub fn replace(&mut self, mut renderers: HashMap<BlockId, RenderBlock>) {
@@ -571,6 +616,102 @@ impl<'a> BlockMapWriter<'a> {
                 true
             }
         });
+        self.0
+            .hidden_blocks
+            .retain(|block_id, _| !block_ids.contains(block_id));
+        self.0.sync(wrap_snapshot, edits);
+    }
+
+    /// Stops showing the given blocks, keeping them so that they can be shown again with the
+    /// same ids.
+    pub fn hide(&mut self, block_ids: HashSet<BlockId>) {
+        let hidden = self
+            .0
+            .blocks
+            .iter()
+            .filter(|block| block_ids.contains(&block.id))
+            .cloned()
+            .collect::<Vec<_>>();
+        self.remove(block_ids);
+        self.0
+            .hidden_blocks
+            .extend(hidden.into_iter().map(|block| (block.id, block)));
+    }
+
+    /// Shows the given blocks again after they were hidden.
+    pub fn show(&mut self, block_ids: HashSet<BlockId>) {
+        let wrap_snapshot = &*self.0.wrap_snapshot.borrow();
+        let buffer = wrap_snapshot.buffer_snapshot();
+        let mut edits = Patch::default();
+        for block_id in block_ids {
+            let Some(block) = self.0.hidden_blocks.remove(&block_id) else {
+                continue;
+            };
+
+            let point = block.position.to_point(buffer);
+            let wrap_row = wrap_snapshot
+                .make_wrap_point(Point::new(point.row, 0), Bias::Left)
+                .row();
+            let start_row = wrap_snapshot.prev_row_boundary(WrapPoint::new(wrap_row, 0));
+            let end_row = wrap_snapshot
+                .next_row_boundary(WrapPoint::new(wrap_row, 0))
+                .unwrap_or(wrap_snapshot.max_point().row() + 1);
+
+            let block_ix = match self
+                .0
+                .blocks
+                .binary_search_by(|probe| probe.position.cmp(&block.position, buffer))
+            {
+                Ok(ix) | Err(ix) => ix,
+            };
+            self.0.blocks.insert(block_ix, block);
+
+            edits = edits.compose([Edit {
+                old: start_row..end_row,
+                new: start_row..end_row,
+            }]);
+        }
+        self.0.sync(wrap_snapshot, edits);
+    }
+
+    pub fn resize(&mut self, mut heights: HashMap<BlockId, u8>) {
+        let wrap_snapshot = &*self.0.wrap_snapshot.borrow();
+        let buffer = wrap_snapshot.buffer_snapshot();
+        let mut edits = Patch::default();
+        let mut last_block_buffer_row = None;
+        for block in &mut self.0.blocks {
+            let Some(height) = heights.remove(&block.id) else {
+                continue;
+            };
+            if block.height == height {
+                continue;
+            }
+
+            *block = Arc::new(Block {
+                id: block.id,
+                position: block.position,
+                height,
+                style: block.style,
+                render: Mutex::new(block.render.lock().clone()),
+                disposition: block.disposition,
+            });
+
+            let buffer_row = block.position.to_point(buffer).row;
+            if last_block_buffer_row != Some(buffer_row) {
+                last_block_buffer_row = Some(buffer_row);
+                let wrap_row = wrap_snapshot
+                    .make_wrap_point(Point::new(buffer_row, 0), Bias::Left)
+                    .row();
+                let start_row = wrap_snapshot.prev_row_boundary(WrapPoint::new(wrap_row, 0));
+                let end_row = wrap_snapshot
+                    .next_row_boundary(WrapPoint::new(wrap_row, 0))
+                    .unwrap_or(wrap_snapshot.max_point().row() + 1);
+                edits.push(Edit {
+                    old: start_row..end_row,
+                    new: start_row..end_row,
+                })
+            }
+        }
         self.0.sync(wrap_snapshot, edits);
     }
 }
@@ -952,6 +1093,14 @@ impl DerefMut for BlockContext<'_, '_> {
 }
 
 impl Block {
+    pub fn id(&self) -> BlockId {
+        self.id
+    }
+
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
     pub fn render(&self, cx: &mut BlockContext) -> AnyElement {
         self.render.lock()(cx)
     }
@@ -1231,6 +1380,155 @@ mod tests {
         );
     }
 
+    #[gpui::test]
+    fn test_dirty_rows(cx: &mut gpui::TestAppContext) {
+        cx.update(|cx| init_test(cx));
+
+        let buffer = cx.update(|cx| MultiBuffer::build_simple("aaa\nbbb\nccc\nddd", cx));
+        let buffer_snapshot = cx.update(|cx| buffer.read(cx).snapshot(cx));
+        let subscription = buffer.update(cx, |buffer, _| buffer.subscribe());
+        let (mut inlay_map, inlay_snapshot) = InlayMap::new(buffer_snapshot.clone());
+        let (mut fold_map, fold_snapshot) = FoldMap::new(inlay_snapshot);
+        let (mut tab_map, tab_snapshot) = TabMap::new(fold_snapshot, 1.try_into().unwrap());
+        let (wrap_map, wraps_snapshot) =
+            cx.update(|cx| WrapMap::new(tab_snapshot, font("Helvetica"), px(14.0), None, cx));
+        let mut block_map = BlockMap::new(wraps_snapshot.clone(), 1, 1);
+
+        let mut writer = block_map.write(wraps_snapshot.clone(), Default::default());
+        writer.insert(vec![BlockProperties {
+            style: BlockStyle::Fixed,
+            position: buffer_snapshot.anchor_after(Point::new(2, 0)),
+            height: 1,
+            disposition: BlockDisposition::Above,
+            render: Arc::new(|_| div().into_any()),
+        }]);
+        assert!(block_map.take_dirty_rows().is_some());
+        assert_eq!(block_map.take_dirty_rows(), None);
+
+        let mut edit = |range: Range<Point>, text: &str| {
+            let buffer_snapshot = buffer.update(cx, |buffer, cx| {
+                buffer.edit([(range, text)], None, cx);
+                buffer.snapshot(cx)
+            });
+            let (inlay_snapshot, inlay_edits) =
+                inlay_map.sync(buffer_snapshot, subscription.consume().into_inner());
+            let (fold_snapshot, fold_edits) = fold_map.read(inlay_snapshot, inlay_edits);
+            let (tab_snapshot, tab_edits) =
+                tab_map.sync(fold_snapshot, fold_edits, 1.try_into().unwrap());
+            let (wraps_snapshot, wrap_edits) = wrap_map.update(cx, |wrap_map, cx| {
+                wrap_map.sync(tab_snapshot, tab_edits, cx)
+            });
+            block_map.read(wraps_snapshot, wrap_edits);
+            block_map.take_dirty_rows()
+        };
+
+        // Editing within a line only dirties its row.
+        assert_eq!(edit(Point::new(0, 1)..Point::new(0, 1), "!"), Some(0..1));
+
+        // Inserting a line dirties every row after it, including the rows below the block.
+        assert_eq!(
+            edit(Point::new(1, 1)..Point::new(1, 1), "\n"),
+            Some(1..u32::MAX)
+        );
+    }
+
+    #[gpui::test]
+    fn test_resizing_blocks(cx: &mut gpui::TestAppContext) {
+        cx.update(|cx| init_test(cx));
+
+        let buffer = cx.update(|cx| MultiBuffer::build_simple("aaa\nbbb\nccc", cx));
+        let buffer_snapshot = cx.update(|cx| buffer.read(cx).snapshot(cx));
+        let (_, inlay_snapshot) = InlayMap::new(buffer_snapshot.clone());
+        let (_, fold_snapshot) = FoldMap::new(inlay_snapshot);
+        let (_, tab_snapshot) = TabMap::new(fold_snapshot, 1.try_into().unwrap());
+        let (_, wraps_snapshot) =
+            cx.update(|cx| WrapMap::new(tab_snapshot, font("Helvetica"), px(14.0), None, cx));
+        let mut block_map = BlockMap::new(wraps_snapshot.clone(), 1, 1);
+
+        let mut writer = block_map.write(wraps_snapshot.clone(), Default::default());
+        let block_ids = writer.insert(vec![BlockProperties {
+            style: BlockStyle::Fixed,
+            position: buffer_snapshot.anchor_after(Point::new(1, 0)),
+            height: 1,
+            disposition: BlockDisposition::Below,
+            render: Arc::new(|_| div().into_any()),
+        }]);
+        let snapshot = block_map.read(wraps_snapshot.clone(), Default::default());
+        assert_eq!(snapshot.text(), "aaa\nbbb\n\nccc");
+
+        let mut writer = block_map.write(wraps_snapshot.clone(), Default::default());
+        writer.resize(HashMap::from_iter([(block_ids[0], 3)]));
+        let snapshot = block_map.read(wraps_snapshot.clone(), Default::default());
+        assert_eq!(snapshot.text(), "aaa\nbbb\n\n\n\nccc");
+        assert_eq!(
+            snapshot
+                .blocks_in_range(0..6)
+                .map(|(row, block)| (row, block.height()))
+                .collect::<Vec<_>>(),
+            [(2, 3)]
+        );
+
+        let mut writer = block_map.write(wraps_snapshot.clone(), Default::default());
+        writer.resize(HashMap::from_iter([(block_ids[0], 1)]));
+        let snapshot = block_map.read(wraps_snapshot, Default::default());
+        assert_eq!(snapshot.text(), "aaa\nbbb\n\nccc");
+    }
+
+    #[gpui::test]
+    fn test_hiding_blocks(cx: &mut gpui::TestAppContext) {
+        cx.update(|cx| init_test(cx));
+
+        let buffer = cx.update(|cx| MultiBuffer::build_simple("aaa\nbbb\nccc", cx));
+        let buffer_snapshot = cx.update(|cx| buffer.read(cx).snapshot(cx));
+        let (_, inlay_snapshot) = InlayMap::new(buffer_snapshot.clone());
+        let (_, fold_snapshot) = FoldMap::new(inlay_snapshot);
+        let (_, tab_snapshot) = TabMap::new(fold_snapshot, 1.try_into().unwrap());
+        let (_, wraps_snapshot) =
+            cx.update(|cx| WrapMap::new(tab_snapshot, font("Helvetica"), px(14.0), None, cx));
+        let mut block_map = BlockMap::new(wraps_snapshot.clone(), 1, 1);
+
+        let mut writer = block_map.write(wraps_snapshot.clone(), Default::default());
+        let block_ids = writer.insert([0, 2].map(|row| BlockProperties {
+            style: BlockStyle::Fixed,
+            position: buffer_snapshot.anchor_after(Point::new(row, 0)),
+            height: 1,
+            disposition: BlockDisposition::Below,
+            render: Arc::new(|_| div().into_any()),
+        }));
+        writer.resize(HashMap::from_iter([(block_ids[0], 2)]));
+        let snapshot = block_map.read(wraps_snapshot.clone(), Default::default());
+        assert_eq!(snapshot.text(), "aaa\n\n\nbbb\nccc\n");
+
+        let mut writer = block_map.write(wraps_snapshot.clone(), Default::default());
+        writer.hide(HashSet::from_iter([block_ids[0]]));
+        let snapshot = block_map.read(wraps_snapshot.clone(), Default::default());
+        assert_eq!(snapshot.text(), "aaa\nbbb\nccc\n");
+
+        // Hidden blocks are shown again with their ids and heights.
+        let mut writer = block_map.write(wraps_snapshot.clone(), Default::default());
+        writer.show(HashSet::from_iter([block_ids[0]]));
+        let snapshot = block_map.read(wraps_snapshot.clone(), Default::default());
+        assert_eq!(snapshot.text(), "aaa\n\n\nbbb\nccc\n");
+        assert_eq!(
+            snapshot
+                .blocks_in_range(0..6)
+                .map(|(row, block)| match block {
+                    TransformBlock::Custom(block) => (row, block.id()),
+                    TransformBlock::ExcerptHeader { .. } => unreachable!(),
+                })
+                .collect::<Vec<_>>(),
+            [(1, block_ids[0]), (5, block_ids[1])]
+        );
+
+        // Removing a hidden block forgets it.
+        let mut writer = block_map.write(wraps_snapshot.clone(), Default::default());
+        writer.hide(HashSet::from_iter([block_ids[0]]));
+        writer.remove(HashSet::from_iter([block_ids[0]]));
+        writer.show(HashSet::from_iter([block_ids[0]]));
+        let snapshot = block_map.read(wraps_snapshot, Default::default());
+        assert_eq!(snapshot.text(), "aaa\nbbb\nccc\n");
+    }
+
     #[gpui::test(iterations = 100)]
     fn test_random_blocks(cx: &mut gpui::TestAppContext, mut rng: StdRng) {
         cx.update(|cx| init_test(cx));
@@ -1399,12 +1697,18 @@ mod tests {
                     },
                 )
             }));
-            expected_blocks.extend(buffer_snapshot.excerpt_boundaries_in_range(0..).map(
+            let mut prev_header_row = None;
+            expected_blocks.extend(buffer_snapshot.excerpt_boundaries_in_range(0..).filter_map(
                 |boundary| {
-                    let position =
-                        wraps_snapshot.make_wrap_point(Point::new(boundary.row, 0), Bias::Left);
-                    (
-                        position.row(),
+                    let row = wraps_snapshot
+                        .make_wrap_point(Point::new(boundary.row, 0), Bias::Left)
+                        .row();
+                    if !boundary.starts_new_buffer && prev_header_row == Some(row) {
+                        return None;
+                    }
+                    prev_header_row = Some(row);
+                    Some((
+                        row,
                         ExpectedBlock::ExcerptHeader {
                             height: if boundary.starts_new_buffer {
                                 buffer_start_header_height
@@ -1413,7 +1717,7 @@ mod tests {
                             },
                             starts_new_buffer: boundary.starts_new_buffer,
                         },
-                    )
+                    ))
                 },
             ));
             expected_blocks.sort_unstable();
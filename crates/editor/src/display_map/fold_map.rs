@@ -213,6 +213,14 @@ impl FoldMap {
         (this, snapshot)
     }
 
+    pub fn fold_ranges(&self) -> Vec<Range<Anchor>> {
+        self.snapshot
+            .folds
+            .iter()
+            .map(|fold| fold.range.0.clone())
+            .collect()
+    }
+
     pub fn read(
         &mut self,
         inlay_snapshot: InlaySnapshot,
@@ -356,6 +356,7 @@ impl<'a> Iterator for InlayChunks<'a> {
                 Chunk {
                     text: chunk,
                     highlight_style,
+                    is_suggestion: matches!(inlay.id, InlayId::Suggestion(_)),
                     ..Default::default()
                 }
             }
@@ -4,7 +4,7 @@ use super::{
     Highlights,
 };
 use crate::MultiBufferSnapshot;
-use gpui::{AppContext, Context, Font, LineWrapper, Model, ModelContext, Pixels, Task};
+use gpui::{AppContext, Context, Font, LineWrapper, Model, ModelContext, Pixels, Task, WrapIndent};
 use language::{Chunk, Point};
 use lazy_static::lazy_static;
 use smol::future::yield_now;
@@ -24,6 +24,7 @@ pub struct WrapMap {
     interpolated_edits: Patch<u32>,
     edits_since_sync: Patch<u32>,
     wrap_width: Option<Pixels>,
+    wrap_indent: WrapIndent,
     background_task: Option<Task<()>>,
     font_with_size: (Font, Pixels),
 }
@@ -80,6 +81,7 @@ impl WrapMap {
             let mut this = Self {
                 font_with_size: (font, font_size),
                 wrap_width: None,
+                wrap_indent: WrapIndent::default(),
                 pending_edits: Default::default(),
                 interpolated_edits: Default::default(),
                 edits_since_sync: Default::default(),
@@ -149,6 +151,20 @@ impl WrapMap {
         true
     }
 
+    pub fn set_wrap_indent(
+        &mut self,
+        wrap_indent: WrapIndent,
+        cx: &mut ModelContext<Self>,
+    ) -> bool {
+        if wrap_indent == self.wrap_indent {
+            return false;
+        }
+
+        self.wrap_indent = wrap_indent;
+        self.rewrap(cx);
+        true
+    }
+
     fn rewrap(&mut self, cx: &mut ModelContext<Self>) {
         self.background_task.take();
         self.interpolated_edits.clear();
@@ -159,6 +175,7 @@ impl WrapMap {
 
             let text_system = cx.text_system().clone();
             let (font, font_size) = self.font_with_size.clone();
+            let wrap_indent = self.wrap_indent;
             let task = cx.background_executor().spawn(async move {
                 let mut line_wrapper = text_system.line_wrapper(font, font_size);
                 let tab_snapshot = new_snapshot.tab_snapshot.clone();
@@ -171,6 +188,7 @@ impl WrapMap {
                             new: range.clone(),
                         }],
                         wrap_width,
+                        wrap_indent,
                         &mut line_wrapper,
                     )
                     .await;
@@ -243,12 +261,19 @@ impl WrapMap {
                 let mut snapshot = self.snapshot.clone();
                 let text_system = cx.text_system().clone();
                 let (font, font_size) = self.font_with_size.clone();
+                let wrap_indent = self.wrap_indent;
                 let update_task = cx.background_executor().spawn(async move {
                     let mut edits = Patch::default();
                     let mut line_wrapper = text_system.line_wrapper(font, font_size);
                     for (tab_snapshot, tab_edits) in pending_edits {
                         let wrap_edits = snapshot
-                            .update(tab_snapshot, &tab_edits, wrap_width, &mut line_wrapper)
+                            .update(
+                                tab_snapshot,
+                                &tab_edits,
+                                wrap_width,
+                                wrap_indent,
+                                &mut line_wrapper,
+                            )
                             .await;
                         edits = edits.compose(&wrap_edits);
                     }
@@ -390,6 +415,7 @@ impl WrapSnapshot {
         new_tab_snapshot: TabSnapshot,
         tab_edits: &[TabEdit],
         wrap_width: Pixels,
+        wrap_indent: WrapIndent,
         line_wrapper: &mut LineWrapper,
     ) -> Patch<u32> {
         #[derive(Debug)]
@@ -467,7 +493,9 @@ impl WrapSnapshot {
                     }
 
                     let mut prev_boundary_ix = 0;
-                    for boundary in line_wrapper.wrap_line(&line, wrap_width) {
+                    for boundary in
+                        line_wrapper.wrap_line_with_indent(&line, wrap_width, wrap_indent)
+                    {
                         let wrapped = &line[prev_boundary_ix..boundary.ix];
                         push_isomorphic(&mut edit_transforms, TextSummary::from(wrapped));
                         edit_transforms.push(Transform::wrap(boundary.next_indent));
@@ -0,0 +1,173 @@
+use crate::{
+    scroll::Autoscroll, CycleEditLocations, Editor, GoToLastEdit, MIN_NAVIGATION_HISTORY_ROW_DELTA,
+};
+use gpui::{Global, Model, ViewContext, WeakModel};
+use language::{Buffer, ToPoint};
+use std::collections::VecDeque;
+
+const MAX_EDIT_LOCATIONS: usize = 100;
+
+/// The positions of the most recent edits made in any editor, ordered from the oldest edit to the
+/// newest one.
+#[derive(Default)]
+struct EditLocations {
+    locations: VecDeque<EditLocation>,
+    /// How many edits before the most recent one the location jumped to last is, while cycling
+    /// through the locations.
+    cycle_ix: Option<usize>,
+}
+
+impl Global for EditLocations {}
+
+struct EditLocation {
+    buffer: WeakModel<Buffer>,
+    anchor: language::Anchor,
+}
+
+impl Editor {
+    /// Moves the cursor to where the most recent edit was made, in whichever buffer it was made.
+    pub fn go_to_last_edit(&mut self, _: &GoToLastEdit, cx: &mut ViewContext<Self>) {
+        self.go_to_edit_location(0, cx);
+    }
+
+    /// Moves the cursor to where the edit before the one jumped to last was made, starting over
+    /// from the most recent edit after the oldest one.
+    pub fn cycle_edit_locations(&mut self, _: &CycleEditLocations, cx: &mut ViewContext<Self>) {
+        let ix = cx
+            .try_global::<EditLocations>()
+            .and_then(|locations| locations.cycle_ix)
+            .map_or(0, |ix| ix + 1);
+        self.go_to_edit_location(ix, cx);
+    }
+
+    /// Records the position of the newest cursor after an edit made in the editor. An edit close
+    /// to the last one replaces it, so that typing doesn't fill the list.
+    pub(crate) fn record_edit_location(&mut self, cx: &mut ViewContext<Self>) {
+        let head = self.selections.newest_anchor().head();
+        let Some((buffer, anchor)) = self.buffer.read(cx).text_anchor_for_position(head, cx) else {
+            return;
+        };
+        let snapshot = buffer.read(cx).snapshot();
+        let row = anchor.to_point(&snapshot).row as i64;
+
+        let locations = cx.default_global::<EditLocations>();
+        locations.cycle_ix = None;
+        if let Some(last) = locations.locations.back() {
+            if last.buffer.entity_id() == buffer.entity_id()
+                && (last.anchor.to_point(&snapshot).row as i64 - row).abs()
+                    < MIN_NAVIGATION_HISTORY_ROW_DELTA
+            {
+                locations.locations.pop_back();
+            }
+        }
+        if locations.locations.len() >= MAX_EDIT_LOCATIONS {
+            locations.locations.pop_front();
+        }
+        locations.locations.push_back(EditLocation {
+            buffer: buffer.downgrade(),
+            anchor,
+        });
+    }
+
+    /// Jumps to the location of the edit made `ix` edits before the most recent one, wrapping
+    /// around after the oldest edit.
+    fn go_to_edit_location(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        let locations = cx.default_global::<EditLocations>();
+        locations
+            .locations
+            .retain(|location| location.buffer.upgrade().is_some());
+        if locations.locations.is_empty() {
+            return;
+        }
+        let ix = ix % locations.locations.len();
+        locations.cycle_ix = Some(ix);
+        let location = &locations.locations[locations.locations.len() - 1 - ix];
+        let anchor = location.anchor;
+        if let Some(buffer) = location.buffer.upgrade() {
+            self.jump_to_edit_location(buffer, anchor, cx);
+        }
+    }
+
+    /// Selects the given position if the editor shows it, and opens its buffer otherwise.
+    fn jump_to_edit_location(
+        &mut self,
+        buffer: Model<Buffer>,
+        anchor: language::Anchor,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let snapshot = buffer.read(cx).snapshot();
+        let excerpt_id = self
+            .buffer
+            .read(cx)
+            .excerpts_for_buffer(&buffer, cx)
+            .into_iter()
+            .find(|(_, range)| {
+                range.context.start.cmp(&anchor, &snapshot).is_le()
+                    && range.context.end.cmp(&anchor, &snapshot).is_ge()
+            })
+            .map(|(excerpt_id, _)| excerpt_id);
+        if let Some(excerpt_id) = excerpt_id {
+            let anchor = self
+                .buffer
+                .read(cx)
+                .snapshot(cx)
+                .anchor_in_excerpt(excerpt_id, anchor);
+            self.change_selections(Some(Autoscroll::center()), cx, |s| {
+                s.select_anchor_ranges([anchor..anchor])
+            });
+            return;
+        }
+
+        let Some(workspace) = self.workspace() else {
+            return;
+        };
+        let point = anchor.to_point(&snapshot);
+        // We defer the pane interaction because we ourselves are a workspace item
+        // and activating a new item causes the pane to call a method on us reentrantly,
+        // which panics if we're on the stack.
+        cx.window_context().defer(move |cx| {
+            workspace.update(cx, |workspace, cx| {
+                let editor = workspace.open_project_item::<Self>(buffer, cx);
+                editor.update(cx, |editor, cx| {
+                    editor.change_selections(Some(Autoscroll::center()), cx, |s| {
+                        s.select_ranges([point..point])
+                    });
+                });
+            })
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+
+    #[gpui::test]
+    async fn test_edit_locations(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorTestContext::new(cx).await;
+        let text = "line\n".repeat(40);
+        cx.set_state(&format!("ˇ{text}"));
+        cx.simulate_input("a");
+        cx.simulate_input("b");
+        cx.update_editor(|editor, cx| {
+            editor.change_selections(None, cx, |s| s.select_ranges([100..100]))
+        });
+        cx.simulate_input("c");
+        cx.update_editor(|editor, cx| {
+            editor.change_selections(None, cx, |s| s.select_ranges([200..200]))
+        });
+
+        let (before, after) = text.split_at(98);
+        cx.dispatch_action(GoToLastEdit);
+        cx.assert_editor_state(&format!("ab{before}cˇ{after}"));
+
+        // Edits close to each other are recorded once.
+        cx.dispatch_action(CycleEditLocations);
+        cx.assert_editor_state(&format!("abˇ{before}c{after}"));
+        cx.dispatch_action(CycleEditLocations);
+        cx.assert_editor_state(&format!("ab{before}cˇ{after}"));
+    }
+}
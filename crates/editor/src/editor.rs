@@ -14,23 +14,63 @@
 //! If you're looking to improve Vim mode, you should check out Vim crate that wraps Editor and overrides it's behaviour.
 pub mod actions;
 mod blink_manager;
+mod block_decorations;
+mod bookmarks;
+mod bracket_colorization;
+mod breadcrumb_bar;
+mod breakpoints;
+mod clipboard_history;
+mod code_cells;
+mod code_lens;
+mod conflict_banner;
+mod cursor_animation;
+mod cursor_name_tags;
+mod cursor_position_overlay;
+pub mod diff_view;
 pub mod display_map;
+mod edit_locations;
 mod editor_settings;
 mod element;
 mod inlay_hint_cache;
+mod large_file;
+mod large_paste;
+mod line_layout_cache;
+mod macros;
+mod marks;
+mod merged_quads;
 
 mod debounced_delay;
 mod git;
+mod gutter_data;
 mod highlight_matching_bracket;
 mod hover_links;
 mod hover_popover;
+mod image_preview;
+mod indentation;
 pub mod items;
+mod middle_click;
 mod mouse_context_menu;
 pub mod movement;
 mod persistence;
+mod preserve_case;
+mod print;
+mod prose;
+mod remote_cursors;
+mod replace_preview;
+mod restore_points;
+mod rich_text_copy;
+mod row_layout_cache;
+mod runnables;
 mod rust_analyzer_ext;
+mod screenshot;
 pub mod scroll;
+mod scrollbar_markers;
+mod selection_highlights;
 mod selections_collection;
+mod sticky_header;
+mod surround;
+mod text_objects;
+mod todo_highlights;
 
 #[cfg(test)]
 mod editor_tests;
@@ -41,57 +81,93 @@ pub(crate) use actions::*;
 use aho_corasick::AhoCorasick;
 use anyhow::{anyhow, Context as _, Result};
 use blink_manager::BlinkManager;
+use block_decorations::BlockDecorations;
+pub use block_decorations::{BlockDecoration, BlockDecorationCallback, BlockDecorationEvent};
+use breadcrumb_bar::BreadcrumbOutline;
 use client::{Collaborator, ParticipantIndex};
+use clipboard_history::ClipboardHistoryMenu;
 use clock::ReplicaId;
+use code_cells::CodeCells;
 use collections::{BTreeMap, Bound, HashMap, HashSet, VecDeque};
 use convert_case::{Case, Casing};
 use copilot::Copilot;
+use cursor_animation::CursorAnimation;
 use debounced_delay::DebouncedDelay;
 pub use display_map::DisplayPoint;
 use display_map::*;
 pub use editor_settings::EditorSettings;
+use editor_settings::{FoldIndicators, InlineBlame, WrappedLineIndent};
 use element::LineWithInvisibles;
 pub use element::{Cursor, EditorElement, HighlightedRange, HighlightedRangeLine};
 use futures::FutureExt;
 use fuzzy::{StringMatch, StringMatchCandidate};
-use git::diff_hunk_to_display;
+use git::{
+    blame::GitBlame, diff_hunk_to_display, inline_diff::InlineDiff, review_mode::ReviewMode,
+};
 use gpui::{
     div, impl_actions, point, prelude::*, px, relative, rems, size, uniform_list, Action,
     AnyElement, AppContext, AsyncWindowContext, BackgroundExecutor, Bounds, ClipboardItem, Context,
-    DispatchPhase, ElementId, EventEmitter, FocusHandle, FocusableView, FontId, FontStyle,
-    FontWeight, HighlightStyle, Hsla, InteractiveText, KeyContext, Model, MouseButton,
-    ParentElement, Pixels, Render, SharedString, Styled, StyledText, Subscription, Task, TextStyle,
-    UnderlineStyle, UniformListScrollHandle, View, ViewContext, ViewInputHandler, VisualContext,
-    WeakView, WhiteSpace, WindowContext,
+    DispatchPhase, ElementId, EventEmitter, FocusHandle, FocusableView, FontFeatures, FontId,
+    FontStyle, FontWeight, HighlightStyle, Hsla, InteractiveText, KeyContext, Model, MouseButton,
+    ParentElement, Pixels, PrintDocument, Render, SharedString, Styled, StyledText, Subscription,
+    Task, TextStyle, UnderlineStyle, UniformListScrollHandle, View, ViewContext, ViewInputHandler,
+    VisualContext, WeakView, WhiteSpace, WindowContext, WrapIndent,
 };
+use gutter_data::GutterData;
+pub use gutter_data::{GutterDataProvider, GutterMark};
 use highlight_matching_bracket::refresh_matching_bracket_highlights;
 use hover_popover::{hide_hover, HoverState};
+use image_preview::ImagePreviewBlock;
 use inlay_hint_cache::{InlayHintCache, InlaySplice, InvalidationStrategy};
 pub use items::MAX_TAB_TITLE_LEN;
 use itertools::Itertools;
 use language::{char_kind, CharKind};
 use language::{
-    language_settings::{self, all_language_settings, InlayHintSettings},
+    language_settings::{
+        self, all_language_settings, InlayHintSettings, LanguageSettings, ProseSettings, WrapGuide,
+    },
     markdown, point_from_lsp, AutoindentMode, BracketPair, Buffer, Capability, CodeAction,
     CodeLabel, Completion, CursorShape, Diagnostic, Documentation, IndentKind, IndentSize,
-    Language, OffsetRangeExt, Point, Selection, SelectionGoal, TransactionId,
+    Language, LineEnding, OffsetRangeExt, Point, Selection, SelectionGoal, TransactionId,
 };
+use large_paste::{LargePaste, LARGE_PASTE_THRESHOLD};
+use line_layout_cache::LineLayoutCache;
+pub use line_layout_cache::LineLayoutStats;
+use macros::MacroStep;
+use middle_click::MiddleClickAutoscroll;
+use surround::SurroundPrompt;
 
 use hover_links::{HoverLink, HoveredLinkState, InlayHighlight};
 use lsp::{DiagnosticSeverity, LanguageServerId};
+use marks::Mark;
 use mouse_context_menu::MouseContextMenu;
 use movement::TextLayoutDetails;
 use multi_buffer::ToOffsetUtf16;
 pub use multi_buffer::{
-    Anchor, AnchorRangeExt, ExcerptId, ExcerptRange, MultiBuffer, MultiBufferSnapshot, ToOffset,
-    ToPoint,
+    Anchor, AnchorRangeExt, ExcerptId, ExcerptRange, ExpandExcerptDirection, MultiBuffer,
+    MultiBufferSnapshot, ToOffset, ToPoint,
 };
 use ordered_float::OrderedFloat;
 use parking_lot::{Mutex, RwLock};
-use project::{FormatTrigger, Location, Project, ProjectPath, ProjectTransaction};
+use persistence::DB;
+use project::{
+    search::MatchCase, FormatTrigger, Location, Project, ProjectPath, ProjectTransaction,
+};
+use prose::ParagraphSpacingBlocks;
 use rand::prelude::*;
+use remote_cursors::RemoteCursors;
+use replace_preview::ReplacePreviews;
+use restore_points::{EditHistory, RestorePointKind};
+use rich_text_copy::FormattedText;
+use row_layout_cache::RowLayoutCache;
 use rpc::proto::*;
-use scroll::{Autoscroll, OngoingScroll, ScrollAnchor, ScrollManager, ScrollbarAutoHide};
+use runnables::Runnables;
+use screenshot::ScreenshotStyle;
+use scroll::{
+    scroll_lock::ScrollLock, Autoscroll, OngoingScroll, ScrollAnchor, ScrollManager,
+    ScrollbarAutoHide,
+};
+use scrollbar_markers::ScrollbarMarkerCache;
 use selections_collection::{resolve_multiple, MutableSelectionsCollection, SelectionsCollection};
 use serde::{Deserialize, Serialize};
 use settings::{Settings, SettingsStore};
@@ -121,14 +197,20 @@ use ui::{
 };
 use util::{maybe, post_inc, RangeExt, ResultExt, TryFutureExt};
 use workspace::Toast;
-use workspace::{searchable::SearchEvent, ItemNavHistory, Pane, SplitDirection, ViewId, Workspace};
+use workspace::{
+    searchable::SearchEvent, ItemId, ItemNavHistory, Pane, SplitDirection, ViewId, Workspace,
+};
 
 use crate::hover_links::find_url;
 
-const CURSOR_BLINK_INTERVAL: Duration = Duration::from_millis(500);
 const MAX_LINE_LEN: usize = 1024;
+/// How many more columns than the line they continue soft-wrapped rows are indented by, when
+/// `wrapped_line_indent` is `extra`.
+const EXTRA_WRAPPED_LINE_INDENT: u32 = 4;
 const MIN_NAVIGATION_HISTORY_ROW_DELTA: i64 = 10;
 const MAX_SELECTION_HISTORY_LEN: usize = 1024;
+const DEFAULT_SCREENSHOT_PADDING: f32 = 32.;
+const MIN_FONT_SIZE: Pixels = px(6.);
 const COPILOT_DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(75);
 pub(crate) const CURSORS_VISIBLE_FOR: Duration = Duration::from_millis(2000);
 #[doc(hidden)]
@@ -143,7 +225,7 @@ pub fn render_parsed_markdown(
     parsed: &language::ParsedMarkdown,
     editor_style: &EditorStyle,
     workspace: Option<WeakView<Workspace>>,
-    cx: &mut ViewContext<Editor>,
+    cx: &mut WindowContext,
 ) -> InteractiveText {
     let code_span_background_color = cx
         .theme()
@@ -219,6 +301,7 @@ impl InlayId {
 enum DocumentHighlightRead {}
 enum DocumentHighlightWrite {}
 enum InputComposition {}
+enum SnippetPlaceholderHighlights {}
 
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub enum Direction {
@@ -240,6 +323,7 @@ pub fn init(cx: &mut AppContext) {
         |workspace: &mut Workspace, _cx: &mut ViewContext<Workspace>| {
             workspace.register_action(Editor::new_file);
             workspace.register_action(Editor::new_file_in_direction);
+            workspace.register_action(Editor::toggle_scroll_lock);
         },
     )
     .detach();
@@ -279,6 +363,12 @@ pub enum SelectPhase {
         position: DisplayPoint,
         goal_column: u32,
     },
+    /// Selects whole lines, as when clicking the gutter. When `extend` is true, the newest
+    /// selection is extended to the line instead, starting from the whole line of its tail.
+    BeginLineWise {
+        position: DisplayPoint,
+        extend: bool,
+    },
     Extend {
         position: DisplayPoint,
         click_count: usize,
@@ -371,7 +461,7 @@ pub struct Editor {
     selection_history: SelectionHistory,
     autoclose_regions: Vec<AutocloseRegion>,
     snippet_stack: InvalidationStack<SnippetState>,
-    select_larger_syntax_node_stack: Vec<Box<[Selection<usize>]>>,
+    select_larger_syntax_node_history: SyntaxNodeSelectionHistory,
     ime_transaction: Option<TransactionId>,
     active_diagnostics: Option<ActiveDiagnosticGroup>,
     soft_wrap_mode_override: Option<language_settings::SoftWrap>,
@@ -379,13 +469,60 @@ pub struct Editor {
     completion_provider: Option<Box<dyn CompletionProvider>>,
     collaboration_hub: Option<Box<dyn CollaborationHub>>,
     blink_manager: Model<BlinkManager>,
+    cursor_animation: CursorAnimation,
+    line_layout_cache: LineLayoutCache,
+    row_layout_cache: RowLayoutCache,
+    layout_caching: bool,
+    quad_merging: bool,
+    scrollbar_marker_cache: ScrollbarMarkerCache,
     show_cursor_names: bool,
     hovered_cursors: HashMap<HoveredCursor, Task<()>>,
+    remote_cursors: RemoteCursors,
     pub show_local_selections: bool,
     mode: EditorMode,
     show_breadcrumbs: bool,
+    breadcrumb_outline: BreadcrumbOutline,
     show_gutter: bool,
     show_wrap_guides: Option<bool>,
+    show_inline_diagnostics: Option<bool>,
+    show_inline_diff: Option<bool>,
+    font_size_adjustment: Pixels,
+    paragraph_spacing_blocks: ParagraphSpacingBlocks,
+    search_within_excerpt: Option<ExcerptId>,
+    case_preserving_selections: HashMap<usize, (Range<Anchor>, MatchCase)>,
+    replace_previews: ReplacePreviews,
+    conflict_banner: Option<BlockId>,
+    large_file_mode: bool,
+    large_file_banner: Option<BlockId>,
+    /// Whether the user turned the features disabled in large files back on.
+    large_file_features_enabled: bool,
+    edit_history: Option<EditHistory>,
+    show_cursor_position_overlay: bool,
+    hide_cursor_position_overlay_task: Option<Task<()>>,
+    large_paste: Option<LargePaste>,
+    /// The project to format and save the editor's buffers with once it loses focus, because they
+    /// were autosaved without formatting while it was focused.
+    format_on_blur: Option<Model<Project>>,
+    middle_click_autoscroll: Option<MiddleClickAutoscroll>,
+    primary_selection_task: Option<Task<()>>,
+    scroll_lock: Option<(Model<ScrollLock>, Subscription)>,
+    todo_highlights_task: Option<Task<()>>,
+    selection_highlights_task: Option<Task<()>>,
+    git_blame: Option<GitBlame>,
+    git_blame_task: Option<Task<()>>,
+    git_blame_edits_task: Option<Task<()>>,
+    gutter_data: GutterData,
+    block_decorations: BlockDecorations,
+    runnables: Runnables,
+    code_cells: CodeCells,
+    image_preview_blocks: Vec<ImagePreviewBlock>,
+    code_lens_blocks: Vec<BlockId>,
+    review_mode: Option<ReviewMode>,
+    inline_diff: InlineDiff,
+    code_lens_task: Option<Task<Option<()>>>,
+    marks: BTreeMap<char, Mark>,
+    macro_recording: Option<Vec<MacroStep>>,
+    surround_prompt: Option<SurroundPrompt>,
     placeholder_text: Option<Arc<str>>,
     highlighted_rows: Option<Range<u32>>,
     background_highlights: BTreeMap<TypeId, BackgroundHighlight>,
@@ -409,6 +546,10 @@ pub struct Editor {
     input_enabled: bool,
     use_modal_editing: bool,
     read_only: bool,
+    /// Whether the editor is read-only because the user can't write to its file.
+    read_only_without_write_permission: bool,
+    centered_layout_override: Option<bool>,
+    ligatures_override: Option<bool>,
     leader_peer_id: Option<PeerId>,
     remote_id: Option<ViewId>,
     hover_state: HoverState,
@@ -435,6 +576,8 @@ pub struct Editor {
 pub struct EditorSnapshot {
     pub mode: EditorMode,
     show_gutter: bool,
+    show_breakpoints: bool,
+    large_file_mode: bool,
     pub display_snapshot: DisplaySnapshot,
     pub placeholder_text: Option<Arc<str>>,
     is_focused: bool,
@@ -446,6 +589,9 @@ pub struct GutterDimensions {
     pub padding: Pixels,
     pub width: Pixels,
     pub margin: Pixels,
+    /// The width of the lane between the diff hunk bars and the line numbers where breakpoints
+    /// are shown, included in `width`.
+    pub breakpoint_lane: Pixels,
 }
 
 impl Default for GutterDimensions {
@@ -454,6 +600,7 @@ impl Default for GutterDimensions {
             padding: Pixels::ZERO,
             width: Pixels::ZERO,
             margin: Pixels::ZERO,
+            breakpoint_lane: Pixels::ZERO,
         }
     }
 }
@@ -577,6 +724,28 @@ struct AddSelectionsState {
     stack: Vec<usize>,
 }
 
+/// The selections replaced by `select_larger_syntax_node`, which `select_smaller_syntax_node`
+/// restores. They are anchored so that they survive edits made while expanding selections.
+#[derive(Default)]
+struct SyntaxNodeSelectionHistory {
+    stack: Vec<Box<[Selection<Anchor>]>>,
+    /// The ranges that were selected last. The history is kept while the selections stay within
+    /// them.
+    ranges: Box<[Range<Anchor>]>,
+}
+
+impl SyntaxNodeSelectionHistory {
+    fn contains(&self, selections: &[Selection<Anchor>], buffer: &MultiBufferSnapshot) -> bool {
+        selections.iter().all(|selection| {
+            let selection = selection.start.to_offset(buffer)..selection.end.to_offset(buffer);
+            self.ranges.iter().any(|range| {
+                range.start.to_offset(buffer) <= selection.start
+                    && selection.end <= range.end.to_offset(buffer)
+            })
+        })
+    }
+}
+
 #[derive(Clone)]
 struct SelectNextState {
     query: AhoCorasick,
@@ -619,6 +788,7 @@ struct InvalidationStack<T>(Vec<T>);
 enum ContextMenu {
     Completions(CompletionsMenu),
     CodeActions(CodeActionsMenu),
+    ClipboardHistory(ClipboardHistoryMenu),
 }
 
 impl ContextMenu {
@@ -631,6 +801,7 @@ impl ContextMenu {
             match self {
                 ContextMenu::Completions(menu) => menu.select_first(project, cx),
                 ContextMenu::CodeActions(menu) => menu.select_first(cx),
+                ContextMenu::ClipboardHistory(menu) => menu.select_first(cx),
             }
             true
         } else {
@@ -647,6 +818,7 @@ impl ContextMenu {
             match self {
                 ContextMenu::Completions(menu) => menu.select_prev(project, cx),
                 ContextMenu::CodeActions(menu) => menu.select_prev(cx),
+                ContextMenu::ClipboardHistory(menu) => menu.select_prev(cx),
             }
             true
         } else {
@@ -663,6 +835,7 @@ impl ContextMenu {
             match self {
                 ContextMenu::Completions(menu) => menu.select_next(project, cx),
                 ContextMenu::CodeActions(menu) => menu.select_next(cx),
+                ContextMenu::ClipboardHistory(menu) => menu.select_next(cx),
             }
             true
         } else {
@@ -679,6 +852,7 @@ impl ContextMenu {
             match self {
                 ContextMenu::Completions(menu) => menu.select_last(project, cx),
                 ContextMenu::CodeActions(menu) => menu.select_last(cx),
+                ContextMenu::ClipboardHistory(menu) => menu.select_last(cx),
             }
             true
         } else {
@@ -690,6 +864,7 @@ impl ContextMenu {
         match self {
             ContextMenu::Completions(menu) => menu.visible(),
             ContextMenu::CodeActions(menu) => menu.visible(),
+            ContextMenu::ClipboardHistory(menu) => menu.visible(),
         }
     }
 
@@ -707,6 +882,7 @@ impl ContextMenu {
                 menu.render(style, max_height, workspace, cx),
             ),
             ContextMenu::CodeActions(menu) => menu.render(cursor_position, style, max_height, cx),
+            ContextMenu::ClipboardHistory(menu) => (cursor_position, menu.render(max_height, cx)),
         }
     }
 }
@@ -1235,6 +1411,22 @@ impl Default for CopilotState {
     }
 }
 
+/// Returns the length of the first word of a suggestion, including the whitespace before it.
+/// Runs of punctuation count as words.
+fn partial_suggestion_word_len(text: &str) -> usize {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    let mut chars = text
+        .char_indices()
+        .skip_while(|(_, c)| c.is_whitespace())
+        .peekable();
+    let Some(first_is_word) = chars.peek().map(|(_, c)| is_word_char(*c)) else {
+        return text.len();
+    };
+    chars
+        .find(|(_, c)| c.is_whitespace() || is_word_char(*c) != first_is_word)
+        .map_or(text.len(), |(ix, _)| ix)
+}
+
 impl CopilotState {
     fn active_completion(&self) -> Option<&copilot::Completion> {
         self.completions.get(self.active_completion_index)
@@ -1335,6 +1527,53 @@ pub struct ClipboardSelection {
     pub first_line_indent: u32,
 }
 
+impl ClipboardSelection {
+    /// Splits text that was copied without metadata, e.g. from another application, into one
+    /// clipboard selection per line, if it has as many lines as there are selections to paste it
+    /// into.
+    fn for_lines(text: &str, selection_count: usize) -> Option<Vec<Self>> {
+        if selection_count < 2 {
+            return None;
+        }
+        let lines = text
+            .strip_suffix('\n')
+            .unwrap_or(text)
+            .split('\n')
+            .collect::<Vec<_>>();
+        if lines.len() != selection_count {
+            return None;
+        }
+        Some(
+            lines
+                .into_iter()
+                .map(|line| Self {
+                    len: line.len(),
+                    is_entire_line: false,
+                    first_line_indent: (line.len() - line.trim_start().len()) as u32,
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Returns the range that pasted text replaces. If the corresponding selection was empty when
+/// the text was copied, then the entire line containing the selection was copied. If this
+/// selection is also empty, then the line is pasted before the current line of the buffer.
+fn paste_range(
+    selection: &Selection<usize>,
+    entire_line: bool,
+    line_mode: bool,
+    snapshot: &MultiBufferSnapshot,
+) -> Range<usize> {
+    if selection.is_empty() && !line_mode && entire_line {
+        let column = selection.start.to_point(snapshot).column as usize;
+        let line_start = selection.start - column;
+        line_start..line_start
+    } else {
+        selection.range()
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct NavigationData {
     cursor_anchor: Anchor,
@@ -1434,6 +1673,10 @@ impl Editor {
         });
         clone.selections.clone_state(&self.selections);
         clone.scroll_manager.clone_state(&self.scroll_manager);
+        clone.soft_wrap_mode_override = self.soft_wrap_mode_override;
+        clone.show_wrap_guides = self.show_wrap_guides;
+        clone.show_inline_diagnostics = self.show_inline_diagnostics;
+        clone.show_inline_diff = self.show_inline_diff;
         clone.searchable = self.searchable;
         clone
     }
@@ -1452,7 +1695,7 @@ impl Editor {
 
         let selections = SelectionsCollection::new(display_map.clone(), buffer.clone());
 
-        let blink_manager = cx.new_model(|cx| BlinkManager::new(CURSOR_BLINK_INTERVAL, cx));
+        let blink_manager = cx.new_model(BlinkManager::new);
 
         let soft_wrap_mode_override =
             (mode == EditorMode::SingleLine).then(|| language_settings::SoftWrap::None);
@@ -1469,6 +1712,15 @@ impl Editor {
                     if let project::Event::RefreshInlayHints = event {
                         editor.refresh_inlay_hints(InlayHintRefreshReason::RefreshRequested, cx);
                     };
+                    if let project::Event::LanguageServerAdded(_) = event {
+                        editor.refresh_code_lens(true, cx);
+                    }
+                    if let project::Event::BreakpointsChanged(_) = event {
+                        cx.notify();
+                    }
+                    if let project::Event::TerminalTaskFinished(task_id) = event {
+                        editor.terminal_task_finished(*task_id, cx);
+                    }
                 }));
             }
         }
@@ -1497,7 +1749,7 @@ impl Editor {
             selection_history: Default::default(),
             autoclose_regions: Default::default(),
             snippet_stack: Default::default(),
-            select_larger_syntax_node_stack: Vec::new(),
+            select_larger_syntax_node_history: Default::default(),
             ime_transaction: Default::default(),
             active_diagnostics: None,
             soft_wrap_mode_override,
@@ -1505,11 +1757,54 @@ impl Editor {
             collaboration_hub: project.clone().map(|project| Box::new(project) as _),
             project,
             blink_manager: blink_manager.clone(),
+            cursor_animation: CursorAnimation::default(),
+            line_layout_cache: LineLayoutCache::default(),
+            row_layout_cache: RowLayoutCache::default(),
+            layout_caching: true,
+            quad_merging: true,
+            scrollbar_marker_cache: ScrollbarMarkerCache::default(),
             show_local_selections: true,
             mode,
             show_breadcrumbs: EditorSettings::get_global(cx).toolbar.breadcrumbs,
+            breadcrumb_outline: BreadcrumbOutline::default(),
             show_gutter: mode == EditorMode::Full,
             show_wrap_guides: None,
+            show_inline_diagnostics: None,
+            show_inline_diff: None,
+            font_size_adjustment: px(0.),
+            paragraph_spacing_blocks: ParagraphSpacingBlocks::default(),
+            search_within_excerpt: None,
+            case_preserving_selections: HashMap::default(),
+            replace_previews: ReplacePreviews::default(),
+            conflict_banner: None,
+            large_file_mode: false,
+            large_file_banner: None,
+            large_file_features_enabled: false,
+            edit_history: None,
+            show_cursor_position_overlay: false,
+            hide_cursor_position_overlay_task: None,
+            large_paste: None,
+            format_on_blur: None,
+            middle_click_autoscroll: None,
+            primary_selection_task: None,
+            scroll_lock: None,
+            todo_highlights_task: None,
+            selection_highlights_task: None,
+            git_blame: None,
+            git_blame_task: None,
+            git_blame_edits_task: None,
+            gutter_data: GutterData::default(),
+            block_decorations: BlockDecorations::default(),
+            runnables: Runnables::default(),
+            code_cells: CodeCells::default(),
+            image_preview_blocks: Vec::new(),
+            code_lens_blocks: Vec::new(),
+            review_mode: None,
+            inline_diff: InlineDiff::default(),
+            code_lens_task: None,
+            marks: BTreeMap::new(),
+            macro_recording: None,
+            surround_prompt: None,
             placeholder_text: None,
             highlighted_rows: None,
             background_highlights: Default::default(),
@@ -1534,6 +1829,9 @@ impl Editor {
             input_enabled: true,
             use_modal_editing: mode == EditorMode::Full,
             read_only: false,
+            read_only_without_write_permission: false,
+            centered_layout_override: None,
+            ligatures_override: None,
             use_autoclose: true,
             leader_peer_id: None,
             remote_id: None,
@@ -1547,6 +1845,7 @@ impl Editor {
             style: None,
             show_cursor_names: false,
             hovered_cursors: Default::default(),
+            remote_cursors: RemoteCursors::default(),
             editor_actions: Default::default(),
             show_copilot_suggestions: mode == EditorMode::Full,
             custom_context_menu: None,
@@ -1556,6 +1855,7 @@ impl Editor {
                 cx.observe(&display_map, Self::on_display_map_changed),
                 cx.observe(&blink_manager, |_, _, cx| cx.notify()),
                 cx.observe_global::<SettingsStore>(Self::settings_changed),
+                bookmarks::observe_bookmarks(cx),
                 observe_buffer_font_size_adjustment(cx, |_, cx| cx.notify()),
                 cx.observe_window_activation(|editor, cx| {
                     let active = cx.is_window_active();
@@ -1572,9 +1872,20 @@ impl Editor {
         };
 
         this._subscriptions.extend(project_subscriptions);
+        cx.on_release(|editor, _, cx| large_file::release_large_file_mode(editor, cx))
+            .detach();
 
         this.end_selection(cx);
         this.scroll_manager.show_scrollbar(cx);
+        large_file::refresh_large_file_mode(&mut this, cx);
+        this.refresh_paragraph_spacing(cx);
+        this.refresh_wrap_indent(cx);
+        this.detect_indentation(cx);
+        conflict_banner::refresh_conflict_banner(&mut this, cx);
+        this.refresh_todo_highlights(cx);
+        this.refresh_inline_diff(cx);
+        this.refresh_git_blame(cx);
+        this.refresh_code_lens(false, cx);
 
         if mode == EditorMode::Full {
             let should_auto_hide_scrollbars = cx.should_auto_hide_scrollbars();
@@ -1597,6 +1908,9 @@ impl Editor {
         if self.pending_rename.is_some() {
             key_context.add("renaming");
         }
+        if self.surround_prompt.is_some() {
+            key_context.add("surrounding");
+        }
         if self.context_menu_visible() {
             match self.context_menu.read().as_ref() {
                 Some(ContextMenu::Completions(_)) => {
@@ -1607,9 +1921,19 @@ impl Editor {
                     key_context.add("menu");
                     key_context.add("showing_code_actions")
                 }
+                Some(ContextMenu::ClipboardHistory(_)) => {
+                    key_context.add("menu");
+                    key_context.add("showing_clipboard_history")
+                }
                 None => {}
             }
         }
+        if self.hover_state.tabs().len() > 1 {
+            key_context.add("showing_hover_tabs");
+        }
+        if self.has_active_copilot_suggestion(cx) {
+            key_context.add("copilot_suggestion");
+        }
 
         for layer in self.keymap_context_layers.values() {
             key_context.extend(layer);
@@ -1694,6 +2018,8 @@ impl Editor {
         EditorSnapshot {
             mode: self.mode,
             show_gutter: self.show_gutter,
+            show_breakpoints: self.breakpoints_enabled(cx),
+            large_file_mode: self.large_file_mode,
             display_snapshot: self.display_map.update(cx, |map, cx| map.snapshot(cx)),
             scroll_anchor: self.scroll_manager.anchor(),
             ongoing_scroll: self.scroll_manager.ongoing_scroll(),
@@ -1818,13 +2144,53 @@ impl Editor {
     }
 
     pub fn read_only(&self, cx: &AppContext) -> bool {
-        self.read_only || self.buffer.read(cx).read_only()
+        self.read_only || self.large_paste.is_some() || !self.can_unlock(cx)
+    }
+
+    /// Whether the editor can be made editable with `toggle_read_only`, which isn't the case when
+    /// its buffer is read-only or the user can't write to its file, as saving would then fail.
+    fn can_unlock(&self, cx: &AppContext) -> bool {
+        !self.read_only_without_write_permission && !self.buffer.read(cx).read_only()
     }
 
     pub fn set_read_only(&mut self, read_only: bool) {
         self.read_only = read_only;
     }
 
+    pub fn toggle_read_only(&mut self, _: &ToggleReadOnly, cx: &mut ViewContext<Self>) {
+        if !self.can_unlock(cx) {
+            return;
+        }
+        self.read_only = !self.read_only;
+        cx.notify();
+    }
+
+    /// Whether the text of the editor is constrained to a column in the middle of its width.
+    pub fn centered_layout(&self, cx: &AppContext) -> bool {
+        self.mode == EditorMode::Full
+            && self
+                .centered_layout_override
+                .unwrap_or_else(|| EditorSettings::get_global(cx).centered_layout.enabled)
+    }
+
+    pub fn toggle_centered_layout(&mut self, _: &ToggleCenteredLayout, cx: &mut ViewContext<Self>) {
+        if self.mode != EditorMode::Full {
+            return;
+        }
+        self.centered_layout_override = Some(!self.centered_layout(cx));
+        cx.notify();
+    }
+
+    /// Returns the shape used to render local cursors. Read-only editors use a hollow cursor
+    /// to indicate that typing has no effect.
+    pub(crate) fn local_cursor_shape(&self, cx: &AppContext) -> CursorShape {
+        if self.mode == EditorMode::Full && self.read_only(cx) {
+            CursorShape::Hollow
+        } else {
+            self.cursor_shape
+        }
+    }
+
     pub fn set_use_autoclose(&mut self, autoclose: bool) {
         self.use_autoclose = autoclose;
     }
@@ -1865,10 +2231,17 @@ impl Editor {
         self.add_selections_state = None;
         self.select_next_state = None;
         self.select_prev_state = None;
-        self.select_larger_syntax_node_stack.clear();
+        if !self
+            .select_larger_syntax_node_history
+            .contains(&self.selections.disjoint_anchors(), buffer)
+        {
+            self.select_larger_syntax_node_history = Default::default();
+        }
         self.invalidate_autoclose_regions(&self.selections.disjoint_anchors(), buffer);
+        self.refresh_case_preserving_selections(buffer);
         self.snippet_stack
             .invalidate(&self.selections.disjoint_anchors(), buffer);
+        self.refresh_snippet_placeholder_highlights(cx);
         self.take_rename(false, cx);
 
         let new_cursor_position = self.selections.newest_anchor().head();
@@ -1942,10 +2315,18 @@ impl Editor {
             }
             self.refresh_code_actions(cx);
             self.refresh_document_highlights(cx);
+            self.refresh_selection_match_highlights(cx);
             refresh_matching_bracket_highlights(self, cx);
             self.discard_copilot_suggestion(cx);
         }
 
+        if local {
+            cursor_position_overlay::refresh_cursor_position_overlay(self, cx);
+            if cfg!(target_os = "linux") {
+                middle_click::refresh_primary_selection(self, cx);
+            }
+        }
+
         self.blink_manager.update(cx, BlinkManager::pause_blinking);
         cx.emit(EditorEvent::SelectionsChanged { local });
 
@@ -2044,6 +2425,9 @@ impl Editor {
                 position,
                 goal_column,
             } => self.begin_columnar_selection(position, goal_column, cx),
+            SelectPhase::BeginLineWise { position, extend } => {
+                self.begin_line_wise_selection(position, extend, cx)
+            }
             SelectPhase::Extend {
                 position,
                 click_count,
@@ -2159,6 +2543,49 @@ impl Editor {
         });
     }
 
+    fn begin_line_wise_selection(
+        &mut self,
+        position: DisplayPoint,
+        extend: bool,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        let tail = self.selections.newest::<Point>(cx).tail();
+        self.begin_selection(position, false, 3, cx);
+        if !extend {
+            return;
+        }
+
+        let buffer = &display_map.buffer_snapshot;
+        let tail_line_start = display_map.prev_line_boundary(tail).0;
+        let tail_line_end = buffer.clip_point(
+            display_map.next_line_boundary(tail).0 + Point::new(1, 0),
+            Bias::Left,
+        );
+        let position = display_map
+            .clip_point(position, Bias::Left)
+            .to_point(&display_map);
+
+        let mut pending_selection = self
+            .selections
+            .pending_anchor()
+            .expect("begin_line_wise_selection not called with pending selection");
+        if position >= tail_line_start {
+            pending_selection.start = buffer.anchor_before(tail_line_start);
+        } else {
+            pending_selection.end = buffer.anchor_before(tail_line_end);
+            pending_selection.reversed = true;
+        }
+        // Dragging keeps the tail's line selected, whichever way the selection grows.
+        let pending_mode = SelectMode::Line(
+            buffer.anchor_before(tail_line_start)..buffer.anchor_before(tail_line_end),
+        );
+
+        self.change_selections(Some(Autoscroll::fit()), cx, |s| {
+            s.set_pending(pending_selection, pending_mode)
+        });
+    }
+
     fn begin_columnar_selection(
         &mut self,
         position: DisplayPoint,
@@ -2343,10 +2770,19 @@ impl Editor {
     }
 
     pub fn cancel(&mut self, _: &Cancel, cx: &mut ViewContext<Self>) {
+        if self.middle_click_autoscroll.is_some() {
+            middle_click::stop_autoscroll(self, cx);
+            return;
+        }
+
         if self.take_rename(false, cx).is_some() {
             return;
         }
 
+        if self.take_surround_prompt(cx).is_some() {
+            return;
+        }
+
         if hide_hover(self, cx) {
             return;
         }
@@ -2360,6 +2796,7 @@ impl Editor {
         }
 
         if self.snippet_stack.pop().is_some() {
+            self.refresh_snippet_placeholder_highlights(cx);
             return;
         }
 
@@ -2383,6 +2820,7 @@ impl Editor {
         if self.read_only(cx) {
             return;
         }
+        self.record_macro_input(&text);
 
         let selections = self.selections.all_adjusted(cx);
         let mut brace_inserted = false;
@@ -2473,8 +2911,12 @@ impl Editor {
                         }
                     }
                     // If an opening bracket is 1 character long and is typed while
-                    // text is selected, then surround that text with the bracket pair.
-                    else if is_bracket_pair_start && bracket_pair.start.chars().count() == 1 {
+                    // text is selected, then surround that text with the bracket pair, unless
+                    // surrounding is disabled for the language.
+                    else if is_bracket_pair_start
+                        && bracket_pair.start.chars().count() == 1
+                        && snapshot.settings_at(selection.start, cx).use_auto_surround
+                    {
                         edits.push((selection.start..selection.start, text.clone()));
                         edits.push((
                             selection.end..selection.end,
@@ -2501,7 +2943,8 @@ impl Editor {
             // newly inserted text.
             let anchor = snapshot.anchor_after(selection.end);
             new_selections.push((selection.map(|_| anchor), 0));
-            edits.push((selection.start..selection.end, text.clone()));
+            let text = self.case_preserved_input(&selection, &text, &snapshot);
+            edits.push((selection.start..selection.end, text));
         }
 
         drop(snapshot);
@@ -2580,6 +3023,10 @@ impl Editor {
     }
 
     pub fn newline(&mut self, _: &Newline, cx: &mut ViewContext<Self>) {
+        if self.read_only(cx) {
+            return;
+        }
+
         self.transact(cx, |this, cx| {
             let (edits, selection_fixup_info): (Vec<_>, Vec<_>) = {
                 let selections = this.selections.all::<usize>(cx);
@@ -2718,6 +3165,10 @@ impl Editor {
     }
 
     pub fn newline_above(&mut self, _: &NewlineAbove, cx: &mut ViewContext<Self>) {
+        if self.read_only(cx) {
+            return;
+        }
+
         let buffer = self.buffer.read(cx);
         let snapshot = buffer.snapshot(cx);
 
@@ -2777,6 +3228,10 @@ impl Editor {
     }
 
     pub fn newline_below(&mut self, _: &NewlineBelow, cx: &mut ViewContext<Self>) {
+        if self.read_only(cx) {
+            return;
+        }
+
         let buffer = self.buffer.read(cx);
         let snapshot = buffer.snapshot(cx);
 
@@ -3357,6 +3812,10 @@ impl Editor {
     ) -> Option<Task<Result<()>>> {
         use language::ToOffset as _;
 
+        if self.read_only(cx) {
+            return None;
+        }
+
         let completions_menu = if let ContextMenu::Completions(menu) = self.hide_context_menu(cx)? {
             menu
         } else {
@@ -3524,6 +3983,10 @@ impl Editor {
         action: &ConfirmCodeAction,
         cx: &mut ViewContext<Self>,
     ) -> Option<Task<Result<()>>> {
+        if self.read_only(cx) {
+            return None;
+        }
+
         let actions_menu = if let ContextMenu::CodeActions(menu) = self.hide_context_menu(cx)? {
             menu
         } else {
@@ -3953,6 +4416,61 @@ impl Editor {
         }
     }
 
+    /// Accepts the next word of the active Copilot suggestion, leaving the rest of it visible.
+    pub fn accept_copilot_suggestion_word(
+        &mut self,
+        _: &AcceptCopilotSuggestionWord,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.accept_partial_copilot_suggestion(partial_suggestion_word_len, cx);
+    }
+
+    /// Accepts the active Copilot suggestion up to the end of its first line.
+    pub fn accept_copilot_suggestion_line(
+        &mut self,
+        _: &AcceptCopilotSuggestionLine,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.accept_partial_copilot_suggestion(
+            |text| text.find('\n').map_or(text.len(), |ix| ix + 1),
+            cx,
+        );
+    }
+
+    fn accept_partial_copilot_suggestion(
+        &mut self,
+        partial_len: impl FnOnce(&str) -> usize,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if !self.has_active_copilot_suggestion(cx) || self.selections.count() > 1 {
+            cx.propagate();
+            return;
+        }
+        let Some(text) = self
+            .copilot_state
+            .suggestion
+            .as_ref()
+            .map(|suggestion| suggestion.text.to_string())
+        else {
+            return;
+        };
+
+        let len = partial_len(&text);
+        if len >= text.len() {
+            self.accept_copilot_suggestion(cx);
+            return;
+        }
+        cx.emit(EditorEvent::InputHandled {
+            utf16_range_to_replace: None,
+            text: text[..len].to_string().into(),
+        });
+        self.insert_with_autoindent_mode(&text[..len], None, cx);
+        // The rest of the suggestion still matches the active completion, so it is shown again
+        // right away.
+        self.refresh_copilot_suggestions(true, cx);
+        cx.notify();
+    }
+
     fn discard_copilot_suggestion(&mut self, cx: &mut ViewContext<Self>) -> bool {
         if let Some(suggestion) = self.take_active_copilot_suggestion(cx) {
             if let Some(copilot) = Copilot::global(cx) {
@@ -4074,6 +4592,27 @@ impl Editor {
         }
     }
 
+    /// Renders the lock painted in the gutter of read-only editors, which makes the editor
+    /// editable when clicked, unless it can't be unlocked.
+    pub fn render_read_only_lock(&self, cx: &mut ViewContext<Self>) -> Option<IconButton> {
+        if self.mode != EditorMode::Full || !self.read_only(cx) {
+            return None;
+        }
+        let lock = IconButton::new("read_only_lock", IconName::FileLock)
+            .icon_size(IconSize::XSmall)
+            .icon_color(Color::Muted);
+        if self.large_paste.is_none() && self.can_unlock(cx) {
+            Some(
+                lock.tooltip(|cx| Tooltip::for_action("Read-only", &ToggleReadOnly, cx))
+                    .on_click(cx.listener(|editor, _e, cx| {
+                        editor.toggle_read_only(&ToggleReadOnly, cx);
+                    })),
+            )
+        } else {
+            Some(lock.tooltip(|cx| Tooltip::text("Read-only", cx)))
+        }
+    }
+
     pub fn render_fold_indicators(
         &self,
         fold_data: Vec<Option<(FoldStatus, u32, bool)>>,
@@ -4093,11 +4632,14 @@ impl Editor {
                             IconButton::new(ix as usize, ui::IconName::ChevronDown)
                                 .on_click({
                                     let view = editor_view.clone();
-                                    move |_e, cx| {
+                                    move |e, cx| {
                                         view.update(cx, |editor, cx| match fold_status {
                                             FoldStatus::Folded => {
                                                 editor.unfold_at(&UnfoldAt { buffer_row }, cx);
                                             }
+                                            FoldStatus::Foldable if e.down.modifiers.alt => {
+                                                editor.fold_recursively_at(buffer_row, cx);
+                                            }
                                             FoldStatus::Foldable => {
                                                 editor.fold_at(&FoldAt { buffer_row }, cx);
                                             }
@@ -4206,6 +4748,7 @@ impl Editor {
                 active_index: 0,
                 ranges: tabstops,
             });
+            self.refresh_snippet_placeholder_highlights(cx);
         }
 
         Ok(())
@@ -4247,6 +4790,7 @@ impl Editor {
                 if snippet.active_index + 1 < snippet.ranges.len() {
                     self.snippet_stack.push(snippet);
                 }
+                self.refresh_snippet_placeholder_highlights(cx);
                 return true;
             }
         }
@@ -4254,6 +4798,32 @@ impl Editor {
         false
     }
 
+    /// Highlights the placeholders of the active snippet's other tab stops, so that it's visible
+    /// where `tab` and `shift-tab` will move the selections.
+    fn refresh_snippet_placeholder_highlights(&mut self, cx: &mut ViewContext<Self>) {
+        let buffer = self.buffer.read(cx).snapshot(cx);
+        let ranges = self.snippet_stack.last().map_or(Vec::new(), |snippet| {
+            snippet
+                .ranges
+                .iter()
+                .enumerate()
+                .filter(|(ix, _)| *ix != snippet.active_index)
+                .flat_map(|(_, ranges)| ranges.iter())
+                .filter(|range| range.start.cmp(&range.end, &buffer).is_lt())
+                .cloned()
+                .collect::<Vec<_>>()
+        });
+        if ranges.is_empty() {
+            self.clear_background_highlights::<SnippetPlaceholderHighlights>(cx);
+        } else {
+            self.highlight_background::<SnippetPlaceholderHighlights>(
+                ranges,
+                |theme| theme.editor_document_highlight_read_background,
+                cx,
+            );
+        }
+    }
+
     pub fn clear(&mut self, cx: &mut ViewContext<Self>) {
         self.transact(cx, |this, cx| {
             this.select_all(&SelectAll, cx);
@@ -4395,11 +4965,11 @@ impl Editor {
             }
 
             // Otherwise, insert a hard or soft tab.
-            let settings = buffer.settings_at(cursor, cx);
-            let tab_size = if settings.hard_tabs {
+            let indent_unit = indent_unit_at(buffer, &snapshot, cursor, cx);
+            let tab_size = if indent_unit.kind == IndentKind::Tab {
                 IndentSize::tab()
             } else {
-                let tab_size = settings.tab_size.get();
+                let tab_size = indent_unit.len;
                 let char_column = snapshot
                     .text_for_range(Point::new(cursor.row, 0)..cursor)
                     .flat_map(str::chars)
@@ -4422,6 +4992,10 @@ impl Editor {
     }
 
     pub fn indent(&mut self, _: &Indent, cx: &mut ViewContext<Self>) {
+        if self.read_only(cx) {
+            return;
+        }
+
         let mut selections = self.selections.all::<Point>(cx);
         let mut prev_edited_row = 0;
         let mut row_delta = 0;
@@ -4452,12 +5026,11 @@ impl Editor {
         delta_for_start_row: u32,
         cx: &AppContext,
     ) -> u32 {
-        let settings = buffer.settings_at(selection.start, cx);
-        let tab_size = settings.tab_size.get();
-        let indent_kind = if settings.hard_tabs {
-            IndentKind::Tab
-        } else {
-            IndentKind::Space
+        let indent_unit = indent_unit_at(buffer, snapshot, selection.start, cx);
+        let indent_kind = indent_unit.kind;
+        let tab_size = match indent_kind {
+            IndentKind::Space => indent_unit.len,
+            IndentKind::Tab => buffer.settings_at(selection.start, cx).tab_size.get(),
         };
         let mut start_row = selection.start.row;
         let mut end_row = selection.end.row + 1;
@@ -4515,6 +5088,10 @@ impl Editor {
     }
 
     pub fn outdent(&mut self, _: &Outdent, cx: &mut ViewContext<Self>) {
+        if self.read_only(cx) {
+            return;
+        }
+
         let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
         let selections = self.selections.all::<Point>(cx);
         let mut deletion_ranges = Vec::new();
@@ -4523,8 +5100,11 @@ impl Editor {
             let buffer = self.buffer.read(cx);
             let snapshot = buffer.snapshot(cx);
             for selection in &selections {
-                let settings = buffer.settings_at(selection.start, cx);
-                let tab_size = settings.tab_size.get();
+                let indent_unit = indent_unit_at(buffer, &snapshot, selection.start, cx);
+                let tab_size = match indent_unit.kind {
+                    IndentKind::Space => indent_unit.len,
+                    IndentKind::Tab => buffer.settings_at(selection.start, cx).tab_size.get(),
+                };
                 let mut rows = selection.spanned_rows(false, &display_map);
 
                 // Avoid re-outdenting a row that has already been outdented by a
@@ -4573,6 +5153,10 @@ impl Editor {
     }
 
     pub fn delete_line(&mut self, _: &DeleteLine, cx: &mut ViewContext<Self>) {
+        if self.read_only(cx) {
+            return;
+        }
+
         let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
         let selections = self.selections.all::<Point>(cx);
 
@@ -4655,6 +5239,21 @@ impl Editor {
     }
 
     pub fn join_lines(&mut self, _: &JoinLines, cx: &mut ViewContext<Self>) {
+        self.join_lines_internal(None, cx)
+    }
+
+    /// Joins the lines like `join_lines`, separating the joined lines with the given separator,
+    /// e.g. to join the elements of a list.
+    pub fn join_lines_with(&mut self, action: &JoinLinesWith, cx: &mut ViewContext<Self>) {
+        let separator = action.separator.as_deref().unwrap_or(", ");
+        self.join_lines_internal(Some(separator), cx)
+    }
+
+    fn join_lines_internal(&mut self, separator: Option<&str>, cx: &mut ViewContext<Self>) {
+        if self.read_only(cx) {
+            return;
+        }
+
         let mut row_ranges = Vec::<Range<u32>>::new();
         for selection in self.selections.all::<Point>(cx) {
             let start = selection.start.row;
@@ -4686,18 +5285,12 @@ impl Editor {
         self.transact(cx, |this, cx| {
             for row_range in row_ranges.into_iter().rev() {
                 for row in row_range.rev() {
-                    let end_of_line = Point::new(row, snapshot.line_len(row));
-                    let indent = snapshot.indent_size_for_line(row + 1);
-                    let start_of_next_line = Point::new(row + 1, indent.len);
-
-                    let replace = if snapshot.line_len(row + 1) > indent.len {
-                        " "
-                    } else {
-                        ""
-                    };
-
+                    if row >= snapshot.max_buffer_row() {
+                        continue;
+                    }
+                    let (range, replacement) = join_line_edit(&snapshot, row, separator);
                     this.buffer.update(cx, |buffer, cx| {
-                        buffer.edit([(end_of_line..start_of_next_line, replace)], None, cx)
+                        buffer.edit([(range, replacement)], None, cx)
                     });
                 }
             }
@@ -4724,6 +5317,44 @@ impl Editor {
         self.manipulate_lines(cx, |lines| lines.sort_by_key(|line| line.to_lowercase()))
     }
 
+    /// Sorts the lines by the first number they contain. Lines without a number come first.
+    pub fn sort_lines_numerically(&mut self, _: &SortLinesNumerically, cx: &mut ViewContext<Self>) {
+        self.manipulate_lines(cx, |lines| {
+            lines.sort_by(|a, b| {
+                let (a, b) = (line_number_key(a), line_number_key(b));
+                a.is_some()
+                    .cmp(&b.is_some())
+                    .then_with(|| a.unwrap_or(0.).total_cmp(&b.unwrap_or(0.)))
+            })
+        })
+    }
+
+    /// Sorts the lines by the text in the columns spanned by the newest selection, which is
+    /// typically one of a columnar selection. When it's empty, lines are sorted by their text
+    /// from its column to their end.
+    pub fn sort_lines_by_column(&mut self, _: &SortLinesByColumn, cx: &mut ViewContext<Self>) {
+        let selection = self.selections.newest::<Point>(cx);
+        let start = selection.start.column.min(selection.end.column) as usize;
+        let end = if selection.is_empty() {
+            usize::MAX
+        } else {
+            selection.start.column.max(selection.end.column) as usize
+        };
+        self.manipulate_lines(cx, |lines| {
+            lines.sort_by(|a, b| line_columns(a, start..end).cmp(line_columns(b, start..end)))
+        })
+    }
+
+    /// Removes the lines that are identical to another selected line, or only the ones identical
+    /// to the line before them when `adjacent` is true.
+    pub fn unique_lines(&mut self, action: &UniqueLines, cx: &mut ViewContext<Self>) {
+        if action.adjacent {
+            self.manipulate_lines(cx, |lines| lines.dedup())
+        } else {
+            self.unique_lines_case_sensitive(&UniqueLinesCaseSensitive, cx)
+        }
+    }
+
     pub fn unique_lines_case_insensitive(
         &mut self,
         _: &UniqueLinesCaseInsensitive,
@@ -4758,6 +5389,10 @@ impl Editor {
     where
         Fn: FnMut(&mut Vec<&str>),
     {
+        if self.read_only(cx) {
+            return;
+        }
+
         let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
         let buffer = self.buffer.read(cx).snapshot(cx);
 
@@ -4892,6 +5527,10 @@ impl Editor {
     where
         Fn: FnMut(&str) -> String,
     {
+        if self.read_only(cx) {
+            return;
+        }
+
         let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
         let buffer = self.buffer.read(cx).snapshot(cx);
 
@@ -4944,34 +5583,102 @@ impl Editor {
     }
 
     pub fn duplicate_line(&mut self, _: &DuplicateLine, cx: &mut ViewContext<Self>) {
-        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
-        let buffer = &display_map.buffer_snapshot;
-        let selections = self.selections.all::<Point>(cx);
+        self.duplicate_lines(false, cx);
+    }
 
-        let mut edits = Vec::new();
-        let mut selections_iter = selections.iter().peekable();
-        while let Some(selection) = selections_iter.next() {
-            // Avoid duplicating the same lines twice.
-            let mut rows = selection.spanned_rows(false, &display_map);
+    /// Duplicates the selected lines, keeping the selections on the copy above the original lines.
+    pub fn duplicate_line_up(&mut self, _: &DuplicateLineUp, cx: &mut ViewContext<Self>) {
+        self.duplicate_lines(true, cx);
+    }
 
-            while let Some(next_selection) = selections_iter.peek() {
-                let next_rows = next_selection.spanned_rows(false, &display_map);
-                if next_rows.start < rows.end {
-                    rows.end = next_rows.end;
-                    selections_iter.next().unwrap();
-                } else {
-                    break;
-                }
-            }
+    /// Inserts a copy of the text of each non-empty selection right after it, and selects the
+    /// copies. When every selection is empty, the lines containing them are duplicated instead.
+    pub fn duplicate_selection(&mut self, _: &DuplicateSelection, cx: &mut ViewContext<Self>) {
+        if self.read_only(cx) {
+            return;
+        }
 
-            // Copy the text from the selected row region and splice it at the start of the region.
-            let start = Point::new(rows.start, 0);
-            let end = Point::new(rows.end - 1, buffer.line_len(rows.end - 1));
-            let text = buffer
-                .text_for_range(start..end)
-                .chain(Some("\n"))
-                .collect::<String>();
-            edits.push((start..start, text));
+        let buffer = self.buffer.read(cx).snapshot(cx);
+        let selections = self.selections.all::<usize>(cx);
+        if selections.iter().all(|selection| selection.is_empty()) {
+            self.duplicate_lines(false, cx);
+            return;
+        }
+
+        let mut edits = Vec::new();
+        let mut new_selections = Vec::with_capacity(selections.len());
+        let mut delta = 0;
+        for selection in selections {
+            if selection.is_empty() {
+                new_selections.push(Selection {
+                    start: selection.start + delta,
+                    end: selection.end + delta,
+                    ..selection
+                });
+                continue;
+            }
+            let text = buffer.text_for_range(selection.range()).collect::<String>();
+            let len = text.len();
+            edits.push((selection.end..selection.end, text));
+            new_selections.push(Selection {
+                start: selection.end + delta,
+                end: selection.end + delta + len,
+                ..selection
+            });
+            delta += len;
+        }
+
+        self.transact(cx, |this, cx| {
+            this.buffer
+                .update(cx, |buffer, cx| buffer.edit(edits, None, cx));
+            this.change_selections(Some(Autoscroll::fit()), cx, |s| s.select(new_selections));
+        });
+    }
+
+    fn duplicate_lines(&mut self, upwards: bool, cx: &mut ViewContext<Self>) {
+        if self.read_only(cx) {
+            return;
+        }
+
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        let buffer = &display_map.buffer_snapshot;
+        let selections = self.selections.all::<Point>(cx);
+
+        let mut edits = Vec::new();
+        let mut new_selections = Vec::with_capacity(selections.len());
+        let mut row_delta = 0;
+        let mut selections_iter = selections.iter().peekable();
+        while let Some(selection) = selections_iter.next() {
+            // Avoid duplicating the same lines twice.
+            let mut rows = selection.spanned_rows(false, &display_map);
+            let mut region_selections = vec![selection.clone()];
+
+            while let Some(next_selection) = selections_iter.peek() {
+                let next_rows = next_selection.spanned_rows(false, &display_map);
+                if next_rows.start < rows.end {
+                    rows.end = next_rows.end;
+                    region_selections.push(selections_iter.next().unwrap().clone());
+                } else {
+                    break;
+                }
+            }
+
+            // Copy the text from the selected row region and splice it at the start of the region,
+            // or at its end when duplicating upwards so that the selections stay on the first copy.
+            let start = Point::new(rows.start, 0);
+            let end = Point::new(rows.end - 1, buffer.line_len(rows.end - 1));
+            let text = buffer.text_for_range(start..end).collect::<String>();
+            if upwards {
+                edits.push((end..end, format!("\n{text}")));
+            } else {
+                edits.push((start..start, format!("{text}\n")));
+            }
+            new_selections.extend(region_selections.into_iter().map(|selection| Selection {
+                start: Point::new(selection.start.row + row_delta, selection.start.column),
+                end: Point::new(selection.end.row + row_delta, selection.end.column),
+                ..selection
+            }));
+            row_delta += rows.end - rows.start;
         }
 
         self.transact(cx, |this, cx| {
@@ -4979,11 +5686,19 @@ impl Editor {
                 buffer.edit(edits, None, cx);
             });
 
-            this.request_autoscroll(Autoscroll::fit(), cx);
+            if upwards {
+                this.change_selections(Some(Autoscroll::fit()), cx, |s| s.select(new_selections));
+            } else {
+                this.request_autoscroll(Autoscroll::fit(), cx);
+            }
         });
     }
 
     pub fn move_line_up(&mut self, _: &MoveLineUp, cx: &mut ViewContext<Self>) {
+        if self.read_only(cx) {
+            return;
+        }
+
         let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
         let buffer = self.buffer.read(cx).snapshot(cx);
 
@@ -5082,6 +5797,10 @@ impl Editor {
     }
 
     pub fn move_line_down(&mut self, _: &MoveLineDown, cx: &mut ViewContext<Self>) {
+        if self.read_only(cx) {
+            return;
+        }
+
         let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
         let buffer = self.buffer.read(cx).snapshot(cx);
 
@@ -5170,7 +5889,91 @@ impl Editor {
         });
     }
 
+    /// Swaps the syntax node enclosing each selection with its previous sibling, or moves the
+    /// selected lines up when there's no such sibling.
+    pub fn move_selection_up(&mut self, _: &MoveSelectionUp, cx: &mut ViewContext<Self>) {
+        if !self.move_selections_by_syntax_node(false, cx) {
+            self.move_line_up(&MoveLineUp, cx);
+        }
+    }
+
+    /// Swaps the syntax node enclosing each selection with its next sibling, or moves the
+    /// selected lines down when there's no such sibling.
+    pub fn move_selection_down(&mut self, _: &MoveSelectionDown, cx: &mut ViewContext<Self>) {
+        if !self.move_selections_by_syntax_node(true, cx) {
+            self.move_line_down(&MoveLineDown, cx);
+        }
+    }
+
+    /// Swaps the syntax node enclosing each selection with its sibling in the given direction,
+    /// keeping the selections at the same place within the moved nodes. Returns false, without
+    /// editing, if any selection has no such sibling.
+    fn move_selections_by_syntax_node(&mut self, next: bool, cx: &mut ViewContext<Self>) -> bool {
+        if self.read_only(cx) {
+            return true;
+        }
+        let buffer = self.buffer.read(cx).snapshot(cx);
+        let selections = self.selections.all::<usize>(cx);
+        let mut edits = Vec::new();
+        let mut new_selections = Vec::with_capacity(selections.len());
+        let mut last_swapped_range: Option<Range<usize>> = None;
+        for selection in selections {
+            let Some((node, sibling)) = buffer.range_for_syntax_sibling(selection.range(), next)
+            else {
+                return false;
+            };
+            let swapped_range = node.start.min(sibling.start)..node.end.max(sibling.end);
+            let new_node_start = if next {
+                sibling.end - node.len()
+            } else {
+                sibling.start
+            };
+            let new_selection_range = new_node_start + (selection.start - node.start)
+                ..new_node_start + (selection.end - node.start);
+
+            // Selections within the same nodes move along with the first of them, and those in
+            // nodes that overlap the swapped ones stay where they are. Since the swapped nodes
+            // keep their combined length, the offsets of those selections remain valid.
+            if let Some(last_swapped_range) = &last_swapped_range {
+                if swapped_range.start < last_swapped_range.end {
+                    if *last_swapped_range == swapped_range {
+                        new_selections.push(Selection {
+                            start: new_selection_range.start,
+                            end: new_selection_range.end,
+                            ..selection
+                        });
+                    } else {
+                        new_selections.push(selection);
+                    }
+                    continue;
+                }
+            }
+
+            let node_text = buffer.text_for_range(node.clone()).collect::<String>();
+            let sibling_text = buffer.text_for_range(sibling.clone()).collect::<String>();
+            edits.push((node, sibling_text));
+            edits.push((sibling, node_text));
+            new_selections.push(Selection {
+                start: new_selection_range.start,
+                end: new_selection_range.end,
+                ..selection
+            });
+            last_swapped_range = Some(swapped_range);
+        }
+
+        self.transact(cx, |this, cx| {
+            this.buffer
+                .update(cx, |buffer, cx| buffer.edit(edits, None, cx));
+            this.change_selections(Some(Autoscroll::fit()), cx, |s| s.select(new_selections));
+        });
+        true
+    }
+
     pub fn transpose(&mut self, _: &Transpose, cx: &mut ViewContext<Self>) {
+        if self.read_only(cx) {
+            return;
+        }
+
         let text_layout_details = &self.text_layout_details(cx);
         self.transact(cx, |this, cx| {
             let edits = this.change_selections(Some(Autoscroll::fit()), cx, |s| {
@@ -5266,7 +6069,10 @@ impl Editor {
                 s.select(selections);
             });
             this.insert("", cx);
-            cx.write_to_clipboard(ClipboardItem::new(text).with_metadata(clipboard_selections));
+            clipboard_history::write_to_clipboard(
+                ClipboardItem::new(text).with_metadata(clipboard_selections),
+                cx,
+            );
         });
     }
 
@@ -5305,85 +6111,188 @@ impl Editor {
             }
         }
 
-        cx.write_to_clipboard(ClipboardItem::new(text).with_metadata(clipboard_selections));
+        clipboard_history::write_to_clipboard(
+            ClipboardItem::new(text).with_metadata(clipboard_selections),
+            cx,
+        );
+    }
+
+    pub fn copy_with_formatting(&mut self, _: &CopyWithFormatting, cx: &mut ViewContext<Self>) {
+        let Some(style) = self.style.as_ref() else {
+            return;
+        };
+
+        let selections = self.selections.all::<Point>(cx);
+        let buffer = self.buffer.read(cx).snapshot(cx);
+        let max_point = buffer.max_point();
+        let mut text = String::new();
+        let mut formatted_text = FormattedText::default();
+        for (ix, selection) in selections.iter().enumerate() {
+            let mut start = selection.start;
+            let mut end = selection.end;
+            if selection.is_empty() || self.selections.line_mode {
+                start = Point::new(start.row, 0);
+                end = cmp::min(max_point, Point::new(end.row + 1, 0));
+            }
+            if ix > 0 {
+                text.push('\n');
+                formatted_text.push("\n", style.text.color, None, None);
+            }
+            for chunk in buffer.chunks(start..end, true) {
+                let highlight = chunk
+                    .syntax_highlight_id
+                    .and_then(|id| id.style(&style.syntax))
+                    .unwrap_or_default();
+                text.push_str(chunk.text);
+                formatted_text.push(
+                    chunk.text,
+                    highlight.color.unwrap_or(style.text.color),
+                    highlight.font_weight,
+                    highlight.font_style,
+                );
+            }
+        }
+
+        let font_family = style.text.font_family.clone();
+        let font_size = f32::from(style.text.font_size.to_pixels(cx.rem_size()));
+        let rtf = formatted_text.to_rtf(&font_family, font_size, style.background);
+        let html = formatted_text.to_html(&font_family, font_size, style.background);
+        clipboard_history::write_to_clipboard(
+            ClipboardItem::new(text).with_rtf(rtf).with_html(html),
+            cx,
+        );
     }
 
     pub fn paste(&mut self, _: &Paste, cx: &mut ViewContext<Self>) {
+        self.do_paste(true, cx);
+    }
+
+    pub fn paste_without_formatting(
+        &mut self,
+        _: &PasteWithoutFormatting,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.do_paste(false, cx);
+    }
+
+    fn do_paste(&mut self, reindent: bool, cx: &mut ViewContext<Self>) {
         if self.read_only(cx) {
             return;
         }
 
-        self.transact(cx, |this, cx| {
-            if let Some(item) = cx.read_from_clipboard() {
-                let clipboard_text = Cow::Borrowed(item.text());
-                if let Some(mut clipboard_selections) = item.metadata::<Vec<ClipboardSelection>>() {
-                    let old_selections = this.selections.all::<usize>(cx);
-                    let all_selections_were_entire_line =
-                        clipboard_selections.iter().all(|s| s.is_entire_line);
-                    let first_selection_indent_column =
-                        clipboard_selections.first().map(|s| s.first_line_indent);
-                    if clipboard_selections.len() != old_selections.len() {
-                        clipboard_selections.drain(..);
-                    }
-
-                    this.buffer.update(cx, |buffer, cx| {
-                        let snapshot = buffer.read(cx);
-                        let mut start_offset = 0;
-                        let mut edits = Vec::new();
-                        let mut original_indent_columns = Vec::new();
-                        let line_mode = this.selections.line_mode;
-                        for (ix, selection) in old_selections.iter().enumerate() {
-                            let to_insert;
-                            let entire_line;
-                            let original_indent_column;
-                            if let Some(clipboard_selection) = clipboard_selections.get(ix) {
-                                let end_offset = start_offset + clipboard_selection.len;
-                                to_insert = &clipboard_text[start_offset..end_offset];
-                                entire_line = clipboard_selection.is_entire_line;
-                                start_offset = end_offset + 1;
-                                original_indent_column =
-                                    Some(clipboard_selection.first_line_indent);
-                            } else {
-                                to_insert = clipboard_text.as_str();
-                                entire_line = all_selections_were_entire_line;
-                                original_indent_column = first_selection_indent_column
-                            }
+        let Some(item) = cx.read_from_clipboard() else {
+            return;
+        };
+        let mut clipboard_text = Cow::Borrowed(item.text());
+        let auto_indent = reindent && self.auto_indent_on_paste(cx);
+        let mut clipboard_selections = item.metadata::<Vec<ClipboardSelection>>();
+        if clipboard_selections.is_none() {
+            // Text copied from other applications may have CRLF line endings, whose `\r`
+            // would otherwise be counted as part of each line.
+            if clipboard_text.contains('\r') {
+                let mut text = clipboard_text.into_owned();
+                LineEnding::normalize(&mut text);
+                clipboard_text = Cow::Owned(text);
+            }
+            clipboard_selections =
+                ClipboardSelection::for_lines(&clipboard_text, self.selections.count());
+        }
+
+        if clipboard_text.len() > LARGE_PASTE_THRESHOLD && self.selections.count() == 1 {
+            let entire_line = clipboard_selections.as_ref().map_or(false, |selections| {
+                selections.iter().all(|s| s.is_entire_line)
+            });
+            let original_indent_column = clipboard_selections
+                .as_ref()
+                .and_then(|selections| Some(selections.first()?.first_line_indent));
+            let selection = self.selections.newest::<usize>(cx);
+            let snapshot = self.buffer.read(cx).snapshot(cx);
+            let range = paste_range(
+                &selection,
+                entire_line,
+                self.selections.line_mode,
+                &snapshot,
+            );
+            large_paste::paste_in_chunks(
+                self,
+                range,
+                clipboard_text.into_owned(),
+                auto_indent,
+                original_indent_column,
+                cx,
+            );
+            return;
+        }
 
-                            // If the corresponding selection was empty when this slice of the
-                            // clipboard text was written, then the entire line containing the
-                            // selection was copied. If this selection is also currently empty,
-                            // then paste the line before the current line of the buffer.
-                            let range = if selection.is_empty() && !line_mode && entire_line {
-                                let column = selection.start.to_point(&snapshot).column as usize;
-                                let line_start = selection.start - column;
-                                line_start..line_start
-                            } else {
-                                selection.range()
-                            };
+        self.transact(cx, |this, cx| {
+            if let Some(mut clipboard_selections) = clipboard_selections {
+                let old_selections = this.selections.all::<usize>(cx);
+                let all_selections_were_entire_line =
+                    clipboard_selections.iter().all(|s| s.is_entire_line);
+                let first_selection_indent_column =
+                    clipboard_selections.first().map(|s| s.first_line_indent);
+                if clipboard_selections.len() != old_selections.len() {
+                    clipboard_selections.drain(..);
+                }
 
-                            edits.push((range, to_insert));
-                            original_indent_columns.extend(original_indent_column);
+                this.buffer.update(cx, |buffer, cx| {
+                    let snapshot = buffer.read(cx);
+                    let mut start_offset = 0;
+                    let mut edits = Vec::new();
+                    let mut original_indent_columns = Vec::new();
+                    let line_mode = this.selections.line_mode;
+                    for (ix, selection) in old_selections.iter().enumerate() {
+                        let to_insert;
+                        let entire_line;
+                        let original_indent_column;
+                        if let Some(clipboard_selection) = clipboard_selections.get(ix) {
+                            let end_offset = start_offset + clipboard_selection.len;
+                            to_insert = &clipboard_text[start_offset..end_offset];
+                            entire_line = clipboard_selection.is_entire_line;
+                            start_offset = end_offset + 1;
+                            original_indent_column = Some(clipboard_selection.first_line_indent);
+                        } else {
+                            to_insert = clipboard_text.as_str();
+                            entire_line = all_selections_were_entire_line;
+                            original_indent_column = first_selection_indent_column
                         }
-                        drop(snapshot);
 
-                        buffer.edit(
-                            edits,
-                            Some(AutoindentMode::Block {
-                                original_indent_columns,
-                            }),
-                            cx,
-                        );
+                        let range = paste_range(selection, entire_line, line_mode, &snapshot);
+                        edits.push((range, to_insert));
+                        original_indent_columns.extend(original_indent_column);
+                    }
+                    drop(snapshot);
+
+                    let autoindent_mode = auto_indent.then(|| AutoindentMode::Block {
+                        original_indent_columns,
                     });
+                    buffer.edit(edits, autoindent_mode, cx);
+                });
 
-                    let selections = this.selections.all::<usize>(cx);
-                    this.change_selections(Some(Autoscroll::fit()), cx, |s| s.select(selections));
-                } else {
-                    this.insert(&clipboard_text, cx);
-                }
+                let selections = this.selections.all::<usize>(cx);
+                this.change_selections(Some(Autoscroll::fit()), cx, |s| s.select(selections));
+            } else if auto_indent {
+                // The pasted lines keep their indentation relative to the first one, which is
+                // indented to match the line it's pasted into.
+                this.insert_with_autoindent_mode(
+                    &clipboard_text,
+                    Some(AutoindentMode::Block {
+                        original_indent_columns: Vec::new(),
+                    }),
+                    cx,
+                );
+            } else {
+                this.insert_with_autoindent_mode(&clipboard_text, None, cx);
             }
         });
     }
 
+    fn auto_indent_on_paste(&self, cx: &AppContext) -> bool {
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let cursor = self.selections.newest_anchor().head();
+        snapshot.settings_at(cursor, cx).auto_indent_on_paste
+    }
+
     pub fn undo(&mut self, _: &Undo, cx: &mut ViewContext<Self>) {
         if self.read_only(cx) {
             return;
@@ -5402,6 +6311,31 @@ impl Editor {
         }
     }
 
+    /// Undoes the most recent edits that touch the selections, leaving the edits elsewhere in the
+    /// buffer applied. The selections are left in place.
+    pub fn undo_in_selection(&mut self, _: &UndoInSelection, cx: &mut ViewContext<Self>) {
+        if self.read_only(cx) {
+            return;
+        }
+
+        let ranges = self
+            .selections
+            .all::<usize>(cx)
+            .into_iter()
+            .map(|selection| selection.range())
+            .collect::<Vec<_>>();
+        if self
+            .buffer
+            .update(cx, |buffer, cx| buffer.undo_in_ranges(&ranges, cx))
+            .is_some()
+        {
+            self.request_autoscroll(Autoscroll::fit(), cx);
+            self.unmark_text(cx);
+            self.refresh_copilot_suggestions(true, cx);
+            cx.emit(EditorEvent::Edited);
+        }
+    }
+
     pub fn redo(&mut self, _: &Redo, cx: &mut ViewContext<Self>) {
         if self.read_only(cx) {
             return;
@@ -6180,6 +7114,20 @@ impl Editor {
         });
     }
 
+    pub fn select_all_in_excerpt(&mut self, _: &SelectAllInExcerpt, cx: &mut ViewContext<Self>) {
+        let head = self.selections.newest::<usize>(cx).head();
+        let buffer = self.buffer.read(cx);
+        let Some((excerpt_id, _, range)) = buffer.excerpt_containing(head, cx) else {
+            return;
+        };
+        let snapshot = buffer.snapshot(cx);
+        let start = snapshot.anchor_in_excerpt(excerpt_id, range.start);
+        let end = snapshot.anchor_in_excerpt(excerpt_id, range.end);
+        self.change_selections(None, cx, |s| {
+            s.select_anchor_ranges([start..end]);
+        });
+    }
+
     pub fn select_line(&mut self, _: &SelectLine, cx: &mut ViewContext<Self>) {
         let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
         let mut selections = self.selections.all::<Point>(cx);
@@ -6220,6 +7168,112 @@ impl Editor {
         });
     }
 
+    pub fn add_cursors_to_line_ends(
+        &mut self,
+        _: &AddCursorsToLineEnds,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.add_cursors_to_lines(|buffer, row| Point::new(row, buffer.line_len(row)), cx);
+    }
+
+    pub fn add_cursors_to_line_starts(
+        &mut self,
+        _: &AddCursorsToLineStarts,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.add_cursors_to_lines(|_, row| Point::new(row, 0), cx);
+    }
+
+    /// Replaces each selection with a cursor on each of the lines it spans, at the position
+    /// returned by `position` for that line.
+    fn add_cursors_to_lines(
+        &mut self,
+        position: impl Fn(&MultiBufferSnapshot, u32) -> Point,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let buffer = self.buffer.read(cx).snapshot(cx);
+        let mut to_unfold = Vec::new();
+        let mut new_selection_ranges = Vec::new();
+        for selection in self.selections.all::<Point>(cx) {
+            for row in selection.start.row..=selection_end_row(&selection) {
+                let cursor = position(&buffer, row);
+                new_selection_ranges.push(cursor..cursor);
+            }
+            to_unfold.push(selection.start..selection.end);
+        }
+        self.unfold_ranges(to_unfold, true, true, cx);
+        self.change_selections(Some(Autoscroll::fit()), cx, |s| {
+            s.select_ranges(new_selection_ranges);
+        });
+    }
+
+    /// Replaces each selection with a cursor on each of the lines it spans, at the visual column
+    /// of the selection's head. Cursors on lines shorter than that are placed at the end of the
+    /// line, or at the column after padding the line with spaces if `pad_short_lines` is set, and
+    /// keep the column as their goal when moving vertically.
+    pub fn add_cursors_at_column(
+        &mut self,
+        action: &AddCursorsAtColumn,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let pad_short_lines = action.pad_short_lines && !self.read_only(cx);
+        let selections = self.selections.all::<Point>(cx);
+        self.unfold_ranges(
+            selections
+                .iter()
+                .map(|selection| selection.start..selection.end),
+            true,
+            true,
+            cx,
+        );
+
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        let text_layout_details = self.text_layout_details(cx);
+        let mut cursors = BTreeMap::new();
+        for selection in &selections {
+            let head = selection.head().to_display_point(&display_map);
+            let x = display_map.x_for_display_point(head, &text_layout_details);
+            for row in selection.start.row..=selection_end_row(selection) {
+                let display_row = Point::new(row, 0).to_display_point(&display_map).row();
+                let line_len = display_map.line_len(display_row);
+                let point = display_map
+                    .clip_point(
+                        DisplayPoint::new(display_row, head.column().min(line_len)),
+                        Bias::Left,
+                    )
+                    .to_point(&display_map);
+                let padding = if pad_short_lines {
+                    head.column().saturating_sub(line_len)
+                } else {
+                    0
+                };
+                cursors.entry(row).or_insert((point, padding, x));
+            }
+        }
+
+        let edits = cursors
+            .values()
+            .filter(|(_, padding, _)| *padding > 0)
+            .map(|(point, padding, _)| (*point..*point, " ".repeat(*padding as usize)))
+            .collect::<Vec<_>>();
+        self.transact(cx, |this, cx| {
+            this.buffer
+                .update(cx, |buffer, cx| buffer.edit(edits, None, cx));
+            this.change_selections(Some(Autoscroll::fit()), cx, |s| {
+                s.select_ranges(cursors.values().map(|(point, padding, _)| {
+                    let cursor = Point::new(point.row, point.column + padding);
+                    cursor..cursor
+                }));
+                s.move_with(|map, selection| {
+                    let row = selection.head().to_point(map).row;
+                    if let Some((_, _, x)) = cursors.get(&row) {
+                        selection.goal = SelectionGoal::HorizontalPosition(x.0);
+                    }
+                });
+            });
+        });
+    }
+
     pub fn add_selection_above(&mut self, _: &AddSelectionAbove, cx: &mut ViewContext<Self>) {
         self.add_selection(true, cx);
     }
@@ -6581,6 +7635,88 @@ impl Editor {
         Ok(())
     }
 
+    /// Removes the most recently added selection, keeping the other ones, so that a cursor added
+    /// by mistake can be backed out of without cancelling all of them.
+    pub fn undo_last_selection_addition(
+        &mut self,
+        _: &UndoLastSelectionAddition,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if self.selections.count() < 2 {
+            return;
+        }
+
+        self.push_to_selection_history();
+        // The removed selection's match can be selected again by `SelectNext`.
+        let select_next_state = self.select_next_state.take().map(|mut state| {
+            state.done = false;
+            state
+        });
+        let newest_id = self.selections.newest_anchor().id;
+        self.change_selections(Some(Autoscroll::newest()), cx, |s| s.delete(newest_id));
+        self.select_next_state = select_next_state;
+    }
+
+    /// Selects every occurrence of the text of each selection, or of the word under each cursor,
+    /// even when the selections don't all contain the same text.
+    pub fn select_all_occurrences_of_each_cursor(
+        &mut self,
+        _: &SelectAllOccurrencesOfEachCursor,
+        cx: &mut ViewContext<Self>,
+    ) -> Result<()> {
+        self.push_to_selection_history();
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        let buffer = &display_map.buffer_snapshot;
+
+        let mut queries = Vec::<(String, bool)>::new();
+        for selection in self.selections.all::<usize>(cx) {
+            let (range, wordwise) = if selection.is_empty() {
+                let word_range = movement::surrounding_word(
+                    &display_map,
+                    selection.start.to_display_point(&display_map),
+                );
+                (
+                    word_range.start.to_offset(&display_map, Bias::Left)
+                        ..word_range.end.to_offset(&display_map, Bias::Left),
+                    true,
+                )
+            } else {
+                (selection.range(), false)
+            };
+            let query = (buffer.text_for_range(range).collect::<String>(), wordwise);
+            if !query.0.is_empty() && !queries.contains(&query) {
+                queries.push(query);
+            }
+        }
+
+        let mut new_ranges = Vec::new();
+        for (query, wordwise) in queries {
+            let query = AhoCorasick::new(&[query])?;
+            for query_match in query.stream_find_iter(buffer.bytes_in_range(0..buffer.len())) {
+                let query_match = query_match.unwrap(); // can only fail due to I/O
+                let offset_range = query_match.start()..query_match.end();
+                if !wordwise
+                    || (!movement::is_inside_word(
+                        &display_map,
+                        offset_range.start.to_display_point(&display_map),
+                    ) && !movement::is_inside_word(
+                        &display_map,
+                        offset_range.end.to_display_point(&display_map),
+                    ))
+                {
+                    new_ranges.push(offset_range);
+                }
+            }
+        }
+        if new_ranges.is_empty() {
+            return Ok(());
+        }
+
+        self.unfold_ranges(new_ranges.iter().cloned(), false, false, cx);
+        self.change_selections(Some(Autoscroll::fit()), cx, |s| s.select_ranges(new_ranges));
+        Ok(())
+    }
+
     pub fn select_previous(
         &mut self,
         action: &SelectPrevious,
@@ -6724,6 +7860,10 @@ impl Editor {
     }
 
     pub fn toggle_comments(&mut self, action: &ToggleComments, cx: &mut ViewContext<Self>) {
+        if self.read_only(cx) {
+            return;
+        }
+
         let text_layout_details = &self.text_layout_details(cx);
         self.transact(cx, |this, cx| {
             let mut selections = this.selections.all::<Point>(cx);
@@ -6991,7 +8131,7 @@ impl Editor {
         let buffer = self.buffer.read(cx).snapshot(cx);
         let old_selections = self.selections.all::<usize>(cx).into_boxed_slice();
 
-        let mut stack = mem::take(&mut self.select_larger_syntax_node_stack);
+        let mut history = mem::take(&mut self.select_larger_syntax_node_history);
         let mut selected_larger_node = false;
         let new_selections = old_selections
             .iter()
@@ -7021,12 +8161,20 @@ impl Editor {
             .collect::<Vec<_>>();
 
         if selected_larger_node {
-            stack.push(old_selections);
+            history
+                .stack
+                .push(self.selections.disjoint_anchors().to_vec().into());
+            history.ranges = new_selections
+                .iter()
+                .map(|selection| {
+                    buffer.anchor_before(selection.start)..buffer.anchor_after(selection.end)
+                })
+                .collect();
             self.change_selections(Some(Autoscroll::fit()), cx, |s| {
                 s.select(new_selections);
             });
         }
-        self.select_larger_syntax_node_stack = stack;
+        self.select_larger_syntax_node_history = history;
     }
 
     pub fn select_smaller_syntax_node(
@@ -7034,13 +8182,66 @@ impl Editor {
         _: &SelectSmallerSyntaxNode,
         cx: &mut ViewContext<Self>,
     ) {
-        let mut stack = mem::take(&mut self.select_larger_syntax_node_stack);
-        if let Some(selections) = stack.pop() {
+        let mut history = mem::take(&mut self.select_larger_syntax_node_history);
+        if let Some(selections) = history.stack.pop() {
+            history.ranges = selections
+                .iter()
+                .map(|selection| selection.start..selection.end)
+                .collect();
+            self.change_selections(Some(Autoscroll::fit()), cx, |s| {
+                s.select_anchors(selections.to_vec());
+            });
+        }
+        self.select_larger_syntax_node_history = history;
+    }
+
+    /// Selects the next named syntax node that is a sibling of the node enclosing each selection.
+    pub fn select_syntax_node_sibling_next(
+        &mut self,
+        _: &SelectSyntaxNodeSiblingNext,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.select_syntax_node_sibling(true, cx);
+    }
+
+    /// Selects the previous named syntax node that is a sibling of the node enclosing each
+    /// selection.
+    pub fn select_syntax_node_sibling_prev(
+        &mut self,
+        _: &SelectSyntaxNodeSiblingPrev,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.select_syntax_node_sibling(false, cx);
+    }
+
+    fn select_syntax_node_sibling(&mut self, next: bool, cx: &mut ViewContext<Self>) {
+        let buffer = self.buffer.read(cx).snapshot(cx);
+        let mut selected_sibling = false;
+        let new_selections = self
+            .selections
+            .all::<usize>(cx)
+            .into_iter()
+            .map(|selection| {
+                let Some((_, sibling)) = buffer.range_for_syntax_sibling(selection.range(), next)
+                else {
+                    return selection;
+                };
+                selected_sibling = true;
+                Selection {
+                    id: selection.id,
+                    start: sibling.start,
+                    end: sibling.end,
+                    goal: SelectionGoal::None,
+                    reversed: selection.reversed,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if selected_sibling {
             self.change_selections(Some(Autoscroll::fit()), cx, |s| {
-                s.select(selections.to_vec());
+                s.select(new_selections);
             });
         }
-        self.select_larger_syntax_node_stack = stack;
     }
 
     pub fn move_to_enclosing_bracket(
@@ -7280,6 +8481,46 @@ impl Editor {
         }
     }
 
+    pub fn go_to_next_excerpt(&mut self, _: &GoToNextExcerpt, cx: &mut ViewContext<Self>) {
+        self.go_to_excerpt_in_direction(Direction::Next, cx);
+    }
+
+    pub fn go_to_prev_excerpt(&mut self, _: &GoToPrevExcerpt, cx: &mut ViewContext<Self>) {
+        self.go_to_excerpt_in_direction(Direction::Prev, cx);
+    }
+
+    fn go_to_excerpt_in_direction(&mut self, direction: Direction, cx: &mut ViewContext<Self>) {
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let excerpt_starts = snapshot
+            .excerpts()
+            .map(|(excerpt_id, _, range)| {
+                snapshot
+                    .anchor_in_excerpt(excerpt_id, range.context.start)
+                    .to_offset(&snapshot)
+            })
+            .collect::<Vec<_>>();
+        if excerpt_starts.len() < 2 {
+            cx.propagate();
+            return;
+        }
+
+        let head = self.selections.newest::<usize>(cx).head();
+        let current_ix = excerpt_starts
+            .iter()
+            .rposition(|start| *start <= head)
+            .unwrap_or(0);
+        let target_ix = match direction {
+            Direction::Prev => current_ix.checked_sub(1),
+            Direction::Next => Some(current_ix + 1).filter(|ix| *ix < excerpt_starts.len()),
+        };
+        if let Some(target_ix) = target_ix {
+            let offset = excerpt_starts[target_ix];
+            self.change_selections(Some(Autoscroll::focused()), cx, |s| {
+                s.select_ranges([offset..offset])
+            });
+        }
+    }
+
     fn seek_in_direction(
         &mut self,
         snapshot: &DisplaySnapshot,
@@ -7673,6 +8914,10 @@ impl Editor {
     pub fn rename(&mut self, _: &Rename, cx: &mut ViewContext<Self>) -> Option<Task<Result<()>>> {
         use language::ToOffset as _;
 
+        if self.read_only(cx) {
+            return None;
+        }
+
         let project = self.project.clone()?;
         let selection = self.selections.newest_anchor().clone();
         let (cursor_buffer, cursor_buffer_position) = self
@@ -7924,6 +9169,10 @@ impl Editor {
     }
 
     fn format(&mut self, _: &Format, cx: &mut ViewContext<Self>) -> Option<Task<Result<()>>> {
+        if self.read_only(cx) {
+            return None;
+        }
+
         let project = match &self.project {
             Some(project) => project.clone(),
             None => return None,
@@ -7938,6 +9187,7 @@ impl Editor {
         trigger: FormatTrigger,
         cx: &mut ViewContext<Self>,
     ) -> Task<Result<()>> {
+        self.record_restore_point(RestorePointKind::Format, None, cx);
         let buffer = self.buffer().clone();
         let buffers = buffer.read(cx).all_buffers();
 
@@ -8093,6 +9343,15 @@ impl Editor {
         self.selections_did_change(false, &old_cursor_position, cx);
     }
 
+    /// Replaces the folds with the ones of the collaborator being followed.
+    pub fn set_folds_from_remote(&mut self, folds: Vec<Range<Anchor>>, cx: &mut ViewContext<Self>) {
+        self.display_map.update(cx, |map, cx| {
+            map.unfold([Anchor::min()..Anchor::max()], true, cx);
+            map.fold(folds, cx);
+        });
+        cx.notify();
+    }
+
     fn push_to_selection_history(&mut self) {
         self.selection_history.push(SelectionHistoryEntry {
             selections: self.selections.disjoint_anchors(),
@@ -8138,6 +9397,7 @@ impl Editor {
                 log::error!("unexpectedly ended a transaction that wasn't started by this editor");
             }
 
+            self.record_edit_location(cx);
             cx.emit(EditorEvent::Edited);
             Some(tx_id)
         } else {
@@ -8176,7 +9436,7 @@ impl Editor {
         let buffer_row = fold_at.buffer_row;
         let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
 
-        if let Some(fold_range) = display_map.foldable_range(buffer_row) {
+        if let Some(fold_range) = Self::gutter_foldable_range(&display_map, buffer_row, cx) {
             let autoscroll = self
                 .selections
                 .all::<Point>(cx)
@@ -8187,6 +9447,46 @@ impl Editor {
         }
     }
 
+    /// Folds the range starting on the given row, along with its siblings and all of the ranges
+    /// nested inside of them.
+    pub fn fold_recursively_at(&mut self, buffer_row: u32, cx: &mut ViewContext<Self>) {
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        let foldable_range = |row: u32| Self::gutter_foldable_range(&display_map, row, cx);
+        let Some(fold_range) = foldable_range(buffer_row) else {
+            return;
+        };
+
+        // The siblings are the ranges inside of the innermost range enclosing this one.
+        let parent_rows = (0..buffer_row)
+            .rev()
+            .find_map(|row| {
+                foldable_range(row)
+                    .filter(|parent| parent.end >= fold_range.end)
+                    .map(|parent| parent.start.row + 1..parent.end.row + 1)
+            })
+            .unwrap_or(0..display_map.buffer_snapshot.max_point().row + 1);
+        let fold_ranges = parent_rows.filter_map(&foldable_range).collect::<Vec<_>>();
+
+        let autoscroll = self.selections.all::<Point>(cx).iter().any(|selection| {
+            fold_ranges
+                .iter()
+                .any(|fold_range| fold_range.overlaps(&selection.range()))
+        });
+        self.fold_ranges(fold_ranges, autoscroll, cx);
+    }
+
+    /// Returns the range to fold when clicking the fold indicator of the given row.
+    fn gutter_foldable_range(
+        display_map: &DisplaySnapshot,
+        buffer_row: u32,
+        cx: &AppContext,
+    ) -> Option<Range<Point>> {
+        match EditorSettings::get_global(cx).fold_indicators {
+            FoldIndicators::Indentation => display_map.foldable_range(buffer_row),
+            FoldIndicators::Syntax => display_map.syntax_foldable_range(buffer_row),
+        }
+    }
+
     pub fn unfold_lines(&mut self, _: &UnfoldLines, cx: &mut ViewContext<Self>) {
         let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
         let buffer = &display_map.buffer_snapshot;
@@ -8254,6 +9554,7 @@ impl Editor {
                 self.request_autoscroll(Autoscroll::fit(), cx);
             }
 
+            cx.emit(EditorEvent::FoldsChanged);
             cx.notify();
         }
     }
@@ -8273,6 +9574,7 @@ impl Editor {
                 self.request_autoscroll(Autoscroll::fit(), cx);
             }
 
+            cx.emit(EditorEvent::FoldsChanged);
             cx.notify();
         }
     }
@@ -8312,6 +9614,20 @@ impl Editor {
         }
     }
 
+    pub fn resize_blocks(
+        &mut self,
+        heights: HashMap<BlockId, u8>,
+        autoscroll: Option<Autoscroll>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.display_map
+            .update(cx, |display_map, cx| display_map.resize_blocks(heights, cx));
+        if let Some(autoscroll) = autoscroll {
+            self.request_autoscroll(autoscroll, cx);
+        }
+        cx.notify();
+    }
+
     pub fn remove_blocks(
         &mut self,
         block_ids: HashSet<BlockId>,
@@ -8369,17 +9685,19 @@ impl Editor {
             .text()
     }
 
-    pub fn wrap_guides(&self, cx: &AppContext) -> SmallVec<[(usize, bool); 2]> {
+    /// Returns the wrap guides to show, and whether each is the active guide at the soft wrap
+    /// column.
+    pub fn wrap_guides(&self, cx: &AppContext) -> SmallVec<[(WrapGuide, bool); 2]> {
         let mut wrap_guides = smallvec::smallvec![];
 
-        if self.show_wrap_guides == Some(false) {
+        if self.show_wrap_guides == Some(false) || self.prose_settings(cx).is_some() {
             return wrap_guides;
         }
 
         let settings = self.buffer.read(cx).settings_at(0, cx);
         if settings.show_wrap_guides {
             if let SoftWrap::Column(soft_wrap) = self.soft_wrap_mode(cx) {
-                wrap_guides.push((soft_wrap as usize, true));
+                wrap_guides.push((WrapGuide::Column(soft_wrap as usize), true));
             }
             wrap_guides.extend(settings.wrap_guides.iter().map(|guide| (*guide, false)))
         }
@@ -8387,7 +9705,23 @@ impl Editor {
         wrap_guides
     }
 
+    /// Returns the number of columns per indentation level if indent guides should be shown.
+    pub fn indent_guides_tab_size(&self, cx: &AppContext) -> Option<u32> {
+        if self.mode != EditorMode::Full || self.prose_settings(cx).is_some() {
+            return None;
+        }
+
+        let settings = self.buffer.read(cx).settings_at(0, cx);
+        settings.show_indent_guides.then(|| settings.tab_size.get())
+    }
+
     pub fn soft_wrap_mode(&self, cx: &AppContext) -> SoftWrap {
+        if self.soft_wrap_mode_override.is_none() {
+            if let Some(prose) = self.prose_settings(cx) {
+                return SoftWrap::Column(prose.measure);
+            }
+        }
+
         let settings = self.buffer.read(cx).settings_at(0, cx);
         let mode = self
             .soft_wrap_mode_override
@@ -8401,6 +9735,70 @@ impl Editor {
         }
     }
 
+    /// Returns the language settings that determine how this editor's buffer is styled, which
+    /// only apply to full editors of a single buffer.
+    fn buffer_language_settings<'a>(&self, cx: &'a AppContext) -> Option<&'a LanguageSettings> {
+        let buffer = self.buffer.read(cx);
+        if self.mode != EditorMode::Full || !buffer.is_singleton() {
+            return None;
+        }
+        Some(buffer.settings_at(0, cx))
+    }
+
+    /// Returns the prose settings for this editor's buffer, if it should be rendered as prose.
+    pub fn prose_settings(&self, cx: &AppContext) -> Option<ProseSettings> {
+        let prose = self.buffer_language_settings(cx)?.prose;
+        prose.enabled.then_some(prose)
+    }
+
+    /// Returns the font family of the buffer, taking its language's override into account.
+    pub fn buffer_font_family(&self, cx: &AppContext) -> SharedString {
+        self.buffer_language_settings(cx)
+            .and_then(|settings| settings.font_family.clone())
+            .map(SharedString::from)
+            .unwrap_or_else(|| ThemeSettings::get_global(cx).buffer_font.family.clone())
+    }
+
+    /// Returns the font features of the buffer, taking its language's overrides and the ligature
+    /// toggle into account.
+    pub fn buffer_font_features(&self, cx: &AppContext) -> FontFeatures {
+        let mut features = self
+            .buffer_language_settings(cx)
+            .and_then(|settings| settings.font_features)
+            .unwrap_or(ThemeSettings::get_global(cx).buffer_font.features);
+        if let Some(ligatures) = self.ligatures_override {
+            features.set_ligatures(ligatures);
+        }
+        features
+    }
+
+    pub fn toggle_ligatures(&mut self, _: &ToggleLigatures, cx: &mut ViewContext<Self>) {
+        self.ligatures_override = Some(!self.buffer_font_features(cx).ligatures());
+        cx.notify();
+    }
+
+    /// Returns the line height of the buffer, as a multiple of its font size.
+    pub fn buffer_line_height(&self, cx: &AppContext) -> f32 {
+        if let Some(prose) = self.prose_settings(cx) {
+            return prose.line_height;
+        }
+        self.buffer_language_settings(cx)
+            .and_then(|settings| settings.line_height)
+            .unwrap_or_else(|| ThemeSettings::get_global(cx).buffer_line_height.value())
+    }
+
+    /// Returns the number of empty rows to leave after each paragraph of this editor's buffer.
+    pub(crate) fn paragraph_spacing(&self, cx: &AppContext) -> u32 {
+        if self.large_file_mode {
+            return 0;
+        }
+        let Some(settings) = self.buffer_language_settings(cx) else {
+            return 0;
+        };
+        let prose_rows = (settings.prose.enabled && settings.prose.paragraph_spacing) as u32;
+        settings.paragraph_spacing.max(prose_rows)
+    }
+
     pub fn set_soft_wrap_mode(
         &mut self,
         mode: language_settings::SoftWrap,
@@ -8410,6 +9808,23 @@ impl Editor {
         cx.notify();
     }
 
+    /// Updates how the rows continuing soft-wrapped lines are indented, leaving a column before
+    /// their text for the soft wrap indicator when it is shown.
+    fn refresh_wrap_indent(&mut self, cx: &mut ViewContext<Self>) {
+        let settings = EditorSettings::get_global(cx);
+        let show_indicator = self.mode == EditorMode::Full && settings.soft_wrap_indicator;
+        let extra_indent = match settings.wrapped_line_indent {
+            WrappedLineIndent::None | WrappedLineIndent::Match => 0,
+            WrappedLineIndent::Extra => EXTRA_WRAPPED_LINE_INDENT,
+        };
+        let indent = WrapIndent {
+            match_line: settings.wrapped_line_indent != WrappedLineIndent::None,
+            extra: extra_indent + show_indicator as u32,
+        };
+        self.display_map
+            .update(cx, |map, cx| map.set_wrap_indent(indent, cx));
+    }
+
     pub fn set_style(&mut self, style: EditorStyle, cx: &mut ViewContext<Self>) {
         let rem_size = cx.rem_size();
         self.display_map.update(cx, |map, cx| {
@@ -8447,6 +9862,45 @@ impl Editor {
         cx.notify();
     }
 
+    pub fn toggle_inline_diagnostics(
+        &mut self,
+        _: &ToggleInlineDiagnostics,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.show_inline_diagnostics = Some(!self.inline_diagnostics_enabled(cx));
+        cx.notify();
+    }
+
+    /// Whether to show diagnostic messages after the ends of the lines they're on.
+    pub fn inline_diagnostics_enabled(&self, cx: &AppContext) -> bool {
+        self.mode == EditorMode::Full
+            && self
+                .show_inline_diagnostics
+                .unwrap_or_else(|| EditorSettings::get_global(cx).inline_diagnostics)
+    }
+
+    /// How often the lines of the visible rows were already shaped when the editor was laid out,
+    /// for tuning the `overscan_rows` setting.
+    pub fn line_layout_stats(&self) -> LineLayoutStats {
+        self.line_layout_cache.stats()
+    }
+
+    /// Sets whether the layouts of the rows are kept between frames, which is only turned off to
+    /// measure the time they save.
+    #[doc(hidden)]
+    pub fn set_layout_caching(&mut self, layout_caching: bool, cx: &mut ViewContext<Self>) {
+        self.layout_caching = layout_caching;
+        cx.notify();
+    }
+
+    /// Sets whether the filled rectangles that continue each other are painted as a single quad,
+    /// which is only turned off to measure the time it saves.
+    #[doc(hidden)]
+    pub fn set_quad_merging(&mut self, quad_merging: bool, cx: &mut ViewContext<Self>) {
+        self.quad_merging = quad_merging;
+        cx.notify();
+    }
+
     pub fn set_show_gutter(&mut self, show_gutter: bool, cx: &mut ViewContext<Self>) {
         self.show_gutter = show_gutter;
         cx.notify();
@@ -8457,6 +9911,191 @@ impl Editor {
         cx.notify();
     }
 
+    pub fn print(&mut self, _: &Print, cx: &mut ViewContext<Self>) {
+        let Some(style) = self.style.as_ref() else {
+            return;
+        };
+
+        let font = style.text.font();
+        let font_size = px(print::PRINT_FONT_SIZE);
+        let line_height = style
+            .text
+            .line_height
+            .to_pixels(font_size.into(), cx.rem_size())
+            .round();
+        let text_system = cx.text_system().clone();
+        let font_id = text_system.resolve_font(&font);
+        let Some(em_advance) = text_system
+            .advance(font_id, font_size, 'm')
+            .log_err()
+            .map(|advance| advance.width)
+        else {
+            return;
+        };
+
+        let buffer = self.buffer.read(cx);
+        let title = buffer.title(cx).to_string();
+        let text = buffer.snapshot(cx).text();
+        let lines = text.lines().collect::<Vec<_>>();
+        let printable_size = cx
+            .printable_page_size()
+            .unwrap_or_else(print::default_printable_page_size);
+        let (lines_per_page, body_width) = print::page_body_dimensions(printable_size, line_height);
+        let gutter_width = em_advance * (lines.len().to_string().len() + 2) as f32;
+        let mut wrapper = text_system.line_wrapper(font.clone(), font_size);
+        let pages = print::paginate(&title, lines.into_iter(), lines_per_page, |line| {
+            wrapper
+                .wrap_line(line, body_width - gutter_width)
+                .map(|boundary| boundary.ix)
+                .collect()
+        });
+
+        cx.print_document(PrintDocument {
+            title,
+            font_family: font.family,
+            font_size: print::PRINT_FONT_SIZE,
+            line_height: line_height.into(),
+            pages,
+        });
+    }
+
+    pub fn screenshot_selection(
+        &mut self,
+        action: &ScreenshotSelection,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(svg) = self.render_selection_svg(action, cx) else {
+            return;
+        };
+
+        let directory = self
+            .buffer
+            .read(cx)
+            .as_singleton()
+            .and_then(|buffer| {
+                let file = buffer.read(cx).file()?.as_local()?;
+                file.abs_path(cx).parent().map(Path::to_path_buf)
+            })
+            .unwrap_or_else(|| util::paths::HOME.to_path_buf());
+        let path = cx.prompt_for_new_path(&directory);
+        let format = action.format;
+        let scale_factor = cx.scale_factor();
+        cx.background_executor()
+            .spawn(async move {
+                let Some(path) = path.await? else {
+                    return Ok(());
+                };
+                match format {
+                    ScreenshotFormat::Svg => smol::fs::write(path, svg).await?,
+                    ScreenshotFormat::Png => {
+                        let png = gpui::render_svg_to_png(&svg, scale_factor)?;
+                        smol::fs::write(path, png).await?
+                    }
+                }
+                anyhow::Ok(())
+            })
+            .detach_and_log_err(cx);
+    }
+
+    /// Renders the lines of the newest selection, or the whole buffer if it's empty, into an SVG
+    /// document, laying them out the same way as on screen.
+    fn render_selection_svg(
+        &mut self,
+        action: &ScreenshotSelection,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<String> {
+        let style = self.style.clone()?;
+        let snapshot = self.snapshot(cx);
+        let selection = self.selections.newest_display(cx);
+        let rows = if selection.is_empty() {
+            0..snapshot.max_point().row() + 1
+        } else if selection.end.column() == 0 && selection.end.row() > selection.start.row() {
+            selection.start.row()..selection.end.row()
+        } else {
+            selection.start.row()..selection.end.row() + 1
+        };
+        let lines = screenshot::layout_lines(&snapshot, rows, &style, cx);
+
+        let font = style.text.font();
+        let font_size = style.text.font_size.to_pixels(cx.rem_size());
+        let text_system = cx.text_system();
+        let font_id = text_system.resolve_font(&font);
+        Some(screenshot::render_svg(
+            &lines,
+            &ScreenshotStyle {
+                title: self.buffer.read(cx).title(cx).to_string(),
+                font_family: font.family.to_string(),
+                font_size,
+                line_height: style.text.line_height_in_pixels(cx.rem_size()),
+                ascent: text_system.ascent(font_id, font_size),
+                background: style.background,
+                foreground: style.text.color,
+                padding: px(action.padding.unwrap_or(DEFAULT_SCREENSHOT_PADDING)),
+                window_chrome: action.window_chrome,
+            },
+        ))
+    }
+
+    pub fn increase_font_size(&mut self, _: &IncreaseFontSize, cx: &mut ViewContext<Self>) {
+        self.adjust_font_size(px(1.), cx);
+    }
+
+    pub fn decrease_font_size(&mut self, _: &DecreaseFontSize, cx: &mut ViewContext<Self>) {
+        self.adjust_font_size(px(-1.), cx);
+    }
+
+    pub fn reset_font_size(&mut self, _: &ResetFontSize, cx: &mut ViewContext<Self>) {
+        self.set_font_size_adjustment(px(0.), cx);
+    }
+
+    fn adjust_font_size(&mut self, delta: Pixels, cx: &mut ViewContext<Self>) {
+        let base_size = self.base_buffer_font_size(cx);
+        let font_size = (self.buffer_font_size(cx) + delta).max(MIN_FONT_SIZE);
+        self.set_font_size_adjustment(font_size - base_size, cx);
+    }
+
+    pub fn set_font_size_adjustment(&mut self, adjustment: Pixels, cx: &mut ViewContext<Self>) {
+        if self.font_size_adjustment == adjustment {
+            return;
+        }
+        self.font_size_adjustment = adjustment;
+        cx.notify();
+
+        if let Some((_, workspace_id)) = self.workspace.as_ref() {
+            let workspace_id = *workspace_id;
+            let item_id = cx.view().item_id().as_u64() as ItemId;
+            let adjustment = f32::from(adjustment);
+            cx.background_executor()
+                .spawn(async move {
+                    DB.save_font_size_adjustment(item_id, workspace_id, adjustment)
+                        .await
+                        .log_err()
+                })
+                .detach();
+        }
+    }
+
+    /// The buffer font size used by this editor, including its own adjustment
+    /// on top of the global buffer font size.
+    pub fn buffer_font_size(&self, cx: &AppContext) -> Pixels {
+        (self.base_buffer_font_size(cx) + self.font_size_adjustment).max(MIN_FONT_SIZE)
+    }
+
+    /// The buffer font size before this editor's own adjustment, which is the
+    /// language's font size if it overrides it. Global font size adjustments
+    /// still apply on top of a language's font size.
+    fn base_buffer_font_size(&self, cx: &AppContext) -> Pixels {
+        let theme_settings = ThemeSettings::get_global(cx);
+        let global_size = theme_settings.buffer_font_size(cx);
+        match self
+            .buffer_language_settings(cx)
+            .and_then(|settings| settings.font_size)
+        {
+            Some(size) => px(size) + (global_size - theme_settings.buffer_font_size),
+            None => global_size,
+        }
+    }
+
     pub fn reveal_in_finder(&mut self, _: &RevealInFinder, cx: &mut ViewContext<Self>) {
         if let Some(buffer) = self.buffer().read(cx).as_singleton() {
             if let Some(file) = buffer.read(cx).file().and_then(|f| f.as_local()) {
@@ -8600,6 +10239,9 @@ impl Editor {
 
         self.background_highlights
             .insert(TypeId::of::<T>(), (color_fetcher, ranges));
+        if scrollbar_markers::is_scrollbar_marker_highlight(TypeId::of::<T>()) {
+            self.scrollbar_marker_cache.invalidate();
+        }
         cx.notify();
     }
 
@@ -8624,6 +10266,9 @@ impl Editor {
             .inlay_background_highlights
             .remove(&Some(TypeId::of::<T>()));
         if text_highlights.is_some() || inlay_highlights.is_some() {
+            if scrollbar_markers::is_scrollbar_marker_highlight(TypeId::of::<T>()) {
+                self.scrollbar_marker_cache.invalidate();
+            }
             cx.notify();
         }
         text_highlights
@@ -8832,6 +10477,9 @@ impl Editor {
         self.display_map.update(cx, |map, _| {
             map.highlight_text(TypeId::of::<T>(), ranges, style)
         });
+        if scrollbar_markers::is_scrollbar_marker_highlight(TypeId::of::<T>()) {
+            self.scrollbar_marker_cache.invalidate();
+        }
         cx.notify();
     }
 
@@ -8859,6 +10507,9 @@ impl Editor {
             .display_map
             .update(cx, |map, _| map.clear_highlights(TypeId::of::<T>()));
         if cleared {
+            if scrollbar_markers::is_scrollbar_marker_highlight(TypeId::of::<T>()) {
+                self.scrollbar_marker_cache.invalidate();
+            }
             cx.notify();
         }
     }
@@ -8884,9 +10535,19 @@ impl Editor {
             } => {
                 self.refresh_active_diagnostics(cx);
                 self.refresh_code_actions(cx);
+                self.line_layout_cache.forget_prefetched_rows();
+                self.gutter_data.invalidate();
+                self.refresh_block_decoration_visibility(cx);
+                large_file::refresh_large_file_mode(self, cx);
                 if self.has_active_copilot_suggestion(cx) {
                     self.update_visible_copilot_suggestion(cx);
                 }
+                self.update_paragraph_spacing_after_edit(cx);
+                self.refresh_edit_history_changes(true, cx);
+                self.update_git_blame_after_edit(cx);
+                if self.code_lens_task.is_some() {
+                    self.refresh_code_lens(true, cx);
+                }
                 cx.emit(EditorEvent::BufferEdited);
                 cx.emit(SearchEvent::MatchesInvalidated);
 
@@ -8935,21 +10596,55 @@ impl Editor {
                 });
                 self.refresh_inlay_hints(InlayHintRefreshReason::NewLinesShown, cx);
             }
+            multi_buffer::Event::ExcerptsExpanded { ids } => {
+                cx.emit(EditorEvent::ExcerptsExpanded { ids: ids.clone() });
+                self.refresh_inlay_hints(InlayHintRefreshReason::NewLinesShown, cx);
+            }
+            multi_buffer::Event::ExcerptMoved { id, predecessor } => {
+                cx.emit(EditorEvent::ExcerptMoved {
+                    id: *id,
+                    predecessor: *predecessor,
+                });
+            }
             multi_buffer::Event::ExcerptsRemoved { ids } => {
                 self.refresh_inlay_hints(InlayHintRefreshReason::ExcerptsRemoved(ids.clone()), cx);
                 cx.emit(EditorEvent::ExcerptsRemoved { ids: ids.clone() })
             }
-            multi_buffer::Event::Reparsed => cx.emit(EditorEvent::Reparsed),
+            multi_buffer::Event::Reparsed => {
+                self.refresh_todo_highlights(cx);
+                cx.emit(EditorEvent::Reparsed);
+            }
             multi_buffer::Event::LanguageChanged => {
+                self.refresh_paragraph_spacing(cx);
+                self.refresh_todo_highlights(cx);
                 cx.emit(EditorEvent::Reparsed);
                 cx.notify();
             }
-            multi_buffer::Event::DirtyChanged => cx.emit(EditorEvent::DirtyChanged),
-            multi_buffer::Event::Saved => cx.emit(EditorEvent::Saved),
+            multi_buffer::Event::DirtyChanged => {
+                conflict_banner::refresh_conflict_banner(self, cx);
+                cx.emit(EditorEvent::DirtyChanged)
+            }
+            multi_buffer::Event::Saved => {
+                conflict_banner::refresh_conflict_banner(self, cx);
+                self.record_restore_point(RestorePointKind::Save, None, cx);
+                self.refresh_git_blame(cx);
+                cx.emit(EditorEvent::Saved)
+            }
             multi_buffer::Event::FileHandleChanged | multi_buffer::Event::Reloaded => {
+                conflict_banner::refresh_conflict_banner(self, cx);
+                self.refresh_git_blame(cx);
                 cx.emit(EditorEvent::TitleChanged)
             }
-            multi_buffer::Event::DiffBaseChanged => cx.emit(EditorEvent::DiffBaseChanged),
+            multi_buffer::Event::DiffBaseChanged => {
+                self.refresh_git_blame(cx);
+                cx.emit(EditorEvent::DiffBaseChanged)
+            }
+            multi_buffer::Event::DiffUpdated => {
+                self.row_layout_cache.invalidate_display_hunks();
+                self.refresh_inline_diff(cx);
+                self.refresh_review_mode(cx);
+                cx.notify();
+            }
             multi_buffer::Event::Closed => cx.emit(EditorEvent::Closed),
             multi_buffer::Event::DiagnosticsUpdated => {
                 self.refresh_active_diagnostics(cx);
@@ -8975,6 +10670,19 @@ impl Editor {
         let editor_settings = EditorSettings::get_global(cx);
         self.scroll_manager.vertical_scroll_margin = editor_settings.vertical_scroll_margin;
         self.show_breadcrumbs = editor_settings.toolbar.breadcrumbs;
+        self.refresh_paragraph_spacing(cx);
+        self.refresh_wrap_indent(cx);
+        self.refresh_todo_highlights(cx);
+        self.refresh_selection_match_highlights(cx);
+        self.refresh_inline_diff(cx);
+        if self.git_blame_task.is_none()
+            || EditorSettings::get_global(cx).inline_blame == InlineBlame::Off
+        {
+            self.refresh_git_blame(cx);
+        }
+        if self.code_lens_task.is_none() || !EditorSettings::get_global(cx).code_lens {
+            self.refresh_code_lens(false, cx);
+        }
         cx.notify();
     }
 
@@ -8986,6 +10694,149 @@ impl Editor {
         self.searchable
     }
 
+    pub fn expand_excerpts_up(&mut self, action: &ExpandExcerptsUp, cx: &mut ViewContext<Self>) {
+        self.expand_selected_excerpts(action.lines, ExpandExcerptDirection::Up, cx)
+    }
+
+    pub fn expand_excerpts_down(
+        &mut self,
+        action: &ExpandExcerptsDown,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.expand_selected_excerpts(action.lines, ExpandExcerptDirection::Down, cx)
+    }
+
+    fn expand_selected_excerpts(
+        &mut self,
+        lines: u32,
+        direction: ExpandExcerptDirection,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if self.buffer.read(cx).is_singleton() {
+            cx.propagate();
+            return;
+        }
+
+        let excerpt_ids = self
+            .selections
+            .disjoint_anchors()
+            .iter()
+            .flat_map(|selection| [selection.start.excerpt_id, selection.end.excerpt_id])
+            .collect::<HashSet<_>>();
+        let lines = if lines == 0 {
+            EditorSettings::get_global(cx).excerpt_header.expand_lines
+        } else {
+            lines
+        };
+        self.expand_excerpts(excerpt_ids, lines, direction, cx);
+    }
+
+    /// Reveals `lines` more lines of context around the given excerpts.
+    pub fn expand_excerpts(
+        &mut self,
+        excerpt_ids: impl IntoIterator<Item = ExcerptId>,
+        lines: u32,
+        direction: ExpandExcerptDirection,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.buffer.update(cx, |buffer, cx| {
+            buffer.expand_excerpts(excerpt_ids, lines, direction, cx)
+        });
+    }
+
+    pub fn dismiss_excerpt(&mut self, _: &DismissExcerpt, cx: &mut ViewContext<Self>) {
+        if self.buffer.read(cx).is_singleton() {
+            cx.propagate();
+            return;
+        }
+
+        let excerpt_ids = self
+            .selections
+            .disjoint_anchors()
+            .iter()
+            .map(|selection| selection.head().excerpt_id)
+            .collect::<HashSet<_>>();
+        self.remove_excerpts(excerpt_ids, cx);
+    }
+
+    /// Removes the given excerpts from the multibuffer, moving any selections inside them to
+    /// the nearest remaining position.
+    pub fn remove_excerpts(
+        &mut self,
+        excerpt_ids: impl IntoIterator<Item = ExcerptId>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.buffer
+            .update(cx, |buffer, cx| buffer.remove_excerpts(excerpt_ids, cx));
+        self.change_selections(Some(Autoscroll::fit()), cx, |s| {
+            s.refresh();
+        });
+    }
+
+    /// Moves an excerpt next to the target excerpt: above it when moving upwards, and below it
+    /// when moving downwards. Selections inside the moved excerpt move along with it.
+    pub fn move_excerpt(
+        &mut self,
+        excerpt_id: ExcerptId,
+        target_excerpt_id: ExcerptId,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let excerpt_ids = self.buffer.read(cx).excerpt_ids();
+        let Some(ix) = excerpt_ids.iter().position(|id| *id == excerpt_id) else {
+            return;
+        };
+        let Some(target_ix) = excerpt_ids.iter().position(|id| *id == target_excerpt_id) else {
+            return;
+        };
+        let prev_excerpt_id = if ix < target_ix {
+            target_excerpt_id
+        } else if ix > target_ix && target_ix > 0 {
+            excerpt_ids[target_ix - 1]
+        } else if ix > target_ix {
+            ExcerptId::min()
+        } else {
+            return;
+        };
+
+        let moved = self.buffer.update(cx, |buffer, cx| {
+            buffer.move_excerpt(excerpt_id, prev_excerpt_id, cx)
+        });
+        if !moved {
+            return;
+        }
+        // Anchors in the moved excerpt stay valid, but a selection spanning the moved excerpt
+        // and its neighbors can't be preserved.
+        let selections = self
+            .selections
+            .disjoint_anchors()
+            .iter()
+            .map(|selection| {
+                let mut selection = selection.clone();
+                if (selection.start.excerpt_id == excerpt_id)
+                    != (selection.end.excerpt_id == excerpt_id)
+                {
+                    selection.collapse_to(selection.head(), SelectionGoal::None);
+                }
+                selection
+            })
+            .collect();
+        self.change_selections(None, cx, |s| s.select_anchors(selections));
+    }
+
+    /// Folds or unfolds the run of consecutive excerpts from the same buffer as the given excerpt,
+    /// leaving only the buffer's header visible while folded.
+    pub fn toggle_excerpt_group_fold(&mut self, excerpt_id: ExcerptId, cx: &mut ViewContext<Self>) {
+        let snapshot = self.snapshot(cx);
+        let Some((range, _)) = snapshot.excerpt_group(excerpt_id) else {
+            return;
+        };
+        if snapshot.is_range_folded(&range) {
+            self.unfold_ranges([range], true, false, cx);
+        } else {
+            self.fold_ranges([range], false, cx);
+        }
+    }
+
     fn open_excerpts(&mut self, _: &OpenExcerpts, cx: &mut ViewContext<Self>) {
         let buffer = self.buffer.read(cx);
         if buffer.is_singleton() {
@@ -9037,6 +10888,56 @@ impl Editor {
         });
     }
 
+    /// Opens the buffer of the given excerpt in a split to the right, with the cursor at the
+    /// start of the excerpt.
+    pub fn open_excerpt_in_split(&mut self, excerpt_id: ExcerptId, cx: &mut ViewContext<Self>) {
+        let Some(workspace) = self.workspace() else {
+            return;
+        };
+        let multibuffer = self.buffer.read(cx);
+        let snapshot = multibuffer.snapshot(cx);
+        let (Some(buffer_snapshot), Some(range)) = (
+            snapshot.buffer_for_excerpt(excerpt_id),
+            snapshot.range_for_excerpt(excerpt_id),
+        ) else {
+            return;
+        };
+        let Some(buffer) = multibuffer.buffer(buffer_snapshot.remote_id()) else {
+            return;
+        };
+        let anchor = range
+            .primary
+            .map_or(range.context.start, |primary| primary.start);
+        let position = language::ToPoint::to_point(&anchor, buffer_snapshot);
+
+        // Deferred for the same reason as in `open_excerpts`.
+        cx.window_context().defer(move |cx| {
+            workspace.update(cx, |workspace, cx| {
+                let editor = workspace.split_project_item::<Self>(buffer, cx);
+                editor.update(cx, |editor, cx| {
+                    editor.change_selections(Some(Autoscroll::center()), cx, |s| {
+                        s.select_ranges([position..position]);
+                    });
+                });
+            })
+        });
+    }
+
+    /// Copies the absolute path of the given excerpt's file to the clipboard.
+    pub fn copy_excerpt_path(&mut self, excerpt_id: ExcerptId, cx: &mut ViewContext<Self>) {
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let Some(file) = snapshot
+            .buffer_for_excerpt(excerpt_id)
+            .and_then(|buffer| buffer.file())
+            .and_then(|file| file.as_local())
+        else {
+            return;
+        };
+        if let Some(path) = file.abs_path(cx).to_str() {
+            cx.write_to_clipboard(ClipboardItem::new(path.to_string()));
+        }
+    }
+
     fn jump(
         &mut self,
         path: ProjectPath,
@@ -9363,10 +11264,14 @@ impl Editor {
 
     pub fn handle_blur(&mut self, cx: &mut ViewContext<Self>) {
         self.blink_manager.update(cx, BlinkManager::disable);
+        middle_click::stop_autoscroll(self, cx);
         self.buffer
             .update(cx, |buffer, cx| buffer.remove_active_selections(cx));
         self.hide_context_menu(cx);
         hide_hover(self, cx);
+        if let Some(project) = self.format_on_blur.take() {
+            items::save_buffers(self, project, true, cx).detach_and_log_err(cx);
+        }
         cx.emit(EditorEvent::Blurred);
         cx.notify();
     }
@@ -9516,6 +11421,107 @@ fn consume_contiguous_rows(
     (start_row, end_row)
 }
 
+/// Returns the range to replace to join the given row with the next one, and its replacement.
+///
+/// The trailing whitespace of the row and the indentation of the next one are removed, as well as
+/// the comment marker starting the next row when it continues a comment. The lines are separated
+/// by a space, or by `separator`, except between brackets and their contents.
+fn join_line_edit(
+    snapshot: &MultiBufferSnapshot,
+    row: u32,
+    separator: Option<&str>,
+) -> (Range<Point>, String) {
+    let line_text = |row| {
+        snapshot
+            .text_for_range(Point::new(row, 0)..Point::new(row, snapshot.line_len(row)))
+            .collect::<String>()
+    };
+    let line = line_text(row);
+    let next_line = line_text(row + 1);
+    let line = line.trim_end();
+    let mut next = next_line.trim_start();
+
+    let next_line_start = Point::new(row + 1, (next_line.len() - next.len()) as u32);
+    if let Some(prefixes) = snapshot
+        .language_scope_at(next_line_start)
+        .and_then(|scope| scope.line_comment_prefixes().cloned())
+    {
+        for prefix in prefixes {
+            let marker = prefix.trim_end();
+            if marker.is_empty() || !line.contains(marker) {
+                continue;
+            }
+            if let Some(rest) = next.strip_prefix(marker) {
+                next = rest.trim_start();
+                break;
+            }
+        }
+    }
+
+    let range = Point::new(row, line.len() as u32)
+        ..Point::new(row + 1, (next_line.len() - next.len()) as u32);
+    let replacement = if line.is_empty()
+        || next.is_empty()
+        || line.ends_with(['(', '['])
+        || next.starts_with([')', ']'])
+    {
+        ""
+    } else if let Some(separator) = separator {
+        let delimiter = separator.trim();
+        if !delimiter.is_empty() && line.ends_with(delimiter) {
+            &separator[separator.trim_end().len()..]
+        } else if !delimiter.is_empty() && next.starts_with(delimiter) {
+            &separator[..separator.len() - separator.trim_start().len()]
+        } else {
+            separator
+        }
+    } else {
+        " "
+    };
+    (range, replacement.to_string())
+}
+
+/// Returns the last row spanned by the selection, leaving out the row it ends on when it ends at
+/// the start of that row.
+fn selection_end_row(selection: &Selection<Point>) -> u32 {
+    if selection.end.column == 0 && selection.end.row > selection.start.row {
+        selection.end.row - 1
+    } else {
+        selection.end.row
+    }
+}
+
+/// Returns the value of the first number in the line, which may be negative or have a fractional
+/// part. A `-` is only read as a sign when it starts a number, rather than joining it to a word.
+fn line_number_key(line: &str) -> Option<f64> {
+    let digits = |text: &str| {
+        text.find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(text.len())
+    };
+    let start = line.find(|c: char| c.is_ascii_digit())?;
+    let mut end = start + digits(&line[start..]);
+    if line[end..].starts_with('.') {
+        let fraction_len = digits(&line[end + 1..]);
+        if fraction_len > 0 {
+            end += 1 + fraction_len;
+        }
+    }
+    let number = line[start..end].parse::<f64>().ok()?;
+    let is_negative = line[..start]
+        .strip_suffix('-')
+        .map_or(false, |before_sign| {
+            !before_sign.ends_with(|c: char| c.is_alphanumeric() || c == '_')
+        });
+    Some(if is_negative { -number } else { number })
+}
+
+/// Returns the part of the line in the given byte range, clipped to the line's length.
+fn line_columns(line: &str, columns: Range<usize>) -> &str {
+    let start = columns.start.min(line.len());
+    let end = columns.end.min(line.len());
+    line.get(start..end).unwrap_or_default()
+}
+
 fn ending_row(next_selection: &Selection<Point>, display_map: &DisplaySnapshot) -> u32 {
     if next_selection.end.column > 0 || next_selection.is_empty() {
         display_map.next_line_boundary(next_selection.end).0.row + 1
@@ -9525,6 +11531,20 @@ fn ending_row(next_selection: &Selection<Point>, display_map: &DisplaySnapshot)
 }
 
 impl EditorSnapshot {
+    /// Returns the offset range spanned by the run of consecutive excerpts from the same buffer
+    /// as the given excerpt, along with the number of excerpts in that run.
+    pub fn excerpt_group(&self, excerpt_id: ExcerptId) -> Option<(Range<usize>, usize)> {
+        self.buffer_snapshot.excerpt_run(excerpt_id)
+    }
+
+    /// Returns whether the given range is entirely hidden by a fold.
+    pub fn is_range_folded(&self, range: &Range<usize>) -> bool {
+        self.folds_in_range(range.clone()).any(|fold| {
+            fold.range.start.to_offset(&self.buffer_snapshot) <= range.start
+                && fold.range.end.to_offset(&self.buffer_snapshot) >= range.end
+        })
+    }
+
     pub fn remote_selections_in_range<'a>(
         &'a self,
         range: &'a Range<Anchor>,
@@ -9586,14 +11606,21 @@ impl EditorSnapshot {
             let gutter_padding = (em_width * gutter_padding_factor).round();
             // Avoid flicker-like gutter resizes when the line number gains another digit and only resize the gutter on files with N*10^5 lines.
             let min_width_for_number_on_gutter = em_width * 4.0;
-            let gutter_width =
-                max_line_number_width.max(min_width_for_number_on_gutter) + gutter_padding * 2.0;
+            let breakpoint_lane = if self.show_breakpoints {
+                (em_width * 1.5).round()
+            } else {
+                Pixels::ZERO
+            };
+            let gutter_width = max_line_number_width.max(min_width_for_number_on_gutter)
+                + gutter_padding * 2.0
+                + breakpoint_lane;
             let gutter_margin = -descent;
 
             GutterDimensions {
                 padding: gutter_padding,
                 width: gutter_width,
                 margin: gutter_margin,
+                breakpoint_lane,
             }
         } else {
             GutterDimensions::default()
@@ -9626,6 +11653,13 @@ pub enum EditorEvent {
     ExcerptsRemoved {
         ids: Vec<ExcerptId>,
     },
+    ExcerptsExpanded {
+        ids: Vec<ExcerptId>,
+    },
+    ExcerptMoved {
+        id: ExcerptId,
+        predecessor: ExcerptId,
+    },
     BufferEdited,
     Edited,
     Reparsed,
@@ -9642,6 +11676,7 @@ pub enum EditorEvent {
         local: bool,
         autoscroll: bool,
     },
+    FoldsChanged,
     Closed,
 }
 
@@ -9673,12 +11708,12 @@ impl Render for Editor {
 
             EditorMode::Full => TextStyle {
                 color: cx.theme().colors().editor_foreground,
-                font_family: settings.buffer_font.family.clone(),
-                font_features: settings.buffer_font.features,
-                font_size: settings.buffer_font_size(cx).into(),
+                font_family: self.buffer_font_family(cx),
+                font_features: self.buffer_font_features(cx),
+                font_size: self.buffer_font_size(cx).into(),
                 font_weight: FontWeight::NORMAL,
                 font_style: FontStyle::Normal,
-                line_height: relative(settings.buffer_line_height.value()),
+                line_height: relative(self.buffer_line_height(cx)),
                 background_color: None,
                 underline: None,
                 strikethrough: None,
@@ -9686,13 +11721,19 @@ impl Render for Editor {
             },
         };
 
+        let read_only = self.mode == EditorMode::Full && self.read_only(cx);
         let background = match self.mode {
             EditorMode::SingleLine => cx.theme().system().transparent,
             EditorMode::AutoHeight { max_lines: _ } => cx.theme().system().transparent,
+            EditorMode::Full if read_only => {
+                let mut dimming = cx.theme().colors().editor_subheader_background;
+                dimming.fade_out(0.5);
+                cx.theme().colors().editor_background.blend(dimming)
+            }
             EditorMode::Full => cx.theme().colors().editor_background,
         };
 
-        EditorElement::new(
+        let element = EditorElement::new(
             cx.view(),
             EditorStyle {
                 background,
@@ -9711,7 +11752,19 @@ impl Render for Editor {
                     ..HighlightStyle::default()
                 },
             },
-        )
+        );
+
+        if self.mode == EditorMode::Full {
+            if let Some(breadcrumb_bar) = breadcrumb_bar::render_breadcrumb_bar(self, cx) {
+                return v_flex()
+                    .size_full()
+                    .child(breadcrumb_bar)
+                    .child(div().flex_1().min_h_0().child(element))
+                    .into_any_element();
+            }
+        }
+
+        element.into_any_element()
     }
 }
 
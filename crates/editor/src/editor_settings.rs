@@ -5,7 +5,10 @@ use settings::Settings;
 #[derive(Deserialize)]
 pub struct EditorSettings {
     pub cursor_blink: bool,
+    pub cursor_blink_interval: u64,
+    pub cursor_animation: bool,
     pub hover_popover_enabled: bool,
+    pub preview_remote_images: bool,
     pub show_completions_on_input: bool,
     pub show_completion_documentation: bool,
     pub completion_documentation_secondary_query_debounce: u64,
@@ -13,9 +16,29 @@ pub struct EditorSettings {
     pub toolbar: Toolbar,
     pub scrollbar: Scrollbar,
     pub vertical_scroll_margin: f32,
+    pub overscan_rows: u32,
     pub relative_line_numbers: bool,
+    pub hybrid_line_numbers: bool,
+    pub relative_line_numbers_alignment: LineNumberAlignment,
     pub seed_search_query_from_cursor: SeedQuerySetting,
     pub redact_private_values: bool,
+    pub breadcrumb_bar: bool,
+    pub cursor_position_overlay: bool,
+    pub todo_keywords: Vec<String>,
+    pub sticky_scroll: bool,
+    pub inline_blame: InlineBlame,
+    pub colorize_brackets: bool,
+    pub fold_indicators: FoldIndicators,
+    pub inline_diagnostics: bool,
+    pub inline_diff: bool,
+    pub code_lens: bool,
+    pub breakpoints: bool,
+    pub centered_layout: CenteredLayout,
+    pub soft_wrap_indicator: bool,
+    pub wrapped_line_indent: WrappedLineIndent,
+    pub selection_highlight: SelectionHighlight,
+    pub excerpt_header: ExcerptHeader,
+    pub large_file: LargeFile,
 }
 
 /// When to populate a new search's query based on the text under the cursor.
@@ -43,6 +66,50 @@ pub struct Scrollbar {
     pub selections: bool,
     pub symbols_selections: bool,
     pub diagnostics: bool,
+    pub todos: bool,
+    pub bookmarks: bool,
+    pub marks: bool,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct CenteredLayout {
+    pub enabled: bool,
+    pub max_column: u32,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct SelectionHighlight {
+    pub enabled: bool,
+    pub min_length: usize,
+    pub case_sensitive: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct ExcerptHeader {
+    pub buttons: Vec<ExcerptHeaderButton>,
+    pub expand_lines: u32,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct LargeFile {
+    pub max_bytes: usize,
+    pub max_lines: u32,
+}
+
+/// An action button shown in the headers of multibuffer excerpts.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExcerptHeaderButton {
+    /// Show more lines of context above the excerpt.
+    ExpandUp,
+    /// Show more lines of context below the excerpt.
+    ExpandDown,
+    /// Open the excerpt's file in a split to the right.
+    OpenInSplit,
+    /// Copy the absolute path of the excerpt's file to the clipboard.
+    CopyPath,
+    /// Remove the excerpt from the multibuffer.
+    Dismiss,
 }
 
 /// When to show the scrollbar in the editor.
@@ -62,17 +129,84 @@ pub enum ShowScrollbar {
     Never,
 }
 
+/// Which lines to annotate with the commit that last changed them.
+///
+/// Default: off
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InlineBlame {
+    /// Don't show blame annotations.
+    Off,
+    /// Only annotate the line containing the newest cursor.
+    CursorLine,
+    /// Annotate every line.
+    AllLines,
+}
+
+/// How to find the ranges that can be folded from the gutter.
+///
+/// Default: indentation
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FoldIndicators {
+    /// Fold lines that are indented more deeply than the line before them.
+    Indentation,
+    /// Fold the syntax node that starts on a line. The indicators are computed
+    /// while the gutter is hovered.
+    Syntax,
+}
+
+/// Which side of the gutter's line number column to align relative line numbers to.
+///
+/// Default: right
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LineNumberAlignment {
+    /// Align the numbers to the right, like absolute line numbers.
+    Right,
+    /// Align the numbers to the left, so that they stand apart from absolute line numbers.
+    Left,
+}
+
+/// How to indent the rows that continue a soft-wrapped line.
+///
+/// Default: match
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WrappedLineIndent {
+    /// Start the rows at the first column.
+    None,
+    /// Indent the rows as much as the line they continue.
+    Match,
+    /// Indent the rows four columns more than the line they continue.
+    Extra,
+}
+
 #[derive(Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct EditorSettingsContent {
     /// Whether the cursor blinks in the editor.
     ///
     /// Default: true
     pub cursor_blink: Option<bool>,
+    /// How long the cursor stays visible or hidden while blinking, in milliseconds.
+    ///
+    /// Default: 500
+    pub cursor_blink_interval: Option<u64>,
+    /// Whether the cursor glides to its new position when it moves, instead of
+    /// jumping there.
+    ///
+    /// Default: false
+    pub cursor_animation: Option<bool>,
     /// Whether to show the informational hover box when moving the mouse
     /// over symbols in the editor.
     ///
     /// Default: true
     pub hover_popover_enabled: Option<bool>,
+    /// Whether to download the images linked from the web when previewing them in hovers and
+    /// blocks, rather than only previewing local images.
+    ///
+    /// Default: false
+    pub preview_remote_images: Option<bool>,
     /// Whether to pop the completions menu while typing in an editor without
     /// explicitly requesting it.
     ///
@@ -102,10 +236,24 @@ pub struct EditorSettingsContent {
     ///
     /// Default: 3.
     pub vertical_scroll_margin: Option<f32>,
+    /// The number of rows above and below the visible ones to lay out along with them, so that
+    /// scrolling by a few rows doesn't need to lay out the rows scrolled into view.
+    ///
+    /// Default: 5
+    pub overscan_rows: Option<u32>,
     /// Whether the line numbers on editors gutter are relative or not.
     ///
     /// Default: false
     pub relative_line_numbers: Option<bool>,
+    /// Whether the current line shows its absolute line number, rather than 0,
+    /// when the line numbers are relative.
+    ///
+    /// Default: true
+    pub hybrid_line_numbers: Option<bool>,
+    /// Which side of the line number column to align relative line numbers to.
+    ///
+    /// Default: right
+    pub relative_line_numbers_alignment: Option<LineNumberAlignment>,
     /// When to populate a new search's query based on the text under the cursor.
     ///
     /// Default: always
@@ -117,6 +265,94 @@ pub struct EditorSettingsContent {
     ///
     /// Default: false
     pub redact_private_values: Option<bool>,
+
+    /// Whether to show a bar at the top of the editor with the file path and the
+    /// chain of symbols enclosing the cursor.
+    ///
+    /// Default: false
+    pub breadcrumb_bar: Option<bool>,
+
+    /// Whether to briefly show the cursor's line, column and offset next to it
+    /// while the selection is changing.
+    ///
+    /// Default: false
+    pub cursor_position_overlay: Option<bool>,
+
+    /// Keywords to highlight when they appear inside comments. An empty list
+    /// disables the highlighting.
+    ///
+    /// Default: ["TODO", "FIXME", "HACK"]
+    pub todo_keywords: Option<Vec<String>>,
+
+    /// Whether to pin the signature of the scope enclosing the top of the
+    /// viewport to the top of the editor when it has been scrolled out of view.
+    ///
+    /// Default: false
+    pub sticky_scroll: Option<bool>,
+
+    /// Which lines to annotate with the author, age and summary of the
+    /// commit that last changed them.
+    ///
+    /// Default: off
+    pub inline_blame: Option<InlineBlame>,
+
+    /// Whether to color matching brackets by how deeply they are nested, and
+    /// underline the pair of brackets enclosing the cursor.
+    ///
+    /// Default: false
+    pub colorize_brackets: Option<bool>,
+
+    /// How to find the ranges that can be folded from the gutter.
+    ///
+    /// Default: indentation
+    pub fold_indicators: Option<FoldIndicators>,
+
+    /// Whether to show the message of the first diagnostic on each line after
+    /// the end of the line.
+    ///
+    /// Default: false
+    pub inline_diagnostics: Option<bool>,
+
+    /// Whether to show the old lines of modified and removed git hunks above
+    /// them, highlighting the words that changed within them.
+    ///
+    /// Default: false
+    pub inline_diff: Option<bool>,
+
+    /// Whether to show the code lenses provided by language servers, such as
+    /// the number of references to a function, above the lines they annotate.
+    ///
+    /// Default: false
+    pub code_lens: Option<bool>,
+
+    /// Whether to show the breakpoints set in the project in a lane at the
+    /// left of the gutter, and toggle them by clicking it.
+    ///
+    /// Default: false
+    pub breakpoints: Option<bool>,
+
+    /// Centered layout related settings.
+    pub centered_layout: Option<CenteredLayoutContent>,
+
+    /// Whether to show a "↪" indicator at the start of the rows that continue
+    /// a soft-wrapped line.
+    ///
+    /// Default: false
+    pub soft_wrap_indicator: Option<bool>,
+
+    /// How to indent the rows that continue a soft-wrapped line.
+    ///
+    /// Default: match
+    pub wrapped_line_indent: Option<WrappedLineIndent>,
+
+    /// Selection match highlighting related settings.
+    pub selection_highlight: Option<SelectionHighlightContent>,
+
+    /// Multibuffer excerpt header related settings.
+    pub excerpt_header: Option<ExcerptHeaderContent>,
+
+    /// Large file related settings.
+    pub large_file: Option<LargeFileContent>,
 }
 
 // Toolbar related settings
@@ -155,6 +391,78 @@ pub struct ScrollbarContent {
     ///
     /// Default: true
     pub diagnostics: Option<bool>,
+    /// Whether to show TODO keyword markers in the scrollbar.
+    ///
+    /// Default: true
+    pub todos: Option<bool>,
+    /// Whether to show bookmark markers in the scrollbar.
+    ///
+    /// Default: true
+    pub bookmarks: Option<bool>,
+    /// Whether to show markers for the marks in the file in the scrollbar.
+    ///
+    /// Default: true
+    pub marks: Option<bool>,
+}
+
+/// Centered layout related settings
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct CenteredLayoutContent {
+    /// Whether to constrain the text of editors to a column in the middle of
+    /// their width, padding both sides.
+    ///
+    /// Default: false
+    pub enabled: Option<bool>,
+    /// The width of the centered column, in characters.
+    ///
+    /// Default: 100
+    pub max_column: Option<u32>,
+}
+
+/// Selection match highlighting related settings
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct SelectionHighlightContent {
+    /// Whether to highlight the other occurrences of the selected text.
+    ///
+    /// Default: false
+    pub enabled: Option<bool>,
+    /// The minimum number of selected characters for which to highlight
+    /// occurrences.
+    ///
+    /// Default: 2
+    pub min_length: Option<usize>,
+    /// Whether occurrences must match the case of the selected text.
+    ///
+    /// Default: true
+    pub case_sensitive: Option<bool>,
+}
+
+/// Multibuffer excerpt header related settings
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct ExcerptHeaderContent {
+    /// The action buttons to show in the header of each excerpt, in order.
+    ///
+    /// Default: ["expand_up", "expand_down", "open_in_split", "copy_path", "dismiss"]
+    pub buttons: Option<Vec<ExcerptHeaderButton>>,
+    /// The number of lines of context that the expand buttons reveal.
+    ///
+    /// Default: 5
+    pub expand_lines: Option<u32>,
+}
+
+/// Large file related settings
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct LargeFileContent {
+    /// The size in bytes above which syntax highlighting, git diff indicators
+    /// and invisibles are turned off in a file.
+    ///
+    /// Default: 20000000
+    pub max_bytes: Option<usize>,
+    /// The number of lines above which syntax highlighting, git diff
+    /// indicators and invisibles are turned off in a file.
+    ///
+    /// Default: 300000
+    pub max_lines: Option<u32>,
 }
 
 impl Settings for EditorSettings {
@@ -1,10 +1,15 @@
 use super::*;
 use crate::{
-    scroll::scroll_amount::ScrollAmount,
+    editor_settings::{ExcerptHeaderContent, LargeFileContent, SelectionHighlightContent},
+    scroll::{
+        scroll_amount::ScrollAmount,
+        scroll_lock::{lock_scrolling, unlock_scrolling},
+    },
     test::{
         assert_text_with_selections, build_editor, editor_lsp_test_context::EditorLspTestContext,
         editor_test_context::EditorTestContext, select_ranges,
     },
+    todo_highlights::TodoHighlights,
     JoinLines,
 };
 
@@ -31,7 +36,7 @@ use util::{
     test::{marked_text_ranges, marked_text_ranges_by, sample_text, TextRangeMarker},
 };
 use workspace::{
-    item::{FollowEvent, FollowableItem, Item, ItemHandle},
+    item::{FollowEvent, FollowableItem, Item, ItemHandle, ProjectItem},
     NavigationEntry, ViewId,
 };
 
@@ -224,6 +229,64 @@ fn test_undo_redo_with_selection_restoration(cx: &mut TestAppContext) {
     });
 }
 
+#[gpui::test]
+async fn test_undo_in_selection(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorTestContext::new(cx).await;
+    cx.set_state("ˇone\ntwo\nthree");
+    cx.simulate_input("1");
+    cx.set_selections_state("1one\ntwo\nthreeˇ");
+    cx.simulate_input("3");
+    cx.set_selections_state("1one\nˇtwo\nthree3");
+    cx.simulate_input("2");
+
+    // Only the edits touching the selection are undone, and the selection stays in place.
+    cx.set_selections_state("«1oneˇ»\n2two\nthree3");
+    cx.update_editor(|editor, cx| editor.undo_in_selection(&UndoInSelection, cx));
+    cx.assert_editor_state("«oneˇ»\n2two\nthree3");
+
+    cx.set_selections_state("one\n2two\nthreeˇ3");
+    cx.update_editor(|editor, cx| editor.undo_in_selection(&UndoInSelection, cx));
+    cx.assert_editor_state("one\n2two\nthreeˇ");
+
+    // Regular undo still undoes the remaining edits from the most recent one.
+    cx.update_editor(|editor, cx| editor.undo(&Undo, cx));
+    assert_eq!(cx.buffer_text(), "one\ntwo\nthree");
+}
+
+#[gpui::test]
+fn test_undo_in_selection_in_multibuffer(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let multibuffer = cx.update(|cx| {
+        MultiBuffer::build_multi(
+            [
+                ("one\ntwo\n", vec![Point::new(0, 0)..Point::new(1, 3)]),
+                ("three\nfour\n", vec![Point::new(0, 0)..Point::new(1, 4)]),
+            ],
+            cx,
+        )
+    });
+    let editor = cx.add_window(|cx| build_editor(multibuffer, cx));
+    _ = editor.update(cx, |editor, cx| {
+        editor.change_selections(None, cx, |s| s.select_ranges([0..0]));
+        editor.handle_input("1", cx);
+        editor.change_selections(None, cx, |s| s.select_ranges([9..9]));
+        editor.handle_input("2", cx);
+        assert_eq!(editor.text(cx), "1one\ntwo\n2three\nfour");
+
+        // The edits undone in one excerpt can be redone, and then undone again.
+        editor.change_selections(None, cx, |s| s.select_ranges([0..4]));
+        editor.undo_in_selection(&UndoInSelection, cx);
+        assert_eq!(editor.text(cx), "one\ntwo\n2three\nfour");
+        editor.redo(&Redo, cx);
+        assert_eq!(editor.text(cx), "1one\ntwo\n2three\nfour");
+        editor.undo(&Undo, cx);
+        assert_eq!(editor.text(cx), "one\ntwo\n2three\nfour");
+    });
+}
+
 #[gpui::test]
 fn test_ime_composition(cx: &mut TestAppContext) {
     init_test(cx, |_| {});
@@ -428,6 +491,85 @@ fn test_selection_with_mouse(cx: &mut TestAppContext) {
     );
 }
 
+#[gpui::test]
+fn test_line_wise_selection_with_mouse(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let editor = cx.add_window(|cx| {
+        let buffer = MultiBuffer::build_simple("aaaaaa\nbbbbbb\ncccccc\nddddddd\n", cx);
+        build_editor(buffer, cx)
+    });
+
+    _ = editor.update(cx, |view, cx| {
+        view.select(
+            SelectPhase::BeginLineWise {
+                position: DisplayPoint::new(1, 2),
+                extend: false,
+            },
+            cx,
+        );
+    });
+    assert_eq!(
+        editor
+            .update(cx, |view, cx| view.selections.display_ranges(cx))
+            .unwrap(),
+        [DisplayPoint::new(1, 0)..DisplayPoint::new(2, 0)]
+    );
+
+    // Dragging selects whole lines, keeping the clicked line selected.
+    _ = editor.update(cx, |view, cx| {
+        view.update_selection(
+            DisplayPoint::new(0, 3),
+            0,
+            gpui::Point::<f32>::default(),
+            cx,
+        );
+    });
+    assert_eq!(
+        editor
+            .update(cx, |view, cx| view.selections.display_ranges(cx))
+            .unwrap(),
+        [DisplayPoint::new(2, 0)..DisplayPoint::new(0, 0)]
+    );
+
+    _ = editor.update(cx, |view, cx| {
+        view.update_selection(
+            DisplayPoint::new(2, 1),
+            0,
+            gpui::Point::<f32>::default(),
+            cx,
+        );
+        view.end_selection(cx);
+    });
+    assert_eq!(
+        editor
+            .update(cx, |view, cx| view.selections.display_ranges(cx))
+            .unwrap(),
+        [DisplayPoint::new(1, 0)..DisplayPoint::new(3, 0)]
+    );
+
+    // Extending starts from the whole line of the selection's tail.
+    _ = editor.update(cx, |view, cx| {
+        view.change_selections(None, cx, |s| {
+            s.select_display_ranges([DisplayPoint::new(2, 3)..DisplayPoint::new(2, 3)])
+        });
+        view.select(
+            SelectPhase::BeginLineWise {
+                position: DisplayPoint::new(0, 1),
+                extend: true,
+            },
+            cx,
+        );
+        view.end_selection(cx);
+    });
+    assert_eq!(
+        editor
+            .update(cx, |view, cx| view.selections.display_ranges(cx))
+            .unwrap(),
+        [DisplayPoint::new(3, 0)..DisplayPoint::new(0, 0)]
+    );
+}
+
 #[gpui::test]
 fn test_canceling_pending_selection(cx: &mut TestAppContext) {
     init_test(cx, |_| {});
@@ -544,6 +686,49 @@ fn test_clone(cx: &mut TestAppContext) {
     );
 }
 
+#[gpui::test]
+fn test_clone_has_independent_display_state(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let editor = cx.add_window(|cx| {
+        let buffer = MultiBuffer::build_simple(&sample_text(6, 4, 'a'), cx);
+        build_editor(buffer, cx)
+    });
+    _ = editor.update(cx, |editor, cx| {
+        editor.set_soft_wrap_mode(language_settings::SoftWrap::EditorWidth, cx);
+    });
+    let cloned_editor = editor
+        .update(cx, |editor, cx| {
+            cx.open_window(Default::default(), |cx| cx.new_view(|cx| editor.clone(cx)))
+        })
+        .unwrap();
+    _ = cloned_editor.update(cx, |editor, cx| {
+        assert_eq!(
+            editor.soft_wrap_mode_override,
+            Some(language_settings::SoftWrap::EditorWidth)
+        );
+        editor.fold_ranges([Point::new(1, 0)..Point::new(3, 0)], true, cx);
+        editor.set_soft_wrap_mode(language_settings::SoftWrap::None, cx);
+        editor.set_scroll_position(point(0., 2.), cx);
+    });
+
+    // Changing the display state of the clone leaves the original untouched, while the text is
+    // still shared between them.
+    _ = editor.update(cx, |editor, cx| {
+        assert_eq!(editor.display_text(cx), sample_text(6, 4, 'a'));
+        assert_eq!(
+            editor.soft_wrap_mode_override,
+            Some(language_settings::SoftWrap::EditorWidth)
+        );
+        assert_eq!(editor.scroll_position(cx), point(0., 0.));
+        editor.handle_input("X", cx);
+    });
+    _ = cloned_editor.update(cx, |editor, cx| {
+        assert_eq!(editor.display_text(cx), "Xaaaa\n⋯dddd\neeee\nffff");
+        assert_eq!(editor.scroll_position(cx), point(0., 2.));
+    });
+}
+
 #[gpui::test]
 async fn test_navigation_history(cx: &mut TestAppContext) {
     init_test(cx, |_| {});
@@ -805,6 +990,76 @@ fn test_fold_action(cx: &mut TestAppContext) {
     });
 }
 
+#[gpui::test]
+fn test_fold_recursively_at(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let view = cx.add_window(|cx| {
+        let buffer = MultiBuffer::build_simple(
+            &"
+                impl Foo {
+                    fn a() {
+                        if x {
+                            1
+                        }
+                    }
+
+                    fn b() {
+                        2
+                    }
+                }
+                fn c() {
+                    3
+                }
+            "
+            .unindent(),
+            cx,
+        );
+        build_editor(buffer.clone(), cx)
+    });
+
+    _ = view.update(cx, |view, cx| {
+        view.fold_recursively_at(1, cx);
+        assert_eq!(
+            view.display_text(cx),
+            "
+                impl Foo {
+                    fn a() {⋯
+                    }
+
+                    fn b() {⋯
+                    }
+                }
+                fn c() {
+                    3
+                }
+            "
+            .unindent(),
+        );
+
+        // Nested ranges stay folded when their parent is unfolded.
+        view.unfold_at(&UnfoldAt { buffer_row: 1 }, cx);
+        assert_eq!(
+            view.display_text(cx),
+            "
+                impl Foo {
+                    fn a() {
+                        if x {⋯
+                        }
+                    }
+
+                    fn b() {⋯
+                    }
+                }
+                fn c() {
+                    3
+                }
+            "
+            .unindent(),
+        );
+    });
+}
+
 #[gpui::test]
 fn test_move_cursor(cx: &mut TestAppContext) {
     init_test(cx, |_| {});
@@ -1487,6 +1742,69 @@ async fn test_scroll_page_up_page_down(cx: &mut gpui::TestAppContext) {
     });
 }
 
+#[gpui::test]
+async fn test_scroll_lock(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let (left, cx) = cx.add_window_view(|cx| {
+        let buffer = MultiBuffer::build_simple(&sample_text(101, 4, 'a'), cx);
+        build_editor(buffer, cx)
+    });
+    let right = cx.new_view(|cx| {
+        let buffer = MultiBuffer::build_simple(&sample_text(51, 4, 'a'), cx);
+        build_editor(buffer, cx)
+    });
+    let scroll_position = |editor: &View<Editor>, cx: &mut VisualTestContext| {
+        editor.update(cx, |editor, cx| editor.scroll_position(cx))
+    };
+
+    cx.update(|cx| lock_scrolling(&[left.clone(), right.clone()], false, cx));
+    left.update(cx, |editor, cx| {
+        editor.set_scroll_position(point(0., 20.), cx)
+    });
+    cx.run_until_parked();
+    assert_eq!(scroll_position(&right, cx), point(0., 20.));
+
+    // Editors are only scrolled as far as their own content allows.
+    left.update(cx, |editor, cx| {
+        editor.set_scroll_position(point(0., 80.), cx)
+    });
+    cx.run_until_parked();
+    assert_eq!(scroll_position(&right, cx), point(0., 50.));
+
+    right.update(cx, |editor, cx| {
+        editor.set_scroll_position(point(0., 10.), cx)
+    });
+    cx.run_until_parked();
+    assert_eq!(scroll_position(&left, cx), point(0., 10.));
+
+    // Autoscrolling to the selections scrolls the locked editors too.
+    left.update(cx, |editor, cx| {
+        editor.change_selections(Some(Autoscroll::center()), cx, |selections| {
+            selections.select_ranges([Point::new(40, 0)..Point::new(40, 0)])
+        })
+    });
+    cx.run_until_parked();
+    let left_scroll_position = scroll_position(&left, cx);
+    assert!(left_scroll_position.y > 10.);
+    assert_eq!(scroll_position(&right, cx), left_scroll_position);
+
+    // Proportional locks scroll to the same fraction of each editor's content.
+    cx.update(|cx| lock_scrolling(&[left.clone(), right.clone()], true, cx));
+    left.update(cx, |editor, cx| {
+        editor.set_scroll_position(point(0., 40.), cx)
+    });
+    cx.run_until_parked();
+    assert_eq!(scroll_position(&right, cx), point(0., 20.));
+
+    cx.update(|cx| unlock_scrolling(&left, cx));
+    left.update(cx, |editor, cx| {
+        editor.set_scroll_position(point(0., 0.), cx)
+    });
+    cx.run_until_parked();
+    assert_eq!(scroll_position(&right, cx), point(0., 20.));
+}
+
 #[gpui::test]
 async fn test_autoscroll(cx: &mut gpui::TestAppContext) {
     init_test(cx, |_| {});
@@ -1570,6 +1888,92 @@ async fn test_autoscroll(cx: &mut gpui::TestAppContext) {
     });
 }
 
+#[gpui::test]
+async fn test_autoscroll_restores_goal_scroll_left(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+    let mut cx = EditorTestContext::new(cx).await;
+    let window = cx.window;
+    cx.simulate_window_resize(window, size(px(500.), px(500.)));
+    cx.set_state(&format!("{}\nb\n{}", "a".repeat(200), "c".repeat(200)));
+
+    // Scroll right to reveal a cursor near the end of the first line, then move it back a bit,
+    // which doesn't scroll.
+    cx.update_editor(|editor, cx| {
+        editor.change_selections(Some(Autoscroll::fit()), cx, |s| {
+            s.select_ranges([Point::new(0, 150)..Point::new(0, 150)])
+        });
+    });
+    let scroll_left = cx.update_editor(|editor, cx| editor.snapshot(cx).scroll_position().x);
+    assert!(scroll_left > 0.);
+    cx.update_editor(|editor, cx| {
+        editor.change_selections(Some(Autoscroll::fit()), cx, |s| {
+            s.select_ranges([Point::new(0, 140)..Point::new(0, 140)])
+        });
+    });
+    cx.update_editor(|editor, cx| {
+        assert_eq!(editor.snapshot(cx).scroll_position().x, scroll_left);
+    });
+
+    // Moving through a shorter line scrolls back to the left.
+    cx.update_editor(|editor, cx| editor.move_down(&Default::default(), cx));
+    cx.update_editor(|editor, cx| {
+        assert_eq!(editor.snapshot(cx).scroll_position().x, 0.);
+    });
+
+    // Once the cursor gets back to its goal position, the editor is scrolled as it was there,
+    // rather than only as far as needed to reveal the cursor.
+    cx.update_editor(|editor, cx| editor.move_down(&Default::default(), cx));
+    cx.update_editor(|editor, cx| {
+        assert_eq!(
+            editor.selections.newest::<Point>(cx).head(),
+            Point::new(2, 140)
+        );
+        assert_eq!(editor.snapshot(cx).scroll_position().x, scroll_left);
+    });
+}
+
+#[gpui::test]
+async fn test_scroll_cursor_left_and_right(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+    let mut cx = EditorTestContext::new(cx).await;
+    let window = cx.window;
+    cx.simulate_window_resize(window, size(px(500.), px(500.)));
+    cx.set_state(&format!("{}ˇ{}", "一".repeat(30), "a".repeat(100)));
+
+    let em_width = cx.update_editor(|editor, cx| {
+        let style = editor.style().unwrap().text.clone();
+        let font_id = cx.text_system().resolve_font(&style.font());
+        let font_size = style.font_size.to_pixels(cx.rem_size());
+        cx.text_system()
+            .typographic_bounds(font_id, font_size, 'm')
+            .unwrap()
+            .size
+            .width
+    });
+
+    // The editor is scrolled by the cursor's laid-out position, which accounts for the wide
+    // characters before it.
+    cx.update_editor(|editor, cx| {
+        let head = editor.selections.newest_display(cx).head();
+        let text_layout_details = editor.text_layout_details(cx);
+        let cursor_x = editor
+            .snapshot(cx)
+            .x_for_display_point(head, &text_layout_details);
+
+        editor.scroll_cursor_left(&ScrollCursorLeft, cx);
+        let scroll_left = editor.snapshot(cx).scroll_position().x;
+        assert_eq!(scroll_left, cursor_x / em_width);
+        assert!(scroll_left > 30.);
+
+        editor.scroll_cursor_right(&ScrollCursorRight, cx);
+        let visible_columns = editor.visible_column_count().unwrap();
+        assert_eq!(
+            editor.snapshot(cx).scroll_position().x,
+            (cursor_x / em_width + 1. - visible_columns).max(0.)
+        );
+    });
+}
+
 #[gpui::test]
 async fn test_move_page_up_page_down(cx: &mut gpui::TestAppContext) {
     init_test(cx, |_| {});
@@ -2687,6 +3091,62 @@ fn test_join_lines_with_multi_selection(cx: &mut TestAppContext) {
     });
 }
 
+#[gpui::test]
+async fn test_join_lines_with_delimiters(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+    let mut cx = EditorTestContext::new(cx).await;
+    let language = Arc::new(Language::new(
+        LanguageConfig {
+            line_comments: vec!["// ".into()],
+            ..Default::default()
+        },
+        Some(tree_sitter_rust::language()),
+    ));
+    cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+
+    // The comment markers of continued comments are removed, along with extra whitespace.
+    cx.set_state(indoc! {"
+        // oneˇ  \t
+        //   two
+        f(
+            a
+        );
+    "});
+    cx.update_editor(|e, cx| e.join_lines(&JoinLines, cx));
+    cx.assert_editor_state(indoc! {"
+        // oneˇ two
+        f(
+            a
+        );
+    "});
+
+    // No space is inserted between brackets and their contents.
+    cx.set_state(indoc! {"
+        // one two
+        «f(
+            a
+        )ˇ»;
+    "});
+    cx.update_editor(|e, cx| e.join_lines(&JoinLines, cx));
+    cx.assert_editor_state(indoc! {"
+        // one two
+        f(aˇ);
+    "});
+
+    // Elements are separated with the given separator, without duplicating delimiters.
+    cx.set_state(indoc! {"
+        «let a = [
+            1,
+            2
+            , 3
+        ]ˇ»;
+    "});
+    cx.update_editor(|e, cx| e.join_lines_with(&JoinLinesWith::default(), cx));
+    cx.assert_editor_state(indoc! {"
+        let a = [1, 2, 3ˇ];
+    "});
+}
+
 #[gpui::test]
 async fn test_manipulate_lines_with_single_selection(cx: &mut TestAppContext) {
     init_test(cx, |_| {});
@@ -2909,12 +3369,85 @@ async fn test_unique_lines_single_selection(cx: &mut TestAppContext) {
 }
 
 #[gpui::test]
-async fn test_manipulate_lines_with_multi_selection(cx: &mut TestAppContext) {
+async fn test_sort_and_unique_lines(cx: &mut TestAppContext) {
     init_test(cx, |_| {});
 
     let mut cx = EditorTestContext::new(cx).await;
 
-    // Manipulate with multiple selections on a single line
+    // Lines are sorted by the value of their first number, not its text. A `-` joining a number
+    // to a word isn't a sign.
+    cx.set_state(indoc! {"
+        «item 10
+        item 9
+        item -2.5
+        step-3
+        no number
+        item 2.25ˇ»
+    "});
+    cx.update_editor(|e, cx| e.sort_lines_numerically(&SortLinesNumerically, cx));
+    cx.assert_editor_state(indoc! {"
+        «no number
+        item -2.5
+        item 2.25
+        step-3
+        item 9
+        item 10ˇ»
+    "});
+
+    // Lines are sorted by the text in the selected columns.
+    cx.set_state(indoc! {"
+        c «1ˇ» x
+        a «3ˇ» y
+        b «2ˇ» z
+    "});
+    cx.update_editor(|e, cx| e.sort_lines_by_column(&SortLinesByColumn, cx));
+    cx.assert_editor_state(indoc! {"
+        «c 1 x
+        b 2 z
+        a 3 yˇ»
+    "});
+
+    // Empty selections sort lines by their text from the cursors' column.
+    cx.set_state(indoc! {"
+        c ˇb
+        a ˇc
+        b ˇa
+    "});
+    cx.update_editor(|e, cx| e.sort_lines_by_column(&SortLinesByColumn, cx));
+    cx.assert_editor_state(indoc! {"
+        «b a
+        c b
+        a cˇ»
+    "});
+
+    cx.set_state(indoc! {"
+        «a
+        a
+        b
+        a
+        bˇ»
+    "});
+    cx.update_editor(|e, cx| e.unique_lines(&UniqueLines { adjacent: true }, cx));
+    cx.assert_editor_state(indoc! {"
+        «a
+        b
+        a
+        bˇ»
+    "});
+    cx.update_editor(|e, cx| e.unique_lines(&UniqueLines { adjacent: false }, cx));
+    cx.assert_editor_state(indoc! {"
+        «a
+        bˇ»
+    "});
+}
+
+#[gpui::test]
+async fn test_manipulate_lines_with_multi_selection(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorTestContext::new(cx).await;
+
+    // Manipulate with multiple selections on a single line
     cx.set_state(indoc! {"
         dd«dd
         cˇ»c«c
@@ -3154,6 +3687,48 @@ fn test_duplicate_line(cx: &mut TestAppContext) {
     });
 }
 
+#[gpui::test]
+async fn test_duplicate_line_up_and_selection(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+    let mut cx = EditorTestContext::new(cx).await;
+
+    cx.set_state(indoc! {"
+        oˇne
+        t«wo
+        threˇ»e
+        four
+    "});
+    cx.update_editor(|e, cx| e.duplicate_line_up(&DuplicateLineUp, cx));
+    cx.assert_editor_state(indoc! {"
+        oˇne
+        one
+        t«wo
+        threˇ»e
+        two
+        three
+        four
+    "});
+
+    // Non-empty selections are duplicated in place, and the copies are selected.
+    cx.set_state(indoc! {"
+        o«neˇ» two
+        th«ree
+        fˇ»our
+    "});
+    cx.update_editor(|e, cx| e.duplicate_selection(&DuplicateSelection, cx));
+    cx.assert_editor_state(indoc! {"
+        one«neˇ» two
+        three
+        f«ree
+        fˇ»our
+    "});
+
+    // Without a non-empty selection, lines are duplicated.
+    cx.set_state("oˇne\n");
+    cx.update_editor(|e, cx| e.duplicate_selection(&DuplicateSelection, cx));
+    cx.assert_editor_state("one\noˇne\n");
+}
+
 #[gpui::test]
 fn test_move_line_up_down(cx: &mut TestAppContext) {
     init_test(cx, |_| {});
@@ -3575,161 +4150,899 @@ async fn test_paste_multiline(cx: &mut gpui::TestAppContext) {
 }
 
 #[gpui::test]
-fn test_select_all(cx: &mut TestAppContext) {
+async fn test_paste_text_copied_from_other_applications(cx: &mut gpui::TestAppContext) {
     init_test(cx, |_| {});
 
-    let view = cx.add_window(|cx| {
-        let buffer = MultiBuffer::build_simple("abc\nde\nfgh", cx);
-        build_editor(buffer, cx)
-    });
-    _ = view.update(cx, |view, cx| {
-        view.select_all(&SelectAll, cx);
-        assert_eq!(
-            view.selections.display_ranges(cx),
-            &[DisplayPoint::new(0, 0)..DisplayPoint::new(2, 3)]
-        );
-    });
-}
+    let mut cx = EditorTestContext::new(cx).await;
+    let language = Arc::new(Language::new(
+        LanguageConfig::default(),
+        Some(tree_sitter_rust::language()),
+    ));
+    cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
 
-#[gpui::test]
-fn test_select_line(cx: &mut TestAppContext) {
-    init_test(cx, |_| {});
+    // When the text has as many lines as there are cursors, each cursor gets one of them.
+    cx.set_state("let ˇa;\nlet ˇb;\nlet ˇc;\n");
+    cx.update(|cx| cx.write_to_clipboard(ClipboardItem::new("x\ny\nz\n".into())));
+    cx.update_editor(|e, cx| e.paste(&Paste, cx));
+    cx.assert_editor_state("let xˇa;\nlet yˇb;\nlet zˇc;\n");
 
-    let view = cx.add_window(|cx| {
-        let buffer = MultiBuffer::build_simple(&sample_text(6, 5, 'a'), cx);
-        build_editor(buffer, cx)
-    });
-    _ = view.update(cx, |view, cx| {
-        view.change_selections(None, cx, |s| {
-            s.select_display_ranges([
-                DisplayPoint::new(0, 0)..DisplayPoint::new(0, 1),
-                DisplayPoint::new(0, 2)..DisplayPoint::new(0, 2),
-                DisplayPoint::new(1, 0)..DisplayPoint::new(1, 0),
-                DisplayPoint::new(4, 2)..DisplayPoint::new(4, 2),
-            ])
-        });
-        view.select_line(&SelectLine, cx);
-        assert_eq!(
-            view.selections.display_ranges(cx),
-            vec![
-                DisplayPoint::new(0, 0)..DisplayPoint::new(2, 0),
-                DisplayPoint::new(4, 0)..DisplayPoint::new(5, 0),
-            ]
-        );
-    });
+    // Lines ending with CRLF are split the same way.
+    cx.set_state("let ˇa;\nlet ˇb;\nlet ˇc;\n");
+    cx.update(|cx| cx.write_to_clipboard(ClipboardItem::new("x\r\ny\r\nz\r\n".into())));
+    cx.update_editor(|e, cx| e.paste(&Paste, cx));
+    cx.assert_editor_state("let xˇa;\nlet yˇb;\nlet zˇc;\n");
 
-    _ = view.update(cx, |view, cx| {
-        view.select_line(&SelectLine, cx);
-        assert_eq!(
-            view.selections.display_ranges(cx),
-            vec![
-                DisplayPoint::new(0, 0)..DisplayPoint::new(3, 0),
-                DisplayPoint::new(4, 0)..DisplayPoint::new(5, 5),
-            ]
-        );
-    });
+    // Otherwise, each cursor gets the whole text.
+    cx.set_state("let ˇa;\nlet ˇb;\n");
+    cx.update(|cx| cx.write_to_clipboard(ClipboardItem::new("x\ny\nz".into())));
+    cx.update_editor(|e, cx| e.paste(&Paste, cx));
+    cx.assert_editor_state("let x\ny\nzˇa;\nlet x\ny\nzˇb;\n");
 
-    _ = view.update(cx, |view, cx| {
-        view.select_line(&SelectLine, cx);
-        assert_eq!(
-            view.selections.display_ranges(cx),
-            vec![DisplayPoint::new(0, 0)..DisplayPoint::new(5, 5)]
-        );
-    });
+    // A pasted block keeps its relative indentation, its first line being indented like the
+    // line it's pasted into.
+    cx.set_state(indoc! {"
+        fn f() {
+            ˇ
+        }
+    "});
+    cx.update(|cx| cx.write_to_clipboard(ClipboardItem::new("if x {\n  y();\n}".into())));
+    cx.update_editor(|e, cx| e.paste(&Paste, cx));
+    cx.assert_editor_state(indoc! {"
+        fn f() {
+            if x {
+              y();
+            }ˇ
+        }
+    "});
 }
 
 #[gpui::test]
-fn test_split_selection_into_lines(cx: &mut TestAppContext) {
+async fn test_paste_without_formatting(cx: &mut gpui::TestAppContext) {
     init_test(cx, |_| {});
 
-    let view = cx.add_window(|cx| {
-        let buffer = MultiBuffer::build_simple(&sample_text(9, 5, 'a'), cx);
-        build_editor(buffer, cx)
-    });
-    _ = view.update(cx, |view, cx| {
-        view.fold_ranges(
-            vec![
-                Point::new(0, 2)..Point::new(1, 2),
-                Point::new(2, 3)..Point::new(4, 1),
-                Point::new(7, 0)..Point::new(8, 4),
-            ],
-            true,
-            cx,
+    let mut cx = EditorTestContext::new(cx).await;
+    let language = Arc::new(Language::new(
+        LanguageConfig::default(),
+        Some(tree_sitter_rust::language()),
+    ));
+    cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+
+    cx.set_state(indoc! {"
+        const a: B = (
+            c(),
+            «d(
+                e,
+                f
+            )ˇ»
         );
-        view.change_selections(None, cx, |s| {
-            s.select_display_ranges([
-                DisplayPoint::new(0, 0)..DisplayPoint::new(0, 1),
-                DisplayPoint::new(0, 2)..DisplayPoint::new(0, 2),
-                DisplayPoint::new(1, 0)..DisplayPoint::new(1, 0),
-                DisplayPoint::new(4, 4)..DisplayPoint::new(4, 4),
-            ])
-        });
-        assert_eq!(view.display_text(cx), "aa⋯bbb\nccc⋯eeee\nfffff\nggggg\n⋯i");
-    });
+    "});
+    cx.update_editor(|e, cx| e.copy(&Copy, cx));
 
-    _ = view.update(cx, |view, cx| {
-        view.split_selection_into_lines(&SplitSelectionIntoLines, cx);
-        assert_eq!(
-            view.display_text(cx),
-            "aaaaa\nbbbbb\nccc⋯eeee\nfffff\nggggg\n⋯i"
+    // The copied text is inserted exactly as it was copied.
+    cx.set_state(indoc! {"
+        ˇ
+        const a: B = (
+            c(),
         );
-        assert_eq!(
-            view.selections.display_ranges(cx),
-            [
-                DisplayPoint::new(0, 1)..DisplayPoint::new(0, 1),
-                DisplayPoint::new(0, 2)..DisplayPoint::new(0, 2),
-                DisplayPoint::new(2, 0)..DisplayPoint::new(2, 0),
-                DisplayPoint::new(5, 4)..DisplayPoint::new(5, 4)
-            ]
+    "});
+    cx.update_editor(|e, cx| e.paste_without_formatting(&PasteWithoutFormatting, cx));
+    cx.assert_editor_state(indoc! {"
+        d(
+                e,
+                f
+            )ˇ
+        const a: B = (
+            c(),
         );
-    });
+    "});
 
-    _ = view.update(cx, |view, cx| {
-        view.change_selections(None, cx, |s| {
-            s.select_display_ranges([DisplayPoint::new(5, 0)..DisplayPoint::new(0, 1)])
+    // Regular pastes also keep the text as-is when re-indentation is disabled.
+    cx.update(|cx| {
+        cx.update_global(|store: &mut SettingsStore, cx| {
+            store.update_user_settings::<AllLanguageSettings>(cx, |settings| {
+                settings.defaults.auto_indent_on_paste = Some(false);
+            });
         });
-        view.split_selection_into_lines(&SplitSelectionIntoLines, cx);
-        assert_eq!(
-            view.display_text(cx),
-            "aaaaa\nbbbbb\nccccc\nddddd\neeeee\nfffff\nggggg\nhhhhh\niiiii"
+    });
+    cx.set_state(indoc! {"
+        ˇ
+        const a: B = (
+            c(),
         );
-        assert_eq!(
-            view.selections.display_ranges(cx),
-            [
-                DisplayPoint::new(0, 5)..DisplayPoint::new(0, 5),
-                DisplayPoint::new(1, 5)..DisplayPoint::new(1, 5),
-                DisplayPoint::new(2, 5)..DisplayPoint::new(2, 5),
-                DisplayPoint::new(3, 5)..DisplayPoint::new(3, 5),
-                DisplayPoint::new(4, 5)..DisplayPoint::new(4, 5),
-                DisplayPoint::new(5, 5)..DisplayPoint::new(5, 5),
-                DisplayPoint::new(6, 5)..DisplayPoint::new(6, 5),
-                DisplayPoint::new(7, 0)..DisplayPoint::new(7, 0)
-            ]
+    "});
+    cx.update_editor(|e, cx| e.paste(&Paste, cx));
+    cx.assert_editor_state(indoc! {"
+        d(
+                e,
+                f
+            )ˇ
+        const a: B = (
+            c(),
         );
-    });
+    "});
 }
 
 #[gpui::test]
-async fn test_add_selection_above_below(cx: &mut TestAppContext) {
+async fn test_per_language_buffer_font_overrides(cx: &mut gpui::TestAppContext) {
     init_test(cx, |_| {});
+    update_test_language_settings(cx, |settings| {
+        settings.languages.insert(
+            "Markdown".into(),
+            LanguageSettingsContent {
+                font_family: Some("Serif".into()),
+                font_size: Some(20.),
+                line_height: Some(1.5),
+                ..Default::default()
+            },
+        );
+    });
 
     let mut cx = EditorTestContext::new(cx).await;
-
-    // let buffer = MultiBuffer::build_simple("abc\ndefghi\n\njk\nlmno\n", cx);
-    cx.set_state(indoc!(
-        r#"abc
-           defˇghi
-
-           jk
-           nlmo
-           "#
-    ));
-
+    let (theme_font_family, theme_font_size, theme_line_height) = cx.update(|cx| {
+        let settings = ThemeSettings::get_global(cx);
+        (
+            settings.buffer_font.family.clone(),
+            settings.buffer_font_size,
+            settings.buffer_line_height.value(),
+        )
+    });
     cx.update_editor(|editor, cx| {
-        editor.add_selection_above(&Default::default(), cx);
+        assert_eq!(editor.buffer_font_family(cx), theme_font_family);
+        assert_eq!(editor.buffer_font_size(cx), theme_font_size);
+        assert_eq!(editor.buffer_line_height(cx), theme_line_height);
     });
 
-    cx.assert_editor_state(indoc!(
+    let language = Arc::new(Language::new(
+        LanguageConfig {
+            name: "Markdown".into(),
+            ..Default::default()
+        },
+        None,
+    ));
+    cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+    cx.update_editor(|editor, cx| {
+        assert_eq!(editor.buffer_font_family(cx), SharedString::from("Serif"));
+        assert_eq!(editor.buffer_font_size(cx), px(20.));
+        assert_eq!(editor.buffer_line_height(cx), 1.5);
+
+        // The editor's own font size adjustment applies on top of the language's font size.
+        editor.increase_font_size(&IncreaseFontSize, cx);
+        assert_eq!(editor.buffer_font_size(cx), px(21.));
+        editor.reset_font_size(&ResetFontSize, cx);
+        assert_eq!(editor.buffer_font_size(cx), px(20.));
+    });
+}
+
+#[gpui::test]
+async fn test_font_features_overrides(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+    let mut markdown_features = FontFeatures::default();
+    markdown_features.set_ligatures(false);
+    update_test_language_settings(cx, |settings| {
+        settings.languages.insert(
+            "Markdown".into(),
+            LanguageSettingsContent {
+                font_features: Some(markdown_features),
+                ..Default::default()
+            },
+        );
+    });
+
+    let mut cx = EditorTestContext::new(cx).await;
+    cx.update_editor(|editor, cx| {
+        assert!(editor.buffer_font_features(cx).ligatures());
+        assert_eq!(editor.buffer_font_features(cx).calt(), None);
+    });
+
+    let language = Arc::new(Language::new(
+        LanguageConfig {
+            name: "Markdown".into(),
+            ..Default::default()
+        },
+        None,
+    ));
+    cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+    cx.update_editor(|editor, cx| {
+        assert!(!editor.buffer_font_features(cx).ligatures());
+        assert_eq!(editor.buffer_font_features(cx).liga(), Some(false));
+
+        // Toggling ligatures overrides the language's font features.
+        editor.toggle_ligatures(&ToggleLigatures, cx);
+        assert!(editor.buffer_font_features(cx).ligatures());
+        assert_eq!(editor.buffer_font_features(cx).calt(), Some(true));
+        assert_eq!(editor.buffer_font_features(cx).liga(), Some(true));
+        editor.toggle_ligatures(&ToggleLigatures, cx);
+        assert!(!editor.buffer_font_features(cx).ligatures());
+    });
+}
+
+#[gpui::test]
+async fn test_wrap_guides(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |settings| {
+        settings.defaults.soft_wrap = Some(language_settings::SoftWrap::PreferredLineLength);
+        settings.defaults.preferred_line_length = Some(60);
+        settings.defaults.wrap_guides = Some(
+            serde_json::from_value(json!([
+                80,
+                { "column": 100, "color": "#ff000080", "style": "dashed" },
+            ]))
+            .unwrap(),
+        );
+    });
+
+    let mut cx = EditorTestContext::new(cx).await;
+    cx.update_editor(|editor, cx| {
+        assert_eq!(
+            editor.wrap_guides(cx).into_vec(),
+            vec![
+                (WrapGuide::Column(60), true),
+                (WrapGuide::Column(80), false),
+                (
+                    WrapGuide::Styled {
+                        column: 100,
+                        color: Some(gpui::rgba(0xff000080)),
+                        style: language_settings::WrapGuideStyle::Dashed,
+                    },
+                    false
+                ),
+            ]
+        );
+
+        editor.set_show_wrap_guides(false, cx);
+        assert!(editor.wrap_guides(cx).is_empty());
+    });
+}
+
+#[gpui::test]
+async fn test_prose_paragraph_spacing(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+    let prose: ProseSettings = serde_json::from_value(json!({
+        "enabled": true,
+        "paragraph_spacing": true,
+    }))
+    .unwrap();
+    update_test_language_settings(cx, |settings| {
+        settings.languages.insert(
+            "Markdown".into(),
+            LanguageSettingsContent {
+                prose: Some(prose),
+                paragraph_spacing: Some(2),
+                ..Default::default()
+            },
+        );
+    });
+
+    let mut cx = EditorTestContext::new(cx).await;
+    let theme_line_height =
+        cx.update(|cx| ThemeSettings::get_global(cx).buffer_line_height.value());
+    cx.set_state(
+        "one
+twoˇ
+
+three",
+    );
+    cx.update_editor(|editor, cx| {
+        assert_eq!(editor.buffer_line_height(cx), theme_line_height);
+        assert_eq!(editor.display_text(cx), "one\ntwo\n\nthree");
+    });
+
+    let language = Arc::new(Language::new(
+        LanguageConfig {
+            name: "Markdown".into(),
+            ..Default::default()
+        },
+        None,
+    ));
+    cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+    cx.update_editor(|editor, cx| {
+        assert_eq!(editor.buffer_line_height(cx), prose.line_height);
+        assert_eq!(editor.display_text(cx), "one\ntwo\n\n\n\nthree");
+    });
+
+    // Edits update the spacing around the paragraphs they touch.
+    cx.update_editor(|editor, cx| {
+        editor.handle_input("\n\nfour", cx);
+        assert_eq!(editor.display_text(cx), "one\ntwo\n\n\n\nfour\n\n\n\nthree");
+        editor.undo(&Undo, cx);
+        assert_eq!(editor.display_text(cx), "one\ntwo\n\n\n\nthree");
+        editor.select_all(&SelectAll, cx);
+        editor.handle_input("one two", cx);
+        assert_eq!(editor.display_text(cx), "one two");
+    });
+}
+
+#[gpui::test]
+async fn test_paragraph_spacing(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |settings| {
+        settings.defaults.paragraph_spacing = Some(1);
+    });
+
+    // The spacing applies to buffers that aren't rendered as prose too.
+    let mut cx = EditorTestContext::new(cx).await;
+    cx.set_state("fn one() {}\nfn two() {}ˇ\n\nfn three() {}");
+    cx.update_editor(|editor, cx| {
+        assert!(editor.prose_settings(cx).is_none());
+        assert_eq!(
+            editor.display_text(cx),
+            "fn one() {}\nfn two() {}\n\n\nfn three() {}"
+        );
+    });
+
+    cx.update(|cx| {
+        cx.update_global::<SettingsStore, _>(|store, cx| {
+            store.update_user_settings::<AllLanguageSettings>(cx, |settings| {
+                settings.defaults.paragraph_spacing = Some(0);
+            });
+        });
+    });
+    cx.update_editor(|editor, cx| {
+        assert_eq!(
+            editor.display_text(cx),
+            "fn one() {}\nfn two() {}\n\nfn three() {}"
+        );
+    });
+}
+
+#[gpui::test]
+async fn test_large_paste(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorTestContext::new(cx).await;
+    let pasted_text = "a line of pasted text\n".repeat(100_000);
+    assert!(pasted_text.len() > LARGE_PASTE_THRESHOLD);
+
+    cx.set_state("one «twoˇ» three");
+    cx.update(|cx| cx.write_to_clipboard(ClipboardItem::new(pasted_text.clone())));
+    cx.update_editor(|e, cx| e.paste(&Paste, cx));
+    cx.run_until_parked();
+    cx.assert_editor_state(&format!("one {pasted_text}ˇ three"));
+
+    // The whole paste is undone in a single step.
+    cx.update_editor(|e, cx| e.undo(&Undo, cx));
+    cx.assert_editor_state("one «twoˇ» three");
+
+    // The editor can't be edited or undone while the paste is in progress.
+    cx.update_editor(|e, cx| {
+        e.paste(&Paste, cx);
+        e.handle_input("x", cx);
+        e.undo(&Undo, cx);
+    });
+    cx.run_until_parked();
+    cx.assert_editor_state(&format!("one {pasted_text}ˇ three"));
+    cx.update_editor(|e, cx| e.undo(&Undo, cx));
+    cx.assert_editor_state("one «twoˇ» three");
+
+    // Undoing the inserted text from elsewhere stops the paste.
+    cx.update_editor(|e, cx| e.paste(&Paste, cx));
+    while !cx.buffer_text().contains("pasted") {
+        assert!(cx.executor().tick());
+    }
+    cx.update_buffer(|buffer, cx| buffer.undo(cx));
+    cx.run_until_parked();
+    assert_eq!(cx.buffer_text(), "one two three");
+    cx.update_editor(|e, cx| assert!(e.large_paste.is_none() && !e.read_only(cx)));
+}
+
+#[gpui::test]
+async fn test_large_paste_like_regular_paste(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorTestContext::new(cx).await;
+    let language = Arc::new(Language::new(
+        LanguageConfig::default(),
+        Some(tree_sitter_rust::language()),
+    ));
+    cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+
+    // Entire lines are pasted above the cursor's line.
+    let lines = "a line of pasted text\n".repeat(100_000);
+    cx.set_state("one\ntwˇo\nthree");
+    cx.update(|cx| {
+        cx.write_to_clipboard(ClipboardItem::new(lines.clone()).with_metadata(vec![
+            ClipboardSelection {
+                len: lines.len(),
+                is_entire_line: true,
+                first_line_indent: 0,
+            },
+        ]))
+    });
+    cx.update_editor(|e, cx| e.paste(&Paste, cx));
+    cx.run_until_parked();
+    cx.assert_editor_state(&format!("one\n{lines}twˇo\nthree"));
+
+    // A pasted block keeps its relative indentation, its first line being indented like the
+    // line it's pasted into.
+    let block = format!("if x {{\n{}}}", "  y();\n".repeat(200_000));
+    assert!(block.len() > LARGE_PASTE_THRESHOLD);
+    cx.set_state("fn f() {\n    ˇ\n}\n");
+    cx.update(|cx| cx.write_to_clipboard(ClipboardItem::new(block)));
+    cx.update_editor(|e, cx| e.paste(&Paste, cx));
+    cx.run_until_parked();
+    cx.assert_editor_state(&format!(
+        "fn f() {{\n    if x {{\n{}    }}ˇ\n}}\n",
+        "      y();\n".repeat(200_000)
+    ));
+}
+
+#[gpui::test]
+fn test_select_all(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let view = cx.add_window(|cx| {
+        let buffer = MultiBuffer::build_simple("abc\nde\nfgh", cx);
+        build_editor(buffer, cx)
+    });
+    _ = view.update(cx, |view, cx| {
+        view.select_all(&SelectAll, cx);
+        assert_eq!(
+            view.selections.display_ranges(cx),
+            &[DisplayPoint::new(0, 0)..DisplayPoint::new(2, 3)]
+        );
+    });
+}
+
+#[gpui::test]
+fn test_toggle_read_only(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let view = cx.add_window(|cx| {
+        let buffer = MultiBuffer::build_simple("abc", cx);
+        build_editor(buffer, cx)
+    });
+    _ = view.update(cx, |view, cx| {
+        view.toggle_read_only(&ToggleReadOnly, cx);
+        assert!(view.read_only(cx));
+        assert_eq!(view.local_cursor_shape(cx), language::CursorShape::Hollow);
+        assert!(view.render_read_only_lock(cx).is_some());
+
+        view.handle_input("x", cx);
+        assert_eq!(view.text(cx), "abc");
+
+        view.toggle_read_only(&ToggleReadOnly, cx);
+        assert!(!view.read_only(cx));
+        assert_eq!(view.local_cursor_shape(cx), language::CursorShape::Bar);
+        assert!(view.render_read_only_lock(cx).is_none());
+
+        view.handle_input("x", cx);
+        assert_eq!(view.text(cx), "xabc");
+    });
+}
+
+#[gpui::test]
+async fn test_read_only_ignores_edit_actions(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorTestContext::new(cx).await;
+    cx.set_state(indoc! {"
+        «b
+        aˇ»
+    "});
+    cx.update_editor(|e, cx| e.toggle_read_only(&ToggleReadOnly, cx));
+
+    cx.dispatch_action(SortLinesCaseSensitive);
+    cx.dispatch_action(ReverseLines);
+    cx.dispatch_action(ConvertToUpperCase);
+    cx.dispatch_action(Indent);
+    cx.dispatch_action(DeleteLine);
+    cx.dispatch_action(MoveLineDown);
+    cx.dispatch_action(Cut);
+    cx.dispatch_action(Newline);
+    cx.dispatch_action(NewlineAbove);
+    cx.dispatch_action(NewlineBelow);
+    cx.assert_editor_state(indoc! {"
+        «b
+        aˇ»
+    "});
+
+    // Selecting and copying text still works.
+    cx.dispatch_action(SelectAll);
+    cx.dispatch_action(Copy);
+    cx.assert_editor_state(indoc! {"
+        «b
+        a
+        ˇ»"});
+    assert_eq!(
+        cx.read_from_clipboard().map(|item| item.text().to_owned()),
+        Some("b\na\n".to_owned())
+    );
+
+    // Pressing enter between brackets on the first row leaves the cursor in place.
+    let language = Arc::new(Language::new(
+        LanguageConfig {
+            brackets: BracketPairConfig {
+                pairs: vec![BracketPair {
+                    start: "{".to_string(),
+                    end: "}".to_string(),
+                    close: true,
+                    newline: true,
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        Some(tree_sitter_rust::language()),
+    ));
+    cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+    cx.update_editor(|e, cx| e.toggle_read_only(&ToggleReadOnly, cx));
+    cx.set_state("{ˇ}");
+    cx.update_editor(|e, cx| e.toggle_read_only(&ToggleReadOnly, cx));
+    cx.dispatch_action(Newline);
+    cx.dispatch_action(NewlineAbove);
+    cx.dispatch_action(NewlineBelow);
+    cx.assert_editor_state("{ˇ}");
+}
+
+#[gpui::test]
+fn test_expand_excerpts_by_configured_lines(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+    _ = cx.update(|cx| {
+        cx.update_global::<SettingsStore, _>(|settings, cx| {
+            settings.update_user_settings::<EditorSettings>(cx, |settings| {
+                settings.excerpt_header = Some(ExcerptHeaderContent {
+                    expand_lines: Some(1),
+                    ..Default::default()
+                });
+            });
+        })
+    });
+
+    let buffer = cx.new_model(|cx| {
+        Buffer::new(
+            0,
+            BufferId::new(cx.entity_id().as_u64()).unwrap(),
+            sample_text(8, 4, 'a'),
+        )
+    });
+    let multibuffer = cx.new_model(|cx| {
+        let mut multibuffer = MultiBuffer::new(0, ReadWrite);
+        multibuffer.push_excerpts(
+            buffer.clone(),
+            [ExcerptRange {
+                context: Point::new(3, 0)..Point::new(4, 4),
+                primary: None,
+            }],
+            cx,
+        );
+        multibuffer
+    });
+
+    let view = cx.add_window(|cx| build_editor(multibuffer.clone(), cx));
+    _ = view.update(cx, |view, cx| {
+        assert_eq!(view.text(cx), "dddd\neeee");
+
+        // Without an explicit line count, excerpts grow by the configured number of lines.
+        view.expand_excerpts_down(&ExpandExcerptsDown::default(), cx);
+        assert_eq!(view.text(cx), "dddd\neeee\nffff");
+        view.expand_excerpts_up(&ExpandExcerptsUp::default(), cx);
+        assert_eq!(view.text(cx), "cccc\ndddd\neeee\nffff");
+
+        let snapshot = multibuffer.read(cx).snapshot(cx);
+        let excerpt_id = snapshot.excerpts().next().unwrap().0;
+        let range = snapshot.range_for_excerpt(excerpt_id).unwrap();
+        let buffer = buffer.read(cx);
+        assert_eq!(
+            language::ToPoint::to_point(&range.context.start, buffer),
+            Point::new(2, 0)
+        );
+        assert_eq!(
+            language::ToPoint::to_point(&range.context.end, buffer),
+            Point::new(5, 4)
+        );
+    });
+}
+
+#[gpui::test]
+async fn test_excerpt_header_file_actions(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree("/a", json!({ "main.rs": "one\ntwo\nthree\nfour\n" }))
+        .await;
+    let project = Project::test(fs, ["/a".as_ref()], cx).await;
+    let workspace = cx.add_window(|cx| Workspace::test_new(project.clone(), cx));
+    let cx = &mut VisualTestContext::from_window(*workspace, cx);
+
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer("/a/main.rs", cx)
+        })
+        .await
+        .unwrap();
+    let multibuffer = cx.new_model(|cx| {
+        let mut multibuffer = MultiBuffer::new(0, ReadWrite);
+        multibuffer.push_excerpts(
+            buffer.clone(),
+            [ExcerptRange {
+                context: Point::new(2, 0)..Point::new(3, 4),
+                primary: None,
+            }],
+            cx,
+        );
+        multibuffer
+    });
+    let editor = cx.new_view(|cx| Editor::for_multibuffer(multibuffer, Some(project.clone()), cx));
+    workspace
+        .update(cx, |workspace, cx| {
+            workspace.add_item(Box::new(editor.clone()), cx)
+        })
+        .unwrap();
+
+    let excerpt_id = editor.update(cx, |editor, cx| {
+        let excerpt_id = editor.buffer.read(cx).excerpt_ids()[0];
+        editor.copy_excerpt_path(excerpt_id, cx);
+        excerpt_id
+    });
+    assert_eq!(
+        cx.read_from_clipboard().map(|item| item.text().to_owned()),
+        Some("/a/main.rs".to_owned())
+    );
+
+    // The excerpt's file is opened in a new pane, at the start of the excerpt.
+    editor.update(cx, |editor, cx| {
+        editor.open_excerpt_in_split(excerpt_id, cx)
+    });
+    cx.run_until_parked();
+    let split_editor = workspace
+        .update(cx, |workspace, cx| {
+            assert_eq!(workspace.panes().len(), 2);
+            workspace.active_item_as::<Editor>(cx).unwrap()
+        })
+        .unwrap();
+    assert_ne!(split_editor.entity_id(), editor.entity_id());
+    split_editor.update(cx, |editor, cx| {
+        assert_eq!(editor.text(cx), "one\ntwo\nthree\nfour\n");
+        assert_eq!(
+            editor.selections.newest::<Point>(cx).range(),
+            Point::new(2, 0)..Point::new(2, 0)
+        );
+    });
+}
+
+#[gpui::test]
+fn test_select_all_in_excerpt(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let buffer = cx.new_model(|cx| {
+        Buffer::new(
+            0,
+            BufferId::new(cx.entity_id().as_u64()).unwrap(),
+            sample_text(6, 4, 'a'),
+        )
+    });
+    let multibuffer = cx.new_model(|cx| {
+        let mut multibuffer = MultiBuffer::new(0, ReadWrite);
+        multibuffer.push_excerpts(
+            buffer.clone(),
+            [
+                ExcerptRange {
+                    context: Point::new(0, 0)..Point::new(1, 4),
+                    primary: None,
+                },
+                ExcerptRange {
+                    context: Point::new(3, 0)..Point::new(4, 4),
+                    primary: None,
+                },
+            ],
+            cx,
+        );
+        assert_eq!(multibuffer.read(cx).text(), "aaaa\nbbbb\ndddd\neeee");
+        multibuffer
+    });
+
+    let view = cx.add_window(|cx| build_editor(multibuffer, cx));
+    _ = view.update(cx, |view, cx| {
+        view.change_selections(None, cx, |s| s.select_ranges([12..12]));
+        view.select_all_in_excerpt(&SelectAllInExcerpt, cx);
+        assert_eq!(view.selections.ranges::<usize>(cx), &[10..19]);
+
+        view.change_selections(None, cx, |s| s.select_ranges([2..2]));
+        view.select_all_in_excerpt(&SelectAllInExcerpt, cx);
+        assert_eq!(view.selections.ranges::<usize>(cx), &[0..9]);
+    });
+}
+
+#[gpui::test]
+fn test_select_line(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let view = cx.add_window(|cx| {
+        let buffer = MultiBuffer::build_simple(&sample_text(6, 5, 'a'), cx);
+        build_editor(buffer, cx)
+    });
+    _ = view.update(cx, |view, cx| {
+        view.change_selections(None, cx, |s| {
+            s.select_display_ranges([
+                DisplayPoint::new(0, 0)..DisplayPoint::new(0, 1),
+                DisplayPoint::new(0, 2)..DisplayPoint::new(0, 2),
+                DisplayPoint::new(1, 0)..DisplayPoint::new(1, 0),
+                DisplayPoint::new(4, 2)..DisplayPoint::new(4, 2),
+            ])
+        });
+        view.select_line(&SelectLine, cx);
+        assert_eq!(
+            view.selections.display_ranges(cx),
+            vec![
+                DisplayPoint::new(0, 0)..DisplayPoint::new(2, 0),
+                DisplayPoint::new(4, 0)..DisplayPoint::new(5, 0),
+            ]
+        );
+    });
+
+    _ = view.update(cx, |view, cx| {
+        view.select_line(&SelectLine, cx);
+        assert_eq!(
+            view.selections.display_ranges(cx),
+            vec![
+                DisplayPoint::new(0, 0)..DisplayPoint::new(3, 0),
+                DisplayPoint::new(4, 0)..DisplayPoint::new(5, 5),
+            ]
+        );
+    });
+
+    _ = view.update(cx, |view, cx| {
+        view.select_line(&SelectLine, cx);
+        assert_eq!(
+            view.selections.display_ranges(cx),
+            vec![DisplayPoint::new(0, 0)..DisplayPoint::new(5, 5)]
+        );
+    });
+}
+
+#[gpui::test]
+fn test_split_selection_into_lines(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let view = cx.add_window(|cx| {
+        let buffer = MultiBuffer::build_simple(&sample_text(9, 5, 'a'), cx);
+        build_editor(buffer, cx)
+    });
+    _ = view.update(cx, |view, cx| {
+        view.fold_ranges(
+            vec![
+                Point::new(0, 2)..Point::new(1, 2),
+                Point::new(2, 3)..Point::new(4, 1),
+                Point::new(7, 0)..Point::new(8, 4),
+            ],
+            true,
+            cx,
+        );
+        view.change_selections(None, cx, |s| {
+            s.select_display_ranges([
+                DisplayPoint::new(0, 0)..DisplayPoint::new(0, 1),
+                DisplayPoint::new(0, 2)..DisplayPoint::new(0, 2),
+                DisplayPoint::new(1, 0)..DisplayPoint::new(1, 0),
+                DisplayPoint::new(4, 4)..DisplayPoint::new(4, 4),
+            ])
+        });
+        assert_eq!(view.display_text(cx), "aa⋯bbb\nccc⋯eeee\nfffff\nggggg\n⋯i");
+    });
+
+    _ = view.update(cx, |view, cx| {
+        view.split_selection_into_lines(&SplitSelectionIntoLines, cx);
+        assert_eq!(
+            view.display_text(cx),
+            "aaaaa\nbbbbb\nccc⋯eeee\nfffff\nggggg\n⋯i"
+        );
+        assert_eq!(
+            view.selections.display_ranges(cx),
+            [
+                DisplayPoint::new(0, 1)..DisplayPoint::new(0, 1),
+                DisplayPoint::new(0, 2)..DisplayPoint::new(0, 2),
+                DisplayPoint::new(2, 0)..DisplayPoint::new(2, 0),
+                DisplayPoint::new(5, 4)..DisplayPoint::new(5, 4)
+            ]
+        );
+    });
+
+    _ = view.update(cx, |view, cx| {
+        view.change_selections(None, cx, |s| {
+            s.select_display_ranges([DisplayPoint::new(5, 0)..DisplayPoint::new(0, 1)])
+        });
+        view.split_selection_into_lines(&SplitSelectionIntoLines, cx);
+        assert_eq!(
+            view.display_text(cx),
+            "aaaaa\nbbbbb\nccccc\nddddd\neeeee\nfffff\nggggg\nhhhhh\niiiii"
+        );
+        assert_eq!(
+            view.selections.display_ranges(cx),
+            [
+                DisplayPoint::new(0, 5)..DisplayPoint::new(0, 5),
+                DisplayPoint::new(1, 5)..DisplayPoint::new(1, 5),
+                DisplayPoint::new(2, 5)..DisplayPoint::new(2, 5),
+                DisplayPoint::new(3, 5)..DisplayPoint::new(3, 5),
+                DisplayPoint::new(4, 5)..DisplayPoint::new(4, 5),
+                DisplayPoint::new(5, 5)..DisplayPoint::new(5, 5),
+                DisplayPoint::new(6, 5)..DisplayPoint::new(6, 5),
+                DisplayPoint::new(7, 0)..DisplayPoint::new(7, 0)
+            ]
+        );
+    });
+}
+
+#[gpui::test]
+async fn test_add_cursors_to_lines(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorTestContext::new(cx).await;
+    let initial_state = indoc! {"
+        a«bc
+        defghij
+        k
+        lmnoˇ»p
+        «qrs
+        ˇ»tuv"};
+
+    cx.set_state(initial_state);
+    cx.update_editor(|e, cx| e.add_cursors_to_line_starts(&AddCursorsToLineStarts, cx));
+    cx.assert_editor_state(indoc! {"
+        ˇabc
+        ˇdefghij
+        ˇk
+        ˇlmnop
+        ˇqrs
+        tuv"});
+
+    cx.set_state(initial_state);
+    cx.update_editor(|e, cx| e.add_cursors_to_line_ends(&AddCursorsToLineEnds, cx));
+    cx.assert_editor_state(indoc! {"
+        abcˇ
+        defghijˇ
+        kˇ
+        lmnopˇ
+        qrsˇ
+        tuv"});
+
+    // Lines shorter than the column of the head get a cursor at their end.
+    cx.set_state(initial_state);
+    cx.update_editor(|e, cx| e.add_cursors_at_column(&AddCursorsAtColumn::default(), cx));
+    cx.assert_editor_state(indoc! {"
+        abcˇ
+        defgˇhij
+        kˇ
+        lmnoˇp
+        ˇqrs
+        tuv"});
+
+    // Or are padded up to the column.
+    cx.set_state(initial_state);
+    cx.update_editor(|e, cx| {
+        e.add_cursors_at_column(
+            &AddCursorsAtColumn {
+                pad_short_lines: true,
+            },
+            cx,
+        )
+    });
+    cx.assert_editor_state(indoc! {"
+        abc ˇ
+        defgˇhij
+        k   ˇ
+        lmnoˇp
+        ˇqrs
+        tuv"});
+}
+
+#[gpui::test]
+async fn test_add_selection_above_below(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorTestContext::new(cx).await;
+
+    // let buffer = MultiBuffer::build_simple("abc\ndefghi\n\njk\nlmno\n", cx);
+    cx.set_state(indoc!(
+        r#"abc
+           defˇghi
+
+           jk
+           nlmo
+           "#
+    ));
+
+    cx.update_editor(|editor, cx| {
+        editor.add_selection_above(&Default::default(), cx);
+    });
+
+    cx.assert_editor_state(indoc!(
         r#"abcˇ
            defˇghi
 
@@ -4004,6 +5317,64 @@ async fn test_select_all_matches(cx: &mut gpui::TestAppContext) {
     cx.assert_editor_state("«abcˇ»\n«abcˇ» «abcˇ»\ndefabc\n«abcˇ»");
 }
 
+#[gpui::test]
+async fn test_undo_last_selection_addition(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorTestContext::new(cx).await;
+    cx.set_state("abc\nˇabc abc\ndefabc\nabc");
+
+    cx.update_editor(|e, cx| e.select_next(&SelectNext::default(), cx))
+        .unwrap();
+    cx.update_editor(|e, cx| e.select_next(&SelectNext::default(), cx))
+        .unwrap();
+    cx.assert_editor_state("abc\n«abcˇ» «abcˇ»\ndefabc\nabc");
+
+    // An unwanted match is skipped by replacing the newest selection.
+    cx.update_editor(|e, cx| {
+        e.select_next(
+            &SelectNext {
+                replace_newest: true,
+            },
+            cx,
+        )
+    })
+    .unwrap();
+    cx.assert_editor_state("abc\n«abcˇ» abc\ndefabc\n«abcˇ»");
+
+    cx.update_editor(|e, cx| e.undo_last_selection_addition(&UndoLastSelectionAddition, cx));
+    cx.assert_editor_state("abc\n«abcˇ» abc\ndefabc\nabc");
+
+    // The last selection is never removed.
+    cx.update_editor(|e, cx| e.undo_last_selection_addition(&UndoLastSelectionAddition, cx));
+    cx.assert_editor_state("abc\n«abcˇ» abc\ndefabc\nabc");
+
+    cx.update_editor(|e, cx| e.select_next(&SelectNext::default(), cx))
+        .unwrap();
+    cx.assert_editor_state("abc\n«abcˇ» «abcˇ»\ndefabc\nabc");
+}
+
+#[gpui::test]
+async fn test_select_all_occurrences_of_each_cursor(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorTestContext::new(cx).await;
+    cx.set_state("foo bar\nbaˇr foo\nfˇoo barfoo");
+    cx.update_editor(|e, cx| {
+        e.select_all_occurrences_of_each_cursor(&SelectAllOccurrencesOfEachCursor, cx)
+    })
+    .unwrap();
+    cx.assert_editor_state("«fooˇ» «barˇ»\n«barˇ» «fooˇ»\n«fooˇ» barfoo");
+
+    // Selected text matches within words.
+    cx.set_state("«fooˇ» bar\nbar «aˇ»\nfoo barfoo");
+    cx.update_editor(|e, cx| {
+        e.select_all_occurrences_of_each_cursor(&SelectAllOccurrencesOfEachCursor, cx)
+    })
+    .unwrap();
+    cx.assert_editor_state("«fooˇ» b«aˇ»r\nb«aˇ»r «aˇ»\n«fooˇ» b«aˇ»r«fooˇ»");
+}
+
 #[gpui::test]
 async fn test_select_next_with_multiple_carets(cx: &mut gpui::TestAppContext) {
     init_test(cx, |_| {});
@@ -4133,9 +5504,75 @@ async fn test_select_previous_with_single_selection(cx: &mut gpui::TestAppContex
         .unwrap();
     cx.assert_editor_state("«abcˇ»\n«ˇabc» abc\ndef«abcˇ»\n«abcˇ»");
 
-    cx.update_editor(|e, cx| e.select_previous(&SelectPrevious::default(), cx))
-        .unwrap();
-    cx.assert_editor_state("«abcˇ»\n«ˇabc» «abcˇ»\ndef«abcˇ»\n«abcˇ»");
+    cx.update_editor(|e, cx| e.select_previous(&SelectPrevious::default(), cx))
+        .unwrap();
+    cx.assert_editor_state("«abcˇ»\n«ˇabc» «abcˇ»\ndef«abcˇ»\n«abcˇ»");
+}
+
+#[gpui::test]
+async fn test_move_selection_by_syntax_node(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+    let mut cx = EditorTestContext::new(cx).await;
+
+    // Without a syntax tree, the selected lines are moved.
+    cx.set_state(indoc! {"
+        one
+        twˇo
+    "});
+    cx.update_editor(|e, cx| e.move_selection_up(&MoveSelectionUp, cx));
+    cx.assert_editor_state(indoc! {"
+        twˇo
+        one
+    "});
+
+    let language = Arc::new(Language::new(
+        LanguageConfig::default(),
+        Some(tree_sitter_rust::language()),
+    ));
+    cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+
+    cx.set_state(indoc! {"
+        fn a(«b: u8ˇ», c: u16) {
+            f(xˇx, y);
+        }
+    "});
+    cx.executor().run_until_parked();
+    cx.update_editor(|e, cx| e.move_selection_down(&MoveSelectionDown, cx));
+    cx.assert_editor_state(indoc! {"
+        fn a(c: u16, «b: u8ˇ») {
+            f(y, xˇx);
+        }
+    "});
+
+    cx.set_state(indoc! {"
+        fn a() {
+            let b = 1;
+            «let c = 2;ˇ»
+        }
+    "});
+    cx.executor().run_until_parked();
+    cx.update_editor(|e, cx| e.move_selection_up(&MoveSelectionUp, cx));
+    cx.assert_editor_state(indoc! {"
+        fn a() {
+            «let c = 2;ˇ»
+            let b = 1;
+        }
+    "});
+
+    // A selection in a node that overlaps the nodes swapped for another selection stays where
+    // it is.
+    cx.set_state(indoc! {"
+        fn a() {
+            f(ˇx, yˇ, z);
+        }
+    "});
+    cx.executor().run_until_parked();
+    cx.update_editor(|e, cx| e.move_selection_down(&MoveSelectionDown, cx));
+    cx.assert_editor_state(indoc! {"
+        fn a() {
+            f(y, ˇxˇ, z);
+        }
+    "});
 }
 
 #[gpui::test]
@@ -4284,6 +5721,40 @@ async fn test_select_larger_smaller_syntax_node(cx: &mut gpui::TestAppContext) {
     );
 }
 
+#[gpui::test]
+async fn test_syntax_node_selection_history_survives_edits(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+    let mut cx = EditorTestContext::new(cx).await;
+    let language = Arc::new(Language::new(
+        LanguageConfig::default(),
+        Some(tree_sitter_rust::language()),
+    ));
+    cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+
+    cx.set_state("fn a() { f(bˇb + c, d); }");
+    cx.executor().run_until_parked();
+    cx.update_editor(|e, cx| {
+        e.select_larger_syntax_node(&SelectLargerSyntaxNode, cx);
+        e.select_larger_syntax_node(&SelectLargerSyntaxNode, cx);
+    });
+    cx.assert_editor_state("fn a() { f(«bb + cˇ», d); }");
+
+    // Edits outside of the selections don't discard the history.
+    cx.update_buffer(|buffer, cx| buffer.edit([(0..0, "// x\n")], None, cx));
+    cx.update_editor(|e, cx| e.select_smaller_syntax_node(&SelectSmallerSyntaxNode, cx));
+    cx.assert_editor_state("// x\nfn a() { f(«bbˇ» + c, d); }");
+
+    cx.update_editor(|e, cx| e.select_syntax_node_sibling_next(&SelectSyntaxNodeSiblingNext, cx));
+    cx.assert_editor_state("// x\nfn a() { f(bb + «cˇ», d); }");
+    cx.update_editor(|e, cx| e.select_syntax_node_sibling_prev(&SelectSyntaxNodeSiblingPrev, cx));
+    cx.assert_editor_state("// x\nfn a() { f(«bbˇ» + c, d); }");
+
+    // Moving the selections elsewhere discards the history.
+    cx.set_state("// x\nfn a() { f(bb + c, dˇ); }");
+    cx.update_editor(|e, cx| e.select_smaller_syntax_node(&SelectSmallerSyntaxNode, cx));
+    cx.assert_editor_state("// x\nfn a() { f(bb + c, dˇ); }");
+}
+
 #[gpui::test]
 async fn test_autoindent_selections(cx: &mut gpui::TestAppContext) {
     init_test(cx, |_| {});
@@ -5012,6 +6483,43 @@ async fn test_surround_with_pair(cx: &mut gpui::TestAppContext) {
     });
 }
 
+#[gpui::test]
+async fn test_surround_with_pair_disabled(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |settings| {
+        settings.defaults.use_auto_surround = Some(false);
+    });
+
+    let mut cx = EditorTestContext::new(cx).await;
+    let language = Arc::new(Language::new(
+        LanguageConfig {
+            brackets: BracketPairConfig {
+                pairs: vec![BracketPair {
+                    start: "(".to_string(),
+                    end: ")".to_string(),
+                    close: true,
+                    newline: true,
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        Some(tree_sitter_rust::language()),
+    ));
+    cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+
+    // Typing an opening bracket replaces the selection instead of surrounding it.
+    cx.set_state("a «bˇ» c");
+    cx.update_editor(|view, cx| view.handle_input("(", cx));
+    cx.assert_editor_state("a (ˇ c");
+
+    // Brackets are still auto-closed, and typed over, at empty selections.
+    cx.set_state("a ˇ c");
+    cx.update_editor(|view, cx| view.handle_input("(", cx));
+    cx.assert_editor_state("a (ˇ) c");
+    cx.update_editor(|view, cx| view.handle_input(")", cx));
+    cx.assert_editor_state("a ()ˇ c");
+}
+
 #[gpui::test]
 async fn test_delete_autoclose_pair(cx: &mut gpui::TestAppContext) {
     init_test(cx, |_| {});
@@ -5140,93 +6648,365 @@ async fn test_snippets(cx: &mut gpui::TestAppContext) {
     _ = editor.update(cx, |editor, cx| {
         let snippet = Snippet::parse("f(${1:one}, ${2:two}, ${1:three})$0").unwrap();
 
-        editor
-            .insert_snippet(&insertion_ranges, snippet, cx)
-            .unwrap();
+        editor
+            .insert_snippet(&insertion_ranges, snippet, cx)
+            .unwrap();
+
+        fn assert(editor: &mut Editor, cx: &mut ViewContext<Editor>, marked_text: &str) {
+            let (expected_text, selection_ranges) = marked_text_ranges(marked_text, false);
+            assert_eq!(editor.text(cx), expected_text);
+            assert_eq!(editor.selections.ranges::<usize>(cx), selection_ranges);
+        }
+
+        assert(
+            editor,
+            cx,
+            indoc! {"
+                a.f(«one», two, «three») b
+                a.f(«one», two, «three») b
+                a.f(«one», two, «three») b
+            "},
+        );
+
+        // Can't move earlier than the first tab stop
+        assert!(!editor.move_to_prev_snippet_tabstop(cx));
+        assert(
+            editor,
+            cx,
+            indoc! {"
+                a.f(«one», two, «three») b
+                a.f(«one», two, «three») b
+                a.f(«one», two, «three») b
+            "},
+        );
+
+        assert!(editor.move_to_next_snippet_tabstop(cx));
+        assert(
+            editor,
+            cx,
+            indoc! {"
+                a.f(one, «two», three) b
+                a.f(one, «two», three) b
+                a.f(one, «two», three) b
+            "},
+        );
+
+        editor.move_to_prev_snippet_tabstop(cx);
+        assert(
+            editor,
+            cx,
+            indoc! {"
+                a.f(«one», two, «three») b
+                a.f(«one», two, «three») b
+                a.f(«one», two, «three») b
+            "},
+        );
+
+        assert!(editor.move_to_next_snippet_tabstop(cx));
+        assert(
+            editor,
+            cx,
+            indoc! {"
+                a.f(one, «two», three) b
+                a.f(one, «two», three) b
+                a.f(one, «two», three) b
+            "},
+        );
+        assert!(editor.move_to_next_snippet_tabstop(cx));
+        assert(
+            editor,
+            cx,
+            indoc! {"
+                a.f(one, two, three)ˇ b
+                a.f(one, two, three)ˇ b
+                a.f(one, two, three)ˇ b
+            "},
+        );
+
+        // As soon as the last tab stop is reached, snippet state is gone
+        editor.move_to_prev_snippet_tabstop(cx);
+        assert(
+            editor,
+            cx,
+            indoc! {"
+                a.f(one, two, three)ˇ b
+                a.f(one, two, three)ˇ b
+                a.f(one, two, three)ˇ b
+            "},
+        );
+    });
+}
+
+#[gpui::test]
+async fn test_snippet_placeholder_highlights(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorTestContext::new(cx).await;
+    cx.set_state("a.ˇ b");
+    cx.update_editor(|editor, cx| {
+        let snippet = Snippet::parse("f(${1:one}, ${2:two})$0").unwrap();
+        editor.insert_snippet(&[2..2], snippet, cx).unwrap();
+    });
+    cx.assert_editor_state("a.f(«oneˇ», two) b");
+    cx.assert_editor_background_highlights::<SnippetPlaceholderHighlights>("a.f(one, «two») b");
+
+    cx.update_editor(|editor, cx| assert!(editor.move_to_next_snippet_tabstop(cx)));
+    cx.assert_editor_state("a.f(one, «twoˇ») b");
+    cx.assert_editor_background_highlights::<SnippetPlaceholderHighlights>("a.f(«one», two) b");
+
+    // Once the last tab stop is reached, the placeholders aren't highlighted anymore.
+    cx.update_editor(|editor, cx| assert!(editor.move_to_next_snippet_tabstop(cx)));
+    cx.assert_editor_state("a.f(one, two)ˇ b");
+    cx.assert_editor_background_highlights::<SnippetPlaceholderHighlights>("a.f(one, two) b");
+
+    // Cancelling the snippet session clears them as well.
+    cx.update_editor(|editor, cx| {
+        let snippet = Snippet::parse("g(${1:x}, ${2:y})").unwrap();
+        editor.insert_snippet(&[0..0], snippet, cx).unwrap();
+    });
+    cx.assert_editor_background_highlights::<SnippetPlaceholderHighlights>(
+        "g(x, «y»)a.f(one, two) b",
+    );
+    cx.update_editor(|editor, cx| editor.cancel(&Cancel, cx));
+    cx.assert_editor_background_highlights::<SnippetPlaceholderHighlights>(
+        "g(x, y)a.f(one, two) b",
+    );
+}
+
+#[gpui::test]
+async fn test_autosave_defers_formatting_while_focused(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut language = Language::new(
+        LanguageConfig {
+            name: "Rust".into(),
+            matcher: LanguageMatcher {
+                path_suffixes: vec!["rs".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        Some(tree_sitter_rust::language()),
+    );
+    let mut fake_servers = language
+        .set_fake_lsp_adapter(Arc::new(FakeLspAdapter {
+            capabilities: lsp::ServerCapabilities {
+                document_formatting_provider: Some(lsp::OneOf::Left(true)),
+                ..Default::default()
+            },
+            ..Default::default()
+        }))
+        .await;
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_file("/file.rs", Default::default()).await;
+
+    let project = Project::test(fs, ["/file.rs".as_ref()], cx).await;
+    _ = project.update(cx, |project, _| project.languages().add(Arc::new(language)));
+    let buffer = project
+        .update(cx, |project, cx| project.open_local_buffer("/file.rs", cx))
+        .await
+        .unwrap();
+
+    cx.executor().start_waiting();
+    let fake_server = fake_servers.next().await.unwrap();
+    fake_server.handle_request::<lsp::request::Formatting, _, _>(move |_, _| async move {
+        panic!("formatting should not be requested while the editor is focused")
+    });
+
+    let buffer = cx.new_model(|cx| MultiBuffer::singleton(buffer, cx));
+    let (editor, cx) = cx.add_window_view(|cx| build_editor(buffer, cx));
+    _ = editor.update(cx, |editor, cx| {
+        editor.focus(cx);
+        editor.set_text("one\ntwo\nthree\n", cx);
+    });
+    assert!(cx.read(|cx| editor.is_dirty(cx)));
+
+    let save = editor.update(cx, |editor, cx| editor.autosave(project.clone(), cx));
+    save.await.unwrap();
+    assert_eq!(
+        editor.update(cx, |editor, cx| editor.text(cx)),
+        "one\ntwo\nthree\n"
+    );
+    assert!(!cx.read(|cx| editor.is_dirty(cx)));
+
+    // The buffer is formatted and saved again once the editor loses focus.
+    fake_server.handle_request::<lsp::request::Formatting, _, _>(move |_, _| async move {
+        Ok(Some(vec![lsp::TextEdit::new(
+            lsp::Range::new(lsp::Position::new(0, 3), lsp::Position::new(1, 0)),
+            ", ".to_string(),
+        )]))
+    });
+    _ = editor.update(cx, |editor, cx| editor.handle_blur(cx));
+    cx.executor().run_until_parked();
+    assert_eq!(
+        editor.update(cx, |editor, cx| editor.text(cx)),
+        "one, two\nthree\n"
+    );
+    assert!(!cx.read(|cx| editor.is_dirty(cx)));
+}
+
+#[gpui::test]
+async fn test_conflict_banner(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree("/dir", json!({ "file.txt": "one\ntwo\n" }))
+        .await;
+    let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer("/dir/file.txt", cx)
+        })
+        .await
+        .unwrap();
+
+    let buffer = cx.new_model(|cx| MultiBuffer::singleton(buffer, cx));
+    let (editor, cx) = cx.add_window_view(|cx| build_editor(buffer, cx));
+    _ = editor.update(cx, |editor, cx| {
+        editor.insert("zero ", cx);
+        assert!(editor.conflict_banner.is_none());
+    });
+
+    fs.save(
+        "/dir/file.txt".as_ref(),
+        &"one\ntwo\nthree\n".into(),
+        language::LineEnding::Unix,
+    )
+    .await
+    .unwrap();
+    cx.executor().run_until_parked();
+    _ = editor.update(cx, |editor, cx| {
+        assert!(editor.conflict_banner.is_some());
+
+        // Reverting the unsaved edits resolves the conflict.
+        editor.undo(&Undo, cx);
+        assert!(editor.conflict_banner.is_none());
+    });
+}
+
+#[gpui::test]
+async fn test_open_unwritable_file_read_only(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/dir",
+        json!({ "locked.txt": "one\n", "open.txt": "two\n" }),
+    )
+    .await;
+    fs.set_read_only("/dir/locked.txt", true);
+    let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+
+    let mut editors = Vec::new();
+    for path in ["/dir/locked.txt", "/dir/open.txt"] {
+        let buffer = project
+            .update(cx, |project, cx| project.open_local_buffer(path, cx))
+            .await
+            .unwrap();
+        let project = project.clone();
+        let editor =
+            cx.add_window(|cx| <Editor as ProjectItem>::for_project_item(project, buffer, cx));
+        editors.push(editor);
+    }
+    cx.executor().run_until_parked();
+
+    _ = editors[0].update(cx, |editor, cx| assert!(editor.read_only(cx)));
+    _ = editors[1].update(cx, |editor, cx| assert!(!editor.read_only(cx)));
+
+    // Editors of files that can't be saved aren't unlocked.
+    _ = editors[0].update(cx, |editor, cx| {
+        editor.toggle_read_only(&ToggleReadOnly, cx);
+        assert!(editor.read_only(cx));
+    });
+}
 
-        fn assert(editor: &mut Editor, cx: &mut ViewContext<Editor>, marked_text: &str) {
-            let (expected_text, selection_ranges) = marked_text_ranges(marked_text, false);
-            assert_eq!(editor.text(cx), expected_text);
-            assert_eq!(editor.selections.ranges::<usize>(cx), selection_ranges);
-        }
+#[gpui::test]
+fn test_large_file_mode(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+    _ = cx.update(|cx| {
+        cx.update_global::<SettingsStore, _>(|settings, cx| {
+            settings.update_user_settings::<EditorSettings>(cx, |settings| {
+                settings.large_file = Some(LargeFileContent {
+                    max_lines: Some(10),
+                    ..Default::default()
+                });
+                settings.selection_highlight = Some(SelectionHighlightContent {
+                    enabled: Some(true),
+                    ..Default::default()
+                });
+            });
+        })
+    });
 
-        assert(
-            editor,
-            cx,
-            indoc! {"
-                a.f(«one», two, «three») b
-                a.f(«one», two, «three») b
-                a.f(«one», two, «three») b
-            "},
-        );
+    let editor = cx.add_window(|cx| {
+        let buffer = MultiBuffer::build_simple(&"line\n".repeat(5), cx);
+        build_editor(buffer, cx)
+    });
+    _ = editor.update(cx, |editor, cx| {
+        assert!(!editor.large_file_mode);
 
-        // Can't move earlier than the first tab stop
-        assert!(!editor.move_to_prev_snippet_tabstop(cx));
-        assert(
-            editor,
-            cx,
-            indoc! {"
-                a.f(«one», two, «three») b
-                a.f(«one», two, «three») b
-                a.f(«one», two, «three») b
-            "},
-        );
+        // Growing past the threshold enters large file mode.
+        editor.insert(&"line\n".repeat(10), cx);
+    });
+    _ = editor.update(cx, |editor, cx| {
+        assert!(editor.large_file_mode);
+        assert!(editor.large_file_banner.is_some());
+        assert!(editor.snapshot(cx).large_file_mode);
 
-        assert!(editor.move_to_next_snippet_tabstop(cx));
-        assert(
-            editor,
-            cx,
-            indoc! {"
-                a.f(one, «two», three) b
-                a.f(one, «two», three) b
-                a.f(one, «two», three) b
-            "},
-        );
+        // The other occurrences of the selected text aren't searched for.
+        editor.change_selections(None, cx, |s| s.select_ranges([0..4]));
+        assert!(editor.selection_highlights_task.is_none());
 
-        editor.move_to_prev_snippet_tabstop(cx);
-        assert(
-            editor,
-            cx,
-            indoc! {"
-                a.f(«one», two, «three») b
-                a.f(«one», two, «three») b
-                a.f(«one», two, «three») b
-            "},
-        );
+        // Turning the features back on leaves it.
+        editor.large_file_features_enabled = true;
+        large_file::refresh_large_file_mode(editor, cx);
+        assert!(!editor.large_file_mode);
+        assert!(editor.large_file_banner.is_none());
+        assert!(editor.selection_highlights_task.is_some());
+    });
+}
 
-        assert!(editor.move_to_next_snippet_tabstop(cx));
-        assert(
-            editor,
-            cx,
-            indoc! {"
-                a.f(one, «two», three) b
-                a.f(one, «two», three) b
-                a.f(one, «two», three) b
-            "},
-        );
-        assert!(editor.move_to_next_snippet_tabstop(cx));
-        assert(
-            editor,
-            cx,
-            indoc! {"
-                a.f(one, two, three)ˇ b
-                a.f(one, two, three)ˇ b
-                a.f(one, two, three)ˇ b
-            "},
-        );
+#[gpui::test]
+fn test_large_file_mode_in_multiple_editors(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+    _ = cx.update(|cx| {
+        cx.update_global::<SettingsStore, _>(|settings, cx| {
+            settings.update_user_settings::<EditorSettings>(cx, |settings| {
+                settings.large_file = Some(LargeFileContent {
+                    max_lines: Some(10),
+                    ..Default::default()
+                });
+            });
+        })
+    });
 
-        // As soon as the last tab stop is reached, snippet state is gone
-        editor.move_to_prev_snippet_tabstop(cx);
-        assert(
-            editor,
-            cx,
-            indoc! {"
-                a.f(one, two, three)ˇ b
-                a.f(one, two, three)ˇ b
-                a.f(one, two, three)ˇ b
-            "},
-        );
+    let buffer = cx.new_model(|cx| {
+        language::Buffer::new(
+            0,
+            BufferId::new(cx.entity_id().as_u64()).unwrap(),
+            "line\n".repeat(20),
+        )
     });
+    let editor1 = cx.add_window(|cx| Editor::for_buffer(buffer.clone(), None, cx));
+    let editor2 = cx.add_window(|cx| Editor::for_buffer(buffer.clone(), None, cx));
+    let is_parsing_enabled =
+        |cx: &mut TestAppContext| buffer.update(cx, |buffer, _| buffer.is_parsing_enabled());
+    assert!(!is_parsing_enabled(cx));
+
+    // Turning the features back on in one editor leaves the buffer to the other editor.
+    _ = editor1.update(cx, |editor, cx| {
+        editor.large_file_features_enabled = true;
+        large_file::refresh_large_file_mode(editor, cx);
+        assert!(!editor.large_file_mode);
+    });
+    _ = editor2.update(cx, |editor, _| assert!(editor.large_file_mode));
+    assert!(!is_parsing_enabled(cx));
+
+    // Closing the other editor releases the buffer.
+    _ = editor2.update(cx, |_, cx| cx.remove_window());
+    cx.run_until_parked();
+    assert!(is_parsing_enabled(cx));
 }
 
 #[gpui::test]
@@ -6490,38 +8270,207 @@ fn test_refresh_selections(cx: &mut TestAppContext) {
     _ = editor.update(cx, |editor, cx| {
         editor.change_selections(None, cx, |s| s.refresh());
         assert_eq!(
-            editor.selections.ranges(cx),
-            [
-                Point::new(1, 3)..Point::new(1, 3),
-                Point::new(2, 1)..Point::new(2, 1),
-            ]
+            editor.selections.ranges(cx),
+            [
+                Point::new(1, 3)..Point::new(1, 3),
+                Point::new(2, 1)..Point::new(2, 1),
+            ]
+        );
+    });
+
+    _ = multibuffer.update(cx, |multibuffer, cx| {
+        multibuffer.remove_excerpts([excerpt1_id.unwrap()], cx);
+    });
+    _ = editor.update(cx, |editor, cx| {
+        // Removing an excerpt causes the first selection to become degenerate.
+        assert_eq!(
+            editor.selections.ranges(cx),
+            [
+                Point::new(0, 0)..Point::new(0, 0),
+                Point::new(0, 1)..Point::new(0, 1)
+            ]
+        );
+
+        // Refreshing selections will relocate the first selection to the original buffer
+        // location.
+        editor.change_selections(None, cx, |s| s.refresh());
+        assert_eq!(
+            editor.selections.ranges(cx),
+            [
+                Point::new(0, 1)..Point::new(0, 1),
+                Point::new(0, 3)..Point::new(0, 3)
+            ]
+        );
+        assert!(editor.selections.pending_anchor().is_some());
+    });
+}
+
+#[gpui::test]
+fn test_dismiss_excerpt(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let buffer = cx.new_model(|cx| {
+        Buffer::new(
+            0,
+            BufferId::new(cx.entity_id().as_u64()).unwrap(),
+            sample_text(6, 4, 'a'),
+        )
+    });
+    let mut excerpt_ids = Vec::new();
+    let multibuffer = cx.new_model(|cx| {
+        let mut multibuffer = MultiBuffer::new(0, ReadWrite);
+        excerpt_ids = multibuffer.push_excerpts(
+            buffer.clone(),
+            [
+                ExcerptRange {
+                    context: Point::new(0, 0)..Point::new(1, 4),
+                    primary: None,
+                },
+                ExcerptRange {
+                    context: Point::new(3, 0)..Point::new(4, 4),
+                    primary: None,
+                },
+            ],
+            cx,
+        );
+        assert_eq!(multibuffer.read(cx).text(), "aaaa\nbbbb\ndddd\neeee");
+        multibuffer
+    });
+
+    let editor = cx.add_window(|cx| build_editor(multibuffer.clone(), cx));
+    _ = editor.update(cx, |editor, cx| {
+        editor.change_selections(None, cx, |s| {
+            s.select_ranges([Point::new(2, 2)..Point::new(2, 2)])
+        });
+        editor.dismiss_excerpt(&DismissExcerpt, cx);
+
+        assert_eq!(editor.text(cx), "aaaa\nbbbb");
+        assert_eq!(
+            editor.selections.newest_anchor().head().excerpt_id,
+            excerpt_ids[0]
+        );
+    });
+}
+
+#[gpui::test]
+fn test_go_to_excerpt(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let buffer = cx.new_model(|cx| {
+        Buffer::new(
+            0,
+            BufferId::new(cx.entity_id().as_u64()).unwrap(),
+            sample_text(6, 4, 'a'),
+        )
+    });
+    let multibuffer = cx.new_model(|cx| {
+        let mut multibuffer = MultiBuffer::new(0, ReadWrite);
+        multibuffer.push_excerpts(
+            buffer.clone(),
+            [
+                ExcerptRange {
+                    context: Point::new(0, 0)..Point::new(1, 4),
+                    primary: None,
+                },
+                ExcerptRange {
+                    context: Point::new(3, 0)..Point::new(4, 4),
+                    primary: None,
+                },
+            ],
+            cx,
+        );
+        multibuffer
+    });
+
+    let editor = cx.add_window(|cx| build_editor(multibuffer.clone(), cx));
+    _ = editor.update(cx, |editor, cx| {
+        editor.change_selections(None, cx, |s| {
+            s.select_ranges([Point::new(0, 1)..Point::new(0, 1)])
+        });
+
+        editor.go_to_next_excerpt(&GoToNextExcerpt, cx);
+        assert_eq!(
+            editor.selections.ranges(cx),
+            [Point::new(2, 0)..Point::new(2, 0)]
+        );
+
+        editor.go_to_next_excerpt(&GoToNextExcerpt, cx);
+        assert_eq!(
+            editor.selections.ranges(cx),
+            [Point::new(2, 0)..Point::new(2, 0)]
+        );
+
+        editor.go_to_prev_excerpt(&GoToPrevExcerpt, cx);
+        assert_eq!(
+            editor.selections.ranges(cx),
+            [Point::new(0, 0)..Point::new(0, 0)]
+        );
+    });
+}
+
+#[gpui::test]
+fn test_toggle_excerpt_group_fold(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let buffer_1 = cx.new_model(|cx| {
+        Buffer::new(
+            0,
+            BufferId::new(cx.entity_id().as_u64()).unwrap(),
+            sample_text(6, 4, 'a'),
+        )
+    });
+    let buffer_2 = cx.new_model(|cx| {
+        Buffer::new(
+            0,
+            BufferId::new(cx.entity_id().as_u64()).unwrap(),
+            sample_text(2, 4, 'x'),
+        )
+    });
+    let mut excerpt_ids = Vec::new();
+    let multibuffer = cx.new_model(|cx| {
+        let mut multibuffer = MultiBuffer::new(0, ReadWrite);
+        excerpt_ids.extend(multibuffer.push_excerpts(
+            buffer_1.clone(),
+            [
+                ExcerptRange {
+                    context: Point::new(0, 0)..Point::new(1, 4),
+                    primary: None,
+                },
+                ExcerptRange {
+                    context: Point::new(3, 0)..Point::new(4, 4),
+                    primary: None,
+                },
+            ],
+            cx,
+        ));
+        excerpt_ids.extend(multibuffer.push_excerpts(
+            buffer_2.clone(),
+            [ExcerptRange {
+                context: Point::new(0, 0)..Point::new(1, 4),
+                primary: None,
+            }],
+            cx,
+        ));
+        assert_eq!(
+            multibuffer.read(cx).text(),
+            "aaaa\nbbbb\ndddd\neeee\nxxxx\nyyyy"
         );
+        multibuffer
     });
 
-    _ = multibuffer.update(cx, |multibuffer, cx| {
-        multibuffer.remove_excerpts([excerpt1_id.unwrap()], cx);
-    });
+    let editor = cx.add_window(|cx| build_editor(multibuffer.clone(), cx));
     _ = editor.update(cx, |editor, cx| {
-        // Removing an excerpt causes the first selection to become degenerate.
-        assert_eq!(
-            editor.selections.ranges(cx),
-            [
-                Point::new(0, 0)..Point::new(0, 0),
-                Point::new(0, 1)..Point::new(0, 1)
-            ]
-        );
+        let snapshot = editor.snapshot(cx);
+        assert_eq!(snapshot.excerpt_group(excerpt_ids[1]), Some((0..19, 2)));
+        assert_eq!(snapshot.excerpt_group(excerpt_ids[2]), Some((20..29, 1)));
 
-        // Refreshing selections will relocate the first selection to the original buffer
-        // location.
-        editor.change_selections(None, cx, |s| s.refresh());
-        assert_eq!(
-            editor.selections.ranges(cx),
-            [
-                Point::new(0, 1)..Point::new(0, 1),
-                Point::new(0, 3)..Point::new(0, 3)
-            ]
-        );
-        assert!(editor.selections.pending_anchor().is_some());
+        editor.toggle_excerpt_group_fold(excerpt_ids[1], cx);
+        let snapshot = editor.snapshot(cx);
+        assert!(snapshot.is_range_folded(&(0..19)));
+        assert!(!snapshot.is_range_folded(&(20..29)));
+
+        editor.toggle_excerpt_group_fold(excerpt_ids[0], cx);
+        assert!(!editor.snapshot(cx).is_range_folded(&(0..19)));
     });
 }
 
@@ -7079,6 +9028,26 @@ async fn test_following_with_multiple_excerpts(cx: &mut gpui::TestAppContext) {
         follower_1.update(cx, |editor, cx| editor.text(cx)),
         leader.update(cx, |editor, cx| editor.text(cx))
     );
+
+    // Expand the remaining excerpts.
+    _ = leader.update(cx, |leader, cx| {
+        let excerpt_ids = leader.buffer.read(cx).excerpt_ids();
+        leader.expand_excerpts(excerpt_ids, 1, ExpandExcerptDirection::UpAndDown, cx);
+    });
+    assert!(update_message.borrow().is_some());
+
+    // Apply the update of expanding the excerpts.
+    follower_1
+        .update(cx, |follower, cx| {
+            follower.apply_update_proto(&project, update_message.borrow().clone().unwrap(), cx)
+        })
+        .await
+        .unwrap();
+    update_message.borrow_mut().take();
+    assert_eq!(
+        follower_1.update(cx, |editor, cx| editor.text(cx)),
+        leader.update(cx, |editor, cx| editor.text(cx))
+    );
 }
 
 #[gpui::test]
@@ -7324,6 +9293,152 @@ async fn go_to_hunk(executor: BackgroundExecutor, cx: &mut gpui::TestAppContext)
     );
 }
 
+#[gpui::test]
+async fn test_go_to_todo(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorTestContext::new(cx).await;
+    let (text, todo_ranges) = marked_text_ranges(
+        indoc! {"
+            // «TODO»: one
+            fn a() {}
+            // «FIXME»: two
+            fn b() {}
+        "},
+        false,
+    );
+    cx.set_state(&format!("ˇ{text}"));
+    cx.update_editor(|editor, cx| {
+        let snapshot = editor.buffer().read(cx).snapshot(cx);
+        let ranges = todo_ranges
+            .into_iter()
+            .map(|range| snapshot.anchor_after(range.start)..snapshot.anchor_before(range.end))
+            .collect();
+        editor.highlight_text::<TodoHighlights>(ranges, HighlightStyle::default(), cx);
+    });
+
+    cx.update_editor(|editor, cx| editor.go_to_todo(&GoToTodo, cx));
+    cx.assert_editor_state(indoc! {"
+        // ˇTODO: one
+        fn a() {}
+        // FIXME: two
+        fn b() {}
+    "});
+
+    cx.update_editor(|editor, cx| editor.go_to_todo(&GoToTodo, cx));
+    cx.assert_editor_state(indoc! {"
+        // TODO: one
+        fn a() {}
+        // ˇFIXME: two
+        fn b() {}
+    "});
+
+    // Navigation wraps around the buffer.
+    cx.update_editor(|editor, cx| editor.go_to_todo(&GoToTodo, cx));
+    cx.assert_editor_state(indoc! {"
+        // ˇTODO: one
+        fn a() {}
+        // FIXME: two
+        fn b() {}
+    "});
+
+    cx.update_editor(|editor, cx| editor.go_to_prev_todo(&GoToPrevTodo, cx));
+    cx.assert_editor_state(indoc! {"
+        // TODO: one
+        fn a() {}
+        // ˇFIXME: two
+        fn b() {}
+    "});
+}
+
+#[gpui::test]
+async fn test_bookmarks(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorTestContext::new(cx).await;
+    cx.set_state(indoc! {"
+        oˇne
+        two
+        threˇe
+        four
+    "});
+    cx.update_editor(|editor, cx| editor.toggle_bookmark(&ToggleBookmark, cx));
+    cx.update_editor(|editor, cx| {
+        let snapshot = editor.snapshot(cx);
+        assert_eq!(
+            editor.bookmark_display_rows(0..4, &snapshot, cx),
+            vec![0, 2]
+        );
+    });
+
+    // Bookmarks follow their lines as text is inserted above them.
+    cx.update_editor(|editor, cx| {
+        editor.change_selections(None, cx, |s| s.select_ranges([0..0]));
+        editor.newline_above(&NewlineAbove, cx);
+    });
+    cx.update_editor(|editor, cx| editor.next_bookmark(&NextBookmark, cx));
+    cx.assert_editor_state(indoc! {"
+
+        ˇone
+        two
+        three
+        four
+    "});
+    cx.update_editor(|editor, cx| editor.next_bookmark(&NextBookmark, cx));
+    cx.assert_editor_state(indoc! {"
+
+        one
+        two
+        ˇthree
+        four
+    "});
+
+    // Navigation wraps around the buffer.
+    cx.update_editor(|editor, cx| editor.next_bookmark(&NextBookmark, cx));
+    cx.assert_editor_state(indoc! {"
+
+        ˇone
+        two
+        three
+        four
+    "});
+    cx.update_editor(|editor, cx| editor.prev_bookmark(&PrevBookmark, cx));
+    cx.assert_editor_state(indoc! {"
+
+        one
+        two
+        ˇthree
+        four
+    "});
+
+    // Toggling a bookmarked line removes its bookmark.
+    cx.update_editor(|editor, cx| editor.toggle_bookmark(&ToggleBookmark, cx));
+    cx.update_editor(|editor, cx| {
+        let snapshot = editor.snapshot(cx);
+        assert_eq!(editor.bookmark_display_rows(0..5, &snapshot, cx), vec![1]);
+    });
+
+    // Other editors on the same buffer show and change the same bookmarks.
+    let buffer = cx.update_editor(|editor, cx| editor.buffer().read(cx).as_singleton().unwrap());
+    let other_editor =
+        cx.new_view(|cx| build_editor(cx.new_model(|cx| MultiBuffer::singleton(buffer, cx)), cx));
+    other_editor.update(&mut cx.cx, |editor, cx| {
+        let snapshot = editor.snapshot(cx);
+        assert_eq!(editor.bookmark_display_rows(0..5, &snapshot, cx), vec![1]);
+        editor.change_selections(None, cx, |s| {
+            s.select_ranges([Point::new(4, 0)..Point::new(4, 0)])
+        });
+        editor.toggle_bookmark(&ToggleBookmark, cx);
+    });
+    cx.update_editor(|editor, cx| {
+        let snapshot = editor.snapshot(cx);
+        assert_eq!(
+            editor.bookmark_display_rows(0..5, &snapshot, cx),
+            vec![1, 4]
+        );
+    });
+}
+
 #[test]
 fn test_split_words() {
     fn split<'a>(text: &'a str) -> Vec<&'a str> {
@@ -7706,6 +9821,56 @@ async fn test_copilot_completion_invalidation(
     });
 }
 
+#[gpui::test]
+async fn test_copilot_partial_accept(executor: BackgroundExecutor, cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let (copilot, copilot_lsp) = Copilot::fake(cx);
+    _ = cx.update(|cx| Copilot::set_global(copilot, cx));
+    let mut cx = EditorLspTestContext::new_rust(lsp::ServerCapabilities::default(), cx).await;
+
+    cx.set_state(indoc! {"
+        let xˇ
+    "});
+    handle_copilot_completion_request(
+        &copilot_lsp,
+        vec![copilot::request::Completion {
+            text: "let x = foo.bar();\nbaz();".into(),
+            range: lsp::Range::new(lsp::Position::new(0, 0), lsp::Position::new(0, 5)),
+            ..Default::default()
+        }],
+        vec![],
+    );
+    cx.update_editor(|editor, cx| editor.next_copilot_suggestion(&Default::default(), cx));
+    executor.advance_clock(COPILOT_DEBOUNCE_TIMEOUT);
+    cx.update_editor(|editor, cx| {
+        assert!(editor.has_active_copilot_suggestion(cx));
+        assert_eq!(editor.display_text(cx), "let x = foo.bar();\nbaz();\n");
+        assert_eq!(editor.text(cx), "let x\n");
+
+        // Accepting words inserts them one at a time, keeping the rest of the suggestion visible.
+        editor.accept_copilot_suggestion_word(&AcceptCopilotSuggestionWord, cx);
+        assert!(editor.has_active_copilot_suggestion(cx));
+        assert_eq!(editor.display_text(cx), "let x = foo.bar();\nbaz();\n");
+        assert_eq!(editor.text(cx), "let x =\n");
+
+        editor.accept_copilot_suggestion_word(&AcceptCopilotSuggestionWord, cx);
+        assert_eq!(editor.display_text(cx), "let x = foo.bar();\nbaz();\n");
+        assert_eq!(editor.text(cx), "let x = foo\n");
+
+        // Accepting a line inserts the rest of the current line.
+        editor.accept_copilot_suggestion_line(&AcceptCopilotSuggestionLine, cx);
+        assert!(editor.has_active_copilot_suggestion(cx));
+        assert_eq!(editor.display_text(cx), "let x = foo.bar();\nbaz();\n");
+        assert_eq!(editor.text(cx), "let x = foo.bar();\n\n");
+
+        // Accepting the last line accepts the whole suggestion.
+        editor.accept_copilot_suggestion_line(&AcceptCopilotSuggestionLine, cx);
+        assert!(!editor.has_active_copilot_suggestion(cx));
+        assert_eq!(editor.text(cx), "let x = foo.bar();\nbaz();\n");
+    });
+}
+
 #[gpui::test]
 async fn test_copilot_multibuffer(executor: BackgroundExecutor, cx: &mut gpui::TestAppContext) {
     init_test(cx, |_| {});
@@ -8426,6 +10591,219 @@ async fn test_document_format_with_prettier(cx: &mut gpui::TestAppContext) {
     );
 }
 
+#[gpui::test]
+fn test_screenshot_selection(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    _ = cx.add_window(|cx| {
+        let buffer = MultiBuffer::build_simple("one two\nthree\nfour\n", cx);
+        let mut editor = build_editor(buffer.clone(), cx);
+        editor.set_style(EditorStyle::default(), cx);
+        let snapshot = buffer.read(cx).snapshot(cx);
+        enum Highlight {}
+        editor.highlight_text::<Highlight>(
+            vec![snapshot.anchor_before(4)..snapshot.anchor_after(7)],
+            HighlightStyle {
+                background_color: Some(gpui::red()),
+                ..Default::default()
+            },
+            cx,
+        );
+
+        // Only the lines of the selection are rendered, with the highlights shown on screen.
+        editor.change_selections(None, cx, |s| {
+            s.select_ranges([Point::new(0, 5)..Point::new(1, 2)])
+        });
+        let svg = editor
+            .render_selection_svg(&ScreenshotSelection::default(), cx)
+            .unwrap();
+        assert!(svg.contains(">one </text>"));
+        assert!(svg.contains(">two</text>"));
+        assert!(svg.contains("fill=\"#ff0000\"/>"));
+        assert!(svg.contains(">three</text>"));
+        assert!(!svg.contains("four"));
+        assert!(!svg.contains("<circle"));
+
+        // Without a selection, the whole buffer is rendered.
+        editor.change_selections(None, cx, |s| s.select_ranges([0..0]));
+        let svg = editor
+            .render_selection_svg(
+                &ScreenshotSelection {
+                    window_chrome: true,
+                    ..Default::default()
+                },
+                cx,
+            )
+            .unwrap();
+        assert!(svg.contains(">one </text>"));
+        assert!(svg.contains(">four</text>"));
+        assert_eq!(svg.matches("<circle").count(), 3);
+
+        editor
+    });
+}
+
+#[gpui::test]
+fn test_per_editor_font_size(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let buffer = cx.update(|cx| MultiBuffer::build_simple("abc", cx));
+    let adjusted = cx.add_window(|cx| build_editor(buffer.clone(), cx));
+    let other = cx.add_window(|cx| build_editor(buffer.clone(), cx));
+    let base_size = cx.update(|cx| ThemeSettings::get_global(cx).buffer_font_size(cx));
+
+    // Adjusting the font size of an editor leaves the other editors as they are.
+    _ = adjusted.update(cx, |editor, cx| {
+        editor.increase_font_size(&IncreaseFontSize, cx);
+        editor.increase_font_size(&IncreaseFontSize, cx);
+        editor.decrease_font_size(&DecreaseFontSize, cx);
+        assert_eq!(editor.buffer_font_size(cx), base_size + px(1.));
+    });
+    _ = other.update(cx, |editor, cx| {
+        assert_eq!(editor.buffer_font_size(cx), base_size);
+    });
+
+    // Changes to the global font size still apply on top of the adjustment.
+    cx.update(|cx| {
+        cx.update_global(|store: &mut SettingsStore, cx| {
+            store.update_user_settings::<ThemeSettings>(cx, |settings| {
+                settings.buffer_font_size = Some(f32::from(base_size) + 4.);
+            });
+        });
+    });
+    _ = adjusted.update(cx, |editor, cx| {
+        assert_eq!(editor.buffer_font_size(cx), base_size + px(5.));
+    });
+    _ = other.update(cx, |editor, cx| {
+        assert_eq!(editor.buffer_font_size(cx), base_size + px(4.));
+    });
+
+    // The font size doesn't get smaller than the minimum, and is adjusted from there.
+    _ = adjusted.update(cx, |editor, cx| {
+        for _ in 0..100 {
+            editor.decrease_font_size(&DecreaseFontSize, cx);
+        }
+        assert_eq!(editor.buffer_font_size(cx), MIN_FONT_SIZE);
+        editor.increase_font_size(&IncreaseFontSize, cx);
+        assert_eq!(editor.buffer_font_size(cx), MIN_FONT_SIZE + px(1.));
+
+        editor.reset_font_size(&ResetFontSize, cx);
+        assert_eq!(editor.buffer_font_size(cx), base_size + px(4.));
+    });
+}
+
+#[gpui::test]
+async fn test_restore_navigation_history(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree("/a", json!({ "main.rs": sample_text(30, 5, 'a') }))
+        .await;
+    let project = Project::test(fs, ["/a".as_ref()], cx).await;
+    let worktree_id = project.update(cx, |project, cx| {
+        project.worktrees().next().unwrap().read(cx).id()
+    });
+    let open_editor = |cx: &mut TestAppContext| {
+        let workspace = cx.add_window(|cx| Workspace::test_new(project.clone(), cx));
+        let mut cx = VisualTestContext::from_window(*workspace, cx);
+        let pane = workspace
+            .update(&mut cx, |workspace, _| workspace.active_pane().clone())
+            .unwrap();
+        let editor = workspace
+            .update(&mut cx, |workspace, cx| {
+                workspace.open_path((worktree_id, "main.rs"), None, true, cx)
+            })
+            .unwrap();
+        (workspace, pane, editor, cx)
+    };
+
+    // A large jump records the previous position in the pane's navigation history, which is
+    // serialized along with the workspace.
+    let (_, pane, editor, mut window_cx) = open_editor(cx);
+    let editor = editor.await.unwrap().downcast::<Editor>().unwrap();
+    editor.update(&mut window_cx, |editor, cx| {
+        editor.change_selections(None, cx, |s| {
+            s.select_display_ranges([DisplayPoint::new(2, 3)..DisplayPoint::new(2, 3)])
+        });
+        editor.change_selections(None, cx, |s| {
+            s.select_display_ranges([DisplayPoint::new(20, 0)..DisplayPoint::new(20, 0)])
+        });
+    });
+    let entries = pane.update(&mut window_cx, |pane, cx| {
+        let mut entries = Vec::new();
+        pane.nav_history()
+            .for_each_navigation_entry(|entry, forward| {
+                let data = entry
+                    .data
+                    .as_ref()
+                    .and_then(|data| editor.read(cx).serialize_navigation_data(data.as_ref(), cx));
+                entries.extend(data.map(|data| (data, forward)));
+            });
+        entries
+    });
+    assert_eq!(
+        entries
+            .last()
+            .map(|(data, _)| serde_json::from_str(data).unwrap()),
+        Some(json!({ "row": 2, "column": 3, "scroll_top_row": 0 }))
+    );
+
+    // Once the history is restored in a new workspace, going back returns to that position.
+    let (workspace, pane, editor, mut window_cx) = open_editor(cx);
+    let editor = editor.await.unwrap().downcast::<Editor>().unwrap();
+    pane.update(&mut window_cx, |pane, cx| {
+        for (data, forward) in &entries {
+            let data = editor
+                .read(cx)
+                .deserialize_navigation_data(data, cx)
+                .unwrap();
+            pane.nav_history_mut()
+                .restore(editor.downgrade_item().into(), data, *forward);
+        }
+    });
+    workspace
+        .update(&mut window_cx, |workspace, cx| {
+            workspace.go_back(pane.downgrade(), cx)
+        })
+        .unwrap()
+        .await
+        .unwrap();
+    editor.update(&mut window_cx, |editor, cx| {
+        assert_eq!(
+            editor.selections.display_ranges(cx),
+            [DisplayPoint::new(2, 3)..DisplayPoint::new(2, 3)]
+        );
+    });
+}
+
+#[gpui::test]
+async fn test_print(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+    let mut cx = EditorTestContext::new(cx).await;
+    cx.set_state("ˇfn main() {\n    println!(\"hello\");\n}");
+
+    cx.dispatch_action(Print);
+    let documents = cx.printed_documents();
+    assert_eq!(documents.len(), 1);
+    let document = &documents[0];
+    assert_eq!(document.font_size, print::PRINT_FONT_SIZE);
+    assert_eq!(
+        document
+            .pages
+            .iter()
+            .map(|page| (page.lines.clone(), page.footer.clone()))
+            .collect::<Vec<_>>(),
+        [(
+            vec![
+                "1  fn main() {".to_string(),
+                "2      println!(\"hello\");".to_string(),
+                "3  }".to_string(),
+            ],
+            "Page 1 of 1".to_string()
+        )]
+    );
+}
+
 fn empty_range(row: usize, column: usize) -> Range<DisplayPoint> {
     let point = DisplayPoint::new(row as u32, column as u32);
     point..point
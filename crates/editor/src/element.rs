@@ -1,41 +1,61 @@
 use crate::{
+    bracket_colorization::{
+        bracket_colors, bracket_pair_color, bracket_pairs, cursor_bracket_pair_underlines,
+        text_bracket_colors, BracketColor,
+    },
+    cursor_name_tags::{name_tag_opacity, stack_name_tag},
+    cursor_position_overlay::render_cursor_position_overlay,
+    diagnostic_style,
     display_map::{
-        BlockContext, BlockStyle, DisplaySnapshot, FoldStatus, HighlightedChunk, ToDisplayPoint,
-        TransformBlock,
+        BlockContext, BlockId, BlockStyle, DisplaySnapshot, FoldStatus, HighlightedChunk,
+        ToDisplayPoint, TransformBlock,
     },
-    editor_settings::ShowScrollbar,
+    editor_settings::{ExcerptHeaderButton, FoldIndicators, LineNumberAlignment, ShowScrollbar},
     git::{diff_hunk_to_display, DisplayDiffHunk},
-    hover_popover::{
-        self, hover_at, HOVER_POPOVER_GAP, MIN_POPOVER_CHARACTER_WIDTH, MIN_POPOVER_LINE_HEIGHT,
-    },
+    gutter_data::{GutterMarkLayout, GutterMarkState},
+    hover_popover::{self, hover_at, MIN_POPOVER_CHARACTER_WIDTH, MIN_POPOVER_LINE_HEIGHT},
     items::BufferSearchHighlights,
-    mouse_context_menu,
+    line_layout_cache::LineLayoutCache,
+    merged_quads::MergedQuads,
+    middle_click, mouse_context_menu,
+    remote_cursors::presence_opacity,
+    row_layout_cache::{RowLayoutCache, RowLayoutKey},
     scroll::scroll_amount::ScrollAmount,
-    CursorShape, DisplayPoint, DocumentHighlightRead, DocumentHighlightWrite, Editor, EditorMode,
-    EditorSettings, EditorSnapshot, EditorStyle, HalfPageDown, HalfPageUp, HoveredCursor, LineDown,
-    LineUp, OpenExcerpts, PageDown, PageUp, Point, SelectPhase, Selection, SoftWrap, ToPoint,
-    CURSORS_VISIBLE_FOR, MAX_LINE_LEN,
+    scrollbar_markers::{ScrollbarMarkerKind, ScrollbarMarkerRows},
+    selection_highlights::SelectionMatchHighlights,
+    sticky_header::render_sticky_header,
+    todo_highlights::TodoHighlights,
+    CursorShape, DismissExcerpt, DisplayPoint, DocumentHighlightRead, DocumentHighlightWrite,
+    Editor, EditorMode, EditorSettings, EditorSnapshot, EditorStyle, ExcerptId,
+    ExpandExcerptDirection, ExpandExcerptsDown, ExpandExcerptsUp, HalfPageDown, HalfPageUp,
+    HoveredCursor, LineDown, LineUp, OpenExcerpts, PageDown, PageUp, Point, SelectPhase, Selection,
+    SoftWrap, ToPoint, CURSORS_VISIBLE_FOR, MAX_LINE_LEN,
 };
 use anyhow::Result;
 use collections::{BTreeMap, HashMap};
 use git::diff::DiffHunkStatus;
 use gpui::{
     div, fill, outline, overlay, point, px, quad, relative, size, transparent_black, Action,
-    AnchorCorner, AnyElement, AvailableSpace, Bounds, ContentMask, Corners, CursorStyle,
-    DispatchPhase, Edges, Element, ElementInputHandler, Entity, Hsla, InteractiveBounds,
-    InteractiveElement, IntoElement, ModifiersChangedEvent, MouseButton, MouseDownEvent,
-    MouseMoveEvent, MouseUpEvent, ParentElement, Pixels, ScrollDelta, ScrollWheelEvent, ShapedLine,
-    SharedString, Size, StackingOrder, StatefulInteractiveElement, Style, Styled, TextRun,
-    TextStyle, View, ViewContext, WindowContext,
+    AnchorCorner, AnyElement, AnyTooltip, AvailableSpace, Bounds, ContentMask, Corners,
+    CursorStyle, DispatchPhase, Edges, Element, ElementInputHandler, Entity, EntityId,
+    HighlightStyle, Hsla, InteractiveBounds, InteractiveElement, IntoElement,
+    ModifiersChangedEvent, MouseButton, MouseDownEvent, MouseMoveEvent, MouseUpEvent,
+    ParentElement, Pixels, ScrollDelta, ScrollWheelEvent, ShapedLine, SharedString, Size,
+    StackingOrder, StatefulInteractiveElement, Style, Styled, TextRun, TextStyle, UnderlineStyle,
+    View, ViewContext, WindowContext,
 };
 use itertools::Itertools;
-use language::language_settings::ShowWhitespaceSetting;
+use language::{
+    language_settings::{ShowWhitespaceSetting, TrailingWhitespaceHighlight, WrapGuideStyle},
+    Diagnostic, IndentKind,
+};
 use lsp::DiagnosticSeverity;
-use multi_buffer::Anchor;
+use multi_buffer::{Anchor, ToOffset};
 use project::{
     project_settings::{GitGutterSetting, ProjectSettings},
     ProjectPath,
 };
+use serde::Serialize;
 use settings::Settings;
 use smallvec::SmallVec;
 use std::{
@@ -43,17 +63,45 @@ use std::{
     borrow::Cow,
     cmp::{self, Ordering},
     fmt::Write,
-    iter,
+    iter, mem,
     ops::Range,
+    rc::Rc,
     sync::Arc,
+    time::Instant,
 };
 use sum_tree::Bias;
 use theme::{ActiveTheme, PlayerColor};
 use ui::prelude::*;
 use ui::{h_flex, ButtonLike, ButtonStyle, IconButton, Tooltip};
+use unicode_segmentation::UnicodeSegmentation;
 use util::ResultExt;
 use workspace::item::Item;
 
+/// The length of each dash of a dashed wrap guide, and of the gaps between them.
+const WRAP_GUIDE_DASH_LENGTH: Pixels = px(4.);
+
+/// The minimum height of the area in which a scrollbar marker can be clicked.
+const SCROLLBAR_MARKER_MIN_HIT_HEIGHT: Pixels = px(4.);
+
+/// The space between the end of a line and its inline diagnostic, in ems.
+const INLINE_DIAGNOSTIC_MARGIN_EMS: f32 = 2.;
+
+/// The radius of the circle marking the point from which a middle-click autoscroll started.
+const AUTOSCROLL_ANCHOR_RADIUS: Pixels = px(6.);
+
+/// The maximum number of characters read to find the grapheme cluster under a block cursor.
+const MAX_GRAPHEME_LEN: usize = 32;
+
+/// How much suggested text is faded out, so that it reads as ghost text rather than as part of
+/// the buffer.
+const SUGGESTION_FADE: f32 = 0.4;
+
+/// The number of rows above and below the viewport in which block decorations that fit their
+/// content are measured, so that they span the rows they need by the time they're scrolled into
+/// view. Every other block keeps the height it was inserted with, so it never needs to be
+/// measured outside of the viewport.
+const BLOCK_OVERSCAN_ROWS: u32 = 20;
+
 struct SelectionLayout {
     head: DisplayPoint,
     cursor_shape: CursorShape,
@@ -62,6 +110,11 @@ struct SelectionLayout {
     range: Range<DisplayPoint>,
     active_rows: Range<u32>,
     user_name: Option<SharedString>,
+    user_name_opacity: f32,
+    /// The collaborator's cursor this selection is of, for animating it.
+    remote_cursor: Option<HoveredCursor>,
+    /// How faded the selection is, for selections of idle collaborators.
+    opacity: f32,
 }
 
 impl SelectionLayout {
@@ -113,6 +166,9 @@ impl SelectionLayout {
             range,
             active_rows,
             user_name,
+            user_name_opacity: 1.,
+            remote_cursor: None,
+            opacity: 1.,
         }
     }
 }
@@ -157,9 +213,17 @@ impl EditorElement {
         register_action(view, cx, Editor::tab_prev);
         register_action(view, cx, Editor::indent);
         register_action(view, cx, Editor::outdent);
+        register_action(view, cx, Editor::reindent);
+        register_action(view, cx, Editor::convert_indentation_to_spaces);
+        register_action(view, cx, Editor::convert_indentation_to_tabs);
         register_action(view, cx, Editor::delete_line);
         register_action(view, cx, Editor::join_lines);
+        register_action(view, cx, Editor::reflow);
+        register_action(view, cx, Editor::join_lines_with);
         register_action(view, cx, Editor::sort_lines_case_sensitive);
+        register_action(view, cx, Editor::sort_lines_numerically);
+        register_action(view, cx, Editor::sort_lines_by_column);
+        register_action(view, cx, Editor::unique_lines);
         register_action(view, cx, Editor::sort_lines_case_insensitive);
         register_action(view, cx, Editor::reverse_lines);
         register_action(view, cx, Editor::shuffle_lines);
@@ -178,18 +242,32 @@ impl EditorElement {
         register_action(view, cx, Editor::delete_to_end_of_line);
         register_action(view, cx, Editor::cut_to_end_of_line);
         register_action(view, cx, Editor::duplicate_line);
+        register_action(view, cx, Editor::duplicate_line_up);
+        register_action(view, cx, Editor::duplicate_selection);
         register_action(view, cx, Editor::move_line_up);
         register_action(view, cx, Editor::move_line_down);
+        register_action(view, cx, Editor::move_selection_up);
+        register_action(view, cx, Editor::move_selection_down);
         register_action(view, cx, Editor::transpose);
         register_action(view, cx, Editor::cut);
         register_action(view, cx, Editor::copy);
+        register_action(view, cx, Editor::copy_with_formatting);
         register_action(view, cx, Editor::paste);
+        register_action(view, cx, Editor::paste_without_formatting);
+        register_action(view, cx, Editor::paste_from_history);
+        register_action(view, cx, Editor::confirm_clipboard_history_entry);
+        register_action(view, cx, Editor::surround_with);
+        register_action(view, cx, Editor::confirm_surround);
+        register_action(view, cx, Editor::delete_surrounding);
         register_action(view, cx, Editor::undo);
+        register_action(view, cx, Editor::undo_in_selection);
         register_action(view, cx, Editor::redo);
         register_action(view, cx, Editor::move_page_up);
         register_action(view, cx, Editor::move_page_down);
         register_action(view, cx, Editor::next_screen);
         register_action(view, cx, Editor::scroll_cursor_top);
+        register_action(view, cx, Editor::scroll_cursor_left);
+        register_action(view, cx, Editor::scroll_cursor_right);
         register_action(view, cx, Editor::scroll_cursor_center);
         register_action(view, cx, Editor::scroll_cursor_bottom);
         register_action(view, cx, |editor, _: &LineDown, cx| {
@@ -235,11 +313,31 @@ impl EditorElement {
         register_action(view, cx, Editor::select_to_beginning);
         register_action(view, cx, Editor::select_to_end);
         register_action(view, cx, Editor::select_all);
+        register_action(view, cx, Editor::select_all_in_excerpt);
+        register_action(view, cx, Editor::toggle_read_only);
+        register_action(view, cx, Editor::toggle_centered_layout);
+        register_action(view, cx, Editor::toggle_edit_history);
+        register_action(view, cx, Editor::create_restore_point);
+        register_action(view, cx, Editor::toggle_ligatures);
+        register_action(view, cx, Editor::toggle_review_mode);
+        register_action(view, cx, Editor::start_recording_macro);
+        register_action(view, cx, Editor::stop_recording_macro);
+        register_action(view, cx, Editor::replay_macro);
         register_action(view, cx, |editor, action, cx| {
             editor.select_all_matches(action, cx).log_err();
         });
+        register_action(view, cx, |editor, action, cx| {
+            editor
+                .select_all_occurrences_of_each_cursor(action, cx)
+                .log_err();
+        });
         register_action(view, cx, Editor::select_line);
         register_action(view, cx, Editor::split_selection_into_lines);
+        register_action(view, cx, Editor::add_cursors_to_line_ends);
+        register_action(view, cx, Editor::add_cursors_to_line_starts);
+        register_action(view, cx, Editor::add_cursors_at_column);
+        register_action(view, cx, Editor::apply_replace_preview);
+        register_action(view, cx, Editor::apply_all_replace_previews);
         register_action(view, cx, Editor::add_selection_above);
         register_action(view, cx, Editor::add_selection_below);
         register_action(view, cx, |editor, action, cx| {
@@ -251,13 +349,31 @@ impl EditorElement {
         register_action(view, cx, Editor::toggle_comments);
         register_action(view, cx, Editor::select_larger_syntax_node);
         register_action(view, cx, Editor::select_smaller_syntax_node);
+        register_action(view, cx, Editor::select_syntax_node_sibling_next);
+        register_action(view, cx, Editor::select_syntax_node_sibling_prev);
+        register_action(view, cx, Editor::select_enclosing_function);
+        register_action(view, cx, Editor::select_enclosing_type);
+        register_action(view, cx, Editor::select_argument);
+        register_action(view, cx, Editor::select_string_contents);
         register_action(view, cx, Editor::move_to_enclosing_bracket);
+        register_action(view, cx, Editor::undo_last_selection_addition);
         register_action(view, cx, Editor::undo_selection);
         register_action(view, cx, Editor::redo_selection);
         register_action(view, cx, Editor::go_to_diagnostic);
         register_action(view, cx, Editor::go_to_prev_diagnostic);
         register_action(view, cx, Editor::go_to_hunk);
         register_action(view, cx, Editor::go_to_prev_hunk);
+        register_action(view, cx, Editor::go_to_todo);
+        register_action(view, cx, Editor::go_to_prev_todo);
+        register_action(view, cx, Editor::toggle_bookmark);
+        register_action(view, cx, Editor::next_bookmark);
+        register_action(view, cx, Editor::prev_bookmark);
+        register_action(view, cx, Editor::run_code_cell);
+        register_action(view, cx, Editor::restart_code_cell_kernel);
+        register_action(view, cx, Editor::set_mark);
+        register_action(view, cx, Editor::go_to_mark);
+        register_action(view, cx, Editor::go_to_last_edit);
+        register_action(view, cx, Editor::cycle_edit_locations);
         register_action(view, cx, Editor::go_to_definition);
         register_action(view, cx, Editor::go_to_definition_split);
         register_action(view, cx, Editor::go_to_type_definition);
@@ -271,15 +387,31 @@ impl EditorElement {
         register_action(view, cx, Editor::show_completions);
         register_action(view, cx, Editor::toggle_code_actions);
         register_action(view, cx, Editor::open_excerpts);
+        register_action(view, cx, Editor::expand_excerpts_up);
+        register_action(view, cx, Editor::expand_excerpts_down);
+        register_action(view, cx, Editor::dismiss_excerpt);
+        register_action(view, cx, Editor::go_to_next_excerpt);
+        register_action(view, cx, Editor::go_to_prev_excerpt);
         register_action(view, cx, Editor::toggle_soft_wrap);
         register_action(view, cx, Editor::toggle_inlay_hints);
+        register_action(view, cx, Editor::toggle_inline_diagnostics);
+        register_action(view, cx, Editor::toggle_inline_diff);
+        register_action(view, cx, Editor::toggle_image_preview);
         register_action(view, cx, hover_popover::hover);
+        register_action(view, cx, hover_popover::next_hover_tab);
+        register_action(view, cx, hover_popover::previous_hover_tab);
+        register_action(view, cx, Editor::print);
+        register_action(view, cx, Editor::increase_font_size);
+        register_action(view, cx, Editor::decrease_font_size);
+        register_action(view, cx, Editor::reset_font_size);
+        register_action(view, cx, Editor::screenshot_selection);
         register_action(view, cx, Editor::reveal_in_finder);
         register_action(view, cx, Editor::copy_path);
         register_action(view, cx, Editor::copy_relative_path);
         register_action(view, cx, Editor::copy_highlight_json);
         register_action(view, cx, Editor::copy_permalink_to_line);
         register_action(view, cx, Editor::open_permalink_to_line);
+        register_action(view, cx, Editor::open_side_by_side_diff);
         register_action(view, cx, |editor, action, cx| {
             if let Some(task) = editor.format(action, cx) {
                 task.detach_and_log_err(cx);
@@ -327,6 +459,8 @@ impl EditorElement {
         register_action(view, cx, Editor::next_copilot_suggestion);
         register_action(view, cx, Editor::previous_copilot_suggestion);
         register_action(view, cx, Editor::copilot_suggest);
+        register_action(view, cx, Editor::accept_copilot_suggestion_word);
+        register_action(view, cx, Editor::accept_copilot_suggestion_line);
         register_action(view, cx, Editor::context_menu_first);
         register_action(view, cx, Editor::context_menu_prev);
         register_action(view, cx, Editor::context_menu_next);
@@ -394,17 +528,22 @@ impl EditorElement {
         position_map: &PositionMap,
         text_bounds: Bounds<Pixels>,
         gutter_bounds: Bounds<Pixels>,
+        breakpoint_lane: Pixels,
         stacking_order: &StackingOrder,
         cx: &mut ViewContext<Editor>,
     ) {
-        let mut click_count = event.click_count;
+        let click_count = event.click_count;
         let modifiers = event.modifiers;
+        let in_gutter = gutter_bounds.contains(&event.position);
+        let line_height = position_map.line_height;
+        let in_breakpoint_lane = in_gutter
+            && breakpoint_lane > Pixels::ZERO
+            && event.position.x
+                < gutter_bounds.left() + diff_hunk_bar_width(line_height) + breakpoint_lane;
 
         if cx.default_prevented() {
             return;
-        } else if gutter_bounds.contains(&event.position) {
-            click_count = 3; // Simulate triple-click when clicking the gutter to select lines
-        } else if !text_bounds.contains(&event.position) {
+        } else if !in_gutter && !text_bounds.contains(&event.position) {
             return;
         }
         if !cx.was_top_layer(&event.position, stacking_order) {
@@ -413,7 +552,17 @@ impl EditorElement {
 
         let point_for_position = position_map.point_for_position(text_bounds, event.position);
         let position = point_for_position.previous_valid;
-        if modifiers.shift && modifiers.alt {
+        if in_breakpoint_lane {
+            editor.toggle_breakpoint_for_display_row(position.row(), cx);
+        } else if in_gutter {
+            editor.select(
+                SelectPhase::BeginLineWise {
+                    position,
+                    extend: modifiers.shift,
+                },
+                cx,
+            );
+        } else if modifiers.shift && modifiers.alt {
             editor.select(
                 SelectPhase::BeginColumnar {
                     position,
@@ -450,7 +599,7 @@ impl EditorElement {
         text_bounds: Bounds<Pixels>,
         cx: &mut ViewContext<Editor>,
     ) {
-        if !text_bounds.contains(&event.position) {
+        if cx.default_prevented() || !text_bounds.contains(&event.position) {
             return;
         }
         let point_for_position = position_map.point_for_position(text_bounds, event.position);
@@ -463,6 +612,32 @@ impl EditorElement {
         cx.stop_propagation();
     }
 
+    fn mouse_middle_down(
+        editor: &mut Editor,
+        event: &MouseDownEvent,
+        position_map: &PositionMap,
+        text_bounds: Bounds<Pixels>,
+        cx: &mut ViewContext<Editor>,
+    ) {
+        if cx.default_prevented() || !text_bounds.contains(&event.position) {
+            return;
+        }
+        if cfg!(target_os = "linux") {
+            let point_for_position = position_map.point_for_position(text_bounds, event.position);
+            middle_click::paste_primary_at(editor, point_for_position.previous_valid, cx);
+        } else {
+            middle_click::start_autoscroll(
+                editor,
+                event.position,
+                position_map.line_height,
+                position_map.em_width,
+                position_map.scroll_max,
+                cx,
+            );
+        }
+        cx.stop_propagation();
+    }
+
     fn mouse_up(
         editor: &mut Editor,
         event: &MouseUpEvent,
@@ -499,7 +674,7 @@ impl EditorElement {
         event: &MouseMoveEvent,
         position_map: &PositionMap,
         text_bounds: Bounds<Pixels>,
-        _gutter_bounds: Bounds<Pixels>,
+        gutter_bounds: Bounds<Pixels>,
         _stacking_order: &StackingOrder,
         cx: &mut ViewContext<Editor>,
     ) {
@@ -519,10 +694,11 @@ impl EditorElement {
             scroll_delta.y = scale_vertical_mouse_autoscroll_delta(event.position.y - bottom);
         }
 
+        // Dragging along the gutter selects whole lines, so it only scrolls vertically.
         let horizontal_margin = position_map.line_height.min(text_bounds.size.width / 3.0);
         let left = text_bounds.origin.x + horizontal_margin;
         let right = text_bounds.upper_right().x - horizontal_margin;
-        if event.position.x < left {
+        if event.position.x < left && !gutter_bounds.contains(&event.position) {
             scroll_delta.x = -scale_horizontal_mouse_autoscroll_delta(left - event.position.x);
         }
         if event.position.x > right {
@@ -623,7 +799,7 @@ impl EditorElement {
         &self,
         gutter_bounds: Bounds<Pixels>,
         text_bounds: Bounds<Pixels>,
-        layout: &LayoutState,
+        layout: &mut LayoutState,
         cx: &mut ElementContext,
     ) {
         let bounds = gutter_bounds.union(&text_bounds);
@@ -633,6 +809,37 @@ impl EditorElement {
         cx.paint_quad(fill(gutter_bounds, gutter_bg));
         cx.paint_quad(fill(text_bounds, self.style.background));
 
+        // Tint the gutter of each excerpt with its own color, and separate it from the excerpt
+        // above it, so that excerpts remain distinguishable when their header is scrolled past.
+        for band in &layout.excerpt_bands {
+            let start_y = gutter_bounds.origin.y
+                + layout.position_map.line_height * band.rows.start as f32
+                - scroll_top;
+            let height = layout.position_map.line_height * band.rows.len() as f32;
+            let mut tint = cx
+                .theme()
+                .players()
+                .color_for_participant(band.id.to_proto() as u32)
+                .cursor;
+            tint.fade_out(0.92);
+            layout.quads.fill(
+                Bounds {
+                    origin: point(gutter_bounds.origin.x, start_y),
+                    size: size(gutter_bounds.size.width, height),
+                },
+                tint,
+            );
+            if band.starts_excerpt {
+                layout.quads.fill(
+                    Bounds {
+                        origin: point(bounds.origin.x, start_y),
+                        size: size(bounds.size.width, px(1.)),
+                    },
+                    cx.theme().colors().border_variant,
+                );
+            }
+        }
+
         if let EditorMode::Full = layout.mode {
             let mut active_rows = layout.active_rows.iter().peekable();
             while let Some((start_row, contains_non_empty_selection)) = active_rows.next() {
@@ -655,7 +862,7 @@ impl EditorElement {
                         layout.position_map.line_height * (end_row - start_row + 1) as f32,
                     );
                     let active_line_bg = cx.theme().colors().editor_active_line_background;
-                    cx.paint_quad(fill(Bounds { origin, size }, active_line_bg));
+                    layout.quads.fill(Bounds { origin, size }, active_line_bg);
                 }
             }
 
@@ -671,14 +878,28 @@ impl EditorElement {
                     layout.position_map.line_height * highlighted_rows.len() as f32,
                 );
                 let highlighted_line_bg = cx.theme().colors().editor_highlighted_line_background;
-                cx.paint_quad(fill(Bounds { origin, size }, highlighted_line_bg));
+                layout
+                    .quads
+                    .fill(Bounds { origin, size }, highlighted_line_bg);
+            }
+
+            for row in &layout.code_cell_rows {
+                let y =
+                    bounds.origin.y + layout.position_map.line_height * *row as f32 - scroll_top;
+                layout.quads.fill(
+                    Bounds {
+                        origin: point(bounds.origin.x, y),
+                        size: size(bounds.size.width, px(1.)),
+                    },
+                    cx.theme().colors().border_variant,
+                );
             }
 
             let scroll_left =
                 layout.position_map.snapshot.scroll_position().x * layout.position_map.em_width;
 
-            for (wrap_position, active) in layout.wrap_guides.iter() {
-                let x = (text_bounds.origin.x + *wrap_position + layout.position_map.em_width / 2.)
+            for guide in layout.wrap_guides.iter() {
+                let x = (text_bounds.origin.x + guide.offset + layout.position_map.em_width / 2.)
                     - scroll_left;
 
                 if x < text_bounds.origin.x
@@ -687,20 +908,34 @@ impl EditorElement {
                     continue;
                 }
 
-                let color = if *active {
-                    cx.theme().colors().editor_active_wrap_guide
-                } else {
-                    cx.theme().colors().editor_wrap_guide
-                };
-                cx.paint_quad(fill(
-                    Bounds {
-                        origin: point(x, text_bounds.origin.y),
-                        size: size(px(1.), text_bounds.size.height),
-                    },
-                    color,
-                ));
+                match guide.style {
+                    WrapGuideStyle::Solid => layout.quads.fill(
+                        Bounds {
+                            origin: point(x, text_bounds.origin.y),
+                            size: size(px(1.), text_bounds.size.height),
+                        },
+                        guide.color,
+                    ),
+                    WrapGuideStyle::Dashed => {
+                        // Offset the dashes by the scroll position so that they move with the text.
+                        let dash_period = WRAP_GUIDE_DASH_LENGTH * 2.;
+                        let mut y = text_bounds.origin.y - scroll_top % dash_period;
+                        while y < text_bounds.lower_left().y {
+                            layout.quads.fill(
+                                Bounds {
+                                    origin: point(x, y),
+                                    size: size(px(1.), WRAP_GUIDE_DASH_LENGTH),
+                                },
+                                guide.color,
+                            );
+                            y += dash_period;
+                        }
+                    }
+                }
             }
         }
+
+        layout.quads.paint(cx);
     }
 
     fn paint_gutter(
@@ -722,19 +957,33 @@ impl EditorElement {
         if show_gutter {
             Self::paint_diff_hunks(bounds, layout, cx);
         }
+        Self::paint_breakpoints(bounds, layout, cx);
+
+        // Everything else is shown to the right of the breakpoint lane.
+        let bounds = Bounds::new(
+            bounds.origin + point(layout.breakpoint_lane, Pixels::ZERO),
+            size(
+                bounds.size.width - layout.breakpoint_lane,
+                bounds.size.height,
+            ),
+        );
 
         for (ix, line) in layout.line_numbers.iter().enumerate() {
             if let Some(line) = line {
-                let line_origin = bounds.origin
-                    + point(
-                        bounds.size.width - line.width - layout.gutter_padding,
-                        ix as f32 * line_height - (scroll_top % line_height),
-                    );
+                let x = if layout.left_aligned_line_numbers[ix] {
+                    layout.gutter_padding
+                } else {
+                    bounds.size.width - line.width - layout.gutter_padding
+                };
+                let line_origin =
+                    bounds.origin + point(x, ix as f32 * line_height - (scroll_top % line_height));
 
                 line.paint(line_origin, line_height, cx).log_err();
             }
         }
 
+        Self::paint_gutter_marks(bounds, layout, cx);
+
         cx.with_z_index(1, |cx| {
             for (ix, fold_indicator) in layout.fold_indicators.drain(..).enumerate() {
                 if let Some(fold_indicator) = fold_indicator {
@@ -759,6 +1008,39 @@ impl EditorElement {
                 }
             }
 
+            for row in &layout.bookmark_rows {
+                let mut icon = Icon::new(IconName::Bookmark)
+                    .size(IconSize::Small)
+                    .color(Color::Accent)
+                    .into_any_element();
+                let available_space = size(
+                    AvailableSpace::MinContent,
+                    AvailableSpace::Definite(line_height),
+                );
+                let icon_size = icon.measure(available_space, cx);
+
+                // Bookmarks are centered in the margin to the left of the line numbers.
+                let x = (layout.gutter_padding - icon_size.width) / 2.;
+                let y =
+                    *row as f32 * line_height - scroll_top + (line_height - icon_size.height) / 2.;
+                icon.draw(bounds.origin + point(x, y), available_space, cx);
+            }
+
+            for (row, button) in layout.runnable_indicators.drain(..) {
+                let mut button = button.into_any_element();
+                let available_space = size(
+                    AvailableSpace::MinContent,
+                    AvailableSpace::Definite(line_height),
+                );
+                let button_size = button.measure(available_space, cx);
+
+                // Buttons are centered where the code actions indicator is shown.
+                let x = (layout.gutter_padding + layout.gutter_margin - button_size.width) / 2.;
+                let y =
+                    row as f32 * line_height - scroll_top + (line_height - button_size.height) / 2.;
+                button.draw(bounds.origin + point(x, y), available_space, cx);
+            }
+
             if let Some(indicator) = layout.code_actions_indicator.take() {
                 let mut button = indicator.button.into_any_element();
                 let available_space = size(
@@ -775,9 +1057,78 @@ impl EditorElement {
 
                 button.draw(bounds.origin + point(x, y), available_space, cx);
             }
+
+            if let Some(lock) = layout.read_only_lock.take() {
+                let mut lock = lock.into_any_element();
+                let available_space = size(
+                    AvailableSpace::MinContent,
+                    AvailableSpace::Definite(line_height),
+                );
+                let lock_size = lock.measure(available_space, cx);
+
+                // The lock stays in the bottom left corner of the gutter as the editor scrolls.
+                let x = (layout.gutter_padding - lock_size.width).max(Pixels::ZERO) / 2.;
+                let y = bounds.size.height - line_height + (line_height - lock_size.height) / 2.;
+                lock.draw(bounds.origin + point(x, y), available_space, cx);
+            }
         });
     }
 
+    fn paint_breakpoints(bounds: Bounds<Pixels>, layout: &LayoutState, cx: &mut ElementContext) {
+        let line_height = layout.position_map.line_height;
+        let scroll_top = layout.position_map.snapshot.scroll_position().y * line_height;
+
+        // Breakpoints are shown as dots centered in their own lane, to the right of the diff hunk
+        // bars.
+        let lane_start = diff_hunk_bar_width(line_height);
+        let diameter = (line_height * 0.5).min(layout.breakpoint_lane);
+        for row in &layout.breakpoint_rows {
+            let origin = bounds.origin
+                + point(
+                    lane_start + (layout.breakpoint_lane - diameter) / 2.,
+                    *row as f32 * line_height - scroll_top + (line_height - diameter) / 2.,
+                );
+            cx.paint_quad(quad(
+                Bounds::new(origin, size(diameter, diameter)),
+                Corners::all(diameter / 2.),
+                cx.theme().status().error,
+                Edges::default(),
+                transparent_black(),
+            ));
+        }
+    }
+
+    fn paint_gutter_marks(
+        bounds: Bounds<Pixels>,
+        layout: &mut LayoutState,
+        cx: &mut ElementContext,
+    ) {
+        let line_height = layout.position_map.line_height;
+        let scroll_top = layout.position_map.snapshot.scroll_position().y * line_height;
+
+        // Each provider's marks are shown as a bar to the right of the diff hunk bars, next to the
+        // bars of the providers before it. Lines whose data is still loading get a faint bar.
+        let width = line_height * 0.15;
+        let left = diff_hunk_bar_width(line_height) + px(1.);
+        let mut placeholder_color = cx.theme().colors().editor_line_number;
+        placeholder_color.fade_out(0.7);
+        for mark in &layout.gutter_marks {
+            let origin = bounds.origin
+                + point(
+                    left + (width + px(1.)) * mark.provider_ix as f32,
+                    mark.row as f32 * line_height - scroll_top,
+                );
+            let color = match mark.state {
+                GutterMarkState::Loading => placeholder_color,
+                GutterMarkState::Loaded(mark) => mark.color,
+            };
+            layout
+                .quads
+                .fill(Bounds::new(origin, size(width, line_height)), color);
+        }
+        layout.quads.paint(cx);
+    }
+
     fn paint_diff_hunks(bounds: Bounds<Pixels>, layout: &LayoutState, cx: &mut ElementContext) {
         let line_height = layout.position_map.line_height;
 
@@ -791,7 +1142,7 @@ impl EditorElement {
                     let start_y = row as f32 * line_height - scroll_top;
                     let end_y = start_y + line_height;
 
-                    let width = 0.275 * line_height;
+                    let width = diff_hunk_bar_width(line_height);
                     let highlight_origin = bounds.origin + point(-width, start_y);
                     let highlight_size = size(width * 2., end_y - start_y);
                     let highlight_bounds = Bounds::new(highlight_origin, highlight_size);
@@ -824,7 +1175,7 @@ impl EditorElement {
                     let start_y = row as f32 * line_height - offset - scroll_top;
                     let end_y = start_y + line_height;
 
-                    let width = 0.275 * line_height;
+                    let width = diff_hunk_bar_width(line_height);
                     let highlight_origin = bounds.origin + point(-width, start_y);
                     let highlight_size = size(width * 2., end_y - start_y);
                     let highlight_bounds = Bounds::new(highlight_origin, highlight_size);
@@ -864,7 +1215,7 @@ impl EditorElement {
             let start_y = start_row as f32 * line_height - scroll_top;
             let end_y = end_row_in_current_excerpt as f32 * line_height - scroll_top;
 
-            let width = 0.275 * line_height;
+            let width = diff_hunk_bar_width(line_height);
             let highlight_origin = bounds.origin + point(-width, start_y);
             let highlight_size = size(width * 2., end_y - start_y);
             let highlight_bounds = Bounds::new(highlight_origin, highlight_size);
@@ -887,13 +1238,13 @@ impl EditorElement {
         let start_row = layout.visible_display_row_range.start;
         let content_origin = text_bounds.origin + point(layout.gutter_margin, Pixels::ZERO);
         let line_end_overshoot = 0.15 * layout.position_map.line_height;
-        let whitespace_setting = self
-            .editor
-            .read(cx)
-            .buffer
-            .read(cx)
-            .settings_at(0, cx)
-            .show_whitespaces;
+        let (whitespace_setting, trailing_whitespace_setting) = {
+            let settings = self.editor.read(cx).buffer.read(cx).settings_at(0, cx);
+            (
+                settings.show_whitespaces,
+                settings.highlight_trailing_whitespace,
+            )
+        };
 
         cx.with_content_mask(
             Some(ContentMask {
@@ -918,6 +1269,63 @@ impl EditorElement {
                     }
                 }
 
+                for guide in &layout.indent_guides {
+                    let x = content_origin.x + guide.offset - layout.position_map.scroll_position.x;
+                    if x < content_origin.x {
+                        continue;
+                    }
+
+                    let y = content_origin.y
+                        + guide.rows.start as f32 * layout.position_map.line_height
+                        - layout.position_map.scroll_position.y;
+                    let color = if guide.active {
+                        cx.theme().colors().editor_indent_guide_active
+                    } else {
+                        cx.theme().colors().editor_indent_guide
+                    };
+                    layout.quads.fill(
+                        Bounds {
+                            origin: point(x, y),
+                            size: size(
+                                px(1.),
+                                layout.position_map.line_height * guide.rows.len() as f32,
+                            ),
+                        },
+                        color,
+                    );
+                }
+
+                for (range, color) in &layout.bracket_pair_underlines {
+                    let row = range.start.row();
+                    let Some(line) = layout
+                        .position_map
+                        .line_layouts
+                        .get(row.wrapping_sub(start_row) as usize)
+                        .map(|line| &line.line)
+                    else {
+                        continue;
+                    };
+
+                    let x_range = line.x_range_for_index_range(
+                        range.start.column() as usize..range.end.column() as usize,
+                    );
+                    let y = content_origin.y + (row + 1) as f32 * layout.position_map.line_height
+                        - layout.position_map.scroll_position.y
+                        - px(1.);
+                    layout.quads.fill(
+                        Bounds {
+                            origin: point(
+                                content_origin.x + x_range.start
+                                    - layout.position_map.scroll_position.x,
+                                y,
+                            ),
+                            size: size(x_range.end - x_range.start, px(1.)),
+                        },
+                        *color,
+                    );
+                }
+                layout.quads.paint(cx);
+
                 let fold_corner_radius = 0.15 * layout.position_map.line_height;
                 cx.with_element_id(Some("folds"), |cx| {
                     let snapshot = &layout.position_map.snapshot;
@@ -938,19 +1346,22 @@ impl EditorElement {
                             continue;
                         };
 
-                        let start_x = content_origin.x
-                            + line_layout.x_for_index(display_range.start.column() as usize)
+                        let x_range = line_layout.x_range_for_index_range(
+                            display_range.start.column() as usize
+                                ..display_range.end.column() as usize,
+                        );
+                        let start_x = content_origin.x + x_range.start
                             - layout.position_map.scroll_position.x;
                         let start_y = content_origin.y
                             + row as f32 * layout.position_map.line_height
                             - layout.position_map.scroll_position.y;
-                        let end_x = content_origin.x
-                            + line_layout.x_for_index(display_range.end.column() as usize)
-                            - layout.position_map.scroll_position.x;
 
                         let fold_bounds = Bounds {
                             origin: point(start_x, start_y),
-                            size: size(end_x - start_x, layout.position_map.line_height),
+                            size: size(
+                                x_range.end - x_range.start,
+                                layout.position_map.line_height,
+                            ),
                         };
 
                         let fold_background = cx.with_z_index(1, |cx| {
@@ -1013,6 +1424,7 @@ impl EditorElement {
                 }
 
                 let mut cursors = SmallVec::<[Cursor; 32]>::new();
+                let mut name_tags = Vec::new();
                 let corner_radius = 0.15 * layout.position_map.line_height;
                 let mut invisible_display_ranges = SmallVec::<[Range<DisplayPoint>; 32]>::new();
 
@@ -1020,9 +1432,11 @@ impl EditorElement {
                     layout.selections.iter().enumerate()
                 {
                     for selection in selections.into_iter() {
+                        let mut selection_color = player_color.selection;
+                        selection_color.fade_out(1. - selection.opacity);
                         self.paint_highlighted_range(
                             selection.range.clone(),
-                            player_color.selection,
+                            selection_color,
                             corner_radius,
                             corner_radius * 2.,
                             layout,
@@ -1046,83 +1460,192 @@ impl EditorElement {
                                     .line;
                                 let cursor_column = cursor_position.column() as usize;
 
-                                let cursor_character_x =
-                                    cursor_row_layout.x_for_index(cursor_column);
-                                let mut block_width = cursor_row_layout
-                                    .x_for_index(cursor_column + 1)
-                                    - cursor_character_x;
+                                // Block cursors cover the whole grapheme cluster under them, such
+                                // as a character followed by combining marks or an emoji sequence.
+                                let grapheme = layout
+                                    .position_map
+                                    .snapshot
+                                    .chars_at(cursor_position)
+                                    .map(|(character, _)| character)
+                                    .take_while(|character| *character != '\n')
+                                    .take(MAX_GRAPHEME_LEN)
+                                    .collect::<String>()
+                                    .graphemes(true)
+                                    .next()
+                                    .map(ToString::to_string);
+                                let grapheme_len = grapheme.as_ref().map_or(1, String::len);
+                                let character_x_range = cursor_row_layout.x_range_for_index_range(
+                                    cursor_column..cursor_column + grapheme_len,
+                                );
+                                let cursor_character_x = match selection.cursor_shape {
+                                    CursorShape::Bar => {
+                                        cursor_row_layout.x_for_index(cursor_column)
+                                    }
+                                    _ => character_x_range.start,
+                                };
+                                let mut block_width =
+                                    character_x_range.end - character_x_range.start;
                                 if block_width == Pixels::ZERO {
                                     block_width = layout.position_map.em_width;
                                 }
                                 let block_text = if let CursorShape::Block = selection.cursor_shape
                                 {
-                                    layout
-                                        .position_map
-                                        .snapshot
-                                        .chars_at(cursor_position)
-                                        .next()
-                                        .and_then(|(character, _)| {
-                                            let text = if character == '\n' {
-                                                SharedString::from(" ")
-                                            } else {
-                                                SharedString::from(character.to_string())
-                                            };
-                                            let len = text.len();
-                                            cx.text_system()
-                                                .shape_line(
-                                                    text,
-                                                    cursor_row_layout.font_size,
-                                                    &[TextRun {
-                                                        len,
-                                                        font: self.style.text.font(),
-                                                        color: self.style.background,
-                                                        background_color: None,
-                                                        strikethrough: None,
-                                                        underline: None,
-                                                    }],
-                                                )
-                                                .log_err()
-                                        })
+                                    // Newlines are shown as a space.
+                                    let text = SharedString::from(
+                                        grapheme.unwrap_or_else(|| " ".to_string()),
+                                    );
+                                    let len = text.len();
+                                    cx.text_system()
+                                        .shape_line(
+                                            text,
+                                            cursor_row_layout.font_size,
+                                            &[TextRun {
+                                                len,
+                                                font: self.style.text.font(),
+                                                color: self.style.background,
+                                                background_color: None,
+                                                strikethrough: None,
+                                                underline: None,
+                                            }],
+                                        )
+                                        .log_err()
                                 } else {
                                     None
                                 };
 
-                                let x = cursor_character_x - layout.position_map.scroll_position.x;
-                                let y = cursor_position.row() as f32
+                                let mut x =
+                                    cursor_character_x - layout.position_map.scroll_position.x;
+                                let mut y = cursor_position.row() as f32
                                     * layout.position_map.line_height
                                     - layout.position_map.scroll_position.y;
                                 if selection.is_newest {
-                                    self.editor.update(cx, |editor, _| {
+                                    let animate = selection.is_local
+                                        && EditorSettings::get_global(cx).cursor_animation;
+                                    let scroll_position = layout.position_map.scroll_position;
+                                    let line_height = layout.position_map.line_height;
+                                    let is_animating = self.editor.update(cx, |editor, _| {
+                                        let mut is_animating = false;
+                                        if animate {
+                                            let (position, animating) =
+                                                editor.cursor_animation.position(
+                                                    point(x, y) + scroll_position,
+                                                    line_height,
+                                                    Instant::now(),
+                                                );
+                                            x = position.x - scroll_position.x;
+                                            y = position.y - scroll_position.y;
+                                            is_animating = animating;
+                                        } else {
+                                            editor.cursor_animation.reset();
+                                        }
+
                                         editor.pixel_position_of_newest_cursor = Some(point(
                                             text_bounds.origin.x + x + block_width / 2.,
                                             text_bounds.origin.y
                                                 + y
                                                 + layout.position_map.line_height / 2.,
-                                        ))
+                                        ));
+                                        is_animating
                                     });
+                                    if is_animating {
+                                        let editor = self.editor.clone();
+                                        cx.on_next_frame(move |cx| {
+                                            editor.update(cx, |_, cx| cx.notify());
+                                        });
+                                    }
+                                } else if let Some(remote_cursor) = &selection.remote_cursor {
+                                    // Collaborators' cursors glide between the positions received
+                                    // from them, which arrive less often than the cursor moves.
+                                    let scroll_position = layout.position_map.scroll_position;
+                                    let (position, is_animating) =
+                                        self.editor.update(cx, |editor, _| {
+                                            editor.remote_cursors.position(
+                                                remote_cursor,
+                                                point(x, y) + scroll_position,
+                                                layout.position_map.line_height,
+                                                Instant::now(),
+                                            )
+                                        });
+                                    x = position.x - scroll_position.x;
+                                    y = position.y - scroll_position.y;
+                                    if is_animating {
+                                        let editor = self.editor.clone();
+                                        cx.on_next_frame(move |cx| {
+                                            editor.update(cx, |_, cx| cx.notify());
+                                        });
+                                    }
                                 }
 
+                                let cursor_name = selection.user_name.clone().map(|name| {
+                                    // Name tags of cursors close to each other on the same row
+                                    // are stacked instead of covering one another.
+                                    let text_size =
+                                        CursorName::text_size(layout.position_map.line_height);
+                                    let font = self.style.text.font();
+                                    let shape_name = |cx: &WindowContext| {
+                                        cx.text_system()
+                                            .shape_line(
+                                                name.clone(),
+                                                text_size,
+                                                &[TextRun {
+                                                    len: name.len(),
+                                                    font: font.clone(),
+                                                    color: self.style.background,
+                                                    background_color: None,
+                                                    strikethrough: None,
+                                                    underline: None,
+                                                }],
+                                            )
+                                            .map_or(Pixels::ZERO, |line| line.width)
+                                    };
+                                    // Names are only shaped again when they or their font change.
+                                    let name_width = match &selection.remote_cursor {
+                                        Some(remote_cursor) => {
+                                            self.editor.update(cx, |editor, cx| {
+                                                editor.remote_cursors.name_width(
+                                                    remote_cursor,
+                                                    &name,
+                                                    &font,
+                                                    text_size,
+                                                    || shape_name(cx),
+                                                )
+                                            })
+                                        }
+                                        None => shape_name(cx),
+                                    };
+                                    let stack_level = stack_name_tag(
+                                        &mut name_tags,
+                                        cursor_position.row(),
+                                        x..x + block_width + name_width + px(4.),
+                                    );
+                                    CursorName {
+                                        string: name,
+                                        color: self.style.background,
+                                        opacity: selection.user_name_opacity,
+                                        stack_level,
+                                        is_top_row: cursor_position.row() == 0,
+                                        z_index: (participant_ix % 256).try_into().unwrap(),
+                                    }
+                                });
+                                let mut cursor_color = player_color.cursor;
+                                cursor_color.fade_out(1. - selection.opacity);
                                 cursors.push(Cursor {
-                                    color: player_color.cursor,
+                                    color: cursor_color,
                                     block_width,
                                     origin: point(x, y),
                                     line_height: layout.position_map.line_height,
                                     shape: selection.cursor_shape,
                                     block_text,
-                                    cursor_name: selection.user_name.clone().map(|name| {
-                                        CursorName {
-                                            string: name,
-                                            color: self.style.background,
-                                            is_top_row: cursor_position.row() == 0,
-                                            z_index: (participant_ix % 256).try_into().unwrap(),
-                                        }
-                                    }),
+                                    cursor_name,
                                 });
                             }
                         }
                     }
                 }
 
+                // The lines are drawn with the layout borrowed, so their quads are collected
+                // separately.
+                let mut line_quads = mem::take(&mut layout.quads);
                 for (ix, line_with_invisibles) in
                     layout.position_map.line_layouts.iter().enumerate()
                 {
@@ -1132,22 +1655,110 @@ impl EditorElement {
                         row,
                         content_origin,
                         whitespace_setting,
+                        trailing_whitespace_setting,
                         &invisible_display_ranges,
+                        &mut line_quads,
                         cx,
                     )
                 }
+                line_quads.paint(cx);
+                layout.quads = line_quads;
+
+                self.paint_diagnostic_underlines(content_origin, layout, cx);
+
+                for (row, message) in &layout.inline_diagnostics {
+                    let Some(line) = layout
+                        .position_map
+                        .line_layouts
+                        .get((row - start_row) as usize)
+                    else {
+                        continue;
+                    };
+                    let x = line.line.width
+                        + INLINE_DIAGNOSTIC_MARGIN_EMS * layout.position_map.em_width
+                        - layout.position_map.scroll_position.x;
+                    let y = *row as f32 * layout.position_map.line_height
+                        - layout.position_map.scroll_position.y;
+                    message
+                        .paint(
+                            content_origin + point(x, y),
+                            layout.position_map.line_height,
+                            cx,
+                        )
+                        .log_err();
+                }
 
                 cx.with_z_index(0, |cx| self.paint_redactions(text_bounds, &layout, cx));
 
                 cx.with_z_index(1, |cx| {
                     for cursor in cursors {
-                        cursor.paint(content_origin, cx);
+                        cursor.paint_merged(content_origin, &mut layout.quads, cx);
                     }
+                    layout.quads.paint(cx);
                 });
             },
         )
     }
 
+    fn paint_diagnostic_underlines(
+        &self,
+        content_origin: gpui::Point<Pixels>,
+        layout: &LayoutState,
+        cx: &mut ElementContext,
+    ) {
+        let start_row = layout.visible_display_row_range.start;
+        let line_height = layout.position_map.line_height;
+        let scroll_position = layout.position_map.scroll_position;
+        for diagnostic in &layout.diagnostics {
+            let rows = cmp::max(diagnostic.range.start.row(), start_row)
+                ..cmp::min(
+                    diagnostic.range.end.row() + 1,
+                    layout.visible_display_row_range.end,
+                );
+            for row in rows {
+                let Some(line) = layout
+                    .position_map
+                    .line_layouts
+                    .get((row - start_row) as usize)
+                    .map(|line| &line.line)
+                else {
+                    continue;
+                };
+                let start_column = if row == diagnostic.range.start.row() {
+                    diagnostic.range.start.column() as usize
+                } else {
+                    0
+                };
+                let end_column = if row == diagnostic.range.end.row() {
+                    diagnostic.range.end.column() as usize
+                } else {
+                    line.len
+                };
+                // Measure the underlined characters rather than the carets at either end, so
+                // that underlines within right-to-left text are not inverted.
+                let x_range = line.x_range_for_index_range(start_column..end_column);
+                let (start_x, end_x) = (x_range.start, x_range.end);
+                if end_x <= start_x {
+                    continue;
+                }
+
+                // Place the underline where the text system places the underlines of text runs.
+                let padding_top = (line_height - line.ascent - line.descent) / 2.;
+                let y = line_height * row as f32 + padding_top + line.ascent + line.descent * 0.618
+                    - scroll_position.y;
+                cx.paint_underline(
+                    content_origin + point(start_x - scroll_position.x, y),
+                    end_x - start_x,
+                    &UnderlineStyle {
+                        color: Some(diagnostic.color),
+                        thickness: px(1.),
+                        wavy: true,
+                    },
+                );
+            }
+        }
+    }
+
     fn paint_redactions(
         &mut self,
         text_bounds: Bounds<Pixels>,
@@ -1174,6 +1785,47 @@ impl EditorElement {
         }
     }
 
+    fn paint_blame_annotations(
+        &mut self,
+        text_bounds: Bounds<Pixels>,
+        layout: &mut LayoutState,
+        cx: &mut ElementContext,
+    ) {
+        let content_origin = text_bounds.origin + point(layout.gutter_margin, Pixels::ZERO);
+        let start_row = layout.visible_display_row_range.start;
+        let available_space = size(AvailableSpace::MinContent, AvailableSpace::MinContent);
+        cx.with_content_mask(
+            Some(ContentMask {
+                bounds: text_bounds,
+            }),
+            |cx| {
+                for (row, mut annotation) in layout.blame_annotations.drain(..) {
+                    let Some(line) = layout
+                        .position_map
+                        .line_layouts
+                        .get((row - start_row) as usize)
+                    else {
+                        continue;
+                    };
+                    // Annotate after the row's inline diagnostic, if there is one.
+                    let line_end = line.line.width
+                        + layout
+                            .inline_diagnostics
+                            .binary_search_by_key(&row, |(row, _)| *row)
+                            .map_or(Pixels::ZERO, |ix| {
+                                INLINE_DIAGNOSTIC_MARGIN_EMS * layout.position_map.em_width
+                                    + layout.inline_diagnostics[ix].1.width
+                            });
+                    let x = line_end + 4. * layout.position_map.em_width
+                        - layout.position_map.scroll_position.x;
+                    let y = row as f32 * layout.position_map.line_height
+                        - layout.position_map.scroll_position.y;
+                    annotation.draw(content_origin + point(x, y), available_space, cx);
+                }
+            },
+        );
+    }
+
     fn paint_overlays(
         &mut self,
         text_bounds: Bounds<Pixels>,
@@ -1209,19 +1861,43 @@ impl EditorElement {
             cx.break_content_mask(|cx| context_menu.draw(list_origin, available_space, cx));
         }
 
-        if let Some((position, mut hover_popovers)) = layout.hover_popovers.take() {
+        if let Some((position, mut overlay)) = layout.cursor_position_overlay.take() {
+            let available_space = size(AvailableSpace::MinContent, AvailableSpace::MinContent);
+            let overlay_size = overlay.measure(available_space, cx);
+
+            let cursor_row_layout =
+                &layout.position_map.line_layouts[(position.row() - start_row) as usize].line;
+            let x = cursor_row_layout.x_for_index(position.column() as usize)
+                - layout.position_map.scroll_position.x;
+            let y = position.row() as f32 * layout.position_map.line_height
+                - layout.position_map.scroll_position.y;
+            let mut overlay_origin = content_origin + point(x, y - overlay_size.height);
+
+            // Show the overlay below the cursor if there is no room for it above.
+            if overlay_origin.y < text_bounds.origin.y {
+                overlay_origin.y += overlay_size.height + layout.position_map.line_height;
+            }
+            if overlay_origin.x + overlay_size.width > text_bounds.lower_right().x {
+                overlay_origin.x =
+                    (text_bounds.lower_right().x - overlay_size.width).max(text_bounds.origin.x);
+            }
+
+            cx.break_content_mask(|cx| overlay.draw(overlay_origin, available_space, cx));
+        }
+
+        if let Some((position, mut hover_popover)) = layout.hover_popover.take() {
             let available_space = size(AvailableSpace::MinContent, AvailableSpace::MinContent);
 
             // This is safe because we check on layout whether the required row is available
             let hovered_row_layout =
                 &layout.position_map.line_layouts[(position.row() - start_row) as usize].line;
 
-            // Minimum required size: Take the first popover, and add 1.5 times the minimum popover
-            // height. This is the size we will use to decide whether to render popovers above or below
-            // the hovered line.
-            let first_size = hover_popovers[0].measure(available_space, cx);
-            let height_to_reserve =
-                first_size.height + 1.5 * MIN_POPOVER_LINE_HEIGHT * layout.position_map.line_height;
+            // Minimum required size: Take the popover, and add 1.5 times the minimum popover
+            // height. This is the size we will use to decide whether to render the popover above
+            // or below the hovered line.
+            let popover_size = hover_popover.measure(available_space, cx);
+            let height_to_reserve = popover_size.height
+                + 1.5 * MIN_POPOVER_LINE_HEIGHT * layout.position_map.line_height;
 
             // Compute Hovered Point
             let x = hovered_row_layout.x_for_index(position.column() as usize)
@@ -1230,44 +1906,25 @@ impl EditorElement {
                 - layout.position_map.scroll_position.y;
             let hovered_point = content_origin + point(x, y);
 
-            if hovered_point.y - height_to_reserve > Pixels::ZERO {
-                // There is enough space above. Render popovers above the hovered point
-                let mut current_y = hovered_point.y;
-                for mut hover_popover in hover_popovers {
-                    let size = hover_popover.measure(available_space, cx);
-                    let mut popover_origin = point(hovered_point.x, current_y - size.height);
-
-                    let x_out_of_bounds =
-                        text_bounds.upper_right().x - (popover_origin.x + size.width);
-                    if x_out_of_bounds < Pixels::ZERO {
-                        popover_origin.x = popover_origin.x + x_out_of_bounds;
-                    }
-
-                    if cx.was_top_layer(&popover_origin, cx.stacking_order()) {
-                        cx.break_content_mask(|cx| {
-                            hover_popover.draw(popover_origin, available_space, cx)
-                        });
-                    }
-
-                    current_y = popover_origin.y - HOVER_POPOVER_GAP;
-                }
+            let mut popover_origin = if hovered_point.y - height_to_reserve > Pixels::ZERO {
+                // There is enough space above. Render the popover above the hovered point
+                point(hovered_point.x, hovered_point.y - popover_size.height)
             } else {
-                // There is not enough space above. Render popovers below the hovered point
-                let mut current_y = hovered_point.y + layout.position_map.line_height;
-                for mut hover_popover in hover_popovers {
-                    let size = hover_popover.measure(available_space, cx);
-                    let mut popover_origin = point(hovered_point.x, current_y);
-
-                    let x_out_of_bounds =
-                        text_bounds.upper_right().x - (popover_origin.x + size.width);
-                    if x_out_of_bounds < Pixels::ZERO {
-                        popover_origin.x = popover_origin.x + x_out_of_bounds;
-                    }
+                // There is not enough space above. Render the popover below the hovered point
+                point(
+                    hovered_point.x,
+                    hovered_point.y + layout.position_map.line_height,
+                )
+            };
 
-                    hover_popover.draw(popover_origin, available_space, cx);
+            let x_out_of_bounds =
+                text_bounds.upper_right().x - (popover_origin.x + popover_size.width);
+            if x_out_of_bounds < Pixels::ZERO {
+                popover_origin.x = popover_origin.x + x_out_of_bounds;
+            }
 
-                    current_y = popover_origin.y + size.height + HOVER_POPOVER_GAP;
-                }
+            if cx.was_top_layer(&popover_origin, cx.stacking_order()) {
+                cx.break_content_mask(|cx| hover_popover.draw(popover_origin, available_space, cx));
             }
         }
 
@@ -1289,6 +1946,32 @@ impl EditorElement {
         bounds.upper_right().x - self.style.scrollbar_width
     }
 
+    fn paint_autoscroll_anchor(&mut self, cx: &mut ElementContext) {
+        let Some(anchor) = self
+            .editor
+            .read(cx)
+            .middle_click_autoscroll
+            .as_ref()
+            .map(|autoscroll| autoscroll.anchor)
+        else {
+            return;
+        };
+
+        let radius = AUTOSCROLL_ANCHOR_RADIUS;
+        let bounds = Bounds {
+            origin: point(anchor.x - radius, anchor.y - radius),
+            size: size(radius * 2., radius * 2.),
+        };
+        let colors = cx.theme().colors();
+        cx.paint_quad(quad(
+            bounds,
+            Corners::all(radius),
+            colors.editor_background,
+            Edges::all(px(1.)),
+            colors.text_muted,
+        ));
+    }
+
     fn paint_scrollbar(
         &mut self,
         bounds: Bounds<Pixels>,
@@ -1332,6 +2015,7 @@ impl EditorElement {
         let thumb_bottom = y_for_row(row_range.end) + first_row_y_offset;
         let track_bounds = Bounds::from_corners(point(left, top), point(right, bottom));
         let thumb_bounds = Bounds::from_corners(point(left, thumb_top), point(right, thumb_bottom));
+        let mut markers = Vec::new();
 
         if layout.show_scrollbars {
             cx.paint_quad(quad(
@@ -1346,177 +2030,44 @@ impl EditorElement {
                 },
                 cx.theme().colors().scrollbar_track_border,
             ));
-            let scrollbar_settings = EditorSettings::get_global(cx).scrollbar;
-            if layout.is_singleton && scrollbar_settings.selections {
-                let start_anchor = Anchor::min();
-                let end_anchor = Anchor::max();
-                let background_ranges = self
-                    .editor
-                    .read(cx)
-                    .background_highlight_row_ranges::<BufferSearchHighlights>(
-                        start_anchor..end_anchor,
-                        &layout.position_map.snapshot,
-                        50000,
-                    );
-                for range in background_ranges {
-                    let start_y = y_for_row(range.start().row() as f32);
-                    let mut end_y = y_for_row(range.end().row() as f32);
-                    if end_y - start_y < px(1.) {
-                        end_y = start_y + px(1.);
-                    }
-                    let bounds = Bounds::from_corners(point(left, start_y), point(right, end_y));
-                    cx.paint_quad(quad(
+            for (ix, marker) in layout.scrollbar_markers.iter().enumerate() {
+                let start_y = y_for_row(marker.rows.start as f32);
+                let end_y = y_for_row(marker.rows.end as f32).max(start_y + px(1.));
+                let bounds = Bounds::from_corners(point(left, start_y), point(right, end_y));
+                if marker.has_label() {
+                    markers.push(ScrollbarMarker {
                         bounds,
-                        Corners::default(),
-                        cx.theme().status().info,
-                        Edges {
-                            top: Pixels::ZERO,
-                            right: px(1.),
-                            bottom: Pixels::ZERO,
-                            left: px(1.),
-                        },
-                        cx.theme().colors().scrollbar_thumb_border,
-                    ));
-                }
-            }
-
-            if layout.is_singleton && scrollbar_settings.symbols_selections {
-                let selection_ranges = self.editor.read(cx).background_highlights_in_range(
-                    Anchor::min()..Anchor::max(),
-                    &layout.position_map.snapshot,
-                    cx.theme().colors(),
-                );
-                for hunk in selection_ranges {
-                    let start_display = Point::new(hunk.0.start.row(), 0)
-                        .to_display_point(&layout.position_map.snapshot.display_snapshot);
-                    let end_display = Point::new(hunk.0.end.row(), 0)
-                        .to_display_point(&layout.position_map.snapshot.display_snapshot);
-                    let start_y = y_for_row(start_display.row() as f32);
-                    let mut end_y = if hunk.0.start == hunk.0.end {
-                        y_for_row((end_display.row() + 1) as f32)
-                    } else {
-                        y_for_row((end_display.row()) as f32)
-                    };
-
-                    if end_y - start_y < px(1.) {
-                        end_y = start_y + px(1.);
-                    }
-                    let bounds = Bounds::from_corners(point(left, start_y), point(right, end_y));
-
-                    cx.paint_quad(quad(
-                        bounds,
-                        Corners::default(),
-                        cx.theme().status().info,
-                        Edges {
-                            top: Pixels::ZERO,
-                            right: px(1.),
-                            bottom: Pixels::ZERO,
-                            left: px(1.),
-                        },
-                        cx.theme().colors().scrollbar_thumb_border,
-                    ));
-                }
-            }
-
-            if layout.is_singleton && scrollbar_settings.git_diff {
-                for hunk in layout
-                    .position_map
-                    .snapshot
-                    .buffer_snapshot
-                    .git_diff_hunks_in_range(0..(max_row.floor() as u32))
-                {
-                    let start_display = Point::new(hunk.buffer_range.start, 0)
-                        .to_display_point(&layout.position_map.snapshot.display_snapshot);
-                    let end_display = Point::new(hunk.buffer_range.end, 0)
-                        .to_display_point(&layout.position_map.snapshot.display_snapshot);
-                    let start_y = y_for_row(start_display.row() as f32);
-                    let mut end_y = if hunk.buffer_range.start == hunk.buffer_range.end {
-                        y_for_row((end_display.row() + 1) as f32)
-                    } else {
-                        y_for_row((end_display.row()) as f32)
-                    };
-
-                    if end_y - start_y < px(1.) {
-                        end_y = start_y + px(1.);
-                    }
-                    let bounds = Bounds::from_corners(point(left, start_y), point(right, end_y));
-
-                    let color = match hunk.status() {
-                        DiffHunkStatus::Added => cx.theme().status().created,
-                        DiffHunkStatus::Modified => cx.theme().status().modified,
-                        DiffHunkStatus::Removed => cx.theme().status().deleted,
-                    };
-                    cx.paint_quad(quad(
-                        bounds,
-                        Corners::default(),
-                        color,
-                        Edges {
-                            top: Pixels::ZERO,
-                            right: px(1.),
-                            bottom: Pixels::ZERO,
-                            left: px(1.),
-                        },
-                        cx.theme().colors().scrollbar_thumb_border,
-                    ));
-                }
-            }
-
-            if layout.is_singleton && scrollbar_settings.diagnostics {
-                let max_point = layout
-                    .position_map
-                    .snapshot
-                    .display_snapshot
-                    .buffer_snapshot
-                    .max_point();
-
-                let diagnostics = layout
-                    .position_map
-                    .snapshot
-                    .buffer_snapshot
-                    .diagnostics_in_range::<_, Point>(Point::zero()..max_point, false)
-                    // We want to sort by severity, in order to paint the most severe diagnostics last.
-                    .sorted_by_key(|diagnostic| std::cmp::Reverse(diagnostic.diagnostic.severity));
-
-                for diagnostic in diagnostics {
-                    let start_display = diagnostic
-                        .range
-                        .start
-                        .to_display_point(&layout.position_map.snapshot.display_snapshot);
-                    let end_display = diagnostic
-                        .range
-                        .end
-                        .to_display_point(&layout.position_map.snapshot.display_snapshot);
-                    let start_y = y_for_row(start_display.row() as f32);
-                    let mut end_y = if diagnostic.range.start == diagnostic.range.end {
-                        y_for_row((end_display.row() + 1) as f32)
-                    } else {
-                        y_for_row((end_display.row()) as f32)
-                    };
-
-                    if end_y - start_y < px(1.) {
-                        end_y = start_y + px(1.);
-                    }
-                    let bounds = Bounds::from_corners(point(left, start_y), point(right, end_y));
-
-                    let color = match diagnostic.diagnostic.severity {
-                        DiagnosticSeverity::ERROR => cx.theme().status().error,
-                        DiagnosticSeverity::WARNING => cx.theme().status().warning,
-                        DiagnosticSeverity::INFORMATION => cx.theme().status().info,
-                        _ => cx.theme().status().hint,
-                    };
-                    cx.paint_quad(quad(
-                        bounds,
-                        Corners::default(),
-                        color,
-                        Edges {
-                            top: Pixels::ZERO,
-                            right: px(1.),
-                            bottom: Pixels::ZERO,
-                            left: px(1.),
-                        },
-                        cx.theme().colors().scrollbar_thumb_border,
-                    ));
+                        row: marker.rows.start,
+                        marker_ix: ix,
+                    });
                 }
+                let status = cx.theme().status();
+                let color = match marker.kind {
+                    ScrollbarMarkerKind::SearchMatch
+                    | ScrollbarMarkerKind::SelectionMatch
+                    | ScrollbarMarkerKind::SymbolHighlight
+                    | ScrollbarMarkerKind::Info
+                    | ScrollbarMarkerKind::Mark(_) => status.info,
+                    ScrollbarMarkerKind::AddedLines => status.created,
+                    ScrollbarMarkerKind::ModifiedLines => status.modified,
+                    ScrollbarMarkerKind::RemovedLines => status.deleted,
+                    ScrollbarMarkerKind::Error => status.error,
+                    ScrollbarMarkerKind::Warning | ScrollbarMarkerKind::Todo => status.warning,
+                    ScrollbarMarkerKind::Hint => status.hint,
+                    ScrollbarMarkerKind::Bookmark => cx.theme().colors().text_accent,
+                };
+                cx.paint_quad(quad(
+                    bounds,
+                    Corners::default(),
+                    color,
+                    Edges {
+                        top: Pixels::ZERO,
+                        right: px(1.),
+                        bottom: Pixels::ZERO,
+                        left: px(1.),
+                    },
+                    cx.theme().colors().scrollbar_thumb_border,
+                ));
             }
 
             cx.paint_quad(quad(
@@ -1538,12 +2089,34 @@ impl EditorElement {
             stacking_order: cx.stacking_order().clone(),
         };
         let mut mouse_position = cx.mouse_position();
+        let markers = Rc::new(markers);
+        let hovered_marker = interactive_track_bounds
+            .visibly_contains(&mouse_position, cx)
+            .then(|| scrollbar_marker_at(&markers, thumb_bounds, mouse_position))
+            .flatten();
         if interactive_track_bounds.visibly_contains(&mouse_position, cx) {
-            cx.set_cursor_style(CursorStyle::Arrow);
+            cx.set_cursor_style(if hovered_marker.is_some() {
+                CursorStyle::PointingHand
+            } else {
+                CursorStyle::Arrow
+            });
+        }
+
+        if let Some(marker) = hovered_marker.map(|ix| &markers[ix]) {
+            let label =
+                layout.scrollbar_markers[marker.marker_ix].label(&layout.position_map.snapshot);
+            if let Some(label) = label {
+                let view = Tooltip::text(label, cx);
+                cx.set_tooltip(AnyTooltip {
+                    view,
+                    cursor_offset: mouse_position,
+                });
+            }
         }
 
         cx.on_mouse_event({
             let editor = self.editor.clone();
+            let markers = markers.clone();
             move |event: &MouseMoveEvent, phase, cx| {
                 if phase == DispatchPhase::Capture {
                     return;
@@ -1570,6 +2143,14 @@ impl EditorElement {
                         editor.scroll_manager.set_is_dragging_scrollbar(false, cx);
                         if interactive_track_bounds.visibly_contains(&event.position, cx) {
                             editor.scroll_manager.show_scrollbar(cx);
+                            // Repaint to update the hovered marker's tooltip.
+                            if scrollbar_marker_at(&markers, thumb_bounds, event.position)
+                                != hovered_marker
+                            {
+                                cx.notify();
+                            }
+                        } else if hovered_marker.is_some() {
+                            cx.notify();
                         }
                     }
                 })
@@ -1599,7 +2180,17 @@ impl EditorElement {
                     }
 
                     editor.update(cx, |editor, cx| {
-                        if track_bounds.contains(&event.position) {
+                        if let Some(ix) =
+                            scrollbar_marker_at(&markers, thumb_bounds, event.position)
+                        {
+                            // Jump to the marked row, centering it in the viewport.
+                            let mut position = editor.scroll_position(cx);
+                            position.y = (markers[ix].row as f32
+                                - (row_range.end - row_range.start) / 2.)
+                                .max(0.);
+                            editor.set_scroll_position(position, cx);
+                            cx.stop_propagation();
+                        } else if track_bounds.contains(&event.position) {
                             editor.scroll_manager.set_is_dragging_scrollbar(true, cx);
 
                             let y = event.position.y;
@@ -1656,17 +2247,28 @@ impl EditorElement {
                     .map(|row| {
                         let line_layout =
                             &layout.position_map.line_layouts[(row - start_row) as usize].line;
+                        let start_column = if row == range.start.row() {
+                            range.start.column() as usize
+                        } else {
+                            0
+                        };
+                        let end_column = if row == range.end.row() {
+                            range.end.column() as usize
+                        } else {
+                            line_layout.len
+                        };
+                        // Measure the selected characters rather than the carets at either end,
+                        // so that selections within right-to-left text are not inverted.
+                        let x_range = line_layout.x_range_for_index_range(start_column..end_column);
                         HighlightedRangeLine {
                             start_x: if row == range.start.row() {
-                                content_origin.x
-                                    + line_layout.x_for_index(range.start.column() as usize)
+                                content_origin.x + x_range.start
                                     - layout.position_map.scroll_position.x
                             } else {
                                 content_origin.x - layout.position_map.scroll_position.x
                             },
                             end_x: if row == range.end.row() {
-                                content_origin.x
-                                    + line_layout.x_for_index(range.end.column() as usize)
+                                content_origin.x + x_range.end
                                     - layout.position_map.scroll_position.x
                             } else {
                                 content_origin.x + line_layout.width + line_end_overshoot
@@ -1704,6 +2306,73 @@ impl EditorElement {
         }
     }
 
+    fn layout_indent_guides(
+        &self,
+        rows: Range<u32>,
+        newest_selection_head: DisplayPoint,
+        tab_size: u32,
+        em_advance: Pixels,
+        snapshot: &EditorSnapshot,
+    ) -> Vec<IndentGuideLayout> {
+        let buffer = &snapshot.buffer_snapshot;
+        let indent_depth = |buffer_row: u32| {
+            let indent = buffer.indent_size_for_line(buffer_row);
+            let columns = match indent.kind {
+                IndentKind::Space => indent.len,
+                IndentKind::Tab => indent.len * tab_size,
+            };
+            columns / tab_size
+        };
+
+        let mut depths = Vec::with_capacity(rows.len());
+        let mut next_non_blank = None;
+        let mut last_depth = 0;
+        for buffer_row in snapshot.buffer_rows(rows.start).take(rows.len()) {
+            let depth = match buffer_row {
+                // Blank lines continue the guides that are open on both sides of them.
+                Some(buffer_row) if buffer.is_line_blank(buffer_row) => {
+                    let next_depth = match next_non_blank {
+                        Some((next_row, next_depth)) if next_row > buffer_row => next_depth,
+                        _ => {
+                            let next = (buffer_row + 1..=buffer.max_point().row)
+                                .find(|row| !buffer.is_line_blank(*row))
+                                .map_or((u32::MAX, 0), |row| (row, indent_depth(row)));
+                            next_non_blank = Some(next);
+                            next.1
+                        }
+                    };
+                    let previous_depth = buffer
+                        .prev_non_blank_row(buffer_row)
+                        .map_or(0, indent_depth);
+                    previous_depth.min(next_depth)
+                }
+                Some(buffer_row) => indent_depth(buffer_row),
+                // Wrapped lines and blocks continue the guides of the line above them.
+                None => last_depth,
+            };
+            depths.push(depth);
+            last_depth = depth;
+        }
+
+        let guides = indent_guide_ranges(&depths);
+        let head_ix = newest_selection_head.row().wrapping_sub(rows.start) as usize;
+        let active_ix = guides
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, range))| range.contains(&head_ix))
+            .max_by_key(|(_, (level, _))| *level)
+            .map(|(ix, _)| ix);
+        guides
+            .into_iter()
+            .enumerate()
+            .map(|(ix, (level, range))| IndentGuideLayout {
+                offset: em_advance * (level * tab_size) as f32,
+                rows: rows.start + range.start as u32..rows.start + range.end as u32,
+                active: Some(ix) == active_ix,
+            })
+            .collect()
+    }
+
     fn column_pixels(&self, column: usize, cx: &WindowContext) -> Pixels {
         let style = &self.style;
         let font_size = style.text.font_size.to_pixels(cx.rem_size());
@@ -1731,6 +2400,90 @@ impl EditorElement {
         self.column_pixels(digit_count, cx)
     }
 
+    fn layout_diagnostics(
+        &self,
+        range: Range<Anchor>,
+        snapshot: &EditorSnapshot,
+    ) -> Vec<DiagnosticLayout> {
+        snapshot
+            .buffer_snapshot
+            .diagnostics_in_range::<_, Point>(range, false)
+            // Omit underlines for HINT/INFO diagnostics on 'unnecessary' code.
+            .filter(|entry| {
+                entry.diagnostic.severity <= DiagnosticSeverity::WARNING
+                    || !entry.diagnostic.is_unnecessary
+            })
+            // Paint the most severe diagnostics last, over any less severe ones.
+            .sorted_by_key(|entry| cmp::Reverse(entry.diagnostic.severity))
+            .map(|entry| DiagnosticLayout {
+                range: entry
+                    .range
+                    .start
+                    .to_display_point(&snapshot.display_snapshot)
+                    ..entry.range.end.to_display_point(&snapshot.display_snapshot),
+                color: diagnostic_style(entry.diagnostic.severity, true, &self.style.status),
+            })
+            .collect()
+    }
+
+    /// Shapes the message of the most severe primary diagnostic on each buffer line, to be painted
+    /// after the end of the line's last display row.
+    fn layout_inline_diagnostics(
+        &self,
+        range: Range<Anchor>,
+        rows: Range<u32>,
+        snapshot: &EditorSnapshot,
+        cx: &ViewContext<Editor>,
+    ) -> Vec<(u32, ShapedLine)> {
+        let mut diagnostics_by_row = BTreeMap::new();
+        for entry in snapshot
+            .buffer_snapshot
+            .diagnostics_in_range::<_, Point>(range, false)
+        {
+            let diagnostic = entry.diagnostic;
+            if !diagnostic.is_primary
+                || (diagnostic.is_unnecessary && diagnostic.severity > DiagnosticSeverity::WARNING)
+            {
+                continue;
+            }
+
+            let buffer_row = entry.range.start.row;
+            let line_end = Point::new(buffer_row, snapshot.buffer_snapshot.line_len(buffer_row));
+            let row = line_end.to_display_point(&snapshot.display_snapshot).row();
+            let is_most_severe = diagnostics_by_row
+                .get(&row)
+                .map_or(true, |shown: &Diagnostic| {
+                    diagnostic.severity < shown.severity
+                });
+            if rows.contains(&row) && is_most_severe {
+                diagnostics_by_row.insert(row, diagnostic);
+            }
+        }
+
+        let font_size = self.style.text.font_size.to_pixels(cx.rem_size());
+        diagnostics_by_row
+            .into_iter()
+            .filter_map(|(row, diagnostic)| {
+                let message = diagnostic.message.lines().next().unwrap_or_default();
+                let mut color = diagnostic_style(diagnostic.severity, true, &self.style.status);
+                color.fade_out(0.3);
+                let run = TextRun {
+                    len: message.len(),
+                    font: self.style.text.font(),
+                    color,
+                    background_color: None,
+                    underline: None,
+                    strikethrough: None,
+                };
+                let line = cx
+                    .text_system()
+                    .shape_line(message.to_string().into(), font_size, &[run])
+                    .log_err()?;
+                Some((row, line))
+            })
+            .collect()
+    }
+
     //Folds contained in a hunk are ignored apart from shrinking visual size
     //If a fold contains any hunks then that fold line is marked as modified
     fn layout_git_gutters(
@@ -1738,6 +2491,9 @@ impl EditorElement {
         display_rows: Range<u32>,
         snapshot: &EditorSnapshot,
     ) -> Vec<DisplayDiffHunk> {
+        if snapshot.large_file_mode {
+            return Vec::new();
+        }
         let buffer_snapshot = &snapshot.buffer_snapshot;
 
         let buffer_start_row = DisplayPoint::new(display_rows.start, 0)
@@ -1759,6 +2515,7 @@ impl EditorElement {
         snapshot: &EditorSnapshot,
         rows: &Range<u32>,
         relative_to: Option<u32>,
+        hybrid: bool,
     ) -> HashMap<u32, u32> {
         let mut relative_rows: HashMap<u32, u32> = Default::default();
         let Some(relative_to) = relative_to else {
@@ -1774,6 +2531,17 @@ impl EditorElement {
             .collect::<Vec<_>>();
 
         let head_idx = relative_to - start;
+        // In hybrid mode, the current line keeps its absolute number.
+        if !hybrid
+            && rows.contains(&relative_to)
+            && buffer_rows
+                .get(head_idx as usize)
+                .copied()
+                .flatten()
+                .is_some()
+        {
+            relative_rows.insert(relative_to, 0);
+        }
         let mut delta = 1;
         let mut i = head_idx + 1;
         while i < buffer_rows.len() as u32 {
@@ -1810,25 +2578,38 @@ impl EditorElement {
         active_rows: &BTreeMap<u32, bool>,
         newest_selection_head: DisplayPoint,
         is_singleton: bool,
+        gutter_hovered: bool,
         snapshot: &EditorSnapshot,
+        row_layout_cache: &mut RowLayoutCache,
         cx: &ViewContext<Editor>,
     ) -> (
         Vec<Option<ShapedLine>>,
+        Vec<bool>,
         Vec<Option<(FoldStatus, BufferRow, bool)>>,
     ) {
         let font_size = self.style.text.font_size.to_pixels(cx.rem_size());
         let include_line_numbers = snapshot.mode == EditorMode::Full;
         let mut shaped_line_numbers = Vec::with_capacity(rows.len());
+        let mut left_aligned_line_numbers = Vec::with_capacity(rows.len());
         let mut fold_statuses = Vec::with_capacity(rows.len());
         let mut line_number = String::new();
-        let is_relative = EditorSettings::get_global(cx).relative_line_numbers;
+        let settings = EditorSettings::get_global(cx);
+        let is_relative = settings.relative_line_numbers;
+        let left_align_relative =
+            settings.relative_line_numbers_alignment == LineNumberAlignment::Left;
+        let fold_indicators = settings.fold_indicators;
         let relative_to = if is_relative {
             Some(newest_selection_head.row())
         } else {
             None
         };
 
-        let relative_rows = self.calculate_relative_line_numbers(&snapshot, &rows, relative_to);
+        let relative_rows = self.calculate_relative_line_numbers(
+            &snapshot,
+            &rows,
+            relative_to,
+            settings.hybrid_line_numbers,
+        );
 
         for (ix, row) in snapshot
             .buffer_rows(rows.start)
@@ -1843,49 +2624,70 @@ impl EditorElement {
             };
             if let Some(buffer_row) = row {
                 if include_line_numbers {
-                    line_number.clear();
                     let default_number = buffer_row + 1;
-                    let number = relative_rows
-                        .get(&(ix as u32 + rows.start))
-                        .unwrap_or(&default_number);
-                    write!(&mut line_number, "{}", number).unwrap();
-                    let run = TextRun {
-                        len: line_number.len(),
-                        font: self.style.text.font(),
-                        color,
-                        background_color: None,
-                        underline: None,
-                        strikethrough: None,
-                    };
-                    let shaped_line = cx
-                        .text_system()
-                        .shape_line(line_number.clone().into(), font_size, &[run])
-                        .unwrap();
+                    let relative_number = relative_rows.get(&display_row);
+                    let number = *relative_number.unwrap_or(&default_number);
+                    let shaped_line = row_layout_cache.line_number(number, active, || {
+                        line_number.clear();
+                        write!(&mut line_number, "{}", number).unwrap();
+                        let run = TextRun {
+                            len: line_number.len(),
+                            font: self.style.text.font(),
+                            color,
+                            background_color: None,
+                            underline: None,
+                            strikethrough: None,
+                        };
+                        cx.text_system()
+                            .shape_line(line_number.clone().into(), font_size, &[run])
+                            .unwrap()
+                    });
                     shaped_line_numbers.push(Some(shaped_line));
-                    fold_statuses.push(
+                    left_aligned_line_numbers
+                        .push(left_align_relative && relative_number.is_some());
+                    fold_statuses.push(row_layout_cache.fold_status(display_row, active, || {
                         is_singleton
                             .then(|| {
-                                snapshot
-                                    .fold_for_line(buffer_row)
-                                    .map(|fold_status| (fold_status, buffer_row, active))
+                                match fold_indicators {
+                                    FoldIndicators::Indentation => {
+                                        snapshot.fold_for_line(buffer_row)
+                                    }
+                                    // Querying the syntax tree for every row is costlier, so only
+                                    // do it for the rows whose indicators are visible.
+                                    FoldIndicators::Syntax if gutter_hovered || active => {
+                                        snapshot.syntax_fold_for_line(buffer_row)
+                                    }
+                                    FoldIndicators::Syntax => snapshot
+                                        .is_line_folded(buffer_row)
+                                        .then_some(FoldStatus::Folded),
+                                }
+                                .map(|fold_status| (fold_status, buffer_row, active))
                             })
-                            .flatten(),
-                    )
+                            .flatten()
+                    }));
                 }
             } else {
                 fold_statuses.push(None);
                 shaped_line_numbers.push(None);
+                left_aligned_line_numbers.push(false);
             }
         }
 
-        (shaped_line_numbers, fold_statuses)
+        (
+            shaped_line_numbers,
+            left_aligned_line_numbers,
+            fold_statuses,
+        )
     }
 
     fn layout_lines(
         &self,
         rows: Range<u32>,
         line_number_layouts: &[Option<ShapedLine>],
+        bracket_colors: &[BracketColor],
         snapshot: &EditorSnapshot,
+        row_layout_cache: &mut RowLayoutCache,
+        line_layout_cache: &mut LineLayoutCache,
         cx: &ViewContext<Editor>,
     ) -> Vec<LineWithInvisibles> {
         if rows.start >= rows.end {
@@ -1922,19 +2724,188 @@ impl EditorElement {
                 .map(|line| LineWithInvisibles {
                     line,
                     invisibles: Vec::new(),
+                    trailing_whitespace: None,
+                    soft_wrap_indicator: None,
+                    is_stale: false,
                 })
                 .collect()
         } else {
-            let chunks = snapshot.highlighted_chunks(rows.clone(), true, &self.style);
-            LineWithInvisibles::from_chunks(
-                chunks,
-                &self.style.text,
-                MAX_LINE_LEN,
-                rows.len() as usize,
-                line_number_layouts,
-                snapshot.mode,
-                cx,
-            )
+            // Only the rows that changed since the last frame are laid out again. Large files are
+            // laid out without syntax highlighting or invisibles, which are too slow to compute
+            // for them while scrolling.
+            let font_size = self.style.text.font_size.to_pixels(cx.rem_size());
+            let language_aware = !snapshot.large_file_mode;
+            let collect_invisibles = snapshot.mode == EditorMode::Full && !snapshot.large_file_mode;
+            let mut lines =
+                row_layout_cache.lines(rows.clone(), bracket_colors, |run, bracket_colors| {
+                    let chunks =
+                        snapshot.highlighted_chunks(run.clone(), language_aware, &self.style);
+                    LineWithInvisibles::from_chunks(
+                        chunks,
+                        &self.style.text,
+                        MAX_LINE_LEN,
+                        run.len(),
+                        &line_number_layouts[(run.start - rows.start) as usize..],
+                        bracket_colors,
+                        collect_invisibles,
+                        font_size,
+                        |line, runs| {
+                            line_layout_cache
+                                .shape_line(line, font_size, runs, cx)
+                                .unwrap()
+                        },
+                    )
+                });
+
+            // The configured number of rows around the visible ones are shaped right away, and
+            // the rows beyond them are highlighted and shaped in the background, so that they're
+            // ready to be shown without shaping them when scrolling. Their brackets are colored
+            // like the visible ones, so that the lines are found once they're scrolled into view.
+            if snapshot.mode == EditorMode::Full {
+                let overscan = EditorSettings::get_global(cx).overscan_rows;
+                let overscan_rows =
+                    rows.start.saturating_sub(overscan)..rows.end.saturating_add(overscan);
+                let row_count = snapshot.max_point().row() + 1;
+                let parse_count = snapshot.buffer_snapshot.parse_count();
+                let rows_to_overscan = line_layout_cache.rows_to_overscan(
+                    rows.clone(),
+                    overscan,
+                    row_count,
+                    parse_count,
+                );
+                let rows_to_prefetch = line_layout_cache.rows_to_prefetch(
+                    rows.clone(),
+                    (rows.len() as u32).max(overscan),
+                    row_count,
+                    parse_count,
+                );
+                let colorize_brackets =
+                    !snapshot.large_file_mode && EditorSettings::get_global(cx).colorize_brackets;
+                let players = cx.theme().players();
+
+                line_layout_cache.begin_overscan();
+                for rows in rows_to_overscan {
+                    let bracket_colors = if colorize_brackets {
+                        let start =
+                            DisplayPoint::new(rows.start, 0).to_offset(snapshot, Bias::Left);
+                        let end = if rows.end >= row_count {
+                            snapshot.buffer_snapshot.len()
+                        } else {
+                            DisplayPoint::new(rows.end, 0).to_offset(snapshot, Bias::Right)
+                        };
+                        let pairs = bracket_pairs(&snapshot.buffer_snapshot, start..end);
+                        bracket_colors(&pairs, rows.clone(), snapshot, players)
+                    } else {
+                        Vec::new()
+                    };
+                    let chunks =
+                        snapshot.highlighted_chunks(rows.clone(), language_aware, &self.style);
+                    LineWithInvisibles::from_chunks(
+                        chunks,
+                        &self.style.text,
+                        MAX_LINE_LEN,
+                        rows.len(),
+                        &[],
+                        &bracket_colors,
+                        collect_invisibles,
+                        font_size,
+                        |line, runs| {
+                            line_layout_cache
+                                .shape_line(line, font_size, runs, cx)
+                                .unwrap()
+                        },
+                    );
+                }
+                line_layout_cache.end_overscan();
+
+                // The rows next to the visible ones were just laid out.
+                let rows_to_prefetch = rows_to_prefetch
+                    .into_iter()
+                    .flat_map(|rows| {
+                        [
+                            rows.start..rows.end.min(overscan_rows.start),
+                            rows.start.max(overscan_rows.end)..rows.end,
+                        ]
+                    })
+                    .filter(|rows| !rows.is_empty())
+                    .map(|rows| snapshot.text_rows(rows))
+                    .collect::<Vec<_>>();
+                if !rows_to_prefetch.is_empty() {
+                    let text_snapshot = snapshot.text_snapshot();
+                    let style = self.style.clone();
+                    let players = players.clone();
+                    line_layout_cache.prefetch(
+                        font_size,
+                        move || {
+                            let mut lines = Vec::new();
+                            for rows in rows_to_prefetch {
+                                let bracket_colors = if colorize_brackets {
+                                    let start = text_snapshot.offset_for_row(rows.start);
+                                    let end = text_snapshot.offset_for_row(rows.end);
+                                    let pairs =
+                                        bracket_pairs(text_snapshot.buffer_snapshot(), start..end);
+                                    text_bracket_colors(
+                                        &pairs,
+                                        rows.clone(),
+                                        &text_snapshot,
+                                        &players,
+                                    )
+                                } else {
+                                    Vec::new()
+                                };
+                                let chunks = text_snapshot.highlighted_chunks(
+                                    rows.clone(),
+                                    language_aware,
+                                    &style,
+                                );
+                                LineWithInvisibles::from_chunks(
+                                    chunks,
+                                    &style.text,
+                                    MAX_LINE_LEN,
+                                    rows.len(),
+                                    &[],
+                                    &bracket_colors,
+                                    collect_invisibles,
+                                    font_size,
+                                    |line, runs| {
+                                        lines.push((line.to_string(), runs.to_vec()));
+                                        (ShapedLine::default(), false)
+                                    },
+                                );
+                            }
+                            lines
+                        },
+                        cx,
+                    );
+                }
+            }
+
+            // The indicator is shown in the last column of the padding that the display map
+            // inserts before the text of soft-wrapped rows, which is reserved for it.
+            if snapshot.mode == EditorMode::Full
+                && EditorSettings::get_global(cx).soft_wrap_indicator
+            {
+                for (row, line) in rows.clone().zip(&mut lines) {
+                    if row > 0 && !snapshot.is_block_line(row) {
+                        line.soft_wrap_indicator = snapshot
+                            .soft_wrap_indent(row - 1)
+                            .and_then(|indent| indent.checked_sub(1))
+                            .map(|column| column as usize);
+                    }
+                }
+            }
+
+            // Whitespace before a soft wrap or a fold doesn't trail its buffer line.
+            for (row, line) in rows.zip(&mut lines) {
+                if line.trailing_whitespace.is_some() {
+                    let end = DisplayPoint::new(row, snapshot.line_len(row))
+                        .to_point(&snapshot.display_snapshot);
+                    if end.column != snapshot.buffer_snapshot.line_len(end.row) {
+                        line.trailing_whitespace = None;
+                    }
+                }
+            }
+            lines
         }
     }
 
@@ -1962,17 +2933,37 @@ impl EditorElement {
 
             editor.gutter_width = gutter_dimensions.width;
 
-            let text_width = bounds.size.width - gutter_dimensions.width;
             let overscroll = size(em_width, px(0.));
+            // In a centered layout, the gutter stays attached to a column of text in the middle
+            // of the editor, and the remaining width is split between both sides.
+            let centering_padding = if editor.centered_layout(cx) {
+                let max_column = EditorSettings::get_global(cx).centered_layout.max_column;
+                let column_width = gutter_dimensions.width
+                    + gutter_dimensions.margin
+                    + em_advance * max_column as f32
+                    + overscroll.width
+                    + em_width;
+                ((bounds.size.width - column_width) / 2.).max(Pixels::ZERO)
+            } else {
+                Pixels::ZERO
+            };
+            let text_width = bounds.size.width - gutter_dimensions.width - centering_padding * 2.;
             let _snapshot = {
                 editor.set_visible_line_count((bounds.size.height / line_height).into(), cx);
 
                 let editor_width = text_width - gutter_dimensions.margin - overscroll.width - em_width;
+                editor.set_visible_column_count(f32::from(editor_width / em_width));
                 let wrap_width = match editor.soft_wrap_mode(cx) {
                     SoftWrap::None => (MAX_LINE_LEN / 2) as f32 * em_advance,
                     SoftWrap::EditorWidth => editor_width,
                     SoftWrap::Column(column) => editor_width.min(column as f32 * em_advance),
                 };
+                if !editor.layout_caching {
+                    editor.line_layout_cache.invalidate();
+                }
+                editor
+                    .line_layout_cache
+                    .begin_frame(&self.style.text, Some(wrap_width));
 
                 if editor.set_wrap_width(Some(wrap_width), cx) {
                     editor.snapshot(cx)
@@ -1984,7 +2975,20 @@ impl EditorElement {
             let wrap_guides = editor
                 .wrap_guides(cx)
                 .iter()
-                .map(|(guide, active)| (self.column_pixels(*guide, cx), *active))
+                .map(|(guide, active)| WrapGuideLayout {
+                    offset: self.column_pixels(guide.column(), cx),
+                    color: guide.color().map_or_else(
+                        || {
+                            if *active {
+                                cx.theme().colors().editor_active_wrap_guide
+                            } else {
+                                cx.theme().colors().editor_wrap_guide
+                            }
+                        },
+                        Hsla::from,
+                    ),
+                    style: guide.style(),
+                })
                 .collect::<SmallVec<[_; 2]>>();
 
             let gutter_size = size(gutter_dimensions.width, bounds.size.height);
@@ -1994,6 +2998,32 @@ impl EditorElement {
                 editor.autoscroll_vertically(bounds.size.height, line_height, cx);
             let mut snapshot = editor.snapshot(cx);
 
+            // Only the layouts of the rows that changed since the last frame are rebuilt.
+            let dirty_rows = editor.display_map.update(cx, |map, cx| map.take_dirty_rows(cx));
+            if dirty_rows.is_some() {
+                editor.scrollbar_marker_cache.invalidate();
+            }
+            if !editor.layout_caching {
+                editor.row_layout_cache = RowLayoutCache::default();
+            }
+            editor.row_layout_cache.begin_frame(
+                RowLayoutKey {
+                    font: style.text.font(),
+                    font_size,
+                    text_color: style.text.color,
+                    line_number_color: cx.theme().colors().editor_line_number,
+                    active_line_number_color: cx.theme().colors().editor_active_line_number,
+                    fold_indicators: EditorSettings::get_global(cx).fold_indicators,
+                    gutter_hovered: editor.gutter_hovered,
+                    large_file_mode: snapshot.large_file_mode,
+                    parse_count: snapshot.buffer_snapshot.parse_count(),
+                    diagnostics_update_count: snapshot.buffer_snapshot.diagnostics_update_count(),
+                    syntax_theme: style.syntax.clone(),
+                },
+                editor.display_map.read(cx).highlights_version(),
+                dirty_rows,
+            );
+
             let scroll_position = snapshot.scroll_position();
             // The scroll position is a fractional point, the whole number of which represents
             // the top of the window in terms of display rows.
@@ -2024,6 +3054,11 @@ impl EditorElement {
             let is_singleton = editor.is_singleton(cx);
 
             let highlighted_rows = editor.highlighted_rows();
+            let excerpt_bands = if is_singleton || snapshot.mode != EditorMode::Full {
+                Vec::new()
+            } else {
+                layout_excerpt_bands(start_row..end_row, &snapshot)
+            };
             let highlighted_ranges = editor.background_highlights_in_range(
                 start_anchor..end_anchor,
                 &snapshot.display_snapshot,
@@ -2048,7 +3083,7 @@ impl EditorElement {
                     let layout = SelectionLayout::new(
                         selection,
                         editor.selections.line_mode,
-                        editor.cursor_shape,
+                        editor.local_cursor_shape(cx),
                         &snapshot.display_snapshot,
                         is_newest,
                         true,
@@ -2095,6 +3130,7 @@ impl EditorElement {
                 }
 
                 let mut remote_selections = HashMap::default();
+                let now = Instant::now();
                 for selection in snapshot.remote_selections_in_range(
                     &(start_anchor..end_anchor),
                     collaboration_hub.as_ref(),
@@ -2116,37 +3152,52 @@ impl EditorElement {
                     let key = HoveredCursor{replica_id: selection.replica_id, selection_id: selection.selection.id};
 
                     let is_shown = editor.show_cursor_names || editor.hovered_cursors.contains_key(&key);
+                    let idle = editor.remote_cursors.record(
+                        key.clone(),
+                        selection.selection.start..selection.selection.end,
+                        now,
+                    );
+                    // Name tags are also shown while their collaborator is moving their cursor.
+                    let name_tag_opacity = name_tag_opacity(idle);
 
+                    let mut layout = SelectionLayout::new(
+                        selection.selection,
+                        selection.line_mode,
+                        selection.cursor_shape,
+                        &snapshot.display_snapshot,
+                        false,
+                        false,
+                        if is_shown || name_tag_opacity > 0. {
+                            selection.user_name
+                        } else {
+                            None
+                        },
+                    );
+                    if !is_shown {
+                        layout.user_name_opacity = name_tag_opacity;
+                    }
+                    layout.remote_cursor = Some(key);
+                    layout.opacity = presence_opacity(idle);
                     remote_selections
                         .entry(selection.replica_id)
                         .or_insert((selection_style, Vec::new()))
                         .1
-                        .push(SelectionLayout::new(
-                            selection.selection,
-                            selection.line_mode,
-                            selection.cursor_shape,
-                            &snapshot.display_snapshot,
-                            false,
-                            false,
-                            if is_shown {
-                                selection.user_name
-                            } else {
-                                None
-                            },
-                        ));
+                        .push(layout);
                 }
 
                 selections.extend(remote_selections.into_values());
+                editor.refresh_remote_cursors(now, cx);
             }
 
             let scrollbar_settings = EditorSettings::get_global(cx).scrollbar;
             let show_scrollbars = match scrollbar_settings.show {
                 ShowScrollbar::Auto => {
                     // Git
-                    (is_singleton && scrollbar_settings.git_diff && snapshot.buffer_snapshot.has_git_diffs())
+                    (is_singleton && scrollbar_settings.git_diff && !snapshot.large_file_mode
+                        && snapshot.buffer_snapshot.has_git_diffs())
                     ||
                     // Selections
-                    (is_singleton && scrollbar_settings.selections && editor.has_background_highlights::<BufferSearchHighlights>())
+                    (is_singleton && scrollbar_settings.selections && (editor.has_background_highlights::<BufferSearchHighlights>() || editor.has_background_highlights::<SelectionMatchHighlights>()))
                     ||
                     // Symbols Selections
                     (is_singleton && scrollbar_settings.symbols_selections && (editor.has_background_highlights::<DocumentHighlightRead>() || editor.has_background_highlights::<DocumentHighlightWrite>()))
@@ -2154,6 +3205,15 @@ impl EditorElement {
                     // Diagnostics
                     (is_singleton && scrollbar_settings.diagnostics && snapshot.buffer_snapshot.has_diagnostics())
                     ||
+                    // TODOs
+                    (is_singleton && scrollbar_settings.todos && editor.text_highlights::<TodoHighlights>(cx).is_some())
+                    ||
+                    // Bookmarks
+                    (scrollbar_settings.bookmarks && editor.has_bookmarks(cx))
+                    ||
+                    // Marks
+                    (scrollbar_settings.marks && !editor.marks.is_empty())
+                    ||
                     // Scrollmanager
                     editor.scroll_manager.scrollbars_visible()
                 }
@@ -2161,13 +3221,18 @@ impl EditorElement {
                 ShowScrollbar::Always => true,
                 ShowScrollbar::Never => false,
             };
+            let scrollbar_markers = if show_scrollbars && snapshot.mode == EditorMode::Full {
+                editor.scrollbar_markers(&snapshot, is_singleton, cx)
+            } else {
+                Rc::default()
+            };
 
             let head_for_relative = newest_selection_head.unwrap_or_else(|| {
                 let newest = editor.selections.newest::<Point>(cx);
                 SelectionLayout::new(
                     newest,
                     editor.selections.line_mode,
-                    editor.cursor_shape,
+                    editor.local_cursor_shape(cx),
                     &snapshot.display_snapshot,
                     true,
                     true,
@@ -2176,34 +3241,107 @@ impl EditorElement {
                 .head
             });
 
-            let (line_numbers, fold_statuses) = self.shape_line_numbers(
+            let indent_guides = editor
+                .indent_guides_tab_size(cx)
+                .map(|tab_size| {
+                    self.layout_indent_guides(
+                        start_row..end_row,
+                        head_for_relative,
+                        tab_size,
+                        em_advance,
+                        &snapshot,
+                    )
+                })
+                .unwrap_or_default();
+
+            let (line_numbers, left_aligned_line_numbers, fold_statuses) = self.shape_line_numbers(
                 start_row..end_row,
                 &active_rows,
                 head_for_relative,
                 is_singleton,
+                editor.gutter_hovered,
                 &snapshot,
+                &mut editor.row_layout_cache,
                 cx,
             );
 
-            let display_hunks = self.layout_git_gutters(start_row..end_row, &snapshot);
+            let visible_brackets = if editor.mode == EditorMode::Full
+                && !snapshot.large_file_mode
+                && EditorSettings::get_global(cx).colorize_brackets
+            {
+                bracket_pairs(
+                    &snapshot.buffer_snapshot,
+                    start_anchor.to_offset(&snapshot.buffer_snapshot)
+                        ..end_anchor.to_offset(&snapshot.buffer_snapshot),
+                )
+            } else {
+                Vec::new()
+            };
+            let visible_bracket_colors = bracket_colors(
+                &visible_brackets,
+                start_row..end_row,
+                &snapshot,
+                cx.theme().players(),
+            );
+            let bracket_pair_underlines = if visible_brackets.is_empty() {
+                Vec::new()
+            } else {
+                let cursor = editor.selections.newest::<usize>(cx).head();
+                cursor_bracket_pair_underlines(&visible_brackets, cursor, &snapshot)
+                    .map(|(pair, ranges)| {
+                        let mut color = bracket_pair_color(pair, cx.theme().players());
+                        color.fade_out(0.6);
+                        ranges.into_iter().map(|range| (range, color)).collect()
+                    })
+                    .unwrap_or_default()
+            };
+
+            let display_hunks = editor.row_layout_cache.display_hunks(
+                start_row..end_row,
+                max_row + 1,
+                snapshot.buffer_snapshot.git_diff_update_count(),
+                |rows| self.layout_git_gutters(rows, &snapshot),
+            );
+            let diagnostics = self.layout_diagnostics(start_anchor..end_anchor, &snapshot);
+            let inline_diagnostics = if editor.inline_diagnostics_enabled(cx) {
+                self.layout_inline_diagnostics(
+                    start_anchor..end_anchor,
+                    start_row..end_row,
+                    &snapshot,
+                    cx,
+                )
+            } else {
+                Vec::new()
+            };
 
             let scrollbar_row_range = scroll_position.y..(scroll_position.y + height_in_lines);
 
             let mut max_visible_line_width = Pixels::ZERO;
-            let line_layouts = self.layout_lines(start_row..end_row, &line_numbers, &snapshot, cx);
+            let line_layouts = self.layout_lines(
+                start_row..end_row,
+                &line_numbers,
+                &visible_bracket_colors,
+                &snapshot,
+                &mut editor.row_layout_cache,
+                &mut editor.line_layout_cache,
+                cx,
+            );
+            editor.line_layout_cache.end_frame();
+            editor.line_layout_cache.shape_stale_lines(cx);
             for line_with_invisibles in &line_layouts {
                 if line_with_invisibles.line.width > max_visible_line_width {
                     max_visible_line_width = line_with_invisibles.line.width;
                 }
             }
 
-            let longest_line_width = layout_line(snapshot.longest_row(), &snapshot, &style, cx)
-                .unwrap()
-                .width;
+            let longest_row = snapshot.longest_row();
+            let longest_line_width = editor.row_layout_cache.longest_line_width(longest_row, || {
+                layout_line(longest_row, &snapshot, &style, cx).unwrap().width
+            });
             let scroll_width = longest_line_width.max(max_visible_line_width) + overscroll.width;
 
             let editor_view = cx.view().clone();
-            let (scroll_width, blocks) = cx.with_element_context(|cx| {
+            let (scroll_width, blocks, resized_blocks) = cx.with_element_context(|cx| {
              cx.with_element_id(Some("editor_blocks"), |cx| {
                 self.layout_blocks(
                     start_row..end_row,
@@ -2212,9 +3350,9 @@ impl EditorElement {
                     scroll_width,
                     text_width,
                     gutter_dimensions.padding,
-                    gutter_dimensions.width,
+                    centering_padding + gutter_dimensions.width,
                     em_width,
-                    gutter_dimensions.width + gutter_dimensions.margin,
+                    centering_padding + gutter_dimensions.width + gutter_dimensions.margin,
                     line_height,
                     &style,
                     &line_layouts,
@@ -2224,6 +3362,11 @@ impl EditorElement {
                 )
             })
             });
+            editor.row_layout_cache.end_frame(start_row..end_row);
+            let blocks_resized = !resized_blocks.is_empty();
+            if blocks_resized {
+                editor.resize_block_decorations(resized_blocks, cx);
+            }
 
             let scroll_max = point(
                 f32::from((scroll_width - text_size.width) / em_width).max(0.0),
@@ -2249,7 +3392,37 @@ impl EditorElement {
                 snapshot = editor.snapshot(cx);
             }
 
+            let breakpoint_rows = editor.breakpoint_display_rows(start_row..end_row, &snapshot, cx);
+            let bookmark_rows = editor.bookmark_display_rows(start_row..end_row, &snapshot, cx);
+            let gutter_marks = editor.gutter_marks(start_row..end_row, &snapshot, cx);
+            let mut runnable_indicators =
+                editor.render_runnable_indicators(start_row..end_row, &snapshot, cx);
+            let code_cell_rows = editor.code_cell_marker_rows(start_row..end_row, &snapshot);
+            runnable_indicators.extend(editor.render_code_cell_indicators(
+                &code_cell_rows,
+                &snapshot,
+                cx,
+            ));
+
+            let blame_annotations = editor.render_blame_annotations(
+                start_row..end_row,
+                head_for_relative,
+                &snapshot,
+                &style,
+                cx,
+            );
+
+            let sticky_header = render_sticky_header(
+                editor,
+                &snapshot,
+                start_row,
+                gutter_dimensions.margin,
+                &style,
+                cx,
+            );
+
             let mut context_menu = None;
+            let mut cursor_position_overlay = None;
             let mut code_actions_indicator = None;
             if let Some(newest_selection_head) = newest_selection_head {
                 if (start_row..end_row).contains(&newest_selection_head.row()) {
@@ -2263,6 +3436,9 @@ impl EditorElement {
                         );
                         context_menu =
                             editor.render_context_menu(newest_selection_head, &self.style, max_height, cx);
+                    } else {
+                        cursor_position_overlay = render_cursor_position_overlay(editor, cx)
+                            .map(|element| (newest_selection_head, element));
                     }
 
                     let active = matches!(
@@ -2279,6 +3455,13 @@ impl EditorElement {
                 }
             }
 
+            // The code actions indicator takes the place of the run button on its row.
+            if let Some(indicator) = &code_actions_indicator {
+                runnable_indicators.retain(|(row, _)| *row != indicator.row);
+            }
+
+            let read_only_lock = editor.render_read_only_lock(cx);
+
             let visible_rows = start_row..start_row + line_layouts.len() as u32;
             let max_size = size(
                 (120. * em_width) // Default size
@@ -2318,28 +3501,35 @@ impl EditorElement {
             });
 
             let invisible_symbol_font_size = font_size / 2.;
-            let tab_invisible = cx
+            let whitespace_map = editor.buffer.read(cx).settings_at(0, cx).whitespace_map.clone();
+            let invisible_color = cx.theme().colors().editor_invisible;
+            let nbsp_color = cx.theme().status().warning;
+            let shape_invisible = |symbol: String, color: Hsla| {
+                cx.text_system()
+                    .shape_line(
+                        symbol.clone().into(),
+                        invisible_symbol_font_size,
+                        &[TextRun {
+                            len: symbol.len(),
+                            font: self.style.text.font(),
+                            color,
+                            background_color: None,
+                            underline: None,
+                            strikethrough: None,
+                        }],
+                    )
+                    .unwrap()
+            };
+            let tab_invisible = shape_invisible(whitespace_map.tab, invisible_color);
+            let space_invisible = shape_invisible(whitespace_map.space, invisible_color);
+            let nbsp_invisible = shape_invisible(whitespace_map.nbsp, nbsp_color);
+            let soft_wrap_indicator = cx
                 .text_system()
                 .shape_line(
-                    "→".into(),
-                    invisible_symbol_font_size,
+                    "↪".into(),
+                    font_size,
                     &[TextRun {
-                        len: "→".len(),
-                        font: self.style.text.font(),
-                        color: cx.theme().colors().editor_invisible,
-                        background_color: None,
-                        underline: None,
-                        strikethrough: None,
-                    }],
-                )
-                .unwrap();
-            let space_invisible = cx
-                .text_system()
-                .shape_line(
-                    "•".into(),
-                    invisible_symbol_font_size,
-                    &[TextRun {
-                        len: "•".len(),
+                        len: "↪".len(),
                         font: self.style.text.font(),
                         color: cx.theme().colors().editor_invisible,
                         background_color: None,
@@ -2367,28 +3557,50 @@ impl EditorElement {
                 visible_anchor_range: start_anchor..end_anchor,
                 visible_display_row_range: start_row..end_row,
                 wrap_guides,
+                indent_guides,
+                bracket_pair_underlines,
+                diagnostics,
+                inline_diagnostics,
                 gutter_size,
                 gutter_padding: gutter_dimensions.padding,
+                breakpoint_lane: gutter_dimensions.breakpoint_lane,
+                centering_padding,
                 text_size,
                 scrollbar_row_range,
                 show_scrollbars,
+                scrollbar_markers,
+                quads: MergedQuads::new(editor.quad_merging),
                 is_singleton,
                 max_row,
                 gutter_margin: gutter_dimensions.margin,
                 active_rows,
                 highlighted_rows,
+                excerpt_bands,
                 highlighted_ranges,
                 redacted_ranges,
                 line_numbers,
+                left_aligned_line_numbers,
+                breakpoint_rows,
+                bookmark_rows,
+                gutter_marks,
+                runnable_indicators,
+                code_cell_rows,
                 display_hunks,
                 blocks,
+                blocks_resized,
                 selections,
                 context_menu,
+                cursor_position_overlay,
+                sticky_header,
+                blame_annotations,
                 code_actions_indicator,
+                read_only_lock,
                 fold_indicators,
                 tab_invisible,
                 space_invisible,
-                hover_popovers: hover,
+                nbsp_invisible,
+                soft_wrap_indicator,
+                hover_popover: hover,
             }
         })
     }
@@ -2411,7 +3623,7 @@ impl EditorElement {
         editor: &mut Editor,
         editor_view: View<Editor>,
         cx: &mut ElementContext,
-    ) -> (Pixels, Vec<BlockLayout>) {
+    ) -> (Pixels, Vec<BlockLayout>, HashMap<BlockId, u8>) {
         let mut block_id = 0;
         let (fixed_blocks, non_fixed_blocks) = snapshot
             .blocks_in_range(rows.clone())
@@ -2420,11 +3632,17 @@ impl EditorElement {
                 TransformBlock::Custom(block) => block.style() == BlockStyle::Fixed,
             });
 
-        let render_block = |block: &TransformBlock,
-                            available_space: Size<AvailableSpace>,
-                            block_id: usize,
-                            editor: &mut Editor,
-                            cx: &mut ElementContext| {
+        // Only the blocks intersecting the visible rows are rendered, and the others are assumed
+        // to span the rows they were inserted with, so avoid doing work that scales with the
+        // total number of blocks or excerpts in the multibuffer.
+        let excerpt_count = snapshot.buffer_snapshot.excerpt_count();
+
+        let mut resized_blocks = HashMap::default();
+        let mut render_block = |block: &TransformBlock,
+                                available_space: Size<AvailableSpace>,
+                                block_id: usize,
+                                editor: &mut Editor,
+                                cx: &mut ElementContext| {
             let mut element = match block {
                 TransformBlock::Custom(block) => {
                     let align_to = block
@@ -2437,8 +3655,11 @@ impl EditorElement {
                                 .line
                                 .x_for_index(align_to.column() as usize)
                         } else {
-                            layout_line(align_to.row(), snapshot, style, cx)
-                                .unwrap()
+                            editor
+                                .row_layout_cache
+                                .row_line(align_to.row(), || {
+                                    layout_line(align_to.row(), snapshot, style, cx).unwrap()
+                                })
                                 .x_for_index(align_to.column() as usize)
                         };
 
@@ -2457,6 +3678,7 @@ impl EditorElement {
                 }
 
                 TransformBlock::ExcerptHeader {
+                    id,
                     buffer,
                     range,
                     starts_new_buffer,
@@ -2484,6 +3706,15 @@ impl EditorElement {
                         })
                     });
 
+                    let excerpt_ix = snapshot
+                        .buffer_snapshot
+                        .excerpt_index(*id)
+                        .unwrap_or_default();
+                    let excerpt_position =
+                        Label::new(format!("Excerpt {} of {}", excerpt_ix + 1, excerpt_count))
+                            .size(LabelSize::Small)
+                            .color(Color::Muted);
+
                     let element = if *starts_new_buffer {
                         let path = buffer.resolve_file_path(cx, include_root);
                         let mut filename = None;
@@ -2495,6 +3726,13 @@ impl EditorElement {
                                 .parent()
                                 .map(|p| SharedString::from(p.to_string_lossy().to_string() + "/"));
                         }
+                        let excerpt_group = snapshot
+                            .excerpt_group(*id)
+                            .filter(|(_, excerpt_count)| *excerpt_count > 1)
+                            .map(|(range, excerpt_count)| {
+                                (excerpt_count, snapshot.is_range_folded(&range))
+                            });
+                        let excerpt_id = *id;
 
                         v_flex()
                             .id(("path header container", block_id))
@@ -2515,23 +3753,60 @@ impl EditorElement {
                                     .justify_between()
                                     .hover(|style| style.bg(cx.theme().colors().element_hover))
                                     .child(
-                                        h_flex().gap_3().child(
-                                            h_flex()
-                                                .gap_2()
-                                                .child(
-                                                    filename
-                                                        .map(SharedString::from)
-                                                        .unwrap_or_else(|| "untitled".into()),
+                                        h_flex()
+                                            .gap_3()
+                                            .when_some(excerpt_group, |this, (_, folded)| {
+                                                this.child(
+                                                    IconButton::new(
+                                                        ("toggle excerpt group", block_id),
+                                                        if folded {
+                                                            IconName::ChevronRight
+                                                        } else {
+                                                            IconName::ChevronDown
+                                                        },
+                                                    )
+                                                    .icon_size(IconSize::Small)
+                                                    .on_click(cx.listener_for(
+                                                        &self.editor,
+                                                        move |editor, _, cx| {
+                                                            editor.toggle_excerpt_group_fold(
+                                                                excerpt_id, cx,
+                                                            );
+                                                        },
+                                                    )),
                                                 )
-                                                .when_some(parent_path, |then, path| {
-                                                    then.child(
-                                                        div().child(path).text_color(
-                                                            cx.theme().colors().text_muted,
-                                                        ),
+                                            })
+                                            .child(
+                                                h_flex()
+                                                    .gap_2()
+                                                    .child(
+                                                        filename
+                                                            .map(SharedString::from)
+                                                            .unwrap_or_else(|| "untitled".into()),
                                                     )
-                                                }),
-                                        ),
+                                                    .when_some(parent_path, |then, path| {
+                                                        then.child(div().child(path).text_color(
+                                                            cx.theme().colors().text_muted,
+                                                        ))
+                                                    }),
+                                            )
+                                            .when_some(excerpt_group, |this, (excerpt_count, _)| {
+                                                this.child(
+                                                    Label::new(format!("{excerpt_count} excerpts"))
+                                                        .size(LabelSize::Small)
+                                                        .color(Color::Muted),
+                                                )
+                                            })
+                                            .child(render_diagnostic_badges(buffer)),
                                     )
+                                    .child(h_flex().gap_2().child(excerpt_position).child(
+                                        render_excerpt_header_buttons(
+                                            &self.editor,
+                                            *id,
+                                            block_id,
+                                            cx,
+                                        ),
+                                    ))
                                     .when_some(jump_handler, |this, jump_handler| {
                                         this.cursor_pointer()
                                             .tooltip(|cx| {
@@ -2541,9 +3816,6 @@ impl EditorElement {
                                                     cx,
                                                 )
                                             })
-                                            .on_mouse_down(MouseButton::Left, |_, cx| {
-                                                cx.stop_propagation()
-                                            })
                                             .on_click(jump_handler)
                                     }),
                             )
@@ -2581,12 +3853,83 @@ impl EditorElement {
                                         })
                                     }),
                             )
+                            .child(
+                                h_flex()
+                                    .flex_none()
+                                    .gap_2()
+                                    .pr(gpui::px(8.))
+                                    .child(excerpt_position)
+                                    .child(render_excerpt_header_buttons(
+                                        &self.editor,
+                                        *id,
+                                        block_id,
+                                        cx,
+                                    )),
+                            )
                     };
-                    element.into_any()
+
+                    let editor_id = self.editor.entity_id();
+                    let target_excerpt_id = *id;
+                    let dragged_excerpt = DraggedExcerpt {
+                        editor_id,
+                        excerpt_id: *id,
+                        ix: excerpt_ix,
+                        title: buffer.file().map_or_else(
+                            || "untitled".into(),
+                            |file| file.file_name(cx).to_string_lossy().to_string().into(),
+                        ),
+                    };
+                    let drop_indicator_color = cx.theme().colors().text_accent;
+                    element
+                        .on_drag(dragged_excerpt, |dragged, cx| {
+                            cx.new_view(|_| dragged.clone())
+                        })
+                        // Registered after the drag listener so that it doesn't prevent dragging.
+                        .on_mouse_down(MouseButton::Left, |_, cx| cx.stop_propagation())
+                        .can_drop(move |dragged, _| {
+                            dragged
+                                .downcast_ref::<DraggedExcerpt>()
+                                .map_or(false, |dragged| dragged.editor_id == editor_id)
+                        })
+                        .drag_over::<DraggedExcerpt>(move |style, dragged, _| {
+                            // Excerpts dragged upwards land above the target, and excerpts dragged
+                            // downwards land below it.
+                            if dragged.ix > excerpt_ix {
+                                style.border_t_2().border_color(drop_indicator_color)
+                            } else if dragged.ix < excerpt_ix {
+                                style.border_b_2().border_color(drop_indicator_color)
+                            } else {
+                                style
+                            }
+                        })
+                        .on_drop(cx.listener_for(
+                            &self.editor,
+                            move |editor, dragged: &DraggedExcerpt, cx| {
+                                editor.move_excerpt(dragged.excerpt_id, target_excerpt_id, cx);
+                            },
+                        ))
+                        .into_any()
                 }
             };
 
-            let size = element.measure(available_space, cx);
+            let size = match block {
+                TransformBlock::Custom(block)
+                    if editor.block_decorations.fits_content(block.id()) =>
+                {
+                    // Decorations that fit their content are measured without limiting their
+                    // height, and resized to the rows they need before the frame is painted.
+                    let measured = element
+                        .measure(size(available_space.width, AvailableSpace::MinContent), cx);
+                    let rows = (measured.height / line_height)
+                        .ceil()
+                        .clamp(1., u8::MAX as f32);
+                    if rows as u8 != block.height() {
+                        resized_blocks.insert(block.id(), rows as u8);
+                    }
+                    measured
+                }
+                _ => element.measure(available_space, cx),
+            };
             (element, size)
         };
 
@@ -2633,9 +3976,46 @@ impl EditorElement {
                 style,
             });
         }
+
+        // Block decorations that fit their content are the only blocks whose height depends on
+        // how they render. They're measured before they're scrolled into view, rather than
+        // jumping to their real height as they appear, and their elements are then discarded.
+        let overscan_rows = rows.start.saturating_sub(BLOCK_OVERSCAN_ROWS)
+            ..(rows.end + BLOCK_OVERSCAN_ROWS).min(snapshot.max_point().row() + 1);
+        let overscan_blocks = snapshot
+            .blocks_in_range(overscan_rows)
+            .filter(|(row, block)| *row >= rows.end || *row + block.height() as u32 <= rows.start)
+            .filter_map(|(_, block)| match block {
+                TransformBlock::Custom(custom)
+                    if editor.block_decorations.fits_content(custom.id()) =>
+                {
+                    Some((block, custom.style()))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for (block, style) in overscan_blocks {
+            let width = match style {
+                BlockStyle::Fixed => AvailableSpace::MinContent,
+                BlockStyle::Sticky => AvailableSpace::Definite(editor_width),
+                BlockStyle::Flex => AvailableSpace::Definite(
+                    editor_width
+                        .max(fixed_block_max_width)
+                        .max(gutter_width + scroll_width),
+                ),
+            };
+            let available_space = size(
+                width,
+                AvailableSpace::Definite(block.height() as f32 * line_height),
+            );
+            render_block(block, available_space, block_id, editor, cx);
+            block_id += 1;
+        }
+
         (
             scroll_width.max(fixed_block_max_width - gutter_width),
             blocks,
+            resized_blocks,
         )
     }
 
@@ -2715,10 +4095,30 @@ impl EditorElement {
         cx.on_mouse_event({
             let position_map = layout.position_map.clone();
             let editor = self.editor.clone();
+            let breakpoint_lane = layout.breakpoint_lane;
             let stacking_order = cx.stacking_order().clone();
             let interactive_bounds = interactive_bounds.clone();
 
             move |event: &MouseDownEvent, phase, cx| {
+                // Clicking outside of the editor ends autoscrolling, and does what it would
+                // otherwise have done.
+                if phase == DispatchPhase::Capture
+                    && editor.read(cx).middle_click_autoscroll.is_some()
+                    && !interactive_bounds.visibly_contains(&event.position, cx)
+                {
+                    editor.update(cx, |editor, cx| middle_click::stop_autoscroll(editor, cx));
+                    return;
+                }
+
+                // Clicking within the editor ends autoscrolling, without doing anything else.
+                if phase == DispatchPhase::Bubble
+                    && editor.read(cx).middle_click_autoscroll.is_some()
+                {
+                    editor.update(cx, |editor, cx| middle_click::stop_autoscroll(editor, cx));
+                    cx.stop_propagation();
+                    return;
+                }
+
                 if phase == DispatchPhase::Bubble
                     && interactive_bounds.visibly_contains(&event.position, cx)
                 {
@@ -2730,6 +4130,7 @@ impl EditorElement {
                                 &position_map,
                                 text_bounds,
                                 gutter_bounds,
+                                breakpoint_lane,
                                 &stacking_order,
                                 cx,
                             );
@@ -2737,6 +4138,9 @@ impl EditorElement {
                         MouseButton::Right => editor.update(cx, |editor, cx| {
                             Self::mouse_right_down(editor, event, &position_map, text_bounds, cx);
                         }),
+                        MouseButton::Middle => editor.update(cx, |editor, cx| {
+                            Self::mouse_middle_down(editor, event, &position_map, text_bounds, cx);
+                        }),
                         _ => {}
                     };
                 }
@@ -2775,6 +4179,8 @@ impl EditorElement {
 
                 if phase == DispatchPhase::Bubble {
                     editor.update(cx, |editor, cx| {
+                        middle_click::update_autoscroll(editor, event.position);
+
                         if event.pressed_button == Some(MouseButton::Left) {
                             Self::mouse_dragged(
                                 editor,
@@ -2805,49 +4211,66 @@ impl EditorElement {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Default)]
 pub(crate) struct LineWithInvisibles {
     pub line: ShapedLine,
     invisibles: Vec<Invisible>,
+    /// The range of whitespace at the end of the line, if the line ends its buffer line.
+    trailing_whitespace: Option<Range<usize>>,
+    /// The column of the soft wrap padding at which to show the soft wrap indicator, if the line
+    /// continues a soft-wrapped line.
+    soft_wrap_indicator: Option<usize>,
+    /// Whether the line is shown with a stale layout of its text while it's shaped on a background
+    /// thread, in which case it's laid out again once it's shaped.
+    pub is_stale: bool,
 }
 
 impl LineWithInvisibles {
-    fn from_chunks<'a>(
+    pub(crate) fn from_chunks<'a>(
         chunks: impl Iterator<Item = HighlightedChunk<'a>>,
         text_style: &TextStyle,
         max_line_len: usize,
         max_line_count: usize,
         line_number_layouts: &[Option<ShapedLine>],
-        editor_mode: EditorMode,
-        cx: &WindowContext,
+        bracket_colors: &[BracketColor],
+        collect_invisibles: bool,
+        font_size: Pixels,
+        mut shape_line: impl FnMut(&str, &[TextRun]) -> (ShapedLine, bool),
     ) -> Vec<Self> {
         let mut layouts = Vec::with_capacity(max_line_count);
         let mut line = String::new();
         let mut invisibles = Vec::new();
         let mut styles = Vec::new();
         let mut non_whitespace_added = false;
+        // The length of the line up to the end of its last chunk of buffer text.
+        let mut buffer_text_len = 0;
         let mut row = 0;
         let mut line_exceeded_max_len = false;
-        let font_size = text_style.font_size.to_pixels(cx.rem_size());
+        let mut bracket_colors = bracket_colors.iter().peekable();
 
         for highlighted_chunk in chunks.chain([HighlightedChunk {
             chunk: "\n",
             style: None,
             is_tab: false,
+            is_suggestion: false,
         }]) {
             for (ix, mut line_chunk) in highlighted_chunk.chunk.split('\n').enumerate() {
                 if ix > 0 {
-                    let shaped_line = cx
-                        .text_system()
-                        .shape_line(line.clone().into(), font_size, &styles)
-                        .unwrap();
+                    let (shaped_line, is_stale) = shape_line(&line, &styles);
+                    let trailing_whitespace = collect_invisibles
+                        .then(|| line[..buffer_text_len].trim_end().len()..buffer_text_len)
+                        .filter(|range| !range.is_empty());
                     layouts.push(Self {
                         line: shaped_line,
                         invisibles: invisibles.drain(..).collect(),
+                        trailing_whitespace,
+                        soft_wrap_indicator: None,
+                        is_stale,
                     });
 
                     line.clear();
                     styles.clear();
+                    buffer_text_len = 0;
                     row += 1;
                     line_exceeded_max_len = false;
                     non_whitespace_added = false;
@@ -2857,11 +4280,18 @@ impl LineWithInvisibles {
                 }
 
                 if !line_chunk.is_empty() && !line_exceeded_max_len {
-                    let text_style = if let Some(style) = highlighted_chunk.style {
+                    let mut text_style = if let Some(style) = highlighted_chunk.style {
                         Cow::Owned(text_style.clone().highlight(style))
                     } else {
                         Cow::Borrowed(text_style)
                     };
+                    if highlighted_chunk.is_suggestion {
+                        text_style =
+                            Cow::Owned(text_style.into_owned().highlight(HighlightStyle {
+                                fade_out: Some(SUGGESTION_FADE),
+                                ..Default::default()
+                            }));
+                    }
 
                     if line.len() + line_chunk.len() > max_line_len {
                         let mut chunk_len = max_line_len - line.len();
@@ -2872,16 +4302,48 @@ impl LineWithInvisibles {
                         line_exceeded_max_len = true;
                     }
 
-                    styles.push(TextRun {
+                    let run = TextRun {
                         len: line_chunk.len(),
                         font: text_style.font(),
                         color: text_style.color,
                         background_color: text_style.background_color,
                         underline: text_style.underline,
                         strikethrough: text_style.strikethrough,
-                    });
+                    };
+                    // Split the run around any brackets in this chunk, to give them their color.
+                    let chunk_end = line.len() + line_chunk.len();
+                    let mut run_start = line.len();
+                    while let Some(bracket) = bracket_colors.next_if(|bracket| {
+                        (bracket.row as usize, bracket.columns.start) < (row, chunk_end)
+                    }) {
+                        if bracket.row as usize != row
+                            || bracket.columns.start < run_start
+                            || bracket.columns.end > chunk_end
+                        {
+                            continue;
+                        }
+                        if bracket.columns.start > run_start {
+                            styles.push(TextRun {
+                                len: bracket.columns.start - run_start,
+                                ..run.clone()
+                            });
+                        }
+                        styles.push(TextRun {
+                            len: bracket.columns.len(),
+                            color: bracket.color,
+                            ..run.clone()
+                        });
+                        run_start = bracket.columns.end;
+                    }
+                    if run_start < chunk_end {
+                        styles.push(TextRun {
+                            len: chunk_end - run_start,
+                            ..run
+                        });
+                    }
 
-                    if editor_mode == EditorMode::Full {
+                    // Suggested text is shown as it will be inserted, without invisibles.
+                    if collect_invisibles && !highlighted_chunk.is_suggestion {
                         // Line wrap pads its contents with fake whitespaces,
                         // avoid printing them
                         let inside_wrapped_string = line_number_layouts
@@ -2897,22 +4359,29 @@ impl LineWithInvisibles {
                         } else {
                             invisibles.extend(
                                 line_chunk
-                                    .chars()
-                                    .enumerate()
+                                    .char_indices()
                                     .filter(|(_, line_char)| {
                                         let is_whitespace = line_char.is_whitespace();
                                         non_whitespace_added |= !is_whitespace;
                                         is_whitespace
                                             && (non_whitespace_added || !inside_wrapped_string)
                                     })
-                                    .map(|(whitespace_index, _)| Invisible::Whitespace {
-                                        line_offset: line.len() + whitespace_index,
+                                    .map(|(whitespace_index, line_char)| {
+                                        let line_offset = line.len() + whitespace_index;
+                                        if is_nbsp(line_char) {
+                                            Invisible::Nbsp { line_offset }
+                                        } else {
+                                            Invisible::Whitespace { line_offset }
+                                        }
                                     }),
                             )
                         }
                     }
 
                     line.push_str(line_chunk);
+                    if !highlighted_chunk.is_suggestion {
+                        buffer_text_len = line.len();
+                    }
                 }
             }
         }
@@ -2926,12 +4395,38 @@ impl LineWithInvisibles {
         row: u32,
         content_origin: gpui::Point<Pixels>,
         whitespace_setting: ShowWhitespaceSetting,
+        trailing_whitespace_setting: TrailingWhitespaceHighlight,
         selection_ranges: &[Range<DisplayPoint>],
+        quads: &mut MergedQuads,
         cx: &mut ElementContext,
     ) {
         let line_height = layout.position_map.line_height;
         let line_y = line_height * row as f32 - layout.position_map.scroll_position.y;
 
+        if let Some(trailing_whitespace) = &self.trailing_whitespace {
+            let highlight = match trailing_whitespace_setting {
+                TrailingWhitespaceHighlight::None => false,
+                TrailingWhitespaceHighlight::InactiveLines => {
+                    !layout.active_rows.contains_key(&row)
+                }
+                TrailingWhitespaceHighlight::All => true,
+            };
+            if highlight {
+                let x_range = self
+                    .line
+                    .x_range_for_index_range(trailing_whitespace.clone());
+                let (start_x, end_x) = (x_range.start, x_range.end);
+                quads.fill(
+                    Bounds {
+                        origin: content_origin
+                            + gpui::point(start_x - layout.position_map.scroll_position.x, line_y),
+                        size: size(end_x - start_x, line_height),
+                    },
+                    cx.theme().status().error_background,
+                );
+            }
+        }
+
         self.line
             .paint(
                 content_origin + gpui::point(-layout.position_map.scroll_position.x, line_y),
@@ -2940,6 +4435,15 @@ impl LineWithInvisibles {
             )
             .log_err();
 
+        if let Some(column) = self.soft_wrap_indicator {
+            let indicator = &layout.soft_wrap_indicator;
+            let x = self.line.x_for_index(column)
+                + (layout.position_map.em_width - indicator.width).max(Pixels::ZERO) / 2.0;
+            let origin =
+                content_origin + gpui::point(x - layout.position_map.scroll_position.x, line_y);
+            indicator.paint(origin, line_height, cx).log_err();
+        }
+
         self.draw_invisibles(
             &selection_ranges,
             layout,
@@ -2963,16 +4467,11 @@ impl LineWithInvisibles {
         whitespace_setting: ShowWhitespaceSetting,
         cx: &mut ElementContext,
     ) {
-        let allowed_invisibles_regions = match whitespace_setting {
-            ShowWhitespaceSetting::None => return,
-            ShowWhitespaceSetting::Selection => Some(selection_ranges),
-            ShowWhitespaceSetting::All => None,
-        };
-
-        for invisible in &self.invisibles {
+        for invisible in self.invisibles_to_paint(selection_ranges, row, whitespace_setting) {
             let (&token_offset, invisible_symbol) = match invisible {
                 Invisible::Tab { line_start_offset } => (line_start_offset, &layout.tab_invisible),
                 Invisible::Whitespace { line_offset } => (line_offset, &layout.space_invisible),
+                Invisible::Nbsp { line_offset } => (line_offset, &layout.nbsp_invisible),
             };
 
             let x_offset = self.line.x_for_index(token_offset);
@@ -2983,25 +4482,114 @@ impl LineWithInvisibles {
                     x_offset + invisible_offset - layout.position_map.scroll_position.x,
                     line_y,
                 );
+            invisible_symbol.paint(origin, line_height, cx).log_err();
+        }
+    }
+
+    /// Returns the invisibles shown with the given whitespace setting, given the selections in
+    /// the line's display row.
+    fn invisibles_to_paint<'a>(
+        &'a self,
+        selection_ranges: &'a [Range<DisplayPoint>],
+        row: u32,
+        whitespace_setting: ShowWhitespaceSetting,
+    ) -> impl Iterator<Item = &'a Invisible> + 'a {
+        let allowed_invisibles_regions = match whitespace_setting {
+            ShowWhitespaceSetting::None => Some(&[][..]),
+            ShowWhitespaceSetting::Selection => Some(selection_ranges),
+            ShowWhitespaceSetting::All | ShowWhitespaceSetting::Boundary => None,
+        };
+        let boundary_columns = (whitespace_setting == ShowWhitespaceSetting::Boundary).then(|| {
+            let text = &self.line.text;
+            let leading_end = text.len() - text.trim_start().len();
+            let trailing_start = text.trim_end().len();
+            (leading_end, trailing_start)
+        });
+
+        self.invisibles.iter().filter(move |invisible| {
+            let token_offset = match invisible {
+                Invisible::Tab { line_start_offset } => *line_start_offset,
+                Invisible::Whitespace { line_offset } | Invisible::Nbsp { line_offset } => {
+                    *line_offset
+                }
+            };
 
-            if let Some(allowed_regions) = allowed_invisibles_regions {
+            // Non-breaking spaces are always drawn, since they are easily mistaken for spaces.
+            if matches!(invisible, Invisible::Nbsp { .. }) {
+                return true;
+            }
+            if let Some((leading_end, trailing_start)) = boundary_columns {
+                if token_offset >= leading_end && token_offset < trailing_start {
+                    return false;
+                }
+            }
+            allowed_invisibles_regions.map_or(true, |allowed_regions| {
                 let invisible_point = DisplayPoint::new(row, token_offset as u32);
-                if !allowed_regions
+                allowed_regions
                     .iter()
                     .any(|region| region.start <= invisible_point && invisible_point < region.end)
-                {
-                    continue;
-                }
-            }
-            invisible_symbol.paint(origin, line_height, cx).log_err();
+            })
+        })
+    }
+}
+
+/// The visible display rows of an excerpt in a multibuffer, including its header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ExcerptBand {
+    rows: Range<u32>,
+    id: ExcerptId,
+    /// Whether the band starts with the excerpt's header, rather than at the top of the editor.
+    starts_excerpt: bool,
+}
+
+/// Splits the given display rows into the excerpts they belong to.
+fn layout_excerpt_bands(rows: Range<u32>, snapshot: &EditorSnapshot) -> Vec<ExcerptBand> {
+    let first_point = DisplayPoint::new(rows.start, 0).to_point(&snapshot.display_snapshot);
+    let mut bands = vec![ExcerptBand {
+        rows: rows.clone(),
+        id: snapshot
+            .buffer_snapshot
+            .anchor_after(first_point)
+            .excerpt_id,
+        starts_excerpt: false,
+    }];
+    for (row, block) in snapshot.blocks_in_range(rows.clone()) {
+        let TransformBlock::ExcerptHeader { id, .. } = block else {
+            continue;
+        };
+        let last_band = bands.last_mut().unwrap();
+        if row == last_band.rows.start {
+            *last_band = ExcerptBand {
+                rows: row..rows.end,
+                id: *id,
+                starts_excerpt: true,
+            };
+        } else {
+            last_band.rows.end = row;
+            bands.push(ExcerptBand {
+                rows: row..rows.end,
+                id: *id,
+                starts_excerpt: true,
+            });
         }
     }
+    bands
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Invisible {
     Tab { line_start_offset: usize },
     Whitespace { line_offset: usize },
+    Nbsp { line_offset: usize },
+}
+
+/// The width of the part of a diff hunk's bar that's painted inside the gutter, at its left edge.
+fn diff_hunk_bar_width(line_height: Pixels) -> Pixels {
+    line_height * 0.275
+}
+
+fn is_nbsp(c: char) -> bool {
+    matches!(c, '\u{a0}' | '\u{202f}')
 }
 
 impl Element for EditorElement {
@@ -3077,8 +4665,11 @@ impl Element for EditorElement {
                 }),
                 |cx| {
                     let mut layout = self.compute_layout(bounds, cx);
+                    if layout.blocks_resized {
+                        layout = self.compute_layout(bounds, cx);
+                    }
                     let gutter_bounds = Bounds {
-                        origin: bounds.origin,
+                        origin: bounds.origin + point(layout.centering_padding, Pixels::ZERO),
                         size: layout.gutter_size,
                     };
                     let text_bounds = Bounds {
@@ -3098,7 +4689,10 @@ impl Element for EditorElement {
                                 ElementInputHandler::new(bounds, self.editor.clone()),
                             );
 
-                            self.paint_background(gutter_bounds, text_bounds, &layout, cx);
+                            if layout.centering_padding > Pixels::ZERO {
+                                cx.paint_quad(fill(bounds, self.style.background));
+                            }
+                            self.paint_background(gutter_bounds, text_bounds, &mut layout, cx);
                             if layout.gutter_size.width > Pixels::ZERO {
                                 self.paint_gutter(gutter_bounds, &mut layout, cx);
                             }
@@ -3121,11 +4715,28 @@ impl Element for EditorElement {
                                 })
                             }
 
+                            if !layout.blame_annotations.is_empty() {
+                                cx.with_z_index(1, |cx| {
+                                    self.paint_blame_annotations(text_bounds, &mut layout, cx);
+                                });
+                            }
+
+                            if let Some(mut sticky_header) = layout.sticky_header.take() {
+                                cx.with_z_index(1, |cx| {
+                                    let available_space = size(
+                                        AvailableSpace::Definite(text_bounds.size.width),
+                                        AvailableSpace::Definite(layout.position_map.line_height),
+                                    );
+                                    sticky_header.draw(text_bounds.origin, available_space, cx);
+                                });
+                            }
+
                             cx.with_z_index(1, |cx| {
                                 self.paint_overlays(text_bounds, &mut layout, cx);
                             });
 
                             cx.with_z_index(2, |cx| self.paint_scrollbar(bounds, &mut layout, cx));
+                            cx.with_z_index(3, |cx| self.paint_autoscroll_anchor(cx));
                         });
                     })
                 },
@@ -3153,29 +4764,64 @@ pub struct LayoutState {
     gutter_size: Size<Pixels>,
     gutter_padding: Pixels,
     gutter_margin: Pixels,
+    /// The width of the gutter lane where breakpoints are shown, or zero when they aren't.
+    breakpoint_lane: Pixels,
+    /// The width left empty on each side of the gutter and text in a centered layout.
+    centering_padding: Pixels,
     text_size: gpui::Size<Pixels>,
     mode: EditorMode,
-    wrap_guides: SmallVec<[(Pixels, bool); 2]>,
+    wrap_guides: SmallVec<[WrapGuideLayout; 2]>,
+    indent_guides: Vec<IndentGuideLayout>,
+    bracket_pair_underlines: Vec<(Range<DisplayPoint>, Hsla)>,
+    diagnostics: Vec<DiagnosticLayout>,
+    /// The messages to show after the ends of display rows, sorted by row.
+    inline_diagnostics: Vec<(u32, ShapedLine)>,
     visible_anchor_range: Range<Anchor>,
     visible_display_row_range: Range<u32>,
     active_rows: BTreeMap<u32, bool>,
     highlighted_rows: Option<Range<u32>>,
+    excerpt_bands: Vec<ExcerptBand>,
     line_numbers: Vec<Option<ShapedLine>>,
+    /// Whether each line number is aligned to the left of the line number column, rather than
+    /// the right.
+    left_aligned_line_numbers: Vec<bool>,
+    /// The display rows showing lines with breakpoints.
+    breakpoint_rows: Vec<u32>,
+    /// The display rows showing bookmarked lines.
+    bookmark_rows: Vec<u32>,
+    /// The marks from the gutter data providers, or placeholders for the ones still loading.
+    gutter_marks: Vec<GutterMarkLayout>,
+    /// The buttons that run the tests or code cells starting on the given display rows.
+    runnable_indicators: Vec<(u32, IconButton)>,
+    /// The display rows showing the markers of code cells, which are separated by a line.
+    code_cell_rows: Vec<u32>,
     display_hunks: Vec<DisplayDiffHunk>,
     blocks: Vec<BlockLayout>,
+    /// Whether blocks that fit their content were resized to the rows they need, so that the rows
+    /// after them are laid out again before being painted.
+    blocks_resized: bool,
     highlighted_ranges: Vec<(Range<DisplayPoint>, Hsla)>,
     redacted_ranges: Vec<Range<DisplayPoint>>,
     selections: Vec<(PlayerColor, Vec<SelectionLayout>)>,
     scrollbar_row_range: Range<f32>,
     show_scrollbars: bool,
+    scrollbar_markers: Rc<[ScrollbarMarkerRows]>,
+    /// The quads painted at the same z-index, which are merged where they continue each other.
+    quads: MergedQuads,
     is_singleton: bool,
     max_row: u32,
     context_menu: Option<(DisplayPoint, AnyElement)>,
+    cursor_position_overlay: Option<(DisplayPoint, AnyElement)>,
+    sticky_header: Option<AnyElement>,
+    blame_annotations: Vec<(u32, AnyElement)>,
     code_actions_indicator: Option<CodeActionsIndicator>,
-    hover_popovers: Option<(DisplayPoint, Vec<AnyElement>)>,
+    read_only_lock: Option<IconButton>,
+    hover_popover: Option<(DisplayPoint, AnyElement)>,
     fold_indicators: Vec<Option<IconButton>>,
     tab_invisible: ShapedLine,
     space_invisible: ShapedLine,
+    nbsp_invisible: ShapedLine,
+    soft_wrap_indicator: ShapedLine,
 }
 
 impl LayoutState {
@@ -3189,6 +4835,55 @@ struct CodeActionsIndicator {
     button: IconButton,
 }
 
+/// A marker painted in the scrollbar, which can be clicked to jump to the row it marks.
+struct ScrollbarMarker {
+    bounds: Bounds<Pixels>,
+    row: u32,
+    /// The index of the marker in the layout's scrollbar markers, whose tooltip is only built
+    /// once it's hovered.
+    marker_ix: usize,
+}
+
+/// Returns the index of the marker under the given position, favoring the markers painted last.
+/// Markers are hit-tested as if they were at least a few pixels tall, to make them clickable,
+/// but not under the thumb so that it can still be dragged.
+fn scrollbar_marker_at(
+    markers: &[ScrollbarMarker],
+    thumb_bounds: Bounds<Pixels>,
+    position: gpui::Point<Pixels>,
+) -> Option<usize> {
+    if thumb_bounds.contains(&position) {
+        return None;
+    }
+    markers.iter().rposition(|marker| {
+        let padding =
+            ((SCROLLBAR_MARKER_MIN_HIT_HEIGHT - marker.bounds.size.height) / 2.).max(Pixels::ZERO);
+        (marker.bounds.left()..marker.bounds.right()).contains(&position.x)
+            && (marker.bounds.top() - padding..marker.bounds.bottom() + padding)
+                .contains(&position.y)
+    })
+}
+
+struct DiagnosticLayout {
+    range: Range<DisplayPoint>,
+    color: Hsla,
+}
+
+struct WrapGuideLayout {
+    /// The horizontal offset of the guide from the start of the text.
+    offset: Pixels,
+    color: Hsla,
+    style: WrapGuideStyle,
+}
+
+struct IndentGuideLayout {
+    /// The horizontal offset of the guide from the start of the text.
+    offset: Pixels,
+    rows: Range<u32>,
+    /// Whether this is the innermost guide containing the newest cursor.
+    active: bool,
+}
+
 struct PositionMap {
     size: Size<Pixels>,
     line_height: Pixels,
@@ -3236,7 +4931,7 @@ impl PositionMap {
             .map(|&LineWithInvisibles { ref line, .. }| line)
         {
             if let Some(ix) = line.index_for_x(x) {
-                (ix as u32, px(0.))
+                (grapheme_start(&line.text, ix) as u32, px(0.))
             } else {
                 (line.len as u32, px(0.).max(x - line.width))
             }
@@ -3266,6 +4961,153 @@ struct BlockLayout {
     style: BlockStyle,
 }
 
+/// Renders the number of primary errors and warnings in the given buffer.
+fn render_diagnostic_badges(buffer: &language::BufferSnapshot) -> impl IntoElement {
+    let (error_count, warning_count) = buffer.diagnostic_severity_counts();
+    let badge = |icon: IconName, color: Color, count: usize| {
+        h_flex()
+            .gap_1()
+            .child(Icon::new(icon).size(IconSize::Small).color(color))
+            .child(Label::new(count.to_string()).size(LabelSize::Small))
+    };
+    h_flex()
+        .gap_2()
+        .when(error_count > 0, |this| {
+            this.child(badge(IconName::XCircle, Color::Error, error_count))
+        })
+        .when(warning_count > 0, |this| {
+            this.child(badge(
+                IconName::ExclamationTriangle,
+                Color::Warning,
+                warning_count,
+            ))
+        })
+}
+
+/// The payload of an excerpt header being dragged to reorder the excerpts of a multibuffer.
+#[derive(Clone)]
+pub struct DraggedExcerpt {
+    editor_id: EntityId,
+    excerpt_id: ExcerptId,
+    ix: usize,
+    title: SharedString,
+}
+
+impl Render for DraggedExcerpt {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        h_flex()
+            .px_2()
+            .py_1()
+            .rounded_md()
+            .border_1()
+            .border_color(cx.theme().colors().border)
+            .bg(cx.theme().colors().editor_subheader_background)
+            .child(Label::new(self.title.clone()))
+    }
+}
+
+fn render_excerpt_header_buttons(
+    editor: &View<Editor>,
+    excerpt_id: ExcerptId,
+    block_id: usize,
+    cx: &mut ElementContext,
+) -> impl IntoElement {
+    let settings = &EditorSettings::get_global(cx).excerpt_header;
+    let lines = settings.expand_lines;
+    let buttons = settings.buttons.clone();
+    h_flex().gap_1().children(buttons.into_iter().map(|button| {
+        match button {
+            ExcerptHeaderButton::ExpandUp => {
+                IconButton::new(("expand excerpt up", block_id), IconName::ArrowUp)
+                    .icon_size(IconSize::Small)
+                    .tooltip(move |cx| {
+                        Tooltip::for_action(
+                            format!("Show {lines} More Lines Above"),
+                            &ExpandExcerptsUp::default(),
+                            cx,
+                        )
+                    })
+                    .on_click(cx.listener_for(editor, move |editor, _, cx| {
+                        editor.expand_excerpts([excerpt_id], lines, ExpandExcerptDirection::Up, cx);
+                    }))
+            }
+            ExcerptHeaderButton::ExpandDown => {
+                IconButton::new(("expand excerpt down", block_id), IconName::ArrowDown)
+                    .icon_size(IconSize::Small)
+                    .tooltip(move |cx| {
+                        Tooltip::for_action(
+                            format!("Show {lines} More Lines Below"),
+                            &ExpandExcerptsDown::default(),
+                            cx,
+                        )
+                    })
+                    .on_click(cx.listener_for(editor, move |editor, _, cx| {
+                        editor.expand_excerpts(
+                            [excerpt_id],
+                            lines,
+                            ExpandExcerptDirection::Down,
+                            cx,
+                        );
+                    }))
+            }
+            ExcerptHeaderButton::OpenInSplit => {
+                IconButton::new(("open excerpt in split", block_id), IconName::Split)
+                    .icon_size(IconSize::Small)
+                    .tooltip(|cx| Tooltip::text("Open in Split", cx))
+                    .on_click(cx.listener_for(editor, move |editor, _, cx| {
+                        editor.open_excerpt_in_split(excerpt_id, cx);
+                    }))
+            }
+            ExcerptHeaderButton::CopyPath => {
+                IconButton::new(("copy excerpt path", block_id), IconName::Copy)
+                    .icon_size(IconSize::Small)
+                    .tooltip(|cx| Tooltip::text("Copy Path", cx))
+                    .on_click(cx.listener_for(editor, move |editor, _, cx| {
+                        editor.copy_excerpt_path(excerpt_id, cx);
+                    }))
+            }
+            ExcerptHeaderButton::Dismiss => {
+                IconButton::new(("dismiss excerpt", block_id), IconName::Close)
+                    .icon_size(IconSize::Small)
+                    .tooltip(|cx| Tooltip::for_action("Dismiss Excerpt", &DismissExcerpt, cx))
+                    .on_click(cx.listener_for(editor, move |editor, _, cx| {
+                        editor.remove_excerpts([excerpt_id], cx);
+                    }))
+            }
+        }
+    }))
+}
+
+/// Returns the start of the grapheme cluster containing the given index of a line, so that
+/// clicking on a combining mark or inside an emoji sequence doesn't split it.
+fn grapheme_start(line: &str, index: usize) -> usize {
+    line.grapheme_indices(true)
+        .map(|(start, _)| start)
+        .take_while(|start| *start <= index)
+        .last()
+        .unwrap_or(0)
+}
+
+/// Returns the indentation level and the range of rows covered by each indent guide, given the
+/// indentation depth of consecutive rows.
+fn indent_guide_ranges(depths: &[u32]) -> Vec<(u32, Range<usize>)> {
+    let mut ranges = Vec::new();
+    let mut open_guide_starts = Vec::new();
+    for (ix, depth) in depths.iter().copied().enumerate() {
+        while open_guide_starts.len() as u32 > depth {
+            let start = open_guide_starts.pop().unwrap();
+            ranges.push((open_guide_starts.len() as u32, start..ix));
+        }
+        while (open_guide_starts.len() as u32) < depth {
+            open_guide_starts.push(ix);
+        }
+    }
+    while let Some(start) = open_guide_starts.pop() {
+        ranges.push((open_guide_starts.len() as u32, start..depths.len()));
+    }
+    ranges
+}
+
 fn layout_line(
     row: u32,
     snapshot: &EditorSnapshot,
@@ -3312,10 +5154,20 @@ pub struct Cursor {
 pub struct CursorName {
     string: SharedString,
     color: Hsla,
+    opacity: f32,
+    /// How many name tags this one is moved away from the cursor by, so that it doesn't cover
+    /// the tags of other cursors on the same row.
+    stack_level: usize,
     is_top_row: bool,
     z_index: u16,
 }
 
+impl CursorName {
+    fn text_size(line_height: Pixels) -> Pixels {
+        line_height / 1.5
+    }
+}
+
 impl Cursor {
     pub fn new(
         origin: gpui::Point<Pixels>,
@@ -3345,6 +5197,19 @@ impl Cursor {
     }
 
     pub fn paint(&self, origin: gpui::Point<Pixels>, cx: &mut ElementContext) {
+        let mut quads = MergedQuads::default();
+        self.paint_merged(origin, &mut quads, cx);
+        quads.paint(cx);
+    }
+
+    /// Paints the cursor, adding its quad to the given quads to be merged rather than painting it
+    /// right away.
+    pub(crate) fn paint_merged(
+        &self,
+        origin: gpui::Point<Pixels>,
+        quads: &mut MergedQuads,
+        cx: &mut ElementContext,
+    ) {
         let bounds = match self.shape {
             CursorShape::Bar => Bounds {
                 origin: self.origin + origin,
@@ -3362,28 +5227,29 @@ impl Cursor {
             },
         };
 
-        //Draw background or border quad
-        let cursor = if matches!(self.shape, CursorShape::Hollow) {
-            outline(bounds, self.color)
-        } else {
-            fill(bounds, self.color)
-        };
-
         if let Some(name) = &self.cursor_name {
-            let text_size = self.line_height / 1.5;
+            let text_size = CursorName::text_size(self.line_height);
+            let stack_offset = (text_size + px(2.)) * name.stack_level as f32;
 
             let name_origin = if name.is_top_row {
-                point(bounds.right() - px(1.), bounds.top())
+                point(bounds.right() - px(1.), bounds.top() + stack_offset)
             } else {
-                point(bounds.left(), bounds.top() - text_size / 2. - px(1.))
+                point(
+                    bounds.left(),
+                    bounds.top() - text_size / 2. - px(1.) - stack_offset,
+                )
             };
+            let mut background = self.color;
+            background.fade_out(1. - name.opacity);
+            let mut text_color = name.color;
+            text_color.fade_out(1. - name.opacity);
             cx.with_z_index(name.z_index, |cx| {
                 div()
-                    .bg(self.color)
+                    .bg(background)
                     .text_size(text_size)
                     .px_0p5()
                     .line_height(text_size + px(2.))
-                    .text_color(name.color)
+                    .text_color(text_color)
                     .child(name.string.clone())
                     .into_any_element()
                     .draw(
@@ -3394,7 +5260,12 @@ impl Cursor {
             })
         }
 
-        cx.paint_quad(cursor);
+        //Draw background or border quad
+        if matches!(self.shape, CursorShape::Hollow) {
+            quads.quad(outline(bounds, self.color));
+        } else {
+            quads.fill(bounds, self.color);
+        }
 
         if let Some(block_text) = &self.block_text {
             block_text
@@ -3556,13 +5427,16 @@ fn scale_horizontal_mouse_autoscroll_delta(delta: Pixels) -> f32 {
 mod tests {
     use super::*;
     use crate::{
-        display_map::{BlockDisposition, BlockProperties},
+        display_map::{BlockDisposition, BlockProperties, Inlay},
         editor_tests::{init_test, update_test_language_settings},
-        Editor, MultiBuffer,
+        test::editor_test_context::EditorTestContext,
+        BlockDecoration, Editor, MultiBuffer, ToggleCenteredLayout, ToggleInlineDiagnostics,
     };
-    use gpui::TestAppContext;
-    use language::language_settings;
+    use gpui::{Context as _, TestAppContext};
+    use language::{language_settings, Capability, Diagnostic, DiagnosticEntry, DiagnosticSet};
     use log::info;
+    use lsp::LanguageServerId;
+    use multi_buffer::ExcerptRange;
     use std::{num::NonZeroU32, sync::Arc};
     use util::test::sample_text;
 
@@ -3587,7 +5461,9 @@ mod tests {
                         &Default::default(),
                         DisplayPoint::new(0, 0),
                         false,
+                        false,
                         &snapshot,
+                        &mut editor.row_layout_cache,
                         cx,
                     )
                     .0
@@ -3598,7 +5474,7 @@ mod tests {
         let relative_rows = window
             .update(cx, |editor, cx| {
                 let snapshot = editor.snapshot(cx);
-                element.calculate_relative_line_numbers(&snapshot, &(0..6), Some(3))
+                element.calculate_relative_line_numbers(&snapshot, &(0..6), Some(3), true)
             })
             .unwrap();
         assert_eq!(relative_rows[&0], 3);
@@ -3613,7 +5489,7 @@ mod tests {
             .update(cx, |editor, cx| {
                 let snapshot = editor.snapshot(cx);
 
-                element.calculate_relative_line_numbers(&snapshot, &(3..6), Some(1))
+                element.calculate_relative_line_numbers(&snapshot, &(3..6), Some(1), true)
             })
             .unwrap();
         assert_eq!(relative_rows.len(), 3);
@@ -3626,52 +5502,228 @@ mod tests {
             .update(cx, |editor, cx| {
                 let snapshot = editor.snapshot(cx);
 
-                element.calculate_relative_line_numbers(&snapshot, &(0..3), Some(6))
+                element.calculate_relative_line_numbers(&snapshot, &(0..3), Some(6), true)
             })
             .unwrap();
         assert_eq!(relative_rows.len(), 3);
         assert_eq!(relative_rows[&0], 5);
         assert_eq!(relative_rows[&1], 4);
         assert_eq!(relative_rows[&2], 3);
+
+        // outside of hybrid mode, the current line is numbered 0
+        let relative_rows = window
+            .update(cx, |editor, cx| {
+                let snapshot = editor.snapshot(cx);
+
+                element.calculate_relative_line_numbers(&snapshot, &(0..6), Some(3), false)
+            })
+            .unwrap();
+        assert_eq!(relative_rows.len(), 6);
+        assert_eq!(relative_rows[&2], 1);
+        assert_eq!(relative_rows[&3], 0);
+        assert_eq!(relative_rows[&4], 1);
     }
 
     #[gpui::test]
-    async fn test_vim_visual_selections(cx: &mut TestAppContext) {
+    fn test_layout_diagnostics(cx: &mut TestAppContext) {
         init_test(cx, |_| {});
-
         let window = cx.add_window(|cx| {
-            let buffer = MultiBuffer::build_simple(&(sample_text(6, 6, 'a') + "\n"), cx);
+            let buffer = MultiBuffer::build_simple("let a = b;\nlet c = d;\n", cx);
             Editor::new(EditorMode::Full, buffer, None, cx)
         });
+
         let editor = window.root(cx).unwrap();
         let style = cx.update(|cx| editor.read(cx).style().unwrap().clone());
-        let mut element = EditorElement::new(&editor, style);
+        let element = EditorElement::new(&editor, style.clone());
 
-        window
+        let diagnostics = window
             .update(cx, |editor, cx| {
-                editor.cursor_shape = CursorShape::Block;
-                editor.change_selections(None, cx, |s| {
-                    s.select_ranges([
-                        Point::new(0, 0)..Point::new(1, 0),
-                        Point::new(3, 2)..Point::new(3, 3),
-                        Point::new(5, 6)..Point::new(6, 0),
-                    ]);
-                });
-            })
-            .unwrap();
-        let state = cx
-            .update_window(window.into(), |view, cx| {
-                cx.with_element_context(|cx| {
-                    cx.with_view_id(view.entity_id(), |cx| {
-                        element.compute_layout(
-                            Bounds {
-                                origin: point(px(500.), px(500.)),
-                                size: size(px(500.), px(500.)),
-                            },
-                            cx,
-                        )
-                    })
-                })
+                set_diagnostics(
+                    editor,
+                    &[
+                        (4..9, DiagnosticSeverity::WARNING, "", true),
+                        (8..9, DiagnosticSeverity::ERROR, "", true),
+                        (15..16, DiagnosticSeverity::HINT, "", true),
+                    ],
+                    cx,
+                );
+
+                let snapshot = editor.snapshot(cx);
+                element.layout_diagnostics(Anchor::min()..Anchor::max(), &snapshot)
+            })
+            .unwrap();
+
+        // Errors are painted over warnings, and hints on unnecessary code aren't underlined.
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diagnostic| (diagnostic.range.clone(), diagnostic.color))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    DisplayPoint::new(0, 4)..DisplayPoint::new(0, 9),
+                    style.status.warning
+                ),
+                (
+                    DisplayPoint::new(0, 8)..DisplayPoint::new(0, 9),
+                    style.status.error
+                ),
+            ]
+        );
+    }
+
+    #[gpui::test]
+    fn test_layout_inline_diagnostics(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let window = cx.add_window(|cx| {
+            let buffer = MultiBuffer::build_simple("let a = b;\nlet c = d;\nlet e = f;\n", cx);
+            Editor::new(EditorMode::Full, buffer, None, cx)
+        });
+
+        let editor = window.root(cx).unwrap();
+        let style = cx.update(|cx| editor.read(cx).style().unwrap().clone());
+        let mut element = EditorElement::new(&editor, style);
+
+        let diagnostics = window
+            .update(cx, |editor, cx| {
+                set_diagnostics(
+                    editor,
+                    &[
+                        (4..5, DiagnosticSeverity::WARNING, "unused", true),
+                        (8..9, DiagnosticSeverity::ERROR, "mismatched\ntypes", true),
+                        (8..9, DiagnosticSeverity::ERROR, "expected here", false),
+                        (15..16, DiagnosticSeverity::HINT, "unnecessary", true),
+                        (26..27, DiagnosticSeverity::ERROR, "not found", true),
+                    ],
+                    cx,
+                );
+
+                let snapshot = editor.snapshot(cx);
+                element.layout_inline_diagnostics(Anchor::min()..Anchor::max(), 0..2, &snapshot, cx)
+            })
+            .unwrap();
+
+        // Only the first line of the most severe primary diagnostic on each row is shown, except
+        // for hints on unnecessary code.
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|(row, line)| (*row, line.text.to_string()))
+                .collect::<Vec<_>>(),
+            vec![(0, "mismatched".to_string())]
+        );
+
+        // The messages are only laid out while inline diagnostics are enabled.
+        let mut layout_rows = |cx: &mut TestAppContext| {
+            cx.update_window(window.into(), |view, cx| {
+                cx.with_element_context(|cx| {
+                    cx.with_view_id(view.entity_id(), |cx| {
+                        element
+                            .compute_layout(
+                                Bounds {
+                                    origin: point(px(0.), px(0.)),
+                                    size: size(px(500.), px(500.)),
+                                },
+                                cx,
+                            )
+                            .inline_diagnostics
+                            .iter()
+                            .map(|(row, _)| *row)
+                            .collect::<Vec<_>>()
+                    })
+                })
+            })
+            .unwrap()
+        };
+        window
+            .update(cx, |editor, cx| {
+                if !editor.inline_diagnostics_enabled(cx) {
+                    editor.toggle_inline_diagnostics(&ToggleInlineDiagnostics, cx);
+                }
+            })
+            .unwrap();
+        assert_eq!(layout_rows(cx), vec![0, 2]);
+
+        window
+            .update(cx, |editor, cx| {
+                editor.toggle_inline_diagnostics(&ToggleInlineDiagnostics, cx);
+                assert!(!editor.inline_diagnostics_enabled(cx));
+            })
+            .unwrap();
+        assert_eq!(layout_rows(cx), Vec::<u32>::new());
+    }
+
+    #[gpui::test]
+    fn test_layout_indent_guides(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let text = "fn a() {\n    if b {\n        c();\n\n    }\n\n    d();\n}";
+        let window = cx.add_window(|cx| {
+            let buffer = MultiBuffer::build_simple(text, cx);
+            Editor::new(EditorMode::Full, buffer, None, cx)
+        });
+
+        let editor = window.root(cx).unwrap();
+        let style = cx.update(|cx| editor.read(cx).style().unwrap().clone());
+        let element = EditorElement::new(&editor, style);
+
+        let guides = window
+            .update(cx, |editor, cx| {
+                let snapshot = editor.snapshot(cx);
+                element.layout_indent_guides(0..8, DisplayPoint::new(2, 0), 4, px(10.), &snapshot)
+            })
+            .unwrap();
+        assert_eq!(
+            guides
+                .iter()
+                .map(|guide| (guide.offset, guide.rows.clone(), guide.active))
+                .collect::<Vec<_>>(),
+            vec![(px(40.), 2..3, true), (px(0.), 1..7, false)]
+        );
+
+        assert!(indent_guide_ranges(&[]).is_empty());
+        assert_eq!(
+            indent_guide_ranges(&[2, 0, 1]),
+            vec![(1, 0..1), (0, 0..1), (0, 2..3)]
+        );
+    }
+
+    #[gpui::test]
+    async fn test_vim_visual_selections(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+
+        let window = cx.add_window(|cx| {
+            let buffer = MultiBuffer::build_simple(&(sample_text(6, 6, 'a') + "\n"), cx);
+            Editor::new(EditorMode::Full, buffer, None, cx)
+        });
+        let editor = window.root(cx).unwrap();
+        let style = cx.update(|cx| editor.read(cx).style().unwrap().clone());
+        let mut element = EditorElement::new(&editor, style);
+
+        window
+            .update(cx, |editor, cx| {
+                editor.cursor_shape = CursorShape::Block;
+                editor.change_selections(None, cx, |s| {
+                    s.select_ranges([
+                        Point::new(0, 0)..Point::new(1, 0),
+                        Point::new(3, 2)..Point::new(3, 3),
+                        Point::new(5, 6)..Point::new(6, 0),
+                    ]);
+                });
+            })
+            .unwrap();
+        let state = cx
+            .update_window(window.into(), |view, cx| {
+                cx.with_element_context(|cx| {
+                    cx.with_view_id(view.entity_id(), |cx| {
+                        element.compute_layout(
+                            Bounds {
+                                origin: point(px(500.), px(500.)),
+                                size: size(px(500.), px(500.)),
+                            },
+                            cx,
+                        )
+                    })
+                })
             })
             .unwrap();
 
@@ -3856,6 +5908,241 @@ mod tests {
         .unwrap()
     }
 
+    #[gpui::test]
+    fn test_layout_blocks_near_viewport(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let window = cx.add_window(|cx| {
+            let buffer = MultiBuffer::build_simple(&sample_text(200, 6, 'a'), cx);
+            Editor::new(EditorMode::Full, buffer, None, cx)
+        });
+        let editor = window.root(cx).unwrap();
+        let style = cx.update(|cx| editor.read(cx).style().unwrap().clone());
+        let mut element = EditorElement::new(&editor, style);
+        let mut layout = |cx: &mut TestAppContext| {
+            cx.update_window(window.into(), |view, cx| {
+                cx.with_element_context(|cx| {
+                    cx.with_view_id(view.entity_id(), |cx| {
+                        element.compute_layout(
+                            Bounds {
+                                origin: point(px(0.), px(0.)),
+                                size: size(px(500.), px(500.)),
+                            },
+                            cx,
+                        )
+                    })
+                })
+            })
+            .unwrap()
+        };
+        let visible_rows = layout(cx).position_map.line_layouts.len() as u32;
+
+        // Decorations just below the viewport are measured before they're scrolled into view,
+        // while the ones further away keep the height they were inserted with.
+        let block_ids = window
+            .update(cx, |editor, cx| {
+                let buffer = editor.buffer.read(cx).snapshot(cx);
+                let decoration = |row| BlockDecoration {
+                    position: buffer.anchor_after(Point::new(row, 0)),
+                    disposition: BlockDisposition::Below,
+                    style: BlockStyle::Flex,
+                    height: 1,
+                    fit_content: true,
+                    render: Arc::new(|_| div().h(px(200.)).into_any_element()),
+                    on_event: None,
+                };
+                editor.insert_block_decorations(
+                    [decoration(visible_rows + 2), decoration(visible_rows + 100)],
+                    cx,
+                )
+            })
+            .unwrap();
+        let state = layout(cx);
+        assert!(state.blocks.is_empty());
+        assert!(state.blocks_resized);
+
+        let heights = window
+            .update(cx, |editor, cx| {
+                let snapshot = editor.snapshot(cx);
+                block_ids
+                    .iter()
+                    .map(|block_id| {
+                        snapshot
+                            .blocks_in_range(0..snapshot.max_point().row() + 1)
+                            .find_map(|(_, block)| match block {
+                                TransformBlock::Custom(block) if block.id() == *block_id => {
+                                    Some(block.height())
+                                }
+                                _ => None,
+                            })
+                            .unwrap()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap();
+        assert!(heights[0] > 1);
+        assert_eq!(heights[1], 1);
+    }
+
+    #[gpui::test]
+    fn test_overscan_rows(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let text = (0..200).map(|row| format!("line{row}")).join("\n");
+        let window = cx.add_window(|cx| {
+            let buffer = MultiBuffer::build_simple(&text, cx);
+            Editor::new(EditorMode::Full, buffer, None, cx)
+        });
+        let editor = window.root(cx).unwrap();
+        let style = cx.update(|cx| editor.read(cx).style().unwrap().clone());
+        let mut element = EditorElement::new(&editor, style);
+        let mut layout = |cx: &mut TestAppContext| {
+            cx.update_window(window.into(), |view, cx| {
+                cx.with_element_context(|cx| {
+                    cx.with_view_id(view.entity_id(), |cx| {
+                        element.compute_layout(
+                            Bounds {
+                                origin: point(px(0.), px(0.)),
+                                size: size(px(500.), px(500.)),
+                            },
+                            cx,
+                        )
+                    })
+                })
+            })
+            .unwrap()
+        };
+        let overscan = cx.update(|cx| EditorSettings::get_global(cx).overscan_rows) as usize;
+        layout(cx);
+        let stats = window
+            .update(cx, |editor, _| editor.line_layout_stats())
+            .unwrap();
+        assert_eq!(stats.hits, 0);
+        assert_eq!(stats.overscanned, overscan);
+
+        // Scrolling a row at a time, the rows scrolled into view were already laid out around the
+        // visible ones, while the row that comes into the overscan is laid out right away.
+        for row in 1..=2 * overscan {
+            window
+                .update(cx, |editor, cx| {
+                    editor.set_scroll_position(gpui::Point::new(0., row as f32), cx)
+                })
+                .unwrap();
+            layout(cx);
+            let new_stats = window
+                .update(cx, |editor, _| editor.line_layout_stats())
+                .unwrap();
+            assert_eq!(new_stats.misses, stats.misses);
+            assert_eq!(new_stats.overscanned, overscan + row);
+        }
+    }
+
+    #[gpui::test]
+    fn test_centered_layout(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let window = cx.add_window(|cx| {
+            let buffer = MultiBuffer::build_simple(&sample_text(6, 6, 'a'), cx);
+            Editor::new(EditorMode::Full, buffer, None, cx)
+        });
+        let editor = window.root(cx).unwrap();
+        let style = cx.update(|cx| editor.read(cx).style().unwrap().clone());
+        let mut element = EditorElement::new(&editor, style);
+        let bounds = Bounds {
+            origin: point(px(0.), px(0.)),
+            size: size(px(3000.), px(500.)),
+        };
+        let mut compute_layout = |cx: &mut TestAppContext| {
+            cx.update_window(window.into(), |view, cx| {
+                cx.with_element_context(|cx| {
+                    cx.with_view_id(view.entity_id(), |cx| element.compute_layout(bounds, cx))
+                })
+            })
+            .unwrap()
+        };
+
+        let state = compute_layout(cx);
+        assert_eq!(state.centering_padding, Pixels::ZERO);
+
+        // Toggling the centered layout pads both sides of the gutter and text equally.
+        window
+            .update(cx, |editor, cx| {
+                editor.toggle_centered_layout(&ToggleCenteredLayout, cx)
+            })
+            .unwrap();
+        let state = compute_layout(cx);
+        assert!(state.centering_padding > Pixels::ZERO);
+        let width = state.centering_padding * 2. + state.gutter_size.width + state.text_size.width;
+        assert!((width - bounds.size.width).abs() < px(0.01));
+    }
+
+    #[gpui::test]
+    fn test_excerpt_bands(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let window = cx.add_window(|cx| {
+            let buffer = MultiBuffer::build_simple(&sample_text(8, 4, 'a'), cx);
+            let buffer = buffer.read(cx).as_singleton().unwrap();
+            let multibuffer = cx.new_model(|cx| {
+                let mut multibuffer = MultiBuffer::new(0, Capability::ReadWrite);
+                multibuffer.push_excerpts(
+                    buffer,
+                    [
+                        ExcerptRange {
+                            context: Point::new(0, 0)..Point::new(2, 0),
+                            primary: None,
+                        },
+                        ExcerptRange {
+                            context: Point::new(4, 0)..Point::new(7, 0),
+                            primary: None,
+                        },
+                    ],
+                    cx,
+                );
+                multibuffer
+            });
+            Editor::new(EditorMode::Full, multibuffer, None, cx)
+        });
+        let snapshot = window.update(cx, |editor, cx| editor.snapshot(cx)).unwrap();
+        let row_count = snapshot.max_point().row() + 1;
+
+        // Each excerpt's band starts at its header and ends where the next one starts.
+        let bands = layout_excerpt_bands(0..row_count, &snapshot);
+        assert_eq!(bands.len(), 2);
+        assert!(bands.iter().all(|band| band.starts_excerpt));
+        assert_eq!(bands[0].rows.start, 0);
+        assert_eq!(bands[0].rows.end, bands[1].rows.start);
+        assert_eq!(bands[1].rows.end, row_count);
+        assert_ne!(bands[0].id, bands[1].id);
+
+        // When the header of an excerpt is scrolled past, its band starts at the top.
+        let bands_after_header = layout_excerpt_bands(row_count - 1..row_count, &snapshot);
+        assert_eq!(
+            bands_after_header,
+            vec![ExcerptBand {
+                rows: row_count - 1..row_count,
+                id: bands[1].id,
+                starts_excerpt: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_grapheme_start() {
+        assert_eq!(grapheme_start("abc", 1), 1);
+        assert_eq!(grapheme_start("abc", 3), 2);
+
+        // Combining marks belong to the character before them.
+        let text = "ae\u{301}\u{302}b";
+        assert_eq!(grapheme_start(text, 1), 1);
+        assert_eq!(grapheme_start(text, 2), 1);
+        assert_eq!(grapheme_start(text, 4), 1);
+        assert_eq!(grapheme_start(text, 6), 6);
+
+        // Emoji sequences and wide characters are single clusters.
+        let text = "a👩‍👩‍👧b漢字";
+        let family_len = "👩‍👩‍👧".len();
+        assert_eq!(grapheme_start(text, 5), 1);
+        assert_eq!(grapheme_start(text, 1 + family_len), 1 + family_len);
+        assert_eq!(grapheme_start(text, family_len + 3), family_len + 2);
+    }
+
     #[gpui::test]
     fn test_all_invisibles_drawing(cx: &mut TestAppContext) {
         const TAB_SIZE: u32 = 4;
@@ -3923,6 +6210,76 @@ mod tests {
         }
     }
 
+    #[gpui::test]
+    fn test_nbsp_invisibles(cx: &mut TestAppContext) {
+        init_test(cx, |s| {
+            s.defaults.show_whitespaces = Some(ShowWhitespaceSetting::Boundary);
+        });
+
+        let invisibles = collect_invisibles_from_new_editor(
+            cx,
+            EditorMode::Full,
+            "a b\u{a0}c\u{202f}d ",
+            px(500.0),
+        );
+        assert_eq!(
+            invisibles,
+            vec![
+                Invisible::Whitespace { line_offset: 1 },
+                Invisible::Nbsp { line_offset: 3 },
+                Invisible::Nbsp { line_offset: 6 },
+                Invisible::Whitespace { line_offset: 10 },
+            ]
+        );
+    }
+
+    #[gpui::test]
+    fn test_boundary_invisibles_painting(cx: &mut TestAppContext) {
+        init_test(cx, |s| {
+            s.defaults.show_whitespaces = Some(ShowWhitespaceSetting::Boundary);
+            s.defaults.tab_size = NonZeroU32::new(4);
+        });
+
+        // Only leading and trailing whitespace is painted, along with non-breaking spaces.
+        let layout =
+            compute_layout_for_new_editor(cx, EditorMode::Full, "\t a\u{a0}b c d ", px(500.0));
+        let line = &layout.position_map.line_layouts[0];
+        let painted = |whitespace_setting, selection_ranges: &[Range<DisplayPoint>]| {
+            line.invisibles_to_paint(selection_ranges, 0, whitespace_setting)
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            painted(ShowWhitespaceSetting::Boundary, &[]),
+            vec![
+                Invisible::Tab {
+                    line_start_offset: 0
+                },
+                Invisible::Whitespace { line_offset: 4 },
+                Invisible::Nbsp { line_offset: 6 },
+                Invisible::Whitespace { line_offset: 13 },
+            ]
+        );
+
+        // Other settings paint all whitespace, or only the selected whitespace.
+        assert_eq!(painted(ShowWhitespaceSetting::All, &[]), line.invisibles);
+        assert_eq!(
+            painted(
+                ShowWhitespaceSetting::Selection,
+                &[DisplayPoint::new(0, 1)..DisplayPoint::new(0, 10)]
+            ),
+            vec![
+                Invisible::Whitespace { line_offset: 4 },
+                Invisible::Nbsp { line_offset: 6 },
+                Invisible::Whitespace { line_offset: 9 },
+            ]
+        );
+        assert_eq!(
+            painted(ShowWhitespaceSetting::None, &[]),
+            vec![Invisible::Nbsp { line_offset: 6 }]
+        );
+    }
+
     #[gpui::test]
     fn test_wrapped_invisibles_drawing(cx: &mut TestAppContext) {
         let tab_size = 4;
@@ -4003,29 +6360,52 @@ mod tests {
         }
     }
 
-    fn collect_invisibles_from_new_editor(
-        cx: &mut TestAppContext,
-        editor_mode: EditorMode,
-        input_text: &str,
-        editor_width: Pixels,
-    ) -> Vec<Invisible> {
-        info!(
-            "Creating editor with mode {editor_mode:?}, width {}px and text '{input_text}'",
-            editor_width.0
+    #[gpui::test]
+    fn test_trailing_whitespace(cx: &mut TestAppContext) {
+        init_test(cx, |s| {
+            s.defaults.tab_size = NonZeroU32::new(4);
+        });
+
+        let layout_state =
+            compute_layout_for_new_editor(cx, EditorMode::Full, "a  \nb\n\t\nc d\n", px(500.));
+        let trailing_whitespace = layout_state
+            .position_map
+            .line_layouts
+            .iter()
+            .map(|line| line.trailing_whitespace.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            trailing_whitespace,
+            vec![Some(1..3), None, Some(0..4), None, None]
         );
+    }
+
+    #[gpui::test]
+    fn test_suggestion_ghost_text(cx: &mut TestAppContext) {
+        init_test(cx, |s| {
+            s.defaults.show_whitespaces = Some(ShowWhitespaceSetting::All);
+        });
+
         let window = cx.add_window(|cx| {
-            let buffer = MultiBuffer::build_simple(&input_text, cx);
-            Editor::new(editor_mode, buffer, None, cx)
+            let buffer = MultiBuffer::build_simple("a \nb\n", cx);
+            let mut editor = Editor::new(EditorMode::Full, buffer, None, cx);
+            let position = editor
+                .buffer
+                .read(cx)
+                .snapshot(cx)
+                .anchor_after(Point::new(0, 2));
+            editor.display_map.update(cx, |map, cx| {
+                map.splice_inlays(
+                    Vec::new(),
+                    vec![Inlay::suggestion(0, position, "c d\ne ")],
+                    cx,
+                )
+            });
+            editor
         });
         let editor = window.root(cx).unwrap();
         let style = cx.update(|cx| editor.read(cx).style().unwrap().clone());
         let mut element = EditorElement::new(&editor, style);
-        window
-            .update(cx, |editor, cx| {
-                editor.set_soft_wrap_mode(language_settings::SoftWrap::EditorWidth, cx);
-                editor.set_wrap_width(Some(editor_width), cx);
-            })
-            .unwrap();
         let layout_state = cx
             .update_window(window.into(), |_, cx| {
                 cx.with_element_context(|cx| {
@@ -4040,7 +6420,121 @@ mod tests {
             })
             .unwrap();
 
-        layout_state
+        // The suggestion's whitespace has no invisibles, and isn't trailing whitespace of the
+        // buffer lines it's shown on.
+        let lines = &layout_state.position_map.line_layouts;
+        assert_eq!(
+            lines[0].invisibles,
+            vec![Invisible::Whitespace { line_offset: 1 }]
+        );
+        assert_eq!(lines[0].trailing_whitespace, Some(1..2));
+        assert_eq!(lines[1].invisibles, Vec::new());
+        assert_eq!(lines[1].trailing_whitespace, None);
+    }
+
+    #[gpui::test]
+    async fn test_scrollbar_marker_clicks(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        let line_height = cx.editor(|editor, cx| {
+            editor
+                .style()
+                .unwrap()
+                .text
+                .line_height_in_pixels(cx.rem_size())
+        });
+        let height = 10. * line_height;
+        cx.simulate_window_resize(cx.window, size(px(500.), height));
+        let text = (0..20).map(|row| format!("line {row}")).join("\n");
+        cx.set_state(&format!("ˇ{text}"));
+        cx.update_editor(|editor, cx| {
+            let buffer = editor.buffer.read(cx).snapshot(cx);
+            let ranges = [2, 15].map(|row| {
+                buffer.anchor_after(Point::new(row, 0))..buffer.anchor_before(Point::new(row, 4))
+            });
+            editor.highlight_background::<BufferSearchHighlights>(
+                ranges.to_vec(),
+                |colors| colors.search_match_background,
+                cx,
+            );
+        });
+        cx.run_until_parked();
+
+        // The scrollbar spans the buffer's rows and a page past its last row, and its thumb spans
+        // the ten visible rows.
+        let x = px(500.) - px(4.);
+        let y_for_row = |row: f32| height * (row / 29.) + px(0.5);
+        let scroll_y = |cx: &mut EditorTestContext| {
+            cx.update_editor(|editor, cx| editor.scroll_position(cx).y)
+        };
+        let mouse_down = |cx: &mut EditorTestContext, y: Pixels| {
+            cx.simulate_event(MouseDownEvent {
+                position: point(x, y),
+                modifiers: gpui::Modifiers::none(),
+                button: MouseButton::Left,
+                click_count: 1,
+            })
+        };
+        let mouse_up = |cx: &mut EditorTestContext, y: Pixels| {
+            cx.simulate_event(MouseUpEvent {
+                position: point(x, y),
+                modifiers: gpui::Modifiers::none(),
+                button: MouseButton::Left,
+                click_count: 1,
+            })
+        };
+
+        // Clicking a marker below the thumb jumps to its row, centering it.
+        mouse_down(&mut cx, y_for_row(15.));
+        mouse_up(&mut cx, y_for_row(15.));
+        assert!((scroll_y(&mut cx) - 10.).abs() < 0.01);
+
+        // Markers under the thumb don't prevent dragging it.
+        cx.update_editor(|editor, cx| editor.set_scroll_position(gpui::Point::default(), cx));
+        cx.run_until_parked();
+        mouse_down(&mut cx, y_for_row(2.));
+        assert_eq!(scroll_y(&mut cx), 0.);
+        cx.simulate_event(MouseMoveEvent {
+            position: point(x, y_for_row(7.)),
+            modifiers: gpui::Modifiers::none(),
+            pressed_button: Some(MouseButton::Left),
+        });
+        mouse_up(&mut cx, y_for_row(7.));
+        assert!((scroll_y(&mut cx) - 5.).abs() < 0.01);
+    }
+
+    fn set_diagnostics(
+        editor: &Editor,
+        diagnostics: &[(Range<usize>, DiagnosticSeverity, &str, bool)],
+        cx: &mut WindowContext,
+    ) {
+        let buffer = editor.buffer().read(cx).as_singleton().unwrap();
+        buffer.update(cx, |buffer, cx| {
+            let snapshot = buffer.text_snapshot();
+            let entries = diagnostics
+                .iter()
+                .map(|(range, severity, message, is_primary)| DiagnosticEntry {
+                    range: snapshot.anchor_before(range.start)..snapshot.anchor_after(range.end),
+                    diagnostic: Diagnostic {
+                        severity: *severity,
+                        message: message.to_string(),
+                        is_primary: *is_primary,
+                        is_unnecessary: *severity == DiagnosticSeverity::HINT,
+                        ..Default::default()
+                    },
+                });
+            let set = DiagnosticSet::from_sorted_entries(entries, &snapshot);
+            buffer.update_diagnostics(LanguageServerId(0), set, cx);
+        });
+    }
+
+    fn collect_invisibles_from_new_editor(
+        cx: &mut TestAppContext,
+        editor_mode: EditorMode,
+        input_text: &str,
+        editor_width: Pixels,
+    ) -> Vec<Invisible> {
+        compute_layout_for_new_editor(cx, editor_mode, input_text, editor_width)
             .position_map
             .line_layouts
             .iter()
@@ -4049,9 +6543,46 @@ mod tests {
             .cloned()
             .collect()
     }
+
+    fn compute_layout_for_new_editor(
+        cx: &mut TestAppContext,
+        editor_mode: EditorMode,
+        input_text: &str,
+        editor_width: Pixels,
+    ) -> LayoutState {
+        info!(
+            "Creating editor with mode {editor_mode:?}, width {}px and text '{input_text}'",
+            editor_width.0
+        );
+        let window = cx.add_window(|cx| {
+            let buffer = MultiBuffer::build_simple(&input_text, cx);
+            Editor::new(editor_mode, buffer, None, cx)
+        });
+        let editor = window.root(cx).unwrap();
+        let style = cx.update(|cx| editor.read(cx).style().unwrap().clone());
+        let mut element = EditorElement::new(&editor, style);
+        window
+            .update(cx, |editor, cx| {
+                editor.set_soft_wrap_mode(language_settings::SoftWrap::EditorWidth, cx);
+                editor.set_wrap_width(Some(editor_width), cx);
+            })
+            .unwrap();
+        cx.update_window(window.into(), |_, cx| {
+            cx.with_element_context(|cx| {
+                element.compute_layout(
+                    Bounds {
+                        origin: point(px(500.), px(500.)),
+                        size: size(px(500.), px(500.)),
+                    },
+                    cx,
+                )
+            })
+        })
+        .unwrap()
+    }
 }
 
-pub fn register_action<T: Action>(
+pub fn register_action<T: Action + Serialize>(
     view: &View<Editor>,
     cx: &mut WindowContext,
     listener: impl Fn(&mut Editor, &T, &mut ViewContext<Editor>) + 'static,
@@ -4061,6 +6592,7 @@ pub fn register_action<T: Action>(
         let action = action.downcast_ref().unwrap();
         if phase == DispatchPhase::Bubble {
             view.update(cx, |editor, cx| {
+                editor.record_macro_action(action);
                 listener(editor, action, cx);
             })
         }
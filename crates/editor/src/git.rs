@@ -1,4 +1,7 @@
+pub mod blame;
+pub mod inline_diff;
 pub mod permalink;
+pub mod review_mode;
 
 use std::ops::Range;
 
@@ -0,0 +1,530 @@
+use crate::{
+    editor_settings::InlineBlame, Anchor, DisplayPoint, Editor, EditorMode, EditorSettings,
+    EditorSnapshot, EditorStyle,
+};
+use anyhow::anyhow;
+use git::blame::CommitDetails;
+use gpui::{AnyElement, Render, ViewContext, VisualContext};
+use language::{Buffer, Point};
+use multi_buffer::{MultiBufferSnapshot, ToPoint};
+use settings::Settings;
+use std::{
+    ops::Range,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use sum_tree::Bias;
+use ui::{prelude::*, tooltip_container};
+
+const GIT_BLAME_DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// The commits that last changed each line of an editor's buffer. The lines are anchored, so
+/// that they keep following the blamed text as it is edited.
+pub(crate) struct GitBlame {
+    entries: Vec<GitBlameEntry>,
+    /// The version of the buffer that the entries were last updated for.
+    version: clock::Global,
+}
+
+struct GitBlameEntry {
+    range: Range<Anchor>,
+    commit: Option<Arc<CommitDetails>>,
+}
+
+impl GitBlame {
+    fn new(
+        blame: git::blame::Blame,
+        version: clock::Global,
+        snapshot: &MultiBufferSnapshot,
+    ) -> Self {
+        let anchor_for_row =
+            |row: u32| snapshot.anchor_before(snapshot.clip_point(Point::new(row, 0), Bias::Left));
+        Self {
+            entries: blame
+                .entries
+                .into_iter()
+                .filter(|entry| !entry.range.is_empty())
+                .map(|entry| GitBlameEntry {
+                    range: anchor_for_row(entry.range.start)..anchor_for_row(entry.range.end),
+                    commit: entry.commit,
+                })
+                .collect(),
+            version,
+        }
+    }
+
+    /// Marks the lines edited since the entries were last updated as not committed yet, keeping
+    /// the commits of every other line instead of blaming the whole buffer again.
+    fn apply_edits(&mut self, buffer: &Buffer, snapshot: &MultiBufferSnapshot) {
+        let edited_rows = buffer
+            .edits_since::<Point>(&self.version)
+            .map(|edit| edit.new.start.row..edit.new.end.row + 1)
+            .collect::<Vec<_>>();
+        if edited_rows.is_empty() {
+            return;
+        }
+
+        let mut entries = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let end = entry.range.end.to_point(snapshot);
+                git::blame::BlameEntry {
+                    range: entry.range.start.to_point(snapshot).row
+                        ..end.row + (end.column > 0) as u32,
+                    commit: entry.commit.clone(),
+                }
+            })
+            .filter(|entry| !entry.range.is_empty())
+            .collect::<Vec<_>>();
+        for rows in edited_rows {
+            mark_not_committed(&mut entries, rows);
+        }
+        *self = Self::new(git::blame::Blame { entries }, buffer.version(), snapshot);
+    }
+
+    /// Returns the entry for the given buffer row, or `None` if the row hasn't been blamed yet.
+    fn entry_for_row(&self, row: u32, snapshot: &MultiBufferSnapshot) -> Option<&GitBlameEntry> {
+        let point = Point::new(row, 0);
+        let ix = self
+            .entries
+            .binary_search_by(|entry| {
+                if entry.range.end.to_point(snapshot) <= point {
+                    std::cmp::Ordering::Less
+                } else if entry.range.start.to_point(snapshot) > point {
+                    std::cmp::Ordering::Greater
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+            .ok()?;
+        Some(&self.entries[ix])
+    }
+}
+
+/// Replaces the commits of the given sorted entries on the given rows with a single entry that
+/// isn't committed yet.
+fn mark_not_committed(entries: &mut Vec<git::blame::BlameEntry>, rows: Range<u32>) {
+    let not_committed = git::blame::BlameEntry {
+        range: rows.clone(),
+        commit: None,
+    };
+    let first_ix = entries.partition_point(|entry| entry.range.end <= rows.start);
+    let end_ix = entries.partition_point(|entry| entry.range.start < rows.end);
+    let mut replacement = Vec::with_capacity(3);
+    if let Some(first) = entries.get(first_ix).filter(|_| first_ix < end_ix) {
+        if first.range.start < rows.start {
+            replacement.push(git::blame::BlameEntry {
+                range: first.range.start..rows.start,
+                commit: first.commit.clone(),
+            });
+        }
+    }
+    replacement.push(not_committed);
+    if let Some(last) = end_ix.checked_sub(1).and_then(|ix| entries.get(ix)) {
+        if end_ix > first_ix && last.range.end > rows.end {
+            replacement.push(git::blame::BlameEntry {
+                range: rows.end..last.range.end,
+                commit: last.commit.clone(),
+            });
+        }
+    }
+    entries.splice(first_ix..end_ix, replacement);
+}
+
+impl Editor {
+    /// Blames the buffer's current text against its git repository, unsaved edits included, so
+    /// that the blamed rows are the buffer's own. Edits made since then are applied to the
+    /// existing blame by [`Editor::update_git_blame_after_edit`].
+    pub(crate) fn refresh_git_blame(&mut self, cx: &mut ViewContext<Self>) {
+        if self.mode != EditorMode::Full
+            || EditorSettings::get_global(cx).inline_blame == InlineBlame::Off
+        {
+            self.git_blame = None;
+            self.git_blame_task = None;
+            self.git_blame_edits_task = None;
+            return;
+        }
+
+        let Some(buffer) = self.buffer.read(cx).as_singleton() else {
+            return;
+        };
+        let Some(file) = project::File::from_dyn(buffer.read(cx).file()) else {
+            return;
+        };
+        let worktree = file.worktree.read(cx);
+        let Some(local_worktree) = worktree.as_local() else {
+            return;
+        };
+        let Some((work_directory, repo)) = local_worktree.local_git_repo_for_path(&file.path)
+        else {
+            return;
+        };
+        let Ok(repo_path) = file.path.strip_prefix(&work_directory) else {
+            return;
+        };
+
+        let repo_path = repo_path.to_path_buf();
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let version = buffer.read(cx).version();
+        self.git_blame_task = Some(cx.spawn(|this, mut cx| async move {
+            let blame = cx
+                .background_executor()
+                .spawn({
+                    let snapshot = snapshot.clone();
+                    async move {
+                        let text = snapshot.text();
+                        let blame = repo.lock().blame(&repo_path, &text);
+                        blame
+                    }
+                })
+                .await;
+            this.update(&mut cx, |this, cx| {
+                // Files that aren't tracked by the repository can't be blamed.
+                this.git_blame = blame.ok().map(|blame| {
+                    let mut blame = GitBlame::new(blame, version, &snapshot);
+                    // The buffer may have been edited while it was being blamed.
+                    blame.apply_edits(buffer.read(cx), &this.buffer.read(cx).snapshot(cx));
+                    blame
+                });
+                cx.notify();
+            })
+            .ok();
+        }));
+    }
+
+    /// Marks the lines edited since the blame was last updated as not committed yet, once the
+    /// edits have paused, so that typing doesn't run a blame of the whole buffer.
+    pub(crate) fn update_git_blame_after_edit(&mut self, cx: &mut ViewContext<Self>) {
+        if self.git_blame.is_none() {
+            return;
+        }
+        self.git_blame_edits_task = Some(cx.spawn(|this, mut cx| async move {
+            cx.background_executor()
+                .timer(GIT_BLAME_DEBOUNCE_TIMEOUT)
+                .await;
+            this.update(&mut cx, |this, cx| {
+                let Some(buffer) = this.buffer.read(cx).as_singleton() else {
+                    return;
+                };
+                let snapshot = this.buffer.read(cx).snapshot(cx);
+                if let Some(blame) = this.git_blame.as_mut() {
+                    blame.apply_edits(buffer.read(cx), &snapshot);
+                    cx.notify();
+                }
+            })
+            .ok();
+        }));
+    }
+
+    /// Renders the blame annotations to show after the ends of the given display rows.
+    pub(crate) fn render_blame_annotations(
+        &self,
+        rows: Range<u32>,
+        newest_selection_head: DisplayPoint,
+        snapshot: &EditorSnapshot,
+        style: &EditorStyle,
+        cx: &mut ViewContext<Self>,
+    ) -> Vec<(u32, AnyElement)> {
+        let Some(blame) = self.git_blame.as_ref() else {
+            return Vec::new();
+        };
+
+        let annotated_rows = match EditorSettings::get_global(cx).inline_blame {
+            InlineBlame::Off => Vec::new(),
+            InlineBlame::CursorLine => {
+                let row = newest_selection_head.row();
+                if rows.contains(&row) {
+                    let buffer_row = newest_selection_head
+                        .to_point(&snapshot.display_snapshot)
+                        .row;
+                    vec![(row, buffer_row)]
+                } else {
+                    Vec::new()
+                }
+            }
+            InlineBlame::AllLines => snapshot
+                .buffer_rows(rows.start)
+                .take(rows.len())
+                .enumerate()
+                .filter_map(|(ix, buffer_row)| Some((rows.start + ix as u32, buffer_row?)))
+                .collect(),
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs() as i64);
+        annotated_rows
+            .into_iter()
+            .filter_map(|(row, buffer_row)| {
+                let entry = blame.entry_for_row(buffer_row, &snapshot.buffer_snapshot)?;
+                let commit = entry.commit.clone();
+                let annotation = div()
+                    .id(("blame-annotation", row as usize))
+                    .font(style.text.font_family.clone())
+                    .text_color(cx.theme().colors().editor_line_number)
+                    .child(blame_annotation_text(commit.as_deref(), now))
+                    .when_some(commit, |this, commit| {
+                        this.tooltip(move |cx| {
+                            cx.new_view(|_| BlameEntryTooltip {
+                                commit: commit.clone(),
+                                now,
+                            })
+                            .into()
+                        })
+                    })
+                    .into_any_element();
+                Some((row, annotation))
+            })
+            .collect()
+    }
+}
+
+/// Shows the full message of the commit that last changed a line.
+struct BlameEntryTooltip {
+    commit: Arc<CommitDetails>,
+    now: i64,
+}
+
+impl Render for BlameEntryTooltip {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let commit = &self.commit;
+        let author = match &commit.author_email {
+            Some(email) => format!("{} <{}>", commit.author, email),
+            None => commit.author.clone(),
+        };
+        tooltip_container(cx, |this, _| {
+            this.gap_1()
+                .child(
+                    h_flex().gap_2().child(Label::new(author)).child(
+                        Label::new(format_age(commit.timestamp, self.now)).color(Color::Muted),
+                    ),
+                )
+                .child(
+                    Label::new(commit.sha.chars().take(8).collect::<String>())
+                        .size(LabelSize::Small)
+                        .color(Color::Muted),
+                )
+                .child(div().max_w(px(480.)).child(commit.message.clone()))
+        })
+    }
+}
+
+/// Describes the commit that last changed a line as its author, age and summary.
+fn blame_annotation_text(commit: Option<&CommitDetails>, now: i64) -> String {
+    match commit {
+        Some(commit) => format!(
+            "{}, {} • {}",
+            commit.author,
+            format_age(commit.timestamp, now),
+            commit.summary
+        ),
+        None => "Not committed yet".to_string(),
+    }
+}
+
+/// Formats the time elapsed between two Unix timestamps, e.g. "3 days ago".
+pub(crate) fn format_age(timestamp: i64, now: i64) -> String {
+    const MINUTE: i64 = 60;
+    const HOUR: i64 = 60 * MINUTE;
+    const DAY: i64 = 24 * HOUR;
+    const MONTH: i64 = 30 * DAY;
+    const YEAR: i64 = 365 * DAY;
+
+    let elapsed = now.saturating_sub(timestamp);
+    let (count, unit) = if elapsed < MINUTE {
+        return "just now".to_string();
+    } else if elapsed < HOUR {
+        (elapsed / MINUTE, "minute")
+    } else if elapsed < DAY {
+        (elapsed / HOUR, "hour")
+    } else if elapsed < MONTH {
+        (elapsed / DAY, "day")
+    } else if elapsed < YEAR {
+        (elapsed / MONTH, "month")
+    } else {
+        (elapsed / YEAR, "year")
+    };
+    if count == 1 {
+        format!("1 {unit} ago")
+    } else {
+        format!("{count} {unit}s ago")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::build_editor_with_project, MultiBuffer};
+    use project::{FakeFs, Project};
+    use serde_json::json;
+    use settings::SettingsStore;
+    use std::path::Path;
+
+    #[gpui::test]
+    async fn test_inline_blame(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|settings, cx| {
+                settings.update_user_settings::<EditorSettings>(cx, |settings| {
+                    settings.inline_blame = Some(InlineBlame::AllLines);
+                });
+            });
+        });
+
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/root",
+            json!({
+                ".git": {},
+                "file.txt": "one\ntwo\nthree\n",
+            }),
+        )
+        .await;
+        let commit = |sha: &str| {
+            Some(Arc::new(CommitDetails {
+                sha: sha.into(),
+                author: "Ada".into(),
+                author_email: None,
+                timestamp: 0,
+                summary: "Summary".into(),
+                message: "Summary".into(),
+            }))
+        };
+        fs.set_blame_for_repo(
+            Path::new("/root/.git"),
+            vec![(
+                Path::new("file.txt"),
+                git::blame::Blame {
+                    entries: vec![
+                        git::blame::BlameEntry {
+                            range: 0..2,
+                            commit: commit("a"),
+                        },
+                        git::blame::BlameEntry {
+                            range: 2..3,
+                            commit: commit("b"),
+                        },
+                    ],
+                },
+            )],
+        );
+
+        let project = Project::test(fs, ["/root".as_ref()], cx).await;
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_local_buffer("/root/file.txt", cx)
+            })
+            .await
+            .unwrap();
+        let buffer = cx.new_model(|cx| MultiBuffer::singleton(buffer, cx));
+        let editor = cx.add_window(|cx| build_editor_with_project(project.clone(), buffer, cx));
+        cx.executor().run_until_parked();
+
+        let blamed_rows = |cx: &mut gpui::TestAppContext| {
+            editor
+                .update(cx, |editor, cx| {
+                    let snapshot = editor.buffer.read(cx).snapshot(cx);
+                    let blame = editor.git_blame.as_ref().unwrap();
+                    (0..=snapshot.max_point().row)
+                        .map(|row| {
+                            let entry = blame.entry_for_row(row, &snapshot)?;
+                            Some(entry.commit.as_ref().map(|commit| commit.sha.clone()))
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .unwrap()
+        };
+        let sha = |sha: &str| Some(Some(sha.to_string()));
+        assert_eq!(blamed_rows(cx), [sha("a"), sha("a"), sha("b"), None]);
+
+        // Edited lines aren't committed anymore once the edits pause, while the other lines keep
+        // their commits rather than the whole buffer being blamed again.
+        editor
+            .update(cx, |editor, cx| {
+                editor.change_selections(None, cx, |s| {
+                    s.select_ranges([Point::new(1, 3)..Point::new(1, 3)])
+                });
+                editor.insert("\nfour", cx);
+            })
+            .unwrap();
+        assert_eq!(
+            blamed_rows(cx),
+            [sha("a"), sha("a"), sha("a"), sha("b"), None]
+        );
+        cx.executor().advance_clock(GIT_BLAME_DEBOUNCE_TIMEOUT);
+        cx.executor().run_until_parked();
+        assert_eq!(
+            blamed_rows(cx),
+            [sha("a"), Some(None), Some(None), sha("b"), None]
+        );
+    }
+
+    #[test]
+    fn test_mark_not_committed() {
+        let commit = Arc::new(CommitDetails {
+            sha: "a".repeat(40),
+            author: "Ada".into(),
+            author_email: None,
+            timestamp: 0,
+            summary: "Summary".into(),
+            message: "Summary".into(),
+        });
+        let entry = |range: Range<u32>, committed: bool| git::blame::BlameEntry {
+            range,
+            commit: committed.then(|| commit.clone()),
+        };
+
+        let mut entries = vec![entry(0..4, true), entry(4..6, true)];
+        mark_not_committed(&mut entries, 2..3);
+        assert_eq!(
+            entries,
+            [
+                entry(0..2, true),
+                entry(2..3, false),
+                entry(3..4, true),
+                entry(4..6, true)
+            ]
+        );
+        mark_not_committed(&mut entries, 3..5);
+        assert_eq!(
+            entries,
+            [
+                entry(0..2, true),
+                entry(2..3, false),
+                entry(3..5, false),
+                entry(5..6, true)
+            ]
+        );
+        mark_not_committed(&mut entries, 6..7);
+        assert_eq!(entries.last(), Some(&entry(6..7, false)));
+    }
+
+    #[test]
+    fn test_blame_annotation_text() {
+        let commit = CommitDetails {
+            sha: "a".repeat(40),
+            author: "Ada".into(),
+            author_email: Some("ada@example.com".into()),
+            timestamp: 1_000_000,
+            summary: "Fix the thing".into(),
+            message: "Fix the thing\n\nIt was broken.".into(),
+        };
+
+        assert_eq!(
+            blame_annotation_text(Some(&commit), 1_000_000 + 3 * 24 * 60 * 60 + 5),
+            "Ada, 3 days ago • Fix the thing"
+        );
+        assert_eq!(blame_annotation_text(None, 0), "Not committed yet");
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(100, 100), "just now");
+        assert_eq!(format_age(100, 90), "just now");
+        assert_eq!(format_age(0, 60), "1 minute ago");
+        assert_eq!(format_age(0, 2 * 60 * 60 + 59), "2 hours ago");
+        assert_eq!(format_age(0, 45 * 24 * 60 * 60), "1 month ago");
+        assert_eq!(format_age(0, 3 * 365 * 24 * 60 * 60), "3 years ago");
+    }
+}
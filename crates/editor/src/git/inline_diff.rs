@@ -0,0 +1,550 @@
+use crate::{
+    display_map::{
+        BlockContext, BlockDisposition, BlockId, BlockProperties, BlockStyle, RenderBlock,
+    },
+    Anchor, Editor, EditorMode, EditorSettings, ToggleInlineDiff,
+};
+use collections::{HashMap, HashSet};
+use git::diff::DiffHunkStatus;
+use gpui::{AppContext, HighlightStyle, StyledText, Task, ViewContext};
+use language::Point;
+use multi_buffer::{MultiBufferSnapshot, ToPoint};
+use settings::Settings;
+use std::{mem, ops::Range, sync::Arc};
+use ui::prelude::*;
+
+/// The largest number of tokens compared between the old and new text of a hunk. Larger hunks
+/// are shown without highlighting the words that changed within them.
+const MAX_COMPARED_TOKENS: usize = 1000;
+
+/// The blocks showing the old lines of the git diff hunks in an editor.
+#[derive(Default)]
+pub(crate) struct InlineDiff {
+    hunks: Vec<InlineDiffHunk>,
+    task: Option<Task<()>>,
+}
+
+/// A hunk shown with an inline diff, whose blocks are kept until its old or new text changes.
+struct InlineDiffHunk {
+    old_text: Arc<str>,
+    new_text: Arc<str>,
+    position: Anchor,
+    disposition: BlockDisposition,
+    /// The blocks showing the old lines, each of which shows at most [`u8::MAX`] of them.
+    blocks: Vec<BlockId>,
+    inserted_words: Vec<Range<Anchor>>,
+}
+
+/// A hunk in the buffer's diff, compared with the hunks already shown.
+enum HunkUpdate {
+    /// The shown hunk at the given index didn't change.
+    Unchanged(usize),
+    Changed {
+        old_text: Arc<str>,
+        new_text: Arc<str>,
+        position: Anchor,
+        disposition: BlockDisposition,
+        deleted_lines: Vec<DeletedLines>,
+        inserted_words: Vec<Range<Anchor>>,
+    },
+}
+
+/// Old lines of a hunk shown by a single block, along with the ranges of their deleted words.
+struct DeletedLines {
+    text: String,
+    deleted_words: Vec<Range<usize>>,
+    row_count: u8,
+}
+
+/// Marks the words inserted by the hunks shown with an inline diff.
+enum InlineDiffHighlights {}
+
+impl Editor {
+    pub fn toggle_inline_diff(&mut self, _: &ToggleInlineDiff, cx: &mut ViewContext<Self>) {
+        self.show_inline_diff = Some(!self.inline_diff_enabled(cx));
+        self.refresh_inline_diff(cx);
+    }
+
+    /// Whether to show the old lines of each git diff hunk above it.
+    pub fn inline_diff_enabled(&self, cx: &AppContext) -> bool {
+        self.mode == EditorMode::Full
+            && !self.large_file_mode
+            && self
+                .show_inline_diff
+                .unwrap_or_else(|| EditorSettings::get_global(cx).inline_diff)
+    }
+
+    /// Shows the old lines of the buffer's modified and removed hunks as deleted lines above
+    /// them, highlighting the words that changed between the old and new lines. The hunks are
+    /// compared in the background, and only the ones that changed since they were last shown.
+    pub(crate) fn refresh_inline_diff(&mut self, cx: &mut ViewContext<Self>) {
+        let diff_base = if self.inline_diff_enabled(cx) {
+            self.buffer.read(cx).as_singleton().and_then(|buffer| {
+                let diff_base = buffer.read(cx).diff_base()?;
+                Some(diff_base.to_string())
+            })
+        } else {
+            None
+        };
+        let Some(diff_base) = diff_base else {
+            self.inline_diff.task = None;
+            let hunks = mem::take(&mut self.inline_diff.hunks);
+            if !hunks.is_empty() {
+                let blocks = hunks.into_iter().flat_map(|hunk| hunk.blocks).collect();
+                self.remove_blocks(blocks, None, cx);
+                self.clear_highlights::<InlineDiffHighlights>(cx);
+            }
+            return;
+        };
+
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let shown_hunks = self
+            .inline_diff
+            .hunks
+            .iter()
+            .map(|hunk| {
+                (
+                    hunk.old_text.clone(),
+                    hunk.new_text.clone(),
+                    hunk.position,
+                    hunk.disposition,
+                )
+            })
+            .collect::<Vec<_>>();
+        self.inline_diff.task = Some(cx.spawn(|this, mut cx| async move {
+            let updates = cx
+                .background_executor()
+                .spawn(async move { diff_hunk_updates(&snapshot, &diff_base, &shown_hunks) })
+                .await;
+            this.update(&mut cx, |this, cx| this.apply_inline_diff(updates, cx))
+                .ok();
+        }));
+    }
+
+    /// Removes the blocks of the hunks that changed or are gone, and inserts the ones of the
+    /// hunks that changed or are new.
+    fn apply_inline_diff(&mut self, updates: Vec<HunkUpdate>, cx: &mut ViewContext<Self>) {
+        let mut shown_hunks = mem::take(&mut self.inline_diff.hunks)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        let mut hunks = Vec::with_capacity(updates.len());
+        let mut new_blocks = Vec::new();
+        let mut new_block_counts = Vec::new();
+        for update in updates {
+            match update {
+                HunkUpdate::Unchanged(ix) => hunks.extend(shown_hunks[ix].take()),
+                HunkUpdate::Changed {
+                    old_text,
+                    new_text,
+                    position,
+                    disposition,
+                    deleted_lines,
+                    inserted_words,
+                } => {
+                    new_block_counts.push((hunks.len(), deleted_lines.len()));
+                    new_blocks.extend(deleted_lines.into_iter().map(|lines| BlockProperties {
+                        position,
+                        height: lines.row_count,
+                        style: BlockStyle::Flex,
+                        render: render_deleted_lines(lines.text, lines.deleted_words),
+                        disposition,
+                    }));
+                    hunks.push(InlineDiffHunk {
+                        old_text,
+                        new_text,
+                        position,
+                        disposition,
+                        blocks: Vec::new(),
+                        inserted_words,
+                    });
+                }
+            }
+        }
+
+        let removed_blocks = shown_hunks
+            .into_iter()
+            .flatten()
+            .flat_map(|hunk| hunk.blocks)
+            .collect::<HashSet<_>>();
+        if !removed_blocks.is_empty() {
+            self.remove_blocks(removed_blocks, None, cx);
+        }
+        if !new_blocks.is_empty() {
+            let mut block_ids = self.insert_blocks(new_blocks, None, cx).into_iter();
+            for (hunk_ix, block_count) in new_block_counts {
+                hunks[hunk_ix].blocks = block_ids.by_ref().take(block_count).collect();
+            }
+        }
+
+        let inserted_words = hunks
+            .iter()
+            .flat_map(|hunk| hunk.inserted_words.iter().cloned())
+            .collect::<Vec<_>>();
+        self.inline_diff.hunks = hunks;
+        if inserted_words.is_empty() {
+            self.clear_highlights::<InlineDiffHighlights>(cx);
+        } else {
+            let style = HighlightStyle {
+                background_color: Some(cx.theme().status().created_border),
+                ..Default::default()
+            };
+            self.highlight_text::<InlineDiffHighlights>(inserted_words, style, cx);
+        }
+        cx.notify();
+    }
+}
+
+/// Compares the hunks of the buffer's diff with the shown ones, finding the old and new words
+/// that changed in the hunks that aren't shown yet.
+fn diff_hunk_updates(
+    snapshot: &MultiBufferSnapshot,
+    diff_base: &str,
+    shown_hunks: &[(Arc<str>, Arc<str>, Anchor, BlockDisposition)],
+) -> Vec<HunkUpdate> {
+    let mut shown_hunks_by_text = HashMap::<(&str, &str), Vec<usize>>::default();
+    for (ix, (old_text, new_text, _, _)) in shown_hunks.iter().enumerate() {
+        shown_hunks_by_text
+            .entry((&**old_text, &**new_text))
+            .or_default()
+            .push(ix);
+    }
+
+    let max_point = snapshot.max_point();
+    let mut updates = Vec::new();
+    for hunk in snapshot.git_diff_hunks_in_range(0..max_point.row + 1) {
+        let Some(old_text) = diff_base.get(hunk.diff_base_byte_range.clone()) else {
+            continue;
+        };
+        if old_text.is_empty() {
+            continue;
+        }
+        let new_range = Point::new(hunk.buffer_range.start, 0)
+            ..Point::new(hunk.buffer_range.end, 0).min(max_point);
+        let new_text = if hunk.status() == DiffHunkStatus::Modified {
+            snapshot
+                .text_for_range(new_range.clone())
+                .collect::<String>()
+        } else {
+            String::new()
+        };
+        // Lines removed from the end of the buffer are shown below its last line.
+        let (point, disposition) = if hunk.buffer_range.start > max_point.row {
+            (max_point, BlockDisposition::Below)
+        } else {
+            (new_range.start, BlockDisposition::Above)
+        };
+
+        // Hunks with the same text can be shown in several places, so the shown hunk must also
+        // be in the same place to be kept.
+        let unchanged_ix = shown_hunks_by_text
+            .get_mut(&(old_text, new_text.as_str()))
+            .and_then(|ixs| {
+                let ix_ix = ixs.iter().position(|&ix| {
+                    let (_, _, position, shown_disposition) = &shown_hunks[ix];
+                    position.to_point(snapshot) == point && *shown_disposition == disposition
+                })?;
+                Some(ixs.remove(ix_ix))
+            });
+        if let Some(ix) = unchanged_ix {
+            updates.push(HunkUpdate::Unchanged(ix));
+            continue;
+        }
+
+        let (deleted_words, inserted_words) = word_diff(old_text, &new_text);
+        let new_start = snapshot.point_to_offset(new_range.start);
+        let old_lines = old_text.strip_suffix('\n').unwrap_or(old_text);
+        updates.push(HunkUpdate::Changed {
+            old_text: old_text.into(),
+            new_text: new_text.as_str().into(),
+            position: snapshot.anchor_before(point),
+            disposition,
+            deleted_lines: split_deleted_lines(old_lines, &deleted_words),
+            inserted_words: inserted_words
+                .into_iter()
+                .map(|range| {
+                    snapshot.anchor_after(new_start + range.start)
+                        ..snapshot.anchor_before(new_start + range.end)
+                })
+                .collect(),
+        });
+    }
+    updates
+}
+
+/// Splits the old lines of a hunk into the parts shown by each of its blocks, since a block is
+/// at most [`u8::MAX`] rows tall, rebasing the deleted words onto them.
+fn split_deleted_lines(old_lines: &str, deleted_words: &[Range<usize>]) -> Vec<DeletedLines> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    loop {
+        let mut end = start;
+        let mut row_count = 0;
+        for line in old_lines[start..]
+            .split_inclusive('\n')
+            .take(u8::MAX as usize)
+        {
+            end += line.len();
+            row_count += 1;
+        }
+        let text = old_lines[start..end]
+            .strip_suffix('\n')
+            .unwrap_or(&old_lines[start..end]);
+        parts.push(DeletedLines {
+            text: text.to_string(),
+            deleted_words: deleted_words
+                .iter()
+                .filter(|range| range.start < end && range.end > start)
+                .map(|range| range.start.max(start) - start..range.end.min(end) - start)
+                .collect(),
+            row_count: row_count.max(1),
+        });
+        if end >= old_lines.len() {
+            return parts;
+        }
+        start = end;
+    }
+}
+
+fn render_deleted_lines(text: String, deleted_words: Vec<Range<usize>>) -> RenderBlock {
+    Arc::new(move |cx: &mut BlockContext| {
+        let text_style = cx.editor_style.text.clone();
+        let deleted_word_background = cx.theme().status().deleted_border;
+        let mut line_start = 0;
+        let lines = text.split('\n').map(|line| {
+            let line_range = line_start..line_start + line.len();
+            line_start = line_range.end + 1;
+            let highlights = deleted_words
+                .iter()
+                .filter(|range| range.start < line_range.end && range.end > line_range.start)
+                .map(|range| {
+                    let start = range.start.max(line_range.start) - line_range.start;
+                    let end = range.end.min(line_range.end) - line_range.start;
+                    let style = HighlightStyle {
+                        background_color: Some(deleted_word_background),
+                        ..Default::default()
+                    };
+                    (start..end, style)
+                })
+                .collect::<Vec<_>>();
+            div()
+                .h(cx.line_height)
+                .child(StyledText::new(line.to_string()).with_highlights(&text_style, highlights))
+        });
+        div()
+            .size_full()
+            .pl(cx.gutter_width)
+            .child(
+                v_flex()
+                    .size_full()
+                    .pl(cx.anchor_x - cx.gutter_width)
+                    .bg(cx.theme().status().deleted_background)
+                    .children(lines),
+            )
+            .into_any_element()
+    })
+}
+
+/// Splits text into the tokens compared by [`word_diff`]: runs of word characters, runs of
+/// whitespace, and single punctuation characters.
+fn tokenize(text: &str) -> Vec<Range<usize>> {
+    #[derive(PartialEq)]
+    enum Kind {
+        Word,
+        Whitespace,
+        Punctuation,
+    }
+    let kind = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            Kind::Word
+        } else if c.is_whitespace() {
+            Kind::Whitespace
+        } else {
+            Kind::Punctuation
+        }
+    };
+
+    let mut tokens: Vec<Range<usize>> = Vec::new();
+    let mut previous_kind = None;
+    for (ix, c) in text.char_indices() {
+        let kind = kind(c);
+        let end = ix + c.len_utf8();
+        match tokens.last_mut() {
+            Some(token) if kind != Kind::Punctuation && previous_kind.as_ref() == Some(&kind) => {
+                token.end = end;
+            }
+            _ => tokens.push(ix..end),
+        }
+        previous_kind = Some(kind);
+    }
+    tokens
+}
+
+/// Compares the old and new text of a hunk word by word, returning the ranges of the old text
+/// that were deleted and of the new text that were inserted. When the texts are too large to
+/// compare, nothing is returned.
+fn word_diff(old: &str, new: &str) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let old_tokens = tokenize(old);
+    let new_tokens = tokenize(new);
+    if old_tokens.len() > MAX_COMPARED_TOKENS || new_tokens.len() > MAX_COMPARED_TOKENS {
+        return (Vec::new(), Vec::new());
+    }
+
+    // The length of the longest common subsequence of the tokens after each pair of positions.
+    let width = new_tokens.len() + 1;
+    let mut common = vec![0u32; (old_tokens.len() + 1) * width];
+    for i in (0..old_tokens.len()).rev() {
+        for j in (0..new_tokens.len()).rev() {
+            common[i * width + j] = if old[old_tokens[i].clone()] == new[new_tokens[j].clone()] {
+                common[(i + 1) * width + j + 1] + 1
+            } else {
+                common[(i + 1) * width + j].max(common[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut deleted = Vec::new();
+    let mut inserted = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old_tokens.len() || j < new_tokens.len() {
+        if i < old_tokens.len()
+            && j < new_tokens.len()
+            && old[old_tokens[i].clone()] == new[new_tokens[j].clone()]
+        {
+            i += 1;
+            j += 1;
+        } else if j == new_tokens.len()
+            || (i < old_tokens.len() && common[(i + 1) * width + j] >= common[i * width + j + 1])
+        {
+            push_range(&mut deleted, old_tokens[i].clone());
+            i += 1;
+        } else {
+            push_range(&mut inserted, new_tokens[j].clone());
+            j += 1;
+        }
+    }
+    (deleted, inserted)
+}
+
+/// Adds a range to the given ranges, merging it with the last one if they are adjacent.
+fn push_range(ranges: &mut Vec<Range<usize>>, range: Range<usize>) {
+    match ranges.last_mut() {
+        Some(last) if last.end == range.start => last.end = range.end,
+        _ => ranges.push(range),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+
+    #[test]
+    fn test_word_diff() {
+        let diff = |old: &'static str, new: &'static str| {
+            let (deleted, inserted) = word_diff(old, new);
+            (
+                deleted.into_iter().map(|r| &old[r]).collect::<Vec<_>>(),
+                inserted.into_iter().map(|r| &new[r]).collect::<Vec<_>>(),
+            )
+        };
+
+        assert_eq!(
+            diff("let x = foo(a);\n", "let x = bar(a, b);\n"),
+            (vec!["foo"], vec!["bar", ", b"])
+        );
+        assert_eq!(
+            diff("one two\nthree\n", "one\nthree four\n"),
+            (vec![" two"], vec![" four"])
+        );
+        assert_eq!(diff("same\n", "same\n"), (vec![], vec![]));
+        assert_eq!(diff("", "added\n"), (vec![], vec!["added\n"]));
+    }
+
+    #[gpui::test]
+    async fn test_inline_diff(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state("ˇone\nlet x = bar(a, b);\ntwo\nthree\n");
+        cx.update_editor(|editor, cx| editor.toggle_inline_diff(&ToggleInlineDiff, cx));
+        let removed_lines = "old\n".repeat(300);
+        cx.set_diff_base(Some(&format!(
+            "one\nlet x = foo(a);\ntwo\n{removed_lines}three\n"
+        )));
+        cx.run_until_parked();
+
+        // The words inserted in modified lines are highlighted, and the old lines are shown above
+        // the new ones, in several blocks when there are too many for one.
+        cx.assert_editor_text_highlights::<InlineDiffHighlights>(
+            "one\nlet x = «bar»(a«, b»);\ntwo\nthree\n",
+        );
+        let hunk_blocks = |cx: &mut EditorTestContext| {
+            cx.update_editor(|editor, cx| {
+                let snapshot = editor.snapshot(cx);
+                assert_eq!(
+                    snapshot.display_snapshot.max_point().row(),
+                    snapshot.buffer_snapshot.max_point().row
+                        + editor
+                            .inline_diff
+                            .hunks
+                            .iter()
+                            .map(|hunk| hunk.old_text.lines().count() as u32)
+                            .sum::<u32>()
+                );
+                editor
+                    .inline_diff
+                    .hunks
+                    .iter()
+                    .map(|hunk| hunk.blocks.clone())
+                    .collect::<Vec<_>>()
+            })
+        };
+        let blocks = hunk_blocks(&mut cx);
+        assert_eq!(
+            blocks.iter().map(|blocks| blocks.len()).collect::<Vec<_>>(),
+            [1, 2]
+        );
+
+        // Only the blocks of the hunks that changed are replaced.
+        cx.set_diff_base(Some(&format!(
+            "one\nlet x = baz(a);\ntwo\n{removed_lines}three\n"
+        )));
+        cx.run_until_parked();
+        let new_blocks = hunk_blocks(&mut cx);
+        assert_ne!(new_blocks[0], blocks[0]);
+        assert_eq!(new_blocks[1], blocks[1]);
+        cx.update_editor(|editor, cx| editor.refresh_inline_diff(cx));
+        cx.run_until_parked();
+        assert_eq!(hunk_blocks(&mut cx), new_blocks);
+
+        // Hiding the inline diff removes the blocks and highlights.
+        cx.update_editor(|editor, cx| editor.toggle_inline_diff(&ToggleInlineDiff, cx));
+        assert!(hunk_blocks(&mut cx).is_empty());
+        cx.assert_editor_text_highlights::<InlineDiffHighlights>(
+            "one\nlet x = bar(a, b);\ntwo\nthree\n",
+        );
+    }
+
+    #[test]
+    fn test_split_deleted_lines() {
+        let old_lines = (0..300)
+            .map(|ix| ix.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let deleted_words = [0..3, old_lines.len() - 5..old_lines.len()];
+        let parts = split_deleted_lines(&old_lines, &deleted_words);
+        assert_eq!(
+            parts.iter().map(|part| part.row_count).collect::<Vec<_>>(),
+            [255, 45]
+        );
+        assert_eq!(
+            parts
+                .iter()
+                .map(|part| part.text.lines().count())
+                .collect::<Vec<_>>(),
+            [255, 45]
+        );
+        assert_eq!(parts[0].deleted_words, [0..3]);
+        assert_eq!(&parts[1].text[parts[1].deleted_words[0].clone()], "8\n299");
+    }
+}
@@ -0,0 +1,286 @@
+use crate::{
+    display_map::{
+        BlockContext, BlockDisposition, BlockId, BlockProperties, BlockStyle, RenderBlock,
+    },
+    Anchor, Editor, EditorMode, ToggleReviewMode,
+};
+use collections::HashSet;
+use gpui::ViewContext;
+use language::Point;
+use multi_buffer::{MultiBufferSnapshot, ToPoint};
+use std::{mem, ops::Range, sync::Arc};
+use ui::prelude::*;
+
+/// The number of unchanged lines kept visible above and below each diff hunk.
+const CONTEXT_LINES: u32 = 3;
+
+/// The minimum number of unchanged lines worth folding away.
+const MIN_FOLDED_LINES: u32 = 2;
+
+/// The regions of an editor's buffer that are folded because they aren't near any diff hunk, so
+/// that its changes can be reviewed one after the other.
+pub(crate) struct ReviewMode {
+    regions: Vec<UnchangedRegion>,
+    /// The folds of the regions that were expanded, whose rows stay unfolded when the regions are
+    /// recomputed.
+    expanded: Vec<Range<Anchor>>,
+}
+
+struct UnchangedRegion {
+    fold: Range<Anchor>,
+    block: BlockId,
+}
+
+impl Editor {
+    /// Folds the lines that aren't adjacent to a git diff hunk into "N unchanged lines" blocks,
+    /// or unfolds them again if they are already folded.
+    pub fn toggle_review_mode(&mut self, _: &ToggleReviewMode, cx: &mut ViewContext<Self>) {
+        if let Some(review_mode) = self.review_mode.take() {
+            self.remove_unchanged_regions(review_mode.regions, cx);
+            return;
+        }
+        if self.mode != EditorMode::Full || !self.buffer.read(cx).is_singleton() {
+            return;
+        }
+
+        self.review_mode = Some(ReviewMode {
+            regions: Vec::new(),
+            expanded: Vec::new(),
+        });
+        self.refresh_review_mode(cx);
+    }
+
+    /// Refolds the unchanged regions around the current diff hunks, leaving the ones that were
+    /// expanded unfolded.
+    pub(crate) fn refresh_review_mode(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(review_mode) = self.review_mode.as_mut() else {
+            return;
+        };
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let row_count = snapshot.max_point().row + 1;
+        let hunks = snapshot
+            .git_diff_hunks_in_range(0..row_count)
+            .map(|hunk| hunk.buffer_range);
+        let expanded_rows = review_mode
+            .expanded
+            .iter()
+            .map(|fold| region_rows(fold, &snapshot))
+            .collect::<Vec<_>>();
+        let new_rows = unchanged_row_ranges(hunks, row_count)
+            .into_iter()
+            .filter(|rows| {
+                !expanded_rows
+                    .iter()
+                    .any(|expanded| expanded.start < rows.end && rows.start < expanded.end)
+            })
+            .collect::<Vec<_>>();
+        let old_rows = review_mode
+            .regions
+            .iter()
+            .map(|region| region_rows(&region.fold, &snapshot))
+            .collect::<Vec<_>>();
+        if new_rows == old_rows {
+            return;
+        }
+
+        let old_regions = mem::take(&mut review_mode.regions);
+        self.remove_unchanged_regions(old_regions, cx);
+
+        let mut folds = Vec::new();
+        let mut blocks = Vec::new();
+        for rows in new_rows {
+            // The fold starts at the end of the row before the region, so that none of the
+            // region's rows remain visible, and the block is shown in their place.
+            let start = Point::new(rows.start - 1, snapshot.line_len(rows.start - 1));
+            let end = Point::new(rows.end - 1, snapshot.line_len(rows.end - 1));
+            let fold = snapshot.anchor_before(start)..snapshot.anchor_after(end);
+            blocks.push(BlockProperties {
+                position: fold.start,
+                height: 1,
+                style: BlockStyle::Flexible,
+                render: render_unchanged_region(rows.len() as u32, fold.clone()),
+                disposition: BlockDisposition::Below,
+            });
+            folds.push(fold);
+        }
+
+        self.fold_ranges(folds.clone(), false, cx);
+        let blocks = self.insert_blocks(blocks, None, cx);
+        if let Some(review_mode) = self.review_mode.as_mut() {
+            review_mode.regions = folds
+                .into_iter()
+                .zip(blocks)
+                .map(|(fold, block)| UnchangedRegion { fold, block })
+                .collect();
+        }
+        cx.notify();
+    }
+
+    fn remove_unchanged_regions(
+        &mut self,
+        regions: Vec<UnchangedRegion>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let (folds, blocks): (Vec<_>, HashSet<_>) = regions
+            .into_iter()
+            .map(|region| (region.fold, region.block))
+            .unzip();
+        self.unfold_ranges(folds, true, false, cx);
+        self.remove_blocks(blocks, None, cx);
+    }
+
+    /// Unfolds one of the unchanged regions folded by the review mode.
+    fn expand_unchanged_region(&mut self, fold: &Range<Anchor>, cx: &mut ViewContext<Self>) {
+        let Some(review_mode) = self.review_mode.as_mut() else {
+            return;
+        };
+        let Some(ix) = review_mode
+            .regions
+            .iter()
+            .position(|region| region.fold == *fold)
+        else {
+            return;
+        };
+        let region = review_mode.regions.remove(ix);
+        review_mode.expanded.push(region.fold.clone());
+        self.remove_unchanged_regions(vec![region], cx);
+    }
+}
+
+/// Returns the rows of the region folded by the given fold, which starts at the end of the row
+/// before the region.
+fn region_rows(fold: &Range<Anchor>, snapshot: &MultiBufferSnapshot) -> Range<u32> {
+    fold.start.to_point(snapshot).row + 1..fold.end.to_point(snapshot).row + 1
+}
+
+/// Returns the ranges of rows that are more than `CONTEXT_LINES` away from all of the given
+/// diff hunks, which must be sorted. Rows are expressed as exclusive ranges. The first row is
+/// never included, since a region's fold starts at the end of the row before it.
+fn unchanged_row_ranges(
+    hunks: impl IntoIterator<Item = Range<u32>>,
+    row_count: u32,
+) -> Vec<Range<u32>> {
+    let mut ranges = Vec::new();
+    let mut start = 1;
+    for hunk in hunks {
+        let context_start = hunk.start.saturating_sub(CONTEXT_LINES);
+        if context_start >= start + MIN_FOLDED_LINES {
+            ranges.push(start..context_start);
+        }
+        start = start.max((hunk.end + CONTEXT_LINES).min(row_count));
+    }
+    if row_count >= start + MIN_FOLDED_LINES {
+        ranges.push(start..row_count);
+    }
+    ranges
+}
+
+fn render_unchanged_region(row_count: u32, fold: Range<Anchor>) -> RenderBlock {
+    Arc::new(move |cx: &mut BlockContext| {
+        let view = cx.view.clone();
+        let fold = fold.clone();
+        let label = if row_count == 1 {
+            "1 unchanged line".to_string()
+        } else {
+            format!("{row_count} unchanged lines")
+        };
+        h_flex()
+            .id(cx.block_id)
+            .size_full()
+            .pl(cx.gutter_width)
+            .bg(cx.theme().colors().editor_subheader_background)
+            .child(
+                Button::new("expand-unchanged-region", label)
+                    .icon(IconName::ChevronDown)
+                    .icon_size(IconSize::Small)
+                    .icon_color(Color::Muted)
+                    .label_size(LabelSize::Small)
+                    .color(Color::Muted)
+                    .on_click(cx.listener_for(&view, move |editor, _, cx| {
+                        editor.expand_unchanged_region(&fold, cx)
+                    })),
+            )
+            .into_any_element()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+
+    #[gpui::test]
+    async fn test_review_mode(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state("ˇa\nb\nc\nd\ne\nf\ng\nh\ni\nJ\nk\nl");
+        cx.set_diff_base(Some("a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl"));
+        cx.executor().run_until_parked();
+
+        // The rows far from the hunk are folded after the first row, and replaced by a block.
+        cx.update_editor(|editor, cx| {
+            editor.toggle_review_mode(&ToggleReviewMode, cx);
+            assert_eq!(editor.display_text(cx), "a⋯\n\ng\nh\ni\nJ\nk\nl");
+        });
+
+        // The regions are recomputed when the hunks change.
+        cx.set_diff_base(Some("a\nb\nc\nd\ne\nf\ng\nh\ni\nJ\nk\nx"));
+        cx.executor().run_until_parked();
+        cx.update_editor(|editor, cx| {
+            assert_eq!(editor.display_text(cx), "a⋯\n\ni\nJ\nk\nl");
+
+            // Expanded regions stay expanded when the hunks change again.
+            let fold = editor.review_mode.as_ref().unwrap().regions[0].fold.clone();
+            editor.expand_unchanged_region(&fold, cx);
+            assert_eq!(
+                editor.display_text(cx),
+                "a\nb\nc\nd\ne\nf\ng\nh\ni\nJ\nk\nl"
+            );
+        });
+        cx.set_diff_base(Some("a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl"));
+        cx.executor().run_until_parked();
+        cx.update_editor(|editor, cx| {
+            assert_eq!(
+                editor.display_text(cx),
+                "a\nb\nc\nd\ne\nf\ng\nh\ni\nJ\nk\nl"
+            );
+
+            editor.toggle_review_mode(&ToggleReviewMode, cx);
+            assert_eq!(
+                editor.display_text(cx),
+                "a\nb\nc\nd\ne\nf\ng\nh\ni\nJ\nk\nl"
+            );
+        });
+    }
+
+    #[test]
+    fn test_unchanged_row_ranges() {
+        // Without any hunk, the whole file is unchanged, apart from its first row which is kept
+        // visible.
+        assert_eq!(unchanged_row_ranges([], 30), vec![1..30]);
+        assert_eq!(unchanged_row_ranges([], 2), vec![]);
+
+        // Rows near a hunk stay visible.
+        assert_eq!(unchanged_row_ranges([10..12], 30), vec![1..7, 15..30]);
+        assert_eq!(unchanged_row_ranges([1..2], 30), vec![5..30]);
+        assert_eq!(unchanged_row_ranges([26..28], 30), vec![1..23]);
+
+        // Deleted lines are shown between two rows, which are both kept visible.
+        assert_eq!(unchanged_row_ranges([20..20], 30), vec![1..17, 23..30]);
+
+        // Hunks whose contexts overlap, or are separated by a single line, aren't split.
+        assert_eq!(
+            unchanged_row_ranges([10..12, 17..18], 30),
+            vec![1..7, 21..30]
+        );
+        assert_eq!(
+            unchanged_row_ranges([10..11, 18..19], 30),
+            vec![1..7, 22..30]
+        );
+        assert_eq!(
+            unchanged_row_ranges([10..11, 19..20], 30),
+            vec![1..7, 14..16, 23..30]
+        );
+    }
+}
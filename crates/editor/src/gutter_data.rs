@@ -0,0 +1,326 @@
+use crate::{Editor, EditorSnapshot};
+use anyhow::Result;
+use collections::HashMap;
+use gpui::{AppContext, Hsla, Model, Task, ViewContext};
+use language::Buffer;
+use std::{ops::Range, rc::Rc, time::Duration};
+use util::ResultExt;
+
+/// The number of buffer rows whose gutter data is loaded at once.
+const CHUNK_ROWS: u32 = 64;
+
+/// The number of chunks before and after the shown ones whose data is kept, so that scrolling
+/// back and forth doesn't load it again.
+const KEPT_CHUNKS: u32 = 4;
+
+/// How long after an edit the data made stale by it is loaded again.
+const GUTTER_DATA_DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// Supplies data shown next to the lines in the editor's gutter, such as test results, which may
+/// take a while to compute. The data is loaded for the lines as they're shown, and a placeholder
+/// is shown next to each line until it arrives, so that laying out the editor never waits on it.
+///
+/// Git diff hunks and blame don't go through a provider: the buffer already diffs itself in the
+/// background and its hunks are shown in multi-buffers too, which providers don't support, and
+/// the blame is loaded by its own background task and shown at the ends of lines.
+pub trait GutterDataProvider: 'static {
+    /// Loads the marks shown next to the given rows of the buffer, along with the rows they're
+    /// shown next to. Rows without marks are left out.
+    fn load(
+        &self,
+        buffer: &Model<Buffer>,
+        rows: Range<u32>,
+        cx: &mut AppContext,
+    ) -> Task<Result<Vec<(u32, GutterMark)>>>;
+}
+
+/// The mark a [`GutterDataProvider`] shows next to a line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GutterMark {
+    pub color: Hsla,
+}
+
+/// What's shown for a provider next to a line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum GutterMarkState {
+    Loading,
+    Loaded(GutterMark),
+}
+
+/// The mark shown next to a display row for the provider at the given index.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct GutterMarkLayout {
+    pub row: u32,
+    pub provider_ix: usize,
+    pub state: GutterMarkState,
+}
+
+/// The providers of the editor's gutter data, along with the data loaded from them so far.
+#[derive(Default)]
+pub(crate) struct GutterData {
+    providers: Vec<ProviderState>,
+    /// Incremented whenever the loaded data becomes stale, such as after an edit.
+    generation: usize,
+    /// Whether the stale data is loaded again only once the edits stop.
+    debounce: bool,
+}
+
+impl GutterData {
+    /// Makes the loaded data stale after an edit, so that it's loaded again for the lines as
+    /// they're shown once the edits stop.
+    pub fn invalidate(&mut self) {
+        self.generation += 1;
+        self.debounce = true;
+    }
+}
+
+struct ProviderState {
+    provider: Rc<dyn GutterDataProvider>,
+    chunks: HashMap<u32, GutterDataChunk>,
+}
+
+#[derive(Default)]
+struct GutterDataChunk {
+    /// The marks loaded most recently, which keep being shown while newer ones load.
+    marks: Option<HashMap<u32, GutterMark>>,
+    requested_generation: Option<usize>,
+    _load: Option<Task<()>>,
+}
+
+impl Editor {
+    /// Shows the data supplied by the given provider in the gutter.
+    pub fn add_gutter_data_provider(
+        &mut self,
+        provider: impl GutterDataProvider,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.gutter_data.providers.push(ProviderState {
+            provider: Rc::new(provider),
+            chunks: HashMap::default(),
+        });
+        cx.notify();
+    }
+
+    /// Loads the gutter data of the shown lines again, such as after it changed. The data loaded
+    /// before keeps being shown until then.
+    pub fn refresh_gutter_data(&mut self, cx: &mut ViewContext<Self>) {
+        self.gutter_data.generation += 1;
+        self.gutter_data.debounce = false;
+        cx.notify();
+    }
+
+    /// Returns the marks shown next to the given display rows, loading the ones that weren't
+    /// loaded yet or are stale in the background, and dropping the ones far from the rows.
+    pub(crate) fn gutter_marks(
+        &mut self,
+        rows: Range<u32>,
+        snapshot: &EditorSnapshot,
+        cx: &mut ViewContext<Self>,
+    ) -> Vec<GutterMarkLayout> {
+        if self.gutter_data.providers.is_empty() {
+            return Vec::new();
+        }
+        let Some(buffer) = self.buffer.read(cx).as_singleton() else {
+            return Vec::new();
+        };
+
+        let buffer_rows = snapshot
+            .buffer_rows(rows.start)
+            .take(rows.len())
+            .enumerate()
+            .filter_map(|(ix, buffer_row)| Some((rows.start + ix as u32, buffer_row?)))
+            .collect::<Vec<_>>();
+        let shown_chunks = buffer_rows
+            .iter()
+            .map(|(_, buffer_row)| buffer_row / CHUNK_ROWS)
+            .fold(None, |chunks: Option<Range<u32>>, chunk_ix| match chunks {
+                Some(chunks) => Some(chunks.start.min(chunk_ix)..chunks.end.max(chunk_ix + 1)),
+                None => Some(chunk_ix..chunk_ix + 1),
+            });
+        if let Some(shown_chunks) = shown_chunks {
+            let kept_chunks =
+                shown_chunks.start.saturating_sub(KEPT_CHUNKS)..shown_chunks.end + KEPT_CHUNKS;
+            for provider in &mut self.gutter_data.providers {
+                provider
+                    .chunks
+                    .retain(|chunk_ix, _| kept_chunks.contains(chunk_ix));
+            }
+        }
+
+        let generation = self.gutter_data.generation;
+        let debounce = self.gutter_data.debounce;
+        let mut marks = Vec::new();
+        for provider_ix in 0..self.gutter_data.providers.len() {
+            for &(row, buffer_row) in &buffer_rows {
+                let chunk_ix = buffer_row / CHUNK_ROWS;
+                let provider = &mut self.gutter_data.providers[provider_ix];
+                let chunk = provider.chunks.entry(chunk_ix).or_default();
+                if chunk.requested_generation != Some(generation) {
+                    chunk.requested_generation = Some(generation);
+                    let rows = chunk_ix * CHUNK_ROWS..(chunk_ix + 1) * CHUNK_ROWS;
+                    // Stale data keeps being shown while the edits continue, and replacing the
+                    // task cancels the load that was waiting for them to stop.
+                    let delay = debounce && chunk.marks.is_some();
+                    let provider = provider.provider.clone();
+                    let buffer = buffer.clone();
+                    chunk._load = Some(cx.spawn(|this, mut cx| async move {
+                        if delay {
+                            cx.background_executor()
+                                .timer(GUTTER_DATA_DEBOUNCE_TIMEOUT)
+                                .await;
+                        }
+                        let Ok(load) = cx.update(|cx| provider.load(&buffer, rows, cx)) else {
+                            return;
+                        };
+                        let loaded = load.await.log_err().unwrap_or_default();
+                        this.update(&mut cx, |this, cx| {
+                            let chunk = this.gutter_data.providers[provider_ix]
+                                .chunks
+                                .entry(chunk_ix)
+                                .or_default();
+                            chunk.marks = Some(loaded.into_iter().collect());
+                            cx.notify();
+                        })
+                        .ok();
+                    }));
+                }
+
+                let state = match &chunk.marks {
+                    None => Some(GutterMarkState::Loading),
+                    Some(chunk_marks) => chunk_marks
+                        .get(&buffer_row)
+                        .map(|mark| GutterMarkState::Loaded(*mark)),
+                };
+                if let Some(state) = state {
+                    marks.push(GutterMarkLayout {
+                        row,
+                        provider_ix,
+                        state,
+                    });
+                }
+            }
+        }
+        marks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+    use std::cell::Cell;
+
+    struct EvenRows {
+        loads: Rc<Cell<usize>>,
+    }
+
+    impl GutterDataProvider for EvenRows {
+        fn load(
+            &self,
+            _: &Model<Buffer>,
+            rows: Range<u32>,
+            cx: &mut AppContext,
+        ) -> Task<Result<Vec<(u32, GutterMark)>>> {
+            self.loads.set(self.loads.get() + 1);
+            cx.background_executor().spawn(async move {
+                Ok(rows
+                    .filter(|row| row % 2 == 0)
+                    .map(|row| (row, GutterMark { color: gpui::red() }))
+                    .collect())
+            })
+        }
+    }
+
+    #[gpui::test]
+    async fn test_gutter_data_provider(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state("ˇone\ntwo\nthree\nfour");
+        let loads = Rc::new(Cell::new(0));
+        cx.update_editor(|editor, cx| {
+            editor.add_gutter_data_provider(
+                EvenRows {
+                    loads: loads.clone(),
+                },
+                cx,
+            )
+        });
+        let gutter_marks = |cx: &mut EditorTestContext| {
+            cx.update_editor(|editor, cx| {
+                let snapshot = editor.snapshot(cx);
+                editor
+                    .gutter_marks(0..4, &snapshot, cx)
+                    .into_iter()
+                    .map(|mark| (mark.row, mark.state))
+                    .collect::<Vec<_>>()
+            })
+        };
+        let loaded = GutterMarkState::Loaded(GutterMark { color: gpui::red() });
+
+        // Placeholders are shown until the data arrives.
+        assert_eq!(
+            gutter_marks(&mut cx),
+            (0..4)
+                .map(|row| (row, GutterMarkState::Loading))
+                .collect::<Vec<_>>()
+        );
+        cx.run_until_parked();
+        assert_eq!(gutter_marks(&mut cx), [(0, loaded), (2, loaded)]);
+        assert_eq!(loads.get(), 1);
+
+        // Stale data keeps being shown while it's loaded again.
+        cx.update_editor(|editor, cx| editor.refresh_gutter_data(cx));
+        assert_eq!(gutter_marks(&mut cx), [(0, loaded), (2, loaded)]);
+        cx.run_until_parked();
+        assert_eq!(gutter_marks(&mut cx), [(0, loaded), (2, loaded)]);
+        assert_eq!(loads.get(), 2);
+
+        // After edits, the data is loaded again once they stop.
+        cx.update_buffer(|buffer, cx| buffer.edit([(0..0, "zero\n")], None, cx));
+        gutter_marks(&mut cx);
+        cx.run_until_parked();
+        cx.update_buffer(|buffer, cx| buffer.edit([(0..0, "-1\n")], None, cx));
+        assert_eq!(gutter_marks(&mut cx), [(0, loaded), (2, loaded)]);
+        cx.run_until_parked();
+        assert_eq!(loads.get(), 2);
+        cx.executor().advance_clock(GUTTER_DATA_DEBOUNCE_TIMEOUT);
+        cx.run_until_parked();
+        assert_eq!(loads.get(), 3);
+    }
+
+    #[gpui::test]
+    async fn test_gutter_data_eviction(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state(&format!("ˇ{}", "line\n".repeat(1000)));
+        let loads = Rc::new(Cell::new(0));
+        cx.update_editor(|editor, cx| {
+            editor.add_gutter_data_provider(
+                EvenRows {
+                    loads: loads.clone(),
+                },
+                cx,
+            )
+        });
+        let loaded_chunks = |rows: Range<u32>, cx: &mut EditorTestContext| {
+            cx.update_editor(|editor, cx| {
+                let snapshot = editor.snapshot(cx);
+                editor.gutter_marks(rows, &snapshot, cx);
+                let mut chunks = editor.gutter_data.providers[0]
+                    .chunks
+                    .keys()
+                    .copied()
+                    .collect::<Vec<_>>();
+                chunks.sort();
+                chunks
+            })
+        };
+
+        // The data of the chunks near the shown rows is kept, and the rest is dropped.
+        assert_eq!(loaded_chunks(0..4, &mut cx), [0]);
+        assert_eq!(loaded_chunks(250..260, &mut cx), [0, 3, 4]);
+        assert_eq!(loaded_chunks(900..910, &mut cx), [14]);
+        cx.run_until_parked();
+        assert_eq!(loads.get(), 1);
+    }
+}
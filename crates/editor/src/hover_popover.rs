@@ -1,14 +1,15 @@
 use crate::{
     display_map::{InlayOffset, ToDisplayPoint},
     hover_links::{InlayHighlight, RangeInEditor},
+    image_preview::{image_link_at, ImagePreview, MAX_IMAGE_PREVIEW_SIZE},
     Anchor, AnchorRangeExt, DisplayPoint, Editor, EditorSettings, EditorSnapshot, EditorStyle,
-    ExcerptId, Hover, RangeToAnchorExt,
+    ExcerptId, Hover, NextHoverTab, PreviousHoverTab, RangeToAnchorExt,
 };
 use futures::FutureExt;
 use gpui::{
     div, px, AnyElement, CursorStyle, Hsla, InteractiveElement, IntoElement, Model, MouseButton,
-    ParentElement, Pixels, SharedString, Size, StatefulInteractiveElement, Styled, Task,
-    ViewContext, WeakView,
+    ParentElement, Pixels, SharedString, Size, StatefulInteractiveElement, Styled, Task, View,
+    ViewContext, VisualContext, WeakView,
 };
 use language::{markdown, Bias, DiagnosticEntry, Language, LanguageRegistry, ParsedMarkdown};
 
@@ -25,7 +26,6 @@ pub const HOVER_REQUEST_DELAY_MILLIS: u64 = 200;
 
 pub const MIN_POPOVER_CHARACTER_WIDTH: f32 = 20.;
 pub const MIN_POPOVER_LINE_HEIGHT: Pixels = px(4.);
-pub const HOVER_POPOVER_GAP: Pixels = px(10.);
 
 /// Bindable action which uses the most recent selection head to trigger a hover
 pub fn hover(editor: &mut Editor, _: &Hover, cx: &mut ViewContext<Editor>) {
@@ -33,6 +33,32 @@ pub fn hover(editor: &mut Editor, _: &Hover, cx: &mut ViewContext<Editor>) {
     show_hover(editor, head, true, cx);
 }
 
+/// Shows the next of the popovers hovering the same point.
+pub fn next_hover_tab(editor: &mut Editor, _: &NextHoverTab, cx: &mut ViewContext<Editor>) {
+    cycle_hover_tab(editor, true, cx);
+}
+
+/// Shows the previous of the popovers hovering the same point.
+pub fn previous_hover_tab(editor: &mut Editor, _: &PreviousHoverTab, cx: &mut ViewContext<Editor>) {
+    cycle_hover_tab(editor, false, cx);
+}
+
+fn cycle_hover_tab(editor: &mut Editor, forward: bool, cx: &mut ViewContext<Editor>) {
+    let tab_count = editor.hover_state.tabs().len();
+    if tab_count < 2 {
+        cx.propagate();
+        return;
+    }
+
+    let active_tab = editor.hover_state.active_tab % tab_count;
+    editor.hover_state.active_tab = if forward {
+        (active_tab + 1) % tab_count
+    } else {
+        (active_tab + tab_count - 1) % tab_count
+    };
+    cx.notify();
+}
+
 /// The internal hover action dispatches between `show_hover` or `hide_hover`
 /// depending on whether a point to hover over is provided.
 pub fn hover_at(editor: &mut Editor, point: Option<DisplayPoint>, cx: &mut ViewContext<Editor>) {
@@ -105,12 +131,15 @@ pub fn hover_at_inlay(editor: &mut Editor, inlay_hover: InlayHover, cx: &mut Vie
                 let language_registry = project.update(&mut cx, |p, _| p.languages().clone())?;
                 let blocks = vec![inlay_hover.tooltip];
                 let parsed_content = parse_blocks(&blocks, &language_registry, None).await;
+                let images =
+                    this.update(&mut cx, |this, cx| this.image_previews(&parsed_content, cx))?;
 
                 let hover_popover = InfoPopover {
                     project: project.clone(),
                     symbol_range: RangeInEditor::Inlay(inlay_hover.range.clone()),
                     blocks,
                     parsed_content,
+                    images,
                 };
 
                 this.update(&mut cx, |this, cx| {
@@ -138,10 +167,12 @@ pub fn hover_at_inlay(editor: &mut Editor, inlay_hover: InlayHover, cx: &mut Vie
 /// selections changed.
 pub fn hide_hover(editor: &mut Editor, cx: &mut ViewContext<Editor>) -> bool {
     let did_hide = editor.hover_state.info_popover.take().is_some()
-        | editor.hover_state.diagnostic_popover.take().is_some();
+        | editor.hover_state.diagnostic_popover.take().is_some()
+        | editor.hover_state.image_popover.take().is_some();
 
     editor.hover_state.info_task = None;
     editor.hover_state.triggered_from = None;
+    editor.hover_state.active_tab = 0;
 
     editor.clear_background_highlights::<HoverState>(cx);
 
@@ -228,6 +259,9 @@ fn show_hover(
         }
     }
 
+    // Images linked from the hovered text are previewed along with the other popovers.
+    let image_link = image_link_at(&snapshot.buffer_snapshot, multibuffer_offset, &buffer, cx);
+
     let task = cx.spawn(|this, mut cx| {
         async move {
             // If we need to delay, delay a set amount initially before making the lsp request
@@ -280,12 +314,26 @@ fn show_hover(
                     })
             });
 
-            this.update(&mut cx, |this, _| {
+            this.update(&mut cx, |this, cx| {
                 this.hover_state.diagnostic_popover =
                     local_diagnostic.map(|local_diagnostic| DiagnosticPopover {
                         local_diagnostic,
                         primary_diagnostic,
                     });
+                let sources = this.image_sources(cx).filter(|sources| {
+                    image_link
+                        .as_ref()
+                        .map_or(false, |(_, link)| sources.can_load(link))
+                });
+                this.hover_state.image_popover =
+                    image_link
+                        .zip(sources)
+                        .map(|((range, link), sources)| ImagePopover {
+                            range,
+                            preview: cx.new_view(|cx| {
+                                ImagePreview::new(&link, MAX_IMAGE_PREVIEW_SIZE, sources, cx)
+                            }),
+                        });
             })?;
 
             let hover_result = hover_request.await.ok().flatten();
@@ -311,12 +359,15 @@ fn show_hover(
                     let blocks = hover_result.contents;
                     let language = hover_result.language;
                     let parsed_content = parse_blocks(&blocks, &language_registry, language).await;
+                    let images =
+                        this.update(&mut cx, |this, cx| this.image_previews(&parsed_content, cx))?;
 
                     Some(InfoPopover {
                         project: project.clone(),
                         symbol_range: RangeInEditor::Text(range),
                         blocks,
                         parsed_content,
+                        images,
                     })
                 }
 
@@ -360,6 +411,7 @@ async fn parse_blocks(
     let mut highlights = Vec::new();
     let mut region_ranges = Vec::new();
     let mut regions = Vec::new();
+    let mut images = Vec::new();
 
     for block in blocks {
         match &block.kind {
@@ -377,6 +429,7 @@ async fn parse_blocks(
                     &mut highlights,
                     &mut region_ranges,
                     &mut regions,
+                    &mut images,
                 )
                 .await
             }
@@ -420,6 +473,7 @@ async fn parse_blocks(
         highlights,
         region_ranges,
         regions,
+        images,
     }
 }
 
@@ -427,13 +481,51 @@ async fn parse_blocks(
 pub struct HoverState {
     pub info_popover: Option<InfoPopover>,
     pub diagnostic_popover: Option<DiagnosticPopover>,
+    pub image_popover: Option<ImagePopover>,
     pub triggered_from: Option<Anchor>,
     pub info_task: Option<Task<Option<()>>>,
+    /// The index of the tab to show when several popovers hover the same point.
+    pub active_tab: usize,
+}
+
+/// A tab of the container showing the popovers hovering the same point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum HoverTab {
+    Diagnostic,
+    Documentation,
+    Image,
+}
+
+impl HoverTab {
+    fn label(self) -> &'static str {
+        match self {
+            HoverTab::Diagnostic => "Diagnostic",
+            HoverTab::Documentation => "Documentation",
+            HoverTab::Image => "Image",
+        }
+    }
 }
 
 impl HoverState {
     pub fn visible(&self) -> bool {
-        self.info_popover.is_some() || self.diagnostic_popover.is_some()
+        self.info_popover.is_some()
+            || self.diagnostic_popover.is_some()
+            || self.image_popover.is_some()
+    }
+
+    /// Returns the tabs of the visible popovers, in the order they are shown in.
+    pub(crate) fn tabs(&self) -> Vec<HoverTab> {
+        let mut tabs = Vec::new();
+        if self.diagnostic_popover.is_some() {
+            tabs.push(HoverTab::Diagnostic);
+        }
+        if self.info_popover.is_some() {
+            tabs.push(HoverTab::Documentation);
+        }
+        if self.image_popover.is_some() {
+            tabs.push(HoverTab::Image);
+        }
+        tabs
     }
 
     pub fn render(
@@ -444,7 +536,7 @@ impl HoverState {
         max_size: Size<Pixels>,
         workspace: Option<WeakView<Workspace>>,
         cx: &mut ViewContext<Editor>,
-    ) -> Option<(DisplayPoint, Vec<AnyElement>)> {
+    ) -> Option<(DisplayPoint, AnyElement)> {
         // If there is a diagnostic, position the popovers based on that.
         // Otherwise use the start of the hover range
         let anchor = self
@@ -458,6 +550,11 @@ impl HoverState {
                         RangeInEditor::Text(range) => &range.start,
                         RangeInEditor::Inlay(range) => &range.inlay_position,
                     })
+            })
+            .or_else(|| {
+                self.image_popover
+                    .as_ref()
+                    .map(|image_popover| &image_popover.range.start)
             })?;
         let point = anchor.to_display_point(&snapshot.display_snapshot);
 
@@ -466,25 +563,65 @@ impl HoverState {
             return None;
         }
 
-        let mut elements = Vec::new();
-
-        if let Some(diagnostic_popover) = self.diagnostic_popover.as_ref() {
-            elements.push(diagnostic_popover.render(style, max_size, cx));
-        }
-        if let Some(info_popover) = self.info_popover.as_mut() {
-            elements.push(info_popover.render(style, max_size, workspace, cx));
+        // Only the active popover is rendered, under tabs to switch to the others.
+        let tabs = self.tabs();
+        let active_tab = self.active_tab % tabs.len();
+        let popover = match tabs[active_tab] {
+            HoverTab::Diagnostic => self
+                .diagnostic_popover
+                .as_ref()?
+                .render(style, max_size, cx),
+            HoverTab::Documentation => self
+                .info_popover
+                .as_mut()?
+                .render(style, max_size, workspace, cx),
+            HoverTab::Image => self.image_popover.as_ref()?.render(cx),
+        };
+        if tabs.len() == 1 {
+            return Some((point, popover));
         }
 
-        Some((point, elements))
+        Some((point, render_tabbed_popover(&tabs, active_tab, popover, cx)))
     }
 }
 
+fn render_tabbed_popover(
+    tabs: &[HoverTab],
+    active_tab: usize,
+    popover: AnyElement,
+    cx: &mut ViewContext<Editor>,
+) -> AnyElement {
+    v_flex()
+        .gap_1()
+        // Prevent a mouse move on the tabs from being propagated to the editor,
+        // because that would dismiss the popover.
+        .on_mouse_move(|_, cx| cx.stop_propagation())
+        .child(
+            h_flex()
+                .elevation_2(cx)
+                .p_0p5()
+                .gap_0p5()
+                .children(tabs.iter().enumerate().map(|(ix, tab)| {
+                    Button::new(("hover-tab", ix), tab.label())
+                        .label_size(LabelSize::Small)
+                        .selected(ix == active_tab)
+                        .on_click(cx.listener(move |editor, _, cx| {
+                            editor.hover_state.active_tab = ix;
+                            cx.notify();
+                        }))
+                })),
+        )
+        .child(popover)
+        .into_any_element()
+}
+
 #[derive(Debug, Clone)]
 pub struct InfoPopover {
     pub project: Model<Project>,
     symbol_range: RangeInEditor,
     pub blocks: Vec<HoverBlock>,
     parsed_content: ParsedMarkdown,
+    images: Vec<View<ImagePreview>>,
 }
 
 impl InfoPopover {
@@ -512,6 +649,28 @@ impl InfoPopover {
                 workspace,
                 cx,
             ))
+            .children(self.images.iter().cloned())
+            .into_any_element()
+    }
+}
+
+/// A preview of the image linked from the hovered text.
+#[derive(Debug, Clone)]
+pub struct ImagePopover {
+    range: Range<Anchor>,
+    preview: View<ImagePreview>,
+}
+
+impl ImagePopover {
+    pub fn render(&self, cx: &mut ViewContext<Editor>) -> AnyElement {
+        div()
+            .id("image_popover")
+            .elevation_2(cx)
+            .p_2()
+            // Prevent a mouse move on the popover from being propagated to the editor,
+            // because that would dismiss the popover.
+            .on_mouse_move(|_, cx| cx.stop_propagation())
+            .child(self.preview.clone())
             .into_any_element()
     }
 }
@@ -617,10 +776,98 @@ mod tests {
     use language::{language_settings::InlayHintSettings, Diagnostic, DiagnosticSet};
     use lsp::LanguageServerId;
     use project::{HoverBlock, HoverBlockKind};
+    use settings::SettingsStore;
     use smol::stream::StreamExt;
     use unindent::Unindent;
     use util::test::marked_text_ranges;
 
+    #[gpui::test]
+    async fn test_mouse_hover_image_link(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                hover_provider: Some(lsp::HoverProviderCapability::Simple(true)),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+
+        cx.set_state(indoc! {"
+            ˇconst LOGO: &str = \"/assets/logo.png\";
+        "});
+        let hover_point = cx.display_point(indoc! {"
+            const LOGO: &str = \"/assets/loˇgo.png\";
+        "});
+        cx.update_editor(|editor, cx| hover_at(editor, Some(hover_point), cx));
+
+        let mut requests = cx
+            .handle_request::<lsp::request::HoverRequest, _, _>(|_, _, _| async move { Ok(None) });
+        cx.background_executor
+            .advance_clock(Duration::from_millis(HOVER_DELAY_MILLIS + 100));
+        requests.next().await;
+        cx.run_until_parked();
+
+        // Hovering a link to an image previews it, even without hover information.
+        cx.editor(|editor, _| {
+            assert_eq!(editor.hover_state.tabs(), [HoverTab::Image]);
+        });
+        cx.update_editor(|editor, cx| {
+            let buffer = editor.buffer.read(cx).snapshot(cx);
+            let range = editor
+                .hover_state
+                .image_popover
+                .as_ref()
+                .unwrap()
+                .range
+                .clone();
+            assert_eq!(
+                buffer.text_for_range(range).collect::<String>(),
+                "/assets/logo.png"
+            );
+        });
+
+        // Hovering other text doesn't.
+        let hover_point = cx.display_point(indoc! {"
+            const LOˇGO: &str = \"/assets/logo.png\";
+        "});
+        cx.update_editor(|editor, cx| hover_at(editor, Some(hover_point), cx));
+        cx.background_executor
+            .advance_clock(Duration::from_millis(HOVER_DELAY_MILLIS + 100));
+        requests.next().await;
+        cx.run_until_parked();
+        cx.editor(|editor, _| assert!(editor.hover_state.image_popover.is_none()));
+
+        // Images on the web are only previewed when opted into.
+        cx.set_state(indoc! {"
+            ˇconst LOGO: &str = \"https://example.com/logo.png\";
+        "});
+        let hover_point = cx.display_point(indoc! {"
+            const LOGO: &str = \"https://example.com/loˇgo.png\";
+        "});
+        for preview_remote_images in [false, true] {
+            cx.update(|cx| {
+                cx.update_global::<SettingsStore, _>(|settings, cx| {
+                    settings.update_user_settings::<EditorSettings>(cx, |settings| {
+                        settings.preview_remote_images = Some(preview_remote_images);
+                    });
+                })
+            });
+            cx.update_editor(|editor, cx| hover_at(editor, Some(hover_point), cx));
+            cx.background_executor
+                .advance_clock(Duration::from_millis(HOVER_DELAY_MILLIS + 100));
+            requests.next().await;
+            cx.run_until_parked();
+            cx.editor(|editor, _| {
+                assert_eq!(
+                    editor.hover_state.image_popover.is_some(),
+                    preview_remote_images
+                )
+            });
+        }
+    }
+
     #[gpui::test]
     async fn test_mouse_hover_info_popover(cx: &mut gpui::TestAppContext) {
         init_test(cx, |_| {});
@@ -914,6 +1161,78 @@ mod tests {
         });
     }
 
+    #[gpui::test]
+    async fn test_hover_tabs(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                hover_provider: Some(lsp::HoverProviderCapability::Simple(true)),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+
+        cx.set_state(indoc! {"
+            fn teˇst() { println!(); }
+        "});
+        let range = cx.text_anchor_range(indoc! {"
+            fn «test»() { println!(); }
+        "});
+        cx.update_buffer(|buffer, cx| {
+            let snapshot = buffer.text_snapshot();
+            let set = DiagnosticSet::from_sorted_entries(
+                vec![DiagnosticEntry {
+                    range,
+                    diagnostic: Diagnostic {
+                        message: "A test diagnostic message.".to_string(),
+                        ..Default::default()
+                    },
+                }],
+                &snapshot,
+            );
+            buffer.update_diagnostics(LanguageServerId(0), set, cx);
+        });
+
+        cx.update_editor(|editor, cx| hover(editor, &Hover, cx));
+        let range = cx.lsp_range(indoc! {"
+            fn «test»() { println!(); }
+        "});
+        cx.handle_request::<lsp::request::HoverRequest, _, _>(move |_, _, _| async move {
+            Ok(Some(lsp::Hover {
+                contents: lsp::HoverContents::Markup(lsp::MarkupContent {
+                    kind: lsp::MarkupKind::Markdown,
+                    value: "some docs".to_string(),
+                }),
+                range: Some(range),
+            }))
+        })
+        .next()
+        .await;
+        cx.condition(|editor, _| editor.hover_state.info_popover.is_some())
+            .await;
+
+        // The diagnostic and the docs are shown in tabs, starting with the diagnostic.
+        cx.update_editor(|editor, cx| {
+            assert_eq!(
+                editor.hover_state.tabs(),
+                vec![HoverTab::Diagnostic, HoverTab::Documentation]
+            );
+            assert_eq!(editor.hover_state.active_tab, 0);
+
+            next_hover_tab(editor, &NextHoverTab, cx);
+            assert_eq!(editor.hover_state.active_tab, 1);
+            next_hover_tab(editor, &NextHoverTab, cx);
+            assert_eq!(editor.hover_state.active_tab, 0);
+            previous_hover_tab(editor, &PreviousHoverTab, cx);
+            assert_eq!(editor.hover_state.active_tab, 1);
+
+            hide_hover(editor, cx);
+            assert_eq!(editor.hover_state.active_tab, 0);
+        });
+    }
+
     #[gpui::test]
     fn test_render_blocks(cx: &mut gpui::TestAppContext) {
         init_test(cx, |_| {});
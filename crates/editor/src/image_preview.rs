@@ -0,0 +1,590 @@
+use crate::{
+    display_map::{BlockContext, BlockDisposition, BlockId, BlockStyle},
+    render_parsed_markdown, Anchor, AnchorRangeExt, BlockDecoration, BlockDecorationEvent, Editor,
+    EditorSettings, ToggleImagePreview,
+};
+use anyhow::{anyhow, Context as _, Result};
+use collections::HashSet;
+use futures::AsyncReadExt as _;
+use gpui::{
+    img, px, AppContext, EntityId, ImageData, ImageSource, IntoElement, Model, ParentElement,
+    Pixels, Render, Size, Styled, Task, View, ViewContext, VisualContext,
+};
+use language::{markdown::Link, Buffer, ParsedMarkdown, Point};
+use multi_buffer::{MultiBufferSnapshot, ToPoint};
+use project::Fs;
+use settings::Settings;
+use std::{
+    cell::Cell,
+    io::Read as _,
+    ops::Range,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Arc,
+};
+use ui::{prelude::*, Label};
+use util::http::{AsyncBody, HttpClient};
+
+/// The largest image file that's previewed, since its pixels are kept in memory while it's shown,
+/// and the most that's downloaded of an image on the web.
+const MAX_IMAGE_FILE_SIZE: u64 = 20 * 1024 * 1024;
+
+/// The largest size images are previewed at, so that large images don't cover the editor.
+/// Smaller images are shown at their own size.
+pub(crate) const MAX_IMAGE_PREVIEW_SIZE: Size<Pixels> = Size {
+    width: px(480.),
+    height: px(320.),
+};
+
+const IMAGE_EXTENSIONS: &[&str] = &["bmp", "gif", "jpeg", "jpg", "png", "tif", "tiff", "webp"];
+
+/// Where the images shown in previews are loaded from. Images on the web are only downloaded when
+/// an HTTP client is given, which the `preview_remote_images` setting opts into.
+#[derive(Clone)]
+pub(crate) struct ImageSources {
+    fs: Arc<dyn Fs>,
+    http_client: Option<Arc<dyn HttpClient>>,
+}
+
+impl ImageSources {
+    pub(crate) fn can_load(&self, link: &Link) -> bool {
+        match link {
+            Link::Path { .. } => true,
+            Link::Web { .. } => self.http_client.is_some(),
+        }
+    }
+}
+
+/// A block previewing the image linked from the text in the given range.
+pub(crate) struct ImagePreviewBlock {
+    range: Range<Anchor>,
+    block_id: BlockId,
+    preview_id: EntityId,
+}
+
+/// A preview of an image, which is loaded in the background.
+pub struct ImagePreview {
+    state: ImagePreviewState,
+    max_size: Size<Pixels>,
+    _load: Task<()>,
+}
+
+enum ImagePreviewState {
+    Loading,
+    Loaded(Arc<ImageData>),
+    Failed(SharedString),
+}
+
+impl ImagePreview {
+    /// Starts loading the image the given link points to, which is shown at most at the given
+    /// size.
+    pub(crate) fn new(
+        link: &Link,
+        max_size: Size<Pixels>,
+        sources: ImageSources,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let link = link.clone();
+        let load = cx.spawn(|this, mut cx| async move {
+            let image = async {
+                let bytes = cx
+                    .background_executor()
+                    .spawn(load_image(link, sources))
+                    .await?;
+                cx.update(|cx| cx.decode_image(bytes))?.await
+            };
+            let state = match image.await {
+                Ok(image) => ImagePreviewState::Loaded(image),
+                Err(error) => ImagePreviewState::Failed(format!("{error}").into()),
+            };
+            this.update(&mut cx, |this, cx| {
+                this.state = state;
+                cx.notify();
+            })
+            .ok();
+        });
+
+        Self {
+            state: ImagePreviewState::Loading,
+            max_size,
+            _load: load,
+        }
+    }
+}
+
+/// Reads the encoded image the given link points to, failing if it's larger than
+/// [`MAX_IMAGE_FILE_SIZE`].
+async fn load_image(link: Link, sources: ImageSources) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    match link {
+        Link::Path { path } => {
+            let file = sources.fs.open_sync(&path).await?;
+            file.take(MAX_IMAGE_FILE_SIZE + 1).read_to_end(&mut bytes)?;
+        }
+        Link::Web { url } => {
+            let http_client = sources
+                .http_client
+                .context("Images on the web aren't previewed")?;
+            let mut response = http_client.get(&url, AsyncBody::empty(), true).await?;
+            if !response.status().is_success() {
+                return Err(anyhow!(
+                    "Failed to download the image: {}",
+                    response.status()
+                ));
+            }
+            response
+                .body_mut()
+                .take(MAX_IMAGE_FILE_SIZE + 1)
+                .read_to_end(&mut bytes)
+                .await?;
+        }
+    }
+    if bytes.len() as u64 > MAX_IMAGE_FILE_SIZE {
+        return Err(anyhow!("The image is too large to preview"));
+    }
+    Ok(bytes)
+}
+
+impl Render for ImagePreview {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        match &self.state {
+            ImagePreviewState::Loading => div().child(
+                Label::new("Loading image…")
+                    .size(LabelSize::Small)
+                    .color(Color::Muted),
+            ),
+            ImagePreviewState::Loaded(image) => {
+                let image_size = image
+                    .size()
+                    .map(|dimension| px(u32::from(dimension) as f32 / cx.scale_factor()));
+                let preview_size = fit_size(image_size, self.max_size);
+                div().child(
+                    img(ImageSource::Data(image.clone()))
+                        .w(preview_size.width)
+                        .h(preview_size.height),
+                )
+            }
+            ImagePreviewState::Failed(error) => div().child(
+                Label::new(error.clone())
+                    .size(LabelSize::Small)
+                    .color(Color::Error),
+            ),
+        }
+    }
+}
+
+/// Scales the given size down to fit within the maximum size, keeping its aspect ratio.
+fn fit_size(size: Size<Pixels>, max_size: Size<Pixels>) -> Size<Pixels> {
+    let scale = (max_size.width / size.width)
+        .min(max_size.height / size.height)
+        .min(1.);
+    if scale.is_finite() {
+        gpui::size(size.width * scale, size.height * scale)
+    } else {
+        gpui::size(Pixels::ZERO, Pixels::ZERO)
+    }
+}
+
+/// Returns the range of the link to an image around the given column of the line, such as the
+/// target of a Markdown image or a path in a string.
+pub(crate) fn image_link_in_line(line: &str, column: usize) -> Option<Range<usize>> {
+    let is_delimiter = |c: char| {
+        c.is_whitespace() || matches!(c, '(' | ')' | '[' | ']' | '<' | '>' | '"' | '\'' | '`')
+    };
+    let column = column.min(line.len());
+    let start = line[..column]
+        .rfind(is_delimiter)
+        .map_or(0, |ix| ix + line[ix..].chars().next().unwrap().len_utf8());
+    let end = line[column..]
+        .find(is_delimiter)
+        .map_or(line.len(), |ix| column + ix);
+    let link = &line[start..end];
+
+    // Query strings and fragments of URLs don't change the kind of image they point to.
+    let path = link.split(['?', '#']).next().unwrap_or_default();
+    let extension = Path::new(path).extension()?.to_str()?.to_lowercase();
+    IMAGE_EXTENSIONS
+        .contains(&extension.as_str())
+        .then_some(start..end)
+}
+
+/// Resolves the link to an image found in a file, resolving relative paths from the file's
+/// directory.
+pub(crate) fn resolve_image_link(link: &str, base_dir: Option<&Path>) -> Option<Link> {
+    if link.starts_with("http://") || link.starts_with("https://") {
+        return Some(Link::Web {
+            url: link.to_string(),
+        });
+    }
+
+    let path = PathBuf::from(link.strip_prefix("file://").unwrap_or(link));
+    if path.is_absolute() {
+        Some(Link::Path { path })
+    } else {
+        Some(Link::Path {
+            path: base_dir?.join(path),
+        })
+    }
+}
+
+/// Returns the link to an image around the given offset, along with its range.
+pub(crate) fn image_link_at(
+    snapshot: &MultiBufferSnapshot,
+    offset: usize,
+    buffer: &Model<Buffer>,
+    cx: &AppContext,
+) -> Option<(Range<Anchor>, Link)> {
+    let point = offset.to_point(snapshot);
+    let line_start = Point::new(point.row, 0);
+    let line = snapshot
+        .text_for_range(line_start..Point::new(point.row, snapshot.line_len(point.row)))
+        .collect::<String>();
+    let range = image_link_in_line(&line, point.column as usize)?;
+
+    let file_path = buffer
+        .read(cx)
+        .file()
+        .and_then(|file| Some(file.as_local()?.abs_path(cx)));
+    let base_dir = file_path.as_deref().and_then(Path::parent);
+    let link = resolve_image_link(&line[range.clone()], base_dir)?;
+    let start = snapshot.anchor_after(Point::new(point.row, range.start as u32));
+    let end = snapshot.anchor_before(Point::new(point.row, range.end as u32));
+    Some((start..end, link))
+}
+
+impl Editor {
+    /// Returns where the images previewed in the editor are loaded from, if it belongs to a
+    /// project.
+    pub(crate) fn image_sources(&self, cx: &AppContext) -> Option<ImageSources> {
+        let project = self.project.as_ref()?.read(cx);
+        let http_client = EditorSettings::get_global(cx)
+            .preview_remote_images
+            .then(|| Arc::new(project.client().http_client()) as Arc<dyn HttpClient>);
+        Some(ImageSources {
+            fs: project.fs().clone(),
+            http_client,
+        })
+    }
+
+    /// Starts loading the images embedded in the given Markdown, skipping the images on the web
+    /// unless they're opted into.
+    pub(crate) fn image_previews(
+        &self,
+        markdown: &ParsedMarkdown,
+        cx: &mut ViewContext<Self>,
+    ) -> Vec<View<ImagePreview>> {
+        let Some(sources) = self.image_sources(cx) else {
+            return Vec::new();
+        };
+        markdown
+            .images
+            .iter()
+            .filter(|link| sources.can_load(link))
+            .map(|link| {
+                cx.new_view(|cx| {
+                    ImagePreview::new(link, MAX_IMAGE_PREVIEW_SIZE, sources.clone(), cx)
+                })
+            })
+            .collect()
+    }
+
+    /// Shows a preview of the image linked from the text under the newest cursor below its line,
+    /// or hides the preview shown for it.
+    pub fn toggle_image_preview(&mut self, _: &ToggleImagePreview, cx: &mut ViewContext<Self>) {
+        let Some(buffer) = self.buffer.read(cx).as_singleton() else {
+            return;
+        };
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let offset = self.selections.newest::<usize>(cx).head();
+        let Some((range, link)) = image_link_at(&snapshot, offset, &buffer, cx) else {
+            return;
+        };
+
+        let existing_preview = self
+            .image_preview_blocks
+            .iter()
+            .position(|block| block.range.cmp(&range, &snapshot).is_eq());
+        if let Some(ix) = existing_preview {
+            let block_id = self.image_preview_blocks[ix].block_id;
+            self.remove_block_decorations(HashSet::from_iter([block_id]), cx);
+        } else {
+            self.insert_image_preview(range, &link, cx);
+        }
+    }
+
+    /// Shows a preview of the image the given link points to below the line of the link, unless
+    /// the editor can't load the image.
+    fn insert_image_preview(
+        &mut self,
+        range: Range<Anchor>,
+        link: &Link,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<BlockId> {
+        let sources = self
+            .image_sources(cx)
+            .filter(|sources| sources.can_load(link))?;
+        let preview =
+            cx.new_view(|cx| ImagePreview::new(link, MAX_IMAGE_PREVIEW_SIZE, sources, cx));
+        let preview_id = preview.entity_id();
+        let decoration = BlockDecoration {
+            position: range.end,
+            disposition: BlockDisposition::Below,
+            style: BlockStyle::Sticky,
+            height: 1,
+            fit_content: true,
+            render: Arc::new(move |cx: &mut BlockContext| {
+                div()
+                    .pl(cx.gutter_width)
+                    .py_1()
+                    .child(preview.clone())
+                    .into_any_element()
+            }),
+            // Deleting the link removes its preview.
+            on_event: Some(Rc::new(
+                move |event: BlockDecorationEvent,
+                      editor: &mut Editor,
+                      cx: &mut ViewContext<Editor>| match event {
+                    BlockDecorationEvent::Hidden => editor.remove_image_preview(preview_id, cx),
+                    BlockDecorationEvent::Removed => editor
+                        .image_preview_blocks
+                        .retain(|block| block.preview_id != preview_id),
+                    BlockDecorationEvent::Resized { .. } | BlockDecorationEvent::Shown => {}
+                },
+            )),
+        };
+        let block_id = self.insert_block_decorations([decoration], cx)[0];
+        self.image_preview_blocks.push(ImagePreviewBlock {
+            range,
+            block_id,
+            preview_id,
+        });
+        Some(block_id)
+    }
+
+    /// Shows the given Markdown below the given position, along with the images it embeds. The
+    /// block is removed when the text it's anchored to is deleted.
+    pub fn insert_markdown_preview(
+        &mut self,
+        position: Anchor,
+        markdown: ParsedMarkdown,
+        cx: &mut ViewContext<Self>,
+    ) -> BlockId {
+        let images = self.image_previews(&markdown, cx);
+        let workspace = self
+            .workspace
+            .as_ref()
+            .map(|(workspace, _)| workspace.clone());
+        let inserted_block_id = Rc::new(Cell::new(None));
+        let decoration = BlockDecoration {
+            position,
+            disposition: BlockDisposition::Below,
+            style: BlockStyle::Sticky,
+            height: 1,
+            fit_content: true,
+            render: Arc::new(move |cx: &mut BlockContext| {
+                let editor_style = cx.editor_style;
+                let markdown = render_parsed_markdown(
+                    ("markdown-preview", cx.block_id),
+                    &markdown,
+                    editor_style,
+                    workspace.clone(),
+                    cx,
+                );
+                v_flex()
+                    .pl(cx.gutter_width)
+                    .py_1()
+                    .gap_1()
+                    .child(markdown)
+                    .children(images.iter().cloned())
+                    .into_any_element()
+            }),
+            on_event: Some(Rc::new({
+                let inserted_block_id = inserted_block_id.clone();
+                move |event: BlockDecorationEvent,
+                      editor: &mut Editor,
+                      cx: &mut ViewContext<Editor>| {
+                    if let (BlockDecorationEvent::Hidden, Some(block_id)) =
+                        (event, inserted_block_id.get())
+                    {
+                        editor.remove_block_decorations(HashSet::from_iter([block_id]), cx);
+                    }
+                }
+            })),
+        };
+        let block_id = self.insert_block_decorations([decoration], cx)[0];
+        inserted_block_id.set(Some(block_id));
+        block_id
+    }
+
+    fn remove_image_preview(&mut self, preview_id: EntityId, cx: &mut ViewContext<Self>) {
+        let block_ids = self
+            .image_preview_blocks
+            .iter()
+            .filter(|block| block.preview_id == preview_id)
+            .map(|block| block.block_id)
+            .collect::<HashSet<_>>();
+        self.remove_block_decorations(block_ids, cx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+    use gpui::{size, TestAppContext};
+    use language::{markdown::parse_markdown, LanguageRegistry};
+    use project::RealFs;
+
+    /// A PNG image of a single transparent pixel.
+    const PIXEL_PNG: &[u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f,
+        0x15, 0xc4, 0x89, 0x00, 0x00, 0x00, 0x0a, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0x00,
+        0x01, 0x00, 0x00, 0x05, 0x00, 0x01, 0x0d, 0x0a, 0x2d, 0xb4, 0x00, 0x00, 0x00, 0x00, 0x49,
+        0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+
+    #[gpui::test]
+    async fn test_image_preview_loading(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("pixel.png"), PIXEL_PNG).unwrap();
+        std::fs::write(dir.path().join("text.png"), "not an image").unwrap();
+        let sources = ImageSources {
+            fs: Arc::new(RealFs),
+            http_client: None,
+        };
+
+        let preview = |link: Link, cx: &mut TestAppContext| {
+            let sources = sources.clone();
+            let (preview, cx) = cx.add_window_view(|cx| {
+                ImagePreview::new(&link, MAX_IMAGE_PREVIEW_SIZE, sources, cx)
+            });
+            cx.run_until_parked();
+            preview.update(cx, |preview, _| match &preview.state {
+                ImagePreviewState::Loading => Err("loading".into()),
+                ImagePreviewState::Loaded(image) => Ok(image.size()),
+                ImagePreviewState::Failed(error) => Err(error.clone()),
+            })
+        };
+        let path = |name: &str| Link::Path {
+            path: dir.path().join(name),
+        };
+
+        assert_eq!(preview(path("pixel.png"), cx), Ok(size(1.into(), 1.into())));
+        assert!(preview(path("text.png"), cx).is_err());
+        assert!(preview(path("missing.png"), cx).is_err());
+
+        // Images on the web are only downloaded when opted into.
+        let link = Link::Web {
+            url: "https://example.com/logo.png".into(),
+        };
+        assert_eq!(
+            preview(link, cx),
+            Err("Images on the web aren't previewed".into())
+        );
+    }
+
+    #[gpui::test]
+    async fn test_toggle_image_preview(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        let fs =
+            cx.update_editor(|editor, cx| editor.project.as_ref().unwrap().read(cx).fs().clone());
+        fs.as_fake()
+            .insert_file("/root/logo.png", "not an image".into())
+            .await;
+        cx.set_state("![logo](loˇgo.png)\n![remote](https://example.com/remote.png)\n");
+
+        // Toggling the preview of the image under the cursor shows it in a block, and hides it.
+        let preview_count = |cx: &mut EditorTestContext| {
+            cx.update_editor(|editor, _| editor.image_preview_blocks.len())
+        };
+        cx.dispatch_action(ToggleImagePreview);
+        cx.run_until_parked();
+        assert_eq!(preview_count(&mut cx), 1);
+        cx.dispatch_action(ToggleImagePreview);
+        cx.run_until_parked();
+        assert_eq!(preview_count(&mut cx), 0);
+
+        // Deleting the link removes its preview.
+        cx.dispatch_action(ToggleImagePreview);
+        cx.run_until_parked();
+        assert_eq!(preview_count(&mut cx), 1);
+        cx.set_state("ˇ\n![remote](https://example.com/remote.png)\n");
+        cx.run_until_parked();
+        assert_eq!(preview_count(&mut cx), 0);
+
+        // Images on the web aren't previewed unless opted into.
+        cx.set_selections_state("\n![remote](https://example.com/remˇote.png)\n");
+        cx.dispatch_action(ToggleImagePreview);
+        cx.run_until_parked();
+        assert_eq!(preview_count(&mut cx), 0);
+    }
+
+    #[gpui::test]
+    async fn test_markdown_preview(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state("ˇone\ntwo\nthree");
+        let markdown = parse_markdown(
+            "Rendered **bold** text",
+            &Arc::new(LanguageRegistry::test()),
+            None,
+        )
+        .await;
+
+        // The Markdown is shown in a block below its position.
+        let block_id = cx.update_editor(|editor, cx| {
+            let buffer = editor.buffer.read(cx).snapshot(cx);
+            let block_id =
+                editor.insert_markdown_preview(buffer.anchor_after(Point::new(1, 0)), markdown, cx);
+            assert!(editor.block_decorations.fits_content(block_id));
+            assert_eq!(editor.snapshot(cx).display_snapshot.max_point().row(), 3);
+            block_id
+        });
+
+        // Deleting the text the preview is anchored to removes it.
+        cx.set_state("ˇone\nthree");
+        cx.run_until_parked();
+        cx.update_editor(|editor, cx| {
+            assert!(!editor.block_decorations.fits_content(block_id));
+            assert_eq!(editor.snapshot(cx).display_snapshot.max_point().row(), 1);
+        });
+    }
+
+    #[test]
+    fn test_image_link_in_line() {
+        let line = "See ![the logo](assets/logo.png) or \"https://example.com/a.JPG?raw=1\".";
+        let link = |column| image_link_in_line(line, column).map(|range| &line[range]);
+        assert_eq!(link(16), Some("assets/logo.png"));
+        assert_eq!(link(31), Some("assets/logo.png"));
+        assert_eq!(link(45), Some("https://example.com/a.JPG?raw=1"));
+        assert_eq!(link(6), None);
+        assert_eq!(image_link_in_line("let x = a.rs;", 9), None);
+
+        assert_eq!(
+            resolve_image_link("assets/logo.png", Some(Path::new("/repo/docs"))),
+            Some(Link::Path {
+                path: PathBuf::from("/repo/docs/assets/logo.png")
+            })
+        );
+        assert_eq!(resolve_image_link("assets/logo.png", None), None);
+    }
+
+    #[test]
+    fn test_fit_size() {
+        let max_size = size(px(400.), px(300.));
+        assert_eq!(
+            fit_size(size(px(100.), px(50.)), max_size),
+            size(px(100.), px(50.))
+        );
+        assert_eq!(
+            fit_size(size(px(800.), px(200.)), max_size),
+            size(px(400.), px(100.))
+        );
+        assert_eq!(
+            fit_size(size(px(300.), px(600.)), max_size),
+            size(px(150.), px(300.))
+        );
+    }
+}
@@ -0,0 +1,324 @@
+use crate::{
+    selection_end_row, ConvertIndentationToSpaces, ConvertIndentationToTabs, Editor, EditorMode,
+    Reindent,
+};
+use collections::BTreeSet;
+use gpui::{AppContext, ViewContext};
+use language::{
+    language_settings::language_settings, BufferSnapshot, IndentKind, IndentSize, Point, ToPoint,
+};
+use multi_buffer::{MultiBuffer, MultiBufferSnapshot};
+use std::ops::Range;
+
+impl Editor {
+    /// The indentation detected from the contents of the buffer, or that it was converted to, if
+    /// the editor shows a single buffer.
+    pub fn indent_size(&self, cx: &AppContext) -> Option<IndentSize> {
+        self.buffer
+            .read(cx)
+            .as_singleton()?
+            .read(cx)
+            .indent_size_override()
+    }
+
+    /// Makes the buffer of a full editor use the indentation detected from the contents of its
+    /// file, unless it already uses a specific indentation.
+    pub(crate) fn detect_indentation(&mut self, cx: &mut ViewContext<Self>) {
+        if self.mode != EditorMode::Full {
+            return;
+        }
+        let Some(buffer) = self.buffer.read(cx).as_singleton() else {
+            return;
+        };
+        buffer.update(cx, |buffer, cx| {
+            let detect = buffer.file().is_some()
+                && buffer.indent_size_override().is_none()
+                && language_settings(buffer.language(), buffer.file(), cx).detect_indentation;
+            if detect {
+                let indent_size = buffer.snapshot().detect_indent_size();
+                buffer.set_indent_size_override(indent_size, cx);
+            }
+        });
+    }
+
+    /// Recomputes the indentation of the selected lines from the indentation rules of their
+    /// language.
+    pub fn reindent(&mut self, _: &Reindent, cx: &mut ViewContext<Self>) {
+        if self.read_only(cx) {
+            return;
+        }
+
+        let buffer = self.buffer.read(cx).snapshot(cx);
+        let ranges = self
+            .selections
+            .all::<Point>(cx)
+            .into_iter()
+            .map(|selection| {
+                let end_row = selection_end_row(&selection);
+                let start = Point::new(selection.start.row, 0);
+                let end = Point::new(end_row, buffer.line_len(end_row));
+                buffer.point_to_offset(start)..buffer.point_to_offset(end)
+            })
+            .collect::<Vec<_>>();
+        self.transact(cx, |this, cx| {
+            this.buffer
+                .update(cx, |buffer, cx| buffer.autoindent_ranges(&ranges, cx));
+        });
+    }
+
+    pub fn convert_indentation_to_spaces(
+        &mut self,
+        _: &ConvertIndentationToSpaces,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.convert_indentation(IndentKind::Space, cx);
+    }
+
+    pub fn convert_indentation_to_tabs(
+        &mut self,
+        _: &ConvertIndentationToTabs,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.convert_indentation(IndentKind::Tab, cx);
+    }
+
+    /// Rewrites the leading whitespace of the selected lines, or of the whole buffer when nothing
+    /// is selected, to use the given kind of indentation, and makes the buffers use it from then
+    /// on.
+    fn convert_indentation(&mut self, kind: IndentKind, cx: &mut ViewContext<Self>) {
+        if self.read_only(cx) {
+            return;
+        }
+
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let selections = self.selections.all::<Point>(cx);
+        let ranges = if selections.iter().all(|selection| selection.is_empty()) {
+            vec![0..snapshot.len()]
+        } else {
+            selections
+                .iter()
+                .map(|selection| {
+                    let end_row = selection_end_row(selection);
+                    let start = Point::new(selection.start.row, 0);
+                    let end = Point::new(end_row, snapshot.line_len(end_row));
+                    snapshot.point_to_offset(start)..snapshot.point_to_offset(end)
+                })
+                .collect()
+        };
+        let ranges_by_buffer = self.buffer.read(cx).buffer_ranges_by_buffer(&ranges, cx);
+
+        self.transact(cx, |_, cx| {
+            for (buffer, ranges) in ranges_by_buffer {
+                buffer.update(cx, |buffer, cx| {
+                    let snapshot = buffer.snapshot();
+                    let tab_size = language_settings(snapshot.language(), snapshot.file(), cx)
+                        .tab_size
+                        .get();
+                    let mut rows = BTreeSet::new();
+                    for range in ranges {
+                        let range = range.to_point(&snapshot);
+                        rows.extend(range.start.row..=range.end.row);
+                    }
+                    let edits = rows
+                        .into_iter()
+                        .filter_map(|row| {
+                            let (range, new_indent) =
+                                converted_indent(&snapshot, row, kind, tab_size);
+                            let old_indent = snapshot.text_for_range(range.clone());
+                            (old_indent.collect::<String>() != new_indent)
+                                .then_some((range, new_indent))
+                        })
+                        .collect::<Vec<_>>();
+                    buffer.edit(edits, None, cx);
+
+                    let indent_size = match kind {
+                        IndentKind::Space => IndentSize::spaces(tab_size),
+                        IndentKind::Tab => IndentSize::tab(),
+                    };
+                    buffer.set_indent_size_override(Some(indent_size), cx);
+                });
+            }
+        });
+    }
+}
+
+/// Returns the unit of indentation at the given position, which is the indentation of its buffer
+/// when it was detected or converted, and comes from the language settings otherwise.
+pub(crate) fn indent_unit_at(
+    buffer: &MultiBuffer,
+    snapshot: &MultiBufferSnapshot,
+    point: Point,
+    cx: &AppContext,
+) -> IndentSize {
+    snapshot
+        .language_indent_size_at(point, cx)
+        .unwrap_or_else(|| {
+            let settings = buffer.settings_at(point, cx);
+            if settings.hard_tabs {
+                IndentSize::tab()
+            } else {
+                IndentSize::spaces(settings.tab_size.get())
+            }
+        })
+}
+
+/// Returns the range of the leading whitespace of the given row, and the same indentation written
+/// with the given kind of whitespace. Tabs within the leading whitespace advance to the next tab
+/// stop, and any remainder that is not a multiple of the tab size stays spaces.
+fn converted_indent(
+    buffer: &BufferSnapshot,
+    row: u32,
+    kind: IndentKind,
+    tab_size: u32,
+) -> (Range<Point>, String) {
+    let mut len = 0;
+    let mut column = 0;
+    for ch in buffer.chars_at(Point::new(row, 0)) {
+        match ch {
+            ' ' => column += 1,
+            '\t' => column += tab_size - column % tab_size,
+            _ => break,
+        }
+        len += 1;
+    }
+
+    let new_indent = match kind {
+        IndentKind::Space => " ".repeat(column as usize),
+        IndentKind::Tab => {
+            let mut indent = "\t".repeat((column / tab_size) as usize);
+            indent.push_str(&" ".repeat((column % tab_size) as usize));
+            indent
+        }
+    };
+    (Point::new(row, 0)..Point::new(row, len), new_indent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        editor_tests::init_test,
+        test::{build_editor, editor_test_context::EditorTestContext},
+        Indent, Outdent, Tab,
+    };
+    use indoc::indoc;
+    use language::{Language, LanguageConfig};
+    use project::{FakeFs, Project};
+    use serde_json::json;
+    use std::{num::NonZeroU32, sync::Arc};
+
+    #[gpui::test]
+    async fn test_reindent(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |settings| {
+            settings.defaults.tab_size = NonZeroU32::new(4);
+        });
+
+        let mut cx = EditorTestContext::new(cx).await;
+        let language = Arc::new(
+            Language::new(
+                LanguageConfig::default(),
+                Some(tree_sitter_rust::language()),
+            )
+            .with_indents_query(r#"(_ "{" "}" @end) @indent"#)
+            .unwrap(),
+        );
+        cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+
+        cx.set_state(indoc! {"
+            fn a() {
+            b«();
+                    c();ˇ»
+
+              d();
+            }
+        "});
+        cx.executor().run_until_parked();
+        cx.update_editor(|e, cx| e.reindent(&Reindent, cx));
+        cx.executor().run_until_parked();
+        cx.assert_editor_state(indoc! {"
+            fn a() {
+                b«();
+                c();ˇ»
+
+              d();
+            }
+        "});
+
+        // A selection ending at the start of a line doesn't reindent that line.
+        cx.set_state(indoc! {"
+            fn a() {
+              c«();
+            ˇ»  d();
+            }
+        "});
+        cx.update_editor(|e, cx| e.reindent(&Reindent, cx));
+        cx.executor().run_until_parked();
+        cx.assert_editor_state(indoc! {"
+            fn a() {
+                c«();
+            ˇ»  d();
+            }
+        "});
+    }
+
+    #[gpui::test]
+    async fn test_detect_indentation(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |settings| {
+            settings.defaults.tab_size = NonZeroU32::new(4);
+        });
+
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree("/dir", json!({ "file.txt": "a\n  b\n    c\n" }))
+            .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_local_buffer("/dir/file.txt", cx)
+            })
+            .await
+            .unwrap();
+
+        let buffer = cx.new_model(|cx| MultiBuffer::singleton(buffer, cx));
+        let (editor, cx) = cx.add_window_view(|cx| build_editor(buffer, cx));
+        _ = editor.update(cx, |editor, cx| {
+            assert_eq!(editor.indent_size(cx), Some(IndentSize::spaces(2)));
+
+            // The detected indentation is used instead of the one from the settings.
+            editor.change_selections(None, cx, |s| s.select_ranges([0..0]));
+            editor.tab(&Tab, cx);
+            assert_eq!(editor.text(cx), "  a\n  b\n    c\n");
+            editor.change_selections(None, cx, |s| s.select_ranges([8..9]));
+            editor.indent(&Indent, cx);
+            assert_eq!(editor.text(cx), "  a\n  b\n      c\n");
+            editor.outdent(&Outdent, cx);
+            editor.outdent(&Outdent, cx);
+            assert_eq!(editor.text(cx), "  a\n  b\n  c\n");
+        });
+    }
+
+    #[gpui::test]
+    async fn test_convert_indentation(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |settings| {
+            settings.defaults.tab_size = NonZeroU32::new(4);
+        });
+
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state("\ta\n  \tb\n      cˇ");
+        cx.update_editor(|e, cx| e.convert_indentation_to_spaces(&ConvertIndentationToSpaces, cx));
+        cx.assert_editor_state("    a\n    b\n      cˇ");
+        cx.update_buffer(|buffer, _| {
+            assert_eq!(buffer.indent_size_override(), Some(IndentSize::spaces(4)));
+        });
+
+        cx.update_editor(|e, cx| e.convert_indentation_to_tabs(&ConvertIndentationToTabs, cx));
+        cx.assert_editor_state("\ta\n\tb\n\t  cˇ");
+        cx.update_buffer(|buffer, _| {
+            assert_eq!(buffer.indent_size_override(), Some(IndentSize::tab()));
+        });
+
+        // With a selection, only the selected lines are converted.
+        cx.set_state("    a\n«    bˇ»\n    c");
+        cx.update_editor(|e, cx| e.convert_indentation_to_tabs(&ConvertIndentationToTabs, cx));
+        cx.assert_editor_state("    a\n«\tbˇ»\n    c");
+    }
+}
@@ -7,7 +7,7 @@ use anyhow::{anyhow, Context as _, Result};
 use collections::HashSet;
 use futures::future::try_join_all;
 use gpui::{
-    div, point, AnyElement, AppContext, AsyncWindowContext, Context, Entity, EntityId,
+    div, point, px, AnyElement, AppContext, AsyncWindowContext, Context, Entity, EntityId,
     EventEmitter, IntoElement, Model, ParentElement, Pixels, Render, SharedString, Styled,
     Subscription, Task, View, ViewContext, VisualContext, WeakView, WindowContext,
 };
@@ -18,6 +18,7 @@ use language::{
 use project::repository::GitFileStatus;
 use project::{search::SearchQuery, FormatTrigger, Item as _, Project, ProjectPath};
 use rpc::proto::{self, update_view, PeerId};
+use serde::{Deserialize, Serialize};
 use settings::Settings;
 use workspace::item::ItemSettings;
 
@@ -40,12 +41,21 @@ use workspace::{
 };
 use workspace::{
     item::{FollowableItem, Item, ItemEvent, ItemHandle, ProjectItem},
-    searchable::{Direction, SearchEvent, SearchableItem, SearchableItemHandle},
+    searchable::{Direction, SearchEvent, SearchOptions, SearchableItem, SearchableItemHandle},
     ItemId, ItemNavHistory, Pane, ToolbarItemLocation, ViewId, Workspace, WorkspaceId,
 };
 
 pub const MAX_TAB_TITLE_LEN: usize = 24;
 
+/// The position an editor's navigation history entry returns to, as it is persisted with the
+/// workspace.
+#[derive(Serialize, Deserialize)]
+struct SerializedNavigationData {
+    row: u32,
+    column: u32,
+    scroll_top_row: u32,
+}
+
 impl FollowableItem for Editor {
     fn remote_id(&self) -> Option<ViewId> {
         self.remote_id
@@ -154,6 +164,8 @@ impl FollowableItem for Editor {
                     scroll_top_anchor: state.scroll_top_anchor,
                     scroll_x: state.scroll_x,
                     scroll_y: state.scroll_y,
+                    folds: state.folds,
+                    folds_changed: true,
                     ..Default::default()
                 },
                 &mut cx,
@@ -231,6 +243,7 @@ impl FollowableItem for Editor {
                 .pending_anchor()
                 .as_ref()
                 .map(serialize_selection),
+            folds: self.serialize_folds(cx),
         }))
     }
 
@@ -287,7 +300,27 @@ impl FollowableItem for Editor {
                         .extend(ids.iter().map(ExcerptId::to_proto));
                     true
                 }
-                EditorEvent::ScrollPositionChanged { autoscroll, .. } if !autoscroll => {
+                EditorEvent::ExcerptsExpanded { ids } => {
+                    let snapshot = self.buffer.read(cx).snapshot(cx);
+                    update.expanded_excerpts.extend(ids.iter().filter_map(|id| {
+                        serialize_excerpt(
+                            snapshot.buffer_id_for_excerpt(*id)?,
+                            id,
+                            &snapshot.range_for_excerpt(*id)?,
+                        )
+                    }));
+                    true
+                }
+                EditorEvent::ExcerptMoved { id, predecessor } => {
+                    update.moved_excerpts.push(proto::ExcerptMove {
+                        excerpt_id: id.to_proto(),
+                        previous_excerpt_id: predecessor.to_proto(),
+                    });
+                    true
+                }
+                // Autoscrolls are sent too, so that followers show the same rows as the leader
+                // rather than the rows their own autoscroll would show.
+                EditorEvent::ScrollPositionChanged { .. } => {
                     let scroll_anchor = self.scroll_manager.anchor();
                     update.scroll_top_anchor = Some(serialize_anchor(&scroll_anchor.anchor));
                     update.scroll_x = scroll_anchor.offset.x;
@@ -308,6 +341,11 @@ impl FollowableItem for Editor {
                         .map(serialize_selection);
                     true
                 }
+                EditorEvent::FoldsChanged => {
+                    update.folds = self.serialize_folds(cx);
+                    update.folds_changed = true;
+                    true
+                }
                 _ => false,
             },
         }
@@ -404,27 +442,53 @@ async fn update_editor_from_message(
                 );
             }
 
+            multibuffer.set_excerpt_contexts(
+                message.expanded_excerpts.into_iter().filter_map(|excerpt| {
+                    let id = ExcerptId::from_proto(excerpt.id);
+                    Some((id, deserialize_excerpt_range(excerpt)?.context))
+                }),
+                cx,
+            );
+            for excerpt_move in message.moved_excerpts {
+                multibuffer.move_excerpt(
+                    ExcerptId::from_proto(excerpt_move.excerpt_id),
+                    ExcerptId::from_proto(excerpt_move.previous_excerpt_id),
+                    cx,
+                );
+            }
             multibuffer.remove_excerpts(removed_excerpt_ids, cx);
             Result::<(), anyhow::Error>::Ok(())
         })
     })??;
 
     // Deserialize the editor state.
-    let (selections, pending_selection, scroll_top_anchor) = this.update(cx, |editor, cx| {
-        let buffer = editor.buffer.read(cx).read(cx);
-        let selections = message
-            .selections
-            .into_iter()
-            .filter_map(|selection| deserialize_selection(&buffer, selection))
-            .collect::<Vec<_>>();
-        let pending_selection = message
-            .pending_selection
-            .and_then(|selection| deserialize_selection(&buffer, selection));
-        let scroll_top_anchor = message
-            .scroll_top_anchor
-            .and_then(|anchor| deserialize_anchor(&buffer, anchor));
-        anyhow::Ok((selections, pending_selection, scroll_top_anchor))
-    })??;
+    let (selections, pending_selection, scroll_top_anchor, folds) =
+        this.update(cx, |editor, cx| {
+            let buffer = editor.buffer.read(cx).read(cx);
+            let selections = message
+                .selections
+                .into_iter()
+                .filter_map(|selection| deserialize_selection(&buffer, selection))
+                .collect::<Vec<_>>();
+            let pending_selection = message
+                .pending_selection
+                .and_then(|selection| deserialize_selection(&buffer, selection));
+            let scroll_top_anchor = message
+                .scroll_top_anchor
+                .and_then(|anchor| deserialize_anchor(&buffer, anchor));
+            let folds = message.folds_changed.then(|| {
+                message
+                    .folds
+                    .into_iter()
+                    .filter_map(|range| {
+                        let start = deserialize_anchor(&buffer, range.start?)?;
+                        let end = deserialize_anchor(&buffer, range.end?)?;
+                        Some(start..end)
+                    })
+                    .collect::<Vec<_>>()
+            });
+            anyhow::Ok((selections, pending_selection, scroll_top_anchor, folds))
+        })??;
 
     // Wait until the buffer has received all of the operations referenced by
     // the editor's new state.
@@ -435,7 +499,13 @@ async fn update_editor_from_message(
                     .iter()
                     .chain(pending_selection.as_ref())
                     .flat_map(|selection| [selection.start, selection.end])
-                    .chain(scroll_top_anchor),
+                    .chain(scroll_top_anchor)
+                    .chain(
+                        folds
+                            .iter()
+                            .flatten()
+                            .flat_map(|range| [range.start, range.end]),
+                    ),
                 cx,
             )
         })
@@ -444,10 +514,14 @@ async fn update_editor_from_message(
 
     // Update the editor's state.
     this.update(cx, |editor, cx| {
-        if !selections.is_empty() || pending_selection.is_some() {
+        if let Some(folds) = folds {
+            editor.set_folds_from_remote(folds, cx);
+        }
+        let selections_changed = !selections.is_empty() || pending_selection.is_some();
+        if selections_changed {
             editor.set_selections_from_remote(selections, pending_selection, cx);
-            editor.request_autoscroll_remotely(Autoscroll::newest(), cx);
-        } else if let Some(scroll_top_anchor) = scroll_top_anchor {
+        }
+        if let Some(scroll_top_anchor) = scroll_top_anchor {
             editor.set_scroll_anchor_remote(
                 ScrollAnchor {
                     anchor: scroll_top_anchor,
@@ -455,6 +529,13 @@ async fn update_editor_from_message(
                 },
                 cx,
             );
+            // The leader's viewport may be taller than the follower's, so it's still scrolled to
+            // fit the cursors if they're out of view.
+            if selections_changed {
+                editor.request_autoscroll_remotely(Autoscroll::fit(), cx);
+            }
+        } else if selections_changed {
+            editor.request_autoscroll_remotely(Autoscroll::newest(), cx);
         }
     })?;
     Ok(())
@@ -490,6 +571,20 @@ fn serialize_selection(selection: &Selection<Anchor>) -> proto::Selection {
     }
 }
 
+impl Editor {
+    fn serialize_folds(&self, cx: &AppContext) -> Vec<proto::EditorAnchorRange> {
+        self.display_map
+            .read(cx)
+            .fold_ranges()
+            .iter()
+            .map(|range| proto::EditorAnchorRange {
+                start: Some(serialize_anchor(&range.start)),
+                end: Some(serialize_anchor(&range.end)),
+            })
+            .collect()
+    }
+}
+
 fn serialize_anchor(anchor: &Anchor) -> proto::EditorAnchor {
     proto::EditorAnchor {
         excerpt_id: anchor.excerpt_id.to_proto(),
@@ -574,6 +669,54 @@ impl Item for Editor {
         }
     }
 
+    fn serialize_navigation_data(
+        &self,
+        data: &(dyn std::any::Any + Send),
+        cx: &AppContext,
+    ) -> Option<String> {
+        let data = data.downcast_ref::<NavigationData>()?;
+        let buffer = self.buffer.read(cx).read(cx);
+        let (cursor_position, scroll_top_row) = if buffer.can_resolve(&data.cursor_anchor) {
+            (
+                data.cursor_anchor.to_point(&buffer),
+                data.scroll_anchor.anchor.to_point(&buffer).row,
+            )
+        } else {
+            (data.cursor_position, data.scroll_top_row)
+        };
+        serde_json::to_string(&SerializedNavigationData {
+            row: cursor_position.row,
+            column: cursor_position.column,
+            scroll_top_row,
+        })
+        .log_err()
+    }
+
+    fn deserialize_navigation_data(
+        &self,
+        data: &str,
+        cx: &AppContext,
+    ) -> Option<Box<dyn std::any::Any + Send>> {
+        let SerializedNavigationData {
+            row,
+            column,
+            scroll_top_row,
+        } = serde_json::from_str(data).log_err()?;
+
+        let buffer = self.buffer.read(cx).read(cx);
+        let cursor_position = buffer.clip_point(Point::new(row, column), Bias::Left);
+        let scroll_top = buffer.clip_point(Point::new(scroll_top_row, 0), Bias::Left);
+        Some(Box::new(NavigationData {
+            cursor_anchor: buffer.anchor_before(cursor_position),
+            cursor_position,
+            scroll_anchor: ScrollAnchor {
+                offset: Default::default(),
+                anchor: buffer.anchor_before(scroll_top),
+            },
+            scroll_top_row,
+        }))
+    }
+
     fn tab_tooltip_text(&self, cx: &AppContext) -> Option<SharedString> {
         let file_path = self
             .buffer()
@@ -704,48 +847,25 @@ impl Item for Editor {
 
     fn save(&mut self, project: Model<Project>, cx: &mut ViewContext<Self>) -> Task<Result<()>> {
         self.report_editor_event("save", None, cx);
-        let buffers = self.buffer().clone().read(cx).all_buffers();
-        cx.spawn(|this, mut cx| async move {
-            this.update(&mut cx, |this, cx| {
-                this.perform_format(project.clone(), FormatTrigger::Save, cx)
-            })?
-            .await?;
-
-            if buffers.len() == 1 {
-                project
-                    .update(&mut cx, |project, cx| project.save_buffers(buffers, cx))?
-                    .await?;
-            } else {
-                // For multi-buffers, only save those ones that contain changes. For clean buffers
-                // we simulate saving by calling `Buffer::did_save`, so that language servers or
-                // other downstream listeners of save events get notified.
-                let (dirty_buffers, clean_buffers) = buffers.into_iter().partition(|buffer| {
-                    buffer
-                        .update(&mut cx, |buffer, _| {
-                            buffer.is_dirty() || buffer.has_conflict()
-                        })
-                        .unwrap_or(false)
-                });
-
-                project
-                    .update(&mut cx, |project, cx| {
-                        project.save_buffers(dirty_buffers, cx)
-                    })?
-                    .await?;
-                for buffer in clean_buffers {
-                    buffer
-                        .update(&mut cx, |buffer, cx| {
-                            let version = buffer.saved_version().clone();
-                            let fingerprint = buffer.saved_version_fingerprint();
-                            let mtime = buffer.saved_mtime();
-                            buffer.did_save(version, fingerprint, mtime, cx);
-                        })
-                        .ok();
-                }
-            }
+        self.format_on_blur = None;
+        save_buffers(self, project, true, cx)
+    }
 
-            Ok(())
-        })
+    fn autosave(
+        &mut self,
+        project: Model<Project>,
+        cx: &mut ViewContext<Self>,
+    ) -> Task<Result<()>> {
+        self.report_editor_event("save", None, cx);
+        // Formatting rewrites the buffer, which would interfere with typing if the editor is
+        // still focused. Defer it until the next explicit save or until the editor loses focus.
+        if self.focus_handle.is_focused(cx) {
+            self.format_on_blur = Some(project.clone());
+            save_buffers(self, project, false, cx)
+        } else {
+            self.format_on_blur = None;
+            save_buffers(self, project, true, cx)
+        }
     }
 
     fn save_as(
@@ -866,16 +986,25 @@ impl Item for Editor {
 
         if let Some(buffer) = self.buffer().read(cx).as_singleton() {
             serialize(buffer.clone(), workspace_id, item_id, cx);
+            self.read_bookmarks_from_db(workspace_id, cx);
+            self.read_marks_from_db(workspace_id, cx);
 
             cx.subscribe(&buffer, |this, buffer, event, cx| {
                 if let Some((_, workspace_id)) = this.workspace.as_ref() {
-                    if let language::Event::FileHandleChanged = event {
-                        serialize(
+                    match event {
+                        language::Event::FileHandleChanged => serialize(
                             buffer,
                             *workspace_id,
                             cx.view().item_id().as_u64() as ItemId,
                             cx,
-                        );
+                        ),
+                        // Bookmarks and marks follow their lines as they are edited, so their
+                        // positions are saved again along with the file.
+                        language::Event::Saved => {
+                            this.save_bookmarks(cx);
+                            this.save_marks(cx);
+                        }
+                        _ => {}
                     }
                 }
             })
@@ -913,7 +1042,10 @@ impl Item for Editor {
                 f(ItemEvent::UpdateBreadcrumbs);
             }
 
-            EditorEvent::ExcerptsAdded { .. } | EditorEvent::ExcerptsRemoved { .. } => {
+            EditorEvent::ExcerptsAdded { .. }
+            | EditorEvent::ExcerptsRemoved { .. }
+            | EditorEvent::ExcerptsExpanded { .. }
+            | EditorEvent::ExcerptMoved { .. } => {
                 f(ItemEvent::Edit);
             }
 
@@ -952,11 +1084,19 @@ impl Item for Editor {
                     let buffer = project_item
                         .downcast::<Buffer>()
                         .map_err(|_| anyhow!("Project item at stored path was not a buffer"))?;
+                    let font_size_adjustment = DB
+                        .get_font_size_adjustment(item_id, workspace_id)
+                        .await
+                        .log_err()
+                        .flatten();
                     Ok(pane.update(&mut cx, |_, cx| {
                         cx.new_view(|cx| {
                             let mut editor = Editor::for_buffer(buffer, Some(project), cx);
 
                             editor.read_scroll_position_from_db(item_id, workspace_id, cx);
+                            if let Some(adjustment) = font_size_adjustment {
+                                editor.font_size_adjustment = px(adjustment);
+                            }
                             editor
                         })
                     })?)
@@ -966,6 +1106,58 @@ impl Item for Editor {
     }
 }
 
+pub(crate) fn save_buffers(
+    editor: &mut Editor,
+    project: Model<Project>,
+    format: bool,
+    cx: &mut ViewContext<Editor>,
+) -> Task<Result<()>> {
+    let buffers = editor.buffer().clone().read(cx).all_buffers();
+    cx.spawn(|this, mut cx| async move {
+        if format {
+            this.update(&mut cx, |this, cx| {
+                this.perform_format(project.clone(), FormatTrigger::Save, cx)
+            })?
+            .await?;
+        }
+
+        if buffers.len() == 1 {
+            project
+                .update(&mut cx, |project, cx| project.save_buffers(buffers, cx))?
+                .await?;
+        } else {
+            // For multi-buffers, only save those ones that contain changes. For clean buffers
+            // we simulate saving by calling `Buffer::did_save`, so that language servers or
+            // other downstream listeners of save events get notified.
+            let (dirty_buffers, clean_buffers) = buffers.into_iter().partition(|buffer| {
+                buffer
+                    .update(&mut cx, |buffer, _| {
+                        buffer.is_dirty() || buffer.has_conflict()
+                    })
+                    .unwrap_or(false)
+            });
+
+            project
+                .update(&mut cx, |project, cx| {
+                    project.save_buffers(dirty_buffers, cx)
+                })?
+                .await?;
+            for buffer in clean_buffers {
+                buffer
+                    .update(&mut cx, |buffer, cx| {
+                        let version = buffer.saved_version().clone();
+                        let fingerprint = buffer.saved_version_fingerprint();
+                        let mtime = buffer.saved_mtime();
+                        buffer.did_save(version, fingerprint, mtime, cx);
+                    })
+                    .ok();
+            }
+        }
+
+        Ok(())
+    })
+}
+
 impl ProjectItem for Editor {
     type Item = Buffer;
 
@@ -974,7 +1166,36 @@ impl ProjectItem for Editor {
         buffer: Model<Buffer>,
         cx: &mut ViewContext<Self>,
     ) -> Self {
-        Self::for_buffer(buffer, Some(project), cx)
+        let mut editor = Self::for_buffer(buffer.clone(), Some(project.clone()), cx);
+        editor.set_read_only_without_write_permission(&project, &buffer, cx);
+        editor
+    }
+}
+
+impl Editor {
+    /// Makes the editor read-only when the user can't write to its file.
+    fn set_read_only_without_write_permission(
+        &mut self,
+        project: &Model<Project>,
+        buffer: &Model<Buffer>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(file) = buffer.read(cx).file().and_then(|file| file.as_local()) else {
+            return;
+        };
+        let abs_path = file.abs_path(cx);
+        let fs = project.read(cx).fs().clone();
+        cx.spawn(|editor, mut cx| async move {
+            // Files that don't exist yet are created when they're saved.
+            if fs.is_file(&abs_path).await && !fs.is_writable(&abs_path).await {
+                editor.update(&mut cx, |editor, cx| {
+                    editor.read_only_without_write_permission = true;
+                    cx.notify();
+                })?;
+            }
+            anyhow::Ok(())
+        })
+        .detach_and_log_err(cx);
     }
 }
 
@@ -984,8 +1205,19 @@ pub(crate) enum BufferSearchHighlights {}
 impl SearchableItem for Editor {
     type Match = Range<Anchor>;
 
+    fn supported_options() -> SearchOptions {
+        SearchOptions {
+            case: true,
+            word: true,
+            regex: true,
+            replacement: true,
+            excerpt: true,
+        }
+    }
+
     fn clear_matches(&mut self, cx: &mut ViewContext<Self>) {
         self.clear_background_highlights::<BufferSearchHighlights>(cx);
+        self.preview_replacements(&[], None, cx);
     }
 
     fn update_matches(&mut self, matches: Vec<Range<Anchor>>, cx: &mut ViewContext<Self>) {
@@ -1114,12 +1346,38 @@ impl SearchableItem for Editor {
         }
     }
 
+    fn preview_replacements(
+        &mut self,
+        matches: &[Range<Anchor>],
+        query: Option<&SearchQuery>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.preview_replacements(matches, query, cx);
+    }
+
+    fn preserve_case_in_selections(&mut self, cx: &mut ViewContext<Self>) {
+        self.preserve_case_in_selections(cx);
+    }
+
+    fn toggle_search_within_excerpt(&mut self, enabled: bool, cx: &mut ViewContext<Self>) {
+        self.search_within_excerpt = if enabled {
+            let head = self.selections.newest::<usize>(cx).head();
+            self.buffer()
+                .read(cx)
+                .excerpt_containing(head, cx)
+                .map(|(excerpt_id, _, _)| excerpt_id)
+        } else {
+            None
+        };
+    }
+
     fn find_matches(
         &mut self,
         query: Arc<project::search::SearchQuery>,
         cx: &mut ViewContext<Self>,
     ) -> Task<Vec<Range<Anchor>>> {
         let buffer = self.buffer().read(cx).snapshot(cx);
+        let search_within_excerpt = self.search_within_excerpt;
         cx.background_executor().spawn(async move {
             let mut ranges = Vec::new();
             if let Some((_, _, excerpt_buffer)) = buffer.as_singleton() {
@@ -1134,6 +1392,9 @@ impl SearchableItem for Editor {
                 );
             } else {
                 for excerpt in buffer.excerpt_boundaries_in_range(0..buffer.len()) {
+                    if search_within_excerpt.map_or(false, |excerpt_id| excerpt_id != excerpt.id) {
+                        continue;
+                    }
                     let excerpt_range = excerpt.range.context.to_offset(&excerpt.buffer);
                     ranges.extend(
                         query
@@ -0,0 +1,122 @@
+use crate::{
+    display_map::{BlockContext, BlockDisposition, BlockProperties, BlockStyle, RenderBlock},
+    Anchor, Editor, EditorMode, EditorSettings,
+};
+use collections::HashSet;
+use gpui::{AppContext, ViewContext};
+use settings::Settings;
+use std::sync::Arc;
+use ui::{prelude::*, Tooltip};
+
+/// Whether the editor shows a single buffer larger than the configured thresholds, in which
+/// syntax highlighting, git diff indicators and invisibles are turned off to keep scrolling smooth,
+/// along with the parsing, diffing and other work that scans the whole buffer after every edit.
+pub(crate) fn is_large_file(editor: &Editor, cx: &AppContext) -> bool {
+    if editor.mode != EditorMode::Full || editor.large_file_features_enabled {
+        return false;
+    }
+    let buffer = editor.buffer.read(cx);
+    if !buffer.is_singleton() {
+        return false;
+    }
+    let settings = EditorSettings::get_global(cx).large_file;
+    let snapshot = buffer.snapshot(cx);
+    snapshot.len() > settings.max_bytes || snapshot.max_buffer_row() >= settings.max_lines
+}
+
+/// Enters or leaves large file mode as the buffer grows or shrinks, showing a banner above the
+/// first line while the editor is in it.
+pub(crate) fn refresh_large_file_mode(editor: &mut Editor, cx: &mut ViewContext<Editor>) {
+    let is_large_file = is_large_file(editor, cx);
+    if is_large_file == editor.large_file_mode {
+        return;
+    }
+    editor.large_file_mode = is_large_file;
+    set_buffer_features_enabled(editor, !is_large_file, cx);
+    editor.refresh_paragraph_spacing(cx);
+    editor.refresh_todo_highlights(cx);
+    editor.refresh_selection_match_highlights(cx);
+    editor.refresh_inline_diff(cx);
+
+    match (is_large_file, editor.large_file_banner) {
+        (true, None) => {
+            let block_ids = editor.insert_blocks(
+                [BlockProperties {
+                    position: Anchor::min(),
+                    height: 2,
+                    style: BlockStyle::Sticky,
+                    render: render_large_file_banner(),
+                    disposition: BlockDisposition::Above,
+                }],
+                None,
+                cx,
+            );
+            editor.large_file_banner = block_ids.into_iter().next();
+        }
+        (false, Some(block_id)) => {
+            editor.large_file_banner = None;
+            editor.remove_blocks(HashSet::from_iter([block_id]), None, cx);
+        }
+        _ => {}
+    }
+    cx.notify();
+}
+
+/// Releases the editor's request to stop parsing and diffing its buffer when it's dropped in
+/// large file mode, so that the other editors of the buffer aren't left without them.
+pub(crate) fn release_large_file_mode(editor: &mut Editor, cx: &mut AppContext) {
+    if editor.large_file_mode {
+        set_buffer_features_enabled(editor, true, cx);
+    }
+}
+
+/// Stops or resumes parsing and diffing the buffer on behalf of the editor. The buffer counts
+/// these requests, so it's only parsed and diffed again once all of its editors left large file
+/// mode.
+fn set_buffer_features_enabled(editor: &Editor, enabled: bool, cx: &mut AppContext) {
+    if let Some(buffer) = editor.buffer.read(cx).as_singleton() {
+        buffer.update(cx, |buffer, cx| {
+            buffer.set_parsing_enabled(enabled, cx);
+            buffer.set_git_diff_enabled(enabled, cx);
+        });
+    }
+}
+
+fn render_large_file_banner() -> RenderBlock {
+    Arc::new(|cx: &mut BlockContext| {
+        let view = cx.view.clone();
+        let colors = cx.theme().colors();
+        h_flex()
+            .id(cx.block_id)
+            .size_full()
+            .pl(cx.gutter_width)
+            .pr_2()
+            .gap_2()
+            .bg(colors.editor_subheader_background)
+            .border_b_1()
+            .border_color(colors.border_variant)
+            .child(
+                Icon::new(IconName::ExclamationTriangle)
+                    .size(IconSize::Small)
+                    .color(Color::Muted),
+            )
+            .child(
+                Label::new(
+                    "Syntax highlighting, git diff and invisibles are off in this large file.",
+                )
+                .size(LabelSize::Small)
+                .color(Color::Muted),
+            )
+            .child(div().flex_1())
+            .child(
+                Button::new("large-file-enable", "Enable Anyway")
+                    .label_size(LabelSize::Small)
+                    .tooltip(|cx| Tooltip::text("Scrolling through the file may be slower", cx))
+                    .on_click(cx.listener_for(&view, |editor, _, cx| {
+                        editor.large_file_features_enabled = true;
+                        refresh_large_file_mode(editor, cx);
+                    })),
+            )
+            .into_any_element()
+    })
+}
@@ -0,0 +1,212 @@
+use crate::{
+    display_map::{
+        BlockContext, BlockDisposition, BlockId, BlockProperties, BlockStyle, RenderBlock,
+    },
+    scroll::Autoscroll,
+    Editor,
+};
+use collections::{HashMap, HashSet};
+use gpui::{Task, ViewContext};
+use language::AutoindentMode;
+use smol::future::yield_now;
+use std::{ops::Range, sync::Arc};
+use ui::prelude::*;
+
+/// Clipboard contents longer than this are inserted in chunks, so that the editor remains
+/// responsive while a large paste is in progress.
+pub(crate) const LARGE_PASTE_THRESHOLD: usize = 1024 * 1024;
+const LARGE_PASTE_CHUNK_LEN: usize = 64 * 1024;
+
+pub(crate) struct LargePaste {
+    progress_block: BlockId,
+    _task: Task<Option<()>>,
+}
+
+/// Replaces `range` with `text` like a regular paste into the newest selection, inserting it a
+/// chunk at a time and showing the progress in a block below the insertion. All chunks are
+/// merged into a single transaction, so the paste is undone in one step, and the editor is
+/// read-only until the paste completes.
+///
+/// When auto-indenting, each chunk is indented as a block, like the text of a regular paste.
+/// The indentation of the first chunk is relative to the original indent column of the copied
+/// text, if known.
+pub(crate) fn paste_in_chunks(
+    editor: &mut Editor,
+    range: Range<usize>,
+    text: String,
+    auto_indent: bool,
+    original_indent_column: Option<u32>,
+    cx: &mut ViewContext<Editor>,
+) {
+    if editor.large_paste.is_some() {
+        return;
+    }
+
+    let selection = editor.selections.newest::<usize>(cx);
+    let snapshot = editor.buffer.read(cx).snapshot(cx);
+    let insertion_start = snapshot.anchor_before(range.start);
+    let insertion_end = snapshot.anchor_after(range.end);
+    let cursor = snapshot.anchor_after(selection.end);
+    let progress_block = editor
+        .insert_blocks(
+            [BlockProperties {
+                position: insertion_end,
+                height: 1,
+                style: BlockStyle::Fixed,
+                render: render_progress(0.),
+                disposition: BlockDisposition::Below,
+            }],
+            None,
+            cx,
+        )
+        .remove(0);
+
+    let task = cx.spawn(|this, mut cx| async move {
+        let total_len = text.len();
+        let chunks = cx
+            .background_executor()
+            .spawn(async move { split_into_chunks(&text, LARGE_PASTE_CHUNK_LEN) })
+            .await;
+
+        let mut first_transaction = None;
+        let mut inserted_len = 0;
+        let mut completed = true;
+        for chunk in chunks {
+            inserted_len += chunk.len();
+            let inserted = this
+                .update(&mut cx, |this, cx| {
+                    // Stop pasting once the inserted text is undone, e.g. from another editor on
+                    // the same buffer, rather than merging the rest into an undone transaction.
+                    if let Some(first) = first_transaction {
+                        if !this.buffer.read(cx).is_transaction_undoable(first, cx) {
+                            return false;
+                        }
+                    }
+
+                    let range = if first_transaction.is_none() {
+                        insertion_start..insertion_end
+                    } else {
+                        insertion_end..insertion_end
+                    };
+                    let autoindent_mode = auto_indent.then(|| {
+                        let original_indent_columns = if first_transaction.is_none() {
+                            original_indent_column.into_iter().collect()
+                        } else {
+                            Vec::new()
+                        };
+                        AutoindentMode::Block {
+                            original_indent_columns,
+                        }
+                    });
+                    let transaction = this.transact(cx, |this, cx| {
+                        this.buffer.update(cx, |buffer, cx| {
+                            buffer.edit([(range, chunk)], autoindent_mode, cx)
+                        });
+                    });
+                    match (first_transaction, transaction) {
+                        (None, _) => first_transaction = transaction,
+                        (Some(first), Some(transaction)) if first != transaction => {
+                            this.buffer.update(cx, |buffer, cx| {
+                                buffer.merge_transactions(transaction, first, cx)
+                            });
+                        }
+                        _ => {}
+                    }
+
+                    let progress = inserted_len as f32 / total_len as f32;
+                    this.replace_blocks(
+                        HashMap::from_iter([(progress_block, render_progress(progress))]),
+                        None,
+                        cx,
+                    );
+                    true
+                })
+                .ok()?;
+            if !inserted {
+                completed = false;
+                break;
+            }
+            yield_now().await;
+        }
+
+        this.update(&mut cx, |this, cx| {
+            if let Some(large_paste) = this.large_paste.take() {
+                this.remove_blocks(HashSet::from_iter([large_paste.progress_block]), None, cx);
+            }
+            if !completed {
+                return;
+            }
+            this.change_selections(Some(Autoscroll::fit()), cx, |s| {
+                s.select_anchor_ranges([cursor..cursor])
+            });
+            if let Some((_, end_selections)) = first_transaction
+                .and_then(|transaction| this.selection_history.transaction_mut(transaction))
+            {
+                *end_selections = Some(this.selections.disjoint_anchors());
+            }
+        })
+        .ok()
+    });
+
+    editor.large_paste = Some(LargePaste {
+        progress_block,
+        _task: task,
+    });
+}
+
+fn render_progress(progress: f32) -> RenderBlock {
+    Arc::new(move |cx: &mut BlockContext| {
+        h_flex()
+            .pl(cx.gutter_width)
+            .gap_1()
+            .child(
+                Icon::new(IconName::ArrowCircle)
+                    .size(IconSize::Small)
+                    .color(Color::Muted),
+            )
+            .child(
+                Label::new(format!("Pasting… {}%", (progress * 100.) as u32))
+                    .size(LabelSize::Small)
+                    .color(Color::Muted),
+            )
+            .into_any_element()
+    })
+}
+
+/// Splits `text` into chunks of at most `max_len` bytes, preferring to split after a newline.
+fn split_into_chunks(text: &str, max_len: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut remaining = text;
+    while remaining.len() > max_len {
+        let mut end = max_len;
+        while !remaining.is_char_boundary(end) {
+            end -= 1;
+        }
+        if let Some(newline_ix) = remaining[..end].rfind('\n') {
+            end = newline_ix + 1;
+        }
+        let (chunk, rest) = remaining.split_at(end);
+        chunks.push(chunk.to_string());
+        remaining = rest;
+    }
+    if !remaining.is_empty() {
+        chunks.push(remaining.to_string());
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_into_chunks() {
+        assert_eq!(split_into_chunks("", 4), Vec::<String>::new());
+        assert_eq!(split_into_chunks("abc", 4), vec!["abc"]);
+        assert_eq!(
+            split_into_chunks("ab\ncdefgh\ni", 5),
+            vec!["ab\n", "cdefg", "h\ni"]
+        );
+        assert_eq!(split_into_chunks("aéb", 2), vec!["a", "é", "b"]);
+    }
+}
@@ -0,0 +1,578 @@
+use anyhow::Result;
+use collections::{HashMap, HashSet};
+use gpui::{
+    Hsla, Pixels, ShapedLine, SharedString, TextRun, TextStyle, WindowContext, WindowTextSystem,
+};
+use parking_lot::Mutex;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    ops::Range,
+    sync::Arc,
+};
+use util::ResultExt;
+
+/// The number of shaped lines kept around, which is enough for a few screens of rows.
+const MAX_CACHED_LINES: usize = 1024;
+
+/// Keeps the lines shaped in previous frames, so that scrolling and cursor blinking don't shape
+/// the rows that didn't change again. Lines are keyed by their text and styles, along with a
+/// revision of the editor's text style and the width lines are wrapped at, and the least recently
+/// used ones are evicted once there are too many.
+///
+/// The rows around the visible ones are highlighted and shaped on a background thread, so that
+/// they're usually ready by the time they're scrolled into view and scrolling doesn't wait on
+/// shaping them. A few rows right next to them are shaped along with the visible ones instead, so
+/// that small scrolls never miss. Visible lines whose text was shaped before with other styles,
+/// such as before the buffer was parsed again, are shown with that stale layout while they're
+/// shaped on a background thread, rather than waiting on shaping them.
+#[derive(Default)]
+pub(crate) struct LineLayoutCache {
+    lines: HashMap<LineLayoutKey, CachedLine>,
+    /// The key of the line last shaped with the text of the given key, ignoring its styles.
+    lines_by_text: HashMap<LineLayoutKey, LineLayoutKey>,
+    text_style: Option<TextStyle>,
+    style_revision: usize,
+    wrap_width: Option<Pixels>,
+    frame: usize,
+    mode: ShapingMode,
+    stats: LineLayoutStats,
+    overscanned_rows: Option<(Range<u32>, usize)>,
+    prefetched_rows: Option<(Range<u32>, usize)>,
+    stale_queue: Vec<(LineLayoutKey, String, Vec<TextRun>, Pixels)>,
+    pending: HashSet<LineLayoutKey>,
+    prefetched: Arc<Mutex<Vec<(LineLayoutKey, CachedLine)>>>,
+}
+
+/// How often the lines of the visible rows were already shaped when laying them out, for tuning
+/// the number of rows laid out around them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LineLayoutStats {
+    /// The number of visible lines that were shaped in a previous frame.
+    pub hits: usize,
+    /// The number of visible lines that had to be shaped while laying out the frame.
+    pub misses: usize,
+    /// The number of visible lines shown with a stale layout of their text while they're shaped
+    /// on a background thread.
+    pub stale: usize,
+    /// The number of lines shaped around the visible ones while laying out the frame.
+    pub overscanned: usize,
+    /// The number of lines shaped on a background thread.
+    pub prefetched: usize,
+}
+
+impl LineLayoutStats {
+    /// The fraction of visible lines that didn't need to be shaped when they were laid out.
+    pub fn hit_rate(&self) -> f32 {
+        let total = self.hits + self.stale + self.misses;
+        if total == 0 {
+            1.
+        } else {
+            (self.hits + self.stale) as f32 / total as f32
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+enum ShapingMode {
+    #[default]
+    Visible,
+    Overscan,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct LineLayoutKey {
+    content_hash: u64,
+    style_revision: usize,
+    wrap_width: Option<Pixels>,
+}
+
+struct CachedLine {
+    text: String,
+    runs: Vec<TextRun>,
+    font_size: Pixels,
+    line: ShapedLine,
+    last_used: usize,
+}
+
+impl LineLayoutCache {
+    /// Starts laying out the lines of a frame, dropping the cached lines if the editor's text
+    /// style changed since the last frame.
+    pub fn begin_frame(&mut self, text_style: &TextStyle, wrap_width: Option<Pixels>) {
+        if self.text_style.as_ref() != Some(text_style) {
+            self.text_style = Some(text_style.clone());
+            self.invalidate();
+        }
+        self.wrap_width = wrap_width;
+        self.frame += 1;
+
+        let frame = self.frame;
+        let style_revision = self.style_revision;
+        for (key, mut line) in self.prefetched.lock().drain(..) {
+            self.pending.remove(&key);
+            // Lines shaped with the style the cache was invalidated for are dropped.
+            if key.style_revision == style_revision {
+                line.last_used = frame;
+                let text_key = self.text_key(&line.text, line.font_size);
+                self.lines_by_text.insert(text_key, key);
+                self.lines.insert(key, line);
+                self.stats.prefetched += 1;
+            }
+        }
+    }
+
+    /// Evicts the least recently used lines once there are too many.
+    pub fn end_frame(&mut self) {
+        if self.lines.len() <= MAX_CACHED_LINES {
+            return;
+        }
+        let mut last_used = self
+            .lines
+            .values()
+            .map(|line| line.last_used)
+            .collect::<Vec<_>>();
+        last_used.sort_unstable_by(|a, b| b.cmp(a));
+        let oldest_kept = last_used[MAX_CACHED_LINES - 1];
+        self.lines.retain(|_, line| line.last_used >= oldest_kept);
+        let lines = &self.lines;
+        self.lines_by_text.retain(|_, key| lines.contains_key(key));
+    }
+
+    /// Makes the lines shaped so far stale, such as after the editor's text style changed.
+    pub fn invalidate(&mut self) {
+        self.style_revision += 1;
+        self.lines.clear();
+        self.lines_by_text.clear();
+        self.stale_queue.clear();
+        self.pending.clear();
+        self.overscanned_rows = None;
+        self.prefetched_rows = None;
+    }
+
+    /// Lays out and prefetches the rows around the visible ones again after an edit moved or
+    /// changed them. The lines shaped so far stay cached, since they're keyed by their text and
+    /// styles.
+    pub fn forget_prefetched_rows(&mut self) {
+        self.overscanned_rows = None;
+        self.prefetched_rows = None;
+    }
+
+    /// Returns the rows within `overscan` rows of the visible ones that weren't laid out around
+    /// the visible rows of a previous frame yet, given the number of times the buffer was parsed.
+    pub fn rows_to_overscan(
+        &mut self,
+        visible_rows: Range<u32>,
+        overscan: u32,
+        row_count: u32,
+        parse_count: usize,
+    ) -> Vec<Range<u32>> {
+        uncovered_rows(
+            &mut self.overscanned_rows,
+            visible_rows,
+            overscan,
+            row_count,
+            parse_count,
+        )
+    }
+
+    /// Returns the rows within `margin` rows of the visible ones that weren't shaped in the
+    /// background yet, given the number of times the buffer was parsed, which changes their
+    /// highlighting.
+    pub fn rows_to_prefetch(
+        &mut self,
+        visible_rows: Range<u32>,
+        margin: u32,
+        row_count: u32,
+        parse_count: usize,
+    ) -> Vec<Range<u32>> {
+        uncovered_rows(
+            &mut self.prefetched_rows,
+            visible_rows,
+            margin,
+            row_count,
+            parse_count,
+        )
+    }
+
+    /// Counts the lines passed to [`Self::shape_line`] as laid out around the visible ones until
+    /// [`Self::end_overscan`] is called, rather than as hits or misses.
+    pub fn begin_overscan(&mut self) {
+        self.mode = ShapingMode::Overscan;
+    }
+
+    /// Counts the lines passed to [`Self::shape_line`] as visible ones again.
+    pub fn end_overscan(&mut self) {
+        self.mode = ShapingMode::Visible;
+    }
+
+    /// Gets the text and styles of lines from `lines` and shapes them on a background thread,
+    /// making them available from the next frame, so that highlighting the lines doesn't block the
+    /// frame either. Lines that were shaped before are skipped.
+    pub fn prefetch(
+        &mut self,
+        font_size: Pixels,
+        lines: impl 'static + Send + FnOnce() -> Vec<(String, Vec<TextRun>)>,
+        cx: &WindowContext,
+    ) {
+        let shaped_keys = self
+            .lines
+            .keys()
+            .chain(&self.pending)
+            .copied()
+            .collect::<HashSet<_>>();
+        let style_revision = self.style_revision;
+        let wrap_width = self.wrap_width;
+        let text_system = cx.text_system().clone();
+        let prefetched = self.prefetched.clone();
+        cx.background_executor()
+            .spawn(async move {
+                let lines = lines()
+                    .into_iter()
+                    .filter_map(|(text, runs)| {
+                        let key = LineLayoutKey {
+                            content_hash: content_hash(&text, font_size, &runs),
+                            style_revision,
+                            wrap_width,
+                        };
+                        if shaped_keys.contains(&key) {
+                            return None;
+                        }
+                        shape_cached_line(&text_system, key, text, runs, font_size)
+                    })
+                    .collect::<Vec<_>>();
+                prefetched.lock().extend(lines);
+            })
+            .detach();
+    }
+
+    /// Shapes the lines of the visible rows that were laid out with a stale layout on a background
+    /// thread, and redraws the window once they're shaped so that the rows are laid out again.
+    pub fn shape_stale_lines(&mut self, cx: &mut WindowContext) {
+        if self.stale_queue.is_empty() {
+            return;
+        }
+
+        let queue = std::mem::take(&mut self.stale_queue);
+        let text_system = cx.text_system().clone();
+        let prefetched = self.prefetched.clone();
+        let shape_lines = cx.background_executor().spawn(async move {
+            let lines = queue
+                .into_iter()
+                .filter_map(|(key, text, runs, font_size)| {
+                    shape_cached_line(&text_system, key, text, runs, font_size)
+                })
+                .collect::<Vec<_>>();
+            prefetched.lock().extend(lines);
+        });
+        cx.spawn(|mut cx| async move {
+            shape_lines.await;
+            cx.update(|cx| cx.refresh()).ok();
+        })
+        .detach();
+    }
+
+    /// Returns the shaped line with the given text and styles, shaping it unless it was shaped
+    /// before, along with whether it's a stale layout of the same text with other styles. Visible
+    /// lines are only laid out with a stale layout while they're shaped on a background thread.
+    pub fn shape_line(
+        &mut self,
+        text: &str,
+        font_size: Pixels,
+        runs: &[TextRun],
+        cx: &WindowContext,
+    ) -> Result<(ShapedLine, bool)> {
+        let key = LineLayoutKey {
+            content_hash: content_hash(text, font_size, runs),
+            style_revision: self.style_revision,
+            wrap_width: self.wrap_width,
+        };
+        if let Some(cached) = self.lines.get_mut(&key) {
+            if cached.text == text && cached.runs == runs {
+                cached.last_used = self.frame;
+                if self.mode == ShapingMode::Visible {
+                    self.stats.hits += 1;
+                }
+                return Ok((cached.line.clone(), false));
+            }
+        }
+
+        match self.mode {
+            ShapingMode::Visible => {
+                if let Some(stale_line) = self.stale_line(text, font_size) {
+                    self.stats.stale += 1;
+                    if self.pending.insert(key) {
+                        self.stale_queue
+                            .push((key, text.to_string(), runs.to_vec(), font_size));
+                    }
+                    return Ok((stale_line, true));
+                }
+                self.stats.misses += 1;
+            }
+            ShapingMode::Overscan => self.stats.overscanned += 1,
+        }
+
+        let line =
+            cx.text_system()
+                .shape_line(SharedString::from(text.to_string()), font_size, runs)?;
+        let text_key = self.text_key(text, font_size);
+        self.lines_by_text.insert(text_key, key);
+        self.lines.insert(
+            key,
+            CachedLine {
+                text: text.to_string(),
+                runs: runs.to_vec(),
+                font_size,
+                line: line.clone(),
+                last_used: self.frame,
+            },
+        );
+        Ok((line, false))
+    }
+
+    /// Returns the layout of the line last shaped with the given text, whatever its styles.
+    fn stale_line(&self, text: &str, font_size: Pixels) -> Option<ShapedLine> {
+        let key = self.lines_by_text.get(&self.text_key(text, font_size))?;
+        let cached = self.lines.get(key).filter(|cached| cached.text == text)?;
+        Some(cached.line.clone())
+    }
+
+    /// Returns the key of the lines with the given text, ignoring their styles.
+    fn text_key(&self, text: &str, font_size: Pixels) -> LineLayoutKey {
+        LineLayoutKey {
+            content_hash: content_hash(text, font_size, &[]),
+            style_revision: self.style_revision,
+            wrap_width: self.wrap_width,
+        }
+    }
+
+    /// Returns how many lines were found shaped or had to be shaped since the editor was created.
+    pub fn stats(&self) -> LineLayoutStats {
+        self.stats
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.lines.len()
+    }
+}
+
+fn shape_cached_line(
+    text_system: &Arc<WindowTextSystem>,
+    key: LineLayoutKey,
+    text: String,
+    runs: Vec<TextRun>,
+    font_size: Pixels,
+) -> Option<(LineLayoutKey, CachedLine)> {
+    let line = text_system
+        .shape_line(SharedString::from(text.clone()), font_size, &runs)
+        .log_err()?;
+    Some((
+        key,
+        CachedLine {
+            text,
+            runs,
+            font_size,
+            line,
+            last_used: 0,
+        },
+    ))
+}
+
+/// Returns the rows within `margin` rows of the visible ones that aren't in the given rows covered
+/// in a previous frame, which are replaced by the rows covered in this one. All of them are
+/// returned when the buffer was parsed again since, or when the two don't overlap.
+fn uncovered_rows(
+    covered_rows: &mut Option<(Range<u32>, usize)>,
+    visible_rows: Range<u32>,
+    margin: u32,
+    row_count: u32,
+    parse_count: usize,
+) -> Vec<Range<u32>> {
+    let rows = visible_rows.start.saturating_sub(margin)
+        ..visible_rows.end.saturating_add(margin).min(row_count);
+    let covered_rows = match covered_rows.replace((rows.clone(), parse_count)) {
+        Some((covered_rows, covered_parse_count))
+            if covered_parse_count == parse_count
+                && covered_rows.start < rows.end
+                && rows.start < covered_rows.end =>
+        {
+            covered_rows
+        }
+        _ => visible_rows.clone(),
+    };
+    [
+        rows.start..covered_rows.start.clamp(rows.start, rows.end),
+        covered_rows.end.clamp(rows.start, rows.end)..rows.end,
+    ]
+    .into_iter()
+    .filter(|rows| !rows.is_empty())
+    .collect()
+}
+
+fn content_hash(text: &str, font_size: Pixels, runs: &[TextRun]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    font_size.hash(&mut hasher);
+    for run in runs {
+        run.len.hash(&mut hasher);
+        run.font.hash(&mut hasher);
+        hash_color(Some(run.color), &mut hasher);
+        hash_color(run.background_color, &mut hasher);
+        if let Some(underline) = run.underline {
+            underline.thickness.hash(&mut hasher);
+            hash_color(underline.color, &mut hasher);
+            underline.wavy.hash(&mut hasher);
+        }
+        if let Some(strikethrough) = run.strikethrough {
+            strikethrough.thickness.hash(&mut hasher);
+            hash_color(strikethrough.color, &mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+fn hash_color(color: Option<Hsla>, hasher: &mut impl Hasher) {
+    if let Some(color) = color {
+        for component in [color.h, color.s, color.l, color.a] {
+            component.to_bits().hash(hasher);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::{font, px, TestAppContext};
+
+    #[gpui::test]
+    fn test_line_layout_cache(cx: &mut TestAppContext) {
+        let window = cx.add_empty_window();
+        let text_style = TextStyle::default();
+        let run = |len| TextRun {
+            len,
+            font: font("Helvetica"),
+            color: gpui::black(),
+            background_color: None,
+            underline: None,
+            strikethrough: None,
+        };
+        let mut red = run(3);
+        red.color = gpui::red();
+        let mut cache = LineLayoutCache::default();
+
+        window.update(|cx| {
+            cache.begin_frame(&text_style, None);
+            cache.shape_line("one", px(14.), &[run(3)], cx).unwrap();
+            cache.shape_line("two", px(14.), &[run(3)], cx).unwrap();
+            cache.end_frame();
+            assert_eq!(cache.len(), 2);
+
+            // Unchanged lines are reused in the next frame.
+            cache.begin_frame(&text_style, None);
+            let (_, is_stale) = cache.shape_line("one", px(14.), &[run(3)], cx).unwrap();
+            assert!(!is_stale);
+            cache.end_frame();
+            assert_eq!(cache.len(), 2);
+
+            // Styling a line differently shows its stale layout while it's shaped again in the
+            // background.
+            cache.begin_frame(&text_style, None);
+            let (_, is_stale) = cache
+                .shape_line("one", px(14.), &[red.clone()], cx)
+                .unwrap();
+            assert!(is_stale);
+            cache.end_frame();
+            cache.shape_stale_lines(cx);
+            assert_eq!(cache.len(), 2);
+        });
+        window.run_until_parked();
+
+        window.update(|cx| {
+            cache.begin_frame(&text_style, None);
+            assert_eq!(cache.len(), 3);
+            let (_, is_stale) = cache.shape_line("one", px(14.), &[red], cx).unwrap();
+            assert!(!is_stale);
+            cache.end_frame();
+
+            // Lines laid out around the visible ones don't count towards the hit rate.
+            cache.begin_frame(&text_style, None);
+            cache.begin_overscan();
+            cache.shape_line("three", px(14.), &[run(5)], cx).unwrap();
+            cache.end_overscan();
+            cache.shape_line("three", px(14.), &[run(5)], cx).unwrap();
+            cache.end_frame();
+            assert_eq!(
+                cache.stats(),
+                LineLayoutStats {
+                    hits: 3,
+                    misses: 2,
+                    stale: 1,
+                    overscanned: 1,
+                    prefetched: 1,
+                }
+            );
+            assert_eq!(cache.stats().hit_rate(), 2. / 3.);
+
+            cache.invalidate();
+            assert_eq!(cache.len(), 0);
+        });
+    }
+
+    #[gpui::test]
+    fn test_prefetching_lines(cx: &mut TestAppContext) {
+        let window = cx.add_empty_window();
+        let text_style = TextStyle::default();
+        let runs = [TextRun {
+            len: 3,
+            font: font("Helvetica"),
+            color: gpui::black(),
+            background_color: None,
+            underline: None,
+            strikethrough: None,
+        }];
+        let mut cache = LineLayoutCache::default();
+
+        // Only the rows around the visible ones that weren't prefetched yet are returned.
+        assert_eq!(cache.rows_to_prefetch(10..20, 10, 100, 0), [0..10, 20..30]);
+        assert_eq!(cache.rows_to_prefetch(15..25, 10, 100, 0), [30..35]);
+        assert_eq!(cache.rows_to_prefetch(15..25, 10, 100, 0), Vec::new());
+        assert_eq!(cache.rows_to_prefetch(90..100, 10, 100, 0), [80..90]);
+        assert_eq!(cache.rows_to_prefetch(90..100, 10, 100, 1), [80..90]);
+
+        window.update(|cx| {
+            cache.begin_frame(&text_style, None);
+            let lines = vec![("one".to_string(), runs.to_vec())];
+            cache.prefetch(px(14.), move || lines, cx);
+            cache.end_frame();
+            assert_eq!(cache.len(), 0);
+        });
+        window.run_until_parked();
+
+        // Prefetched lines are available from the next frame, and aren't prefetched again.
+        window.update(|cx| {
+            cache.begin_frame(&text_style, None);
+            assert_eq!(cache.len(), 1);
+            let (_, is_stale) = cache.shape_line("one", px(14.), &runs, cx).unwrap();
+            assert!(!is_stale);
+            let lines = vec![("one".to_string(), runs.to_vec())];
+            cache.prefetch(px(14.), move || lines, cx);
+            cache.end_frame();
+        });
+        window.run_until_parked();
+        cache.begin_frame(&text_style, None);
+        cache.end_frame();
+        assert_eq!(
+            cache.stats(),
+            LineLayoutStats {
+                hits: 1,
+                prefetched: 1,
+                ..Default::default()
+            }
+        );
+
+        // Edits make the rows around the visible ones be prefetched again, keeping the lines.
+        cache.forget_prefetched_rows();
+        assert_eq!(cache.rows_to_prefetch(90..100, 10, 100, 1), [80..90]);
+        assert_eq!(cache.len(), 1);
+    }
+}
@@ -0,0 +1,257 @@
+use crate::{persistence::DB, Editor, ReplayMacro, StartRecordingMacro, StopRecordingMacro};
+use anyhow::{Context as _, Result};
+use gpui::{Action, AppContext, Global, ViewContext};
+use serde::{Deserialize, Serialize};
+use util::ResultExt;
+
+/// A step of a keyboard macro, replayed against the selections of the editor it's replayed in.
+pub(crate) enum MacroStep {
+    /// An action, along with its parameters as they'd be written in a keymap.
+    Action {
+        action: Box<dyn Action>,
+        data: serde_json::Value,
+    },
+    Input(String),
+}
+
+impl Clone for MacroStep {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Action { action, data } => Self::Action {
+                action: action.boxed_clone(),
+                data: data.clone(),
+            },
+            Self::Input(text) => Self::Input(text.clone()),
+        }
+    }
+}
+
+/// The macro that was recorded last, which is replayed when `ReplayMacro` isn't given a name.
+struct LastMacro(Vec<MacroStep>);
+
+impl Global for LastMacro {}
+
+/// The way macro steps are saved in the database. Actions are saved by name, along with their
+/// parameters.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum SerializedMacroStep {
+    Action {
+        name: String,
+        #[serde(default)]
+        data: serde_json::Value,
+    },
+    Input {
+        text: String,
+    },
+}
+
+impl Editor {
+    /// Starts recording the actions dispatched to the editor and the text typed in it, discarding
+    /// any recording in progress.
+    pub fn start_recording_macro(&mut self, _: &StartRecordingMacro, cx: &mut ViewContext<Self>) {
+        self.macro_recording = Some(Vec::new());
+        cx.notify();
+    }
+
+    /// Stops recording, making the recorded macro the one replayed by default. Named macros are
+    /// also saved in the workspace database.
+    pub fn stop_recording_macro(
+        &mut self,
+        action: &StopRecordingMacro,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(steps) = self.macro_recording.take() else {
+            return;
+        };
+        if let Some(name) = action.name.clone() {
+            self.save_macro(name, &steps, cx);
+        }
+        cx.set_global(LastMacro(steps));
+        cx.notify();
+    }
+
+    /// Replays the last recorded macro, or the one saved under the given name, `count` times.
+    pub fn replay_macro(&mut self, action: &ReplayMacro, cx: &mut ViewContext<Self>) {
+        // The replayed steps would be recorded into the macro being recorded.
+        if self.macro_recording.is_some() {
+            return;
+        }
+        let count = action.count.unwrap_or(1);
+        match action.name.clone() {
+            Some(name) => self.read_and_replay_macro(name, count, cx),
+            None => {
+                if let Some(steps) = cx.try_global::<LastMacro>().map(|last| last.0.clone()) {
+                    self.replay_macro_steps(&steps, count, cx);
+                }
+            }
+        }
+    }
+
+    fn replay_macro_steps(
+        &mut self,
+        steps: &[MacroStep],
+        count: usize,
+        cx: &mut ViewContext<Self>,
+    ) {
+        // Steps are deferred, so that each one is dispatched once the previous one was handled.
+        for _ in 0..count {
+            for step in steps {
+                match step {
+                    MacroStep::Action { action, .. } => cx.dispatch_action(action.boxed_clone()),
+                    MacroStep::Input(text) => {
+                        let text = text.clone();
+                        cx.defer(move |editor, cx| editor.handle_input(&text, cx));
+                    }
+                }
+            }
+        }
+    }
+
+    pub(crate) fn record_macro_action<A: Action + Serialize>(&mut self, action: &A) {
+        let Some(steps) = self.macro_recording.as_mut() else {
+            return;
+        };
+        let any_action = action.as_any();
+        if any_action.is::<StartRecordingMacro>()
+            || any_action.is::<StopRecordingMacro>()
+            || any_action.is::<ReplayMacro>()
+        {
+            return;
+        }
+        let Some(data) = serde_json::to_value(action).log_err() else {
+            return;
+        };
+        steps.push(MacroStep::Action {
+            action: action.boxed_clone(),
+            data,
+        });
+    }
+
+    pub(crate) fn record_macro_input(&mut self, text: &str) {
+        if let Some(steps) = self.macro_recording.as_mut() {
+            steps.push(MacroStep::Input(text.to_string()));
+        }
+    }
+
+    fn save_macro(&self, name: String, steps: &[MacroStep], cx: &mut ViewContext<Self>) {
+        let Some((_, workspace_id)) = self.workspace.as_ref() else {
+            return;
+        };
+        let workspace_id = *workspace_id;
+        let Some(steps) = serde_json::to_string(&serialize_macro(steps)).log_err() else {
+            return;
+        };
+        cx.background_executor()
+            .spawn(async move { DB.save_macro(workspace_id, name, steps).await.log_err() })
+            .detach();
+    }
+
+    /// Reads the macro saved under the given name from the workspace database, and replays it
+    /// once it was read.
+    fn read_and_replay_macro(&self, name: String, count: usize, cx: &mut ViewContext<Self>) {
+        let Some((_, workspace_id)) = self.workspace.as_ref() else {
+            return;
+        };
+        let workspace_id = *workspace_id;
+        cx.spawn(|this, mut cx| async move {
+            let Some(steps) = DB.get_macro(workspace_id, name.clone()).await? else {
+                return Ok(());
+            };
+            let steps = serde_json::from_str::<Vec<SerializedMacroStep>>(&steps)?;
+            this.update(&mut cx, |this, cx| {
+                let steps = deserialize_macro(steps, cx)
+                    .with_context(|| format!("failed to read macro {name:?}"))?;
+                if this.macro_recording.is_none() {
+                    this.replay_macro_steps(&steps, count, cx);
+                }
+                anyhow::Ok(())
+            })?
+        })
+        .detach_and_log_err(cx);
+    }
+}
+
+fn serialize_macro(steps: &[MacroStep]) -> Vec<SerializedMacroStep> {
+    steps
+        .iter()
+        .map(|step| match step {
+            MacroStep::Action { action, data } => SerializedMacroStep::Action {
+                name: action.name().to_string(),
+                data: data.clone(),
+            },
+            MacroStep::Input(text) => SerializedMacroStep::Input { text: text.clone() },
+        })
+        .collect()
+}
+
+/// Rebuilds the steps of a saved macro, failing if any of its actions can't be built anymore.
+fn deserialize_macro(steps: Vec<SerializedMacroStep>, cx: &AppContext) -> Result<Vec<MacroStep>> {
+    steps
+        .into_iter()
+        .map(|step| match step {
+            SerializedMacroStep::Action { name, data } => Ok(MacroStep::Action {
+                action: cx.build_action(&name, Some(data.clone()))?,
+                data,
+            }),
+            SerializedMacroStep::Input { text } => Ok(MacroStep::Input(text)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        editor_tests::init_test, test::editor_test_context::EditorTestContext, MoveDownByLines,
+        MoveToBeginningOfLine,
+    };
+
+    #[gpui::test]
+    async fn test_record_and_replay_macro(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state("ˇone\ntwo\nthree\nfour\nfive\nsix\nseven\n");
+
+        cx.dispatch_action(StartRecordingMacro);
+        cx.dispatch_action(MoveToBeginningOfLine);
+        cx.simulate_input("- ");
+        cx.dispatch_action(MoveDownByLines { lines: 2 });
+        cx.dispatch_action(StopRecordingMacro::default());
+        cx.assert_editor_state("- one\ntwo\nthˇree\nfour\nfive\nsix\nseven\n");
+
+        cx.dispatch_action(ReplayMacro {
+            name: None,
+            count: Some(2),
+        });
+        cx.run_until_parked();
+        cx.assert_editor_state("- one\ntwo\n- three\nfour\n- five\nsix\nseˇven\n");
+
+        // The macro actions themselves aren't recorded, and the parameters of the recorded actions
+        // are saved along with them.
+        cx.update(|cx| {
+            let steps = serialize_macro(&cx.global::<LastMacro>().0);
+            let expected_steps = serde_json::json!([
+                { "kind": "action", "name": "editor::MoveToBeginningOfLine", "data": null },
+                { "kind": "input", "text": "-" },
+                { "kind": "input", "text": " " },
+                { "kind": "action", "name": "editor::MoveDownByLines", "data": { "lines": 2 } },
+            ]);
+            assert_eq!(serde_json::to_value(&steps).unwrap(), expected_steps);
+
+            let steps = deserialize_macro(steps, cx).unwrap();
+            assert_eq!(
+                serde_json::to_value(serialize_macro(&steps)).unwrap(),
+                expected_steps
+            );
+
+            // Macros with actions that can't be built anymore fail to deserialize.
+            let steps = vec![SerializedMacroStep::Action {
+                name: "editor::NoSuchAction".into(),
+                data: serde_json::Value::Null,
+            }];
+            assert!(deserialize_macro(steps, cx).is_err());
+        });
+    }
+}
@@ -0,0 +1,441 @@
+use crate::{
+    display_map::ToDisplayPoint, persistence::DB, scroll::Autoscroll, Anchor, Editor,
+    EditorSnapshot, GoToMark, SetMark,
+};
+use gpui::ViewContext;
+use language::{Bias, Point};
+use multi_buffer::ToPoint;
+use std::{ops::Range, path::PathBuf};
+use util::ResultExt;
+use workspace::WorkspaceId;
+
+/// A named position in the editor's buffer.
+pub(crate) struct Mark {
+    anchor: Anchor,
+}
+
+impl Mark {
+    fn new(anchor: Anchor) -> Self {
+        Self { anchor }
+    }
+}
+
+/// Uppercase marks are global to the workspace, and are saved along with the file they're in.
+fn is_global_mark(name: char) -> bool {
+    name.is_ascii_uppercase()
+}
+
+impl Editor {
+    /// Sets a mark with the action's name at the head of the newest selection.
+    pub fn set_mark(&mut self, action: &SetMark, cx: &mut ViewContext<Self>) {
+        let head = self.selections.newest_anchor().head();
+        self.insert_mark(action.name, head, cx);
+    }
+
+    /// Moves the cursor to the mark with the action's name. Global marks are looked up in the
+    /// workspace database in the background, and those that were last set in another file are
+    /// jumped to by opening that file.
+    pub fn go_to_mark(&mut self, action: &GoToMark, cx: &mut ViewContext<Self>) {
+        let name = action.name;
+        let workspace_id = self
+            .workspace
+            .as_ref()
+            .map(|(_, workspace_id)| *workspace_id);
+        let Some(workspace_id) = workspace_id.filter(|_| is_global_mark(name)) else {
+            self.jump_to_mark(name, cx);
+            return;
+        };
+
+        let path = self.marks_path(cx);
+        cx.spawn(|this, mut cx| async move {
+            let global_mark = DB.get_mark(workspace_id, name.to_string()).await?;
+            this.update(&mut cx, |this, cx| {
+                if let Some((mark_path, row, column)) = global_mark {
+                    let point = Point::new(row, column);
+                    if Some(&mark_path) != path.as_ref() {
+                        if this.marks.remove(&name).is_some() {
+                            this.scrollbar_marker_cache.invalidate();
+                        }
+                        this.open_global_mark(name, mark_path, point, cx);
+                        return;
+                    }
+                    if !this.marks.contains_key(&name) {
+                        let snapshot = this.buffer.read(cx).snapshot(cx);
+                        let anchor = snapshot.anchor_before(snapshot.clip_point(point, Bias::Left));
+                        this.marks.insert(name, Mark::new(anchor));
+                        this.scrollbar_marker_cache.invalidate();
+                    }
+                }
+                this.jump_to_mark(name, cx);
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    /// Sets the mark with the given name to the given position, replacing the mark with the same
+    /// name. Marks move along with the text around them as the buffer is edited.
+    pub fn insert_mark(&mut self, name: char, position: Anchor, cx: &mut ViewContext<Self>) {
+        self.marks.insert(name, Mark::new(position));
+        self.scrollbar_marker_cache.invalidate();
+        if is_global_mark(name) {
+            self.save_global_mark(name, position, cx);
+        }
+        cx.notify();
+    }
+
+    /// Removes the mark with the given name from the editor, returning its position.
+    pub fn remove_mark(&mut self, name: char, cx: &mut ViewContext<Self>) -> Option<Anchor> {
+        let mark = self.marks.remove(&name)?;
+        self.scrollbar_marker_cache.invalidate();
+        if is_global_mark(name) {
+            if let Some(((_, workspace_id), path)) =
+                self.workspace.as_ref().zip(self.marks_path(cx))
+            {
+                let workspace_id = *workspace_id;
+                cx.background_executor()
+                    .spawn(async move {
+                        DB.delete_mark(workspace_id, name.to_string(), path)
+                            .await
+                            .log_err()
+                    })
+                    .detach();
+            }
+        }
+        cx.notify();
+        Some(mark.anchor)
+    }
+
+    /// Returns the position of the mark with the given name, if it's set in this editor.
+    pub fn mark(&self, name: char) -> Option<Anchor> {
+        self.marks.get(&name).map(|mark| mark.anchor)
+    }
+
+    /// Returns the marks set in this editor, ordered by name.
+    pub fn marks(&self) -> impl Iterator<Item = (char, Anchor)> + '_ {
+        self.marks.iter().map(|(name, mark)| (*name, mark.anchor))
+    }
+
+    /// Returns the names of the marks shown in the given range of display rows, along with their
+    /// display row, sorted by row.
+    pub(crate) fn mark_display_rows(
+        &self,
+        rows: Range<u32>,
+        snapshot: &EditorSnapshot,
+    ) -> Vec<(char, u32)> {
+        let mut marks = self
+            .marks
+            .iter()
+            .map(|(name, mark)| {
+                let row = mark
+                    .anchor
+                    .to_display_point(&snapshot.display_snapshot)
+                    .row();
+                (*name, row)
+            })
+            .filter(|(_, row)| rows.contains(row))
+            .collect::<Vec<_>>();
+        marks.sort_unstable_by_key(|(_, row)| *row);
+        marks
+    }
+
+    /// Restores the global marks that were last set in the editor's file in the given workspace,
+    /// reading them from the database in the background.
+    pub(crate) fn read_marks_from_db(
+        &mut self,
+        workspace_id: WorkspaceId,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(path) = self.marks_path(cx) else {
+            return;
+        };
+        cx.spawn(|this, mut cx| async move {
+            let marks = DB.get_marks(workspace_id, path).await?;
+            this.update(&mut cx, |this, cx| {
+                let snapshot = this.buffer.read(cx).snapshot(cx);
+                for (name, row, column) in marks {
+                    let Some(name) = name.chars().next() else {
+                        continue;
+                    };
+                    let point = snapshot.clip_point(Point::new(row, column), Bias::Left);
+                    this.marks
+                        .entry(name)
+                        .or_insert_with(|| Mark::new(snapshot.anchor_before(point)));
+                }
+                this.scrollbar_marker_cache.invalidate();
+                cx.notify();
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    /// Saves the positions of the global marks in the editor, which move with unsaved edits, for
+    /// the marks that weren't set in another file since.
+    pub(crate) fn save_marks(&self, cx: &mut ViewContext<Self>) {
+        let Some((_, workspace_id)) = self.workspace.as_ref() else {
+            return;
+        };
+        let Some(path) = self.marks_path(cx) else {
+            return;
+        };
+        let workspace_id = *workspace_id;
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let marks = self
+            .marks
+            .iter()
+            .filter(|(name, _)| is_global_mark(**name))
+            .map(|(name, mark)| (name.to_string(), mark.anchor.to_point(&snapshot)))
+            .collect::<Vec<_>>();
+        if marks.is_empty() {
+            return;
+        }
+        cx.background_executor()
+            .spawn(async move {
+                for (name, point) in marks {
+                    DB.update_mark(workspace_id, name, path.clone(), point.row, point.column)
+                        .await
+                        .log_err();
+                }
+            })
+            .detach();
+    }
+
+    fn save_global_mark(&self, name: char, position: Anchor, cx: &mut ViewContext<Self>) {
+        let Some((_, workspace_id)) = self.workspace.as_ref() else {
+            return;
+        };
+        let Some(path) = self.marks_path(cx) else {
+            return;
+        };
+        let workspace_id = *workspace_id;
+        let point = position.to_point(&self.buffer.read(cx).snapshot(cx));
+        cx.background_executor()
+            .spawn(async move {
+                DB.save_mark(
+                    workspace_id,
+                    name.to_string(),
+                    path,
+                    point.row,
+                    point.column,
+                )
+                .await
+                .log_err()
+            })
+            .detach();
+    }
+
+    /// Opens the file of a global mark and jumps to the mark in it.
+    fn open_global_mark(
+        &mut self,
+        name: char,
+        path: PathBuf,
+        point: Point,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some((workspace, _)) = self.workspace.as_ref() else {
+            return;
+        };
+        let Some(open) = workspace
+            .update(cx, |workspace, cx| workspace.open_abs_path(path, true, cx))
+            .log_err()
+        else {
+            return;
+        };
+        cx.spawn(|_, mut cx| async move {
+            let item = open.await?;
+            if let Some(editor) = item.downcast::<Editor>() {
+                editor.update(&mut cx, |editor, cx| {
+                    if !editor.marks.contains_key(&name) {
+                        let snapshot = editor.buffer.read(cx).snapshot(cx);
+                        let anchor = snapshot.anchor_before(snapshot.clip_point(point, Bias::Left));
+                        editor.marks.insert(name, Mark::new(anchor));
+                        editor.scrollbar_marker_cache.invalidate();
+                    }
+                    editor.jump_to_mark(name, cx);
+                })?;
+            }
+            anyhow::Ok(())
+        })
+        .detach_and_log_err(cx);
+    }
+
+    /// Moves the cursor to the mark with the given name, if it's set in this editor.
+    fn jump_to_mark(&mut self, name: char, cx: &mut ViewContext<Self>) {
+        let Some(mark) = self.marks.get(&name) else {
+            return;
+        };
+        let point = mark.anchor.to_point(&self.buffer.read(cx).snapshot(cx));
+        self.change_selections(Some(Autoscroll::center()), cx, |s| {
+            s.select_ranges([point..point])
+        });
+    }
+
+    /// Global marks are saved for editors showing a single local file.
+    fn marks_path(&self, cx: &ViewContext<Self>) -> Option<PathBuf> {
+        let buffer = self.buffer.read(cx).as_singleton()?;
+        let file = buffer.read(cx).file()?.as_local()?;
+        Some(file.abs_path(cx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+    use gpui::{View, VisualTestContext, WindowHandle};
+    use indoc::indoc;
+    use project::{FakeFs, Project};
+    use serde_json::json;
+    use workspace::{SaveIntent, Workspace, WORKSPACE_DB};
+
+    #[gpui::test]
+    async fn test_marks(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state(indoc! {"
+            one
+            two ˇthree
+            four
+        "});
+        cx.dispatch_action(SetMark { name: 'a' });
+        cx.update_editor(|editor, cx| {
+            editor.change_selections(None, cx, |s| s.select_ranges([18..18]))
+        });
+        cx.dispatch_action(SetMark { name: 'b' });
+
+        // Marks move with the text around them.
+        cx.update_editor(|editor, cx| {
+            editor.change_selections(None, cx, |s| s.select_ranges([0..0]));
+            editor.handle_input("zero\n", cx);
+        });
+        cx.dispatch_action(GoToMark { name: 'a' });
+        cx.assert_editor_state(indoc! {"
+            zero
+            one
+            two ˇthree
+            four
+        "});
+        cx.update_editor(|editor, cx| {
+            let marks = editor.marks().map(|(name, _)| name).collect::<Vec<_>>();
+            assert_eq!(marks, ['a', 'b']);
+            assert!(editor.remove_mark('b', cx).is_some());
+            assert_eq!(editor.mark('b'), None);
+        });
+
+        // Going to a mark that isn't set leaves the selections alone.
+        cx.dispatch_action(GoToMark { name: 'b' });
+        cx.assert_editor_state(indoc! {"
+            zero
+            one
+            two ˇthree
+            four
+        "});
+    }
+
+    #[gpui::test]
+    async fn test_global_marks(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/root",
+            json!({ "a.txt": "one\ntwo\nthree\n", "b.txt": "four\nfive\n" }),
+        )
+        .await;
+        let project = Project::test(fs, ["/root".as_ref()], cx).await;
+        let workspace = cx.add_window(|cx| Workspace::test_new(project.clone(), cx));
+        let cx = &mut VisualTestContext::from_window(*workspace, cx);
+        let workspace_id = WORKSPACE_DB.next_id().await.unwrap();
+
+        let editor_a = open_editor(&workspace, "/root/a.txt", workspace_id, cx).await;
+        editor_a.update(cx, |editor, cx| {
+            editor.change_selections(None, cx, |s| {
+                s.select_ranges([Point::new(1, 2)..Point::new(1, 2)])
+            });
+            editor.set_mark(&SetMark { name: 'A' }, cx);
+        });
+        cx.run_until_parked();
+        assert_eq!(
+            DB.get_mark(workspace_id, "A".to_string()).await.unwrap(),
+            Some((PathBuf::from("/root/a.txt"), 1, 2))
+        );
+
+        // Global marks outlive the editors they were set in, and going to one whose file isn't
+        // open opens it again.
+        let editor_b = open_editor(&workspace, "/root/b.txt", workspace_id, cx).await;
+        workspace
+            .update(cx, |workspace, cx| {
+                workspace.active_pane().update(cx, |pane, cx| {
+                    pane.close_item_by_id(editor_a.entity_id(), SaveIntent::Skip, cx)
+                })
+            })
+            .unwrap()
+            .await
+            .unwrap();
+        editor_b.update(cx, |editor, cx| {
+            editor.go_to_mark(&GoToMark { name: 'A' }, cx)
+        });
+        cx.run_until_parked();
+        let editor_a = workspace
+            .update(cx, |workspace, cx| workspace.active_item_as::<Editor>(cx))
+            .unwrap()
+            .unwrap();
+        editor_a.update(cx, |editor, cx| {
+            assert_eq!(editor.marks_path(cx), Some(PathBuf::from("/root/a.txt")));
+            assert_eq!(
+                editor.selections.newest::<Point>(cx).head(),
+                Point::new(1, 2)
+            );
+        });
+
+        // Setting a global mark in another file moves it there, and the file it was set in before
+        // doesn't save its position anymore.
+        use_workspace_id(&editor_a, workspace_id, cx);
+        editor_b.update(cx, |editor, cx| editor.set_mark(&SetMark { name: 'A' }, cx));
+        cx.run_until_parked();
+        editor_a.update(cx, |editor, cx| editor.save_marks(cx));
+        cx.run_until_parked();
+        assert_eq!(
+            DB.get_mark(workspace_id, "A".to_string()).await.unwrap(),
+            Some((PathBuf::from("/root/b.txt"), 0, 0))
+        );
+        assert!(DB
+            .get_marks(workspace_id, PathBuf::from("/root/a.txt"))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    async fn open_editor(
+        workspace: &WindowHandle<Workspace>,
+        path: &str,
+        workspace_id: WorkspaceId,
+        cx: &mut VisualTestContext,
+    ) -> View<Editor> {
+        let editor = workspace
+            .update(cx, |workspace, cx| {
+                workspace.open_abs_path(PathBuf::from(path), true, cx)
+            })
+            .unwrap()
+            .await
+            .unwrap()
+            .downcast::<Editor>()
+            .unwrap();
+        use_workspace_id(&editor, workspace_id, cx);
+        editor
+    }
+
+    /// Test workspaces aren't saved in the database, so their editors are given the id of one
+    /// that is for their marks to be saved.
+    fn use_workspace_id(
+        editor: &View<Editor>,
+        workspace_id: WorkspaceId,
+        cx: &mut VisualTestContext,
+    ) {
+        editor.update(cx, |editor, _| {
+            if let Some((_, id)) = editor.workspace.as_mut() {
+                *id = workspace_id;
+            }
+        });
+    }
+}
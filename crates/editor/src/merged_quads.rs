@@ -0,0 +1,116 @@
+use gpui::{fill, Bounds, ElementContext, Hsla, PaintQuad, Pixels};
+
+/// Collects the quads the editor paints at the same z-index, such as the backgrounds of the
+/// active lines and the cursors, so that filled rectangles that continue the previous one with the
+/// same color, like the backgrounds of consecutive rows or the cursors of a column selection, are
+/// painted as a single quad.
+///
+/// Only a fill added right after the one it continues is merged into it, and the remaining quads
+/// are still painted one at a time, so this saves work in proportion to how many fills it merges.
+pub(crate) struct MergedQuads {
+    quads: Vec<MergedQuad>,
+    merge: bool,
+}
+
+impl Default for MergedQuads {
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+enum MergedQuad {
+    Fill(Bounds<Pixels>, Hsla),
+    Quad(PaintQuad),
+}
+
+impl MergedQuads {
+    /// Creates a collection that merges the rectangles it's given only if `merge` is set, which is only
+    /// turned off to measure the time merging saves.
+    pub fn new(merge: bool) -> Self {
+        Self {
+            quads: Vec::new(),
+            merge,
+        }
+    }
+
+    /// Adds a rectangle filled with the given color.
+    pub fn fill(&mut self, bounds: Bounds<Pixels>, color: Hsla) {
+        if let Some(MergedQuad::Fill(last_bounds, last_color)) = self.quads.last_mut() {
+            if self.merge && *last_color == color && continues(last_bounds, &bounds) {
+                *last_bounds = last_bounds.union(&bounds);
+                return;
+            }
+        }
+        self.quads.push(MergedQuad::Fill(bounds, color));
+    }
+
+    /// Adds a quad with rounded corners or borders, which isn't merged with the others.
+    pub fn quad(&mut self, quad: PaintQuad) {
+        self.quads.push(MergedQuad::Quad(quad));
+    }
+
+    /// Paints the quads added since they were last painted.
+    pub fn paint(&mut self, cx: &mut ElementContext) {
+        for quad in self.quads.drain(..) {
+            cx.paint_quad(match quad {
+                MergedQuad::Fill(bounds, color) => fill(bounds, color),
+                MergedQuad::Quad(quad) => quad,
+            });
+        }
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.quads.len()
+    }
+}
+
+/// Whether the second rectangle is right below or right of the first one and spans the same
+/// columns or rows, so that their union is a rectangle too.
+fn continues(first: &Bounds<Pixels>, second: &Bounds<Pixels>) -> bool {
+    let below = first.origin.x == second.origin.x
+        && first.size.width == second.size.width
+        && first.lower_left().y == second.origin.y;
+    let right = first.origin.y == second.origin.y
+        && first.size.height == second.size.height
+        && first.upper_right().x == second.origin.x;
+    below || right
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::{outline, point, px, size};
+
+    #[test]
+    fn test_merging_quads() {
+        let row = |ix: f32, x: f32| Bounds {
+            origin: point(px(x), px(ix * 10.)),
+            size: size(px(100.), px(10.)),
+        };
+        let mut quads = MergedQuads::default();
+
+        // Consecutive rows with the same background are merged.
+        quads.fill(row(0., 0.), gpui::red());
+        quads.fill(row(1., 0.), gpui::red());
+        quads.fill(row(2., 0.), gpui::red());
+        assert_eq!(quads.len(), 1);
+
+        // Rows further down, at another offset or of another color aren't.
+        quads.fill(row(4., 0.), gpui::red());
+        quads.fill(row(5., 10.), gpui::red());
+        quads.fill(row(6., 10.), gpui::blue());
+        assert_eq!(quads.len(), 4);
+
+        // Neither are quads with borders.
+        quads.quad(outline(row(7., 10.), gpui::blue()));
+        quads.fill(row(8., 10.), gpui::blue());
+        assert_eq!(quads.len(), 6);
+
+        // Nothing is merged when merging is turned off.
+        let mut quads = MergedQuads::new(false);
+        quads.fill(row(0., 0.), gpui::red());
+        quads.fill(row(1., 0.), gpui::red());
+        assert_eq!(quads.len(), 2);
+    }
+}
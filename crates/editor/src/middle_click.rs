@@ -0,0 +1,215 @@
+use crate::{scroll::Autoscroll, DisplayPoint, Editor};
+use gpui::{point, px, ClipboardItem, Pixels, Point, Task, ViewContext};
+use std::time::{Duration, Instant};
+
+/// How long the selections have to stay unchanged before they're written to the primary selection.
+const PRIMARY_SELECTION_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// How often the editor scrolls while autoscrolling.
+const AUTOSCROLL_INTERVAL: Duration = Duration::from_millis(16);
+
+/// How far the mouse can move away from the anchor before the editor starts scrolling.
+const AUTOSCROLL_DEAD_ZONE: Pixels = px(8.);
+
+/// The scrolling speed, in rows or columns per second, for each line height or em width that
+/// the mouse is away from the anchor.
+const AUTOSCROLL_SPEED: f32 = 2.;
+
+/// The state of an editor being scrolled after a middle-click, towards wherever the mouse is
+/// relative to the clicked point.
+pub(crate) struct MiddleClickAutoscroll {
+    pub anchor: Point<Pixels>,
+    mouse_position: Point<Pixels>,
+    _task: Task<()>,
+}
+
+/// Writes the text of the non-empty selections to the primary selection once they stop changing
+/// and the mouse is released, so that it can be pasted with a middle-click. Whether it can be
+/// pasted in other applications depends on the platform's support for the primary selection.
+pub(crate) fn refresh_primary_selection(editor: &mut Editor, cx: &mut ViewContext<Editor>) {
+    editor.primary_selection_task = Some(cx.spawn(|this, mut cx| async move {
+        cx.background_executor()
+            .timer(PRIMARY_SELECTION_DEBOUNCE)
+            .await;
+        this.update(&mut cx, |this, cx| {
+            if !this.has_pending_selection() {
+                write_selections_to_primary(this, cx);
+            }
+        })
+        .ok();
+    }));
+}
+
+fn write_selections_to_primary(editor: &Editor, cx: &mut ViewContext<Editor>) {
+    let buffer = editor.buffer.read(cx).snapshot(cx);
+    let text = editor
+        .selections
+        .all::<usize>(cx)
+        .into_iter()
+        .filter(|selection| !selection.is_empty())
+        .map(|selection| {
+            buffer
+                .text_for_range(selection.start..selection.end)
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !text.is_empty() {
+        cx.write_to_primary(ClipboardItem::new(text));
+    }
+}
+
+/// Moves the cursor to the given position and inserts the contents of the primary selection.
+pub(crate) fn paste_primary_at(
+    editor: &mut Editor,
+    position: DisplayPoint,
+    cx: &mut ViewContext<Editor>,
+) {
+    let Some(item) = cx.read_from_primary() else {
+        return;
+    };
+    editor.change_selections(Some(Autoscroll::fit()), cx, |s| {
+        s.select_display_ranges([position..position])
+    });
+    editor.insert(item.text(), cx);
+}
+
+/// Starts scrolling the editor continuously towards the mouse, at a speed proportional to its
+/// distance from `anchor`, until the next click, Escape, or the editor losing focus.
+pub(crate) fn start_autoscroll(
+    editor: &mut Editor,
+    anchor: Point<Pixels>,
+    line_height: Pixels,
+    em_width: Pixels,
+    scroll_max: Point<f32>,
+    cx: &mut ViewContext<Editor>,
+) {
+    let task = cx.spawn(|this, mut cx| async move {
+        let mut last_tick = Instant::now();
+        loop {
+            cx.background_executor().timer(AUTOSCROLL_INTERVAL).await;
+            let now = Instant::now();
+            let elapsed = now.saturating_duration_since(last_tick);
+            last_tick = now;
+
+            let updated = this.update(&mut cx, |this, cx| {
+                let Some(autoscroll) = this.middle_click_autoscroll.as_ref() else {
+                    return;
+                };
+                let offset = point(
+                    autoscroll.mouse_position.x - autoscroll.anchor.x,
+                    autoscroll.mouse_position.y - autoscroll.anchor.y,
+                );
+                let delta = autoscroll_delta(offset, line_height, em_width, elapsed);
+                if delta.x != 0. || delta.y != 0. {
+                    let position = this.scroll_position(cx);
+                    let position = point(position.x + delta.x, position.y + delta.y)
+                        .clamp(&point(0., 0.), &scroll_max);
+                    this.set_scroll_position(position, cx);
+                }
+            });
+            if updated.is_err() {
+                break;
+            }
+        }
+    });
+    editor.middle_click_autoscroll = Some(MiddleClickAutoscroll {
+        anchor,
+        mouse_position: anchor,
+        _task: task,
+    });
+    cx.notify();
+}
+
+pub(crate) fn update_autoscroll(editor: &mut Editor, mouse_position: Point<Pixels>) {
+    if let Some(autoscroll) = editor.middle_click_autoscroll.as_mut() {
+        autoscroll.mouse_position = mouse_position;
+    }
+}
+
+pub(crate) fn stop_autoscroll(editor: &mut Editor, cx: &mut ViewContext<Editor>) {
+    if editor.middle_click_autoscroll.take().is_some() {
+        cx.notify();
+    }
+}
+
+/// Returns the number of columns and rows to scroll by after `elapsed`, given the offset of the
+/// mouse from the anchor.
+fn autoscroll_delta(
+    offset: Point<Pixels>,
+    line_height: Pixels,
+    em_width: Pixels,
+    elapsed: Duration,
+) -> Point<f32> {
+    let delta = |distance: Pixels, unit: Pixels| {
+        let distance = if distance > AUTOSCROLL_DEAD_ZONE {
+            distance - AUTOSCROLL_DEAD_ZONE
+        } else if distance < -AUTOSCROLL_DEAD_ZONE {
+            distance + AUTOSCROLL_DEAD_ZONE
+        } else {
+            return 0.;
+        };
+        distance / unit * AUTOSCROLL_SPEED * elapsed.as_secs_f32()
+    };
+    point(delta(offset.x, em_width), delta(offset.y, line_height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+    use gpui::TestAppContext;
+
+    #[gpui::test]
+    async fn test_paste_primary_selection(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+
+        // Middle-clicking pastes the primary selection at the clicked position.
+        cx.set_state("one ˇtwo three");
+        cx.update(|cx| cx.write_to_primary(ClipboardItem::new("four ".into())));
+        cx.update_editor(|editor, cx| paste_primary_at(editor, DisplayPoint::new(0, 8), cx));
+        cx.assert_editor_state("one two four ˇthree");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[gpui::test]
+    async fn test_write_selections_to_primary(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.update(|cx| cx.write_to_primary(ClipboardItem::new("four ".into())));
+
+        // Selections are written to the primary selection once they stop changing.
+        cx.set_state("«oneˇ» two four three");
+        cx.set_state("one «twoˇ» four three");
+        assert_eq!(
+            cx.update(|cx| cx.read_from_primary()).unwrap().text(),
+            "four "
+        );
+        cx.executor().advance_clock(PRIMARY_SELECTION_DEBOUNCE);
+        cx.run_until_parked();
+        assert_eq!(
+            cx.update(|cx| cx.read_from_primary()).unwrap().text(),
+            "two"
+        );
+    }
+
+    #[test]
+    fn test_autoscroll_delta() {
+        let line_height = px(20.);
+        let em_width = px(10.);
+        let second = Duration::from_secs(1);
+
+        // Moving the mouse within the dead zone doesn't scroll.
+        let delta = autoscroll_delta(point(px(5.), px(-8.)), line_height, em_width, second);
+        assert_eq!(delta, point(0., 0.));
+
+        // Beyond it, the speed grows with the distance from the anchor.
+        let delta = autoscroll_delta(point(px(0.), px(48.)), line_height, em_width, second);
+        assert_eq!(delta, point(0., 4.));
+        let delta = autoscroll_delta(point(px(-18.), px(-28.)), line_height, em_width, second);
+        assert_eq!(delta, point(-2., -2.));
+        let delta = autoscroll_delta(point(px(0.), px(48.)), line_height, em_width, second / 2);
+        assert_eq!(delta, point(0., 2.));
+    }
+}
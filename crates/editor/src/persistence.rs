@@ -1,5 +1,6 @@
 use std::path::PathBuf;
 
+use anyhow::Result;
 use db::sqlez_macros::sql;
 use db::{define_connection, query};
 
@@ -14,6 +15,27 @@ define_connection!(
     //   scroll_top_row: usize,
     //   scroll_vertical_offset: f32,
     //   scroll_horizontal_offset: f32,
+    //   font_size_adjustment: f32,
+    // )
+    //
+    // bookmarks(
+    //   workspace_id: usize,
+    //   path: PathBuf,
+    //   row: u32,
+    // )
+    //
+    // macros(
+    //   workspace_id: usize,
+    //   name: String,
+    //   steps: String,
+    // )
+    //
+    // marks(
+    //   workspace_id: usize,
+    //   name: String,
+    //   path: PathBuf,
+    //   row: u32,
+    //   col: u32,
     // )
     pub static ref DB: EditorDb<WorkspaceDb> =
         &[sql! (
@@ -31,6 +53,44 @@ define_connection!(
             ALTER TABLE editors ADD COLUMN scroll_top_row INTEGER NOT NULL DEFAULT 0;
             ALTER TABLE editors ADD COLUMN scroll_horizontal_offset REAL NOT NULL DEFAULT 0;
             ALTER TABLE editors ADD COLUMN scroll_vertical_offset REAL NOT NULL DEFAULT 0;
+        ),
+        sql! (
+            ALTER TABLE editors ADD COLUMN font_size_adjustment REAL NOT NULL DEFAULT 0;
+        ),
+        sql! (
+            CREATE TABLE bookmarks(
+                workspace_id INTEGER NOT NULL,
+                path BLOB NOT NULL,
+                row INTEGER NOT NULL,
+                PRIMARY KEY(workspace_id, path, row),
+                FOREIGN KEY(workspace_id) REFERENCES workspaces(workspace_id)
+                ON DELETE CASCADE
+                ON UPDATE CASCADE
+            ) STRICT;
+        ),
+        sql! (
+            CREATE TABLE macros(
+                workspace_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                steps TEXT NOT NULL,
+                PRIMARY KEY(workspace_id, name),
+                FOREIGN KEY(workspace_id) REFERENCES workspaces(workspace_id)
+                ON DELETE CASCADE
+                ON UPDATE CASCADE
+            ) STRICT;
+        ),
+        sql! (
+            CREATE TABLE marks(
+                workspace_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                path BLOB NOT NULL,
+                row INTEGER NOT NULL,
+                col INTEGER NOT NULL,
+                PRIMARY KEY(workspace_id, name),
+                FOREIGN KEY(workspace_id) REFERENCES workspaces(workspace_id)
+                ON DELETE CASCADE
+                ON UPDATE CASCADE
+            ) STRICT;
         )];
 );
 
@@ -80,4 +140,130 @@ impl EditorDb {
             WHERE item_id = ?1 AND workspace_id = ?2
         }
     }
+
+    query! {
+        pub async fn get_font_size_adjustment(item_id: ItemId, workspace_id: WorkspaceId) -> Result<Option<f32>> {
+            SELECT font_size_adjustment
+            FROM editors
+            WHERE item_id = ? AND workspace_id = ?
+        }
+    }
+
+    query! {
+        pub async fn save_font_size_adjustment(
+            item_id: ItemId,
+            workspace_id: WorkspaceId,
+            font_size_adjustment: f32
+        ) -> Result<()> {
+            UPDATE OR IGNORE editors
+            SET font_size_adjustment = ?3
+            WHERE item_id = ?1 AND workspace_id = ?2
+        }
+    }
+
+    query! {
+        pub async fn get_bookmarks(workspace_id: WorkspaceId, path: PathBuf) -> Result<Vec<u32>> {
+            SELECT row FROM bookmarks
+            WHERE workspace_id = ? AND path = ?
+            ORDER BY row
+        }
+    }
+
+    /// Replaces the bookmarks saved for the file at the given path.
+    pub async fn save_bookmarks(
+        &self,
+        workspace_id: WorkspaceId,
+        path: PathBuf,
+        rows: Vec<u32>,
+    ) -> Result<()> {
+        self.write(move |conn| {
+            conn.with_savepoint("save_bookmarks", || {
+                conn.exec_bound(sql!(
+                    DELETE FROM bookmarks WHERE workspace_id = ? AND path = ?
+                ))?((workspace_id, path.clone()))?;
+
+                let mut insert = conn.exec_bound(sql!(
+                    INSERT INTO bookmarks(workspace_id, path, row) VALUES (?, ?, ?)
+                ))?;
+                for row in rows {
+                    insert((workspace_id, path.clone(), row))?;
+                }
+                Ok(())
+            })
+        })
+        .await
+    }
+
+    query! {
+        pub async fn get_macro(workspace_id: WorkspaceId, name: String) -> Result<Option<String>> {
+            SELECT steps FROM macros
+            WHERE workspace_id = ? AND name = ?
+        }
+    }
+
+    query! {
+        pub async fn save_macro(workspace_id: WorkspaceId, name: String, steps: String) -> Result<()> {
+            INSERT INTO macros
+                (workspace_id, name, steps)
+            VALUES
+                (?1, ?2, ?3)
+            ON CONFLICT DO UPDATE SET
+                steps = ?3
+        }
+    }
+
+    query! {
+        pub async fn get_mark(workspace_id: WorkspaceId, name: String) -> Result<Option<(PathBuf, u32, u32)>> {
+            SELECT path, row, col FROM marks
+            WHERE workspace_id = ? AND name = ?
+        }
+    }
+
+    query! {
+        pub async fn get_marks(workspace_id: WorkspaceId, path: PathBuf) -> Result<Vec<(String, u32, u32)>> {
+            SELECT name, row, col FROM marks
+            WHERE workspace_id = ? AND path = ?
+        }
+    }
+
+    query! {
+        pub async fn save_mark(
+            workspace_id: WorkspaceId,
+            name: String,
+            path: PathBuf,
+            row: u32,
+            col: u32
+        ) -> Result<()> {
+            INSERT INTO marks
+                (workspace_id, name, path, row, col)
+            VALUES
+                (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT DO UPDATE SET
+                path = ?3,
+                row = ?4,
+                col = ?5
+        }
+    }
+
+    // Moves a mark that's still set in the file at the given path
+    query! {
+        pub async fn update_mark(
+            workspace_id: WorkspaceId,
+            name: String,
+            path: PathBuf,
+            row: u32,
+            col: u32
+        ) -> Result<()> {
+            UPDATE marks
+            SET row = ?4, col = ?5
+            WHERE workspace_id = ?1 AND name = ?2 AND path = ?3
+        }
+    }
+
+    query! {
+        pub async fn delete_mark(workspace_id: WorkspaceId, name: String, path: PathBuf) -> Result<()> {
+            DELETE FROM marks
+            WHERE workspace_id = ? AND name = ? AND path = ?
+        }
+    }
 }
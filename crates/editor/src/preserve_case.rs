@@ -0,0 +1,106 @@
+use crate::Editor;
+use gpui::ViewContext;
+use language::{Point, Selection};
+use multi_buffer::{MultiBufferSnapshot, ToPoint};
+use project::search::MatchCase;
+use std::{ops::Range, sync::Arc};
+
+impl Editor {
+    /// Makes text typed into each of the current selections take the capitalization of the text
+    /// it selects, so that typing over all the matches of a search keeps the case of each match.
+    /// This lasts until the cursors leave the text they're typing over.
+    pub fn preserve_case_in_selections(&mut self, cx: &mut ViewContext<Self>) {
+        let buffer = self.buffer.read(cx).snapshot(cx);
+        self.case_preserving_selections = self
+            .selections
+            .all::<usize>(cx)
+            .into_iter()
+            .map(|selection| {
+                let text = buffer.text_for_range(selection.range()).collect::<String>();
+                let range =
+                    buffer.anchor_before(selection.start)..buffer.anchor_after(selection.end);
+                (selection.id, (range, MatchCase::of(&text)))
+            })
+            .collect();
+    }
+
+    /// Stops preserving case unless every selection is one it's preserved in, and is still within
+    /// the text typed over it.
+    pub(crate) fn refresh_case_preserving_selections(&mut self, buffer: &MultiBufferSnapshot) {
+        if self.case_preserving_selections.is_empty() {
+            return;
+        }
+        let is_preserving = self.selections.disjoint_anchors().iter().all(|selection| {
+            self.case_preserving_selections
+                .get(&selection.id)
+                .map_or(false, |(range, _)| {
+                    let head = selection.head();
+                    range.start.cmp(&head, buffer).is_le() && head.cmp(&range.end, buffer).is_le()
+                })
+        });
+        if !is_preserving {
+            self.case_preserving_selections.clear();
+        }
+    }
+
+    /// Returns the text to insert in place of the given selection, changed to the capitalization
+    /// that's preserved in it. Capitalized text only gets its first letter uppercased when typed at
+    /// the start of where the selection began.
+    pub(crate) fn case_preserved_input(
+        &self,
+        selection: &Selection<Point>,
+        text: &Arc<str>,
+        buffer: &MultiBufferSnapshot,
+    ) -> Arc<str> {
+        let Some((range, case)) = self.case_preserving_selections.get(&selection.id) else {
+            return text.clone();
+        };
+        if *case == MatchCase::Capitalized && selection.start != range.start.to_point(buffer) {
+            return text.clone();
+        }
+        case.apply(text).as_ref().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        editor_tests::init_test, test::editor_test_context::EditorTestContext, MoveToEndOfLine,
+    };
+    use indoc::indoc;
+
+    #[gpui::test]
+    async fn test_preserve_case_in_selections(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state(indoc! {"
+            «fooˇ» = «Fooˇ»::new(«FOOˇ»);
+        "});
+        cx.update_editor(|e, cx| e.preserve_case_in_selections(cx));
+        cx.simulate_input("bar");
+        cx.assert_editor_state(indoc! {"
+            barˇ = Barˇ::new(BARˇ);
+        "});
+
+        // Once the cursors leave the text they're typing over, text is inserted as typed.
+        cx.set_state(indoc! {"
+            «fooˇ» = «Fooˇ»::new(«FOOˇ»);
+        "});
+        cx.update_editor(|e, cx| e.preserve_case_in_selections(cx));
+        cx.update_editor(|e, cx| e.move_to_end_of_line(&MoveToEndOfLine::default(), cx));
+        cx.simulate_input("x");
+        cx.assert_editor_state(indoc! {"
+            foo = Foo::new(FOO);xˇ
+        "});
+
+        // Once text is typed with other selections, it's inserted as typed.
+        cx.set_state(indoc! {"
+            «BARˇ»
+        "});
+        cx.simulate_input("baz");
+        cx.assert_editor_state(indoc! {"
+            bazˇ
+        "});
+    }
+}
@@ -0,0 +1,113 @@
+use gpui::{px, size, Pixels, PrintPage, Size};
+
+/// Font size, in points, used when printing a buffer.
+pub(crate) const PRINT_FONT_SIZE: f32 = 10.;
+/// Dimensions of a US Letter page, in points, used when the platform doesn't report the size of
+/// the pages it prints on.
+const DEFAULT_PAGE_SIZE: (f32, f32) = (612., 792.);
+/// Margin applied to every edge of a page of the default size, in points.
+const DEFAULT_PAGE_MARGIN: f32 = 54.;
+/// Rows reserved for the header and footer, including their separating blank lines.
+const HEADER_AND_FOOTER_ROWS: usize = 4;
+
+/// Returns the size of the area within the margins of a page of the default size.
+pub(crate) fn default_printable_page_size() -> Size<Pixels> {
+    let (page_width, page_height) = DEFAULT_PAGE_SIZE;
+    size(
+        px(page_width - 2. * DEFAULT_PAGE_MARGIN),
+        px(page_height - 2. * DEFAULT_PAGE_MARGIN),
+    )
+}
+
+/// Returns how many body lines of the given height fit within the given printable area of a page,
+/// and the width available to them.
+pub(crate) fn page_body_dimensions(
+    printable_size: Size<Pixels>,
+    line_height: Pixels,
+) -> (usize, Pixels) {
+    let rows = (printable_size.height / line_height).floor() as usize;
+    let lines_per_page = rows.saturating_sub(HEADER_AND_FOOTER_ROWS).max(1);
+    (lines_per_page, printable_size.width)
+}
+
+/// Splits the given buffer lines into pages, prefixing each line with its line number.
+///
+/// `wrap_line` receives the text of a line and returns the byte offsets at which it should be
+/// soft-wrapped to fit the page. Continuation rows are printed with an empty line number gutter.
+pub(crate) fn paginate<'a>(
+    title: &str,
+    lines: impl ExactSizeIterator<Item = &'a str>,
+    lines_per_page: usize,
+    mut wrap_line: impl FnMut(&str) -> Vec<usize>,
+) -> Vec<PrintPage> {
+    let line_number_width = lines.len().max(1).to_string().len();
+    let mut rows = Vec::new();
+    for (ix, line) in lines.enumerate() {
+        let mut start = 0;
+        for end in wrap_line(line).into_iter().chain(Some(line.len())) {
+            let line_number = if start == 0 {
+                (ix + 1).to_string()
+            } else {
+                String::new()
+            };
+            rows.push(format!(
+                "{line_number:>line_number_width$}  {}",
+                &line[start..end]
+            ));
+            start = end;
+        }
+    }
+
+    let lines_per_page = lines_per_page.max(1);
+    let page_count = ((rows.len() + lines_per_page - 1) / lines_per_page).max(1);
+    let mut rows = rows.into_iter();
+    (0..page_count)
+        .map(|page_ix| PrintPage {
+            header: title.to_string(),
+            lines: rows.by_ref().take(lines_per_page).collect(),
+            footer: format!("Page {} of {}", page_ix + 1, page_count),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_body_dimensions() {
+        assert_eq!(
+            page_body_dimensions(default_printable_page_size(), px(12.)),
+            (53, px(504.))
+        );
+        assert_eq!(
+            page_body_dimensions(size(px(400.), px(60.)), px(20.)),
+            (1, px(400.))
+        );
+    }
+
+    #[test]
+    fn test_paginate() {
+        let text = "fn main() {\n    println!(\"hello world\");\n}";
+        let lines = text.lines().collect::<Vec<_>>();
+        let pages = paginate("main.rs", lines.into_iter(), 2, |line| {
+            (16..line.len()).step_by(16).collect()
+        });
+
+        assert_eq!(
+            pages,
+            vec![
+                PrintPage {
+                    header: "main.rs".into(),
+                    lines: vec!["1  fn main() {".into(), "2      println!(\"he".into()],
+                    footer: "Page 1 of 2".into(),
+                },
+                PrintPage {
+                    header: "main.rs".into(),
+                    lines: vec!["   llo world\");".into(), "3  }".into()],
+                    footer: "Page 2 of 2".into(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,608 @@
+use crate::{
+    display_map::{BlockDisposition, BlockId, BlockProperties, BlockStyle},
+    selection_end_row, Editor, Reflow,
+};
+use collections::HashSet;
+use gpui::{div, IntoElement, ViewContext};
+use language::{Point, Subscription};
+use multi_buffer::{Anchor, MultiBufferSnapshot, ToPoint};
+use std::{
+    mem,
+    ops::{Range, RangeInclusive},
+    sync::Arc,
+};
+
+/// The blocks leaving empty rows after each paragraph of a buffer, as configured by the
+/// `paragraph_spacing` setting.
+#[derive(Default)]
+pub(crate) struct ParagraphSpacingBlocks {
+    /// The number of rows each block is tall.
+    rows: u32,
+    /// The blocks, ordered by the position of the paragraph end they follow.
+    blocks: Vec<(Anchor, BlockId)>,
+    /// The edits made to the buffer since the blocks were last updated.
+    edits: Option<Subscription>,
+}
+
+/// Returns the rows in the given range that end a paragraph, i.e. non-blank rows that are followed
+/// by a blank row.
+pub(crate) fn paragraph_end_rows(snapshot: &MultiBufferSnapshot, rows: Range<u32>) -> Vec<u32> {
+    let max_row = snapshot.max_buffer_row();
+    (rows.start..rows.end.min(max_row))
+        .filter(|&row| !snapshot.is_line_blank(row) && snapshot.is_line_blank(row + 1))
+        .collect()
+}
+
+impl Editor {
+    /// Recreates the paragraph spacing blocks if the number of rows they should be tall changed,
+    /// for example because the buffer's language or the settings changed.
+    pub(crate) fn refresh_paragraph_spacing(&mut self, cx: &mut ViewContext<Self>) {
+        let rows = self.paragraph_spacing(cx);
+        let spacing = &mut self.paragraph_spacing_blocks;
+        if rows == spacing.rows && (rows == 0 || spacing.edits.is_some()) {
+            return;
+        }
+
+        let old_blocks = mem::take(&mut spacing.blocks);
+        spacing.rows = rows;
+        spacing.edits = (rows > 0).then(|| self.buffer.update(cx, |buffer, _| buffer.subscribe()));
+        self.remove_blocks(old_blocks.into_iter().map(|(_, id)| id).collect(), None, cx);
+        if rows > 0 {
+            let snapshot = self.buffer.read(cx).snapshot(cx);
+            let end_rows = paragraph_end_rows(&snapshot, 0..snapshot.max_buffer_row());
+            self.paragraph_spacing_blocks.blocks =
+                self.insert_paragraph_spacing_blocks(end_rows, &snapshot, cx);
+        }
+    }
+
+    /// Updates the paragraph spacing blocks around the rows edited since they were last updated.
+    pub(crate) fn update_paragraph_spacing_after_edit(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(subscription) = &self.paragraph_spacing_blocks.edits else {
+            return;
+        };
+        let edits = subscription.consume();
+        if edits.is_empty() {
+            return;
+        }
+
+        // An edit can start or end the paragraph on the row before it, by making its first row
+        // blank or non-blank.
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let mut row_ranges: Vec<Range<u32>> = Vec::new();
+        for edit in edits.edits() {
+            let start = edit.new.start.to_point(&snapshot).row.saturating_sub(1);
+            let end = edit.new.end.to_point(&snapshot).row + 1;
+            match row_ranges.last_mut() {
+                Some(last) if last.end >= start => last.end = last.end.max(end),
+                _ => row_ranges.push(start..end),
+            }
+        }
+
+        let mut blocks = mem::take(&mut self.paragraph_spacing_blocks.blocks);
+        let mut removed_blocks = HashSet::default();
+        for rows in row_ranges.into_iter().rev() {
+            let block_row = |(anchor, _): &(Anchor, BlockId)| anchor.to_point(&snapshot).row;
+            let start_ix = blocks.partition_point(|block| block_row(block) < rows.start);
+            let end_ix = blocks.partition_point(|block| block_row(block) < rows.end);
+            let new_blocks = self.insert_paragraph_spacing_blocks(
+                paragraph_end_rows(&snapshot, rows),
+                &snapshot,
+                cx,
+            );
+            removed_blocks.extend(
+                blocks
+                    .splice(start_ix..end_ix, new_blocks)
+                    .map(|(_, id)| id),
+            );
+        }
+        self.paragraph_spacing_blocks.blocks = blocks;
+        self.remove_blocks(removed_blocks, None, cx);
+    }
+
+    fn insert_paragraph_spacing_blocks(
+        &mut self,
+        end_rows: Vec<u32>,
+        snapshot: &MultiBufferSnapshot,
+        cx: &mut ViewContext<Self>,
+    ) -> Vec<(Anchor, BlockId)> {
+        let height = self.paragraph_spacing_blocks.rows.min(u8::MAX as u32) as u8;
+        let positions = end_rows
+            .into_iter()
+            .map(|row| snapshot.anchor_after(Point::new(row, 0)))
+            .collect::<Vec<_>>();
+        let ids = self.insert_blocks(
+            positions.iter().map(|&position| BlockProperties {
+                position,
+                height,
+                style: BlockStyle::Fixed,
+                render: Arc::new(|_| div().into_any_element()),
+                disposition: BlockDisposition::Below,
+            }),
+            None,
+            cx,
+        );
+        positions.into_iter().zip(ids).collect()
+    }
+
+    /// Rewraps the paragraphs touched by the selections to the preferred line length. Lines of a
+    /// comment block are rewrapped together and keep their comment prefix.
+    pub fn reflow(&mut self, _: &Reflow, cx: &mut ViewContext<Self>) {
+        if self.read_only(cx) {
+            return;
+        }
+
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let mut edits = Vec::new();
+        let mut next_row = 0;
+        let mut code_blocks = FencedCodeBlocks::default();
+        for selection in self.selections.all::<Point>(cx) {
+            let end_row = selection_end_row(&selection);
+            let mut row = selection.start.row.max(next_row);
+            while row <= end_row {
+                if code_blocks.contains(&snapshot, row) {
+                    row += 1;
+                    continue;
+                }
+                let Some(paragraph) = paragraph_at(&snapshot, row) else {
+                    row += 1;
+                    continue;
+                };
+                next_row = paragraph.rows.end() + 1;
+                row = next_row;
+
+                let start = Point::new(*paragraph.rows.start(), 0);
+                let end = Point::new(
+                    *paragraph.rows.end(),
+                    snapshot.line_len(*paragraph.rows.end()),
+                );
+                let settings = snapshot.settings_at(start, cx);
+                let new_text = paragraph.reflow(
+                    settings.preferred_line_length as usize,
+                    settings.tab_size.get() as usize,
+                );
+                let range = snapshot.point_to_offset(start)..snapshot.point_to_offset(end);
+                let old_text = snapshot.text_for_range(range.clone()).collect::<String>();
+                if let Some((changed_range, new_text)) = minimal_edit(&old_text, &new_text) {
+                    edits.push((
+                        range.start + changed_range.start..range.start + changed_range.end,
+                        new_text,
+                    ));
+                }
+            }
+        }
+
+        self.transact(cx, |this, cx| {
+            this.buffer
+                .update(cx, |buffer, cx| buffer.edit(edits, None, cx));
+        });
+    }
+}
+
+/// A run of consecutive lines that share the same prefix, such as an indentation followed by a
+/// comment marker, and that have text after it. A list item starts a paragraph of its own, which
+/// continues over the lines indented to its text.
+struct Paragraph {
+    rows: RangeInclusive<u32>,
+    first_line_prefix: String,
+    prefix: String,
+    words: Vec<String>,
+}
+
+impl Paragraph {
+    /// Returns the text of the paragraph with its words wrapped to the given line length, each line
+    /// starting with the paragraph's prefix. Words longer than a line are left on their own line.
+    fn reflow(&self, line_length: usize, tab_size: usize) -> String {
+        let width_of = |prefix: &str| {
+            prefix
+                .chars()
+                .map(|c| if c == '\t' { tab_size } else { 1 })
+                .sum::<usize>()
+        };
+        let prefix_width = width_of(&self.prefix);
+
+        let mut text = self.first_line_prefix.clone();
+        let mut width = width_of(&self.first_line_prefix);
+        for (ix, word) in self.words.iter().enumerate() {
+            let word_width = word.chars().count();
+            if ix > 0 {
+                if width + 1 + word_width > line_length {
+                    text.push('\n');
+                    text.push_str(&self.prefix);
+                    width = prefix_width;
+                } else {
+                    text.push(' ');
+                    width += 1;
+                }
+            }
+            text.push_str(word);
+            width += word_width;
+        }
+        text
+    }
+}
+
+/// The start of a line that's kept when the line is rewrapped.
+struct LinePrefix {
+    /// The indentation and comment marker the line starts with, and the whitespace after them.
+    prefix: String,
+    /// The list marker after `prefix` and the whitespace after it, if the line starts a list item.
+    list_marker: Option<String>,
+    /// The rest of the line.
+    content: String,
+}
+
+impl LinePrefix {
+    /// Returns the prefix of the lines that continue this one's paragraph.
+    fn continuation_prefix(&self) -> String {
+        let list_marker_len = self.list_marker.as_ref().map_or(0, |marker| marker.len());
+        format!("{}{}", self.prefix, " ".repeat(list_marker_len))
+    }
+}
+
+/// Returns the paragraph containing the given row, unless it's blank apart from its prefix.
+fn paragraph_at(snapshot: &MultiBufferSnapshot, row: u32) -> Option<Paragraph> {
+    let mut start = line_prefix(snapshot, row)?;
+    let mut start_row = row;
+    while start.list_marker.is_none() && start_row > 0 {
+        let Some(previous) = line_prefix(snapshot, start_row - 1) else {
+            break;
+        };
+        if previous.continuation_prefix() != start.prefix {
+            break;
+        }
+        start = previous;
+        start_row -= 1;
+    }
+
+    let prefix = start.continuation_prefix();
+    let mut end_row = row;
+    while end_row < snapshot.max_buffer_row() {
+        match line_prefix(snapshot, end_row + 1) {
+            Some(next) if next.list_marker.is_none() && next.prefix == prefix => end_row += 1,
+            _ => break,
+        }
+    }
+
+    let words = (start_row..=end_row)
+        .filter_map(|row| line_prefix(snapshot, row))
+        .flat_map(|line| {
+            line.content
+                .split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    Some(Paragraph {
+        rows: start_row..=end_row,
+        first_line_prefix: format!("{}{}", start.prefix, start.list_marker.unwrap_or_default()),
+        prefix,
+        words,
+    })
+}
+
+/// Tracks whether rows are inside of fenced code blocks, such as the ones in Markdown files and doc
+/// comments, by scanning the buffer from its start. Rows must be queried in increasing order.
+#[derive(Default)]
+struct FencedCodeBlocks {
+    next_row: u32,
+    open: bool,
+}
+
+impl FencedCodeBlocks {
+    /// Returns whether a code block was opened before the given row and not closed yet.
+    fn contains(&mut self, snapshot: &MultiBufferSnapshot, row: u32) -> bool {
+        while self.next_row < row {
+            if let Some((line, marker_end)) = split_comment_marker(snapshot, self.next_row) {
+                if is_code_fence(line[marker_end..].trim_start()) {
+                    self.open = !self.open;
+                }
+            }
+            self.next_row += 1;
+        }
+        self.open
+    }
+}
+
+/// Splits the given row into its prefix and the rest of the line. Returns `None` for rows with no
+/// text after their prefix, for lines of code, i.e. rows without a comment marker in a language
+/// that has comments, and for Markdown headings, code fences and table rows, which are never
+/// rewrapped or joined with the lines around them.
+fn line_prefix(snapshot: &MultiBufferSnapshot, row: u32) -> Option<LinePrefix> {
+    let (line, marker_end) = split_comment_marker(snapshot, row)?;
+    let text = line[marker_end..].trim_start();
+    if is_heading(text) || is_code_fence(text) || text.starts_with('|') {
+        return None;
+    }
+    let list_marker_len = list_marker_len(text);
+    let content = text[list_marker_len..].trim_start();
+    if content.is_empty() {
+        return None;
+    }
+    let prefix_len = line.len() - text.len();
+    let content_start = line.len() - content.len();
+    Some(LinePrefix {
+        prefix: line[..prefix_len].to_string(),
+        list_marker: (list_marker_len > 0).then(|| line[prefix_len..content_start].to_string()),
+        content: content.to_string(),
+    })
+}
+
+/// Returns the text of the given row and the end of its indentation and comment marker, if any.
+/// Returns `None` for lines of code, i.e. rows without a comment marker in a language that has
+/// comments. Besides line comments, the lines of block comments such as `/* */` can start with the
+/// last character of the comment's opening delimiter, e.g. ` * `.
+fn split_comment_marker(snapshot: &MultiBufferSnapshot, row: u32) -> Option<(String, usize)> {
+    let line = snapshot
+        .text_for_range(Point::new(row, 0)..Point::new(row, snapshot.line_len(row)))
+        .collect::<String>();
+    let indent_len = line.len() - line.trim_start().len();
+    let rest = &line[indent_len..];
+
+    let scope = snapshot.language_scope_at(Point::new(row, indent_len as u32));
+    let mut comment_markers = scope
+        .as_ref()
+        .and_then(|scope| scope.line_comment_prefixes().cloned())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|prefix| prefix.trim_end().to_string())
+        .filter(|marker| !marker.is_empty())
+        .collect::<Vec<_>>();
+    if let Some((start, _)) = scope
+        .as_ref()
+        .and_then(|scope| scope.block_comment_delimiters())
+    {
+        if start.trim_end().ends_with('*') {
+            comment_markers.push("*".to_string());
+        }
+    }
+
+    let comment_marker = comment_markers
+        .iter()
+        .filter(|marker| {
+            rest.strip_prefix(marker.as_str()).map_or(false, |after| {
+                marker.as_str() != "*" || after.is_empty() || after.starts_with(char::is_whitespace)
+            })
+        })
+        .max_by_key(|marker| marker.len());
+    if comment_marker.is_none() && !comment_markers.is_empty() {
+        return None;
+    }
+
+    let marker_end = indent_len + comment_marker.map_or(0, |marker| marker.len());
+    Some((line, marker_end))
+}
+
+/// Returns whether the given text is a Markdown heading, such as `## Examples`.
+fn is_heading(text: &str) -> bool {
+    let level = text.chars().take_while(|&c| c == '#').count();
+    let rest = &text[level..];
+    (1..=6).contains(&level) && (rest.is_empty() || rest.starts_with(char::is_whitespace))
+}
+
+/// Returns whether the given text opens or closes a Markdown code block.
+fn is_code_fence(text: &str) -> bool {
+    text.starts_with("```") || text.starts_with("~~~")
+}
+
+/// Returns the length of the list marker the given text starts with, such as `-` or `1.`, or zero
+/// if it doesn't start with one followed by whitespace.
+fn list_marker_len(text: &str) -> usize {
+    let digits = text.chars().take_while(char::is_ascii_digit).count();
+    let len = if digits > 0 {
+        if text[digits..].starts_with(['.', ')']) {
+            digits + 1
+        } else {
+            0
+        }
+    } else if text.starts_with(['-', '*', '+']) {
+        1
+    } else {
+        0
+    };
+    if len > 0 && text[len..].starts_with(char::is_whitespace) {
+        len
+    } else {
+        0
+    }
+}
+
+/// Returns the range of `old_text` that differs from `new_text`, and the text replacing it, leaving
+/// out their common prefix and suffix so that positions outside of the change are preserved.
+fn minimal_edit(old_text: &str, new_text: &str) -> Option<(Range<usize>, String)> {
+    if old_text == new_text {
+        return None;
+    }
+
+    let prefix_len = old_text
+        .char_indices()
+        .zip(new_text.chars())
+        .find(|((_, old_char), new_char)| old_char != new_char)
+        .map_or(old_text.len().min(new_text.len()), |((ix, _), _)| ix);
+    let suffix_len = old_text[prefix_len..]
+        .chars()
+        .rev()
+        .zip(new_text[prefix_len..].chars().rev())
+        .take_while(|(old_char, new_char)| old_char == new_char)
+        .map(|(old_char, _)| old_char.len_utf8())
+        .sum::<usize>();
+    Some((
+        prefix_len..old_text.len() - suffix_len,
+        new_text[prefix_len..new_text.len() - suffix_len].to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+    use gpui::AppContext;
+    use indoc::indoc;
+    use language::{Language, LanguageConfig};
+    use multi_buffer::MultiBuffer;
+    use std::sync::Arc;
+
+    #[gpui::test]
+    fn test_paragraph_end_rows(cx: &mut AppContext) {
+        let buffer = MultiBuffer::build_simple(
+            "# Title\n\nFirst paragraph\nstill first\n\n\nSecond paragraph\n  \nlast",
+            cx,
+        );
+        let snapshot = buffer.read(cx).snapshot(cx);
+        assert_eq!(paragraph_end_rows(&snapshot, 0..9), vec![0, 3, 6]);
+        assert_eq!(paragraph_end_rows(&snapshot, 1..6), vec![3]);
+    }
+
+    #[gpui::test]
+    async fn test_reflow(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |settings| {
+            settings.defaults.preferred_line_length = Some(20);
+        });
+
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state(indoc! {"
+            ˇThe quick brown fox jumps over the
+            lazy dog.
+
+            Next paragraph is left alone.
+        "});
+        cx.update_editor(|e, cx| e.reflow(&Reflow, cx));
+        cx.assert_editor_state(indoc! {"
+            ˇThe quick brown fox
+            jumps over the lazy
+            dog.
+
+            Next paragraph is left alone.
+        "});
+
+        // List items are rewrapped separately, and keep their text aligned after the marker.
+        cx.set_state(indoc! {"
+            «- first item is long enough to wrap
+            - second
+              itemˇ»
+        "});
+        cx.update_editor(|e, cx| e.reflow(&Reflow, cx));
+        cx.assert_editor_state(indoc! {"
+            «- first item is long
+              enough to wrap
+            - second itemˇ»
+        "});
+
+        let language = Arc::new(Language::new(
+            LanguageConfig {
+                line_comments: vec!["// ".into(), "/// ".into()],
+                ..Default::default()
+            },
+            Some(tree_sitter_rust::language()),
+        ));
+        cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+
+        // Comment blocks keep their prefix, and selections rewrap every paragraph they touch.
+        cx.set_state(indoc! {"
+            «    /// Aaa bbb ccc ddd eee fff
+                ///
+                /// ggg hhh iii jjj kkk lllˇ»
+                fn a() {}
+        "});
+        cx.executor().run_until_parked();
+        cx.update_editor(|e, cx| e.reflow(&Reflow, cx));
+        cx.assert_editor_state(indoc! {"
+            «    /// Aaa bbb ccc
+                /// ddd eee fff
+                ///
+                /// ggg hhh iii
+                /// jjj kkk lllˇ»
+                fn a() {}
+        "});
+
+        // Lines of code are left alone, even when they share their indentation.
+        cx.set_state(indoc! {"
+            «fn a() {
+                let aaa = 1;
+                let bbb = 2;
+            }ˇ»
+        "});
+        cx.executor().run_until_parked();
+        cx.update_editor(|e, cx| e.reflow(&Reflow, cx));
+        cx.assert_editor_state(indoc! {"
+            «fn a() {
+                let aaa = 1;
+                let bbb = 2;
+            }ˇ»
+        "});
+    }
+
+    #[gpui::test]
+    async fn test_reflow_markdown_blocks(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |settings| {
+            settings.defaults.preferred_line_length = Some(20);
+        });
+
+        // Headings, code fences and table rows end paragraphs, and code blocks are left alone.
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state(indoc! {"
+            «# A heading line
+            text under
+            it
+            ```
+            code is not
+            rewrapped
+            ```
+            | a | b |
+            | c | d |
+            last
+            lineˇ»
+        "});
+        cx.update_editor(|e, cx| e.reflow(&Reflow, cx));
+        cx.assert_editor_state(indoc! {"
+            «# A heading line
+            text under it
+            ```
+            code is not
+            rewrapped
+            ```
+            | a | b |
+            | c | d |
+            last lineˇ»
+        "});
+    }
+
+    #[gpui::test]
+    async fn test_reflow_block_comments(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |settings| {
+            settings.defaults.preferred_line_length = Some(20);
+        });
+
+        let mut cx = EditorTestContext::new(cx).await;
+        let language = Arc::new(Language::new(
+            LanguageConfig {
+                line_comments: vec!["// ".into()],
+                block_comment: Some(("/*".into(), "*/".into())),
+                ..Default::default()
+            },
+            Some(tree_sitter_rust::language()),
+        ));
+        cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+
+        // The lines of a block comment keep their ` * ` prefix, while its delimiters are left alone.
+        cx.set_state(indoc! {"
+            «/**
+             * Aaa bbb ccc ddd eee
+             * fff
+             *
+             * ggg
+             */
+            fn a() {}ˇ»
+        "});
+        cx.executor().run_until_parked();
+        cx.update_editor(|e, cx| e.reflow(&Reflow, cx));
+        cx.assert_editor_state(indoc! {"
+            «/**
+             * Aaa bbb ccc ddd
+             * eee fff
+             *
+             * ggg
+             */
+            fn a() {}ˇ»
+        "});
+    }
+}
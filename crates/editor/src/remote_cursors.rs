@@ -0,0 +1,249 @@
+use crate::{
+    cursor_animation::CursorAnimation,
+    cursor_name_tags::{NameTag, NAME_TAG_FADE_OUT, NAME_TAG_VISIBLE_FOR},
+    Anchor, Editor, HoveredCursor,
+};
+use collections::HashMap;
+use gpui::{Font, Pixels, Point, SharedString, Task, ViewContext};
+use std::{
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+/// How long a collaborator's selections stay unchanged before they're faded, so that the
+/// selections of people who are actively editing stand out.
+const PRESENCE_IDLE_AFTER: Duration = Duration::from_secs(30);
+
+/// How long idle selections take to fade.
+const PRESENCE_FADE_OUT: Duration = Duration::from_secs(1);
+
+/// The opacity of the selections of collaborators who are idle.
+const IDLE_PRESENCE_OPACITY: f32 = 0.4;
+
+/// Tracks when the selections of collaborators last changed and animates their cursors, so that
+/// cursors glide between the positions received from the collaborator, their name tags are shown
+/// while they're editing, and their selections fade once they stop.
+#[derive(Default)]
+pub(crate) struct RemoteCursors {
+    cursors: HashMap<HoveredCursor, CursorActivity>,
+    refresh: Option<(Instant, Task<()>)>,
+}
+
+struct CursorActivity {
+    range: Range<Anchor>,
+    changed_at: Instant,
+    animation: CursorAnimation,
+    name_tag: Option<NameTag>,
+    seen: bool,
+}
+
+impl CursorActivity {
+    fn next_update(&self, now: Instant) -> Option<Duration> {
+        let idle = now.saturating_duration_since(self.changed_at);
+        [
+            NAME_TAG_VISIBLE_FOR..NAME_TAG_VISIBLE_FOR + NAME_TAG_FADE_OUT,
+            PRESENCE_IDLE_AFTER..PRESENCE_IDLE_AFTER + PRESENCE_FADE_OUT,
+        ]
+        .into_iter()
+        .filter_map(|fade| {
+            if idle < fade.start {
+                Some(fade.start - idle)
+            } else if idle < fade.end {
+                Some(Duration::ZERO)
+            } else {
+                None
+            }
+        })
+        .min()
+    }
+}
+
+impl RemoteCursors {
+    /// Records the selection of a collaborator, returning how long it has been unchanged for.
+    /// Selections that weren't laid out in the previous frame count as having just changed.
+    pub fn record(
+        &mut self,
+        cursor: HoveredCursor,
+        range: Range<Anchor>,
+        now: Instant,
+    ) -> Duration {
+        let activity = self
+            .cursors
+            .entry(cursor)
+            .or_insert_with(|| CursorActivity {
+                range: range.clone(),
+                changed_at: now,
+                animation: CursorAnimation::default(),
+                name_tag: None,
+                seen: true,
+            });
+        if activity.range != range {
+            activity.range = range;
+            activity.changed_at = now;
+        }
+        activity.seen = true;
+        now.saturating_duration_since(activity.changed_at)
+    }
+
+    /// Returns the position at which to paint a collaborator's cursor moving to `target`, and
+    /// whether it is still moving.
+    pub fn position(
+        &mut self,
+        cursor: &HoveredCursor,
+        target: Point<Pixels>,
+        line_height: Pixels,
+        now: Instant,
+    ) -> (Point<Pixels>, bool) {
+        match self.cursors.get_mut(cursor) {
+            Some(activity) => activity.animation.position(target, line_height, now),
+            None => (target, false),
+        }
+    }
+
+    /// Returns the width of the name tag of a collaborator's cursor, calling `shape` to measure the
+    /// name only when it wasn't measured with the same font before.
+    pub fn name_width(
+        &mut self,
+        cursor: &HoveredCursor,
+        name: &SharedString,
+        font: &Font,
+        font_size: Pixels,
+        shape: impl FnOnce() -> Pixels,
+    ) -> Pixels {
+        match self.cursors.get_mut(cursor) {
+            Some(activity) => NameTag::width(&mut activity.name_tag, name, font, font_size, shape),
+            None => shape(),
+        }
+    }
+
+    /// Forgets the cursors that weren't laid out since the last call, and returns how long until
+    /// a name tag or selection starts fading, or zero if one is fading.
+    pub fn end_frame(&mut self, now: Instant) -> Option<Duration> {
+        self.cursors
+            .retain(|_, activity| std::mem::take(&mut activity.seen));
+        self.cursors
+            .values()
+            .filter_map(|activity| activity.next_update(now))
+            .min()
+    }
+}
+
+/// Returns the opacity of the selections of a collaborator whose selection was unchanged for the
+/// given duration.
+pub(crate) fn presence_opacity(idle: Duration) -> f32 {
+    match idle.checked_sub(PRESENCE_IDLE_AFTER) {
+        None => 1.,
+        Some(fading_for) => {
+            let progress = (fading_for.as_secs_f32() / PRESENCE_FADE_OUT.as_secs_f32()).min(1.);
+            IDLE_PRESENCE_OPACITY + (1. - IDLE_PRESENCE_OPACITY) * (1. - progress)
+        }
+    }
+}
+
+impl Editor {
+    /// Repaints the editor once the name tags or selections of collaborators need to start or keep
+    /// fading.
+    pub(crate) fn refresh_remote_cursors(&mut self, now: Instant, cx: &mut ViewContext<Self>) {
+        let Some(delay) = self.remote_cursors.end_frame(now) else {
+            self.remote_cursors.refresh = None;
+            return;
+        };
+        if delay.is_zero() {
+            self.remote_cursors.refresh = None;
+            cx.on_next_frame(|_, cx| cx.notify());
+            return;
+        }
+
+        let refresh_at = now + delay;
+        if self
+            .remote_cursors
+            .refresh
+            .as_ref()
+            .map_or(false, |(scheduled_at, _)| *scheduled_at == refresh_at)
+        {
+            return;
+        }
+        let task = cx.spawn(|this, mut cx| async move {
+            cx.background_executor().timer(delay).await;
+            this.update(&mut cx, |_, cx| cx.notify()).ok();
+        });
+        self.remote_cursors.refresh = Some((refresh_at, task));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cursor_name_tags::name_tag_opacity;
+    use gpui::{point, px};
+
+    #[test]
+    fn test_remote_cursor_activity() {
+        let start = Instant::now();
+        let cursor = HoveredCursor {
+            replica_id: 1,
+            selection_id: 0,
+        };
+        let mut cursors = RemoteCursors::default();
+        let unchanged = Anchor::min()..Anchor::min();
+        let moved = Anchor::max()..Anchor::max();
+
+        assert_eq!(
+            cursors.record(cursor.clone(), unchanged.clone(), start),
+            Duration::ZERO
+        );
+        assert_eq!(cursors.end_frame(start), Some(NAME_TAG_VISIBLE_FOR));
+
+        // The name tag fades out once the cursor stays still.
+        let fading = start + NAME_TAG_VISIBLE_FOR + NAME_TAG_FADE_OUT / 2;
+        let idle = cursors.record(cursor.clone(), unchanged.clone(), fading);
+        assert!(name_tag_opacity(idle) > 0. && name_tag_opacity(idle) < 1.);
+        assert_eq!(presence_opacity(idle), 1.);
+        assert_eq!(cursors.end_frame(fading), Some(Duration::ZERO));
+
+        let hidden = start + NAME_TAG_VISIBLE_FOR + NAME_TAG_FADE_OUT;
+        let idle = cursors.record(cursor.clone(), unchanged.clone(), hidden);
+        assert_eq!(name_tag_opacity(idle), 0.);
+        assert_eq!(cursors.end_frame(hidden), Some(PRESENCE_IDLE_AFTER - idle));
+
+        // The selections fade once the collaborator is idle.
+        let away = start + PRESENCE_IDLE_AFTER + PRESENCE_FADE_OUT;
+        let idle = cursors.record(cursor.clone(), unchanged.clone(), away);
+        assert_eq!(presence_opacity(idle), IDLE_PRESENCE_OPACITY);
+        assert_eq!(cursors.end_frame(away), None);
+
+        // Moving the cursor shows the name tag again.
+        let idle = cursors.record(cursor.clone(), moved.clone(), away);
+        assert_eq!(name_tag_opacity(idle), 1.);
+        assert_eq!(presence_opacity(idle), 1.);
+        cursors.end_frame(away);
+
+        // Cursors that aren't laid out are forgotten.
+        cursors.end_frame(away);
+        let later = away + PRESENCE_IDLE_AFTER;
+        assert_eq!(cursors.record(cursor, moved, later), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_remote_cursor_animation() {
+        let start = Instant::now();
+        let cursor = HoveredCursor {
+            replica_id: 1,
+            selection_id: 0,
+        };
+        let line_height = px(20.);
+        let mut cursors = RemoteCursors::default();
+
+        cursors.record(cursor.clone(), Anchor::min()..Anchor::min(), start);
+        let target = point(px(10.), px(0.));
+        assert_eq!(
+            cursors.position(&cursor, target, line_height, start),
+            (target, false)
+        );
+
+        let (position, moving) =
+            cursors.position(&cursor, point(px(50.), px(20.)), line_height, start);
+        assert_eq!(position, target);
+        assert!(moving);
+    }
+}
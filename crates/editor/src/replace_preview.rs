@@ -0,0 +1,254 @@
+use crate::{
+    display_map::Inlay, Anchor, ApplyAllReplacePreviews, ApplyReplacePreview, Editor, InlayId,
+};
+use gpui::{px, HighlightStyle, StrikethroughStyle, ViewContext};
+use multi_buffer::ToOffset;
+use project::search::SearchQuery;
+use std::{mem, ops::Range};
+use util::post_inc;
+
+/// The search matches whose replacements are previewed, and the query that replaces them.
+#[derive(Default)]
+pub(crate) struct ReplacePreviews {
+    matches: Vec<Range<Anchor>>,
+    query: Option<SearchQuery>,
+    /// The previews shown as ghost text after the visible matches. The other matches get theirs
+    /// once they're scrolled into view.
+    shown: Vec<ReplacePreview>,
+}
+
+/// The ghost text showing the replacement of a match.
+struct ReplacePreview {
+    range: Range<Anchor>,
+    inlay_id: InlayId,
+}
+
+enum ReplacePreviewHighlight {}
+
+impl Editor {
+    /// Shows the text that replacing each of the given matches with the replacement of the query
+    /// would produce, in place of any previous preview. Passing no query clears the preview.
+    pub fn preview_replacements(
+        &mut self,
+        matches: &[Range<Anchor>],
+        query: Option<&SearchQuery>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if query.is_none() && self.replace_previews.query.is_none() {
+            return;
+        }
+        self.replace_previews.matches = query.map_or_else(Vec::new, |_| matches.to_vec());
+        self.replace_previews.query = query.cloned();
+        self.update_replace_previews(true, cx);
+    }
+
+    /// Shows the previews of the matches scrolled into view and removes the previews of the ones
+    /// scrolled out of view, leaving the inlays alone when the visible matches didn't change.
+    pub(crate) fn refresh_replace_previews(&mut self, cx: &mut ViewContext<Self>) {
+        self.update_replace_previews(false, cx);
+    }
+
+    /// Shows the previews of the matches in the visible rows. The previews that were shown before
+    /// are kept for the matches that are still visible, unless `replace_shown` is set because the
+    /// replacements may have changed.
+    fn update_replace_previews(&mut self, replace_shown: bool, cx: &mut ViewContext<Self>) {
+        if self.replace_previews.query.is_none() && self.replace_previews.shown.is_empty() {
+            return;
+        }
+
+        let display_snapshot = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        let visible_range = self.visible_offset_range(&display_snapshot);
+        let buffer = display_snapshot.buffer_snapshot;
+        let mut previous = mem::take(&mut self.replace_previews.shown);
+        let mut to_remove = Vec::new();
+        if replace_shown {
+            to_remove.extend(previous.drain(..).map(|preview| preview.inlay_id));
+        }
+        let mut shown = Vec::new();
+        let mut to_insert = Vec::new();
+        if let Some(query) = self.replace_previews.query.as_ref() {
+            let matches = &self.replace_previews.matches;
+            let first_visible =
+                matches.partition_point(|range| range.end.to_offset(&buffer) < visible_range.start);
+            let visible_matches = matches[first_visible..]
+                .iter()
+                .take_while(|range| range.start.to_offset(&buffer) < visible_range.end);
+            for range in visible_matches {
+                if let Some(ix) = previous.iter().position(|preview| preview.range == *range) {
+                    shown.push(previous.swap_remove(ix));
+                    continue;
+                }
+
+                let text = buffer.text_for_range(range.clone()).collect::<String>();
+                let Some(replacement) = query.replacement_for(&text) else {
+                    continue;
+                };
+                let inlay = Inlay::suggestion(
+                    post_inc(&mut self.next_inlay_id),
+                    range.end,
+                    replacement.as_ref(),
+                );
+                shown.push(ReplacePreview {
+                    range: range.clone(),
+                    inlay_id: inlay.id,
+                });
+                to_insert.push(inlay);
+            }
+        }
+        to_remove.extend(previous.into_iter().map(|preview| preview.inlay_id));
+        self.replace_previews.shown = shown;
+        if to_remove.is_empty() && to_insert.is_empty() {
+            return;
+        }
+
+        self.display_map
+            .update(cx, |map, cx| map.splice_inlays(to_remove, to_insert, cx));
+        self.highlight_replace_previews(cx);
+    }
+
+    /// Applies the previewed replacements of the matches that intersect a selection.
+    pub fn apply_replace_preview(&mut self, _: &ApplyReplacePreview, cx: &mut ViewContext<Self>) {
+        let selections = self.selections.all::<usize>(cx);
+        self.apply_replace_previews(
+            |range| {
+                selections
+                    .iter()
+                    .any(|selection| selection.start <= range.end && range.start <= selection.end)
+            },
+            cx,
+        );
+    }
+
+    /// Applies the previewed replacements of all the matches, including those that aren't
+    /// visible.
+    pub fn apply_all_replace_previews(
+        &mut self,
+        _: &ApplyAllReplacePreviews,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.apply_replace_previews(|_| true, cx);
+    }
+
+    fn apply_replace_previews(
+        &mut self,
+        should_apply: impl Fn(&Range<usize>) -> bool,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if self.read_only(cx) {
+            return;
+        }
+        let Some(query) = self.replace_previews.query.as_ref() else {
+            return;
+        };
+
+        let buffer = self.buffer.read(cx).snapshot(cx);
+        let mut edits = Vec::new();
+        self.replace_previews.matches.retain(|range| {
+            let range = range.start.to_offset(&buffer)..range.end.to_offset(&buffer);
+            if !should_apply(&range) {
+                return true;
+            }
+            let text = buffer.text_for_range(range.clone()).collect::<String>();
+            if let Some(replacement) = query.replacement_for(&text) {
+                edits.push((range, replacement.into_owned()));
+            }
+            false
+        });
+        if edits.is_empty() {
+            return;
+        }
+
+        self.refresh_replace_previews(cx);
+        self.transact(cx, |this, cx| {
+            this.buffer
+                .update(cx, |buffer, cx| buffer.edit(edits, None, cx));
+        });
+    }
+
+    /// Strikes through the matches that have a previewed replacement.
+    fn highlight_replace_previews(&mut self, cx: &mut ViewContext<Self>) {
+        if self.replace_previews.shown.is_empty() {
+            self.clear_highlights::<ReplacePreviewHighlight>(cx);
+        } else {
+            let ranges = self
+                .replace_previews
+                .shown
+                .iter()
+                .map(|preview| preview.range.clone())
+                .collect();
+            self.highlight_text::<ReplacePreviewHighlight>(
+                ranges,
+                HighlightStyle {
+                    strikethrough: Some(StrikethroughStyle {
+                        thickness: px(1.),
+                        color: None,
+                    }),
+                    fade_out: Some(0.4),
+                    ..Default::default()
+                },
+                cx,
+            );
+        }
+        cx.notify();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::build_editor};
+    use gpui::TestAppContext;
+    use multi_buffer::MultiBuffer;
+
+    #[gpui::test]
+    fn test_replace_preview(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+
+        let buffer = cx.update(|cx| MultiBuffer::build_simple("foo baz foo\nfoo", cx));
+        let editor = cx.add_window(|cx| build_editor(buffer, cx));
+        _ = editor.update(cx, |editor, cx| {
+            let snapshot = editor.buffer.read(cx).snapshot(cx);
+            let matches = [0..3, 8..11, 12..15]
+                .into_iter()
+                .map(|range| snapshot.anchor_before(range.start)..snapshot.anchor_after(range.end))
+                .collect::<Vec<_>>();
+            let query = SearchQuery::text("foo", false, false, false, Vec::new(), Vec::new())
+                .unwrap()
+                .with_replacement("bar".into());
+
+            // Only the matches in the visible rows are previewed, which is the first row in an
+            // editor that wasn't laid out.
+            editor.preview_replacements(&matches, Some(&query), cx);
+            assert_eq!(editor.display_text(cx), "foobar baz foobar\nfoo");
+            assert_eq!(editor.text(cx), "foo baz foo\nfoo");
+
+            // Scrolling without bringing other matches into view keeps the previews' inlays.
+            let shown_inlay_ids = |editor: &Editor| {
+                editor
+                    .replace_previews
+                    .shown
+                    .iter()
+                    .map(|preview| preview.inlay_id)
+                    .collect::<Vec<_>>()
+            };
+            let inlay_ids = shown_inlay_ids(editor);
+            editor.refresh_replace_previews(cx);
+            assert_eq!(shown_inlay_ids(editor), inlay_ids);
+            assert_eq!(editor.display_text(cx), "foobar baz foobar\nfoo");
+
+            // Only the matches touching a selection are replaced.
+            editor.change_selections(None, cx, |s| s.select_ranges([1..1]));
+            editor.apply_replace_preview(&ApplyReplacePreview, cx);
+            assert_eq!(editor.text(cx), "bar baz foo\nfoo");
+            assert_eq!(editor.display_text(cx), "bar baz foobar\nfoo");
+
+            // All the matches are replaced, including those that weren't visible.
+            editor.apply_all_replace_previews(&ApplyAllReplacePreviews, cx);
+            assert_eq!(editor.text(cx), "bar baz bar\nbar");
+            assert_eq!(editor.display_text(cx), "bar baz bar\nbar");
+
+            editor.preview_replacements(&matches, None, cx);
+            assert_eq!(editor.display_text(cx), "bar baz bar\nbar");
+        });
+    }
+}
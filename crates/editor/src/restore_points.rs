@@ -0,0 +1,420 @@
+use crate::{
+    display_map::{BlockContext, BlockDisposition, BlockId, BlockProperties, BlockStyle},
+    git::blame::format_age,
+    Anchor, CreateRestorePoint, Editor, ToggleEditHistory,
+};
+use ::git::diff::line_changes;
+use collections::HashSet;
+use gpui::{AppContext, Model, SharedString, Task, ViewContext};
+use language::Buffer;
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use ui::{prelude::*, Tooltip};
+use util::ResultExt;
+use workspace::SplitDirection;
+
+/// How long to wait after an edit before updating the changes listed in the edit history.
+const EDIT_HISTORY_CHANGES_DEBOUNCE: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RestorePointKind {
+    Save,
+    Format,
+    Manual,
+    Restore,
+}
+
+impl RestorePointKind {
+    fn label(self) -> &'static str {
+        match self {
+            RestorePointKind::Save => "Saved",
+            RestorePointKind::Format => "Before formatting",
+            RestorePointKind::Manual => "Restore point",
+            RestorePointKind::Restore => "Before restoring",
+        }
+    }
+}
+
+/// The list of the restore points of the editor's buffer, shown above its first line.
+pub(crate) struct EditHistory {
+    block_id: BlockId,
+    /// The number of lines that restoring each restore point would add and remove, once they
+    /// have been computed.
+    line_changes: Arc<[(usize, usize)]>,
+    _line_changes_task: Task<()>,
+}
+
+impl Editor {
+    /// Names the current state of the buffer's undo tree as a restore point.
+    pub fn create_restore_point(
+        &mut self,
+        action: &CreateRestorePoint,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let name = action.name.clone().map(SharedString::from);
+        self.record_restore_point(RestorePointKind::Manual, name, cx);
+    }
+
+    /// Shows or hides the list of restore points above the first line.
+    pub fn toggle_edit_history(&mut self, _: &ToggleEditHistory, cx: &mut ViewContext<Self>) {
+        if let Some(edit_history) = self.edit_history.take() {
+            self.remove_blocks(HashSet::from_iter([edit_history.block_id]), None, cx);
+        } else {
+            let block_id = self.insert_edit_history_block(cx);
+            self.edit_history = Some(EditHistory {
+                block_id,
+                line_changes: Arc::from([]),
+                _line_changes_task: Task::ready(()),
+            });
+            self.refresh_edit_history_changes(false, cx);
+        }
+        cx.notify();
+    }
+
+    /// Names the current state of each of the editor's buffers as a restore point, in the
+    /// buffers' undo trees. Unnamed restore points aren't recorded for states that already have
+    /// one.
+    pub(crate) fn record_restore_point(
+        &mut self,
+        kind: RestorePointKind,
+        name: Option<SharedString>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let is_named = name.is_some();
+        let name: Arc<str> = name.map_or_else(|| kind.label().into(), |name| name.as_ref().into());
+        self.finalize_last_transaction(cx);
+        for buffer in self.buffer.read(cx).all_buffers() {
+            buffer.update(cx, |buffer, cx| {
+                let undo_tree = buffer.undo_tree();
+                let is_recorded = undo_tree
+                    .restore_points()
+                    .last()
+                    .map_or(false, |point| point.transaction_id() == undo_tree.current());
+                if is_named || !is_recorded {
+                    buffer.add_restore_point(name.clone(), cx);
+                }
+            });
+        }
+        self.refresh_edit_history_block(cx);
+    }
+
+    /// Returns the buffer to the state of the given restore point, by moving through its undo
+    /// tree. The current state is recorded as a restore point first.
+    pub(crate) fn restore_to_point(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        if self.read_only(cx) {
+            return;
+        }
+        let Some(buffer) = self.buffer.read(cx).as_singleton() else {
+            return;
+        };
+        let Some(transaction_id) = buffer
+            .read(cx)
+            .undo_tree()
+            .restore_points()
+            .get(ix)
+            .map(|point| point.transaction_id())
+        else {
+            return;
+        };
+        self.record_restore_point(RestorePointKind::Restore, None, cx);
+        buffer.update(cx, |buffer, cx| {
+            buffer.go_to_undo_tree_node(transaction_id, cx);
+        });
+    }
+
+    /// Opens a new editor to the side containing the buffer's text, diffed against the text of the
+    /// given restore point.
+    fn compare_with_restore_point(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        let (Some(project), Some(workspace)) = (self.project.clone(), self.workspace()) else {
+            return;
+        };
+        let Some(buffer) = self.buffer.read(cx).as_singleton() else {
+            return;
+        };
+        let buffer = buffer.read(cx);
+        let Some(point) = buffer.undo_tree().restore_points().get(ix) else {
+            return;
+        };
+        let text = buffer.text();
+        let language = buffer.language().cloned();
+        let base_text = point.text().to_string();
+
+        workspace.update(cx, |workspace, cx| {
+            let Some(buffer) = project
+                .update(cx, |project, cx| project.create_buffer(&text, language, cx))
+                .log_err()
+            else {
+                return;
+            };
+            buffer.update(cx, |buffer, cx| buffer.set_diff_base(Some(base_text), cx));
+            let editor = cx.new_view(|cx| Editor::for_buffer(buffer, Some(project), cx));
+            workspace.split_item(SplitDirection::Right, Box::new(editor), cx);
+        });
+    }
+
+    fn refresh_edit_history_block(&mut self, cx: &mut ViewContext<Self>) {
+        if let Some(edit_history) = self.edit_history.as_mut() {
+            let old_block_id = edit_history.block_id;
+            self.remove_blocks(HashSet::from_iter([old_block_id]), None, cx);
+            let block_id = self.insert_edit_history_block(cx);
+            if let Some(edit_history) = self.edit_history.as_mut() {
+                edit_history.block_id = block_id;
+            }
+            self.refresh_edit_history_changes(false, cx);
+        }
+    }
+
+    /// Recomputes the number of lines that restoring each restore point would change, in the
+    /// background and after a delay if `debounce` is true.
+    pub(crate) fn refresh_edit_history_changes(
+        &mut self,
+        debounce: bool,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if self.edit_history.is_none() {
+            return;
+        }
+        let Some(buffer) = self.buffer.read(cx).as_singleton() else {
+            return;
+        };
+
+        let task = cx.spawn(|this, mut cx| async move {
+            if debounce {
+                cx.background_executor()
+                    .timer(EDIT_HISTORY_CHANGES_DEBOUNCE)
+                    .await;
+            }
+            let Some((text, point_texts)) = buffer
+                .update(&mut cx, |buffer, _| {
+                    let point_texts = buffer
+                        .undo_tree()
+                        .restore_points()
+                        .iter()
+                        .map(|point| point.text().clone())
+                        .collect::<Vec<_>>();
+                    (buffer.as_rope().clone(), point_texts)
+                })
+                .ok()
+            else {
+                return;
+            };
+            let changes = cx
+                .background_executor()
+                .spawn(async move {
+                    let text = text.to_string();
+                    point_texts
+                        .iter()
+                        .map(|point_text| line_changes(&text, &point_text.to_string()))
+                        .collect::<Arc<[_]>>()
+                })
+                .await;
+            this.update(&mut cx, |this, cx| {
+                if let Some(edit_history) = this.edit_history.as_mut() {
+                    edit_history.line_changes = changes;
+                    cx.notify();
+                }
+            })
+            .ok();
+        });
+        if let Some(edit_history) = self.edit_history.as_mut() {
+            edit_history._line_changes_task = task;
+        }
+    }
+
+    fn insert_edit_history_block(&mut self, cx: &mut ViewContext<Self>) -> BlockId {
+        let restore_point_count = self.buffer.read(cx).as_singleton().map_or(0, |buffer| {
+            buffer.read(cx).undo_tree().restore_points().len()
+        });
+        let block_ids = self.insert_blocks(
+            [BlockProperties {
+                position: Anchor::min(),
+                height: restore_point_count.max(1) as u8 + 1,
+                style: BlockStyle::Sticky,
+                render: Arc::new(render_edit_history),
+                disposition: BlockDisposition::Above,
+            }],
+            None,
+            cx,
+        );
+        block_ids[0]
+    }
+}
+
+/// Returns the restore points of the given buffer, which the editor's edit history lists.
+fn restore_points(buffer: &Model<Buffer>, cx: &AppContext) -> Vec<(SharedString, i64)> {
+    buffer
+        .read(cx)
+        .undo_tree()
+        .restore_points()
+        .iter()
+        .map(|point| {
+            let timestamp = point
+                .timestamp()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs() as i64);
+            (SharedString::from(point.name().to_string()), timestamp)
+        })
+        .collect()
+}
+
+/// Lists the restore points, newest first, with the number of lines restoring them would change
+/// and buttons to compare the buffer with them or to restore them.
+fn render_edit_history(cx: &mut BlockContext) -> AnyElement {
+    let view = cx.view.clone();
+    let editor = view.read(cx);
+    let restore_points = editor
+        .buffer
+        .read(cx)
+        .as_singleton()
+        .map(|buffer| restore_points(&buffer, cx))
+        .unwrap_or_default();
+    let line_changes = editor.edit_history.as_ref().map_or_else(
+        || Arc::from([]),
+        |edit_history| edit_history.line_changes.clone(),
+    );
+    let now = unix_timestamp();
+    let colors = cx.theme().colors();
+
+    let header = h_flex()
+        .gap_2()
+        .child(Label::new("Edit History").size(LabelSize::Small))
+        .child(div().flex_1())
+        .child(
+            IconButton::new("edit-history-close", IconName::Close)
+                .icon_size(IconSize::Small)
+                .tooltip(|cx| Tooltip::text("Close", cx))
+                .on_click(cx.listener_for(&view, |editor, _, cx| {
+                    editor.toggle_edit_history(&ToggleEditHistory, cx)
+                })),
+        );
+
+    let entries = restore_points
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(ix, (name, timestamp))| {
+            h_flex()
+                .gap_2()
+                .child(Label::new(name.clone()).size(LabelSize::Small))
+                .child(
+                    Label::new(format_age(*timestamp, now))
+                        .size(LabelSize::Small)
+                        .color(Color::Muted),
+                )
+                .when_some(line_changes.get(ix), |this, (added, removed)| {
+                    this.child(
+                        Label::new(format!("+{added}"))
+                            .size(LabelSize::Small)
+                            .color(Color::Created),
+                    )
+                    .child(
+                        Label::new(format!("-{removed}"))
+                            .size(LabelSize::Small)
+                            .color(Color::Deleted),
+                    )
+                })
+                .child(div().flex_1())
+                .child(
+                    Button::new(("edit-history-compare", ix), "Compare")
+                        .label_size(LabelSize::Small)
+                        .tooltip(|cx| Tooltip::text("Compare the buffer with this point", cx))
+                        .on_click(cx.listener_for(&view, move |editor, _, cx| {
+                            editor.compare_with_restore_point(ix, cx)
+                        })),
+                )
+                .child(
+                    Button::new(("edit-history-restore", ix), "Restore")
+                        .label_size(LabelSize::Small)
+                        .tooltip(|cx| Tooltip::text("Restore the buffer to this point", cx))
+                        .on_click(cx.listener_for(&view, move |editor, _, cx| {
+                            editor.restore_to_point(ix, cx)
+                        })),
+                )
+        })
+        .collect::<Vec<_>>();
+
+    v_flex()
+        .id(cx.block_id)
+        .size_full()
+        .pl(cx.gutter_width)
+        .pr_2()
+        .bg(colors.editor_subheader_background)
+        .border_b_1()
+        .border_color(colors.border_variant)
+        .child(header)
+        .when(entries.is_empty(), |this| {
+            this.child(
+                Label::new(
+                    "No restore points yet. They are recorded on save and before formatting.",
+                )
+                .size(LabelSize::Small)
+                .color(Color::Muted),
+            )
+        })
+        .children(entries)
+        .into_any_element()
+}
+
+fn unix_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+
+    #[gpui::test]
+    async fn test_restore_points(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state("oneˇ");
+        cx.update_editor(|e, cx| e.create_restore_point(&CreateRestorePoint::default(), cx));
+        cx.simulate_input(" two");
+        cx.update_editor(|e, cx| {
+            e.record_restore_point(RestorePointKind::Save, None, cx);
+            // Unnamed points aren't recorded for a state that already has one.
+            e.record_restore_point(RestorePointKind::Format, None, cx);
+            e.toggle_edit_history(&ToggleEditHistory, cx);
+        });
+        cx.simulate_input(" three");
+
+        // Restore points are kept by the buffer, rather than by the editor.
+        let names = |cx: &mut EditorTestContext| {
+            cx.update_buffer(|buffer, _| {
+                let points = buffer.undo_tree().restore_points();
+                points
+                    .iter()
+                    .map(|point| point.name().to_string())
+                    .collect::<Vec<_>>()
+            })
+        };
+        assert_eq!(names(&mut cx), ["Restore point", "Saved"]);
+        cx.update_editor(|e, cx| e.restore_to_point(0, cx));
+        cx.assert_editor_state("oneˇ");
+
+        // The state before restoring is recorded, so that it can be restored as well.
+        assert_eq!(
+            names(&mut cx),
+            ["Restore point", "Saved", "Before restoring"]
+        );
+        cx.update_editor(|e, cx| e.restore_to_point(2, cx));
+        cx.update_editor(|e, cx| assert_eq!(e.text(cx), "one two three"));
+
+        // The list shows how many lines restoring each point would change.
+        cx.run_until_parked();
+        cx.update_editor(|e, _| {
+            let line_changes = &e.edit_history.as_ref().unwrap().line_changes;
+            assert_eq!(line_changes.to_vec(), [(1, 1), (1, 1), (0, 0)]);
+        });
+
+        // Restoring moves through the undo tree, so undoing continues from the restored state.
+        cx.update_editor(|e, cx| e.undo(&crate::Undo, cx));
+        cx.update_editor(|e, cx| assert_eq!(e.text(cx), "one two"));
+    }
+}
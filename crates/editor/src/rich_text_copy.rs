@@ -0,0 +1,213 @@
+use std::fmt::Write as _;
+
+use gpui::{FontStyle, FontWeight, Hsla, Rgba};
+
+/// A run of copied text sharing a single syntax style.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct StyledRun {
+    pub text: String,
+    pub color: Hsla,
+    pub bold: bool,
+    pub italic: bool,
+}
+
+impl StyledRun {
+    fn has_same_style(&self, other: &StyledRun) -> bool {
+        self.color == other.color && self.bold == other.bold && self.italic == other.italic
+    }
+}
+
+/// Accumulates styled runs, merging adjacent runs that share a style.
+#[derive(Default)]
+pub(crate) struct FormattedText {
+    runs: Vec<StyledRun>,
+}
+
+impl FormattedText {
+    pub fn push(
+        &mut self,
+        text: &str,
+        color: Hsla,
+        font_weight: Option<FontWeight>,
+        font_style: Option<FontStyle>,
+    ) {
+        if text.is_empty() {
+            return;
+        }
+
+        let run = StyledRun {
+            text: text.to_string(),
+            color,
+            bold: font_weight.map_or(false, |weight| weight >= FontWeight::BOLD),
+            italic: font_style.map_or(false, |style| style == FontStyle::Italic),
+        };
+        if let Some(last_run) = self.runs.last_mut() {
+            if last_run.has_same_style(&run) {
+                last_run.text.push_str(&run.text);
+                return;
+            }
+        }
+        self.runs.push(run);
+    }
+
+    /// Render the runs as an RTF document using the given font and background.
+    pub fn to_rtf(&self, font_family: &str, font_size: f32, background: Hsla) -> String {
+        let mut colors = vec![Rgba::from(background)];
+        let mut color_indices = Vec::with_capacity(self.runs.len());
+        for run in &self.runs {
+            let color = Rgba::from(run.color);
+            let ix = colors.iter().position(|c| *c == color).unwrap_or_else(|| {
+                colors.push(color);
+                colors.len() - 1
+            });
+            color_indices.push(ix + 1);
+        }
+
+        let mut rtf = String::new();
+        rtf.push_str("{\\rtf1\\ansi\\deff0");
+        write!(
+            rtf,
+            "{{\\fonttbl{{\\f0\\fmodern {};}}}}",
+            escape_rtf(font_family)
+        )
+        .ok();
+        rtf.push_str("{\\colortbl;");
+        for color in &colors {
+            let (r, g, b) = rgb_bytes(*color);
+            write!(rtf, "\\red{r}\\green{g}\\blue{b};").ok();
+        }
+        rtf.push('}');
+        // RTF font sizes are expressed in half-points.
+        write!(
+            rtf,
+            "\\f0\\fs{}\\cbpat1\\highlight1 ",
+            (font_size * 2.).round() as u32
+        )
+        .ok();
+
+        for (run, color_ix) in self.runs.iter().zip(color_indices) {
+            write!(rtf, "{{\\cf{color_ix}").ok();
+            if run.bold {
+                rtf.push_str("\\b");
+            }
+            if run.italic {
+                rtf.push_str("\\i");
+            }
+            rtf.push(' ');
+            rtf.push_str(&escape_rtf(&run.text));
+            rtf.push('}');
+        }
+        rtf.push('}');
+        rtf
+    }
+
+    /// Render the runs as an HTML fragment using the given font and background.
+    pub fn to_html(&self, font_family: &str, font_size: f32, background: Hsla) -> String {
+        let mut html = String::new();
+        write!(
+            html,
+            "<pre style=\"background-color:{};font-family:'{}',monospace;font-size:{}px;\">",
+            css_color(Rgba::from(background)),
+            escape_html(font_family),
+            font_size
+        )
+        .ok();
+        for run in &self.runs {
+            write!(
+                html,
+                "<span style=\"color:{};",
+                css_color(Rgba::from(run.color))
+            )
+            .ok();
+            if run.bold {
+                html.push_str("font-weight:bold;");
+            }
+            if run.italic {
+                html.push_str("font-style:italic;");
+            }
+            html.push_str("\">");
+            html.push_str(&escape_html(&run.text));
+            html.push_str("</span>");
+        }
+        html.push_str("</pre>");
+        html
+    }
+}
+
+fn rgb_bytes(color: Rgba) -> (u8, u8, u8) {
+    let channel = |value: f32| (value.clamp(0., 1.) * 255.).round() as u8;
+    (channel(color.r), channel(color.g), channel(color.b))
+}
+
+pub(crate) fn css_color(color: Rgba) -> String {
+    let (r, g, b) = rgb_bytes(color);
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+fn escape_rtf(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\\' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(ch);
+            }
+            '\n' => escaped.push_str("\\line\n"),
+            '\t' => escaped.push_str("\\tab "),
+            ch if ch.is_ascii() => escaped.push(ch),
+            ch => {
+                let mut units = [0; 2];
+                for unit in ch.encode_utf16(&mut units) {
+                    write!(escaped, "\\u{}?", *unit as i16).ok();
+                }
+            }
+        }
+    }
+    escaped
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::{black, rgb, white};
+
+    #[test]
+    fn test_styled_text_export() {
+        let red: Hsla = rgb(0xff0000).into();
+        let mut text = FormattedText::default();
+        text.push("fn ", red, Some(FontWeight::BOLD), None);
+        text.push("main", black(), None, None);
+        text.push("() {}\n", black(), None, None);
+        text.push("ü<", black(), None, Some(FontStyle::Italic));
+
+        assert_eq!(
+            text.to_rtf("Zed Mono", 12., white()),
+            "{\\rtf1\\ansi\\deff0{\\fonttbl{\\f0\\fmodern Zed Mono;}}\
+             {\\colortbl;\\red255\\green255\\blue255;\\red255\\green0\\blue0;\\red0\\green0\\blue0;}\
+             \\f0\\fs24\\cbpat1\\highlight1 \
+             {\\cf2\\b fn }{\\cf3 main() \\{\\}\\line\n}{\\cf3\\i \\u252?<}}"
+        );
+        assert_eq!(
+            text.to_html("Zed Mono", 12., white()),
+            "<pre style=\"background-color:#ffffff;font-family:'Zed Mono',monospace;font-size:12px;\">\
+             <span style=\"color:#ff0000;font-weight:bold;\">fn </span>\
+             <span style=\"color:#000000;\">main() {}\n</span>\
+             <span style=\"color:#000000;font-style:italic;\">ü&lt;</span></pre>"
+        );
+    }
+}
@@ -0,0 +1,463 @@
+use crate::{
+    bracket_colorization::BracketColor, display_map::FoldStatus, editor_settings::FoldIndicators,
+    element::LineWithInvisibles, git::DisplayDiffHunk,
+};
+use collections::{HashMap, HashSet};
+use gpui::{Font, Hsla, Pixels, ShapedLine};
+use std::{mem, ops::Range, sync::Arc};
+use theme::SyntaxTheme;
+
+/// The inputs to the layout of the gutter and blocks that don't change when only the selections
+/// change, such as when the cursor moves.
+#[derive(Clone)]
+pub(crate) struct RowLayoutKey {
+    pub font: Font,
+    pub font_size: Pixels,
+    pub text_color: Hsla,
+    pub line_number_color: Hsla,
+    pub active_line_number_color: Hsla,
+    pub fold_indicators: FoldIndicators,
+    pub gutter_hovered: bool,
+    pub large_file_mode: bool,
+    pub parse_count: usize,
+    pub diagnostics_update_count: usize,
+    pub syntax_theme: Arc<SyntaxTheme>,
+}
+
+impl PartialEq for RowLayoutKey {
+    fn eq(&self, other: &Self) -> bool {
+        // Syntax themes can't be compared, but changing the theme replaces its syntax theme.
+        self.font == other.font
+            && self.font_size == other.font_size
+            && self.text_color == other.text_color
+            && self.line_number_color == other.line_number_color
+            && self.active_line_number_color == other.active_line_number_color
+            && self.fold_indicators == other.fold_indicators
+            && self.gutter_hovered == other.gutter_hovered
+            && self.large_file_mode == other.large_file_mode
+            && self.parse_count == other.parse_count
+            && self.diagnostics_update_count == other.diagnostics_update_count
+            && Arc::ptr_eq(&self.syntax_theme, &other.syntax_theme)
+    }
+}
+
+/// Keeps the parts of the editor's layout that depend on the contents of the visible rows between
+/// frames, so that a frame in which only the selections or the scroll position changed doesn't
+/// rebuild the lines, line numbers, fold indicators, git hunks and block alignments of every row.
+///
+/// The display map reports which rows changed since the previous frame, and only the layouts of
+/// those rows are dropped. Everything is dropped when the key changes, and the lines are dropped
+/// when the editor's text highlights change too.
+#[derive(Default)]
+pub(crate) struct RowLayoutCache {
+    key: Option<RowLayoutKey>,
+    highlights_version: usize,
+    lines: HashMap<u32, CachedRowLine>,
+    line_numbers: HashMap<(u32, bool), ShapedLine>,
+    used_line_numbers: HashSet<(u32, bool)>,
+    fold_statuses: HashMap<u32, (bool, Option<(FoldStatus, u32, bool)>)>,
+    display_hunks: Option<(usize, Range<u32>, Vec<DisplayDiffHunk>)>,
+    row_lines: HashMap<u32, ShapedLine>,
+    used_row_lines: HashSet<u32>,
+    longest_line: Option<(u32, Pixels)>,
+}
+
+/// The layout of a visible row, along with the colors of its brackets, which can change without
+/// the row changing when brackets are inserted or removed before it.
+struct CachedRowLine {
+    bracket_colors: Vec<(Range<usize>, Hsla)>,
+    line: LineWithInvisibles,
+}
+
+impl RowLayoutCache {
+    /// Starts laying out a frame, dropping the layouts made stale by the given key, by the given
+    /// version of the editor's text highlights or by changes to the given display rows.
+    pub fn begin_frame(
+        &mut self,
+        key: RowLayoutKey,
+        highlights_version: usize,
+        dirty_rows: Option<Range<u32>>,
+    ) {
+        if self.highlights_version != highlights_version {
+            self.highlights_version = highlights_version;
+            self.lines.clear();
+        }
+
+        if self.key.as_ref() != Some(&key) {
+            self.key = Some(key);
+            self.lines.clear();
+            self.line_numbers.clear();
+            self.fold_statuses.clear();
+            self.display_hunks = None;
+            self.row_lines.clear();
+            self.longest_line = None;
+            return;
+        }
+
+        if let Some(dirty_rows) = dirty_rows {
+            // Fold indicators and hunks depend on the rows around them, so any change drops them.
+            self.fold_statuses.clear();
+            self.display_hunks = None;
+            self.lines.retain(|row, _| !dirty_rows.contains(row));
+            self.row_lines.retain(|row, _| !dirty_rows.contains(row));
+            if self
+                .longest_line
+                .map_or(false, |(row, _)| dirty_rows.contains(&row))
+            {
+                self.longest_line = None;
+            }
+        }
+    }
+
+    /// Drops the layouts that weren't used in the frame, whose rows were visible.
+    pub fn end_frame(&mut self, rows: Range<u32>) {
+        let used_line_numbers = mem::take(&mut self.used_line_numbers);
+        self.line_numbers
+            .retain(|number, _| used_line_numbers.contains(number));
+        let used_row_lines = mem::take(&mut self.used_row_lines);
+        self.row_lines.retain(|row, _| used_row_lines.contains(row));
+        self.fold_statuses.retain(|row, _| rows.contains(row));
+    }
+
+    /// Returns the layouts of the visible rows, given the colors of their brackets relative to the
+    /// first row. The rows that changed since they were last laid out are laid out in runs of
+    /// consecutive rows, with the colors of the brackets relative to the first row of the run.
+    pub fn lines(
+        &mut self,
+        rows: Range<u32>,
+        bracket_colors: &[BracketColor],
+        mut layout: impl FnMut(Range<u32>, &[BracketColor]) -> Vec<LineWithInvisibles>,
+    ) -> Vec<LineWithInvisibles> {
+        let mut row_bracket_colors = vec![Vec::new(); rows.len()];
+        for bracket in bracket_colors {
+            if let Some(colors) = row_bracket_colors.get_mut(bracket.row as usize) {
+                colors.push((bracket.columns.clone(), bracket.color));
+            }
+        }
+        let is_cached = |cached_lines: &HashMap<u32, CachedRowLine>, row: u32| {
+            cached_lines.get(&row).map_or(false, |cached| {
+                cached.bracket_colors == row_bracket_colors[(row - rows.start) as usize]
+            })
+        };
+
+        let mut cached_lines = mem::take(&mut self.lines);
+        let mut lines = Vec::with_capacity(rows.len());
+        let mut row = rows.start;
+        while row < rows.end {
+            if is_cached(&cached_lines, row) {
+                let cached = cached_lines.remove(&row).unwrap();
+                lines.push(cached.line.clone());
+                self.lines.insert(row, cached);
+                row += 1;
+                continue;
+            }
+
+            let mut end = row + 1;
+            while end < rows.end && !is_cached(&cached_lines, end) {
+                end += 1;
+            }
+            let run = row - rows.start..end - rows.start;
+            let run_bracket_colors = bracket_colors
+                .iter()
+                .filter(|bracket| run.contains(&bracket.row))
+                .map(|bracket| BracketColor {
+                    row: bracket.row - run.start,
+                    ..bracket.clone()
+                })
+                .collect::<Vec<_>>();
+            for (row, line) in (row..end).zip(layout(row..end, &run_bracket_colors)) {
+                lines.push(line.clone());
+                // Lines shown with a stale layout are laid out again once they're shaped.
+                if line.is_stale {
+                    continue;
+                }
+                self.lines.insert(
+                    row,
+                    CachedRowLine {
+                        bracket_colors: row_bracket_colors[(row - rows.start) as usize].clone(),
+                        line,
+                    },
+                );
+            }
+            row = end;
+        }
+        lines
+    }
+
+    /// Returns the shaped line number, shaping it unless it was shown in the previous frame.
+    pub fn line_number(
+        &mut self,
+        number: u32,
+        active: bool,
+        shape: impl FnOnce() -> ShapedLine,
+    ) -> ShapedLine {
+        self.used_line_numbers.insert((number, active));
+        if let Some(line) = self.line_numbers.get(&(number, active)) {
+            return line.clone();
+        }
+        let line = shape();
+        self.line_numbers.insert((number, active), line.clone());
+        line
+    }
+
+    /// Returns the fold indicator of the given row, computing it unless it was computed for the
+    /// row while it had the same active state.
+    pub fn fold_status(
+        &mut self,
+        row: u32,
+        active: bool,
+        compute: impl FnOnce() -> Option<(FoldStatus, u32, bool)>,
+    ) -> Option<(FoldStatus, u32, bool)> {
+        if let Some((cached_active, fold_status)) = self.fold_statuses.get(&row) {
+            if *cached_active == active {
+                return *fold_status;
+            }
+        }
+        let fold_status = compute();
+        self.fold_statuses.insert(row, (active, fold_status));
+        fold_status
+    }
+
+    /// Returns the git hunks intersecting the given rows, given the number of times the diff was
+    /// updated. The hunks are computed for the rows around the given ones too, so that scrolling
+    /// within them doesn't compute them again until the rows or the diff change.
+    pub fn display_hunks(
+        &mut self,
+        rows: Range<u32>,
+        row_count: u32,
+        diff_update_count: usize,
+        compute: impl FnOnce(Range<u32>) -> Vec<DisplayDiffHunk>,
+    ) -> Vec<DisplayDiffHunk> {
+        let is_cached =
+            self.display_hunks
+                .as_ref()
+                .map_or(false, |(cached_update_count, cached_rows, _)| {
+                    *cached_update_count == diff_update_count
+                        && cached_rows.start <= rows.start
+                        && rows.end <= cached_rows.end
+                });
+        if !is_cached {
+            let margin = rows.len() as u32;
+            let cached_rows =
+                rows.start.saturating_sub(margin)..rows.end.saturating_add(margin).min(row_count);
+            let hunks = compute(cached_rows.clone());
+            self.display_hunks = Some((diff_update_count, cached_rows, hunks));
+        }
+
+        let (_, _, hunks) = self.display_hunks.as_ref().unwrap();
+        hunks
+            .iter()
+            .filter(|hunk| match hunk {
+                DisplayDiffHunk::Folded { display_row } => rows.contains(display_row),
+                DisplayDiffHunk::Unfolded {
+                    display_row_range, ..
+                } => {
+                    // Removed lines are shown between rows, at the start of the empty range.
+                    display_row_range.start < rows.end
+                        && display_row_range.end.max(display_row_range.start + 1) > rows.start
+                }
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Drops the git hunks, such as after the diff was updated.
+    pub fn invalidate_display_hunks(&mut self) {
+        self.display_hunks = None;
+    }
+
+    /// Returns the layout of a row that isn't visible, such as the row a block is aligned to,
+    /// shaping it unless it didn't change since it was last shaped.
+    pub fn row_line(&mut self, row: u32, shape: impl FnOnce() -> ShapedLine) -> ShapedLine {
+        self.used_row_lines.insert(row);
+        if let Some(line) = self.row_lines.get(&row) {
+            return line.clone();
+        }
+        let line = shape();
+        self.row_lines.insert(row, line.clone());
+        line
+    }
+
+    /// Returns the width of the longest row, measuring it unless it didn't change since it was
+    /// last measured.
+    pub fn longest_line_width(&mut self, row: u32, measure: impl FnOnce() -> Pixels) -> Pixels {
+        if let Some((longest_row, width)) = self.longest_line {
+            if longest_row == row {
+                return width;
+            }
+        }
+        let width = measure();
+        self.longest_line = Some((row, width));
+        width
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git::diff::DiffHunkStatus;
+    use gpui::{font, px};
+    use std::cell::{Cell, RefCell};
+
+    fn key() -> RowLayoutKey {
+        RowLayoutKey {
+            font: font("Helvetica"),
+            font_size: px(14.),
+            text_color: gpui::black(),
+            line_number_color: gpui::black(),
+            active_line_number_color: gpui::red(),
+            fold_indicators: FoldIndicators::Indentation,
+            gutter_hovered: false,
+            large_file_mode: false,
+            parse_count: 0,
+            diagnostics_update_count: 0,
+            syntax_theme: Arc::default(),
+        }
+    }
+
+    #[test]
+    fn test_row_layout_cache() {
+        let key = key();
+        let shaped = Cell::new(0);
+        let shape = || {
+            shaped.set(shaped.get() + 1);
+            ShapedLine::default()
+        };
+        let mut cache = RowLayoutCache::default();
+
+        let layout_frame = |cache: &mut RowLayoutCache,
+                            key: &RowLayoutKey,
+                            dirty_rows: Option<Range<u32>>,
+                            active_row: u32| {
+            cache.begin_frame(key.clone(), 0, dirty_rows);
+            for row in 0..3 {
+                cache.line_number(row + 1, row == active_row, shape);
+                cache.fold_status(row, row == active_row, || {
+                    shaped.set(shaped.get() + 1);
+                    None
+                });
+            }
+            cache.row_line(10, shape);
+            cache.longest_line_width(20, || {
+                shaped.set(shaped.get() + 1);
+                px(100.)
+            });
+            cache.end_frame(0..3);
+            shaped.replace(0)
+        };
+
+        assert_eq!(layout_frame(&mut cache, &key, None, 0), 8);
+
+        // Moving the cursor only lays out the rows whose active state changed.
+        assert_eq!(layout_frame(&mut cache, &key, None, 1), 4);
+        assert_eq!(layout_frame(&mut cache, &key, None, 1), 0);
+
+        // Editing a row lays it out again, along with the fold indicators.
+        assert_eq!(layout_frame(&mut cache, &key, Some(10..11), 1), 4);
+        assert_eq!(layout_frame(&mut cache, &key, Some(20..u32::MAX), 1), 4);
+
+        // Changing the style or the theme lays out everything again.
+        let key = RowLayoutKey {
+            font_size: px(16.),
+            ..key
+        };
+        assert_eq!(layout_frame(&mut cache, &key, None, 1), 8);
+        let key = RowLayoutKey {
+            syntax_theme: Arc::default(),
+            ..key
+        };
+        assert_eq!(layout_frame(&mut cache, &key, None, 1), 8);
+    }
+
+    #[test]
+    fn test_lines() {
+        let bracket = |row: u32, color: Hsla| BracketColor {
+            row,
+            columns: 0..1,
+            color,
+        };
+        let laid_out = RefCell::new(Vec::new());
+        let mut cache = RowLayoutCache::default();
+        let mut layout_frame = |rows: Range<u32>,
+                                bracket_colors: &[BracketColor],
+                                highlights_version: usize,
+                                dirty_rows: Option<Range<u32>>| {
+            cache.begin_frame(key(), highlights_version, dirty_rows);
+            let lines = cache.lines(rows.clone(), bracket_colors, |rows, bracket_colors| {
+                laid_out
+                    .borrow_mut()
+                    .push((rows.clone(), bracket_colors.to_vec()));
+                rows.map(|_| LineWithInvisibles::default()).collect()
+            });
+            assert_eq!(lines.len(), rows.len());
+            cache.end_frame(rows);
+            laid_out.take()
+        };
+
+        let red = bracket(1, gpui::red());
+        assert_eq!(
+            layout_frame(0..5, &[red.clone()], 0, None),
+            [(0..5, vec![red.clone()])]
+        );
+        assert!(layout_frame(0..5, &[red.clone()], 0, None).is_empty());
+
+        // Only the rows that changed or were scrolled into view are laid out.
+        assert_eq!(layout_frame(0..5, &[red], 0, Some(2..4)), [(2..4, vec![])]);
+        assert_eq!(layout_frame(2..7, &[], 0, None), [(5..7, vec![])]);
+
+        // Rows whose brackets changed color are laid out, with the colors relative to the run.
+        let blue = bracket(3, gpui::blue());
+        assert_eq!(
+            layout_frame(2..7, &[blue.clone()], 0, None),
+            [(5..6, vec![bracket(0, gpui::blue())])]
+        );
+
+        // Changing the text highlights lays out every row.
+        assert_eq!(
+            layout_frame(2..7, &[blue.clone()], 1, None),
+            [(2..7, vec![blue])]
+        );
+    }
+
+    #[test]
+    fn test_display_hunks_while_scrolling() {
+        let hunk = |rows: Range<u32>| DisplayDiffHunk::Unfolded {
+            display_row_range: rows,
+            status: DiffHunkStatus::Modified,
+        };
+        let all_hunks = [hunk(2..4), hunk(12..13), hunk(25..25), hunk(40..45)];
+        let computed = Cell::new(0);
+        let compute = |rows: Range<u32>| {
+            computed.set(computed.get() + 1);
+            all_hunks
+                .iter()
+                .filter(|hunk| {
+                    let start = hunk.start_display_row();
+                    rows.start <= start && start <= rows.end
+                })
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        let mut cache = RowLayoutCache::default();
+
+        assert_eq!(cache.display_hunks(10..20, 100, 0, compute), [hunk(12..13)]);
+        assert_eq!(computed.get(), 1);
+
+        // Scrolling within the rows around the visible ones reuses the hunks found for them.
+        assert_eq!(
+            cache.display_hunks(3..13, 100, 0, compute),
+            [hunk(2..4), hunk(12..13)]
+        );
+        assert_eq!(cache.display_hunks(20..30, 100, 0, compute), [hunk(25..25)]);
+        assert_eq!(computed.get(), 1);
+
+        // Scrolling further or updating the diff finds them again.
+        assert_eq!(cache.display_hunks(35..45, 100, 0, compute), [hunk(40..45)]);
+        assert_eq!(computed.get(), 2);
+        cache.display_hunks(35..45, 100, 1, compute);
+        assert_eq!(computed.get(), 3);
+        cache.invalidate_display_hunks();
+        cache.display_hunks(35..45, 100, 1, compute);
+        assert_eq!(computed.get(), 4);
+    }
+}
@@ -0,0 +1,588 @@
+use crate::{
+    display_map::{BlockContext, BlockDisposition, BlockId, BlockStyle, ToDisplayPoint},
+    Anchor, Bias, BlockDecoration, DisplayPoint, Editor, EditorMode, EditorSnapshot,
+    GutterDataProvider, GutterMark, ToOffset,
+};
+use anyhow::Result;
+use collections::{HashMap, HashSet};
+use gpui::{AppContext, ClickEvent, Model, StyledText, Task, ViewContext};
+use language::{Buffer, BufferSnapshot, Language, Point};
+use std::{cell::RefCell, ops::Range, path::Path, rc::Rc, sync::Arc};
+use tempfile::TempDir;
+use ui::{prelude::*, IconButton, Tooltip};
+use workspace::RunInTerminal;
+
+/// The number of lines at the end of a failed test's output that are shown below the test.
+const MAX_FAILURE_LINES: usize = 20;
+
+/// How the last run of a test went.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TestStatus {
+    Running,
+    Passed,
+    Failed,
+}
+
+/// A runnable in the editor's buffer that its language can run, such as a test.
+#[derive(Clone)]
+pub(crate) struct Runnable {
+    pub range: Range<Anchor>,
+    /// The name the runnable is run by, qualified by its module path in languages that do so.
+    pub name: String,
+    pub language: Arc<Language>,
+}
+
+struct TestRun {
+    /// The end of the test, below which the output of a failed run is shown.
+    position: Anchor,
+    failure_block: Option<BlockId>,
+    /// The id of the terminal task running the test.
+    task_id: usize,
+    /// The directory that the test's exit code and output are written to.
+    output_dir: TempDir,
+    _read_output: Task<()>,
+}
+
+/// The tests run from the editor's gutter, by their qualified names.
+#[derive(Default)]
+pub(crate) struct Runnables {
+    runs: HashMap<String, TestRun>,
+    /// How the runs went, which are shown in the gutter by a [`TestStatusGutterData`].
+    statuses: Rc<RefCell<HashMap<String, TestStatus>>>,
+    shows_statuses: bool,
+    /// The runnables in the buffer, along with the parse count of the buffer they were found in.
+    cached: Option<(usize, Arc<[Runnable]>)>,
+}
+
+impl Runnables {
+    pub fn status(&self, name: &str) -> Option<TestStatus> {
+        self.statuses.borrow().get(name).copied()
+    }
+}
+
+/// Shows how the last run of each test went next to the line it starts on.
+struct TestStatusGutterData {
+    statuses: Rc<RefCell<HashMap<String, TestStatus>>>,
+}
+
+impl GutterDataProvider for TestStatusGutterData {
+    fn load(
+        &self,
+        buffer: &Model<Buffer>,
+        rows: Range<u32>,
+        cx: &mut AppContext,
+    ) -> Task<Result<Vec<(u32, GutterMark)>>> {
+        let buffer = buffer.read(cx);
+        let snapshot = buffer.snapshot();
+        let file_path = buffer.file().map(|file| file.path().clone());
+        let statuses = self.statuses.borrow().clone();
+        let colors = cx.theme().status();
+        let (running, passed, failed) = (colors.info, colors.success, colors.error);
+        cx.background_executor().spawn(async move {
+            let max_point = snapshot.max_point();
+            let range = snapshot.point_to_offset(Point::new(rows.start, 0).min(max_point))
+                ..snapshot.point_to_offset(Point::new(rows.end, 0).min(max_point));
+            Ok(find_runnables(&snapshot, range, file_path.as_deref())
+                .into_iter()
+                .filter_map(|(range, name, _)| {
+                    let row = snapshot.offset_to_point(range.start).row;
+                    let color = match statuses.get(&name)? {
+                        TestStatus::Running => running,
+                        TestStatus::Passed => passed,
+                        TestStatus::Failed => failed,
+                    };
+                    rows.contains(&row).then_some((row, GutterMark { color }))
+                })
+                .collect())
+        })
+    }
+}
+
+/// Finds the runnables in the given range of the buffer that its language can run, returning
+/// their ranges, the names they're run by and their languages.
+fn find_runnables(
+    buffer: &BufferSnapshot,
+    range: Range<usize>,
+    file_path: Option<&Path>,
+) -> Vec<(Range<usize>, String, Arc<Language>)> {
+    buffer
+        .runnable_ranges(range)
+        .into_iter()
+        .filter_map(|runnable| {
+            let language = buffer.language_at(runnable.run_range.start)?;
+            language.test_command(&runnable.name, false)?;
+            let name = language.test_name(&runnable, file_path);
+            Some((runnable.run_range, name, language.clone()))
+        })
+        .collect()
+}
+
+impl Editor {
+    /// Returns the runnables in the editor's buffer, which are found again only once the buffer
+    /// is reparsed.
+    fn runnables(&mut self, snapshot: &EditorSnapshot, cx: &ViewContext<Self>) -> Arc<[Runnable]> {
+        let buffer = &snapshot.buffer_snapshot;
+        let parse_count = buffer.parse_count();
+        if let Some((cached_parse_count, runnables)) = &self.runnables.cached {
+            if *cached_parse_count == parse_count {
+                return runnables.clone();
+            }
+        }
+
+        let runnables: Arc<[Runnable]> = match buffer.as_singleton() {
+            Some((_, _, singleton)) => {
+                let file_path = self
+                    .buffer
+                    .read(cx)
+                    .as_singleton()
+                    .and_then(|buffer| Some(buffer.read(cx).file()?.path().clone()));
+                find_runnables(singleton, 0..singleton.len(), file_path.as_deref())
+                    .into_iter()
+                    .map(|(range, name, language)| Runnable {
+                        range: buffer.anchor_after(range.start)..buffer.anchor_before(range.end),
+                        name,
+                        language,
+                    })
+                    .collect()
+            }
+            None => Arc::from([]),
+        };
+        self.runnables.cached = Some((parse_count, runnables.clone()));
+        runnables
+    }
+
+    /// Returns the runnables starting on the given display rows, such as tests, along with the
+    /// rows they start on.
+    pub(crate) fn runnables_in_rows(
+        &mut self,
+        rows: Range<u32>,
+        snapshot: &EditorSnapshot,
+        cx: &ViewContext<Self>,
+    ) -> Vec<(u32, Runnable)> {
+        let runnables = self.runnables(snapshot, cx);
+        let buffer = &snapshot.buffer_snapshot;
+        let start = DisplayPoint::new(rows.start, 0).to_offset(snapshot, Bias::Left);
+        let first_ix =
+            runnables.partition_point(|runnable| runnable.range.start.to_offset(buffer) < start);
+
+        let mut runnables = runnables[first_ix..]
+            .iter()
+            .map(|runnable| {
+                let row = runnable.range.start.to_display_point(snapshot).row();
+                (row, runnable)
+            })
+            .take_while(|(row, _)| *row < rows.end)
+            .filter(|(row, _)| rows.contains(row))
+            .map(|(row, runnable)| (row, runnable.clone()))
+            .collect::<Vec<_>>();
+        runnables.dedup_by_key(|(row, _)| *row);
+        runnables
+    }
+
+    /// Renders the buttons next to the tests starting on the given display rows, which run the
+    /// tests. How their last runs went is shown next to them by a [`TestStatusGutterData`].
+    pub(crate) fn render_runnable_indicators(
+        &mut self,
+        rows: Range<u32>,
+        snapshot: &EditorSnapshot,
+        cx: &mut ViewContext<Self>,
+    ) -> Vec<(u32, IconButton)> {
+        if self.mode != EditorMode::Full {
+            return Vec::new();
+        }
+
+        self.runnables_in_rows(rows, snapshot, cx)
+            .into_iter()
+            .map(|(row, runnable)| {
+                let can_debug = runnable
+                    .language
+                    .test_command(&runnable.name, true)
+                    .is_some();
+                let title = match self.runnables.status(&runnable.name) {
+                    None => "Run test",
+                    Some(TestStatus::Running) => "Running",
+                    Some(TestStatus::Passed) => "Test passed",
+                    Some(TestStatus::Failed) => "Test failed",
+                };
+                let meta = if can_debug {
+                    "Click to run, alt-click to debug"
+                } else {
+                    "Click to run"
+                };
+                let button = IconButton::new(("run-test", row as usize), IconName::Play)
+                    .icon_size(IconSize::XSmall)
+                    .icon_color(Color::Muted)
+                    .tooltip(move |cx| Tooltip::with_meta(title, None, meta, cx))
+                    .on_click(cx.listener(move |editor, event: &ClickEvent, cx| {
+                        let debug = can_debug && event.down.modifiers.alt;
+                        editor.run_test(&runnable, debug, cx);
+                    }));
+                (row, button)
+            })
+            .collect()
+    }
+
+    /// Runs the given test in a terminal, or debugs it, and shows how it went next to it once it
+    /// finishes.
+    pub(crate) fn run_test(
+        &mut self,
+        runnable: &Runnable,
+        debug: bool,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(command) = runnable.language.test_command(&runnable.name, debug) else {
+            return;
+        };
+        let Some(project) = self.project.clone() else {
+            return;
+        };
+        let working_directory = self.buffer.read(cx).as_singleton().and_then(|buffer| {
+            let path = buffer.read(cx).file()?.as_local()?.abs_path(cx);
+            Some(path.parent()?.to_path_buf())
+        });
+        let output_dir = match tempfile::Builder::new().prefix("zed-test").tempdir() {
+            Ok(output_dir) => output_dir,
+            Err(error) => {
+                log::error!("failed to create a directory for the test's output: {error}");
+                return;
+            }
+        };
+
+        // The terminal only reports that the test's process exited, so its exit code and output
+        // are written to files to find out how the test went.
+        let task_id = project.update(cx, |project, _| project.next_terminal_task_id());
+        let script = wrap_test_command(
+            &command,
+            &output_dir.path().join("status"),
+            &output_dir.path().join("output"),
+        );
+        cx.dispatch_action(Box::new(RunInTerminal {
+            command: script,
+            args: Vec::new(),
+            is_shell_script: true,
+            working_directory,
+            task_id,
+        }));
+
+        let run = TestRun {
+            position: runnable.range.end,
+            failure_block: None,
+            task_id,
+            output_dir,
+            _read_output: Task::ready(()),
+        };
+        if let Some(previous_run) = self.runnables.runs.insert(runnable.name.clone(), run) {
+            if let Some(block_id) = previous_run.failure_block {
+                self.remove_block_decorations(HashSet::from_iter([block_id]), cx);
+            }
+        }
+        self.set_test_status(&runnable.name, Some(TestStatus::Running), cx);
+    }
+
+    /// Records how the run of the test with the given name is going, and shows it in the gutter.
+    fn set_test_status(
+        &mut self,
+        name: &str,
+        status: Option<TestStatus>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let mut statuses = self.runnables.statuses.borrow_mut();
+        match status {
+            Some(status) => statuses.insert(name.to_string(), status),
+            None => statuses.remove(name),
+        };
+        drop(statuses);
+
+        if !self.runnables.shows_statuses {
+            self.runnables.shows_statuses = true;
+            let statuses = self.runnables.statuses.clone();
+            self.add_gutter_data_provider(TestStatusGutterData { statuses }, cx);
+        }
+        self.refresh_gutter_data(cx);
+    }
+
+    /// Reads how the test run by the terminal task with the given id went, once the task
+    /// finished. A test whose terminal was closed before it finished is no longer shown as
+    /// running.
+    pub(crate) fn terminal_task_finished(&mut self, task_id: usize, cx: &mut ViewContext<Self>) {
+        let statuses = self.runnables.statuses.clone();
+        let Some((name, run)) = self.runnables.runs.iter_mut().find(|(name, run)| {
+            run.task_id == task_id && statuses.borrow().get(*name) == Some(&TestStatus::Running)
+        }) else {
+            return;
+        };
+
+        let name = name.clone();
+        let output_dir = run.output_dir.path().to_path_buf();
+        let read_output = cx.background_executor().spawn(async move {
+            let status = std::fs::read_to_string(output_dir.join("status")).ok()?;
+            let exit_code = status.trim().parse::<i32>().ok()?;
+            let output = std::fs::read_to_string(output_dir.join("output")).unwrap_or_default();
+            Some((exit_code, output))
+        });
+        run._read_output = cx.spawn(|this, mut cx| async move {
+            let result = read_output.await;
+            this.update(&mut cx, |this, cx| {
+                let run = this.runnables.runs.get(&name);
+                if run.map_or(true, |run| run.task_id != task_id) {
+                    return;
+                }
+                match result {
+                    Some((exit_code, output)) => {
+                        this.finish_test_run(&name, exit_code == 0, &output, cx)
+                    }
+                    None => {
+                        this.runnables.runs.remove(&name);
+                        this.set_test_status(&name, None, cx);
+                    }
+                }
+            })
+            .ok();
+        });
+    }
+
+    /// Records how the run of the test with the given name went, showing the end of its output
+    /// below it if it failed.
+    pub(crate) fn finish_test_run(
+        &mut self,
+        name: &str,
+        passed: bool,
+        output: &str,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(run) = self.runnables.runs.get_mut(name) else {
+            return;
+        };
+        let position = run.position;
+        if let Some(block_id) = run.failure_block.take() {
+            self.remove_block_decorations(HashSet::from_iter([block_id]), cx);
+        }
+        let status = if passed {
+            TestStatus::Passed
+        } else {
+            TestStatus::Failed
+        };
+        self.set_test_status(name, Some(status), cx);
+
+        if !passed {
+            let message: SharedString = failure_message(output).into();
+            let block_ids = self.insert_block_decorations(
+                [BlockDecoration {
+                    position,
+                    disposition: BlockDisposition::Below,
+                    style: BlockStyle::Sticky,
+                    height: 1,
+                    fit_content: true,
+                    render: Arc::new(move |cx: &mut BlockContext| {
+                        div()
+                            .pl(cx.gutter_width)
+                            .py_1()
+                            .text_color(cx.theme().status().error)
+                            .child(StyledText::new(message.clone()))
+                            .into_any_element()
+                    }),
+                    on_event: None,
+                }],
+                cx,
+            );
+            if let Some(run) = self.runnables.runs.get_mut(name) {
+                run.failure_block = block_ids.first().copied();
+            }
+        }
+        cx.notify();
+    }
+}
+
+/// Returns the shell script that runs the given test command, writing its output to the given
+/// file as well as the terminal, and its exit code to the other file.
+fn wrap_test_command(command: &str, status_path: &Path, output_path: &Path) -> String {
+    format!(
+        "{{ {command}; echo $? > {status}; }} 2>&1 | tee {output}",
+        status = shell_quote(&status_path.to_string_lossy()),
+        output = shell_quote(&output_path.to_string_lossy()),
+    )
+}
+
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// Returns the last lines of the output of a failed test.
+fn failure_message(output: &str) -> String {
+    let lines = output.trim_end().lines().collect::<Vec<_>>();
+    let start = lines.len().saturating_sub(MAX_FAILURE_LINES);
+    let message = lines[start..].join("\n");
+    if message.is_empty() {
+        "Test failed".to_string()
+    } else {
+        message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        editor_tests::init_test, gutter_data::GutterMarkState,
+        test::editor_test_context::EditorTestContext,
+    };
+    use indoc::indoc;
+    use language::{Language, LanguageConfig};
+
+    #[test]
+    fn test_wrap_test_command() {
+        assert_eq!(
+            wrap_test_command(
+                "go test -run ^'TestA'$ .",
+                Path::new("/tmp/a/status"),
+                Path::new("/tmp/a/output")
+            ),
+            "{ go test -run ^'TestA'$ .; echo $? > '/tmp/a/status'; } 2>&1 | tee '/tmp/a/output'"
+        );
+        assert_eq!(failure_message("\n"), "Test failed");
+        assert_eq!(failure_message("a\nb\n"), "a\nb");
+    }
+
+    #[gpui::test]
+    async fn test_running_tests(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let language = Arc::new(
+            Language::new(
+                LanguageConfig {
+                    test_command: Some("cargo test -- --exact $NAME".into()),
+                    test_name_separator: Some("::".into()),
+                    ..Default::default()
+                },
+                Some(tree_sitter_rust::language()),
+            )
+            .with_runnable_query(
+                r#"
+                (
+                    (attribute_item (attribute) @_attribute
+                        (#match? @_attribute "^test$"))
+                    .
+                    (function_item
+                        name: (_) @name) @run
+                )
+                (mod_item
+                    name: (_) @scope_name) @scope
+                "#,
+            )
+            .unwrap(),
+        );
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+        cx.set_state(indoc! {"
+            ˇfn helper() {}
+
+            #[test]
+            fn one() {
+                helper();
+            }
+
+            mod tests {
+                #[test]
+                fn one() {}
+            }
+        "});
+
+        // Tests with the same name in different modules are run separately.
+        let runnables = cx.update_editor(|editor, cx| {
+            let snapshot = editor.snapshot(cx);
+            let runnables = editor.runnables_in_rows(0..12, &snapshot, cx);
+            assert_eq!(
+                runnables
+                    .iter()
+                    .map(|(row, runnable)| (*row, runnable.name.as_str()))
+                    .collect::<Vec<_>>(),
+                [(3, "one"), (9, "tests::one")]
+            );
+            assert_eq!(
+                runnables[1]
+                    .1
+                    .language
+                    .test_command(&runnables[1].1.name, false),
+                Some("cargo test -- --exact 'tests::one'".into())
+            );
+            runnables
+        });
+        let (one, tests_one) = (&runnables[0].1, &runnables[1].1);
+        let task_id = cx.update_editor(|editor, cx| {
+            editor.run_test(one, false, cx);
+            editor.run_test(tests_one, false, cx);
+            assert_eq!(editor.runnables.status("one"), Some(TestStatus::Running));
+            assert_eq!(
+                editor.runnables.status("tests::one"),
+                Some(TestStatus::Running)
+            );
+            editor.runnables.runs["tests::one"].task_id
+        });
+
+        // Once the test's terminal task finishes, its exit code and output are read.
+        cx.update_editor(|editor, _| {
+            let output_dir = editor.runnables.runs["tests::one"].output_dir.path();
+            std::fs::write(output_dir.join("status"), "0\n").unwrap();
+        });
+        let project = cx.update_editor(|editor, _| editor.project.clone().unwrap());
+        project.update(&mut cx.cx, |_, cx| {
+            cx.emit(project::Event::TerminalTaskFinished(task_id))
+        });
+        cx.executor().run_until_parked();
+        cx.update_editor(|editor, _| {
+            assert_eq!(editor.runnables.status("one"), Some(TestStatus::Running));
+            assert_eq!(
+                editor.runnables.status("tests::one"),
+                Some(TestStatus::Passed)
+            );
+        });
+
+        // How the runs went is shown in the gutter next to the tests.
+        let gutter_marks = |cx: &mut EditorTestContext| {
+            cx.update_editor(|editor, cx| {
+                let snapshot = editor.snapshot(cx);
+                editor
+                    .gutter_marks(0..12, &snapshot, cx)
+                    .into_iter()
+                    .map(|mark| (mark.row, mark.state))
+                    .collect::<Vec<_>>()
+            })
+        };
+        gutter_marks(&mut cx);
+        cx.executor().run_until_parked();
+        let colors = cx.update(|cx| cx.theme().status().clone());
+        assert_eq!(
+            gutter_marks(&mut cx),
+            [
+                (
+                    3,
+                    GutterMarkState::Loaded(GutterMark { color: colors.info })
+                ),
+                (
+                    9,
+                    GutterMarkState::Loaded(GutterMark {
+                        color: colors.success
+                    })
+                ),
+            ]
+        );
+
+        // A failed test shows the end of its output below it.
+        cx.update_editor(|editor, cx| {
+            editor.finish_test_run("one", false, "running 1 test\ntest one ... FAILED\n", cx);
+            assert_eq!(editor.runnables.status("one"), Some(TestStatus::Failed));
+            assert!(editor.runnables.runs["one"].failure_block.is_some());
+            let snapshot = editor.snapshot(cx);
+            assert!(snapshot.display_snapshot.max_point().row() > 11);
+        });
+
+        // Running it again until it passes removes the output.
+        cx.update_editor(|editor, cx| {
+            editor.run_test(one, false, cx);
+            editor.finish_test_run("one", true, "", cx);
+            assert_eq!(editor.runnables.status("one"), Some(TestStatus::Passed));
+            assert!(editor.runnables.runs["one"].failure_block.is_none());
+            let snapshot = editor.snapshot(cx);
+            assert_eq!(snapshot.display_snapshot.max_point().row(), 11);
+        });
+    }
+}
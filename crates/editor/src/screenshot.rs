@@ -0,0 +1,372 @@
+use std::{cmp, fmt::Write as _, ops::Range};
+
+use gpui::{px, Hsla, Pixels, Rgba, ShapedLine, WindowContext};
+use itertools::Itertools;
+use lsp::DiagnosticSeverity;
+use settings::Settings;
+use sum_tree::Bias;
+use theme::ActiveTheme;
+use util::ResultExt;
+
+use crate::{
+    bracket_colorization::{bracket_colors, bracket_pairs},
+    diagnostic_style,
+    display_map::ToDisplayPoint,
+    editor_settings::EditorSettings,
+    element::LineWithInvisibles,
+    rich_text_copy::{css_color, escape_html},
+    DisplayPoint, EditorSnapshot, EditorStyle, Point, MAX_LINE_LEN,
+};
+
+/// Height of the title bar drawn when window chrome is requested.
+const WINDOW_CHROME_HEIGHT: Pixels = px(32.);
+/// Colors of the close, minimize and zoom buttons drawn in the window chrome.
+const WINDOW_BUTTON_COLORS: [u32; 3] = [0xff5f57, 0xfebc2e, 0x28c840];
+/// Length of one wave of a diagnostic underline.
+const UNDERLINE_WAVELENGTH: f32 = 4.;
+/// How far the control points of a diagnostic underline's curves are from its baseline.
+const UNDERLINE_AMPLITUDE: f32 = 1.;
+
+/// A run of shaped text, positioned relative to the start of its line.
+pub(crate) struct ScreenshotRun {
+    pub text: String,
+    pub color: Hsla,
+    pub background: Option<Hsla>,
+    pub x: Pixels,
+    pub width: Pixels,
+}
+
+/// A wavy diagnostic underline, positioned relative to the start of its line.
+pub(crate) struct ScreenshotUnderline {
+    pub color: Hsla,
+    pub x: Pixels,
+    pub width: Pixels,
+}
+
+pub(crate) struct ScreenshotLine {
+    pub runs: Vec<ScreenshotRun>,
+    pub underlines: Vec<ScreenshotUnderline>,
+    pub width: Pixels,
+}
+
+impl ScreenshotLine {
+    fn new(line: &ShapedLine) -> Self {
+        let mut start = 0;
+        let runs = line
+            .decoration_runs()
+            .iter()
+            .map(|run| {
+                let range = start..start + run.len as usize;
+                start = range.end;
+                let x = line.x_for_index(range.start);
+                ScreenshotRun {
+                    text: line.text[range.clone()].to_string(),
+                    color: run.color,
+                    background: run.background_color,
+                    x,
+                    width: line.x_for_index(range.end) - x,
+                }
+            })
+            .collect();
+        Self {
+            runs,
+            underlines: Vec::new(),
+            width: line.width,
+        }
+    }
+}
+
+pub(crate) struct ScreenshotStyle {
+    pub title: String,
+    pub font_family: String,
+    pub font_size: Pixels,
+    pub line_height: Pixels,
+    pub ascent: Pixels,
+    pub background: Hsla,
+    pub foreground: Hsla,
+    pub padding: Pixels,
+    pub window_chrome: bool,
+}
+
+/// Lays out the given display rows the same way as they're laid out on screen, with their syntax
+/// highlighting, highlights, inlays, bracket colors and diagnostic underlines.
+pub(crate) fn layout_lines(
+    snapshot: &EditorSnapshot,
+    rows: Range<u32>,
+    style: &EditorStyle,
+    cx: &WindowContext,
+) -> Vec<ScreenshotLine> {
+    let start = DisplayPoint::new(rows.start, 0).to_offset(snapshot, Bias::Left);
+    let end = if rows.end > snapshot.max_point().row() {
+        snapshot.buffer_snapshot.len()
+    } else {
+        DisplayPoint::new(rows.end, 0).to_offset(snapshot, Bias::Right)
+    };
+    let bracket_colors = if EditorSettings::get_global(cx).colorize_brackets {
+        let pairs = bracket_pairs(&snapshot.buffer_snapshot, start..end);
+        bracket_colors(&pairs, rows.clone(), snapshot, cx.theme().players())
+    } else {
+        Vec::new()
+    };
+
+    let font_size = style.text.font_size.to_pixels(cx.rem_size());
+    let line_layouts = LineWithInvisibles::from_chunks(
+        snapshot.highlighted_chunks(rows.clone(), true, style),
+        &style.text,
+        MAX_LINE_LEN,
+        rows.len(),
+        &[],
+        &bracket_colors,
+        false,
+        font_size,
+        |text, runs| {
+            let line = cx
+                .text_system()
+                .shape_line(text.to_string().into(), font_size, runs)
+                .log_err()
+                .unwrap_or_default();
+            (line, false)
+        },
+    );
+    let mut lines = line_layouts
+        .iter()
+        .map(|line| ScreenshotLine::new(&line.line))
+        .collect::<Vec<_>>();
+
+    // Diagnostics are underlined the same way as the editor element underlines them, since
+    // they're not part of the highlighted chunks.
+    for entry in snapshot
+        .buffer_snapshot
+        .diagnostics_in_range::<_, Point>(start..end, false)
+        .filter(|entry| {
+            entry.diagnostic.severity <= DiagnosticSeverity::WARNING
+                || !entry.diagnostic.is_unnecessary
+        })
+        .sorted_by_key(|entry| cmp::Reverse(entry.diagnostic.severity))
+    {
+        let range = entry
+            .range
+            .start
+            .to_display_point(&snapshot.display_snapshot)
+            ..entry.range.end.to_display_point(&snapshot.display_snapshot);
+        let color = diagnostic_style(entry.diagnostic.severity, true, &style.status);
+        for row in range.start.row().max(rows.start)..(range.end.row() + 1).min(rows.end) {
+            let ix = (row - rows.start) as usize;
+            let Some(line) = line_layouts.get(ix).map(|line| &line.line) else {
+                continue;
+            };
+            let start_x = if row == range.start.row() {
+                line.x_for_index(range.start.column() as usize)
+            } else {
+                px(0.)
+            };
+            let end_x = if row == range.end.row() {
+                line.x_for_index(range.end.column() as usize)
+            } else {
+                line.width
+            };
+            if end_x > start_x {
+                lines[ix].underlines.push(ScreenshotUnderline {
+                    color,
+                    x: start_x,
+                    width: end_x - start_x,
+                });
+            }
+        }
+    }
+    lines
+}
+
+/// Renders already shaped lines into a standalone SVG document.
+pub(crate) fn render_svg(lines: &[ScreenshotLine], style: &ScreenshotStyle) -> String {
+    let content_width = lines
+        .iter()
+        .map(|line| line.width)
+        .max()
+        .unwrap_or_default();
+    let chrome_height = if style.window_chrome {
+        WINDOW_CHROME_HEIGHT
+    } else {
+        px(0.)
+    };
+    let width = f32::from(content_width + style.padding * 2.).ceil();
+    let height =
+        f32::from(chrome_height + style.line_height * lines.len() as f32 + style.padding * 2.)
+            .ceil();
+
+    let mut svg = String::new();
+    write!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         viewBox=\"0 0 {width} {height}\">"
+    )
+    .ok();
+    let corner_radius = if style.window_chrome { 8. } else { 0. };
+    write!(
+        svg,
+        "<rect width=\"{width}\" height=\"{height}\" rx=\"{corner_radius}\" fill=\"{}\"/>",
+        css_color(Rgba::from(style.background))
+    )
+    .ok();
+
+    if style.window_chrome {
+        let center_y = f32::from(WINDOW_CHROME_HEIGHT) / 2.;
+        for (ix, color) in WINDOW_BUTTON_COLORS.iter().enumerate() {
+            write!(
+                svg,
+                "<circle cx=\"{}\" cy=\"{center_y}\" r=\"6\" fill=\"#{color:06x}\"/>",
+                16. + ix as f32 * 20.
+            )
+            .ok();
+        }
+        write!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\" font-family=\"sans-serif\" \
+             font-size=\"12\" fill=\"{}\">{}</text>",
+            width / 2.,
+            center_y + 4.,
+            css_color(Rgba::from(style.foreground)),
+            escape_html(&style.title)
+        )
+        .ok();
+    }
+
+    write!(
+        svg,
+        "<g font-family=\"{}, monospace\" font-size=\"{}\" xml:space=\"preserve\">",
+        escape_html(&style.font_family),
+        f32::from(style.font_size)
+    )
+    .ok();
+    let baseline_offset = (style.line_height - style.font_size) / 2. + style.ascent;
+    for (row, line) in lines.iter().enumerate() {
+        let top = chrome_height + style.padding + style.line_height * row as f32;
+        for run in &line.runs {
+            if let Some(background) = run.background {
+                write!(
+                    svg,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                    f32::from(style.padding + run.x),
+                    f32::from(top),
+                    f32::from(run.width),
+                    f32::from(style.line_height),
+                    css_color(Rgba::from(background))
+                )
+                .ok();
+            }
+        }
+        let y = top + baseline_offset;
+        for run in &line.runs {
+            if run.text.trim().is_empty() {
+                continue;
+            }
+            write!(
+                svg,
+                "<text x=\"{}\" y=\"{}\" fill=\"{}\">{}</text>",
+                f32::from(style.padding + run.x),
+                f32::from(y),
+                css_color(Rgba::from(run.color)),
+                escape_html(&run.text)
+            )
+            .ok();
+        }
+        // Halfway into the descent, roughly where the editor places underlines.
+        let underline_y = y + (style.font_size - style.ascent) / 2.;
+        for underline in &line.underlines {
+            let half_waves = (f32::from(underline.width) * 2. / UNDERLINE_WAVELENGTH)
+                .ceil()
+                .max(1.) as usize;
+            write!(
+                svg,
+                "<path d=\"M{} {}q{} -{UNDERLINE_AMPLITUDE} {} 0{}\" fill=\"none\" \
+                 stroke=\"{}\" stroke-width=\"1\"/>",
+                f32::from(style.padding + underline.x),
+                f32::from(underline_y),
+                UNDERLINE_WAVELENGTH / 4.,
+                UNDERLINE_WAVELENGTH / 2.,
+                format!("t{} 0", UNDERLINE_WAVELENGTH / 2.).repeat(half_waves - 1),
+                css_color(Rgba::from(underline.color))
+            )
+            .ok();
+        }
+    }
+    svg.push_str("</g></svg>");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::{black, rgb, white};
+
+    #[test]
+    fn test_render_svg() {
+        let red: Hsla = rgb(0xff0000).into();
+        let lines = [ScreenshotLine {
+            runs: vec![
+                ScreenshotRun {
+                    text: "a<b".into(),
+                    color: red,
+                    background: Some(black()),
+                    x: px(0.),
+                    width: px(30.),
+                },
+                ScreenshotRun {
+                    text: "  ".into(),
+                    color: black(),
+                    background: None,
+                    x: px(30.),
+                    width: px(20.),
+                },
+            ],
+            underlines: Vec::new(),
+            width: px(50.),
+        }];
+        let style = ScreenshotStyle {
+            title: "main.rs".into(),
+            font_family: "Zed Mono".into(),
+            font_size: px(10.),
+            line_height: px(20.),
+            ascent: px(8.),
+            background: white(),
+            foreground: black(),
+            padding: px(4.),
+            window_chrome: false,
+        };
+
+        assert_eq!(
+            render_svg(&lines, &style),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"58\" height=\"28\" \
+             viewBox=\"0 0 58 28\"><rect width=\"58\" height=\"28\" rx=\"0\" fill=\"#ffffff\"/>\
+             <g font-family=\"Zed Mono, monospace\" font-size=\"10\" xml:space=\"preserve\">\
+             <rect x=\"4\" y=\"4\" width=\"30\" height=\"20\" fill=\"#000000\"/>\
+             <text x=\"4\" y=\"17\" fill=\"#ff0000\">a&lt;b</text></g></svg>"
+        );
+
+        // The window chrome adds a title bar with the buttons and title above the lines.
+        let svg = render_svg(
+            &lines,
+            &ScreenshotStyle {
+                window_chrome: true,
+                ..style
+            },
+        );
+        assert_eq!(svg.matches("<circle").count(), WINDOW_BUTTON_COLORS.len());
+        assert!(svg.contains(">main.rs</text>"));
+        assert!(svg.contains("<text x=\"4\" y=\"49\" fill=\"#ff0000\">a&lt;b</text>"));
+
+        // Diagnostic underlines are drawn as waves below the baseline, covering their width.
+        let lines = [ScreenshotLine {
+            runs: Vec::new(),
+            underlines: vec![ScreenshotUnderline {
+                color: red,
+                x: px(10.),
+                width: px(5.),
+            }],
+            width: px(50.),
+        }];
+        assert!(render_svg(&lines, &style).contains(
+            "<path d=\"M14 18q1 -1 2 0t2 0t2 0\" fill=\"none\" stroke=\"#ff0000\" stroke-width=\"1\"/>"
+        ));
+    }
+}
@@ -1,6 +1,7 @@
 mod actions;
 pub(crate) mod autoscroll;
 pub(crate) mod scroll_amount;
+pub mod scroll_lock;
 
 use crate::{
     display_map::{DisplaySnapshot, ToDisplayPoint},
@@ -137,6 +138,11 @@ pub struct ScrollManager {
     hide_scrollbar_task: Option<Task<()>>,
     dragging_scrollbar: bool,
     visible_line_count: Option<f32>,
+    visible_column_count: Option<f32>,
+    /// The x position the newest cursor was last autoscrolled to while it was at its goal
+    /// position, and the horizontal scroll position it was shown with, which is restored when
+    /// the cursor gets back to that position after moving through shorter lines.
+    goal_scroll_left: Option<(f32, f32)>,
 }
 
 impl ScrollManager {
@@ -151,6 +157,8 @@ impl ScrollManager {
             dragging_scrollbar: false,
             last_autoscroll: None,
             visible_line_count: None,
+            visible_column_count: None,
+            goal_scroll_left: None,
         }
     }
 
@@ -314,6 +322,15 @@ impl Editor {
         self.scroll_manager.visible_line_count
     }
 
+    /// The number of columns of text that fit in the editor's width.
+    pub fn visible_column_count(&self) -> Option<f32> {
+        self.scroll_manager.visible_column_count
+    }
+
+    pub(crate) fn set_visible_column_count(&mut self, columns: f32) {
+        self.scroll_manager.visible_column_count = Some(columns);
+    }
+
     pub(crate) fn set_visible_line_count(&mut self, lines: f32, cx: &mut ViewContext<Self>) {
         let opened_first_time = self.scroll_manager.visible_line_count.is_none();
         self.scroll_manager.visible_line_count = Some(lines);
@@ -378,6 +395,10 @@ impl Editor {
         );
 
         self.refresh_inlay_hints(InlayHintRefreshReason::NewLinesShown, cx);
+        self.refresh_selection_match_highlights(cx);
+        self.refresh_todo_highlights(cx);
+        self.refresh_replace_previews(cx);
+        self.update_scroll_lock(cx);
     }
 
     pub fn scroll_position(&self, cx: &mut ViewContext<Self>) -> gpui::Point<f32> {
@@ -394,6 +415,7 @@ impl Editor {
             .row;
         self.scroll_manager
             .set_anchor(scroll_anchor, top_row, true, false, workspace_id, cx);
+        self.update_scroll_lock(cx);
     }
 
     pub(crate) fn set_scroll_anchor_remote(
@@ -411,6 +433,7 @@ impl Editor {
         let top_row = scroll_anchor.anchor.to_point(snapshot).row;
         self.scroll_manager
             .set_anchor(scroll_anchor, top_row, false, false, workspace_id, cx);
+        self.update_scroll_lock(cx);
     }
 
     pub fn scroll_screen(&mut self, amount: &ScrollAmount, cx: &mut ViewContext<Self>) {
@@ -1,7 +1,7 @@
 use super::Axis;
 use crate::{
     Autoscroll, Bias, Editor, EditorMode, NextScreen, ScrollAnchor, ScrollCursorBottom,
-    ScrollCursorCenter, ScrollCursorTop,
+    ScrollCursorCenter, ScrollCursorLeft, ScrollCursorRight, ScrollCursorTop,
 };
 use gpui::{Point, ViewContext};
 
@@ -99,4 +99,45 @@ impl Editor {
             cx,
         )
     }
+
+    /// Scrolls horizontally so that the newest cursor is at the left edge of the editor.
+    pub fn scroll_cursor_left(&mut self, _: &ScrollCursorLeft, cx: &mut ViewContext<Editor>) {
+        let mut scroll_position = self.scroll_position(cx);
+        scroll_position.x = self.newest_cursor_x_in_ems(cx);
+        self.set_scroll_position(scroll_position, cx);
+    }
+
+    /// Scrolls horizontally so that the newest cursor is at the right edge of the editor.
+    pub fn scroll_cursor_right(&mut self, _: &ScrollCursorRight, cx: &mut ViewContext<Editor>) {
+        let visible_columns = if let Some(visible_columns) = self.visible_column_count() {
+            visible_columns
+        } else {
+            return;
+        };
+
+        let mut scroll_position = self.scroll_position(cx);
+        scroll_position.x = (self.newest_cursor_x_in_ems(cx) + 1. - visible_columns).max(0.);
+        self.set_scroll_position(scroll_position, cx);
+    }
+
+    /// Returns the laid-out x position of the newest cursor in em widths, which horizontal scroll
+    /// positions are measured in, so that wide characters before it are accounted for.
+    fn newest_cursor_x_in_ems(&mut self, cx: &mut ViewContext<Editor>) -> f32 {
+        let text_layout_details = self.text_layout_details(cx);
+        let style = &text_layout_details.editor_style;
+        let font_id = cx.text_system().resolve_font(&style.text.font());
+        let font_size = style.text.font_size.to_pixels(cx.rem_size());
+        let em_width = cx
+            .text_system()
+            .typographic_bounds(font_id, font_size, 'm')
+            .unwrap()
+            .size
+            .width;
+
+        let head = self.selections.newest_display(cx).head();
+        let x = self
+            .snapshot(cx)
+            .x_for_display_point(head, &text_layout_details);
+        x / em_width
+    }
 }
@@ -1,7 +1,7 @@
 use std::{cmp, f32};
 
 use gpui::{px, Pixels, ViewContext};
-use language::Point;
+use language::{Point, SelectionGoal};
 
 use crate::{display_map::ToDisplayPoint, Editor, EditorMode, LineWithInvisibles};
 
@@ -237,10 +237,25 @@ impl Editor {
             return false;
         }
 
+        // When the newest cursor gets back to its goal position after moving vertically through
+        // shorter lines, the horizontal scroll position it was shown with there is restored.
+        let at_goal_x = self.newest_cursor_at_goal_x(start_row, max_glyph_width, layouts, cx);
+        if let Some((goal_x, goal_scroll_left)) = self.scroll_manager.goal_scroll_left {
+            let restored_left = goal_scroll_left * max_glyph_width;
+            if at_goal_x.map_or(false, |x| (x - goal_x).abs() < max_glyph_width.0 / 2.)
+                && target_left >= restored_left
+                && target_right <= restored_left + viewport_width
+            {
+                let scrolled = self.scroll_manager.anchor.offset.x != goal_scroll_left;
+                self.scroll_manager.anchor.offset.x = goal_scroll_left;
+                return scrolled;
+            }
+        }
+
         let scroll_left = self.scroll_manager.anchor.offset.x * max_glyph_width;
         let scroll_right = scroll_left + viewport_width;
 
-        if target_left < scroll_left {
+        let scrolled = if target_left < scroll_left {
             self.scroll_manager.anchor.offset.x = (target_left / max_glyph_width).into();
             true
         } else if target_right > scroll_right {
@@ -249,6 +264,38 @@ impl Editor {
             true
         } else {
             false
+        };
+        if let Some(x) = at_goal_x {
+            self.scroll_manager.goal_scroll_left = Some((x, self.scroll_manager.anchor.offset.x));
+        }
+        scrolled
+    }
+
+    /// Returns the x position of the newest cursor if it's at the x position it keeps when moving
+    /// vertically, which may be past the end of shorter lines. Cursors that haven't moved
+    /// vertically are at their goal position.
+    fn newest_cursor_at_goal_x(
+        &self,
+        start_row: u32,
+        max_glyph_width: Pixels,
+        layouts: &[LineWithInvisibles],
+        cx: &mut ViewContext<Self>,
+    ) -> Option<f32> {
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        let newest = self.selections.newest::<Point>(cx);
+        if !newest.is_empty() {
+            return None;
+        }
+        let head = newest.head().to_display_point(&display_map);
+        let layout = layouts.get(head.row().checked_sub(start_row)? as usize)?;
+        let x = layout.line.x_for_index(head.column() as usize).0;
+        match newest.goal {
+            SelectionGoal::HorizontalPosition(goal_x)
+                if (x - goal_x).abs() >= max_glyph_width.0 / 2. =>
+            {
+                None
+            }
+            _ => Some(x),
         }
     }
 
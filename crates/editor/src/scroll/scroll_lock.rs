@@ -0,0 +1,139 @@
+use crate::{hover_popover::hide_hover, Editor, InlayHintRefreshReason, ToggleScrollLock};
+use gpui::{Context, EntityId, Model, View, ViewContext, WeakView, WindowContext};
+use workspace::Workspace;
+
+/// Scroll state shared by editors whose scrolling has been locked together. Whenever one of the
+/// editors scrolls, it records its position here and the others follow it.
+pub struct ScrollLock {
+    /// Whether followers scroll to the same fraction of their content rather than the same row.
+    proportional: bool,
+    editors: Vec<WeakView<Editor>>,
+    leader: Option<EntityId>,
+    /// The display row at the top of the leader's viewport.
+    scroll_top: f32,
+    /// The leader's scroll position as a fraction of its maximum scroll position.
+    scroll_ratio: f32,
+}
+
+/// Locks the scrolling of the given editors together, replacing any locks they were part of.
+pub fn lock_scrolling(editors: &[View<Editor>], proportional: bool, cx: &mut WindowContext) {
+    for editor in editors {
+        unlock_scrolling(editor, cx);
+    }
+
+    let lock = cx.new_model(|_| ScrollLock {
+        proportional,
+        editors: editors.iter().map(|editor| editor.downgrade()).collect(),
+        leader: None,
+        scroll_top: 0.,
+        scroll_ratio: 0.,
+    });
+    for editor in editors {
+        editor.update(cx, |editor, cx| {
+            let subscription = cx.observe(&lock, Editor::follow_scroll_lock);
+            editor.scroll_lock = Some((lock.clone(), subscription));
+        });
+    }
+    if let Some(editor) = editors.first() {
+        editor.update(cx, |editor, cx| editor.update_scroll_lock(cx));
+    }
+}
+
+/// Unlocks the scrolling of the given editor and of every editor it was locked with.
+pub fn unlock_scrolling(editor: &View<Editor>, cx: &mut WindowContext) {
+    let Some(lock) = editor
+        .read(cx)
+        .scroll_lock
+        .as_ref()
+        .map(|(lock, _)| lock.clone())
+    else {
+        return;
+    };
+    for editor in lock.read(cx).editors.clone() {
+        editor
+            .update(cx, |editor, cx| {
+                editor.scroll_lock = None;
+                cx.notify();
+            })
+            .ok();
+    }
+}
+
+impl Editor {
+    pub fn toggle_scroll_lock(
+        workspace: &mut Workspace,
+        action: &ToggleScrollLock,
+        cx: &mut ViewContext<Workspace>,
+    ) {
+        let Some(editor) = workspace.active_item_as::<Editor>(cx) else {
+            return;
+        };
+        if editor.read(cx).scroll_lock.is_some() {
+            unlock_scrolling(&editor, cx);
+            return;
+        }
+
+        let active_pane = workspace.active_pane().clone();
+        let other_editor = workspace
+            .panes()
+            .iter()
+            .filter(|pane| **pane != active_pane)
+            .find_map(|pane| pane.read(cx).active_item()?.downcast::<Editor>());
+        if let Some(other_editor) = other_editor {
+            lock_scrolling(&[editor, other_editor], action.proportional, cx);
+        }
+    }
+
+    /// Records this editor's scroll position in its scroll lock, so that the editors it is locked
+    /// with can follow it.
+    pub(crate) fn update_scroll_lock(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(lock) = self.scroll_lock.as_ref().map(|(lock, _)| lock.clone()) else {
+            return;
+        };
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        let scroll_top = self.scroll_manager.scroll_position(&display_map).y;
+        let max_row = display_map.max_point().row() as f32;
+        let scroll_ratio = if max_row > 0. {
+            (scroll_top / max_row).min(1.)
+        } else {
+            0.
+        };
+        let leader = cx.entity_id();
+        lock.update(cx, |lock, cx| {
+            lock.leader = Some(leader);
+            lock.scroll_top = scroll_top;
+            lock.scroll_ratio = scroll_ratio;
+            cx.notify();
+        });
+    }
+
+    fn follow_scroll_lock(&mut self, lock: Model<ScrollLock>, cx: &mut ViewContext<Self>) {
+        let lock = lock.read(cx);
+        if lock.leader == Some(cx.entity_id()) {
+            return;
+        }
+        let (proportional, scroll_top, scroll_ratio) =
+            (lock.proportional, lock.scroll_top, lock.scroll_ratio);
+
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        let max_row = display_map.max_point().row() as f32;
+        let mut scroll_position = self.scroll_manager.scroll_position(&display_map);
+        scroll_position.y = if proportional {
+            scroll_ratio * max_row
+        } else {
+            scroll_top.min(max_row)
+        };
+
+        hide_hover(self, cx);
+        let workspace_id = self.workspace.as_ref().map(|workspace| workspace.1);
+        self.scroll_manager.set_scroll_position(
+            scroll_position,
+            &display_map,
+            true,
+            false,
+            workspace_id,
+            cx,
+        );
+        self.refresh_inlay_hints(InlayHintRefreshReason::NewLinesShown, cx);
+    }
+}
@@ -0,0 +1,426 @@
+use crate::{
+    display_map::ToDisplayPoint, editor_settings::Scrollbar, items::BufferSearchHighlights,
+    selection_highlights::SelectionMatchHighlights, todo_highlights::TodoHighlights, Anchor,
+    DisplayPoint, DocumentHighlightRead, DocumentHighlightWrite, Editor, EditorSettings,
+    EditorSnapshot,
+};
+use git::diff::DiffHunkStatus;
+use gpui::{SharedString, WindowContext};
+use itertools::Itertools;
+use language::Point;
+use lsp::DiagnosticSeverity;
+use settings::Settings;
+use std::{any::TypeId, ops::Range, rc::Rc};
+
+/// The maximum number of markers of each kind shown in the scrollbar, which bounds the work of
+/// computing them in very large files.
+const MAX_MARKERS_PER_KIND: usize = 10_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ScrollbarMarkerKind {
+    SearchMatch,
+    SelectionMatch,
+    SymbolHighlight,
+    AddedLines,
+    ModifiedLines,
+    RemovedLines,
+    Error,
+    Warning,
+    Info,
+    Hint,
+    Todo,
+    Bookmark,
+    /// A mark with the given name.
+    Mark(char),
+}
+
+/// Whether highlights of the given type are shown in the scrollbar, in which case changing them
+/// makes the cached markers stale.
+pub(crate) fn is_scrollbar_marker_highlight(type_id: TypeId) -> bool {
+    [
+        TypeId::of::<BufferSearchHighlights>(),
+        TypeId::of::<SelectionMatchHighlights>(),
+        TypeId::of::<DocumentHighlightRead>(),
+        TypeId::of::<DocumentHighlightWrite>(),
+        TypeId::of::<TodoHighlights>(),
+    ]
+    .contains(&type_id)
+}
+
+/// A marker shown in the scrollbar next to the given display rows.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ScrollbarMarkerRows {
+    pub rows: Range<u32>,
+    pub kind: ScrollbarMarkerKind,
+}
+
+impl ScrollbarMarkerRows {
+    /// Whether a tooltip describing the marker is shown when hovering it.
+    pub fn has_label(&self) -> bool {
+        !matches!(
+            self.kind,
+            ScrollbarMarkerKind::SymbolHighlight | ScrollbarMarkerKind::Todo
+        )
+    }
+
+    /// Returns the tooltip shown when hovering the marker, which is only built once it's hovered
+    /// since diagnostics are described by their message.
+    pub fn label(&self, snapshot: &EditorSnapshot) -> Option<SharedString> {
+        let label = match self.kind {
+            ScrollbarMarkerKind::SearchMatch => "Search match",
+            ScrollbarMarkerKind::SelectionMatch => "Selection match",
+            ScrollbarMarkerKind::AddedLines => "Added lines",
+            ScrollbarMarkerKind::ModifiedLines => "Modified lines",
+            ScrollbarMarkerKind::RemovedLines => "Removed lines",
+            ScrollbarMarkerKind::Bookmark => "Bookmark",
+            ScrollbarMarkerKind::Mark(name) => return Some(format!("Mark {name}").into()),
+            ScrollbarMarkerKind::SymbolHighlight | ScrollbarMarkerKind::Todo => return None,
+            ScrollbarMarkerKind::Error
+            | ScrollbarMarkerKind::Warning
+            | ScrollbarMarkerKind::Info
+            | ScrollbarMarkerKind::Hint => {
+                let buffer = &snapshot.buffer_snapshot;
+                let start = DisplayPoint::new(self.rows.start, 0).to_point(snapshot);
+                let end = DisplayPoint::new(self.rows.end, 0).to_point(snapshot);
+                let end = Point::new(end.row, buffer.line_len(end.row));
+                let diagnostic = buffer
+                    .diagnostics_in_range::<_, Point>(start..end, false)
+                    .find(|entry| {
+                        diagnostic_marker_kind(entry.diagnostic.severity).0 == self.kind
+                    })?;
+                let severity = diagnostic_marker_kind(diagnostic.diagnostic.severity).1;
+                let message = diagnostic
+                    .diagnostic
+                    .message
+                    .lines()
+                    .next()
+                    .unwrap_or_default();
+                return Some(format!("{severity}: {message}").into());
+            }
+        };
+        Some(label.into())
+    }
+}
+
+#[derive(Clone, PartialEq)]
+struct ScrollbarMarkersKey {
+    settings: Scrollbar,
+    is_singleton: bool,
+    large_file_mode: bool,
+    edit_count: usize,
+    diagnostics_update_count: usize,
+    git_diff_update_count: usize,
+}
+
+/// Keeps the search matches, highlights, git hunks, diagnostics, TODOs, bookmarks and marks shown
+/// in the scrollbar between frames, since finding them means going through the whole buffer.
+#[derive(Default)]
+pub(crate) struct ScrollbarMarkerCache {
+    key: Option<ScrollbarMarkersKey>,
+    markers: Rc<[ScrollbarMarkerRows]>,
+}
+
+impl ScrollbarMarkerCache {
+    /// Makes the markers stale, such as after the highlights, bookmarks or marks shown in the
+    /// scrollbar changed or rows moved.
+    pub fn invalidate(&mut self) {
+        self.key = None;
+    }
+}
+
+impl Editor {
+    /// Returns the markers shown in the scrollbar, in the order they're painted, finding them
+    /// again only if the buffer, its diagnostics or git diff, the highlights, the bookmarks or the
+    /// marks changed since they were last found.
+    pub(crate) fn scrollbar_markers(
+        &mut self,
+        snapshot: &EditorSnapshot,
+        is_singleton: bool,
+        cx: &WindowContext,
+    ) -> Rc<[ScrollbarMarkerRows]> {
+        let key = ScrollbarMarkersKey {
+            settings: EditorSettings::get_global(cx).scrollbar,
+            is_singleton,
+            large_file_mode: snapshot.large_file_mode,
+            edit_count: snapshot.buffer_snapshot.edit_count(),
+            diagnostics_update_count: snapshot.buffer_snapshot.diagnostics_update_count(),
+            git_diff_update_count: snapshot.buffer_snapshot.git_diff_update_count(),
+        };
+        if self.scrollbar_marker_cache.key.as_ref() != Some(&key) {
+            let markers = self.find_scrollbar_markers(snapshot, key.settings, is_singleton, cx);
+            self.scrollbar_marker_cache = ScrollbarMarkerCache {
+                key: Some(key),
+                markers: markers.into(),
+            };
+        }
+        self.scrollbar_marker_cache.markers.clone()
+    }
+
+    fn find_scrollbar_markers(
+        &self,
+        snapshot: &EditorSnapshot,
+        settings: Scrollbar,
+        is_singleton: bool,
+        cx: &WindowContext,
+    ) -> Vec<ScrollbarMarkerRows> {
+        let display_snapshot = &snapshot.display_snapshot;
+        let mut markers = Vec::new();
+        let max_row = snapshot.max_point().row();
+
+        // Bookmarks and marks are painted last, and are shown in multi-buffers too.
+        if !is_singleton {
+            self.find_bookmark_and_mark_markers(snapshot, settings, max_row, &mut markers, cx);
+            return markers;
+        }
+
+        if settings.selections {
+            for (kind, ranges) in [
+                (
+                    ScrollbarMarkerKind::SearchMatch,
+                    self.background_highlight_row_ranges::<BufferSearchHighlights>(
+                        Anchor::min()..Anchor::max(),
+                        display_snapshot,
+                        MAX_MARKERS_PER_KIND,
+                    ),
+                ),
+                (
+                    ScrollbarMarkerKind::SelectionMatch,
+                    self.background_highlight_row_ranges::<SelectionMatchHighlights>(
+                        Anchor::min()..Anchor::max(),
+                        display_snapshot,
+                        MAX_MARKERS_PER_KIND,
+                    ),
+                ),
+            ] {
+                markers.extend(ranges.into_iter().map(|range| ScrollbarMarkerRows {
+                    rows: range.start().row()..range.end().row(),
+                    kind,
+                }));
+            }
+        }
+
+        // Rows of empty ranges are marked, rather than the line between them and the next row.
+        let marker_rows = |range: Range<Point>| {
+            let start_row = range.start.to_display_point(display_snapshot).row();
+            let end_row = range.end.to_display_point(display_snapshot).row();
+            if range.start == range.end {
+                start_row..end_row + 1
+            } else {
+                start_row..end_row
+            }
+        };
+
+        if settings.symbols_selections {
+            let read_ranges = self.background_highlight_row_ranges::<DocumentHighlightRead>(
+                Anchor::min()..Anchor::max(),
+                display_snapshot,
+                MAX_MARKERS_PER_KIND,
+            );
+            let write_ranges = self.background_highlight_row_ranges::<DocumentHighlightWrite>(
+                Anchor::min()..Anchor::max(),
+                display_snapshot,
+                MAX_MARKERS_PER_KIND,
+            );
+            markers.extend(read_ranges.into_iter().chain(write_ranges).map(|range| {
+                ScrollbarMarkerRows {
+                    rows: range.start().row()..range.end().row(),
+                    kind: ScrollbarMarkerKind::SymbolHighlight,
+                }
+            }));
+        }
+
+        if settings.git_diff && !snapshot.large_file_mode {
+            let buffer_rows = 0..snapshot.buffer_snapshot.max_buffer_row() + 1;
+            markers.extend(
+                snapshot
+                    .buffer_snapshot
+                    .git_diff_hunks_in_range(buffer_rows)
+                    .take(MAX_MARKERS_PER_KIND)
+                    .map(|hunk| ScrollbarMarkerRows {
+                        rows: marker_rows(
+                            Point::new(hunk.buffer_range.start, 0)
+                                ..Point::new(hunk.buffer_range.end, 0),
+                        ),
+                        kind: match hunk.status() {
+                            DiffHunkStatus::Added => ScrollbarMarkerKind::AddedLines,
+                            DiffHunkStatus::Modified => ScrollbarMarkerKind::ModifiedLines,
+                            DiffHunkStatus::Removed => ScrollbarMarkerKind::RemovedLines,
+                        },
+                    }),
+            );
+        }
+
+        if settings.diagnostics {
+            let max_point = snapshot.buffer_snapshot.max_point();
+            // The most severe diagnostics are painted last, and kept when there are too many.
+            let diagnostics = snapshot
+                .buffer_snapshot
+                .diagnostics_in_range::<_, Point>(Point::zero()..max_point, false)
+                .sorted_by_key(|diagnostic| std::cmp::Reverse(diagnostic.diagnostic.severity))
+                .collect::<Vec<_>>();
+            let skipped = diagnostics.len().saturating_sub(MAX_MARKERS_PER_KIND);
+            markers.extend(diagnostics.into_iter().skip(skipped).map(|diagnostic| {
+                ScrollbarMarkerRows {
+                    rows: marker_rows(diagnostic.range),
+                    kind: diagnostic_marker_kind(diagnostic.diagnostic.severity).0,
+                }
+            }));
+        }
+
+        if settings.todos {
+            if let Some((_, ranges)) = self.text_highlights::<TodoHighlights>(cx) {
+                markers.extend(ranges.iter().take(MAX_MARKERS_PER_KIND).map(|range| {
+                    let row = range.start.to_display_point(display_snapshot).row();
+                    ScrollbarMarkerRows {
+                        rows: row..row + 1,
+                        kind: ScrollbarMarkerKind::Todo,
+                    }
+                }));
+            }
+        }
+
+        self.find_bookmark_and_mark_markers(snapshot, settings, max_row, &mut markers, cx);
+        markers
+    }
+
+    fn find_bookmark_and_mark_markers(
+        &self,
+        snapshot: &EditorSnapshot,
+        settings: Scrollbar,
+        max_row: u32,
+        markers: &mut Vec<ScrollbarMarkerRows>,
+        cx: &WindowContext,
+    ) {
+        if settings.bookmarks {
+            let rows = self.bookmark_display_rows(0..max_row + 1, snapshot, cx);
+            markers.extend(rows.into_iter().map(|row| ScrollbarMarkerRows {
+                rows: row..row + 1,
+                kind: ScrollbarMarkerKind::Bookmark,
+            }));
+        }
+
+        if settings.marks {
+            let marks = self.mark_display_rows(0..max_row + 1, snapshot);
+            markers.extend(marks.into_iter().map(|(name, row)| ScrollbarMarkerRows {
+                rows: row..row + 1,
+                kind: ScrollbarMarkerKind::Mark(name),
+            }));
+        }
+    }
+}
+
+/// Returns the kind of marker shown for diagnostics of the given severity, along with the name of
+/// the severity.
+fn diagnostic_marker_kind(severity: DiagnosticSeverity) -> (ScrollbarMarkerKind, &'static str) {
+    match severity {
+        DiagnosticSeverity::ERROR => (ScrollbarMarkerKind::Error, "Error"),
+        DiagnosticSeverity::WARNING => (ScrollbarMarkerKind::Warning, "Warning"),
+        DiagnosticSeverity::INFORMATION => (ScrollbarMarkerKind::Info, "Info"),
+        _ => (ScrollbarMarkerKind::Hint, "Hint"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        actions::Newline, editor_tests::init_test, hover_popover::HoverState,
+        test::editor_test_context::EditorTestContext,
+    };
+
+    #[gpui::test]
+    async fn test_scrollbar_markers_after_edits(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state("ˇone\ntwo\nthree\nfour");
+
+        let markers = cx.update_editor(|editor, cx| {
+            let buffer = editor.buffer.read(cx).snapshot(cx);
+            let range =
+                buffer.anchor_after(Point::new(2, 0))..buffer.anchor_before(Point::new(2, 5));
+            editor.highlight_background::<BufferSearchHighlights>(
+                vec![range],
+                |colors| colors.search_match_background,
+                cx,
+            );
+            let snapshot = editor.snapshot(cx);
+            let markers = editor.scrollbar_markers(&snapshot, true, cx);
+            assert!(Rc::ptr_eq(
+                &markers,
+                &editor.scrollbar_markers(&snapshot, true, cx)
+            ));
+            markers
+        });
+        assert_eq!(
+            markers.as_ref(),
+            [ScrollbarMarkerRows {
+                rows: 2..2,
+                kind: ScrollbarMarkerKind::SearchMatch,
+            }]
+        );
+        let label = cx.update_editor(|editor, cx| markers[0].label(&editor.snapshot(cx)));
+        assert_eq!(label, Some("Search match".into()));
+
+        // Markers move along with the text they mark.
+        cx.update_editor(|editor, cx| editor.newline(&Newline, cx));
+        let markers = cx.update_editor(|editor, cx| {
+            let snapshot = editor.snapshot(cx);
+            editor.scrollbar_markers(&snapshot, true, cx)
+        });
+        assert_eq!(markers[0].rows, 3..3);
+
+        // Removing the highlights removes the markers.
+        let markers = cx.update_editor(|editor, cx| {
+            editor.clear_background_highlights::<BufferSearchHighlights>(cx);
+            let snapshot = editor.snapshot(cx);
+            editor.scrollbar_markers(&snapshot, true, cx)
+        });
+        assert!(markers.is_empty());
+    }
+
+    #[gpui::test]
+    async fn test_scrollbar_markers_invalidation(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state("ˇone\ntwo\nthree\nfour");
+
+        // Highlights that aren't shown in the scrollbar, such as those of hovered symbols, keep the
+        // markers that were found.
+        let markers = cx.update_editor(|editor, cx| {
+            let snapshot = editor.snapshot(cx);
+            editor.scrollbar_markers(&snapshot, true, cx)
+        });
+        cx.update_editor(|editor, cx| {
+            let buffer = editor.buffer.read(cx).snapshot(cx);
+            let range =
+                buffer.anchor_after(Point::new(1, 0))..buffer.anchor_before(Point::new(1, 3));
+            editor.highlight_background::<HoverState>(
+                vec![range],
+                |colors| colors.element_hover,
+                cx,
+            );
+            let snapshot = editor.snapshot(cx);
+            assert!(Rc::ptr_eq(
+                &markers,
+                &editor.scrollbar_markers(&snapshot, true, cx)
+            ));
+        });
+
+        // Marks are shown in the scrollbar as soon as they're set.
+        let markers = cx.update_editor(|editor, cx| {
+            let buffer = editor.buffer.read(cx).snapshot(cx);
+            editor.insert_mark('a', buffer.anchor_before(Point::new(3, 0)), cx);
+            let snapshot = editor.snapshot(cx);
+            editor.scrollbar_markers(&snapshot, true, cx)
+        });
+        assert_eq!(
+            markers.as_ref(),
+            [ScrollbarMarkerRows {
+                rows: 3..4,
+                kind: ScrollbarMarkerKind::Mark('a'),
+            }]
+        );
+        let label = cx.update_editor(|editor, cx| markers[0].label(&editor.snapshot(cx)));
+        assert_eq!(label, Some("Mark a".into()));
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{display_map::DisplaySnapshot, DisplayPoint, Editor, EditorMode, EditorSettings};
+use aho_corasick::AhoCorasick;
+use gpui::ViewContext;
+use language::Bias;
+use multi_buffer::MultiBufferSnapshot;
+use settings::Settings;
+use std::{ops::Range, time::Duration};
+
+const SELECTION_HIGHLIGHTS_DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(75);
+
+pub(crate) enum SelectionMatchHighlights {}
+
+impl Editor {
+    /// Highlights the other occurrences of the text of the newest selection, if it isn't empty,
+    /// in the visible rows. The search waits for the selection and scroll position to settle.
+    pub(crate) fn refresh_selection_match_highlights(&mut self, cx: &mut ViewContext<Self>) {
+        let settings = EditorSettings::get_global(cx).selection_highlight;
+        if self.mode != EditorMode::Full
+            || self.large_file_mode
+            || !settings.enabled
+            || self.selections.newest::<usize>(cx).is_empty()
+        {
+            self.selection_highlights_task = None;
+            if self.has_background_highlights::<SelectionMatchHighlights>() {
+                self.clear_background_highlights::<SelectionMatchHighlights>(cx);
+            }
+            return;
+        }
+
+        self.selection_highlights_task = Some(cx.spawn(|this, mut cx| async move {
+            cx.background_executor()
+                .timer(SELECTION_HIGHLIGHTS_DEBOUNCE_TIMEOUT)
+                .await;
+            let Some((snapshot, selection, visible_range)) = this
+                .update(&mut cx, |this, cx| {
+                    let display_snapshot = this.display_map.update(cx, |map, cx| map.snapshot(cx));
+                    let selection = this.selections.newest::<usize>(cx).range();
+                    let visible_range = this.visible_offset_range(&display_snapshot);
+                    (display_snapshot.buffer_snapshot, selection, visible_range)
+                })
+                .ok()
+            else {
+                return;
+            };
+            let ranges = cx
+                .background_executor()
+                .spawn(async move {
+                    let query = snapshot
+                        .text_for_range(selection.clone())
+                        .collect::<String>();
+                    if query.trim().is_empty() || query.chars().count() < settings.min_length {
+                        return Vec::new();
+                    }
+                    // Occurrences that start in the last visible row may end below it.
+                    let search_end = snapshot.clip_offset(
+                        (visible_range.end + query.len()).min(snapshot.len()),
+                        Bias::Right,
+                    );
+                    let search_range = visible_range.start..search_end;
+                    selection_matches(&snapshot, &query, search_range, settings.case_sensitive)
+                        .into_iter()
+                        .filter(|range| *range != selection)
+                        .map(|range| {
+                            snapshot.anchor_after(range.start)..snapshot.anchor_before(range.end)
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .await;
+            this.update(&mut cx, |this, cx| {
+                if ranges.is_empty() {
+                    this.clear_background_highlights::<SelectionMatchHighlights>(cx);
+                } else {
+                    this.highlight_background::<SelectionMatchHighlights>(
+                        ranges,
+                        |theme| theme.editor_document_highlight_read_background,
+                        cx,
+                    );
+                }
+            })
+            .ok();
+        }));
+    }
+
+    /// The offsets of the display rows shown in the editor, which may span many more buffer rows
+    /// than are visible when some of them are folded.
+    pub(crate) fn visible_offset_range(&self, snapshot: &DisplaySnapshot) -> Range<usize> {
+        let top_row = self.scroll_manager.anchor().scroll_position(snapshot).y as u32;
+        let visible_rows = self.visible_line_count().unwrap_or(0.).ceil() as u32;
+        let start = DisplayPoint::new(top_row, 0).to_offset(snapshot, Bias::Left);
+        let end_row = top_row + visible_rows + 1;
+        let end = if end_row > snapshot.max_point().row() {
+            snapshot.buffer_snapshot.len()
+        } else {
+            DisplayPoint::new(end_row, 0).to_offset(snapshot, Bias::Left)
+        };
+        start..end
+    }
+}
+
+/// Returns the ranges of the occurrences of `query` within `range`. When `case_sensitive` is
+/// false, ASCII letters match regardless of their case.
+fn selection_matches(
+    snapshot: &MultiBufferSnapshot,
+    query: &str,
+    range: Range<usize>,
+    case_sensitive: bool,
+) -> Vec<Range<usize>> {
+    let Ok(query) = AhoCorasick::builder()
+        .ascii_case_insensitive(!case_sensitive)
+        .build([query])
+    else {
+        return Vec::new();
+    };
+    query
+        .stream_find_iter(snapshot.bytes_in_range(range.clone()))
+        .filter_map(|query_match| query_match.ok())
+        .map(|query_match| range.start + query_match.start()..range.start + query_match.end())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        editor_settings::SelectionHighlightContent,
+        editor_tests::init_test,
+        scrollbar_markers::{ScrollbarMarkerKind, ScrollbarMarkerRows},
+        test::editor_test_context::EditorTestContext,
+    };
+    use gpui::point;
+    use settings::SettingsStore;
+
+    #[gpui::test]
+    async fn test_selection_match_highlights(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        _ = cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|settings, cx| {
+                settings.update_user_settings::<EditorSettings>(cx, |settings| {
+                    settings.selection_highlight = Some(SelectionHighlightContent {
+                        enabled: Some(true),
+                        min_length: Some(2),
+                        case_sensitive: Some(false),
+                    });
+                });
+            })
+        });
+
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state("«fooˇ» bar Foo\nbaz foo f");
+        cx.executor()
+            .advance_clock(SELECTION_HIGHLIGHTS_DEBOUNCE_TIMEOUT);
+        cx.run_until_parked();
+        cx.assert_editor_background_highlights::<SelectionMatchHighlights>(
+            "foo bar «Foo»\nbaz «foo» f",
+        );
+        let markers = cx.update_editor(|editor, cx| {
+            let snapshot = editor.snapshot(cx);
+            editor.scrollbar_markers(&snapshot, true, cx)
+        });
+        // Occurrences on adjacent rows share a marker.
+        assert_eq!(
+            markers.as_ref(),
+            [ScrollbarMarkerRows {
+                rows: 0..1,
+                kind: ScrollbarMarkerKind::SelectionMatch,
+            }]
+        );
+
+        // Selections shorter than the minimum length aren't highlighted.
+        cx.set_state("foo bar Foo\nbaz foo «fˇ»");
+        cx.executor()
+            .advance_clock(SELECTION_HIGHLIGHTS_DEBOUNCE_TIMEOUT);
+        cx.run_until_parked();
+        cx.assert_editor_background_highlights::<SelectionMatchHighlights>(
+            "foo bar Foo\nbaz foo f",
+        );
+
+        // Only the occurrences in the visible rows are highlighted, until the editor is scrolled.
+        let filler = "filler\n".repeat(500);
+        cx.set_state(&format!("«fooˇ»\nfoo\n{filler}foo"));
+        cx.executor()
+            .advance_clock(SELECTION_HIGHLIGHTS_DEBOUNCE_TIMEOUT);
+        cx.run_until_parked();
+        cx.assert_editor_background_highlights::<SelectionMatchHighlights>(&format!(
+            "foo\n«foo»\n{filler}foo"
+        ));
+        cx.update_editor(|editor, cx| editor.set_scroll_position(point(0., 480.), cx));
+        cx.executor()
+            .advance_clock(SELECTION_HIGHLIGHTS_DEBOUNCE_TIMEOUT);
+        cx.run_until_parked();
+        cx.assert_editor_background_highlights::<SelectionMatchHighlights>(&format!(
+            "foo\nfoo\n{filler}«foo»"
+        ));
+    }
+}
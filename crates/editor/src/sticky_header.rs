@@ -0,0 +1,123 @@
+use crate::{
+    display_map::ToDisplayPoint, scroll::Autoscroll, Anchor, DisplayPoint, Editor, EditorMode,
+    EditorSettings, EditorSnapshot, EditorStyle,
+};
+use gpui::{AnyElement, MouseButton, StyledText, ViewContext};
+use language::OutlineItem;
+use multi_buffer::ToPoint;
+use settings::Settings;
+use theme::SyntaxTheme;
+use ui::prelude::*;
+
+/// Renders the signature of the scope enclosing the top of the viewport, to be pinned over the
+/// first visible row. Clicking it moves the cursor to the start of the scope.
+pub(crate) fn render_sticky_header(
+    editor: &Editor,
+    snapshot: &EditorSnapshot,
+    first_visible_row: u32,
+    padding_left: Pixels,
+    style: &EditorStyle,
+    cx: &mut ViewContext<Editor>,
+) -> Option<AnyElement> {
+    if editor.mode != EditorMode::Full || !EditorSettings::get_global(cx).sticky_scroll {
+        return None;
+    }
+
+    let item = sticky_header_item(snapshot, first_visible_row, Some(&style.syntax))?;
+    let scope_start = item.range.start;
+    let colors = cx.theme().colors();
+    Some(
+        div()
+            .id("sticky-header")
+            .size_full()
+            .pl(padding_left)
+            .overflow_hidden()
+            .cursor_pointer()
+            .bg(colors.editor_background)
+            .border_b_1()
+            .border_color(colors.border_variant)
+            .hover(|style| style.bg(colors.editor_active_line_background))
+            .child(StyledText::new(item.text).with_highlights(&style.text, item.highlight_ranges))
+            .on_mouse_down(MouseButton::Left, |_, cx| cx.stop_propagation())
+            .on_click(cx.listener(move |editor, _, cx| {
+                editor.change_selections(Some(Autoscroll::center()), cx, |s| {
+                    s.select_anchor_ranges([scope_start..scope_start])
+                });
+                cx.stop_propagation();
+            }))
+            .into_any_element(),
+    )
+}
+
+/// Returns the innermost outline item that continues below the given display row and whose
+/// start is above it, so that its signature has scrolled out of view when that row is the first
+/// visible one.
+pub(crate) fn sticky_header_item(
+    snapshot: &EditorSnapshot,
+    row: u32,
+    theme: Option<&SyntaxTheme>,
+) -> Option<OutlineItem<Anchor>> {
+    let first_uncovered_row = row + 1;
+    if first_uncovered_row > snapshot.max_point().row() {
+        return None;
+    }
+
+    let position = DisplayPoint::new(first_uncovered_row, 0).to_point(&snapshot.display_snapshot);
+    let (_, items) = snapshot
+        .buffer_snapshot
+        .symbols_containing(position, theme)?;
+    items.into_iter().rev().find(|item| {
+        item.range
+            .start
+            .to_display_point(&snapshot.display_snapshot)
+            .row()
+            < row
+            && item.range.end.to_point(&snapshot.buffer_snapshot) > position
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, MultiBuffer};
+    use gpui::{Context, TestAppContext};
+    use language::{Buffer, BufferId, Language, LanguageConfig};
+    use std::sync::Arc;
+
+    #[gpui::test]
+    async fn test_sticky_header_item(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+
+        let language = Arc::new(
+            Language::new(
+                LanguageConfig::default(),
+                Some(tree_sitter_rust::language()),
+            )
+            .with_outline_query("(function_item \"fn\" @context name: (_) @name) @item")
+            .unwrap(),
+        );
+        let text = "fn a() {\n    1;\n    2;\n    3;\n}\n\nfn b() {}\n";
+        let buffer = cx.new_model(|cx| {
+            Buffer::new(0, BufferId::new(cx.entity_id().as_u64()).unwrap(), text)
+                .with_language(language, cx)
+        });
+        let buffer = cx.new_model(|cx| MultiBuffer::singleton(buffer, cx));
+        let (view, cx) = cx.add_window_view(|cx| Editor::new(EditorMode::Full, buffer, None, cx));
+        view.condition::<crate::EditorEvent>(&cx, |view, cx| !view.buffer.read(cx).is_parsing(cx))
+            .await;
+
+        _ = view.update(cx, |view, cx| {
+            let snapshot = view.snapshot(cx);
+            let header = |row| {
+                sticky_header_item(&snapshot, row, None)
+                    .map(|item| item.range.start.to_point(&snapshot.buffer_snapshot).row)
+            };
+            // No header repeats a signature that is still visible.
+            assert_eq!(header(0), None);
+            assert_eq!(header(2), Some(0));
+            assert_eq!(header(3), Some(0));
+            assert_eq!(header(4), None);
+            assert_eq!(header(6), None);
+        });
+    }
+}
@@ -0,0 +1,368 @@
+use crate::{
+    display_map::{BlockContext, BlockDisposition, BlockId, BlockProperties, BlockStyle},
+    scroll::Autoscroll,
+    ConfirmSurround, DeleteSurrounding, Editor, EditorElement, EditorStyle, SurroundWith,
+};
+use gpui::{View, ViewContext};
+use language::{Selection, SelectionGoal};
+use multi_buffer::MultiBufferSnapshot;
+use std::{ops::Range, sync::Arc};
+use ui::prelude::*;
+
+/// The pairs used in buffers whose language doesn't define any brackets.
+const DEFAULT_PAIRS: &[(&str, &str)] = &[
+    ("(", ")"),
+    ("[", "]"),
+    ("{", "}"),
+    ("<", ">"),
+    ("\"", "\""),
+    ("'", "'"),
+    ("`", "`"),
+];
+
+/// The input shown below the newest selection, asking for the text to surround selections with.
+pub(crate) struct SurroundPrompt {
+    editor: View<Editor>,
+    block_id: BlockId,
+}
+
+impl Editor {
+    /// Wraps every selection with a pair of brackets or quotes, or with an HTML tag. When no
+    /// opening text is given, it is asked for in a prompt.
+    pub fn surround_with(&mut self, action: &SurroundWith, cx: &mut ViewContext<Self>) {
+        if self.read_only(cx) {
+            return;
+        }
+        match action.open.as_deref() {
+            Some(open) if !open.is_empty() => self.surround_selections(open, cx),
+            _ => self.show_surround_prompt(cx),
+        }
+    }
+
+    pub fn confirm_surround(&mut self, _: &ConfirmSurround, cx: &mut ViewContext<Self>) {
+        let Some(prompt) = self.take_surround_prompt(cx) else {
+            cx.propagate();
+            return;
+        };
+        let open = prompt.editor.read(cx).text(cx);
+        if !open.trim().is_empty() {
+            self.surround_selections(open.trim(), cx);
+        }
+    }
+
+    /// Removes the innermost pair enclosing each selection, found with the syntax tree when the
+    /// buffer has one.
+    pub fn delete_surrounding(&mut self, _: &DeleteSurrounding, cx: &mut ViewContext<Self>) {
+        if self.read_only(cx) {
+            return;
+        }
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let mut ranges = Vec::new();
+        for selection in self.selections.all::<usize>(cx) {
+            let range = selection.range();
+            let enclosing_pair = snapshot
+                .innermost_enclosing_bracket_ranges(range.clone())
+                .or_else(|| {
+                    let pairs = pairs_at(&snapshot, range.start);
+                    enclosing_pair(&snapshot, range, &pairs)
+                });
+            if let Some((open, close)) = enclosing_pair {
+                ranges.push(open);
+                ranges.push(close);
+            }
+        }
+        if ranges.is_empty() {
+            return;
+        }
+        ranges.sort_unstable_by_key(|range| range.start);
+        ranges.dedup();
+
+        self.transact(cx, |this, cx| {
+            this.buffer.update(cx, |buffer, cx| {
+                buffer.edit(ranges.into_iter().map(|range| (range, "")), None, cx)
+            });
+            this.request_autoscroll(Autoscroll::fit(), cx);
+        });
+    }
+
+    pub(crate) fn take_surround_prompt(
+        &mut self,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<SurroundPrompt> {
+        let prompt = self.surround_prompt.take()?;
+        if prompt.editor.focus_handle(cx).is_focused(cx) {
+            cx.focus(&self.focus_handle);
+        }
+        self.remove_blocks(
+            [prompt.block_id].into_iter().collect(),
+            Some(Autoscroll::fit()),
+            cx,
+        );
+        Some(prompt)
+    }
+
+    fn show_surround_prompt(&mut self, cx: &mut ViewContext<Self>) {
+        self.take_surround_prompt(cx);
+        let prompt_editor = cx.new_view(Editor::single_line);
+        cx.focus(&prompt_editor.focus_handle(cx));
+        let block_id = self.insert_blocks(
+            [BlockProperties {
+                style: BlockStyle::Flex,
+                position: self.selections.newest_anchor().head(),
+                height: 1,
+                render: Arc::new({
+                    let prompt_editor = prompt_editor.clone();
+                    move |cx: &mut BlockContext| {
+                        let style = EditorStyle {
+                            background: cx.theme().system().transparent,
+                            ..cx.editor_style.clone()
+                        };
+                        h_flex()
+                            .pl(cx.anchor_x)
+                            .gap_2()
+                            .child(Label::new("Surround with:").color(Color::Muted))
+                            .child(
+                                div()
+                                    .flex_1()
+                                    .child(EditorElement::new(&prompt_editor, style)),
+                            )
+                            .into_any_element()
+                    }
+                }),
+                disposition: BlockDisposition::Below,
+            }],
+            Some(Autoscroll::fit()),
+            cx,
+        )[0];
+        self.surround_prompt = Some(SurroundPrompt {
+            editor: prompt_editor,
+            block_id,
+        });
+        cx.notify();
+    }
+
+    /// Wraps every selection with `open` and its closing counterpart, keeping the wrapped text
+    /// selected.
+    fn surround_selections(&mut self, open: &str, cx: &mut ViewContext<Self>) {
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let mut edits = Vec::new();
+        let mut new_selections = Vec::new();
+        let mut delta = 0;
+        for selection in self.selections.all::<usize>(cx) {
+            let (open, close) = surround_pair(open, &pairs_at(&snapshot, selection.start));
+            if selection.is_empty() {
+                edits.push((selection.start..selection.start, format!("{open}{close}")));
+            } else {
+                edits.push((selection.start..selection.start, open.clone()));
+                edits.push((selection.end..selection.end, close.clone()));
+            }
+            new_selections.push(Selection {
+                id: selection.id,
+                start: selection.start + delta + open.len(),
+                end: selection.end + delta + open.len(),
+                reversed: selection.reversed,
+                goal: SelectionGoal::None,
+            });
+            delta += open.len() + close.len();
+        }
+
+        self.transact(cx, |this, cx| {
+            this.buffer
+                .update(cx, |buffer, cx| buffer.edit(edits, None, cx));
+            this.change_selections(Some(Autoscroll::fit()), cx, |s| s.select(new_selections));
+        });
+    }
+}
+
+/// Returns the bracket pairs of the language at the given offset.
+fn pairs_at(snapshot: &MultiBufferSnapshot, offset: usize) -> Vec<(String, String)> {
+    let pairs = snapshot
+        .language_scope_at(offset)
+        .map(|scope| {
+            scope
+                .brackets()
+                .map(|(pair, _)| (pair.start.clone(), pair.end.clone()))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    if pairs.is_empty() {
+        DEFAULT_PAIRS
+            .iter()
+            .map(|(start, end)| (start.to_string(), end.to_string()))
+            .collect()
+    } else {
+        pairs
+    }
+}
+
+/// Returns the text to insert before and after a selection to surround it with `text`, which is
+/// either one side of a pair, an HTML tag with or without its angle brackets, or any other text,
+/// which is then inserted on both sides.
+fn surround_pair(text: &str, pairs: &[(String, String)]) -> (String, String) {
+    if let Some((start, end)) = pairs
+        .iter()
+        .find(|(start, end)| start == text || end == text)
+    {
+        return (start.clone(), end.clone());
+    }
+
+    let tag = text
+        .strip_prefix('<')
+        .and_then(|tag| tag.strip_suffix('>'))
+        .unwrap_or(text);
+    let name = tag
+        .split(|c: char| c.is_whitespace() || c == '/')
+        .next()
+        .unwrap_or_default();
+    let is_tag_name = name.starts_with(|c: char| c.is_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | ':' | '.'));
+    if is_tag_name {
+        (format!("<{tag}>"), format!("</{name}>"))
+    } else {
+        (text.to_string(), text.to_string())
+    }
+}
+
+/// Finds the innermost pair of single-character, asymmetric brackets enclosing `range`, by
+/// counting the brackets around it. This is used when the buffer has no syntax tree.
+fn enclosing_pair(
+    snapshot: &MultiBufferSnapshot,
+    range: Range<usize>,
+    pairs: &[(String, String)],
+) -> Option<(Range<usize>, Range<usize>)> {
+    let single_char = |text: &str| {
+        let mut chars = text.chars();
+        chars.next().filter(|_| chars.next().is_none())
+    };
+    let pairs = pairs
+        .iter()
+        .filter_map(|(start, end)| Some((single_char(start)?, single_char(end)?)))
+        .filter(|(open, close)| open != close)
+        .collect::<Vec<_>>();
+
+    let mut depths = vec![0; pairs.len()];
+    let mut offset = range.start;
+    for ch in snapshot.reversed_chars_at(range.start) {
+        offset -= ch.len_utf8();
+        for (ix, &(open, close)) in pairs.iter().enumerate() {
+            if ch == close {
+                depths[ix] += 1;
+            } else if ch == open {
+                if depths[ix] > 0 {
+                    depths[ix] -= 1;
+                } else if let Some(close_offset) = matching_close(snapshot, range.end, open, close)
+                {
+                    return Some((
+                        offset..offset + open.len_utf8(),
+                        close_offset..close_offset + close.len_utf8(),
+                    ));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Returns the offset of the first `close` after `offset` that isn't matched by an `open`.
+fn matching_close(
+    snapshot: &MultiBufferSnapshot,
+    mut offset: usize,
+    open: char,
+    close: char,
+) -> Option<usize> {
+    let mut depth = 0;
+    for ch in snapshot.chars_at(offset) {
+        if ch == open {
+            depth += 1;
+        } else if ch == close {
+            if depth == 0 {
+                return Some(offset);
+            }
+            depth -= 1;
+        }
+        offset += ch.len_utf8();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+
+    #[gpui::test]
+    async fn test_surround_with(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state("«oneˇ» two ˇ three");
+        cx.update_editor(|e, cx| {
+            e.surround_with(
+                &SurroundWith {
+                    open: Some("(".into()),
+                },
+                cx,
+            )
+        });
+        cx.assert_editor_state("(«oneˇ») two (ˇ) three");
+
+        cx.set_state("«oneˇ» two «threeˇ»");
+        cx.update_editor(|e, cx| {
+            e.surround_with(
+                &SurroundWith {
+                    open: Some("<a href=\"#\">".into()),
+                },
+                cx,
+            )
+        });
+        cx.assert_editor_state("<a href=\"#\">«oneˇ»</a> two <a href=\"#\">«threeˇ»</a>");
+
+        // Without an opening text, it's asked for in a prompt.
+        cx.set_state("«oneˇ» two");
+        cx.update_editor(|e, cx| {
+            e.surround_with(&SurroundWith::default(), cx);
+            let prompt_editor = e.surround_prompt.as_ref().unwrap().editor.clone();
+            prompt_editor.update(cx, |prompt_editor, cx| prompt_editor.insert("em", cx));
+            e.confirm_surround(&ConfirmSurround, cx);
+            assert!(e.surround_prompt.is_none());
+        });
+        cx.assert_editor_state("<em>«oneˇ»</em> two");
+    }
+
+    #[gpui::test]
+    async fn test_delete_surrounding(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state("(one [tˇwo (three)] four)");
+        cx.update_editor(|e, cx| e.delete_surrounding(&DeleteSurrounding, cx));
+        cx.assert_editor_state("(one tˇwo (three) four)");
+
+        cx.update_editor(|e, cx| e.delete_surrounding(&DeleteSurrounding, cx));
+        cx.assert_editor_state("one tˇwo (three) four");
+
+        // Selections without an enclosing pair are left untouched.
+        cx.update_editor(|e, cx| e.delete_surrounding(&DeleteSurrounding, cx));
+        cx.assert_editor_state("one tˇwo (three) four");
+    }
+
+    #[test]
+    fn test_surround_pair() {
+        let pairs = DEFAULT_PAIRS
+            .iter()
+            .map(|(start, end)| (start.to_string(), end.to_string()))
+            .collect::<Vec<_>>();
+        let pair = |text| surround_pair(text, &pairs);
+        assert_eq!(pair("{"), ("{".into(), "}".into()));
+        assert_eq!(pair("]"), ("[".into(), "]".into()));
+        assert_eq!(pair("'"), ("'".into(), "'".into()));
+        assert_eq!(pair("div"), ("<div>".into(), "</div>".into()));
+        assert_eq!(
+            pair("<div class=\"a\">"),
+            ("<div class=\"a\">".into(), "</div>".into())
+        );
+        assert_eq!(pair("**"), ("**".into(), "**".into()));
+    }
+}
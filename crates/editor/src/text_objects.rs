@@ -0,0 +1,158 @@
+use crate::{
+    scroll::Autoscroll, Editor, SelectArgument, SelectEnclosingFunction, SelectEnclosingType,
+    SelectStringContents,
+};
+use gpui::ViewContext;
+use language::{Selection, SelectionGoal, TextObject};
+
+impl Editor {
+    pub fn select_enclosing_function(
+        &mut self,
+        _: &SelectEnclosingFunction,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.select_text_object(TextObject::Function, true, cx);
+    }
+
+    pub fn select_enclosing_type(&mut self, _: &SelectEnclosingType, cx: &mut ViewContext<Self>) {
+        self.select_text_object(TextObject::Type, true, cx);
+    }
+
+    pub fn select_argument(&mut self, _: &SelectArgument, cx: &mut ViewContext<Self>) {
+        self.select_text_object(TextObject::Argument, false, cx);
+    }
+
+    pub fn select_string_contents(&mut self, _: &SelectStringContents, cx: &mut ViewContext<Self>) {
+        self.select_text_object(TextObject::String, false, cx);
+    }
+
+    /// Selects the innermost text object of the given kind enclosing each selection, or only its
+    /// inside when `around` is false. Selections outside of any such object are left unchanged.
+    pub fn select_text_object(
+        &mut self,
+        object: TextObject,
+        around: bool,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let buffer = self.buffer.read(cx).snapshot(cx);
+        let mut selected_object = false;
+        let new_selections = self
+            .selections
+            .all::<usize>(cx)
+            .into_iter()
+            .map(|selection| {
+                let Some(range) = buffer.range_for_text_object(selection.range(), object, around)
+                else {
+                    return selection;
+                };
+                selected_object = true;
+                Selection {
+                    id: selection.id,
+                    start: range.start,
+                    end: range.end,
+                    goal: SelectionGoal::None,
+                    reversed: false,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if selected_object {
+            self.change_selections(Some(Autoscroll::fit()), cx, |s| s.select(new_selections));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+    use indoc::indoc;
+    use language::{Language, LanguageConfig};
+    use std::sync::Arc;
+
+    #[gpui::test]
+    async fn test_select_text_objects(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorTestContext::new(cx).await;
+        let language = Arc::new(
+            Language::new(
+                LanguageConfig::default(),
+                Some(tree_sitter_rust::language()),
+            )
+            .with_text_object_query(indoc! {r#"
+                (function_item body: (_) @function.inside) @function.around
+                (impl_item body: (_)? @class.inside) @class.around
+                (parameters (_) @parameter.inside)
+                (arguments (_) @parameter.inside)
+                (string_literal) @string.around
+            "#})
+            .unwrap(),
+        );
+        cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+
+        let text = indoc! {r#"
+            impl A {
+                fn b(&self, c: u8, d: &str) {
+                    e(c, "fˇoo");
+                }
+            }
+        "#};
+        cx.set_state(text);
+        cx.executor().run_until_parked();
+        cx.update_editor(|e, cx| e.select_string_contents(&SelectStringContents, cx));
+        cx.assert_editor_state(indoc! {r#"
+            impl A {
+                fn b(&self, c: u8, d: &str) {
+                    e(c, "«fooˇ»");
+                }
+            }
+        "#});
+
+        cx.set_state(text);
+        cx.update_editor(|e, cx| e.select_argument(&SelectArgument, cx));
+        cx.assert_editor_state(indoc! {r#"
+            impl A {
+                fn b(&self, c: u8, d: &str) {
+                    e(c, «"foo"ˇ»);
+                }
+            }
+        "#});
+
+        cx.set_state(text);
+        cx.update_editor(|e, cx| e.select_text_object(TextObject::Argument, true, cx));
+        cx.assert_editor_state(indoc! {r#"
+            impl A {
+                fn b(&self, c: u8, d: &str) {
+                    e(c«, "foo"ˇ»);
+                }
+            }
+        "#});
+
+        cx.set_state(text);
+        cx.update_editor(|e, cx| e.select_enclosing_function(&SelectEnclosingFunction, cx));
+        cx.assert_editor_state(indoc! {r#"
+            impl A {
+                «fn b(&self, c: u8, d: &str) {
+                    e(c, "foo");
+                }ˇ»
+            }
+        "#});
+
+        cx.set_state(text);
+        cx.update_editor(|e, cx| e.select_enclosing_type(&SelectEnclosingType, cx));
+        cx.assert_editor_state(indoc! {r#"
+            «impl A {
+                fn b(&self, c: u8, d: &str) {
+                    e(c, "foo");
+                }
+            }ˇ»
+        "#});
+
+        // Outside of any function, selections are left unchanged.
+        cx.set_state("ˇconst A: u8 = 1;");
+        cx.executor().run_until_parked();
+        cx.update_editor(|e, cx| e.select_enclosing_function(&SelectEnclosingFunction, cx));
+        cx.assert_editor_state("ˇconst A: u8 = 1;");
+    }
+}
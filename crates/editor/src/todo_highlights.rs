@@ -0,0 +1,257 @@
+use crate::{
+    scroll::Autoscroll, Direction, Editor, EditorMode, EditorSettings, GoToPrevTodo, GoToTodo,
+};
+use gpui::{FontWeight, HighlightStyle, ViewContext};
+use multi_buffer::MultiBufferSnapshot;
+use settings::Settings;
+use std::{ops::Range, time::Duration};
+use theme::{ActiveTheme, SyntaxTheme};
+
+const TODO_HIGHLIGHTS_DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(200);
+
+pub(crate) enum TodoHighlights {}
+
+impl Editor {
+    /// Highlights the configured TODO keywords that appear inside comments in the visible rows.
+    /// Only those rows are scanned, so that edits and scrolling don't rescan the whole buffer.
+    pub(crate) fn refresh_todo_highlights(&mut self, cx: &mut ViewContext<Self>) {
+        let keywords = EditorSettings::get_global(cx).todo_keywords.clone();
+        if self.mode != EditorMode::Full || self.large_file_mode || keywords.is_empty() {
+            self.todo_highlights_task = None;
+            self.clear_highlights::<TodoHighlights>(cx);
+            return;
+        }
+
+        let syntax_theme = cx.theme().syntax().clone();
+        let status = cx.theme().status();
+        let style = HighlightStyle {
+            color: Some(status.warning),
+            background_color: Some(status.warning_background),
+            font_weight: Some(FontWeight::BOLD),
+            ..Default::default()
+        };
+        self.todo_highlights_task = Some(cx.spawn(|this, mut cx| async move {
+            cx.background_executor()
+                .timer(TODO_HIGHLIGHTS_DEBOUNCE_TIMEOUT)
+                .await;
+            let Some((snapshot, visible_range)) = this
+                .update(&mut cx, |this, cx| {
+                    let display_snapshot = this.display_map.update(cx, |map, cx| map.snapshot(cx));
+                    let visible_range = this.visible_offset_range(&display_snapshot);
+                    (display_snapshot.buffer_snapshot, visible_range)
+                })
+                .ok()
+            else {
+                return;
+            };
+            let ranges = cx
+                .background_executor()
+                .spawn(async move {
+                    todo_ranges(&snapshot, visible_range, &keywords, &syntax_theme)
+                        .into_iter()
+                        .map(|range| {
+                            snapshot.anchor_after(range.start)..snapshot.anchor_before(range.end)
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .await;
+            this.update(&mut cx, |this, cx| {
+                if ranges.is_empty() {
+                    this.clear_highlights::<TodoHighlights>(cx);
+                } else {
+                    this.highlight_text::<TodoHighlights>(ranges, style, cx);
+                }
+            })
+            .ok();
+        }));
+    }
+
+    pub fn go_to_todo(&mut self, _: &GoToTodo, cx: &mut ViewContext<Self>) {
+        self.go_to_todo_in_direction(Direction::Next, cx);
+    }
+
+    pub fn go_to_prev_todo(&mut self, _: &GoToPrevTodo, cx: &mut ViewContext<Self>) {
+        self.go_to_todo_in_direction(Direction::Prev, cx);
+    }
+
+    fn go_to_todo_in_direction(&mut self, direction: Direction, cx: &mut ViewContext<Self>) {
+        // The highlights only cover the visible rows, so the whole buffer is scanned here.
+        let keywords = EditorSettings::get_global(cx).todo_keywords.clone();
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let todo_starts = todo_ranges(&snapshot, 0..snapshot.len(), &keywords, cx.theme().syntax())
+            .into_iter()
+            .map(|range| range.start)
+            .collect::<Vec<_>>();
+
+        let head = self.selections.newest::<usize>(cx).head();
+        let target = match direction {
+            Direction::Next => todo_starts
+                .iter()
+                .find(|start| **start > head)
+                .or(todo_starts.first()),
+            Direction::Prev => todo_starts
+                .iter()
+                .rev()
+                .find(|start| **start < head)
+                .or(todo_starts.last()),
+        };
+        if let Some(target) = target.copied() {
+            self.change_selections(Some(Autoscroll::fit()), cx, |s| {
+                s.select_ranges([target..target])
+            });
+        }
+    }
+}
+
+/// Returns the ranges of the given keywords that appear inside comments within the given range.
+fn todo_ranges(
+    snapshot: &MultiBufferSnapshot,
+    range: Range<usize>,
+    keywords: &[String],
+    syntax_theme: &SyntaxTheme,
+) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut comment_start = range.start;
+    let mut comment_text = String::new();
+    let mut offset = range.start;
+    for chunk in snapshot.chunks(range, true) {
+        let is_comment = chunk
+            .syntax_highlight_id
+            .and_then(|id| id.name(syntax_theme))
+            .map_or(false, |name| name.starts_with("comment"));
+        if is_comment {
+            if comment_text.is_empty() {
+                comment_start = offset;
+            }
+            comment_text.push_str(chunk.text);
+        } else if !comment_text.is_empty() {
+            ranges.extend(
+                find_keywords(&comment_text, keywords)
+                    .into_iter()
+                    .map(|range| comment_start + range.start..comment_start + range.end),
+            );
+            comment_text.clear();
+        }
+        offset += chunk.text.len();
+    }
+    ranges.extend(
+        find_keywords(&comment_text, keywords)
+            .into_iter()
+            .map(|range| comment_start + range.start..comment_start + range.end),
+    );
+    ranges
+}
+
+/// Finds the occurrences of the given keywords in `text` that are whole words.
+fn find_keywords(text: &str, keywords: &[String]) -> Vec<Range<usize>> {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    let mut ranges = keywords
+        .iter()
+        .filter(|keyword| !keyword.is_empty())
+        .flat_map(|keyword| {
+            text.match_indices(keyword.as_str())
+                .map(|(ix, keyword)| ix..ix + keyword.len())
+        })
+        .filter(|range| {
+            !text[..range.start]
+                .chars()
+                .next_back()
+                .map_or(false, is_word_char)
+                && !text[range.end..].chars().next().map_or(false, is_word_char)
+        })
+        .collect::<Vec<_>>();
+    ranges.sort_unstable_by_key(|range| range.start);
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+    use gpui::point;
+    use indoc::indoc;
+    use language::{Language, LanguageConfig};
+    use std::sync::Arc;
+
+    #[gpui::test]
+    async fn test_todo_highlights(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorTestContext::new(cx).await;
+        let language = Arc::new(
+            Language::new(
+                LanguageConfig {
+                    name: "Rust".into(),
+                    ..Default::default()
+                },
+                Some(tree_sitter_rust::language()),
+            )
+            .with_highlights_query("(line_comment) @comment (string_literal) @string")
+            .unwrap(),
+        );
+        cx.update(|cx| language.set_theme(cx.theme().syntax()));
+        cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+
+        // Keywords are only highlighted inside comments.
+        cx.set_state(indoc! {r#"
+            ˇ// TODO: one
+            let todo = "TODO";
+            // FIXME and TODO
+        "#});
+        cx.executor()
+            .advance_clock(TODO_HIGHLIGHTS_DEBOUNCE_TIMEOUT);
+        cx.run_until_parked();
+        cx.assert_editor_text_highlights::<TodoHighlights>(indoc! {r#"
+            // «TODO»: one
+            let todo = "TODO";
+            // «FIXME» and «TODO»
+        "#});
+
+        cx.update_editor(|editor, cx| editor.go_to_todo(&GoToTodo, cx));
+        cx.update_editor(|editor, cx| editor.go_to_todo(&GoToTodo, cx));
+        cx.assert_editor_state(indoc! {r#"
+            // TODO: one
+            let todo = "TODO";
+            // ˇFIXME and TODO
+        "#});
+        cx.update_editor(|editor, cx| editor.go_to_prev_todo(&GoToPrevTodo, cx));
+        cx.assert_editor_state(indoc! {r#"
+            // ˇTODO: one
+            let todo = "TODO";
+            // FIXME and TODO
+        "#});
+
+        // Only the visible rows are highlighted, while going to a TODO considers the whole buffer.
+        let filler = "let a = 1;\n".repeat(500);
+        cx.set_state(&format!("// TODO: topˇ\n{filler}// TODO: bottom\n"));
+        cx.executor()
+            .advance_clock(TODO_HIGHLIGHTS_DEBOUNCE_TIMEOUT);
+        cx.run_until_parked();
+        cx.assert_editor_text_highlights::<TodoHighlights>(&format!(
+            "// «TODO»: top\n{filler}// TODO: bottom\n"
+        ));
+        cx.update_editor(|editor, cx| editor.go_to_todo(&GoToTodo, cx));
+        cx.assert_editor_state(&format!("// TODO: top\n{filler}// ˇTODO: bottom\n"));
+        cx.update_editor(|editor, cx| editor.set_scroll_position(point(0., 480.), cx));
+        cx.executor()
+            .advance_clock(TODO_HIGHLIGHTS_DEBOUNCE_TIMEOUT);
+        cx.run_until_parked();
+        cx.assert_editor_text_highlights::<TodoHighlights>(&format!(
+            "// TODO: top\n{filler}// «TODO»: bottom\n"
+        ));
+    }
+
+    #[test]
+    fn test_find_keywords() {
+        let keywords = ["TODO".to_string(), "FIXME".to_string(), String::new()];
+        let ranges = |text| find_keywords(text, &keywords);
+
+        assert_eq!(ranges("// TODO: fix this"), vec![3..7]);
+        assert_eq!(ranges("/* FIXME(a), TODO */"), vec![3..8, 13..17]);
+        assert_eq!(
+            ranges("// TODOS and MY_TODO and todo"),
+            Vec::<Range<usize>>::new()
+        );
+        assert_eq!(ranges("//TODO"), vec![2..6]);
+    }
+}
@@ -31,7 +31,7 @@ use text::LineEnding;
 use util::ResultExt;
 
 #[cfg(any(test, feature = "test-support"))]
-use collections::{btree_map, BTreeMap};
+use collections::{btree_map, BTreeMap, HashSet};
 #[cfg(any(test, feature = "test-support"))]
 use repository::{FakeGitRepositoryState, GitFileStatus};
 #[cfg(any(test, feature = "test-support"))]
@@ -51,6 +51,8 @@ pub trait Fs: Send + Sync {
     async fn save(&self, path: &Path, text: &Rope, line_ending: LineEnding) -> Result<()>;
     async fn canonicalize(&self, path: &Path) -> Result<PathBuf>;
     async fn is_file(&self, path: &Path) -> bool;
+    /// Whether the current user may write to the file at the given path.
+    async fn is_writable(&self, path: &Path) -> bool;
     async fn metadata(&self, path: &Path) -> Result<Option<Metadata>>;
     async fn read_link(&self, path: &Path) -> Result<PathBuf>;
     async fn read_dir(
@@ -221,6 +223,11 @@ impl Fs for RealFs {
             .map_or(false, |metadata| metadata.is_file())
     }
 
+    async fn is_writable(&self, path: &Path) -> bool {
+        let path = path.to_owned();
+        smol::unblock(move || is_writable(&path)).await
+    }
+
     async fn metadata(&self, path: &Path) -> Result<Option<Metadata>> {
         let symlink_metadata = match smol::fs::symlink_metadata(path).await {
             Ok(metadata) => metadata,
@@ -405,6 +412,7 @@ struct FakeFsState {
     buffered_events: Vec<fsevent::Event>,
     metadata_call_count: usize,
     read_dir_call_count: usize,
+    read_only_paths: HashSet<PathBuf>,
 }
 
 #[cfg(any(test, feature = "test-support"))]
@@ -555,6 +563,7 @@ impl FakeFs {
                 events_paused: false,
                 read_dir_call_count: 0,
                 metadata_call_count: 0,
+                read_only_paths: Default::default(),
             }),
         })
     }
@@ -609,6 +618,17 @@ impl FakeFs {
         Ok(())
     }
 
+    /// Sets whether the given path is reported as one that can't be written to.
+    pub fn set_read_only(&self, path: impl AsRef<Path>, read_only: bool) {
+        let path = normalize_path(path.as_ref());
+        let mut state = self.state.lock();
+        if read_only {
+            state.read_only_paths.insert(path);
+        } else {
+            state.read_only_paths.remove(&path);
+        }
+    }
+
     pub fn pause_events(&self) {
         self.state.lock().events_paused = true;
     }
@@ -695,6 +715,17 @@ impl FakeFs {
         });
     }
 
+    pub fn set_blame_for_repo(&self, dot_git: &Path, blames: Vec<(&Path, git::blame::Blame)>) {
+        self.with_git_state(dot_git, true, |state| {
+            state.blames.clear();
+            state.blames.extend(
+                blames
+                    .into_iter()
+                    .map(|(path, blame)| (path.to_path_buf(), blame)),
+            );
+        });
+    }
+
     pub fn set_status_for_repo_via_working_copy_change(
         &self,
         dot_git: &Path,
@@ -1117,6 +1148,12 @@ impl Fs for FakeFs {
         }
     }
 
+    async fn is_writable(&self, path: &Path) -> bool {
+        self.simulate_random_delay().await;
+        let path = normalize_path(path);
+        !self.state.lock().read_only_paths.contains(&path)
+    }
+
     async fn metadata(&self, path: &Path) -> Result<Option<Metadata>> {
         self.simulate_random_delay().await;
         let path = normalize_path(path);
@@ -1250,6 +1287,23 @@ fn chunks(rope: &Rope, line_ending: LineEnding) -> impl Iterator<Item = &str> {
     })
 }
 
+/// Whether the current user may write to the given path. Unlike checking the permission bits
+/// of its metadata, this accounts for the file's owner and group, ACLs, and read-only mounts.
+#[cfg(unix)]
+fn is_writable(path: &Path) -> bool {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    unsafe { libc::faccessat(libc::AT_FDCWD, path.as_ptr(), libc::W_OK, libc::AT_EACCESS) == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_writable(path: &Path) -> bool {
+    std::fs::metadata(path).map_or(false, |metadata| !metadata.permissions().readonly())
+}
+
 pub fn normalize_path(path: &Path) -> PathBuf {
     let mut components = path.components().peekable();
     let mut ret = if let Some(c @ Component::Prefix(..)) = components.peek().cloned() {
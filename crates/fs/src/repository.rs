@@ -1,5 +1,6 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use collections::HashMap;
+use git::blame::Blame;
 use git2::{BranchType, StatusShow};
 use parking_lot::Mutex;
 use serde_derive::{Deserialize, Serialize};
@@ -53,6 +54,10 @@ pub trait GitRepository: Send {
     fn branches(&self) -> Result<Vec<Branch>>;
     fn change_branch(&self, _: &str) -> Result<()>;
     fn create_branch(&self, _: &str) -> Result<()>;
+
+    /// Returns the commits that last changed each line of the given content of the file at
+    /// `path`, relative to the working directory.
+    fn blame(&self, path: &Path, content: &str) -> Result<Blame>;
 }
 
 impl std::fmt::Debug for dyn GitRepository {
@@ -209,6 +214,11 @@ impl GitRepository for LibGitRepository {
 
         Ok(())
     }
+
+    fn blame(&self, path: &Path, content: &str) -> Result<Blame> {
+        check_path_to_repo_path_errors(path)?;
+        Blame::for_path(self, path, content)
+    }
 }
 
 fn matches_index(repo: &LibGitRepository, path: &RepoPath, mtime: SystemTime) -> bool {
@@ -253,6 +263,7 @@ pub struct FakeGitRepositoryState {
     pub index_contents: HashMap<PathBuf, String>,
     pub worktree_statuses: HashMap<RepoPath, GitFileStatus>,
     pub branch_name: Option<String>,
+    pub blames: HashMap<PathBuf, Blame>,
 }
 
 impl FakeGitRepository {
@@ -317,6 +328,15 @@ impl GitRepository for FakeGitRepository {
         state.branch_name = Some(name.to_owned());
         Ok(())
     }
+
+    fn blame(&self, path: &Path, _content: &str) -> Result<Blame> {
+        let state = self.state.lock();
+        state
+            .blames
+            .get(path)
+            .cloned()
+            .ok_or_else(|| anyhow!("no blame for {path:?}"))
+    }
 }
 
 fn check_path_to_repo_path_errors(relative_file_path: &Path) -> Result<()> {
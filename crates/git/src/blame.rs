@@ -0,0 +1,73 @@
+use anyhow::Result;
+use collections::HashMap;
+use std::{ops::Range, path::Path, sync::Arc};
+
+pub use git2 as libgit;
+use libgit::{BlameOptions as GitBlameOptions, Oid};
+
+/// The commits that last changed each line of a file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Blame {
+    pub entries: Vec<BlameEntry>,
+}
+
+/// A run of consecutive lines that were last changed by the same commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameEntry {
+    /// The zero-based rows of the blamed content covered by this entry.
+    pub range: Range<u32>,
+    /// The commit that last changed these lines, or `None` if they have not been committed yet.
+    pub commit: Option<Arc<CommitDetails>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitDetails {
+    pub sha: String,
+    pub author: String,
+    pub author_email: Option<String>,
+    /// The time the commit was authored, in seconds since the Unix epoch.
+    pub timestamp: i64,
+    pub summary: String,
+    pub message: String,
+}
+
+impl Blame {
+    /// Blames the given content of the file at `path`, relative to the repository's working
+    /// directory. Lines that differ from the committed version of the file are reported as not
+    /// committed yet.
+    pub fn for_path(repo: &libgit::Repository, path: &Path, content: &str) -> Result<Self> {
+        let mut options = GitBlameOptions::new();
+        options.track_copies_same_file(true);
+        let committed_blame = repo.blame_file(path, Some(&mut options))?;
+        let blame = committed_blame.blame_buffer(content.as_bytes())?;
+
+        let mut commits = HashMap::<Oid, Arc<CommitDetails>>::default();
+        let mut entries = Vec::new();
+        for hunk in blame.iter() {
+            let start = hunk.final_start_line().saturating_sub(1) as u32;
+            let range = start..start + hunk.lines_in_hunk() as u32;
+            let oid = hunk.final_commit_id();
+            let commit = if oid.is_zero() {
+                None
+            } else if let Some(commit) = commits.get(&oid) {
+                Some(commit.clone())
+            } else {
+                let commit = repo.find_commit(oid)?;
+                let author = commit.author();
+                let details = Arc::new(CommitDetails {
+                    sha: oid.to_string(),
+                    author: author.name().unwrap_or_default().to_string(),
+                    author_email: author.email().map(|email| email.to_string()),
+                    timestamp: author.when().seconds(),
+                    summary: commit.summary().unwrap_or_default().to_string(),
+                    message: commit.message().unwrap_or_default().trim_end().to_string(),
+                });
+                commits.insert(oid, details.clone());
+                Some(details)
+            };
+            entries.push(BlameEntry { range, commit });
+        }
+
+        Ok(Self { entries })
+    }
+}
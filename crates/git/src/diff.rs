@@ -275,6 +275,13 @@ impl BufferDiff {
     }
 }
 
+/// Returns the number of lines added and removed by changing `old_text` into `new_text`.
+pub fn line_changes(old_text: &str, new_text: &str) -> (usize, usize) {
+    BufferDiff::diff(old_text, new_text)
+        .and_then(|patch| patch.line_stats().ok())
+        .map_or((0, 0), |(_, additions, deletions)| (additions, deletions))
+}
+
 /// Range (crossing new lines), old, new
 #[cfg(any(test, feature = "test-support"))]
 #[track_caller]
@@ -317,6 +324,13 @@ mod tests {
     use text::{Buffer, BufferId};
     use unindent::Unindent as _;
 
+    #[test]
+    fn test_line_changes() {
+        assert_eq!(line_changes("one\ntwo\n", "one\ntwo\n"), (0, 0));
+        assert_eq!(line_changes("one\ntwo\n", "one\n2\nthree\n"), (2, 1));
+        assert_eq!(line_changes("one\ntwo\n", ""), (0, 2));
+    }
+
     #[test]
     fn test_buffer_diff_simple() {
         let diff_base = "
@@ -19,6 +19,7 @@ pub struct GoToLine {
     line_editor: View<Editor>,
     active_editor: View<Editor>,
     current_text: SharedString,
+    status_text: SharedString,
     prev_scroll_position: Option<gpui::Point<f32>>,
     _subscriptions: Vec<Subscription>,
 }
@@ -64,10 +65,12 @@ impl GoToLine {
             cursor.column + 1,
         );
 
+        let current_text: SharedString = current_text.into();
         Self {
             line_editor,
             active_editor,
-            current_text: current_text.into(),
+            status_text: current_text.clone(),
+            current_text,
             prev_scroll_position: Some(scroll_position),
             _subscriptions: vec![line_editor_change, cx.on_release(Self::release)],
         }
@@ -96,23 +99,48 @@ impl GoToLine {
     ) {
         match event {
             editor::EditorEvent::Blurred => cx.emit(DismissEvent),
-            editor::EditorEvent::BufferEdited { .. } => self.highlight_current_line(cx),
+            editor::EditorEvent::BufferEdited { .. } => self.preview_target(cx),
             _ => {}
         }
     }
 
-    fn highlight_current_line(&mut self, cx: &mut ViewContext<Self>) {
+    /// Scrolls the active editor to the line and column typed so far and highlights that line, or
+    /// scrolls it back to where it was when nothing valid has been typed.
+    fn preview_target(&mut self, cx: &mut ViewContext<Self>) {
+        let query = self.line_editor.read(cx).text(cx);
         if let Some(point) = self.point_from_query(cx) {
-            self.active_editor.update(cx, |active_editor, cx| {
+            let (point, last_line) = self.active_editor.update(cx, |active_editor, cx| {
                 let snapshot = active_editor.snapshot(cx).display_snapshot;
                 let point = snapshot.buffer_snapshot.clip_point(point, Bias::Left);
                 let display_point = point.to_display_point(&snapshot);
                 let row = display_point.row();
                 active_editor.highlight_rows(Some(row..row + 1));
                 active_editor.request_autoscroll(Autoscroll::center(), cx);
+                (point, snapshot.buffer_snapshot.max_point().row)
             });
-            cx.notify();
+            self.status_text = format!(
+                "Go to line {} of {}, column {}",
+                point.row + 1,
+                last_line + 1,
+                point.column + 1
+            )
+            .into();
+        } else {
+            let scroll_position = self.prev_scroll_position;
+            self.active_editor.update(cx, |active_editor, cx| {
+                active_editor.highlight_rows(None);
+                if let Some(scroll_position) = scroll_position {
+                    active_editor.set_scroll_position(scroll_position, cx);
+                }
+                cx.notify();
+            });
+            self.status_text = if query.trim().is_empty() {
+                self.current_text.clone()
+            } else {
+                "Type a line number, optionally followed by a column".into()
+            };
         }
+        cx.notify();
     }
 
     fn point_from_query(&self, cx: &ViewContext<Self>) -> Option<Point> {
@@ -181,8 +209,110 @@ impl Render for GoToLine {
                             .justify_between()
                             .px_2()
                             .py_1()
-                            .child(Label::new(self.current_text.clone()).color(Color::Muted)),
+                            .child(Label::new(self.status_text.clone()).color(Color::Muted)),
                     ),
             )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use editor::test::editor_test_context::EditorTestContext;
+    use gpui::{px, size, TestAppContext};
+    use project::Project;
+    use settings::SettingsStore;
+
+    #[gpui::test]
+    async fn test_go_to_line_preview(cx: &mut TestAppContext) {
+        init_test(cx);
+        let mut cx = EditorTestContext::new(cx).await;
+        let window = cx.window;
+        cx.simulate_window_resize(window, size(px(500.), px(200.)));
+        let text = (1..=100)
+            .map(|row| format!("line {row}\n"))
+            .collect::<String>();
+        cx.set_state(&format!("ˇ{text}"));
+
+        let editor = cx.editor.clone();
+        let go_to_line = cx.new_view(|cx| GoToLine::new(editor.clone(), cx));
+        let type_query = |query: &str, cx: &mut EditorTestContext| {
+            go_to_line.update(&mut cx.cx, |go_to_line, cx| {
+                go_to_line
+                    .line_editor
+                    .update(cx, |line_editor, cx| line_editor.set_text(query, cx))
+            });
+            cx.run_until_parked();
+        };
+        let status_text = |cx: &mut EditorTestContext| {
+            go_to_line.update(&mut cx.cx, |go_to_line, _| {
+                go_to_line.status_text.to_string()
+            })
+        };
+
+        // The target line is highlighted and scrolled into view while typing.
+        type_query("50:3", &mut cx);
+        cx.update_editor(|editor, cx| {
+            assert_eq!(editor.highlighted_rows(), Some(49..50));
+            let scroll_top = editor.scroll_position(cx).y;
+            assert!(scroll_top > 30. && scroll_top < 50., "{scroll_top}");
+        });
+        assert_eq!(status_text(&mut cx), "Go to line 50 of 101, column 3");
+
+        // Typing something that isn't a line scrolls the editor back to where it was.
+        type_query("abc", &mut cx);
+        cx.update_editor(|editor, cx| {
+            assert_eq!(editor.highlighted_rows(), None);
+            assert_eq!(editor.scroll_position(cx), gpui::Point::new(0., 0.));
+        });
+        assert_eq!(
+            status_text(&mut cx),
+            "Type a line number, optionally followed by a column"
+        );
+        type_query("", &mut cx);
+        assert_eq!(status_text(&mut cx), "line 1 of 101 (column 1)");
+
+        // Dismissing the modal without confirming restores the scroll position.
+        type_query("80", &mut cx);
+        cx.update_editor(|editor, cx| assert!(editor.scroll_position(cx).y > 0.));
+        drop(go_to_line);
+        cx.run_until_parked();
+        cx.update_editor(|editor, cx| {
+            assert_eq!(editor.highlighted_rows(), None);
+            assert_eq!(editor.scroll_position(cx), gpui::Point::new(0., 0.));
+        });
+
+        // Confirming moves the cursor to the target and keeps it scrolled into view.
+        let go_to_line = cx.new_view(|cx| GoToLine::new(editor.clone(), cx));
+        go_to_line.update(&mut cx.cx, |go_to_line, cx| {
+            go_to_line
+                .line_editor
+                .update(cx, |line_editor, cx| line_editor.set_text("80:4", cx));
+            go_to_line.confirm(&menu::Confirm, cx);
+        });
+        drop(go_to_line);
+        cx.run_until_parked();
+        cx.update_editor(|editor, cx| {
+            assert_eq!(editor.highlighted_rows(), None);
+            assert_eq!(
+                editor.selections.newest::<Point>(cx).head(),
+                Point::new(79, 3)
+            );
+            assert!(editor.scroll_position(cx).y > 0.);
+        });
+    }
+
+    fn init_test(cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            let store = SettingsStore::test(cx);
+            cx.set_global(store);
+            theme::init(theme::LoadThemes::JustBase, cx);
+            release_channel::init("0.0.0", cx);
+            client::init_settings(cx);
+            language::init(cx);
+            Project::init_settings(cx);
+            workspace::init_settings(cx);
+            editor::init(cx);
+        });
+    }
+}
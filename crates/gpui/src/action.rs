@@ -183,7 +183,7 @@ macro_rules! actions {
     ($namespace:path, [ $($name:ident),* $(,)? ]) => {
         $(
             /// The `$name` action see [`gpui::actions!`]
-            #[derive(::std::cmp::PartialEq, ::std::clone::Clone, ::std::default::Default, ::std::fmt::Debug, gpui::private::serde_derive::Deserialize)]
+            #[derive(::std::cmp::PartialEq, ::std::clone::Clone, ::std::default::Default, ::std::fmt::Debug, gpui::private::serde_derive::Deserialize, gpui::private::serde_derive::Serialize)]
             #[serde(crate = "gpui::private::serde")]
             pub struct $name;
 
@@ -32,10 +32,11 @@ use crate::WindowAppearance;
 use crate::{
     current_platform, image_cache::ImageCache, init_app_menus, Action, ActionRegistry, Any,
     AnyView, AnyWindowHandle, AppMetadata, AssetSource, BackgroundExecutor, ClipboardItem, Context,
-    DispatchPhase, Entity, EventEmitter, ForegroundExecutor, Global, KeyBinding, Keymap, Keystroke,
-    LayoutId, Menu, PathPromptOptions, Pixels, Platform, PlatformDisplay, Point, Render,
-    SharedString, SubscriberSet, Subscription, SvgRenderer, Task, TextStyle, TextStyleRefinement,
-    TextSystem, View, ViewContext, Window, WindowContext, WindowHandle, WindowId,
+    DispatchPhase, Entity, EventEmitter, ForegroundExecutor, Global, ImageData, KeyBinding, Keymap,
+    Keystroke, LayoutId, Menu, PathPromptOptions, Pixels, Platform, PlatformDisplay, Point,
+    PrintDocument, Render, SharedString, Size, SubscriberSet, Subscription, SvgRenderer, Task,
+    TextStyle, TextStyleRefinement, TextSystem, View, ViewContext, Window, WindowContext,
+    WindowHandle, WindowId,
 };
 
 mod async_context;
@@ -537,6 +538,17 @@ impl AppContext {
         self.platform.read_from_clipboard()
     }
 
+    /// Writes data to the primary selection, on platforms that have one. On Linux, it's only
+    /// shared between the application's windows for now.
+    pub fn write_to_primary(&self, item: ClipboardItem) {
+        self.platform.write_to_primary(item)
+    }
+
+    /// Reads data from the primary selection, on platforms that have one.
+    pub fn read_from_primary(&self) -> Option<ClipboardItem> {
+        self.platform.read_from_primary()
+    }
+
     /// Writes credentials to the platform keychain.
     pub fn write_credentials(
         &self,
@@ -557,6 +569,15 @@ impl AppContext {
         self.platform.delete_credentials(url)
     }
 
+    /// Decodes the given encoded image, such as the contents of a PNG file, in the background.
+    pub fn decode_image(&self, bytes: Vec<u8>) -> Task<Result<Arc<ImageData>>> {
+        self.background_executor().spawn(async move {
+            let format = image::guess_format(&bytes)?;
+            let image = image::load_from_memory_with_format(&bytes, format)?.into_bgra8();
+            Ok(Arc::new(ImageData::new(image)))
+        })
+    }
+
     /// Directs the platform's default browser to open the given URL.
     pub fn open_url(&self, url: &str) {
         self.platform.open_url(url);
@@ -601,6 +622,17 @@ impl AppContext {
         self.platform.reveal_path(path)
     }
 
+    /// Sends the given document to the platform's print system, showing the print dialog.
+    pub fn print_document(&self, document: PrintDocument) {
+        self.platform.print_document(document)
+    }
+
+    /// Returns the size, in points, of the area within the margins of the pages the platform prints
+    /// on by default, or `None` if the platform doesn't report it.
+    pub fn printable_page_size(&self) -> Option<Size<Pixels>> {
+        self.platform.printable_page_size()
+    }
+
     /// Returns whether the user has configured scrollbars to auto-hide at the platform level.
     pub fn should_auto_hide_scrollbars(&self) -> bool {
         self.platform.should_auto_hide_scrollbars()
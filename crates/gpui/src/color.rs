@@ -1,5 +1,8 @@
 use anyhow::bail;
-use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::{
+    de::{self, Deserialize, Deserializer, Visitor},
+    Serialize, Serializer,
+};
 use std::fmt;
 
 /// Convert an RGB hex color code number to a color type
@@ -86,6 +89,12 @@ impl<'de> Deserialize<'de> for Rgba {
     }
 }
 
+impl Serialize for Rgba {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("#{:08x}", u32::from(*self)))
+    }
+}
+
 impl From<Hsla> for Rgba {
     fn from(color: Hsla) -> Self {
         let h = color.h;
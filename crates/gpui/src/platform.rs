@@ -95,6 +95,8 @@ pub(crate) trait Platform: 'static {
     ) -> oneshot::Receiver<Option<Vec<PathBuf>>>;
     fn prompt_for_new_path(&self, directory: &Path) -> oneshot::Receiver<Option<PathBuf>>;
     fn reveal_path(&self, path: &Path);
+    fn print_document(&self, document: PrintDocument);
+    fn printable_page_size(&self) -> Option<Size<Pixels>>;
 
     fn on_become_active(&self, callback: Box<dyn FnMut()>);
     fn on_resign_active(&self, callback: Box<dyn FnMut()>);
@@ -120,6 +122,8 @@ pub(crate) trait Platform: 'static {
 
     fn write_to_clipboard(&self, item: ClipboardItem);
     fn read_from_clipboard(&self) -> Option<ClipboardItem>;
+    fn write_to_primary(&self, item: ClipboardItem);
+    fn read_from_primary(&self) -> Option<ClipboardItem>;
 
     fn write_credentials(&self, url: &str, username: &str, password: &[u8]) -> Task<Result<()>>;
     fn read_credentials(&self, url: &str) -> Task<Result<Option<(String, Vec<u8>)>>>;
@@ -616,6 +620,32 @@ pub struct PathPromptOptions {
     pub multiple: bool,
 }
 
+/// A paginated plain-text document to hand to the platform's print system
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PrintDocument {
+    /// The title of the print job
+    pub title: String,
+    /// The font family the pages should be rendered with
+    pub font_family: SharedString,
+    /// The font size, in points
+    pub font_size: f32,
+    /// The height of each line, in points
+    pub line_height: f32,
+    /// The pages of the document, in order
+    pub pages: Vec<PrintPage>,
+}
+
+/// A single page of a [`PrintDocument`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PrintPage {
+    /// The text shown at the top of the page
+    pub header: String,
+    /// The lines of the page body, already wrapped to the page width
+    pub lines: Vec<String>,
+    /// The text shown at the bottom of the page
+    pub footer: String,
+}
+
 /// What kind of prompt styling to show
 #[derive(Copy, Clone, Debug)]
 pub enum PromptLevel {
@@ -715,6 +745,8 @@ impl Default for CursorStyle {
 pub struct ClipboardItem {
     pub(crate) text: String,
     pub(crate) metadata: Option<String>,
+    pub(crate) rtf: Option<String>,
+    pub(crate) html: Option<String>,
 }
 
 impl ClipboardItem {
@@ -723,6 +755,8 @@ impl ClipboardItem {
         Self {
             text,
             metadata: None,
+            rtf: None,
+            html: None,
         }
     }
 
@@ -732,6 +766,18 @@ impl ClipboardItem {
         self
     }
 
+    /// Attach an RTF representation of the text to the clipboard item
+    pub fn with_rtf(mut self, rtf: String) -> Self {
+        self.rtf = Some(rtf);
+        self
+    }
+
+    /// Attach an HTML representation of the text to the clipboard item
+    pub fn with_html(mut self, html: String) -> Self {
+        self.html = Some(html);
+        self
+    }
+
     /// Get the text of the clipboard item
     pub fn text(&self) -> &String {
         &self.text
@@ -747,6 +793,16 @@ impl ClipboardItem {
             .and_then(|m| serde_json::from_str(m).ok())
     }
 
+    /// Get the RTF representation of the clipboard item, if any
+    pub fn rtf(&self) -> Option<&str> {
+        self.rtf.as_deref()
+    }
+
+    /// Get the HTML representation of the clipboard item, if any
+    pub fn html(&self) -> Option<&str> {
+        self.html.as_deref()
+    }
+
     pub(crate) fn text_hash(text: &str) -> u64 {
         let mut hasher = SeaHasher::new();
         text.hash(&mut hasher);
@@ -22,9 +22,9 @@ use crate::platform::linux::wayland::{WaylandClient, WaylandClientDispatcher};
 use crate::platform::{X11Client, X11ClientDispatcher, XcbAtoms};
 use crate::{
     Action, AnyWindowHandle, BackgroundExecutor, ClipboardItem, CursorStyle, DisplayId,
-    ForegroundExecutor, Keymap, LinuxDispatcher, LinuxTextSystem, Menu, PathPromptOptions,
-    Platform, PlatformDisplay, PlatformInput, PlatformTextSystem, PlatformWindow, Result,
-    SemanticVersion, Task, WindowOptions,
+    ForegroundExecutor, Keymap, LinuxDispatcher, LinuxTextSystem, Menu, PathPromptOptions, Pixels,
+    Platform, PlatformDisplay, PlatformInput, PlatformTextSystem, PlatformWindow, PrintDocument,
+    Result, SemanticVersion, Size, Task, WindowOptions,
 };
 
 #[derive(Default)]
@@ -56,6 +56,7 @@ pub(crate) struct LinuxPlatform {
 
 pub(crate) struct LinuxPlatformState {
     pub(crate) quit_requested: bool,
+    pub(crate) primary_selection: Option<ClipboardItem>,
 }
 
 impl Default for LinuxPlatform {
@@ -74,6 +75,7 @@ impl LinuxPlatform {
         let callbacks = Mutex::new(Callbacks::default());
         let state = Mutex::new(LinuxPlatformState {
             quit_requested: false,
+            primary_selection: None,
         });
 
         if use_wayland {
@@ -297,6 +299,19 @@ impl Platform for LinuxPlatform {
         open::that(path);
     }
 
+    //todo!(linux)
+    fn print_document(&self, document: PrintDocument) {
+        log::warn!(
+            "Printing isn't supported on Linux yet, {:?} wasn't printed",
+            document.title
+        );
+    }
+
+    //todo!(linux)
+    fn printable_page_size(&self) -> Option<Size<Pixels>> {
+        None
+    }
+
     fn on_become_active(&self, callback: Box<dyn FnMut()>) {
         self.inner.callbacks.lock().become_active = Some(callback);
     }
@@ -384,6 +399,16 @@ impl Platform for LinuxPlatform {
         None
     }
 
+    //todo!(linux): read and write the X11 and Wayland primary selections, which this only
+    // stands in for, so that it's shared with other applications and not just Zed's windows.
+    fn write_to_primary(&self, item: ClipboardItem) {
+        self.inner.state.lock().primary_selection = Some(item);
+    }
+
+    fn read_from_primary(&self) -> Option<ClipboardItem> {
+        self.inner.state.lock().primary_selection.clone()
+    }
+
     fn write_credentials(&self, url: &str, username: &str, password: &[u8]) -> Task<Result<()>> {
         unimplemented!()
     }
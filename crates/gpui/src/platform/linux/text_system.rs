@@ -364,7 +364,11 @@ impl LinuxTextSystemState {
                 index: glyph.start,
                 is_emoji: self.is_emoji(font_id),
             });
-            runs.push(crate::ShapedRun { font_id, glyphs });
+            runs.push(crate::ShapedRun {
+                font_id,
+                glyphs,
+                is_rtl: glyph.level.is_rtl(),
+            });
         }
         LineLayout {
             font_size,
@@ -373,6 +377,7 @@ impl LinuxTextSystemState {
             descent: layout.max_descent.into(),
             runs,
             len: text.len(),
+            glyph_extents: Default::default(),
         }
     }
 }
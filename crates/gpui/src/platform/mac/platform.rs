@@ -1,9 +1,10 @@
-use super::{events::key_to_native, BoolExt};
+use super::{events::key_to_native, BoolExt, NSRange};
 use crate::{
-    Action, AnyWindowHandle, BackgroundExecutor, ClipboardItem, CursorStyle, DisplayId,
+    px, Action, AnyWindowHandle, BackgroundExecutor, ClipboardItem, CursorStyle, DisplayId,
     ForegroundExecutor, Keymap, MacDispatcher, MacDisplay, MacTextSystem, MacWindow, Menu,
-    MenuItem, PathPromptOptions, Platform, PlatformDisplay, PlatformInput, PlatformTextSystem,
-    PlatformWindow, Result, SemanticVersion, Task, WindowAppearance, WindowOptions,
+    MenuItem, PathPromptOptions, Pixels, Platform, PlatformDisplay, PlatformInput,
+    PlatformTextSystem, PlatformWindow, PrintDocument, Result, SemanticVersion, Size, Task,
+    WindowAppearance, WindowOptions,
 };
 use anyhow::anyhow;
 use block::ConcreteBlock;
@@ -11,12 +12,12 @@ use cocoa::{
     appkit::{
         NSApplication, NSApplicationActivationPolicy::NSApplicationActivationPolicyRegular,
         NSEventModifierFlags, NSMenu, NSMenuItem, NSModalResponse, NSOpenPanel, NSPasteboard,
-        NSPasteboardTypeString, NSSavePanel, NSWindow,
+        NSPasteboardTypeHTML, NSPasteboardTypeRTF, NSPasteboardTypeString, NSSavePanel, NSWindow,
     },
     base::{id, nil, selector, BOOL, YES},
     foundation::{
-        NSArray, NSAutoreleasePool, NSBundle, NSData, NSInteger, NSProcessInfo, NSString,
-        NSUInteger, NSURL,
+        NSArray, NSAutoreleasePool, NSBundle, NSData, NSInteger, NSPoint, NSProcessInfo, NSRect,
+        NSSize, NSString, NSUInteger, NSURL,
     },
 };
 use core_foundation::{
@@ -623,6 +624,89 @@ impl Platform for MacPlatform {
         }
     }
 
+    fn print_document(&self, document: PrintDocument) {
+        // Pages are separated by form feeds, which the text system treats as page breaks.
+        let mut text = String::new();
+        for (ix, page) in document.pages.iter().enumerate() {
+            if ix > 0 {
+                text.push('\u{c}');
+            }
+            text.push_str(&page.header);
+            text.push_str("\n\n");
+            for line in &page.lines {
+                text.push_str(line);
+                text.push('\n');
+            }
+            text.push('\n');
+            text.push_str(&page.footer);
+        }
+
+        self.foreground_executor()
+            .spawn(async move {
+                unsafe {
+                    let print_info: id = msg_send![class!(NSPrintInfo), sharedPrintInfo];
+                    let paper_size: NSSize = msg_send![print_info, paperSize];
+                    let text_view: id = msg_send![class!(NSTextView), alloc];
+                    let text_view: id = msg_send![
+                        text_view,
+                        initWithFrame: NSRect::new(NSPoint::new(0., 0.), paper_size)
+                    ];
+                    let _: () = msg_send![text_view, setString: ns_string(&text)];
+
+                    let font_size = document.font_size as f64;
+                    let mut font: id = msg_send![
+                        class!(NSFont),
+                        fontWithName: ns_string(&document.font_family)
+                        size: font_size
+                    ];
+                    if font == nil {
+                        font = msg_send![class!(NSFont), userFixedPitchFontOfSize: font_size];
+                    }
+                    let _: () = msg_send![text_view, setFont: font];
+
+                    // Lay out lines at the height the pages were split with.
+                    let line_height = document.line_height as f64;
+                    let paragraph_style: id = msg_send![class!(NSMutableParagraphStyle), new];
+                    let _: () = msg_send![paragraph_style, setMinimumLineHeight: line_height];
+                    let _: () = msg_send![paragraph_style, setMaximumLineHeight: line_height];
+                    let text_storage: id = msg_send![text_view, textStorage];
+                    let length: NSUInteger = msg_send![text_storage, length];
+                    let _: () = msg_send![
+                        text_storage,
+                        addAttribute: ns_string("NSParagraphStyle")
+                        value: paragraph_style
+                        range: NSRange::from(0..length as usize)
+                    ];
+                    let _: () = msg_send![paragraph_style, release];
+
+                    let operation: id = msg_send![
+                        class!(NSPrintOperation),
+                        printOperationWithView: text_view
+                        printInfo: print_info
+                    ];
+                    let _: () = msg_send![operation, setJobTitle: ns_string(&document.title)];
+                    let _: BOOL = msg_send![operation, runOperation];
+                    let _: () = msg_send![text_view, release];
+                }
+            })
+            .detach();
+    }
+
+    fn printable_page_size(&self) -> Option<Size<Pixels>> {
+        unsafe {
+            let print_info: id = msg_send![class!(NSPrintInfo), sharedPrintInfo];
+            let paper_size: NSSize = msg_send![print_info, paperSize];
+            let left_margin: f64 = msg_send![print_info, leftMargin];
+            let right_margin: f64 = msg_send![print_info, rightMargin];
+            let top_margin: f64 = msg_send![print_info, topMargin];
+            let bottom_margin: f64 = msg_send![print_info, bottomMargin];
+            Some(Size {
+                width: px((paper_size.width - left_margin - right_margin) as f32),
+                height: px((paper_size.height - top_margin - bottom_margin) as f32),
+            })
+        }
+    }
+
     fn on_become_active(&self, callback: Box<dyn FnMut()>) {
         self.0.lock().become_active = Some(callback);
     }
@@ -800,6 +884,28 @@ impl Platform for MacPlatform {
                 .pasteboard
                 .setData_forType(text_bytes, NSPasteboardTypeString);
 
+            if let Some(rtf) = item.rtf.as_ref() {
+                let rtf_bytes = NSData::dataWithBytes_length_(
+                    nil,
+                    rtf.as_ptr() as *const c_void,
+                    rtf.len() as u64,
+                );
+                state
+                    .pasteboard
+                    .setData_forType(rtf_bytes, NSPasteboardTypeRTF);
+            }
+
+            if let Some(html) = item.html.as_ref() {
+                let html_bytes = NSData::dataWithBytes_length_(
+                    nil,
+                    html.as_ptr() as *const c_void,
+                    html.len() as u64,
+                );
+                state
+                    .pasteboard
+                    .setData_forType(html_bytes, NSPasteboardTypeHTML);
+            }
+
             if let Some(metadata) = item.metadata.as_ref() {
                 let hash_bytes = ClipboardItem::text_hash(&item.text).to_be_bytes();
                 let hash_bytes = NSData::dataWithBytes_length_(
@@ -843,18 +949,14 @@ impl Platform for MacPlatform {
                         Some(ClipboardItem {
                             text,
                             metadata: Some(metadata),
+                            rtf: None,
+                            html: None,
                         })
                     } else {
-                        Some(ClipboardItem {
-                            text,
-                            metadata: None,
-                        })
+                        Some(ClipboardItem::new(text))
                     }
                 } else {
-                    Some(ClipboardItem {
-                        text,
-                        metadata: None,
-                    })
+                    Some(ClipboardItem::new(text))
                 }
             } else {
                 None
@@ -862,6 +964,13 @@ impl Platform for MacPlatform {
         }
     }
 
+    // macOS has no primary selection.
+    fn write_to_primary(&self, _item: ClipboardItem) {}
+
+    fn read_from_primary(&self) -> Option<ClipboardItem> {
+        None
+    }
+
     fn write_credentials(&self, url: &str, username: &str, password: &[u8]) -> Task<Result<()>> {
         let url = url.to_string();
         let username = username.to_string();
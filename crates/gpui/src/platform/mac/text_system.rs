@@ -24,6 +24,7 @@ use core_text::{
         kCTFontSlantTrait, kCTFontSymbolicTrait, kCTFontWeightTrait, kCTFontWidthTrait,
     },
     line::CTLine,
+    run::CTRunRef,
     string_attributes::kCTFontAttributeName,
 };
 use font_kit::{
@@ -475,7 +476,13 @@ impl MacTextSystemState {
                 });
             }
 
-            runs.push(ShapedRun { font_id, glyphs })
+            let is_rtl =
+                unsafe { CTRunGetStatus(run.as_concrete_TypeRef()) } & kCTRunStatusRightToLeft != 0;
+            runs.push(ShapedRun {
+                font_id,
+                glyphs,
+                is_rtl,
+            })
         }
 
         let typographic_bounds = line.get_typographic_bounds();
@@ -486,6 +493,7 @@ impl MacTextSystemState {
             ascent: typographic_bounds.ascent.into(),
             descent: typographic_bounds.descent.into(),
             len: text.len(),
+            glyph_extents: Default::default(),
         }
     }
 
@@ -571,8 +579,13 @@ pub(crate) struct __CFTypesetter(c_void);
 
 type CTTypesetterRef = *const __CFTypesetter;
 
+#[allow(non_upper_case_globals)]
+const kCTRunStatusRightToLeft: u32 = 1 << 0;
+
 #[link(name = "CoreText", kind = "framework")]
 extern "C" {
+    fn CTRunGetStatus(run: CTRunRef) -> u32;
+
     fn CTTypesetterCreateWithAttributedString(string: CFAttributedStringRef) -> CTTypesetterRef;
 
     fn CTTypesetterSuggestLineBreak(
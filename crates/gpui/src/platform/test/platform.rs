@@ -1,7 +1,7 @@
 use crate::{
     AnyWindowHandle, BackgroundExecutor, ClipboardItem, CursorStyle, DisplayId, ForegroundExecutor,
-    Keymap, Platform, PlatformDisplay, PlatformTextSystem, Task, TestDisplay, TestWindow,
-    WindowAppearance, WindowOptions,
+    Keymap, Pixels, Platform, PlatformDisplay, PlatformTextSystem, PrintDocument, Size, Task,
+    TestDisplay, TestWindow, WindowAppearance, WindowOptions,
 };
 use anyhow::{anyhow, Result};
 use collections::VecDeque;
@@ -24,8 +24,10 @@ pub(crate) struct TestPlatform {
     active_display: Rc<dyn PlatformDisplay>,
     active_cursor: Mutex<CursorStyle>,
     current_clipboard_item: Mutex<Option<ClipboardItem>>,
+    current_primary_item: Mutex<Option<ClipboardItem>>,
     pub(crate) prompts: RefCell<TestPrompts>,
     pub opened_url: RefCell<Option<String>>,
+    pub printed_documents: RefCell<Vec<PrintDocument>>,
     weak: Weak<Self>,
 }
 
@@ -45,8 +47,10 @@ impl TestPlatform {
             active_display: Rc::new(TestDisplay::new()),
             active_window: Default::default(),
             current_clipboard_item: Mutex::new(None),
+            current_primary_item: Mutex::new(None),
             weak: weak.clone(),
             opened_url: Default::default(),
+            printed_documents: Default::default(),
         })
     }
 
@@ -217,6 +221,14 @@ impl Platform for TestPlatform {
         unimplemented!()
     }
 
+    fn print_document(&self, document: PrintDocument) {
+        self.printed_documents.borrow_mut().push(document);
+    }
+
+    fn printable_page_size(&self) -> Option<Size<Pixels>> {
+        None
+    }
+
     fn on_become_active(&self, _callback: Box<dyn FnMut()>) {}
 
     fn on_resign_active(&self, _callback: Box<dyn FnMut()>) {}
@@ -279,6 +291,14 @@ impl Platform for TestPlatform {
         self.current_clipboard_item.lock().clone()
     }
 
+    fn write_to_primary(&self, item: ClipboardItem) {
+        *self.current_primary_item.lock() = Some(item);
+    }
+
+    fn read_from_primary(&self) -> Option<ClipboardItem> {
+        self.current_primary_item.lock().clone()
+    }
+
     fn write_credentials(&self, _url: &str, _username: &str, _password: &[u8]) -> Task<Result<()>> {
         Task::ready(Ok(()))
     }
@@ -44,3 +44,20 @@ impl SvgRenderer {
         Ok(alpha_mask)
     }
 }
+
+/// Rasterizes an SVG document into PNG-encoded bytes, scaling it by the given factor.
+/// Text in the document is rendered with the fonts installed on the system.
+pub fn render_svg_to_png(svg: &str, scale: f32) -> Result<Vec<u8>> {
+    let mut options = usvg::Options::default();
+    options.fontdb.load_system_fonts();
+    let tree = usvg::Tree::from_str(svg, &options)?;
+
+    let size = tree.svg_node().size;
+    let width = (size.width() * scale as f64).ceil() as u32;
+    let height = (size.height() * scale as f64).ceil() as u32;
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| anyhow!("can't render at a zero size"))?;
+    resvg::render(&tree, usvg::FitTo::Width(width), pixmap.as_mut())
+        .ok_or_else(|| anyhow!("failed to render svg"))?;
+    Ok(pixmap.encode_png()?)
+}
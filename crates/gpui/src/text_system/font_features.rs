@@ -13,13 +13,27 @@ macro_rules! create_definitions {
             disabled: u64,
         }
 
+        /// The bit storing each feature in the masks of a [`FontFeatures`].
+        #[allow(non_camel_case_types)]
+        #[derive(Copy, Clone)]
+        enum FeatureBit {
+            $($name = $idx,)*
+        }
+
+        impl FeatureBit {
+            fn mask(self) -> u64 {
+                1 << self as u64
+            }
+        }
+
         impl FontFeatures {
             $(
                 /// Get the current value of the corresponding OpenType feature
                 pub fn $name(&self) -> Option<bool> {
-                    if (self.enabled & (1 << $idx)) != 0 {
+                    let mask = FeatureBit::$name.mask();
+                    if (self.enabled & mask) != 0 {
                         Some(true)
-                    } else if (self.disabled & (1 << $idx)) != 0 {
+                    } else if (self.disabled & mask) != 0 {
                         Some(false)
                     } else {
                         None
@@ -163,3 +177,24 @@ create_definitions!(
     (tnum, 32),
     (zero, 33)
 );
+
+impl FontFeatures {
+    /// Enable or disable the contextual alternates and standard ligatures
+    /// (the `calt` and `liga` features), which fonts use to join characters
+    /// such as `->` into a single glyph.
+    pub fn set_ligatures(&mut self, enabled: bool) {
+        let mask = FeatureBit::calt.mask() | FeatureBit::liga.mask();
+        if enabled {
+            self.enabled |= mask;
+            self.disabled &= !mask;
+        } else {
+            self.enabled &= !mask;
+            self.disabled |= mask;
+        }
+    }
+
+    /// Whether ligatures are shown, unless the font doesn't have any.
+    pub fn ligatures(&self) -> bool {
+        self.calt() != Some(false) && self.liga() != Some(false)
+    }
+}
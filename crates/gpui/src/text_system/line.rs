@@ -43,6 +43,11 @@ impl ShapedLine {
         self.layout.len
     }
 
+    /// The runs of text the line is decorated with, in the order they appear in the line.
+    pub fn decoration_runs(&self) -> &[DecorationRun] {
+        &self.decoration_runs
+    }
+
     /// Paint the line of text to the window.
     pub fn paint(
         &self,
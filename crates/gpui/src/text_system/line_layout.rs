@@ -5,7 +5,8 @@ use smallvec::SmallVec;
 use std::{
     borrow::Borrow,
     hash::{Hash, Hasher},
-    sync::Arc,
+    ops::Range,
+    sync::{Arc, OnceLock},
 };
 
 /// A laid out and styled line of text
@@ -23,6 +24,8 @@ pub struct LineLayout {
     pub runs: Vec<ShapedRun>,
     /// The length of the line in utf-8 bytes
     pub len: usize,
+    /// The horizontal extents of the glyphs, computed the first time the line is hit tested.
+    pub(crate) glyph_extents: OnceLock<GlyphExtents>,
 }
 
 /// A run of text that has been shaped .
@@ -32,6 +35,28 @@ pub struct ShapedRun {
     pub font_id: FontId,
     /// The glyphs that make up this run
     pub glyphs: SmallVec<[ShapedGlyph; 8]>,
+    /// Whether the text of this run is laid out from right to left, as resolved by the
+    /// bidirectional algorithm while shaping
+    pub is_rtl: bool,
+}
+
+/// The horizontal extent of a glyph within a line, used for hit testing.
+#[derive(Clone, Debug)]
+struct GlyphExtent {
+    index: usize,
+    start_x: Pixels,
+    end_x: Pixels,
+    is_rtl: bool,
+}
+
+/// The extents of every glyph in a line, so that glyphs can be looked up both by position and
+/// by character index with a binary search.
+#[derive(Default, Debug)]
+pub(crate) struct GlyphExtents {
+    /// The extents, sorted by their position from left to right.
+    by_x: Vec<GlyphExtent>,
+    /// Indices into `by_x`, sorted by the glyphs' character indices.
+    by_index: Vec<usize>,
 }
 
 /// A single glyph, ready to paint.
@@ -56,50 +81,112 @@ impl LineLayout {
         if x >= self.width {
             None
         } else {
-            for run in self.runs.iter().rev() {
-                for glyph in run.glyphs.iter().rev() {
-                    if glyph.position.x <= x {
-                        return Some(glyph.index);
-                    }
-                }
-            }
-            Some(0)
+            let extents = &self.glyph_extents().by_x;
+            let ix = extents
+                .partition_point(|extent| extent.start_x <= x)
+                .saturating_sub(1);
+            Some(extents.get(ix).map_or(0, |extent| extent.index))
         }
     }
 
     /// closest_index_for_x returns the character boundary closest to the given x coordinate
     /// (e.g. to handle aligning up/down arrow keys)
     pub fn closest_index_for_x(&self, x: Pixels) -> usize {
-        let mut prev_index = 0;
-        let mut prev_x = px(0.);
-
-        for run in self.runs.iter() {
-            for glyph in run.glyphs.iter() {
-                if glyph.position.x >= x {
-                    if glyph.position.x - x < x - prev_x {
-                        return glyph.index;
-                    } else {
-                        return prev_index;
-                    }
-                }
-                prev_index = glyph.index;
-                prev_x = glyph.position.x;
-            }
-        }
+        let extents = self.glyph_extents();
+        let ix = extents.by_x.partition_point(|extent| extent.start_x <= x);
 
-        self.len
+        // The closest boundary is at an edge of one of the glyphs around x. Each glyph's edges
+        // are the carets of its own character and of the character that follows it.
+        let neighbors = &extents.by_x[ix.saturating_sub(2)..(ix + 1).min(extents.by_x.len())];
+        neighbors
+            .iter()
+            .flat_map(|extent| [extent.index, self.next_index(extents, extent.index)])
+            .chain(Some(self.len))
+            .min_by_key(|index| (self.caret_x(extents, *index) - x).abs())
+            .unwrap_or(self.len)
     }
 
     /// The x position of the character at the given index
+    ///
+    /// For characters in right-to-left runs, this is the right edge of the glyph,
+    /// which is where a caret placed before the character is drawn.
     pub fn x_for_index(&self, index: usize) -> Pixels {
-        for run in &self.runs {
-            for glyph in &run.glyphs {
-                if glyph.index >= index {
-                    return glyph.position.x;
+        self.caret_x(self.glyph_extents(), index)
+    }
+
+    /// The horizontal span covered by the characters in the given range.
+    ///
+    /// Unlike subtracting two calls to [`Self::x_for_index`], this is correct for
+    /// ranges that cover right-to-left or mixed-direction text.
+    pub fn x_range_for_index_range(&self, range: Range<usize>) -> Range<Pixels> {
+        let extents = self.glyph_extents();
+        let start = extents.position_for_index(range.start);
+        let end = extents.position_for_index(range.end);
+        extents.by_index[start..end]
+            .iter()
+            .map(|ix| &extents.by_x[*ix])
+            .map(|extent| extent.start_x..extent.end_x)
+            .reduce(|result, range| result.start.min(range.start)..result.end.max(range.end))
+            .unwrap_or_else(|| {
+                let x = self.caret_x(extents, range.start);
+                x..x
+            })
+    }
+
+    /// Returns the extents of every glyph in the line, computing them on first use.
+    fn glyph_extents(&self) -> &GlyphExtents {
+        self.glyph_extents.get_or_init(|| {
+            let mut by_x = Vec::new();
+            for run in &self.runs {
+                for glyph in &run.glyphs {
+                    by_x.push(GlyphExtent {
+                        index: glyph.index,
+                        start_x: glyph.position.x,
+                        end_x: self.width,
+                        is_rtl: run.is_rtl,
+                    });
                 }
             }
+            by_x.sort_by_key(|extent| extent.start_x);
+
+            for ix in 1..by_x.len() {
+                by_x[ix - 1].end_x = by_x[ix].start_x;
+            }
+
+            let mut by_index = (0..by_x.len()).collect::<Vec<_>>();
+            by_index.sort_by_key(|ix| by_x[*ix].index);
+            GlyphExtents { by_x, by_index }
+        })
+    }
+
+    /// The index of the first character after the one at the given index.
+    fn next_index(&self, extents: &GlyphExtents, index: usize) -> usize {
+        let position = extents.position_for_index(index + 1);
+        extents
+            .by_index
+            .get(position)
+            .map_or(self.len, |ix| extents.by_x[*ix].index)
+    }
+
+    fn caret_x(&self, extents: &GlyphExtents, index: usize) -> Pixels {
+        let position = extents.position_for_index(index);
+        if let Some(ix) = extents.by_index.get(position) {
+            let extent = &extents.by_x[*ix];
+            if extent.is_rtl {
+                extent.end_x
+            } else {
+                extent.start_x
+            }
+        } else if let Some(ix) = extents.by_index.last() {
+            let last = &extents.by_x[*ix];
+            if last.is_rtl {
+                last.start_x
+            } else {
+                last.end_x
+            }
+        } else {
+            self.width
         }
-        self.width
     }
 
     fn compute_wrap_boundaries(
@@ -168,6 +255,14 @@ impl LineLayout {
     }
 }
 
+impl GlyphExtents {
+    /// The position in `by_index` of the first glyph whose character index is at least `index`.
+    fn position_for_index(&self, index: usize) -> usize {
+        self.by_index
+            .partition_point(|ix| self.by_x[*ix].index < index)
+    }
+}
+
 /// A line of text that has been wrapped to fit a given width
 #[derive(Default, Debug)]
 pub struct WrappedLineLayout {
@@ -494,3 +589,93 @@ impl<'a> AsCacheKeyRef for CacheKeyRef<'a> {
         *self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point;
+
+    /// Lays out a line from runs of glyphs, given by their character index and x position, and
+    /// whether each run is right-to-left.
+    fn line_layout(runs: &[(bool, &[(usize, f32)])], width: f32) -> LineLayout {
+        LineLayout {
+            width: px(width),
+            len: runs.iter().map(|(_, glyphs)| glyphs.len()).sum(),
+            runs: runs
+                .iter()
+                .map(|&(is_rtl, glyphs)| ShapedRun {
+                    font_id: FontId(0),
+                    glyphs: glyphs
+                        .iter()
+                        .map(|&(index, x)| ShapedGlyph {
+                            id: GlyphId(0),
+                            position: point(px(x), px(0.)),
+                            index,
+                            is_emoji: false,
+                        })
+                        .collect(),
+                    is_rtl,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_left_to_right_hit_testing() {
+        let line = line_layout(&[(false, &[(0, 0.), (1, 10.), (2, 20.)])], 30.);
+        assert_eq!(line.index_for_x(px(15.)), Some(1));
+        assert_eq!(line.index_for_x(px(30.)), None);
+        assert_eq!(line.closest_index_for_x(px(14.)), 1);
+        assert_eq!(line.closest_index_for_x(px(26.)), 3);
+        assert_eq!(line.x_for_index(1), px(10.));
+        assert_eq!(line.x_for_index(3), px(30.));
+        assert_eq!(line.x_range_for_index_range(1..3), px(10.)..px(30.));
+    }
+
+    #[test]
+    fn test_mixed_direction_hit_testing() {
+        // "ab" followed by a right-to-left run of three characters, laid out visually
+        // as the characters at indices 4, 3, 2.
+        let line = line_layout(
+            &[
+                (false, &[(0, 0.), (1, 10.)]),
+                (true, &[(4, 20.), (3, 30.), (2, 40.)]),
+            ],
+            50.,
+        );
+        assert_eq!(line.index_for_x(px(45.)), Some(2));
+        assert_eq!(line.index_for_x(px(25.)), Some(4));
+        assert_eq!(line.x_for_index(2), px(50.));
+        assert_eq!(line.x_for_index(3), px(40.));
+        assert_eq!(line.x_for_index(5), px(20.));
+        assert_eq!(line.closest_index_for_x(px(49.)), 2);
+        assert_eq!(line.x_range_for_index_range(3..5), px(20.)..px(40.));
+        assert_eq!(line.x_range_for_index_range(1..3), px(10.)..px(50.));
+
+        // A cluster of characters within the right-to-left run, such as a letter followed by a
+        // combining mark, spans the glyphs of all of its characters.
+        assert_eq!(line.x_range_for_index_range(2..4), px(30.)..px(50.));
+    }
+
+    #[test]
+    fn test_right_to_left_hit_testing() {
+        // A right-to-left line of three characters, laid out visually as the characters at
+        // indices 2, 1, 0.
+        let line = line_layout(&[(true, &[(2, 0.), (1, 10.), (0, 20.)])], 30.);
+        assert_eq!(line.index_for_x(px(25.)), Some(0));
+        assert_eq!(line.index_for_x(px(5.)), Some(2));
+        assert_eq!(line.x_for_index(0), px(30.));
+        assert_eq!(line.x_for_index(2), px(10.));
+        assert_eq!(line.x_for_index(3), px(0.));
+        assert_eq!(line.closest_index_for_x(px(1.)), 3);
+        assert_eq!(line.x_range_for_index_range(0..2), px(10.)..px(30.));
+
+        // The direction of a run of a single character comes from the run, since there are no
+        // neighboring glyphs in the run to infer it from.
+        let line = line_layout(&[(false, &[(0, 0.), (1, 10.)]), (true, &[(2, 20.)])], 30.);
+        assert_eq!(line.x_for_index(2), px(30.));
+        assert_eq!(line.x_for_index(3), px(20.));
+        assert_eq!(line.index_for_x(px(25.)), Some(2));
+    }
+}
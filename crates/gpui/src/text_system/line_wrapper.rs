@@ -34,6 +34,17 @@ impl LineWrapper {
         &'a mut self,
         line: &'a str,
         wrap_width: Pixels,
+    ) -> impl Iterator<Item = Boundary> + 'a {
+        self.wrap_line_with_indent(line, wrap_width, WrapIndent::default())
+    }
+
+    /// Wrap a line of text to the given width with this wrapper's font and font size, indenting
+    /// the lines following each boundary as described by `wrap_indent`.
+    pub fn wrap_line_with_indent<'a>(
+        &'a mut self,
+        line: &'a str,
+        wrap_width: Pixels,
+        wrap_indent: WrapIndent,
     ) -> impl Iterator<Item = Boundary> + 'a {
         let mut width = px(0.);
         let mut first_non_whitespace_ix = None;
@@ -63,9 +74,12 @@ impl LineWrapper {
                 if width > wrap_width && ix > last_wrap_ix {
                     if let (None, Some(first_non_whitespace_ix)) = (indent, first_non_whitespace_ix)
                     {
-                        indent = Some(
-                            Self::MAX_INDENT.min((first_non_whitespace_ix - last_wrap_ix) as u32),
-                        );
+                        let line_indent = if wrap_indent.match_line {
+                            (first_non_whitespace_ix - last_wrap_ix) as u32
+                        } else {
+                            0
+                        };
+                        indent = Some(Self::MAX_INDENT.min(line_indent + wrap_indent.extra));
                     }
 
                     if last_candidate_ix > 0 {
@@ -125,6 +139,24 @@ impl LineWrapper {
     }
 }
 
+/// How to indent the lines following a boundary.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WrapIndent {
+    /// Whether to indent the lines as much as the line being wrapped.
+    pub match_line: bool,
+    /// The number of columns to indent the lines by, in addition to the line's indent.
+    pub extra: u32,
+}
+
+impl Default for WrapIndent {
+    fn default() -> Self {
+        Self {
+            match_line: true,
+            extra: 0,
+        }
+    }
+}
+
 /// A boundary between two lines of text.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Boundary {
@@ -188,6 +220,32 @@ mod tests {
                     Boundary::new(11, 5),
                 ]
             );
+            assert_eq!(
+                wrapper
+                    .wrap_line_with_indent(
+                        "     aaaaaaa",
+                        px(72.),
+                        WrapIndent {
+                            match_line: false,
+                            extra: 0,
+                        },
+                    )
+                    .collect::<Vec<_>>(),
+                &[Boundary::new(7, 0)]
+            );
+            assert_eq!(
+                wrapper
+                    .wrap_line_with_indent(
+                        "     aaaaaaa",
+                        px(72.),
+                        WrapIndent {
+                            match_line: false,
+                            extra: 2,
+                        },
+                    )
+                    .collect::<Vec<_>>(),
+                &[Boundary::new(7, 2)]
+            );
             assert_eq!(
                 wrapper
                     .wrap_line("                            ", px(72.))
@@ -27,7 +27,7 @@ use smallvec::SmallVec;
 use smol::future::yield_now;
 use std::{
     any::Any,
-    cmp::{self, Ordering},
+    cmp::{self, Ordering, Reverse},
     collections::BTreeMap,
     ffi::OsStr,
     future::Future,
@@ -98,6 +98,12 @@ pub struct Buffer {
     sync_parse_timeout: Duration,
     syntax_map: Mutex<SyntaxMap>,
     parsing_in_background: bool,
+    /// The number of requests to leave the buffer unparsed as it changes, see
+    /// [Buffer::set_parsing_enabled].
+    parsing_disabled: usize,
+    /// The number of requests to leave the Git diff as it is as the buffer changes, see
+    /// [Buffer::set_git_diff_enabled].
+    git_diff_disabled: usize,
     parse_count: usize,
     diagnostics: SmallVec<[(LanguageServerId, DiagnosticSet); 2]>,
     remote_selections: TreeMap<ReplicaId, SelectionSet>,
@@ -106,6 +112,10 @@ pub struct Buffer {
     diagnostics_timestamp: clock::Lamport,
     file_update_count: usize,
     git_diff_update_count: usize,
+    /// The indentation used in the buffer, when it differs from the language settings.
+    indent_size_override: Option<IndentSize>,
+    indent_size_override_timestamp: clock::Lamport,
+    indent_size_update_count: usize,
     completion_triggers: Vec<String>,
     completion_triggers_timestamp: clock::Lamport,
     deferred_ops: OperationQueue<Operation>,
@@ -123,6 +133,7 @@ pub struct BufferSnapshot {
     diagnostics_update_count: usize,
     file_update_count: usize,
     git_diff_update_count: usize,
+    indent_size_override: Option<IndentSize>,
     remote_selections: TreeMap<ReplicaId, SelectionSet>,
     selections_update_count: usize,
     language: Option<Arc<Language>>,
@@ -277,6 +288,17 @@ pub struct CodeAction {
     pub lsp_action: lsp::CodeAction,
 }
 
+/// A runnable found by a language's runnables query, such as a test function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RunnableRange {
+    /// The range of the runnable, whose first line shows the button that runs it.
+    pub run_range: Range<usize>,
+    /// The name of the runnable, such as the name of a test function.
+    pub name: String,
+    /// The names of the scopes enclosing the runnable, such as modules, outermost first.
+    pub scopes: Vec<String>,
+}
+
 /// An operation used to synchronize this buffer with its other replicas.
 #[derive(Clone, Debug, PartialEq)]
 pub enum Operation {
@@ -313,6 +335,15 @@ pub enum Operation {
         /// The buffer's lamport timestamp.
         lamport_timestamp: clock::Lamport,
     },
+
+    /// An update to the indentation used in the buffer instead of the one
+    /// from the language settings.
+    UpdateIndentSize {
+        /// The indentation used in the buffer, if any.
+        indent_size: Option<IndentSize>,
+        /// The buffer's lamport timestamp.
+        lamport_timestamp: clock::Lamport,
+    },
 }
 
 /// An event that occurs in a buffer.
@@ -333,6 +364,8 @@ pub enum Event {
     Reloaded,
     /// The buffer's diff_base changed.
     DiffBaseChanged,
+    /// The buffer's git diff status was recomputed.
+    DiffUpdated,
     /// The buffer's language was changed.
     LanguageChanged,
     /// The buffer's syntax trees were updated.
@@ -435,6 +468,8 @@ struct AutoindentRequest {
     before_edit: BufferSnapshot,
     entries: Vec<AutoindentRequestEntry>,
     is_block_mode: bool,
+    /// Whether to leave the indentation of empty lines alone.
+    ignore_empty_lines: bool,
 }
 
 #[derive(Clone)]
@@ -495,6 +530,8 @@ pub struct Chunk<'a> {
     pub is_unnecessary: bool,
     /// Whether this chunk of text was originally a tab character.
     pub is_tab: bool,
+    /// Whether this chunk of text is suggested in the editor, rather than part of the buffer.
+    pub is_suggestion: bool,
 }
 
 /// A set of edits to a given version of a buffer, computed asynchronously.
@@ -523,6 +560,82 @@ pub enum CharKind {
     Word,
 }
 
+/// A region of code that can be selected as a whole, found with the text objects query of the
+/// language.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum TextObject {
+    /// A function, method or closure. Its inside is the function's body.
+    Function,
+    /// A type definition, such as a class, struct, enum, trait or impl block. Its inside is the
+    /// definition's body.
+    Type,
+    /// An argument or a parameter. Around it, it includes the comma separating it from the next
+    /// or previous argument.
+    Argument,
+    /// A string literal. Its inside excludes the quotes.
+    String,
+}
+
+impl TextObject {
+    /// Returns the text object captured by a capture of the text objects query, such as
+    /// `@function.around`, and whether the capture is the whole object rather than its inside.
+    pub(crate) fn from_capture_name(name: &str) -> Option<(Self, bool)> {
+        let (object, part) = name.split_once('.')?;
+        let object = match object {
+            "function" => TextObject::Function,
+            "class" => TextObject::Type,
+            "parameter" => TextObject::Argument,
+            "string" => TextObject::String,
+            _ => return None,
+        };
+        match part {
+            "around" => Some((object, true)),
+            "inside" => Some((object, false)),
+            _ => None,
+        }
+    }
+
+    /// Returns the inside of the object, which excludes the delimiters of functions, types and
+    /// strings, such as braces and quotes.
+    fn inside_range(self, node: tree_sitter::Node) -> Range<usize> {
+        match self {
+            TextObject::Argument => node.byte_range(),
+            _ => range_within_delimiters(node),
+        }
+    }
+
+    /// Returns the range around the object, for queries that only capture its inside.
+    fn around_range(self, inside: tree_sitter::Node) -> Range<usize> {
+        match self {
+            TextObject::Argument => {
+                if let Some(next) = inside.next_named_sibling() {
+                    inside.start_byte()..next.start_byte()
+                } else if let Some(previous) = inside.prev_named_sibling() {
+                    previous.end_byte()..inside.end_byte()
+                } else {
+                    inside.byte_range()
+                }
+            }
+            _ => inside.byte_range(),
+        }
+    }
+}
+
+/// Returns the range between the first and the last child of the given node, when they are
+/// delimiters such as braces or quotes.
+fn range_within_delimiters(node: tree_sitter::Node) -> Range<usize> {
+    let first = node.child(0);
+    let last = node.child(node.child_count().saturating_sub(1));
+    match (first, last) {
+        (Some(first), Some(last))
+            if node.child_count() >= 2 && !first.is_named() && !last.is_named() =>
+        {
+            first.end_byte()..last.start_byte()
+        }
+        _ => node.byte_range(),
+    }
+}
+
 impl Buffer {
     /// Create a new buffer with the given base text.
     pub fn new<T: Into<String>>(replica_id: ReplicaId, id: BufferId, base_text: T) -> Self {
@@ -626,6 +739,11 @@ impl Buffer {
             },
         ));
 
+        operations.push(proto::serialize_operation(&Operation::UpdateIndentSize {
+            indent_size: self.indent_size_override,
+            lamport_timestamp: self.indent_size_override_timestamp,
+        }));
+
         let text_operations = self.text.operations().clone();
         cx.background_executor().spawn(async move {
             let since = since.unwrap_or_default();
@@ -683,6 +801,8 @@ impl Buffer {
             capability,
             syntax_map: Mutex::new(SyntaxMap::new()),
             parsing_in_background: false,
+            parsing_disabled: 0,
+            git_diff_disabled: 0,
             parse_count: 0,
             sync_parse_timeout: Duration::from_millis(1),
             autoindent_requests: Default::default(),
@@ -695,6 +815,9 @@ impl Buffer {
             diagnostics_timestamp: Default::default(),
             file_update_count: 0,
             git_diff_update_count: 0,
+            indent_size_override: None,
+            indent_size_override_timestamp: Default::default(),
+            indent_size_update_count: 0,
             completion_triggers: Default::default(),
             completion_triggers_timestamp: Default::default(),
             deferred_ops: OperationQueue::new(),
@@ -719,6 +842,7 @@ impl Buffer {
             diagnostics_update_count: self.diagnostics_update_count,
             file_update_count: self.file_update_count,
             git_diff_update_count: self.git_diff_update_count,
+            indent_size_override: self.indent_size_override,
             language: self.language.clone(),
             parse_count: self.parse_count,
             selections_update_count: self.selections_update_count,
@@ -922,8 +1046,25 @@ impl Buffer {
         cx.emit(Event::DiffBaseChanged);
     }
 
+    /// Stops or resumes recomputing the Git diff as the buffer changes, such as for files that
+    /// are too large to diff after every edit. Every call that stops it must be followed by one
+    /// that resumes it, and the diff is only recomputed again once all of them did.
+    pub fn set_git_diff_enabled(&mut self, enabled: bool, cx: &mut ModelContext<Self>) {
+        if enabled {
+            self.git_diff_disabled = self.git_diff_disabled.saturating_sub(1);
+            if self.git_diff_disabled == 0 {
+                self.git_diff_recalc(cx);
+            }
+        } else {
+            self.git_diff_disabled += 1;
+        }
+    }
+
     /// Recomputes the Git diff status.
     pub fn git_diff_recalc(&mut self, cx: &mut ModelContext<Self>) -> Option<Task<()>> {
+        if self.git_diff_disabled > 0 {
+            return None;
+        }
         let diff_base = self.diff_base.clone()?; // TODO: Make this an Arc
         let snapshot = self.snapshot();
 
@@ -935,9 +1076,10 @@ impl Buffer {
 
         Some(cx.spawn(|this, mut cx| async move {
             let buffer_diff = diff.await;
-            this.update(&mut cx, |this, _| {
+            this.update(&mut cx, |this, cx| {
                 this.git_diff = buffer_diff;
                 this.git_diff_update_count += 1;
+                cx.emit(Event::DiffUpdated);
             })
             .ok();
         }))
@@ -984,12 +1126,65 @@ impl Buffer {
         self.git_diff_update_count
     }
 
+    /// The number of times the buffer's indentation override was updated.
+    pub fn indent_size_update_count(&self) -> usize {
+        self.indent_size_update_count
+    }
+
+    /// The indentation used in the buffer instead of the one from the language settings, if any.
+    pub fn indent_size_override(&self) -> Option<IndentSize> {
+        self.indent_size_override
+    }
+
+    /// Sets the indentation to use in the buffer instead of the one from the language settings,
+    /// e.g. after its indentation was converted to a different kind.
+    pub fn set_indent_size_override(
+        &mut self,
+        indent_size: Option<IndentSize>,
+        cx: &mut ModelContext<Self>,
+    ) {
+        if self.indent_size_override != indent_size {
+            self.indent_size_override = indent_size;
+            self.indent_size_override_timestamp = self.text.lamport_clock.tick();
+            self.indent_size_update_count += 1;
+            self.send_operation(
+                Operation::UpdateIndentSize {
+                    indent_size,
+                    lamport_timestamp: self.indent_size_override_timestamp,
+                },
+                cx,
+            );
+            cx.notify();
+        }
+    }
+
+    /// Stops or resumes parsing the buffer as it changes, such as for files that are too large
+    /// to parse after every edit. The syntax tree parsed last is kept while parsing is stopped.
+    /// Every call that stops parsing must be followed by one that resumes it, and the buffer is
+    /// only parsed again once all of them did.
+    pub fn set_parsing_enabled(&mut self, enabled: bool, cx: &mut ModelContext<Self>) {
+        if enabled {
+            self.parsing_disabled = self.parsing_disabled.saturating_sub(1);
+            if self.parsing_disabled == 0 {
+                self.reparse(cx);
+            }
+        } else {
+            self.parsing_disabled += 1;
+        }
+    }
+
     /// Whether the buffer is being parsed in the background.
     #[cfg(any(test, feature = "test-support"))]
     pub fn is_parsing(&self) -> bool {
         self.parsing_in_background
     }
 
+    /// Whether the buffer is parsed as it changes, see [Buffer::set_parsing_enabled].
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn is_parsing_enabled(&self) -> bool {
+        self.parsing_disabled == 0
+    }
+
     /// Indicates whether the buffer contains any regions that may be
     /// written in a language that hasn't been loaded yet.
     pub fn contains_unknown_injections(&self) -> bool {
@@ -1025,7 +1220,7 @@ impl Buffer {
     /// for the same buffer, we only initiate a new parse if we are not already
     /// parsing in the background.
     pub fn reparse(&mut self, cx: &mut ModelContext<Self>) {
-        if self.parsing_in_background {
+        if self.parsing_in_background || self.parsing_disabled > 0 {
             return;
         }
         let language = if let Some(language) = self.language.clone() {
@@ -1115,6 +1310,32 @@ impl Buffer {
         self.send_operation(op, cx);
     }
 
+    /// Recomputes the indentation of the lines in the given ranges from the language's
+    /// indentation rules, regardless of whether it changed since the lines were edited.
+    pub fn autoindent_ranges<I, T>(&mut self, ranges: I, cx: &mut ModelContext<Self>)
+    where
+        I: IntoIterator<Item = Range<T>>,
+        T: ToOffset + Copy,
+    {
+        let before_edit = self.snapshot();
+        let entries = ranges
+            .into_iter()
+            .map(|range| AutoindentRequestEntry {
+                range: before_edit.anchor_before(range.start)..before_edit.anchor_after(range.end),
+                first_line_is_new: true,
+                indent_size: before_edit.language_indent_size_at(range.start, cx),
+                original_indent_column: None,
+            })
+            .collect();
+        self.autoindent_requests.push(Arc::new(AutoindentRequest {
+            before_edit,
+            entries,
+            is_block_mode: false,
+            ignore_empty_lines: true,
+        }));
+        self.request_autoindent(cx);
+    }
+
     fn request_autoindent(&mut self, cx: &mut ModelContext<Self>) {
         if let Some(indent_sizes) = self.compute_autoindents() {
             let indent_sizes = cx.background_executor().spawn(indent_sizes);
@@ -1235,6 +1456,9 @@ impl Buffer {
                         .into_iter()
                         .flatten();
                     for (new_row, suggestion) in new_edited_row_range.zip(suggestions) {
+                        if request.ignore_empty_lines && snapshot.line_len(new_row) == 0 {
+                            continue;
+                        }
                         if let Some(suggestion) = suggestion {
                             // Find the indent size based on the language for this row.
                             while let Some((row, size)) = language_indent_sizes.peek() {
@@ -1596,6 +1820,12 @@ impl Buffer {
         self.text.finalize_last_transaction()
     }
 
+    /// Names the current state of the buffer's undo tree, so that it can be returned to later.
+    pub fn add_restore_point(&mut self, name: impl Into<Arc<str>>, cx: &mut ModelContext<Self>) {
+        self.text.add_restore_point(name);
+        cx.notify();
+    }
+
     /// Manually group all changes since a given transaction.
     pub fn group_until_transaction(&mut self, transaction_id: TransactionId) {
         self.text.group_until_transaction(transaction_id);
@@ -1803,6 +2033,7 @@ impl Buffer {
                 before_edit,
                 entries,
                 is_block_mode: matches!(mode, AutoindentMode::Block { .. }),
+                ignore_empty_lines: false,
             }));
         }
 
@@ -1891,7 +2122,7 @@ impl Buffer {
             Operation::UpdateSelections { selections, .. } => selections
                 .iter()
                 .all(|s| self.can_resolve(&s.start) && self.can_resolve(&s.end)),
-            Operation::UpdateCompletionTriggers { .. } => true,
+            Operation::UpdateCompletionTriggers { .. } | Operation::UpdateIndentSize { .. } => true,
         }
     }
 
@@ -1944,6 +2175,17 @@ impl Buffer {
                 self.completion_triggers = triggers;
                 self.text.lamport_clock.observe(lamport_timestamp);
             }
+            Operation::UpdateIndentSize {
+                indent_size,
+                lamport_timestamp,
+            } => {
+                if lamport_timestamp > self.indent_size_override_timestamp {
+                    self.indent_size_override = indent_size;
+                    self.indent_size_override_timestamp = lamport_timestamp;
+                    self.indent_size_update_count += 1;
+                }
+                self.text.lamport_clock.observe(lamport_timestamp);
+            }
         }
     }
 
@@ -2015,6 +2257,25 @@ impl Buffer {
         }
     }
 
+    /// Undoes the edits of the most recent transaction that touch any of the given ranges, leaving
+    /// the edits elsewhere in the buffer applied.
+    pub fn undo_in_ranges(
+        &mut self,
+        ranges: &[Range<usize>],
+        cx: &mut ModelContext<Self>,
+    ) -> Option<TransactionId> {
+        let was_dirty = self.is_dirty();
+        let old_version = self.version.clone();
+
+        if let Some((transaction_id, operation)) = self.text.undo_in_ranges(ranges) {
+            self.send_operation(Operation::Buffer(operation), cx);
+            self.did_edit(&old_version, was_dirty, cx);
+            Some(transaction_id)
+        } else {
+            None
+        }
+    }
+
     /// Manually undoes all changes after a given transaction in the buffer's undo history.
     pub fn undo_to_transaction(
         &mut self,
@@ -2069,6 +2330,27 @@ impl Buffer {
         redone
     }
 
+    /// Moves the buffer to the state of the given node of its undo tree, or to its original
+    /// text if `transaction_id` is `None`.
+    pub fn go_to_undo_tree_node(
+        &mut self,
+        transaction_id: Option<TransactionId>,
+        cx: &mut ModelContext<Self>,
+    ) -> bool {
+        let was_dirty = self.is_dirty();
+        let old_version = self.version.clone();
+
+        let operations = self.text.go_to_undo_tree_node(transaction_id);
+        let changed = !operations.is_empty();
+        for operation in operations {
+            self.send_operation(Operation::Buffer(operation), cx);
+        }
+        if changed {
+            self.did_edit(&old_version, was_dirty, cx)
+        }
+        changed
+    }
+
     /// Override current completion triggers with the user-provided completion triggers.
     pub fn set_completion_triggers(&mut self, triggers: Vec<String>, cx: &mut ModelContext<Self>) {
         self.completion_triggers = triggers.clone();
@@ -2168,8 +2450,11 @@ impl BufferSnapshot {
         indent_size_for_line(self, row)
     }
     /// Returns [`IndentSize`] for a given position that respects user settings
-    /// and language preferences.
+    /// and language preferences, unless the buffer overrides its indentation.
     pub fn language_indent_size_at<T: ToOffset>(&self, position: T, cx: &AppContext) -> IndentSize {
+        if let Some(indent_size) = self.indent_size_override {
+            return indent_size;
+        }
         let settings = language_settings(self.language_at(position), self.file(), cx);
         if settings.hard_tabs {
             IndentSize::tab()
@@ -2178,6 +2463,47 @@ impl BufferSnapshot {
         }
     }
 
+    /// Detects the indentation used in the buffer from the leading whitespace of its first lines.
+    /// It is tabs when more lines are indented with tabs than with spaces, and otherwise the most
+    /// common difference in indentation between consecutive lines.
+    pub fn detect_indent_size(&self) -> Option<IndentSize> {
+        const MAX_ROWS: u32 = 1000;
+
+        let mut tab_indented_lines = 0;
+        let mut space_indented_lines = 0;
+        let mut space_indent_deltas = [0; 9];
+        let mut prev_indent_len = 0;
+        for row in 0..=self.max_point().row.min(MAX_ROWS) {
+            if self.is_line_blank(row) {
+                continue;
+            }
+
+            let indent = self.indent_size_for_line(row);
+            if indent.kind == IndentKind::Tab {
+                tab_indented_lines += 1;
+                continue;
+            }
+            if indent.len > 0 {
+                space_indented_lines += 1;
+            }
+            // Differences of a single column are most likely alignment, such as in block comments.
+            let delta = indent.len.abs_diff(prev_indent_len) as usize;
+            if (2..space_indent_deltas.len()).contains(&delta) {
+                space_indent_deltas[delta] += 1;
+            }
+            prev_indent_len = indent.len;
+        }
+
+        if tab_indented_lines > space_indented_lines {
+            return Some(IndentSize::tab());
+        }
+        let (width, count) = space_indent_deltas
+            .into_iter()
+            .enumerate()
+            .max_by_key(|&(width, count)| (count, Reverse(width)))?;
+        (count > 0).then(|| IndentSize::spaces(width as u32))
+    }
+
     /// Retrieve the suggested indent size for all of the given rows. The unit of indentation
     /// is passed in as `single_indent_size`.
     pub fn suggested_indents(
@@ -2664,6 +2990,158 @@ impl BufferSnapshot {
         result
     }
 
+    /// Returns the range of the smallest named syntax node enclosing the given range, along with
+    /// the range of its previous or next named sibling, if any.
+    pub fn range_for_syntax_sibling<T: ToOffset>(
+        &self,
+        range: Range<T>,
+        next: bool,
+    ) -> Option<(Range<usize>, Range<usize>)> {
+        let range = range.start.to_offset(self)..range.end.to_offset(self);
+        let mut result: Option<(Range<usize>, Range<usize>)> = None;
+        for layer in self.syntax.layers_for_range(range.clone(), &self.text) {
+            let Some(mut node) = layer
+                .node()
+                .named_descendant_for_byte_range(range.start, range.end)
+            else {
+                continue;
+            };
+
+            // Prefer the outermost node among those spanning the same text, e.g. a statement
+            // rather than the expression it consists of.
+            while let Some(parent) = node.parent() {
+                if parent.byte_range() != node.byte_range() || !parent.is_named() {
+                    break;
+                }
+                node = parent;
+            }
+
+            let sibling = if next {
+                node.next_named_sibling()
+            } else {
+                node.prev_named_sibling()
+            };
+            let Some(sibling) = sibling else {
+                continue;
+            };
+            if let Some((previous_node, _)) = &result {
+                if previous_node.len() < node.byte_range().len() {
+                    continue;
+                }
+            }
+            result = Some((node.byte_range(), sibling.byte_range()));
+        }
+
+        result
+    }
+
+    /// Returns the range of the innermost text object of the given kind enclosing the given range.
+    ///
+    /// Objects whose inside isn't captured by the text objects query are inside their whole
+    /// capture, and objects that are only captured by their inside extend around it.
+    pub fn range_for_text_object<T: ToOffset>(
+        &self,
+        range: Range<T>,
+        object: TextObject,
+        around: bool,
+    ) -> Option<Range<usize>> {
+        let range = range.start.to_offset(self)..range.end.to_offset(self);
+        let mut syntax_matches = self.syntax.matches(range.clone(), &self.text, |grammar| {
+            grammar
+                .text_object_config
+                .as_ref()
+                .map(|config| &config.query)
+        });
+        let configs = syntax_matches
+            .grammars()
+            .iter()
+            .map(|grammar| grammar.text_object_config.as_ref())
+            .collect::<Vec<_>>();
+
+        let mut result: Option<(usize, Range<usize>)> = None;
+        while let Some(mat) = syntax_matches.peek() {
+            if let Some(config) = configs[mat.grammar_index] {
+                let mut around_node = None;
+                let mut inside_node = None;
+                for capture in mat.captures {
+                    match config.text_objects_by_capture_ix.get(&capture.index) {
+                        Some((capture_object, true)) if *capture_object == object => {
+                            around_node = Some(capture.node)
+                        }
+                        Some((capture_object, false)) if *capture_object == object => {
+                            inside_node = Some(capture.node)
+                        }
+                        _ => {}
+                    }
+                }
+
+                let object_range = if around {
+                    around_node
+                        .map(|node| node.byte_range())
+                        .or_else(|| inside_node.map(|node| object.around_range(node)))
+                } else {
+                    inside_node
+                        .or(around_node)
+                        .map(|node| object.inside_range(node))
+                };
+                let outer_range = around_node.or(inside_node).map(|node| node.byte_range());
+                if let Some((outer_range, object_range)) = outer_range.zip(object_range) {
+                    let encloses = outer_range.start <= range.start && range.end <= outer_range.end;
+                    let is_innermost = result
+                        .as_ref()
+                        .map_or(true, |(len, _)| outer_range.len() < *len);
+                    if encloses && is_innermost {
+                        result = Some((outer_range.len(), object_range));
+                    }
+                }
+            }
+            syntax_matches.advance();
+        }
+
+        result.map(|(_, range)| range)
+    }
+
+    /// Returns the range to fold in order to collapse the outermost syntax node, other than the
+    /// root, that starts at the first non-whitespace character of the given row and spans at
+    /// least three rows.
+    ///
+    /// The range starts at the end of the row and ends at the indentation of the node's last
+    /// row, so that the node's first and last lines stay visible around the fold.
+    pub fn syntax_fold_range(&self, row: u32) -> Option<Range<Point>> {
+        let line_len = self.line_len(row);
+        let indent = self.indent_size_for_line(row);
+        if indent.len >= line_len {
+            return None;
+        }
+
+        let offset = Point::new(row, indent.len).to_offset(self);
+        let mut end: Option<Point> = None;
+        for layer in self.syntax.layers_for_range(offset..offset, &self.text) {
+            let Some(mut node) = layer.node().descendant_for_byte_range(offset, offset) else {
+                continue;
+            };
+            if node.start_byte() != offset {
+                continue;
+            }
+
+            // Ascend to the outermost node starting at the offset, short of the layer's root.
+            while let Some(parent) = node
+                .parent()
+                .filter(|parent| parent.start_byte() == offset && parent.parent().is_some())
+            {
+                node = parent;
+            }
+            let node_end = node.end_byte().to_point(self);
+            if end.map_or(true, |end| end < node_end) {
+                end = Some(node_end);
+            }
+        }
+
+        let end = end.filter(|end| end.row > row + 1)?;
+        let end_indent = self.indent_size_for_line(end.row);
+        Some(Point::new(row, line_len)..Point::new(end.row, end_indent.len.min(end.column)))
+    }
+
     /// Returns the outline for the buffer.
     ///
     /// This method allows passing an optional [SyntaxTheme] to
@@ -2923,6 +3401,61 @@ impl BufferSnapshot {
         })
     }
 
+    /// Returns the runnables, such as tests, found by the runnables query within the given range,
+    /// along with their names and the names of the scopes enclosing them.
+    pub fn runnable_ranges<T: ToOffset>(&self, range: Range<T>) -> Vec<RunnableRange> {
+        let offset_range = range.start.to_offset(self)..range.end.to_offset(self);
+        let mut syntax_matches = self.syntax.matches(offset_range, self, |grammar| {
+            grammar.runnable_config.as_ref().map(|config| &config.query)
+        });
+
+        let configs = syntax_matches
+            .grammars()
+            .iter()
+            .map(|grammar| grammar.runnable_config.as_ref())
+            .collect::<Vec<_>>();
+
+        let mut runnables = Vec::new();
+        let mut scopes = Vec::new();
+        while let Some(mat) = syntax_matches.peek() {
+            if let Some(config) = configs[mat.grammar_index] {
+                let capture = |capture_ix: Option<u32>| {
+                    let capture_ix = capture_ix?;
+                    mat.captures
+                        .iter()
+                        .find(|capture| capture.index == capture_ix)
+                        .map(|capture| capture.node.byte_range())
+                };
+                if let Some(ranges) =
+                    capture(Some(config.run_capture_ix)).zip(capture(Some(config.name_capture_ix)))
+                {
+                    runnables.push(ranges);
+                } else if let Some(ranges) =
+                    capture(config.scope_capture_ix).zip(capture(config.scope_name_capture_ix))
+                {
+                    scopes.push(ranges);
+                }
+            }
+            syntax_matches.advance();
+        }
+        scopes.sort_by_key(|(range, _)| (range.start, Reverse(range.end)));
+
+        runnables
+            .into_iter()
+            .map(|(run_range, name_range)| RunnableRange {
+                scopes: scopes
+                    .iter()
+                    .filter(|(scope_range, _)| {
+                        scope_range.start <= run_range.start && scope_range.end >= run_range.end
+                    })
+                    .map(|(_, name_range)| self.text_for_range(name_range.clone()).collect())
+                    .collect(),
+                name: self.text_for_range(name_range).collect(),
+                run_range,
+            })
+            .collect()
+    }
+
     /// Returns selections for remote peers intersecting the given range.
     #[allow(clippy::type_complexity)]
     pub fn remote_selections_in_range(
@@ -2999,6 +3532,17 @@ impl BufferSnapshot {
         !self.diagnostics.is_empty()
     }
 
+    /// Returns the number of errors and of warnings in the buffer, counting each group of related
+    /// diagnostics once. The counts are kept up to date as diagnostics are updated, rather than
+    /// being computed on each call.
+    pub fn diagnostic_severity_counts(&self) -> (usize, usize) {
+        self.diagnostics
+            .iter()
+            .fold((0, 0), |(errors, warnings), (_, set)| {
+                (errors + set.error_count(), warnings + set.warning_count())
+            })
+    }
+
     /// Returns all the diagnostics intersecting the given range.
     pub fn diagnostics_in_range<'a, T, O>(
         &'a self,
@@ -3152,6 +3696,7 @@ impl Clone for BufferSnapshot {
             diagnostics_update_count: self.diagnostics_update_count,
             file_update_count: self.file_update_count,
             git_diff_update_count: self.git_diff_update_count,
+            indent_size_override: self.indent_size_override,
             language: self.language.clone(),
             parse_count: self.parse_count,
         }
@@ -3364,6 +3909,9 @@ impl operation_queue::Operation for Operation {
             }
             | Operation::UpdateCompletionTriggers {
                 lamport_timestamp, ..
+            }
+            | Operation::UpdateIndentSize {
+                lamport_timestamp, ..
             } => *lamport_timestamp,
         }
     }
@@ -456,6 +456,52 @@ async fn test_reparse(cx: &mut gpui::TestAppContext) {
     );
 }
 
+#[gpui::test]
+async fn test_parsing_disabled(cx: &mut gpui::TestAppContext) {
+    let buffer = cx.new_model(|cx| {
+        Buffer::new(
+            0,
+            BufferId::new(cx.entity_id().as_u64()).unwrap(),
+            "fn a() {}",
+        )
+        .with_language(Arc::new(rust_lang()), cx)
+    });
+    cx.executor().run_until_parked();
+    let parse_count = buffer.update(cx, |buffer, _| buffer.parse_count());
+
+    // Edits made while parsing is off aren't parsed.
+    buffer.update(cx, |buffer, cx| {
+        buffer.set_parsing_enabled(false, cx);
+        buffer.set_parsing_enabled(false, cx);
+        buffer.edit([(5..5, "b: C")], None, cx);
+        assert!(!buffer.is_parsing());
+    });
+    cx.executor().run_until_parked();
+    assert_eq!(
+        buffer.update(cx, |buffer, _| buffer.parse_count()),
+        parse_count
+    );
+
+    // Parsing stays off until everything that turned it off turns it back on.
+    buffer.update(cx, |buffer, cx| buffer.set_parsing_enabled(true, cx));
+    cx.executor().run_until_parked();
+    assert_eq!(
+        buffer.update(cx, |buffer, _| buffer.parse_count()),
+        parse_count
+    );
+    buffer.update(cx, |buffer, cx| buffer.set_parsing_enabled(true, cx));
+    cx.executor().run_until_parked();
+    assert!(buffer.update(cx, |buffer, _| buffer.parse_count()) > parse_count);
+    assert_eq!(
+        get_tree_sexp(&buffer, cx),
+        concat!(
+            "(source_file (function_item name: (identifier) ",
+            "parameters: (parameters (parameter pattern: (identifier) type: (type_identifier))) ",
+            "body: (block)))"
+        )
+    );
+}
+
 #[gpui::test]
 async fn test_resetting_language(cx: &mut gpui::TestAppContext) {
     let buffer = cx.new_model(|cx| {
@@ -662,6 +708,74 @@ async fn test_outline_with_extra_context(cx: &mut gpui::TestAppContext) {
     );
 }
 
+#[gpui::test]
+async fn test_runnable_ranges(cx: &mut gpui::TestAppContext) {
+    let language = rust_lang()
+        .with_runnable_query(
+            r#"
+            (
+                (attribute_item (attribute) @_attribute
+                    (#match? @_attribute "^test$"))
+                .
+                (function_item
+                    name: (_) @name) @run
+            )
+            (mod_item
+                name: (_) @scope_name) @scope
+            "#,
+        )
+        .unwrap();
+
+    let text = r#"
+        fn helper() {}
+
+        #[test]
+        fn one() {}
+
+        mod tests {
+            mod nested {
+                #[test]
+                fn one() {
+                    helper();
+                }
+            }
+        }
+    "#
+    .unindent();
+
+    let buffer = cx.new_model(|cx| {
+        Buffer::new(0, BufferId::new(cx.entity_id().as_u64()).unwrap(), text)
+            .with_language(Arc::new(language), cx)
+    });
+    let snapshot = buffer.update(cx, |buffer, _| buffer.snapshot());
+
+    assert_eq!(
+        snapshot
+            .runnable_ranges(0..snapshot.len())
+            .into_iter()
+            .map(|runnable| (
+                runnable.scopes,
+                runnable.name,
+                snapshot.offset_to_point(runnable.run_range.start)
+            ))
+            .collect::<Vec<_>>(),
+        &[
+            (vec![], "one".to_string(), Point::new(3, 0)),
+            (
+                vec!["tests".to_string(), "nested".to_string()],
+                "one".to_string(),
+                Point::new(7, 8)
+            )
+        ]
+    );
+
+    // Runnables are found with their enclosing scopes when only part of the buffer is queried.
+    let start = snapshot.point_to_offset(Point::new(7, 0));
+    let runnables = snapshot.runnable_ranges(start..snapshot.len());
+    assert_eq!(runnables.len(), 1);
+    assert_eq!(runnables[0].scopes, ["tests", "nested"]);
+}
+
 #[gpui::test]
 async fn test_symbols_containing(cx: &mut gpui::TestAppContext) {
     let text = r#"
@@ -938,6 +1052,89 @@ fn test_range_for_syntax_ancestor(cx: &mut AppContext) {
     }
 }
 
+#[gpui::test]
+fn test_syntax_fold_range(cx: &mut AppContext) {
+    cx.new_model(|cx| {
+        let text = "
+            fn a() {
+                b(
+                    1,
+                );
+                c(2);
+            }
+        "
+        .unindent();
+        let buffer = Buffer::new(0, BufferId::new(cx.entity_id().as_u64()).unwrap(), text)
+            .with_language(Arc::new(rust_lang()), cx);
+        let snapshot = buffer.snapshot();
+
+        assert_eq!(
+            snapshot.syntax_fold_range(0),
+            Some(Point::new(0, 8)..Point::new(5, 0))
+        );
+        assert_eq!(
+            snapshot.syntax_fold_range(1),
+            Some(Point::new(1, 6)..Point::new(3, 4))
+        );
+        assert_eq!(snapshot.syntax_fold_range(2), None);
+        assert_eq!(snapshot.syntax_fold_range(4), None);
+        assert_eq!(snapshot.syntax_fold_range(6), None);
+
+        buffer
+    });
+}
+
+#[gpui::test]
+fn test_diagnostic_severity_counts(cx: &mut AppContext) {
+    init_settings(cx, |_| {});
+
+    cx.new_model(|cx| {
+        let mut buffer = Buffer::new(
+            0,
+            BufferId::new(cx.entity_id().as_u64()).unwrap(),
+            "one\ntwo\nthree",
+        );
+        let diagnostics = |entries: &[(u32, DiagnosticSeverity, bool)], buffer: &Buffer| {
+            DiagnosticSet::new(
+                entries
+                    .iter()
+                    .map(|&(row, severity, is_primary)| DiagnosticEntry {
+                        range: PointUtf16::new(row, 0)..PointUtf16::new(row, 3),
+                        diagnostic: Diagnostic {
+                            severity,
+                            is_primary,
+                            ..Default::default()
+                        },
+                    }),
+                buffer,
+            )
+        };
+        assert_eq!(buffer.snapshot().diagnostic_severity_counts(), (0, 0));
+
+        // Only the primary diagnostic of each group is counted, across all language servers.
+        let set = diagnostics(
+            &[
+                (0, DiagnosticSeverity::ERROR, true),
+                (1, DiagnosticSeverity::ERROR, false),
+                (1, DiagnosticSeverity::WARNING, true),
+                (2, DiagnosticSeverity::HINT, true),
+            ],
+            &buffer,
+        );
+        buffer.update_diagnostics(LanguageServerId(0), set, cx);
+        let set = diagnostics(&[(2, DiagnosticSeverity::ERROR, true)], &buffer);
+        buffer.update_diagnostics(LanguageServerId(1), set, cx);
+        assert_eq!(buffer.snapshot().diagnostic_severity_counts(), (2, 1));
+
+        // The counts follow the diagnostics as they're updated.
+        let set = diagnostics(&[], &buffer);
+        buffer.update_diagnostics(LanguageServerId(0), set, cx);
+        assert_eq!(buffer.snapshot().diagnostic_severity_counts(), (1, 0));
+
+        buffer
+    });
+}
+
 #[gpui::test]
 fn test_autoindent_with_soft_tabs(cx: &mut AppContext) {
     init_settings(cx, |_| {});
@@ -1669,6 +1866,26 @@ fn test_autoindent_query_with_outdent_captures(cx: &mut AppContext) {
     });
 }
 
+#[test]
+fn test_detect_indent_size() {
+    let detect = |text: &str| {
+        Buffer::new(0, BufferId::new(1).unwrap(), text)
+            .snapshot()
+            .detect_indent_size()
+    };
+
+    assert_eq!(
+        detect("fn a() {\n  b();\n  if c {\n    d();\n  }\n}\n"),
+        Some(IndentSize::spaces(2))
+    );
+    assert_eq!(detect("a\n\tb\n\t\tc\n    d\n"), Some(IndentSize::tab()));
+    assert_eq!(
+        detect("/**\n * a\n */\nfn b() {\n    c();\n}\n"),
+        Some(IndentSize::spaces(4))
+    );
+    assert_eq!(detect("a\n\nb\n"), None);
+}
+
 #[gpui::test]
 fn test_language_scope_at_with_javascript(cx: &mut AppContext) {
     init_settings(cx, |_| {});
@@ -1949,6 +2166,8 @@ fn test_serialization(cx: &mut gpui::AppContext) {
 
         buffer.edit([(4..4, "F")], None, cx);
         assert_eq!(buffer.text(), "abcDF");
+
+        buffer.set_indent_size_override(Some(IndentSize::tab()), cx);
         buffer
     });
     assert_eq!(buffer1.read(cx).text(), "abcDF");
@@ -1969,6 +2188,10 @@ fn test_serialization(cx: &mut gpui::AppContext) {
         buffer
     });
     assert_eq!(buffer2.read(cx).text(), "abcDF");
+    assert_eq!(
+        buffer2.read(cx).indent_size_override(),
+        Some(IndentSize::tab())
+    );
 }
 
 #[gpui::test(iterations = 100)]
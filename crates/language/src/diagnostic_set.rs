@@ -1,6 +1,6 @@
 use crate::Diagnostic;
 use collections::HashMap;
-use lsp::LanguageServerId;
+use lsp::{DiagnosticSeverity, LanguageServerId};
 use std::{
     cmp::{Ordering, Reverse},
     iter,
@@ -49,6 +49,8 @@ pub struct Summary {
     min_start: Anchor,
     max_end: Anchor,
     count: usize,
+    error_count: usize,
+    warning_count: usize,
 }
 
 impl<T> DiagnosticEntry<T> {
@@ -105,6 +107,16 @@ impl DiagnosticSet {
         self.diagnostics.summary().count
     }
 
+    /// Returns the number of primary diagnostics in the set with an error severity.
+    pub fn error_count(&self) -> usize {
+        self.diagnostics.summary().error_count
+    }
+
+    /// Returns the number of primary diagnostics in the set with a warning severity.
+    pub fn warning_count(&self) -> usize {
+        self.diagnostics.summary().warning_count
+    }
+
     /// Returns an iterator over the diagnostic entries in the set.
     pub fn iter(&self) -> impl Iterator<Item = &DiagnosticEntry<Anchor>> {
         self.diagnostics.iter()
@@ -215,12 +227,17 @@ impl sum_tree::Item for DiagnosticEntry<Anchor> {
     type Summary = Summary;
 
     fn summary(&self) -> Self::Summary {
+        let primary_severity = |severity| {
+            (self.diagnostic.is_primary && self.diagnostic.severity == severity) as usize
+        };
         Summary {
             start: self.range.start,
             end: self.range.end,
             min_start: self.range.start,
             max_end: self.range.end,
             count: 1,
+            error_count: primary_severity(DiagnosticSeverity::ERROR),
+            warning_count: primary_severity(DiagnosticSeverity::WARNING),
         }
     }
 }
@@ -244,6 +261,8 @@ impl Default for Summary {
             min_start: Anchor::MAX,
             max_end: Anchor::MIN,
             count: 0,
+            error_count: 0,
+            warning_count: 0,
         }
     }
 }
@@ -261,5 +280,7 @@ impl sum_tree::Summary for Summary {
         self.start = other.start;
         self.end = other.end;
         self.count += other.count;
+        self.error_count += other.error_count;
+        self.warning_count += other.warning_count;
     }
 }
@@ -424,6 +424,27 @@ pub struct LanguageConfig {
     /// The name of a Prettier parser that should be used for this language.
     #[serde(default)]
     pub prettier_parser_name: Option<String>,
+    /// The shell command that runs a single test found by the runnables query, in which `$NAME`
+    /// is replaced by the quoted name of the test.
+    #[serde(default)]
+    pub test_command: Option<String>,
+    /// The shell command that runs a single test under a debugger, in which `$NAME` is replaced
+    /// by the quoted name of the test.
+    #[serde(default)]
+    pub debug_test_command: Option<String>,
+    /// When set, the names of tests are qualified by the module path of their file and the
+    /// scopes enclosing them, joined by this separator. A file's module path is its path within
+    /// the nearest `src` directory, where `lib`, `main` and `mod` files are their directory's
+    /// module.
+    #[serde(default)]
+    pub test_name_separator: Option<String>,
+    /// The program and arguments of the long-lived process that runs the code cells delimited by
+    /// `%%` comments, one after the other. The code of each cell is written to its standard input
+    /// followed by a line containing only the U+001E record separator, which the process prints
+    /// on a line of its own once it finished running the cell. The process should print its
+    /// errors to its standard output too, so that they are shown before that line.
+    #[serde(default)]
+    pub cell_command: Option<Vec<String>>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Default, JsonSchema)]
@@ -506,6 +527,10 @@ impl Default for LanguageConfig {
             word_characters: Default::default(),
             prettier_parser_name: None,
             collapsed_placeholder: Default::default(),
+            test_command: None,
+            debug_test_command: None,
+            test_name_separator: None,
+            cell_command: None,
         }
     }
 }
@@ -651,6 +676,8 @@ pub struct Grammar {
     pub(crate) highlights_query: Option<Query>,
     pub(crate) brackets_config: Option<BracketConfig>,
     pub(crate) redactions_config: Option<RedactionConfig>,
+    pub(crate) runnable_config: Option<RunnableConfig>,
+    pub(crate) text_object_config: Option<TextObjectConfig>,
     pub(crate) indents_config: Option<IndentConfig>,
     pub outline_config: Option<OutlineConfig>,
     pub embedding_config: Option<EmbeddingConfig>,
@@ -697,6 +724,21 @@ struct RedactionConfig {
     pub redaction_capture_ix: u32,
 }
 
+struct RunnableConfig {
+    pub query: Query,
+    pub run_capture_ix: u32,
+    pub name_capture_ix: u32,
+    pub scope_capture_ix: Option<u32>,
+    pub scope_name_capture_ix: Option<u32>,
+}
+
+pub(crate) struct TextObjectConfig {
+    pub query: Query,
+    /// The text object that each capture of the query is part of, and whether it captures the
+    /// whole object rather than its inside.
+    pub text_objects_by_capture_ix: HashMap<u32, (TextObject, bool)>,
+}
+
 struct OverrideConfig {
     query: Query,
     values: HashMap<u32, (String, LanguageConfigOverride)>,
@@ -742,6 +784,8 @@ impl Language {
                     injection_config: None,
                     override_config: None,
                     redactions_config: None,
+                    runnable_config: None,
+                    text_object_config: None,
                     error_query: Query::new(&ts_language, "(ERROR) @error").unwrap(),
                     ts_language,
                     highlight_map: Default::default(),
@@ -799,6 +843,16 @@ impl Language {
                 .with_redaction_query(query.as_ref())
                 .context("Error loading redaction query")?;
         }
+        if let Some(query) = queries.runnables {
+            self = self
+                .with_runnable_query(query.as_ref())
+                .context("Error loading runnables query")?;
+        }
+        if let Some(query) = queries.textobjects {
+            self = self
+                .with_text_object_query(query.as_ref())
+                .context("Error loading text objects query")?;
+        }
         Ok(self)
     }
 
@@ -1045,6 +1099,55 @@ impl Language {
         Ok(self)
     }
 
+    pub fn with_runnable_query(mut self, source: &str) -> anyhow::Result<Self> {
+        let grammar = self.grammar_mut();
+        let query = Query::new(&grammar.ts_language, source)?;
+        let mut run_capture_ix = None;
+        let mut name_capture_ix = None;
+        let mut scope_capture_ix = None;
+        let mut scope_name_capture_ix = None;
+        get_capture_indices(
+            &query,
+            &mut [
+                ("run", &mut run_capture_ix),
+                ("name", &mut name_capture_ix),
+                ("scope", &mut scope_capture_ix),
+                ("scope_name", &mut scope_name_capture_ix),
+            ],
+        );
+
+        if let Some((run_capture_ix, name_capture_ix)) = run_capture_ix.zip(name_capture_ix) {
+            grammar.runnable_config = Some(RunnableConfig {
+                query,
+                run_capture_ix,
+                name_capture_ix,
+                scope_capture_ix,
+                scope_name_capture_ix,
+            });
+        }
+
+        Ok(self)
+    }
+
+    /// Loads the query that finds the text objects of the language. Its captures are named after
+    /// the object they're part of, such as `@function.around` for a whole function and
+    /// `@function.inside` for its body.
+    pub fn with_text_object_query(mut self, source: &str) -> anyhow::Result<Self> {
+        let grammar = self.grammar_mut();
+        let query = Query::new(&grammar.ts_language, source)?;
+        let text_objects_by_capture_ix = query
+            .capture_names()
+            .iter()
+            .enumerate()
+            .filter_map(|(ix, name)| Some((ix as u32, TextObject::from_capture_name(name)?)))
+            .collect();
+        grammar.text_object_config = Some(TextObjectConfig {
+            query,
+            text_objects_by_capture_ix,
+        });
+        Ok(self)
+    }
+
     fn grammar_mut(&mut self) -> &mut Grammar {
         Arc::get_mut(self.grammar.as_mut().unwrap()).unwrap()
     }
@@ -1177,6 +1280,33 @@ impl Language {
     pub fn prettier_parser_name(&self) -> Option<&str> {
         self.config.prettier_parser_name.as_deref()
     }
+
+    /// Returns the name the given runnable in the file at the given path is run by, which is
+    /// qualified by its module path when the language has a `test_name_separator`.
+    pub fn test_name(&self, runnable: &RunnableRange, file_path: Option<&Path>) -> String {
+        let Some(separator) = self.config.test_name_separator.as_deref() else {
+            return runnable.name.clone();
+        };
+        let mut path = file_path.map(module_path).unwrap_or_default();
+        path.extend(runnable.scopes.iter().cloned());
+        path.push(runnable.name.clone());
+        path.join(separator)
+    }
+
+    /// Returns the shell command that runs the test with the given name, or debugs it.
+    pub fn test_command(&self, name: &str, debug: bool) -> Option<String> {
+        let command = if debug {
+            self.config.debug_test_command.as_ref()
+        } else {
+            self.config.test_command.as_ref()
+        };
+        let quoted_name = format!("'{}'", name.replace('\'', r"'\''"));
+        Some(command?.replace("$NAME", &quoted_name))
+    }
+
+    pub fn cell_command(&self) -> Option<&[String]> {
+        self.config.cell_command.as_deref()
+    }
 }
 
 impl LanguageScope {
@@ -1449,6 +1579,28 @@ fn get_capture_indices(query: &Query, captures: &mut [(&str, &mut Option<u32>)])
     }
 }
 
+/// Returns the module path of the file at the given path: its path within the nearest `src`
+/// directory without its extension, where `lib`, `main` and `mod` files are the module of their
+/// directory.
+fn module_path(file_path: &Path) -> Vec<String> {
+    let components = file_path
+        .with_extension("")
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    let Some(src_ix) = components.iter().rposition(|component| component == "src") else {
+        return Vec::new();
+    };
+    let mut path = components[src_ix + 1..].to_vec();
+    if matches!(
+        path.last().map(String::as_str),
+        Some("lib" | "main" | "mod")
+    ) {
+        path.pop();
+    }
+    path
+}
+
 pub fn point_to_lsp(point: PointUtf16) -> lsp::Position {
     lsp::Position::new(point.row, point.column)
 }
@@ -1577,4 +1729,31 @@ mod tests {
         // Loading an unknown language returns an error.
         assert!(languages.language_for_name("Unknown").await.is_err());
     }
+
+    #[test]
+    fn test_test_name() {
+        let language = Language::new(
+            LanguageConfig {
+                test_command: Some("cargo test -- --exact $NAME".into()),
+                test_name_separator: Some("::".into()),
+                ..Default::default()
+            },
+            None,
+        );
+        let runnable = RunnableRange {
+            run_range: 0..0,
+            name: "one".into(),
+            scopes: vec!["tests".into()],
+        };
+
+        let name = |path: &str| language.test_name(&runnable, Some(Path::new(path)));
+        assert_eq!(name("crates/a/src/lib.rs"), "tests::one");
+        assert_eq!(name("crates/a/src/foo/mod.rs"), "foo::tests::one");
+        assert_eq!(name("crates/a/src/foo/bar.rs"), "foo::bar::tests::one");
+        assert_eq!(name("crates/a/tests/integration.rs"), "tests::one");
+        assert_eq!(
+            language.test_command("it's::one", false).unwrap(),
+            r"cargo test -- --exact 'it'\''s::one'"
+        );
+    }
 }
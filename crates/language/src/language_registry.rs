@@ -95,6 +95,8 @@ pub const QUERY_FILENAME_PREFIXES: &[(
     ("injections", |q| &mut q.injections),
     ("overrides", |q| &mut q.overrides),
     ("redactions", |q| &mut q.redactions),
+    ("runnables", |q| &mut q.runnables),
+    ("textobjects", |q| &mut q.textobjects),
 ];
 
 /// Tree-sitter language queries for a given language.
@@ -108,6 +110,8 @@ pub struct LanguageQueries {
     pub injections: Option<Cow<'static, str>>,
     pub overrides: Option<Cow<'static, str>>,
     pub redactions: Option<Cow<'static, str>>,
+    pub runnables: Option<Cow<'static, str>>,
+    pub textobjects: Option<Cow<'static, str>>,
 }
 
 #[derive(Clone, Default)]
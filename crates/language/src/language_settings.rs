@@ -4,7 +4,7 @@ use crate::{File, Language};
 use anyhow::Result;
 use collections::{HashMap, HashSet};
 use globset::GlobMatcher;
-use gpui::AppContext;
+use gpui::{AppContext, FontFeatures, Rgba};
 use schemars::{
     schema::{InstanceType, ObjectValidation, Schema, SchemaObject},
     JsonSchema,
@@ -54,6 +54,9 @@ pub struct LanguageSettings {
     /// Whether to indent lines using tab characters, as opposed to multiple
     /// spaces.
     pub hard_tabs: bool,
+    /// Whether to detect the indentation of files from their contents when they are opened, and
+    /// use it instead of `tab_size` and `hard_tabs`.
+    pub detect_indentation: bool,
     /// How to soft-wrap long lines of text.
     pub soft_wrap: SoftWrap,
     /// The column at which to soft-wrap lines, for buffers where soft-wrap
@@ -65,7 +68,9 @@ pub struct LanguageSettings {
     /// by the 'wrap_guides' setting.
     pub show_wrap_guides: bool,
     /// Character counts at which to show wrap guides in the editor.
-    pub wrap_guides: Vec<usize>,
+    pub wrap_guides: Vec<WrapGuide>,
+    /// Whether to show vertical guides at each level of indentation in the editor.
+    pub show_indent_guides: bool,
     /// Whether or not to perform a buffer format before saving.
     pub format_on_save: FormatOnSave,
     /// Whether or not to remove any trailing whitespace from lines of a buffer
@@ -87,12 +92,32 @@ pub struct LanguageSettings {
     pub show_copilot_suggestions: bool,
     /// Whether to show tabs and spaces in the editor.
     pub show_whitespaces: ShowWhitespaceSetting,
+    /// The symbols used to draw whitespace characters.
+    pub whitespace_map: WhitespaceMap,
+    /// Which lines to highlight trailing whitespace on.
+    pub highlight_trailing_whitespace: TrailingWhitespaceHighlight,
     /// Whether to start a new line with a comment when a previous line is a comment as well.
     pub extend_comment_on_newline: bool,
     /// Inlay hint related settings.
     pub inlay_hints: InlayHintSettings,
+    /// Settings for rendering buffers as prose.
+    pub prose: ProseSettings,
+    /// The font family to use in editors instead of the buffer font, if any.
+    pub font_family: Option<String>,
+    /// The font features to use in editors instead of the buffer font features, if any.
+    pub font_features: Option<FontFeatures>,
+    /// The font size to use in editors instead of the buffer font size, if any.
+    pub font_size: Option<f32>,
+    /// The line height to use in editors instead of the buffer line height, if any.
+    pub line_height: Option<f32>,
+    /// The number of empty rows to leave after each paragraph.
+    pub paragraph_spacing: u32,
     /// Whether to automatically close brackets.
     pub use_autoclose: bool,
+    /// Whether to surround selections with brackets and quotes typed over them.
+    pub use_auto_surround: bool,
+    /// Whether to re-indent pasted text to match the indentation at the destination.
+    pub auto_indent_on_paste: bool,
     /// Which code actions to run on save
     pub code_actions_on_format: HashMap<String, bool>,
 }
@@ -137,6 +162,12 @@ pub struct LanguageSettingsContent {
     /// Default: false
     #[serde(default)]
     pub hard_tabs: Option<bool>,
+    /// Whether to detect the indentation of files from their contents when they
+    /// are opened, and use it instead of `tab_size` and `hard_tabs`.
+    ///
+    /// Default: true
+    #[serde(default)]
+    pub detect_indentation: Option<bool>,
     /// How to soft-wrap long lines of text.
     ///
     /// Default: none
@@ -156,11 +187,18 @@ pub struct LanguageSettingsContent {
     /// Default: true
     #[serde(default)]
     pub show_wrap_guides: Option<bool>,
-    /// Character counts at which to show wrap guides in the editor.
+    /// Character counts at which to show wrap guides in the editor. Each guide
+    /// is either a column, or an object with a column and its own color and
+    /// style.
     ///
     /// Default: []
     #[serde(default)]
-    pub wrap_guides: Option<Vec<usize>>,
+    pub wrap_guides: Option<Vec<WrapGuide>>,
+    /// Whether to show vertical guides at each level of indentation in the editor.
+    ///
+    /// Default: true
+    #[serde(default)]
+    pub show_indent_guides: Option<bool>,
     /// Whether or not to perform a buffer format before saving.
     ///
     /// Default: on
@@ -204,6 +242,15 @@ pub struct LanguageSettingsContent {
     /// Whether to show tabs and spaces in the editor.
     #[serde(default)]
     pub show_whitespaces: Option<ShowWhitespaceSetting>,
+    /// The symbols used to draw tabs, spaces and non-breaking spaces when
+    /// whitespace is shown.
+    #[serde(default)]
+    pub whitespace_map: Option<WhitespaceMap>,
+    /// Which lines to highlight trailing whitespace on.
+    ///
+    /// Default: none
+    #[serde(default)]
+    pub highlight_trailing_whitespace: Option<TrailingWhitespaceHighlight>,
     /// Whether to start a new line with a comment when a previous line is a comment as well.
     ///
     /// Default: true
@@ -212,11 +259,54 @@ pub struct LanguageSettingsContent {
     /// Inlay hint related settings.
     #[serde(default)]
     pub inlay_hints: Option<InlayHintSettings>,
+    /// Settings for rendering buffers as prose, with more generous line
+    /// spacing and a comfortable line length.
+    #[serde(default)]
+    pub prose: Option<ProseSettings>,
+    /// The font family to use when editing files of this language, overriding
+    /// the `buffer_font_family` setting.
+    ///
+    /// Default: null
+    #[serde(default)]
+    pub font_family: Option<String>,
+    /// The OpenType features to enable when editing files of this language,
+    /// overriding the `buffer_font_features` setting.
+    ///
+    /// Default: null
+    #[serde(default)]
+    pub font_features: Option<FontFeatures>,
+    /// The font size (in pixels) to use when editing files of this language,
+    /// overriding the `buffer_font_size` setting.
+    ///
+    /// Default: null
+    #[serde(default)]
+    pub font_size: Option<f32>,
+    /// The line height to use when editing files of this language, as a
+    /// multiple of the font size, overriding the `buffer_line_height` setting.
+    ///
+    /// Default: null
+    #[serde(default)]
+    pub line_height: Option<f32>,
+    /// The number of empty rows to leave after each paragraph when editing
+    /// files of this language. Paragraphs are runs of non-blank lines.
+    ///
+    /// Default: 0
+    pub paragraph_spacing: Option<u32>,
     /// Whether to automatically type closing characters for you. For example,
     /// when you type (, Zed will automatically add a closing ) at the correct position.
     ///
     /// Default: true
     pub use_autoclose: Option<bool>,
+    /// Whether to surround the selected text with a bracket or a quote when typing
+    /// its opening character, instead of replacing the selection.
+    ///
+    /// Default: true
+    pub use_auto_surround: Option<bool>,
+    /// Whether to adjust the indentation of pasted text to match the indentation
+    /// of the lines it is pasted into.
+    ///
+    /// Default: true
+    pub auto_indent_on_paste: Option<bool>,
 
     /// Which code actions to run on save
     ///
@@ -271,6 +361,63 @@ pub enum FormatOnSave {
     },
 }
 
+/// A vertical line drawn at a column of the editor.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(untagged)]
+pub enum WrapGuide {
+    /// A solid guide at the given column, in the theme's wrap guide color.
+    Column(usize),
+    /// A guide at the given column with its own color and style.
+    Styled {
+        /// The column at which to draw the guide.
+        column: usize,
+        /// The color of the guide, as a hex string such as "#ff000080". Guides
+        /// without a color use the theme's wrap guide color.
+        #[serde(default)]
+        #[schemars(with = "Option<String>")]
+        color: Option<Rgba>,
+        /// How to draw the guide.
+        #[serde(default)]
+        style: WrapGuideStyle,
+    },
+}
+
+impl WrapGuide {
+    /// The column at which to draw the guide.
+    pub fn column(&self) -> usize {
+        match self {
+            WrapGuide::Column(column) | WrapGuide::Styled { column, .. } => *column,
+        }
+    }
+
+    /// The color of the guide, if it overrides the theme's.
+    pub fn color(&self) -> Option<Rgba> {
+        match self {
+            WrapGuide::Column(_) => None,
+            WrapGuide::Styled { color, .. } => *color,
+        }
+    }
+
+    /// How to draw the guide.
+    pub fn style(&self) -> WrapGuideStyle {
+        match self {
+            WrapGuide::Column(_) => WrapGuideStyle::Solid,
+            WrapGuide::Styled { style, .. } => *style,
+        }
+    }
+}
+
+/// How to draw a wrap guide.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WrapGuideStyle {
+    /// Draw a continuous line.
+    #[default]
+    Solid,
+    /// Draw a line broken into short dashes.
+    Dashed,
+}
+
 /// Controls how whitespace should be displayedin the editor.
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -281,6 +428,54 @@ pub enum ShowWhitespaceSetting {
     None,
     /// Draw all invisible symbols.
     All,
+    /// Draw only the whitespace at the start and at the end of lines.
+    Boundary,
+}
+
+/// The symbols used to draw whitespace characters.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct WhitespaceMap {
+    /// The symbol used to draw tabs.
+    ///
+    /// Default: "→"
+    #[serde(default = "default_tab_symbol")]
+    pub tab: String,
+    /// The symbol used to draw spaces.
+    ///
+    /// Default: "•"
+    #[serde(default = "default_space_symbol")]
+    pub space: String,
+    /// The symbol used to draw non-breaking spaces, which are always drawn
+    /// since they are easily mistaken for regular spaces.
+    ///
+    /// Default: "·"
+    #[serde(default = "default_nbsp_symbol")]
+    pub nbsp: String,
+}
+
+fn default_tab_symbol() -> String {
+    "→".into()
+}
+
+fn default_space_symbol() -> String {
+    "•".into()
+}
+
+fn default_nbsp_symbol() -> String {
+    "·".into()
+}
+
+/// Controls which lines trailing whitespace is highlighted on.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TrailingWhitespaceHighlight {
+    /// Do not highlight trailing whitespace.
+    None,
+    /// Highlight trailing whitespace on lines without a cursor or selection, so
+    /// that it isn't highlighted while typing.
+    InactiveLines,
+    /// Highlight trailing whitespace on all lines.
+    All,
 }
 
 /// Controls which formatter should be used when formatting code.
@@ -329,10 +524,44 @@ pub struct InlayHintSettings {
     pub show_other_hints: bool,
 }
 
+/// The settings for rendering buffers as prose.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct ProseSettings {
+    /// Whether to render buffers as prose.
+    ///
+    /// Default: false
+    #[serde(default)]
+    pub enabled: bool,
+    /// The line height to use, as a multiple of the buffer font size.
+    ///
+    /// Default: 1.8
+    #[serde(default = "default_prose_line_height")]
+    pub line_height: f32,
+    /// Whether to leave at least one empty row after each paragraph, even
+    /// when the language's `paragraph_spacing` is 0.
+    ///
+    /// Default: false
+    #[serde(default)]
+    pub paragraph_spacing: bool,
+    /// The column at which to soft-wrap lines, unless the editor is narrower.
+    ///
+    /// Default: 72
+    #[serde(default = "default_prose_measure")]
+    pub measure: u32,
+}
+
 fn default_true() -> bool {
     true
 }
 
+fn default_prose_line_height() -> f32 {
+    1.8
+}
+
+fn default_prose_measure() -> u32 {
+    72
+}
+
 impl InlayHintSettings {
     /// Returns the kinds of inlay hints that are enabled based on the settings.
     pub fn enabled_inlay_hint_kinds(&self) -> HashSet<Option<InlayHintKind>> {
@@ -553,10 +782,14 @@ impl settings::Settings for AllLanguageSettings {
 fn merge_settings(settings: &mut LanguageSettings, src: &LanguageSettingsContent) {
     merge(&mut settings.tab_size, src.tab_size);
     merge(&mut settings.hard_tabs, src.hard_tabs);
+    merge(&mut settings.detect_indentation, src.detect_indentation);
     merge(&mut settings.soft_wrap, src.soft_wrap);
     merge(&mut settings.use_autoclose, src.use_autoclose);
+    merge(&mut settings.use_auto_surround, src.use_auto_surround);
+    merge(&mut settings.auto_indent_on_paste, src.auto_indent_on_paste);
     merge(&mut settings.show_wrap_guides, src.show_wrap_guides);
     merge(&mut settings.wrap_guides, src.wrap_guides.clone());
+    merge(&mut settings.show_indent_guides, src.show_indent_guides);
     merge(
         &mut settings.code_actions_on_format,
         src.code_actions_on_format.clone(),
@@ -586,11 +819,22 @@ fn merge_settings(settings: &mut LanguageSettings, src: &LanguageSettingsContent
         src.show_copilot_suggestions,
     );
     merge(&mut settings.show_whitespaces, src.show_whitespaces);
+    merge(&mut settings.whitespace_map, src.whitespace_map.clone());
+    merge(
+        &mut settings.highlight_trailing_whitespace,
+        src.highlight_trailing_whitespace,
+    );
     merge(
         &mut settings.extend_comment_on_newline,
         src.extend_comment_on_newline,
     );
     merge(&mut settings.inlay_hints, src.inlay_hints);
+    merge(&mut settings.prose, src.prose);
+    merge(&mut settings.font_family, src.font_family.clone().map(Some));
+    merge(&mut settings.font_features, src.font_features.map(Some));
+    merge(&mut settings.font_size, src.font_size.map(Some));
+    merge(&mut settings.line_height, src.line_height.map(Some));
+    merge(&mut settings.paragraph_spacing, src.paragraph_spacing);
     fn merge<T>(target: &mut T, value: Option<T>) {
         if let Some(value) = value {
             *target = value;
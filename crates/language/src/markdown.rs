@@ -18,6 +18,8 @@ pub struct ParsedMarkdown {
     pub region_ranges: Vec<Range<usize>>,
     /// The regions of the Markdown document.
     pub regions: Vec<ParsedRegion>,
+    /// The images embedded in the Markdown document, in the order they appear in.
+    pub images: Vec<Link>,
 }
 
 /// A run of highlighted Markdown text.
@@ -80,7 +82,7 @@ pub struct ParsedRegion {
 }
 
 /// A Markdown link.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Link {
     /// A link to a webpage.
     Web {
@@ -119,6 +121,7 @@ pub async fn parse_markdown(
     let mut highlights = Vec::new();
     let mut region_ranges = Vec::new();
     let mut regions = Vec::new();
+    let mut images = Vec::new();
 
     parse_markdown_block(
         markdown,
@@ -128,6 +131,7 @@ pub async fn parse_markdown(
         &mut highlights,
         &mut region_ranges,
         &mut regions,
+        &mut images,
     )
     .await;
 
@@ -136,6 +140,7 @@ pub async fn parse_markdown(
         highlights,
         region_ranges,
         regions,
+        images,
     }
 }
 
@@ -148,6 +153,7 @@ pub async fn parse_markdown_block(
     highlights: &mut Vec<(Range<usize>, MarkdownHighlight)>,
     region_ranges: &mut Vec<Range<usize>>,
     regions: &mut Vec<ParsedRegion>,
+    images: &mut Vec<Link>,
 ) {
     let mut bold_depth = 0;
     let mut italic_depth = 0;
@@ -244,6 +250,14 @@ pub async fn parse_markdown_block(
 
                 Tag::Link(_, url, _) => link_url = Some(url.to_string()),
 
+                // The description of an image links to it, and the image is shown below the text.
+                Tag::Image(_, url, _) => {
+                    if let Some(link) = Link::identify(url.to_string()) {
+                        images.push(link);
+                    }
+                    link_url = Some(url.to_string());
+                }
+
                 Tag::List(number) => {
                     list_stack.push((number, false));
                 }
@@ -276,7 +290,7 @@ pub async fn parse_markdown_block(
                 Tag::CodeBlock(_) => current_language = None,
                 Tag::Emphasis => italic_depth -= 1,
                 Tag::Strong => bold_depth -= 1,
-                Tag::Link(_, _, _) => link_url = None,
+                Tag::Link(_, _, _) | Tag::Image(_, _, _) => link_url = None,
                 Tag::List(_) => drop(list_stack.pop()),
                 _ => {}
             },
@@ -2,7 +2,7 @@
 
 use crate::{
     diagnostic_set::DiagnosticEntry, CodeAction, CodeLabel, Completion, CursorShape, Diagnostic,
-    Language,
+    IndentKind, IndentSize, Language,
 };
 use anyhow::{anyhow, Result};
 use clock::ReplicaId;
@@ -99,6 +99,15 @@ pub fn serialize_operation(operation: &crate::Operation) -> proto::Operation {
                     triggers: triggers.clone(),
                 },
             ),
+
+            crate::Operation::UpdateIndentSize {
+                indent_size,
+                lamport_timestamp,
+            } => proto::operation::Variant::UpdateIndentSize(proto::operation::UpdateIndentSize {
+                replica_id: lamport_timestamp.replica_id as u32,
+                lamport_timestamp: lamport_timestamp.value,
+                indent_size: indent_size.as_ref().map(serialize_indent_size),
+            }),
         }),
     }
 }
@@ -203,6 +212,31 @@ pub fn deserialize_cursor_shape(cursor_shape: proto::CursorShape) -> CursorShape
     }
 }
 
+/// Serializes an [`IndentSize`] to be sent over RPC.
+pub fn serialize_indent_size(indent_size: &IndentSize) -> proto::IndentSize {
+    let kind = match indent_size.kind {
+        IndentKind::Space => proto::IndentKind::IndentSpace,
+        IndentKind::Tab => proto::IndentKind::IndentTab,
+    };
+    proto::IndentSize {
+        len: indent_size.len,
+        kind: kind as i32,
+    }
+}
+
+/// Deserializes an [`IndentSize`] from the RPC representation.
+pub fn deserialize_indent_size(indent_size: proto::IndentSize) -> Result<IndentSize> {
+    let kind = match proto::IndentKind::from_i32(indent_size.kind) {
+        Some(proto::IndentKind::IndentSpace) => IndentKind::Space,
+        Some(proto::IndentKind::IndentTab) => IndentKind::Tab,
+        None => return Err(anyhow!("Unknown indent kind")),
+    };
+    Ok(IndentSize {
+        len: indent_size.len,
+        kind,
+    })
+}
+
 /// Serializes a list of diagnostics to be sent over RPC.
 pub fn serialize_diagnostics<'a>(
     diagnostics: impl IntoIterator<Item = &'a DiagnosticEntry<Anchor>>,
@@ -325,6 +359,18 @@ pub fn deserialize_operation(message: proto::Operation) -> Result<crate::Operati
                     },
                 }
             }
+            proto::operation::Variant::UpdateIndentSize(message) => {
+                crate::Operation::UpdateIndentSize {
+                    indent_size: message
+                        .indent_size
+                        .map(deserialize_indent_size)
+                        .transpose()?,
+                    lamport_timestamp: clock::Lamport {
+                        replica_id: message.replica_id as ReplicaId,
+                        value: message.lamport_timestamp,
+                    },
+                }
+            }
         },
     )
 }
@@ -464,6 +510,10 @@ pub fn lamport_timestamp_for_operation(operation: &proto::Operation) -> Option<c
             replica_id = op.replica_id;
             value = op.lamport_timestamp;
         }
+        proto::operation::Variant::UpdateIndentSize(op) => {
+            replica_id = op.replica_id;
+            value = op.lamport_timestamp;
+        }
     }
 
     Some(clock::Lamport {
@@ -696,6 +696,7 @@ impl SearchableItem for LspLogView {
             regex: true,
             // LSP log is read-only.
             replacement: false,
+            excerpt: false,
         }
     }
     fn active_match_index(
@@ -586,6 +586,9 @@ impl LanguageServer {
                     on_type_formatting: Some(DynamicRegistrationClientCapabilities {
                         dynamic_registration: None,
                     }),
+                    code_lens: Some(DynamicRegistrationClientCapabilities {
+                        dynamic_registration: None,
+                    }),
                     diagnostic: Some(DiagnosticClientCapabilities {
                         related_document_support: Some(true),
                         dynamic_registration: None,
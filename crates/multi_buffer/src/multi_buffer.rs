@@ -13,7 +13,7 @@ use language::{
     language_settings::{language_settings, LanguageSettings},
     AutoindentMode, Buffer, BufferChunks, BufferSnapshot, Capability, CharKind, Chunk, CursorShape,
     DiagnosticEntry, File, IndentSize, Language, LanguageScope, OffsetRangeExt, OffsetUtf16,
-    Outline, OutlineItem, Point, PointUtf16, Selection, TextDimension, ToOffset as _,
+    Outline, OutlineItem, Point, PointUtf16, Selection, TextDimension, TextObject, ToOffset as _,
     ToOffsetUtf16 as _, ToPoint as _, ToPointUtf16 as _, TransactionId, Unclipped,
 };
 use std::{
@@ -78,6 +78,13 @@ pub enum Event {
     ExcerptsEdited {
         ids: Vec<ExcerptId>,
     },
+    ExcerptsExpanded {
+        ids: Vec<ExcerptId>,
+    },
+    ExcerptMoved {
+        id: ExcerptId,
+        predecessor: ExcerptId,
+    },
     Edited {
         singleton_buffer_edited: bool,
     },
@@ -86,6 +93,7 @@ pub enum Event {
     },
     Reloaded,
     DiffBaseChanged,
+    DiffUpdated,
     LanguageChanged,
     CapabilityChanged,
     Reparsed,
@@ -138,6 +146,7 @@ struct BufferState {
     last_diagnostics_update_count: usize,
     last_file_update_count: usize,
     last_git_diff_update_count: usize,
+    last_indent_size_update_count: usize,
     excerpts: Vec<Locator>,
     _subscriptions: [gpui::Subscription; 2],
 }
@@ -208,6 +217,24 @@ pub struct ExcerptRange<T> {
     pub primary: Option<Range<T>>,
 }
 
+/// The direction in which [`MultiBuffer::expand_excerpts`] grows an excerpt's context.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExpandExcerptDirection {
+    Up,
+    Down,
+    UpAndDown,
+}
+
+impl ExpandExcerptDirection {
+    pub fn should_expand_up(&self) -> bool {
+        matches!(self, Self::Up | Self::UpAndDown)
+    }
+
+    pub fn should_expand_down(&self) -> bool {
+        matches!(self, Self::Down | Self::UpAndDown)
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 struct ExcerptSummary {
     excerpt_id: ExcerptId,
@@ -215,9 +242,14 @@ struct ExcerptSummary {
     excerpt_locator: Locator,
     /// The maximum row of the [`Excerpt`]s being summarized
     max_buffer_row: u32,
+    /// The number of [`Excerpt`]s being summarized
+    count: usize,
     text: TextSummary,
 }
 
+#[derive(Copy, Clone, Debug, Default)]
+struct ExcerptCount(usize);
+
 #[derive(Clone)]
 pub struct MultiBufferRows<'a> {
     buffer_row_range: Range<u32>,
@@ -288,6 +320,7 @@ impl MultiBuffer {
                     last_diagnostics_update_count: buffer_state.last_diagnostics_update_count,
                     last_file_update_count: buffer_state.last_file_update_count,
                     last_git_diff_update_count: buffer_state.last_git_diff_update_count,
+                    last_indent_size_update_count: buffer_state.last_indent_size_update_count,
                     excerpts: buffer_state.excerpts.clone(),
                     _subscriptions: [
                         new_cx.observe(&buffer_state.buffer, |_, _, cx| cx.notify()),
@@ -709,6 +742,18 @@ impl MultiBuffer {
         }
     }
 
+    /// Returns whether the given transaction is on the undo stack, i.e. it hasn't been undone.
+    pub fn is_transaction_undoable(&self, transaction_id: TransactionId, cx: &AppContext) -> bool {
+        if let Some(buffer) = self.as_singleton() {
+            buffer.read(cx).is_transaction_undoable(transaction_id)
+        } else {
+            self.history
+                .undo_stack
+                .iter()
+                .any(|transaction| transaction.id == transaction_id)
+        }
+    }
+
     pub fn finalize_last_transaction(&mut self, cx: &mut ModelContext<Self>) {
         self.history.finalize_last_transaction();
         for BufferState { buffer, .. } in self.buffers.borrow().values() {
@@ -886,6 +931,41 @@ impl MultiBuffer {
         None
     }
 
+    /// Recomputes the indentation of the lines in the given ranges from the indentation rules of
+    /// their languages.
+    pub fn autoindent_ranges(&mut self, ranges: &[Range<usize>], cx: &mut ModelContext<Self>) {
+        for (buffer, ranges) in self.buffer_ranges_by_buffer(ranges, cx) {
+            buffer.update(cx, |buffer, cx| buffer.autoindent_ranges(ranges, cx));
+        }
+    }
+
+    /// Undoes, in each buffer, the edits of the most recent transaction that touch any of the
+    /// given ranges, leaving the edits elsewhere applied.
+    pub fn undo_in_ranges(
+        &mut self,
+        ranges: &[Range<usize>],
+        cx: &mut ModelContext<Self>,
+    ) -> Option<TransactionId> {
+        let mut transaction_id = None;
+        let mut buffer_transactions = HashMap::default();
+        for (buffer, ranges) in self.buffer_ranges_by_buffer(ranges, cx) {
+            buffer.update(cx, |buffer, cx| {
+                let undone = buffer.undo_in_ranges(&ranges, cx)?;
+                transaction_id = transaction_id.or(Some(undone));
+                let redo_entry = buffer.peek_redo_stack()?;
+                buffer_transactions.insert(buffer.remote_id(), redo_entry.transaction_id());
+                Some(())
+            });
+        }
+
+        // The undone edits are put on each buffer's redo stack, so in a multi-buffer they're
+        // recorded as a transaction of its own history for them to be redone from it.
+        if !self.is_singleton() && !buffer_transactions.is_empty() {
+            transaction_id = Some(self.history.push_redo(buffer_transactions, Instant::now()));
+        }
+        transaction_id
+    }
+
     pub fn undo_transaction(&mut self, transaction_id: TransactionId, cx: &mut ModelContext<Self>) {
         if let Some(buffer) = self.as_singleton() {
             buffer.update(cx, |buffer, cx| buffer.undo_transaction(transaction_id, cx));
@@ -1067,6 +1147,7 @@ impl MultiBuffer {
             last_diagnostics_update_count: buffer_snapshot.diagnostics_update_count(),
             last_file_update_count: buffer_snapshot.file_update_count(),
             last_git_diff_update_count: buffer_snapshot.git_diff_update_count(),
+            last_indent_size_update_count: buffer.read(cx).indent_size_update_count(),
             excerpts: Default::default(),
             _subscriptions: [
                 cx.observe(&buffer, |_, _, cx| cx.notify()),
@@ -1263,6 +1344,26 @@ impl MultiBuffer {
         })
     }
 
+    /// Groups the ranges of the buffers that the given ranges span by buffer.
+    pub fn buffer_ranges_by_buffer(
+        &self,
+        ranges: &[Range<usize>],
+        cx: &AppContext,
+    ) -> Vec<(Model<Buffer>, Vec<Range<usize>>)> {
+        let mut ranges_by_buffer =
+            HashMap::<BufferId, (Model<Buffer>, Vec<Range<usize>>)>::default();
+        for range in ranges {
+            for (buffer, range, _) in self.range_to_buffer_ranges(range.clone(), cx) {
+                ranges_by_buffer
+                    .entry(buffer.read(cx).remote_id())
+                    .or_insert_with(|| (buffer, Vec::new()))
+                    .1
+                    .push(range);
+            }
+        }
+        ranges_by_buffer.into_values().collect()
+    }
+
     pub fn range_to_buffer_ranges<T: ToOffset>(
         &self,
         range: Range<T>,
@@ -1388,6 +1489,212 @@ impl MultiBuffer {
         cx.notify();
     }
 
+    /// Grows the context range of the given excerpts by `line_count` lines in the given
+    /// direction, keeping their ids so that anchors into them remain valid.
+    pub fn expand_excerpts(
+        &mut self,
+        excerpt_ids: impl IntoIterator<Item = ExcerptId>,
+        line_count: u32,
+        direction: ExpandExcerptDirection,
+        cx: &mut ModelContext<Self>,
+    ) {
+        if line_count == 0 {
+            return;
+        }
+        self.sync(cx);
+
+        let snapshot = self.snapshot.borrow();
+        let contexts = excerpt_ids
+            .into_iter()
+            .filter_map(|id| {
+                let excerpt = snapshot.excerpt(id)?;
+                let buffer = &excerpt.buffer;
+                let mut start = excerpt.range.context.start.to_point(buffer);
+                if direction.should_expand_up() {
+                    start = Point::new(start.row.saturating_sub(line_count), 0);
+                }
+                let mut end = excerpt.range.context.end.to_point(buffer);
+                if direction.should_expand_down() {
+                    let row = end
+                        .row
+                        .saturating_add(line_count)
+                        .min(buffer.max_point().row);
+                    end = Point::new(row, buffer.line_len(row));
+                }
+                Some((id, buffer.anchor_before(start)..buffer.anchor_after(end)))
+            })
+            .collect::<Vec<_>>();
+        drop(snapshot);
+        self.set_excerpt_contexts(contexts, cx);
+    }
+
+    /// Replaces the context range of the given excerpts, keeping their ids and primary ranges, as
+    /// when they are expanded.
+    pub fn set_excerpt_contexts(
+        &mut self,
+        contexts: impl IntoIterator<Item = (ExcerptId, Range<text::Anchor>)>,
+        cx: &mut ModelContext<Self>,
+    ) {
+        self.sync(cx);
+
+        let mut snapshot = self.snapshot.borrow_mut();
+        let mut contexts = contexts
+            .into_iter()
+            .filter(|(id, _)| *id != ExcerptId::min() && *id != ExcerptId::max())
+            .filter(|(id, _)| snapshot.excerpt(*id).is_some())
+            .map(|(id, context)| (snapshot.excerpt_locator_for_id(id).clone(), id, context))
+            .collect::<Vec<_>>();
+        if contexts.is_empty() {
+            return;
+        }
+        contexts.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+        contexts.dedup_by(|(a, _, _), (b, _, _)| a == b);
+
+        let mut ids = Vec::new();
+        let mut new_excerpts = SumTree::new();
+        let mut cursor = snapshot.excerpts.cursor::<(Option<&Locator>, usize)>();
+        let mut edits = Vec::<Edit<usize>>::new();
+        for (locator, id, context) in contexts {
+            new_excerpts.append(cursor.slice(&Some(&locator), Bias::Left, &()), &());
+            let Some(excerpt) = cursor.item() else {
+                break;
+            };
+            let range = ExcerptRange {
+                context,
+                primary: excerpt.range.primary.clone(),
+            };
+            let new_excerpt = Excerpt::new(
+                excerpt.id,
+                excerpt.locator.clone(),
+                excerpt.buffer_id,
+                excerpt.buffer.clone(),
+                range,
+                excerpt.has_trailing_newline,
+            );
+
+            let old_start = cursor.start().1;
+            let new_start = new_excerpts.summary().text.len;
+            edits.push(Edit {
+                old: old_start..old_start + excerpt.text_summary.len,
+                new: new_start..new_start + new_excerpt.text_summary.len,
+            });
+            new_excerpts.push(new_excerpt, &());
+            ids.push(id);
+            cursor.next(&());
+        }
+        new_excerpts.append(cursor.suffix(&()), &());
+        drop(cursor);
+        snapshot.excerpts = new_excerpts;
+        drop(snapshot);
+
+        self.subscriptions.publish_mut(edits);
+        cx.emit(Event::Edited {
+            singleton_buffer_edited: false,
+        });
+        cx.emit(Event::ExcerptsExpanded { ids });
+        cx.notify();
+    }
+
+    /// Moves an excerpt so that it follows `prev_excerpt_id`, or to the start of the multibuffer
+    /// when `prev_excerpt_id` is [`ExcerptId::min`], returning whether it was moved.
+    ///
+    /// The excerpt keeps its id, so that anchors into it remain valid.
+    pub fn move_excerpt(
+        &mut self,
+        excerpt_id: ExcerptId,
+        prev_excerpt_id: ExcerptId,
+        cx: &mut ModelContext<Self>,
+    ) -> bool {
+        if excerpt_id == prev_excerpt_id {
+            return false;
+        }
+        self.sync(cx);
+
+        let mut buffers = self.buffers.borrow_mut();
+        let mut snapshot = self.snapshot.borrow_mut();
+        if snapshot.excerpt(excerpt_id).is_none()
+            || (prev_excerpt_id != ExcerptId::min() && snapshot.excerpt(prev_excerpt_id).is_none())
+        {
+            return false;
+        }
+
+        // Take the excerpt out of the multibuffer.
+        let old_locator = snapshot.excerpt_locator_for_id(excerpt_id).clone();
+        let mut cursor = snapshot.excerpts.cursor::<(Option<&Locator>, usize)>();
+        let mut remaining_excerpts = cursor.slice(&Some(&old_locator), Bias::Left, &());
+        let Some(mut excerpt) = cursor.item().cloned() else {
+            return false;
+        };
+        let old_range = cursor.start().1..cursor.end(&()).1;
+        cursor.next(&());
+        let was_last = cursor.item().is_none();
+        if was_last {
+            remaining_excerpts.update_last(|e| e.has_trailing_newline = false, &());
+        }
+        remaining_excerpts.append(cursor.suffix(&()), &());
+        drop(cursor);
+
+        // Insert it again after its new predecessor, with a locator ordering it there.
+        let prev_locator = snapshot.excerpt_locator_for_id(prev_excerpt_id).clone();
+        let mut cursor = remaining_excerpts.cursor::<(Option<&Locator>, usize)>();
+        let mut new_excerpts = cursor.slice(&Some(&prev_locator), Bias::Right, &());
+        let prev_locator = cursor.start().0.unwrap_or(Locator::min_ref()).clone();
+        let next_locator = cursor
+            .item()
+            .map_or(Locator::max(), |excerpt| excerpt.locator.clone());
+        let insertion_offset = cursor.start().1;
+        let is_last = cursor.item().is_none();
+        new_excerpts.update_last(|e| e.has_trailing_newline = true, &());
+        excerpt.locator = Locator::between(&prev_locator, &next_locator);
+        excerpt.has_trailing_newline = !is_last;
+        new_excerpts.push(excerpt.clone(), &());
+        new_excerpts.append(cursor.suffix(&()), &());
+        drop(cursor);
+
+        snapshot.excerpts = new_excerpts;
+        snapshot.excerpt_ids.insert_or_replace(
+            ExcerptIdMapping {
+                id: excerpt_id,
+                locator: excerpt.locator.clone(),
+            },
+            &(),
+        );
+        if was_last || is_last {
+            snapshot.trailing_excerpt_update_count += 1;
+        }
+        if let Some(buffer_state) = buffers.get_mut(&excerpt.buffer_id) {
+            buffer_state.excerpts.retain(|l| l != &old_locator);
+            if let Err(ix) = buffer_state.excerpts.binary_search(&excerpt.locator) {
+                buffer_state.excerpts.insert(ix, excerpt.locator.clone());
+            }
+        }
+        drop(snapshot);
+        drop(buffers);
+
+        // The text between the excerpt's old and new position is reordered without changing
+        // its length.
+        let insertion_offset = if insertion_offset <= old_range.start {
+            insertion_offset
+        } else {
+            insertion_offset + old_range.len()
+        };
+        let start = old_range.start.min(insertion_offset);
+        let end = old_range.end.max(insertion_offset);
+        self.subscriptions.publish_mut([Edit {
+            old: start..end,
+            new: start..end,
+        }]);
+        cx.emit(Event::Edited {
+            singleton_buffer_edited: false,
+        });
+        cx.emit(Event::ExcerptMoved {
+            id: excerpt_id,
+            predecessor: prev_excerpt_id,
+        });
+        cx.notify();
+        true
+    }
+
     pub fn wait_for_anchors<'a>(
         &self,
         anchors: impl 'a + Iterator<Item = Anchor>,
@@ -1452,6 +1759,7 @@ impl MultiBuffer {
             language::Event::FileHandleChanged => Event::FileHandleChanged,
             language::Event::Reloaded => Event::Reloaded,
             language::Event::DiffBaseChanged => Event::DiffBaseChanged,
+            language::Event::DiffUpdated => Event::DiffUpdated,
             language::Event::LanguageChanged => Event::LanguageChanged,
             language::Event::Reparsed => Event::Reparsed,
             language::Event::DiagnosticsUpdated => Event::DiagnosticsUpdated,
@@ -1583,6 +1891,7 @@ impl MultiBuffer {
             let diagnostics_update_count = buffer.diagnostics_update_count();
             let file_update_count = buffer.file_update_count();
             let git_diff_update_count = buffer.git_diff_update_count();
+            let indent_size_update_count = buffer.indent_size_update_count();
 
             let buffer_edited = version.changed_since(&buffer_state.last_version);
             let buffer_reparsed = parse_count > buffer_state.last_parse_count;
@@ -1593,12 +1902,15 @@ impl MultiBuffer {
             let buffer_file_updated = file_update_count > buffer_state.last_file_update_count;
             let buffer_git_diff_updated =
                 git_diff_update_count > buffer_state.last_git_diff_update_count;
+            let buffer_indent_size_updated =
+                indent_size_update_count > buffer_state.last_indent_size_update_count;
             if buffer_edited
                 || buffer_reparsed
                 || buffer_selections_updated
                 || buffer_diagnostics_updated
                 || buffer_file_updated
                 || buffer_git_diff_updated
+                || buffer_indent_size_updated
             {
                 buffer_state.last_version = version;
                 buffer_state.last_parse_count = parse_count;
@@ -1606,6 +1918,7 @@ impl MultiBuffer {
                 buffer_state.last_diagnostics_update_count = diagnostics_update_count;
                 buffer_state.last_file_update_count = file_update_count;
                 buffer_state.last_git_diff_update_count = git_diff_update_count;
+                buffer_state.last_indent_size_update_count = indent_size_update_count;
                 excerpts_to_edit.extend(
                     buffer_state
                         .excerpts
@@ -2838,6 +3151,20 @@ impl MultiBufferSnapshot {
         })
     }
 
+    /// Returns the excerpts overlapping the given `range`, with the range of each excerpt in its
+    /// buffer and the offset at which the excerpt starts in the multi-buffer.
+    pub fn excerpts_in_range<'a, T: ToOffset>(
+        &'a self,
+        range: Range<T>,
+    ) -> impl Iterator<Item = (&'a BufferSnapshot, Range<usize>, usize)> + 'a {
+        self.excerpts_for_range(range)
+            .map(|(excerpt, excerpt_offset)| {
+                let buffer = &excerpt.buffer;
+                let context = excerpt.range.context.to_offset(buffer);
+                (buffer, context, excerpt_offset)
+            })
+    }
+
     pub fn excerpt_boundaries_in_range<R, T>(
         &self,
         range: R,
@@ -3282,6 +3609,63 @@ impl MultiBufferSnapshot {
             })
     }
 
+    /// Returns the range of the smallest named syntax node enclosing the given range, along with
+    /// the range of its previous or next named sibling, if both are within the same excerpt.
+    pub fn range_for_syntax_sibling<T: ToOffset>(
+        &self,
+        range: Range<T>,
+        next: bool,
+    ) -> Option<(Range<usize>, Range<usize>)> {
+        let range = range.start.to_offset(self)..range.end.to_offset(self);
+
+        let (excerpt, excerpt_offset) = self.excerpt_containing(range.clone())?;
+        let excerpt_buffer_start = excerpt.range.context.start.to_offset(&excerpt.buffer);
+        let excerpt_buffer_end = excerpt_buffer_start + excerpt.text_summary.len;
+
+        let start_in_buffer = excerpt_buffer_start + range.start.saturating_sub(excerpt_offset);
+        let end_in_buffer = excerpt_buffer_start + range.end.saturating_sub(excerpt_offset);
+        let (node, sibling) = excerpt
+            .buffer
+            .range_for_syntax_sibling(start_in_buffer..end_in_buffer, next)?;
+        let to_excerpt = |range: Range<usize>| {
+            if range.start < excerpt_buffer_start || range.end > excerpt_buffer_end {
+                return None;
+            }
+            let start = excerpt_offset + (range.start - excerpt_buffer_start);
+            let end = excerpt_offset + (range.end - excerpt_buffer_start);
+            Some(start..end)
+        };
+        Some((to_excerpt(node)?, to_excerpt(sibling)?))
+    }
+
+    /// Returns the range of the innermost text object of the given kind enclosing the given
+    /// range, clipped to the excerpt containing it.
+    pub fn range_for_text_object<T: ToOffset>(
+        &self,
+        range: Range<T>,
+        object: TextObject,
+        around: bool,
+    ) -> Option<Range<usize>> {
+        let range = range.start.to_offset(self)..range.end.to_offset(self);
+
+        let (excerpt, excerpt_offset) = self.excerpt_containing(range.clone())?;
+        let excerpt_buffer_start = excerpt.range.context.start.to_offset(&excerpt.buffer);
+        let excerpt_buffer_end = excerpt_buffer_start + excerpt.text_summary.len;
+
+        let start_in_buffer = excerpt_buffer_start + range.start.saturating_sub(excerpt_offset);
+        let end_in_buffer = excerpt_buffer_start + range.end.saturating_sub(excerpt_offset);
+        let mut object_range =
+            excerpt
+                .buffer
+                .range_for_text_object(start_in_buffer..end_in_buffer, object, around)?;
+        object_range.start = cmp::max(object_range.start, excerpt_buffer_start);
+        object_range.end = cmp::min(object_range.end, excerpt_buffer_end);
+
+        let start = excerpt_offset + (object_range.start - excerpt_buffer_start);
+        let end = excerpt_offset + (object_range.end - excerpt_buffer_start);
+        Some(start..end)
+    }
+
     pub fn outline(&self, theme: Option<&SyntaxTheme>) -> Option<Outline<Anchor>> {
         let (excerpt_id, _, buffer) = self.as_singleton()?;
         let outline = buffer.outline(theme)?;
@@ -3345,6 +3729,56 @@ impl MultiBufferSnapshot {
         }
     }
 
+    /// Returns the number of excerpts in the multibuffer.
+    pub fn excerpt_count(&self) -> usize {
+        self.excerpts.summary().count
+    }
+
+    /// Returns the position of the given excerpt among all excerpts, without iterating over them.
+    pub fn excerpt_index(&self, excerpt_id: ExcerptId) -> Option<usize> {
+        let mut cursor = self.excerpts.cursor::<(Option<&Locator>, ExcerptCount)>();
+        let locator = self.excerpt_locator_for_id(excerpt_id);
+        cursor.seek(&Some(locator), Bias::Left, &());
+        let excerpt = cursor.item()?;
+        (excerpt.id == excerpt_id).then(|| cursor.start().1 .0)
+    }
+
+    /// Returns the offset range covered by the run of adjacent excerpts that share the given
+    /// excerpt's buffer, along with the number of excerpts in that run.
+    pub fn excerpt_run(&self, excerpt_id: ExcerptId) -> Option<(Range<usize>, usize)> {
+        let mut cursor = self.excerpts.cursor::<(Option<&Locator>, usize)>();
+        let locator = self.excerpt_locator_for_id(excerpt_id);
+        cursor.seek(&Some(locator), Bias::Left, &());
+        let excerpt = cursor.item().filter(|excerpt| excerpt.id == excerpt_id)?;
+        let buffer_id = excerpt.buffer_id;
+        let mut count = 1;
+
+        let mut end = cursor.start().1 + excerpt.text_summary.len;
+        cursor.next(&());
+        while let Some(excerpt) = cursor
+            .item()
+            .filter(|excerpt| excerpt.buffer_id == buffer_id)
+        {
+            end = cursor.start().1 + excerpt.text_summary.len;
+            count += 1;
+            cursor.next(&());
+        }
+
+        cursor.seek(&Some(locator), Bias::Left, &());
+        let mut start = cursor.start().1;
+        cursor.prev(&());
+        while let Some(excerpt) = cursor
+            .item()
+            .filter(|excerpt| excerpt.buffer_id == buffer_id)
+        {
+            start = cursor.start().1;
+            count += 1;
+            cursor.prev(&());
+        }
+
+        Some((start..end, count))
+    }
+
     pub fn buffer_id_for_excerpt(&self, excerpt_id: ExcerptId) -> Option<BufferId> {
         Some(self.excerpt(excerpt_id)?.buffer_id)
     }
@@ -3353,6 +3787,10 @@ impl MultiBufferSnapshot {
         Some(&self.excerpt(excerpt_id)?.buffer)
     }
 
+    pub fn range_for_excerpt(&self, excerpt_id: ExcerptId) -> Option<ExcerptRange<text::Anchor>> {
+        Some(self.excerpt(excerpt_id)?.range.clone())
+    }
+
     fn excerpt<'a>(&'a self, excerpt_id: ExcerptId) -> Option<&'a Excerpt> {
         let mut cursor = self.excerpts.cursor::<Option<&Locator>>();
         let locator = self.excerpt_locator_for_id(excerpt_id);
@@ -3568,6 +4006,25 @@ impl History {
             })
     }
 
+    /// Pushes a transaction onto the redo stack that redoes the given transactions of the
+    /// buffers, which were undone outside of the multi-buffer's history.
+    fn push_redo(
+        &mut self,
+        buffer_transactions: HashMap<BufferId, TransactionId>,
+        now: Instant,
+    ) -> TransactionId {
+        assert_eq!(self.transaction_depth, 0);
+        let id = self.next_transaction_id.tick();
+        self.redo_stack.push(Transaction {
+            id,
+            buffer_transactions,
+            first_edit_at: now,
+            last_edit_at: now,
+            suppress_grouping: true,
+        });
+        id
+    }
+
     fn pop_undo(&mut self) -> Option<&mut Transaction> {
         assert_eq!(self.transaction_depth, 0);
         if let Some(transaction) = self.undo_stack.pop() {
@@ -3825,6 +4282,7 @@ impl sum_tree::Item for Excerpt {
             excerpt_id: self.id,
             excerpt_locator: self.locator.clone(),
             max_buffer_row: self.max_buffer_row,
+            count: 1,
             text,
         }
     }
@@ -3862,6 +4320,7 @@ impl sum_tree::Summary for ExcerptSummary {
         self.excerpt_locator = summary.excerpt_locator.clone();
         self.text.add_summary(&summary.text, &());
         self.max_buffer_row = cmp::max(self.max_buffer_row, summary.max_buffer_row);
+        self.count += summary.count;
     }
 }
 
@@ -3925,6 +4384,12 @@ impl<'a> sum_tree::Dimension<'a, ExcerptSummary> for Option<ExcerptId> {
     }
 }
 
+impl<'a> sum_tree::Dimension<'a, ExcerptSummary> for ExcerptCount {
+    fn add_summary(&mut self, summary: &'a ExcerptSummary, _: &()) {
+        self.0 += summary.count;
+    }
+}
+
 impl<'a> MultiBufferRows<'a> {
     pub fn seek(&mut self, row: u32) {
         self.buffer_row_range = 0..0;
@@ -4591,6 +5056,9 @@ mod tests {
                         excerpts,
                     } => follower.insert_excerpts_with_ids_after(predecessor, buffer, excerpts, cx),
                     Event::ExcerptsRemoved { ids } => follower.remove_excerpts(ids, cx),
+                    Event::ExcerptMoved { id, predecessor } => {
+                        follower.move_excerpt(id, predecessor, cx);
+                    }
                     Event::Edited { .. } => {
                         *follower_edit_event_count.write() += 1;
                     }
@@ -4668,13 +5136,27 @@ mod tests {
         assert_eq!(*follower_edit_event_count.read(), 3);
 
         leader_multibuffer.update(cx, |leader, cx| {
-            leader.clear(cx);
+            let excerpt_ids = leader.excerpt_ids();
+            leader.move_excerpt(excerpt_ids[0], excerpt_ids[1], cx);
         });
         assert_eq!(
             leader_multibuffer.read(cx).snapshot(cx).text(),
             follower_multibuffer.read(cx).snapshot(cx).text(),
         );
+        assert_eq!(
+            leader_multibuffer.read(cx).excerpt_ids(),
+            follower_multibuffer.read(cx).excerpt_ids(),
+        );
         assert_eq!(*follower_edit_event_count.read(), 4);
+
+        leader_multibuffer.update(cx, |leader, cx| {
+            leader.clear(cx);
+        });
+        assert_eq!(
+            leader_multibuffer.read(cx).snapshot(cx).text(),
+            follower_multibuffer.read(cx).snapshot(cx).text(),
+        );
+        assert_eq!(*follower_edit_event_count.read(), 5);
     }
 
     #[gpui::test]
@@ -4719,6 +5201,184 @@ mod tests {
         );
     }
 
+    #[gpui::test]
+    fn test_expand_excerpts(cx: &mut AppContext) {
+        let buffer = cx.new_model(|cx| {
+            Buffer::new(
+                0,
+                BufferId::new(cx.entity_id().as_u64()).unwrap(),
+                sample_text(20, 3, 'a'),
+            )
+        });
+        let multibuffer = cx.new_model(|_| MultiBuffer::new(0, Capability::ReadWrite));
+        let excerpt_ids = multibuffer.update(cx, |multibuffer, cx| {
+            multibuffer.push_excerpts(
+                buffer.clone(),
+                [
+                    ExcerptRange {
+                        context: Point::new(2, 0)..Point::new(4, 3),
+                        primary: None,
+                    },
+                    ExcerptRange {
+                        context: Point::new(10, 0)..Point::new(11, 3),
+                        primary: None,
+                    },
+                ],
+                cx,
+            )
+        });
+        let snapshot = multibuffer.read(cx).snapshot(cx);
+        assert_eq!(snapshot.text(), "ccc\nddd\neee\nkkk\nlll");
+        let anchor = snapshot.anchor_before(Point::new(3, 1));
+
+        multibuffer.update(cx, |multibuffer, cx| {
+            multibuffer.expand_excerpts([excerpt_ids[1]], 2, ExpandExcerptDirection::UpAndDown, cx);
+            multibuffer.expand_excerpts([excerpt_ids[0]], 5, ExpandExcerptDirection::Up, cx);
+        });
+        let snapshot = multibuffer.read(cx).snapshot(cx);
+        assert_eq!(
+            snapshot.text(),
+            "aaa\nbbb\nccc\nddd\neee\niii\njjj\nkkk\nlll\nmmm\nnnn"
+        );
+        assert_eq!(anchor.to_point(&snapshot), Point::new(7, 1));
+        assert_eq!(snapshot.excerpts().count(), 2);
+
+        // Expanding past the start or the end of the buffer stops at its bounds.
+        multibuffer.update(cx, |multibuffer, cx| {
+            multibuffer.expand_excerpts([excerpt_ids[0]], u32::MAX, ExpandExcerptDirection::Up, cx);
+            multibuffer.expand_excerpts(
+                [excerpt_ids[1]],
+                u32::MAX,
+                ExpandExcerptDirection::Down,
+                cx,
+            );
+        });
+        let snapshot = multibuffer.read(cx).snapshot(cx);
+        assert_eq!(
+            snapshot.text(),
+            "aaa\nbbb\nccc\nddd\neee\niii\njjj\nkkk\nlll\nmmm\nnnn\nooo\nppp\nqqq\nrrr\nsss\nttt"
+        );
+        assert_eq!(anchor.to_point(&snapshot), Point::new(7, 1));
+    }
+
+    #[gpui::test]
+    fn test_move_excerpt(cx: &mut AppContext) {
+        let buffer_1 = cx
+            .new_model(|cx| Buffer::new(0, BufferId::new(cx.entity_id().as_u64()).unwrap(), "one"));
+        let buffer_2 = cx
+            .new_model(|cx| Buffer::new(0, BufferId::new(cx.entity_id().as_u64()).unwrap(), "two"));
+        let buffer_3 = cx.new_model(|cx| {
+            Buffer::new(0, BufferId::new(cx.entity_id().as_u64()).unwrap(), "three")
+        });
+        let multibuffer = cx.new_model(|_| MultiBuffer::new(0, Capability::ReadWrite));
+        let ids = multibuffer.update(cx, |multibuffer, cx| {
+            [&buffer_1, &buffer_2, &buffer_3]
+                .into_iter()
+                .flat_map(|buffer| {
+                    multibuffer.push_excerpts(
+                        buffer.clone(),
+                        [ExcerptRange {
+                            context: 0..buffer.read(cx).len(),
+                            primary: None,
+                        }],
+                        cx,
+                    )
+                })
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(multibuffer.read(cx).read(cx).text(), "one\ntwo\nthree");
+
+        let anchor = multibuffer
+            .read(cx)
+            .read(cx)
+            .anchor_before(Point::new(2, 2));
+        assert!(multibuffer.update(cx, |multibuffer, cx| {
+            multibuffer.move_excerpt(ids[2], ExcerptId::min(), cx)
+        }));
+        assert_eq!(multibuffer.read(cx).read(cx).text(), "three\none\ntwo");
+
+        // Moved excerpts keep their ids, so anchors into them move along with them.
+        assert_eq!(multibuffer.read(cx).excerpt_ids(), [ids[2], ids[0], ids[1]]);
+        let snapshot = multibuffer.read(cx).snapshot(cx);
+        assert_eq!(anchor.excerpt_id, ids[2]);
+        assert_eq!(anchor.to_point(&snapshot), Point::new(0, 2));
+
+        multibuffer.update(cx, |multibuffer, cx| {
+            assert!(multibuffer.move_excerpt(ids[0], ids[1], cx));
+            assert!(multibuffer.move_excerpt(ids[2], ids[1], cx));
+            assert!(!multibuffer.move_excerpt(ids[1], ids[1], cx));
+        });
+        assert_eq!(multibuffer.read(cx).excerpt_ids(), [ids[1], ids[2], ids[0]]);
+        let snapshot = multibuffer.read(cx).snapshot(cx);
+        assert_eq!(snapshot.text(), "two\nthree\none");
+        assert_eq!(anchor.to_point(&snapshot), Point::new(1, 2));
+        assert_eq!(
+            snapshot
+                .excerpt_boundaries_in_range(0..snapshot.len())
+                .map(|boundary| boundary.row)
+                .collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+    }
+
+    #[gpui::test]
+    fn test_excerpt_index_and_run(cx: &mut AppContext) {
+        let buffer_1 = cx.new_model(|cx| {
+            Buffer::new(
+                0,
+                BufferId::new(cx.entity_id().as_u64()).unwrap(),
+                "one\ntwo\nthree",
+            )
+        });
+        let buffer_2 = cx.new_model(|cx| {
+            Buffer::new(0, BufferId::new(cx.entity_id().as_u64()).unwrap(), "four")
+        });
+        let multibuffer = cx.new_model(|_| MultiBuffer::new(0, Capability::ReadWrite));
+        let ids = multibuffer.update(cx, |multibuffer, cx| {
+            [
+                (&buffer_1, 0..3),
+                (&buffer_1, 8..13),
+                (&buffer_2, 0..4),
+                (&buffer_1, 4..7),
+            ]
+            .into_iter()
+            .flat_map(|(buffer, range)| {
+                multibuffer.push_excerpts(
+                    buffer.clone(),
+                    [ExcerptRange {
+                        context: range,
+                        primary: None,
+                    }],
+                    cx,
+                )
+            })
+            .collect::<Vec<_>>()
+        });
+
+        let snapshot = multibuffer.read(cx).snapshot(cx);
+        assert_eq!(snapshot.text(), "one\nthree\nfour\ntwo");
+        assert_eq!(snapshot.excerpt_count(), 4);
+        assert_eq!(
+            ids.iter()
+                .map(|id| snapshot.excerpt_index(*id))
+                .collect::<Vec<_>>(),
+            [Some(0), Some(1), Some(2), Some(3)]
+        );
+        assert_eq!(snapshot.excerpt_run(ids[0]), Some((0..9, 2)));
+        assert_eq!(snapshot.excerpt_run(ids[1]), Some((0..9, 2)));
+        assert_eq!(snapshot.excerpt_run(ids[2]), Some((10..14, 1)));
+        assert_eq!(snapshot.excerpt_run(ids[3]), Some((15..18, 1)));
+
+        multibuffer.update(cx, |multibuffer, cx| {
+            multibuffer.remove_excerpts([ids[2]], cx);
+        });
+        let snapshot = multibuffer.read(cx).snapshot(cx);
+        assert_eq!(snapshot.excerpt_count(), 3);
+        assert_eq!(snapshot.excerpt_index(ids[2]), None);
+        assert_eq!(snapshot.excerpt_index(ids[3]), Some(2));
+        assert_eq!(snapshot.excerpt_run(ids[3]), Some((0..13, 3)));
+    }
+
     #[gpui::test]
     async fn test_stream_excerpts_with_context_lines(cx: &mut TestAppContext) {
         let buffer = cx.new_model(|cx| {
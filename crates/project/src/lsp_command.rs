@@ -1,5 +1,5 @@
 use crate::{
-    DocumentHighlight, Hover, HoverBlock, HoverBlockKind, InlayHint, InlayHintLabel,
+    CodeLens, DocumentHighlight, Hover, HoverBlock, HoverBlockKind, InlayHint, InlayHintLabel,
     InlayHintLabelPart, InlayHintLabelPartTooltip, InlayHintTooltip, Location, LocationLink,
     MarkupContent, Project, ProjectTransaction, ResolveState,
 };
@@ -22,6 +22,7 @@ use lsp::{
 };
 use std::{cmp::Reverse, ops::Range, path::Path, sync::Arc};
 use text::{BufferId, LineEnding};
+use util::ResultExt as _;
 
 pub fn lsp_formatting_options(tab_size: u32) -> lsp::FormattingOptions {
     lsp::FormattingOptions {
@@ -126,6 +127,8 @@ pub(crate) struct GetCodeActions {
     pub kinds: Option<Vec<lsp::CodeActionKind>>,
 }
 
+pub(crate) struct GetCodeLens;
+
 pub(crate) struct OnTypeFormatting {
     pub position: PointUtf16,
     pub trigger: String,
@@ -1714,6 +1717,177 @@ impl LspCommand for GetCodeActions {
     }
 }
 
+#[async_trait(?Send)]
+impl LspCommand for GetCodeLens {
+    type Response = Vec<CodeLens>;
+    type LspRequest = lsp::request::CodeLensRequest;
+    type ProtoRequest = proto::GetCodeLens;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        capabilities.code_lens_provider.is_some()
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::CodeLensParams {
+        lsp::CodeLensParams {
+            text_document: lsp::TextDocumentIdentifier::new(
+                lsp::Url::from_file_path(path).unwrap(),
+            ),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        lenses: Option<Vec<lsp::CodeLens>>,
+        project: Model<Project>,
+        buffer: Model<Buffer>,
+        server_id: LanguageServerId,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<CodeLens>> {
+        // Servers may leave resolving the commands of the lenses until they are shown.
+        let lenses = lenses.unwrap_or_default();
+        let resolving_server = project
+            .update(&mut cx, |project, _| {
+                project.language_server_for_id(server_id)
+            })?
+            .filter(|server| {
+                server
+                    .capabilities()
+                    .code_lens_provider
+                    .as_ref()
+                    .and_then(|provider| provider.resolve_provider)
+                    .unwrap_or(false)
+            });
+        let lenses = if let Some(server) = resolving_server {
+            // Lenses are resolved independently, so that one failing to resolve only leaves out
+            // that lens.
+            future::join_all(lenses.into_iter().map(|lens| {
+                let server = server.clone();
+                async move {
+                    if lens.command.is_some() {
+                        Some(lens)
+                    } else {
+                        server
+                            .request::<lsp::request::CodeLensResolve>(lens)
+                            .await
+                            .log_err()
+                    }
+                }
+            }))
+            .await
+            .into_iter()
+            .flatten()
+            .collect()
+        } else {
+            lenses
+        };
+
+        buffer.update(&mut cx, |buffer, _| {
+            lenses
+                .into_iter()
+                .map(|lsp_lens| {
+                    let start =
+                        buffer.clip_point_utf16(point_from_lsp(lsp_lens.range.start), Bias::Left);
+                    let end =
+                        buffer.clip_point_utf16(point_from_lsp(lsp_lens.range.end), Bias::Left);
+                    CodeLens {
+                        server_id,
+                        range: buffer.anchor_after(start)..buffer.anchor_before(end),
+                        lsp_lens,
+                    }
+                })
+                .collect()
+        })
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetCodeLens {
+        proto::GetCodeLens {
+            project_id,
+            buffer_id: buffer.remote_id().into(),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetCodeLens,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        Ok(Self)
+    }
+
+    fn response_to_proto(
+        lenses: Vec<CodeLens>,
+        _: &mut Project,
+        _: PeerId,
+        buffer_version: &clock::Global,
+        _: &mut AppContext,
+    ) -> proto::GetCodeLensResponse {
+        proto::GetCodeLensResponse {
+            lenses: lenses
+                .iter()
+                .map(|lens| proto::CodeLens {
+                    server_id: lens.server_id.0 as u64,
+                    start: Some(serialize_anchor(&lens.range.start)),
+                    end: Some(serialize_anchor(&lens.range.end)),
+                    lsp_lens: serde_json::to_vec(&lens.lsp_lens).unwrap(),
+                })
+                .collect(),
+            version: serialize_version(buffer_version),
+        }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetCodeLensResponse,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<CodeLens>> {
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        message
+            .lenses
+            .into_iter()
+            .map(|lens| {
+                let start = lens
+                    .start
+                    .and_then(deserialize_anchor)
+                    .ok_or_else(|| anyhow!("invalid start"))?;
+                let end = lens
+                    .end
+                    .and_then(deserialize_anchor)
+                    .ok_or_else(|| anyhow!("invalid end"))?;
+                Ok(CodeLens {
+                    server_id: LanguageServerId(lens.server_id as usize),
+                    range: start..end,
+                    lsp_lens: serde_json::from_slice(&lens.lsp_lens)?,
+                })
+            })
+            .collect()
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetCodeLens) -> Result<BufferId> {
+        BufferId::new(message.buffer_id)
+    }
+}
+
 #[async_trait(?Send)]
 impl LspCommand for OnTypeFormatting {
     type Response = Option<Transaction>;
@@ -41,8 +41,8 @@ use language::{
     range_from_lsp, range_to_lsp, Bias, Buffer, BufferSnapshot, CachedLspAdapter, Capability,
     CodeAction, CodeLabel, Completion, Diagnostic, DiagnosticEntry, DiagnosticSet, Diff,
     Documentation, Event as BufferEvent, File as _, Language, LanguageRegistry, LanguageServerName,
-    LocalFile, LspAdapterDelegate, OffsetRangeExt, Operation, Patch, PendingLanguageServer,
-    PointUtf16, TextBufferSnapshot, ToOffset, ToPointUtf16, Transaction, Unclipped,
+    LocalFile, LspAdapterDelegate, OffsetRangeExt, Operation, Patch, PendingLanguageServer, Point,
+    PointUtf16, TextBufferSnapshot, ToOffset, ToPoint, ToPointUtf16, Transaction, Unclipped,
 };
 use log::error;
 use lsp::{
@@ -133,6 +133,7 @@ pub struct Project {
     loading_local_worktrees:
         HashMap<Arc<Path>, Shared<Task<Result<Model<Worktree>, Arc<anyhow::Error>>>>>,
     opened_buffers: HashMap<BufferId, OpenBuffer>,
+    breakpoints: HashMap<ProjectPath, FileBreakpoints>,
     local_buffer_ids_by_path: HashMap<ProjectPath, BufferId>,
     local_buffer_ids_by_entry_id: HashMap<ProjectEntryId, BufferId>,
     /// A mapping from a buffer ID to None means that we've started waiting for an ID but haven't finished loading it.
@@ -272,6 +273,10 @@ pub enum Event {
     CollaboratorLeft(proto::PeerId),
     RefreshInlayHints,
     RevealInProjectPanel(ProjectEntryId),
+    BreakpointsChanged(ProjectPath),
+    /// The program run by [`Project::create_task_terminal`] with the given id exited, or its
+    /// terminal was closed before then.
+    TerminalTaskFinished(usize),
 }
 
 pub enum LanguageServerState {
@@ -428,6 +433,86 @@ impl Hover {
     }
 }
 
+/// A command that a language server shows above a range of a buffer, such as the number of
+/// references to a function.
+#[derive(Clone, Debug)]
+pub struct CodeLens {
+    pub server_id: LanguageServerId,
+    pub range: Range<language::Anchor>,
+    pub lsp_lens: lsp::CodeLens,
+}
+
+impl CodeLens {
+    /// Returns the text to show for the lens, or `None` if it has no command to run.
+    pub fn title(&self) -> Option<&str> {
+        let command = self.lsp_lens.command.as_ref()?;
+        Some(command.title.as_str())
+    }
+}
+
+/// A line of a buffer on which a debugger should pause.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Breakpoint {
+    /// The start of the line, anchored so that the breakpoint follows it across edits.
+    pub position: language::Anchor,
+}
+
+/// The breakpoints set in a file, which are kept while it isn't open.
+#[derive(Clone, Debug)]
+enum FileBreakpoints {
+    /// The breakpoints of a file that's open in the buffer with the given id, sorted by their
+    /// positions.
+    Open {
+        buffer_id: BufferId,
+        breakpoints: Vec<Breakpoint>,
+    },
+    /// The sorted rows of the breakpoints of a file that isn't open.
+    Closed(Vec<u32>),
+}
+
+impl FileBreakpoints {
+    /// Anchors the breakpoints in the buffer the file was opened in.
+    fn open(&mut self, buffer: &Buffer) {
+        let breakpoints = match self {
+            Self::Open { buffer_id, .. } if *buffer_id == buffer.remote_id() => return,
+            Self::Open { .. } => Vec::new(),
+            Self::Closed(rows) => rows
+                .iter()
+                .filter(|row| **row <= buffer.max_point().row)
+                .map(|row| Breakpoint {
+                    position: buffer.anchor_after(Point::new(*row, 0)),
+                })
+                .collect(),
+        };
+        *self = Self::Open {
+            buffer_id: buffer.remote_id(),
+            breakpoints,
+        };
+    }
+
+    /// Keeps the rows of the breakpoints once the buffer the file was open in is closed.
+    fn close(&mut self, buffer: &Buffer) {
+        if let Self::Open {
+            buffer_id,
+            breakpoints,
+        } = self
+        {
+            if *buffer_id == buffer.remote_id() {
+                *self = Self::Closed(breakpoint_rows(breakpoints, buffer));
+            }
+        }
+    }
+}
+
+fn breakpoint_rows(breakpoints: &[Breakpoint], buffer: &text::BufferSnapshot) -> Vec<u32> {
+    let mut rows = breakpoints
+        .iter()
+        .map(|breakpoint| breakpoint.position.to_point(buffer).row)
+        .collect::<Vec<_>>();
+    rows.dedup();
+    rows
+}
+
 #[derive(Default)]
 pub struct ProjectTransaction(pub HashMap<Model<Buffer>, language::Transaction>);
 
@@ -583,6 +668,7 @@ impl Project {
         client.add_model_request_handler(Self::handle_synchronize_buffers);
         client.add_model_request_handler(Self::handle_format_buffers);
         client.add_model_request_handler(Self::handle_lsp_command::<GetCodeActions>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetCodeLens>);
         client.add_model_request_handler(Self::handle_lsp_command::<GetCompletions>);
         client.add_model_request_handler(Self::handle_lsp_command::<GetHover>);
         client.add_model_request_handler(Self::handle_lsp_command::<GetDefinition>);
@@ -621,6 +707,7 @@ impl Project {
                 collaborators: Default::default(),
                 next_buffer_id: BufferId::new(1).unwrap(),
                 opened_buffers: Default::default(),
+                breakpoints: Default::default(),
                 shared_buffers: Default::default(),
                 incomplete_remote_buffers: Default::default(),
                 loading_buffers_by_path: Default::default(),
@@ -657,6 +744,7 @@ impl Project {
                 nonce: StdRng::from_entropy().gen(),
                 terminals: Terminals {
                     local_handles: Vec::new(),
+                    next_task_id: 0,
                 },
                 copilot_lsp_subscription,
                 copilot_log_subscription: None,
@@ -755,6 +843,7 @@ impl Project {
                     .collect(),
                 last_workspace_edits_by_language_server: Default::default(),
                 opened_buffers: Default::default(),
+                breakpoints: Default::default(),
                 buffers_being_formatted: Default::default(),
                 buffers_needing_diff: Default::default(),
                 git_diff_debouncer: DebouncedDelay::new(),
@@ -762,6 +851,7 @@ impl Project {
                 nonce: StdRng::from_entropy().gen(),
                 terminals: Terminals {
                     local_handles: Vec::new(),
+                    next_task_id: 0,
                 },
                 copilot_lsp_subscription,
                 copilot_log_subscription: None,
@@ -986,6 +1076,85 @@ impl Project {
             .and_then(|buffer| buffer.upgrade())
     }
 
+    /// Adds a breakpoint on the given row of the buffer's file, or removes the one that is
+    /// already there.
+    pub fn toggle_breakpoint(
+        &mut self,
+        buffer: &Model<Buffer>,
+        row: u32,
+        cx: &mut ModelContext<Self>,
+    ) {
+        let buffer = buffer.read(cx);
+        let Some(file) = File::from_dyn(buffer.file()) else {
+            return;
+        };
+        let project_path = ProjectPath {
+            worktree_id: file.worktree_id(cx),
+            path: file.path.clone(),
+        };
+        let file_breakpoints = self
+            .breakpoints
+            .entry(project_path.clone())
+            .or_insert(FileBreakpoints::Closed(Vec::new()));
+        file_breakpoints.open(buffer);
+        let FileBreakpoints::Open { breakpoints, .. } = file_breakpoints else {
+            return;
+        };
+
+        // Breakpoints on lines that were joined by an edit are all removed together.
+        let start = breakpoints
+            .partition_point(|breakpoint| breakpoint.position.to_point(buffer).row < row);
+        let end = breakpoints
+            .partition_point(|breakpoint| breakpoint.position.to_point(buffer).row <= row);
+        if start < end {
+            breakpoints.drain(start..end);
+        } else {
+            let position = buffer.anchor_after(Point::new(row, 0));
+            breakpoints.insert(start, Breakpoint { position });
+        }
+        if breakpoints.is_empty() {
+            self.breakpoints.remove(&project_path);
+        }
+        cx.emit(Event::BreakpointsChanged(project_path));
+    }
+
+    /// Returns the breakpoints that were set in the given buffer's file, sorted by their
+    /// positions.
+    pub fn breakpoints_for_buffer(&self, buffer: &Buffer, cx: &AppContext) -> &[Breakpoint] {
+        let Some(file) = File::from_dyn(buffer.file()) else {
+            return &[];
+        };
+        let project_path = ProjectPath {
+            worktree_id: file.worktree_id(cx),
+            path: file.path.clone(),
+        };
+        match self.breakpoints.get(&project_path) {
+            Some(FileBreakpoints::Open {
+                buffer_id,
+                breakpoints,
+            }) if *buffer_id == buffer.remote_id() => breakpoints,
+            _ => &[],
+        }
+    }
+
+    /// Returns the sorted rows of the breakpoints in each file that has any, whether or not
+    /// it's open, e.g. for a debugger to set them when a debugging session starts.
+    pub fn breakpoints(&self, cx: &AppContext) -> Vec<(ProjectPath, Vec<u32>)> {
+        self.breakpoints
+            .iter()
+            .filter_map(|(project_path, breakpoints)| {
+                let rows = match breakpoints {
+                    FileBreakpoints::Open {
+                        buffer_id,
+                        breakpoints,
+                    } => breakpoint_rows(breakpoints, self.buffer_for_id(*buffer_id)?.read(cx)),
+                    FileBreakpoints::Closed(rows) => rows.clone(),
+                };
+                Some((project_path.clone(), rows))
+            })
+            .collect()
+    }
+
     pub fn languages(&self) -> &Arc<LanguageRegistry> {
         &self.languages
     }
@@ -2046,8 +2215,24 @@ impl Project {
         self.detect_language_for_buffer(buffer, cx);
         self.register_buffer_with_language_servers(buffer, cx);
         self.register_buffer_with_copilot(buffer, cx);
+        if let Some(file) = File::from_dyn(buffer.read(cx).file()) {
+            let project_path = ProjectPath {
+                worktree_id: file.worktree_id(cx),
+                path: file.path.clone(),
+            };
+            if let Some(breakpoints) = self.breakpoints.get_mut(&project_path) {
+                breakpoints.open(buffer.read(cx));
+            }
+        }
         cx.observe_release(buffer, |this, buffer, cx| {
             if let Some(file) = File::from_dyn(buffer.file()) {
+                let project_path = ProjectPath {
+                    worktree_id: file.worktree_id(cx),
+                    path: file.path.clone(),
+                };
+                if let Some(breakpoints) = this.breakpoints.get_mut(&project_path) {
+                    breakpoints.close(buffer);
+                }
                 if file.is_local() {
                     let uri = lsp::Url::from_file_path(file.abs_path(cx)).unwrap();
                     for server in this.language_servers_for_buffer(buffer, cx) {
@@ -5819,6 +6004,79 @@ impl Project {
         self.on_type_format_impl(buffer, position, trigger, push_to_history, cx)
     }
 
+    pub fn code_lens(
+        &self,
+        buffer_handle: &Model<Buffer>,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<CodeLens>>> {
+        self.request_lsp(
+            buffer_handle.clone(),
+            LanguageServerToQuery::Primary,
+            GetCodeLens,
+            cx,
+        )
+    }
+
+    /// Asks the language server that provided a code lens to run its command, returning the
+    /// edits the server made while doing so.
+    pub fn execute_code_lens(
+        &self,
+        buffer_handle: &Model<Buffer>,
+        lens: CodeLens,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<ProjectTransaction>> {
+        if !self.is_local() {
+            return Task::ready(Err(anyhow!(
+                "code lens commands can only be run in local projects"
+            )));
+        }
+        let Some(command) = lens.lsp_lens.command else {
+            return Task::ready(Ok(Default::default()));
+        };
+        let buffer = buffer_handle.read(cx);
+        let Some((_, language_server)) =
+            self.language_server_for_buffer(buffer, lens.server_id, cx)
+        else {
+            return Task::ready(Ok(Default::default()));
+        };
+        // Lenses can also carry commands meant for the client, which the server can't run.
+        let can_execute = language_server
+            .capabilities()
+            .execute_command_provider
+            .as_ref()
+            .map_or(false, |provider| {
+                provider.commands.contains(&command.command)
+            });
+        if !can_execute {
+            return Task::ready(Err(anyhow!(
+                "language server doesn't support the {:?} command",
+                command.command
+            )));
+        }
+        let language_server = language_server.clone();
+
+        cx.spawn(move |this, mut cx| async move {
+            this.update(&mut cx, |this, _| {
+                this.last_workspace_edits_by_language_server
+                    .remove(&language_server.server_id());
+            })?;
+
+            language_server
+                .request::<lsp::request::ExecuteCommand>(lsp::ExecuteCommandParams {
+                    command: command.command,
+                    arguments: command.arguments.unwrap_or_default(),
+                    ..Default::default()
+                })
+                .await?;
+
+            this.update(&mut cx, |this, _| {
+                this.last_workspace_edits_by_language_server
+                    .remove(&language_server.server_id())
+                    .unwrap_or_default()
+            })
+        })
+    }
+
     pub fn inlay_hints<T: ToOffset>(
         &self,
         buffer_handle: Model<Buffer>,
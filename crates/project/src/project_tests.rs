@@ -3155,6 +3155,83 @@ async fn test_buffer_deduping(cx: &mut gpui::TestAppContext) {
     assert_eq!(buffer_a_3.entity_id(), buffer_a_id);
 }
 
+#[gpui::test]
+async fn test_toggling_breakpoints(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree("/dir", json!({ "a.rs": "one\ntwo\nthree\n" }))
+        .await;
+
+    let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+    let buffer = project
+        .update(cx, |project, cx| project.open_local_buffer("/dir/a.rs", cx))
+        .await
+        .unwrap();
+    let project_path = buffer.update(cx, |buffer, cx| {
+        let file = File::from_dyn(buffer.file()).unwrap();
+        ProjectPath {
+            worktree_id: file.worktree_id(cx),
+            path: file.path.clone(),
+        }
+    });
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    cx.update(|cx| {
+        let events = events.clone();
+        cx.subscribe(&project, move |_, event, _| {
+            if let Event::BreakpointsChanged(_) = event {
+                events.lock().push(event.clone());
+            }
+        })
+        .detach();
+    });
+
+    project.update(cx, |project, cx| {
+        project.toggle_breakpoint(&buffer, 2, cx);
+        project.toggle_breakpoint(&buffer, 0, cx);
+    });
+    assert_eq!(
+        *events.lock(),
+        [
+            Event::BreakpointsChanged(project_path.clone()),
+            Event::BreakpointsChanged(project_path.clone())
+        ]
+    );
+    project.read_with(cx, |project, cx| {
+        assert_eq!(
+            project.breakpoints(cx),
+            [(project_path.clone(), vec![0, 2])]
+        );
+    });
+
+    // Breakpoints follow their lines across edits, and toggling a row again removes them.
+    buffer.update(cx, |buffer, cx| buffer.edit([(0..0, "zero\n")], None, cx));
+    project.update(cx, |project, cx| project.toggle_breakpoint(&buffer, 1, cx));
+    project.read_with(cx, |project, cx| {
+        assert_eq!(project.breakpoints(cx), [(project_path.clone(), vec![3])]);
+        let buffer = buffer.read(cx);
+        assert_eq!(project.breakpoints_for_buffer(buffer, cx).len(), 1);
+    });
+
+    // The breakpoints of a file are kept while it's closed, and restored when it's reopened.
+    drop(buffer);
+    cx.run_until_parked();
+    project.read_with(cx, |project, cx| {
+        assert_eq!(project.breakpoints(cx), [(project_path.clone(), vec![3])]);
+    });
+    let buffer = project
+        .update(cx, |project, cx| project.open_local_buffer("/dir/a.rs", cx))
+        .await
+        .unwrap();
+    project.read_with(cx, |project, cx| {
+        let buffer = buffer.read(cx);
+        let breakpoints = project.breakpoints_for_buffer(buffer, cx);
+        assert_eq!(breakpoints.len(), 1);
+        assert_eq!(breakpoints[0].position.to_point(buffer).row, 3);
+    });
+}
+
 #[gpui::test]
 async fn test_buffer_is_dirty(cx: &mut gpui::TestAppContext) {
     init_test(cx);
@@ -37,6 +37,7 @@ pub enum SearchQuery {
     Text {
         search: Arc<AhoCorasick>,
         replacement: Option<String>,
+        preserve_case: bool,
         whole_word: bool,
         case_sensitive: bool,
         include_ignored: bool,
@@ -46,6 +47,7 @@ pub enum SearchQuery {
     Regex {
         regex: Regex,
         replacement: Option<String>,
+        preserve_case: bool,
         multiline: bool,
         whole_word: bool,
         case_sensitive: bool,
@@ -75,6 +77,7 @@ impl SearchQuery {
         Ok(Self::Text {
             search: Arc::new(search),
             replacement: None,
+            preserve_case: false,
             whole_word,
             case_sensitive,
             include_ignored,
@@ -113,6 +116,7 @@ impl SearchQuery {
         Ok(Self::Regex {
             regex,
             replacement: None,
+            preserve_case: false,
             multiline,
             whole_word,
             case_sensitive,
@@ -157,6 +161,23 @@ impl SearchQuery {
             }
         }
     }
+    /// Makes replacements take the capitalization of the text they replace, so that replacing
+    /// `foo` with `bar` also replaces `Foo` with `Bar` and `FOO` with `BAR`.
+    pub fn with_preserve_case(mut self, new_preserve_case: bool) -> Self {
+        match self {
+            Self::Text {
+                ref mut preserve_case,
+                ..
+            }
+            | Self::Regex {
+                ref mut preserve_case,
+                ..
+            } => {
+                *preserve_case = new_preserve_case;
+                self
+            }
+        }
+    }
     pub fn to_proto(&self, project_id: u64) -> proto::SearchProject {
         proto::SearchProject {
             project_id,
@@ -225,7 +246,7 @@ impl SearchQuery {
     }
     /// Replaces search hits if replacement is set. `text` is assumed to be a string that matches this `SearchQuery` exactly, without any leftovers on either side.
     pub fn replacement_for<'a>(&self, text: &'a str) -> Option<Cow<'a, str>> {
-        match self {
+        let replacement = match self {
             SearchQuery::Text { replacement, .. } => replacement.clone().map(Cow::from),
             SearchQuery::Regex {
                 regex, replacement, ..
@@ -236,6 +257,11 @@ impl SearchQuery {
                     None
                 }
             }
+        }?;
+        if self.preserve_case() {
+            Some(MatchCase::of(text).apply(&replacement).into_owned().into())
+        } else {
+            Some(replacement)
         }
     }
     pub async fn search(
@@ -356,6 +382,13 @@ impl SearchQuery {
         }
     }
 
+    pub fn preserve_case(&self) -> bool {
+        match self {
+            Self::Text { preserve_case, .. } => *preserve_case,
+            Self::Regex { preserve_case, .. } => *preserve_case,
+        }
+    }
+
     pub fn is_regex(&self) -> bool {
         matches!(self, Self::Regex { .. })
     }
@@ -401,6 +434,53 @@ impl SearchQuery {
     }
 }
 
+/// The capitalization of a piece of text, which case-preserving replacements of it are changed to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchCase {
+    /// All letters are uppercase, as in `FOO`.
+    Upper,
+    /// The first letter is uppercase and the rest are lowercase, as in `Foo`.
+    Capitalized,
+    /// Any other capitalization, such as `foo` or `fooBar`, which replacements keep as typed.
+    Other,
+}
+
+impl MatchCase {
+    pub fn of(text: &str) -> Self {
+        let mut letters = text.chars().filter(|c| c.is_alphabetic());
+        match letters.next() {
+            Some(first) if first.is_uppercase() => {
+                let rest = letters.collect::<Vec<_>>();
+                if rest.iter().all(|c| c.is_lowercase()) {
+                    Self::Capitalized
+                } else if rest.iter().all(|c| c.is_uppercase()) {
+                    Self::Upper
+                } else {
+                    Self::Other
+                }
+            }
+            _ => Self::Other,
+        }
+    }
+
+    /// Returns the given text with this capitalization.
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self {
+            Self::Upper => text.to_uppercase().into(),
+            Self::Capitalized => {
+                let mut chars = text.chars();
+                match chars.next() {
+                    Some(first) if !first.is_uppercase() => {
+                        first.to_uppercase().chain(chars).collect::<String>().into()
+                    }
+                    _ => text.into(),
+                }
+            }
+            Self::Other => text.into(),
+        }
+    }
+}
+
 fn deserialize_path_matches(glob_set: &str) -> anyhow::Result<Vec<PathMatcher>> {
     glob_set
         .split(',')
@@ -417,6 +497,30 @@ fn deserialize_path_matches(glob_set: &str) -> anyhow::Result<Vec<PathMatcher>>
 mod tests {
     use super::*;
 
+    #[test]
+    fn case_preserving_replacement() {
+        let query = SearchQuery::text("foo", false, false, false, Vec::new(), Vec::new())
+            .unwrap()
+            .with_replacement("bar".into())
+            .with_preserve_case(true);
+        for (text, replacement) in [
+            ("foo", "bar"),
+            ("Foo", "Bar"),
+            ("FOO", "BAR"),
+            ("fOo", "bar"),
+        ] {
+            assert_eq!(query.replacement_for(text).as_deref(), Some(replacement));
+        }
+
+        let query = query.with_preserve_case(false);
+        assert_eq!(query.replacement_for("FOO").as_deref(), Some("bar"));
+
+        assert_eq!(MatchCase::of("F"), MatchCase::Capitalized);
+        assert_eq!(MatchCase::of("FOO_BAR"), MatchCase::Upper);
+        assert_eq!(MatchCase::of("fooBar"), MatchCase::Other);
+        assert_eq!(MatchCase::Capitalized.apply("barBaz"), "BarBaz");
+    }
+
     #[test]
     fn path_matcher_creation_for_valid_paths() {
         for valid_path in [
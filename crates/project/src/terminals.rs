@@ -1,10 +1,10 @@
-use crate::Project;
+use crate::{Event, Project};
 use gpui::{AnyWindowHandle, Context, Entity, Model, ModelContext, WeakModel};
 use settings::Settings;
 use std::path::{Path, PathBuf};
 use terminal::{
-    terminal_settings::{self, TerminalSettings, VenvSettingsContent},
-    Terminal, TerminalBuilder,
+    terminal_settings::{self, Shell, TerminalSettings, VenvSettingsContent},
+    TaskStatus, Terminal, TerminalBuilder,
 };
 
 // #[cfg(target_os = "macos")]
@@ -12,6 +12,7 @@ use terminal::{
 
 pub struct Terminals {
     pub(crate) local_handles: Vec<WeakModel<terminal::Terminal>>,
+    pub(crate) next_task_id: usize,
 }
 
 impl Project {
@@ -20,6 +21,56 @@ impl Project {
         working_directory: Option<PathBuf>,
         window: AnyWindowHandle,
         cx: &mut ModelContext<Self>,
+    ) -> anyhow::Result<Model<Terminal>> {
+        self.create_terminal_internal(working_directory, None, window, cx)
+    }
+
+    /// Returns an id for a program run with [`Project::create_task_terminal`].
+    pub fn next_terminal_task_id(&mut self) -> usize {
+        let task_id = self.terminals.next_task_id;
+        self.terminals.next_task_id += 1;
+        task_id
+    }
+
+    /// Creates a terminal that runs the given program instead of a shell, and stays open once it
+    /// exits. [`Event::TerminalTaskFinished`] is emitted with the given id once the program exits
+    /// or the terminal is closed before then.
+    pub fn create_task_terminal(
+        &mut self,
+        task_id: usize,
+        command: String,
+        args: Vec<String>,
+        working_directory: Option<PathBuf>,
+        window: AnyWindowHandle,
+        cx: &mut ModelContext<Self>,
+    ) -> anyhow::Result<Model<Terminal>> {
+        let shell = Shell::WithArguments {
+            program: command,
+            args,
+        };
+        let terminal = self.create_terminal_internal(working_directory, Some(shell), window, cx)?;
+
+        cx.subscribe(&terminal, move |_, _, event, cx| {
+            if let terminal::Event::TaskFinished = event {
+                cx.emit(Event::TerminalTaskFinished(task_id));
+            }
+        })
+        .detach();
+        cx.observe_release(&terminal, move |_, terminal, cx| {
+            if terminal.task_status() == Some(TaskStatus::Running) {
+                cx.emit(Event::TerminalTaskFinished(task_id));
+            }
+        })
+        .detach();
+        Ok(terminal)
+    }
+
+    fn create_terminal_internal(
+        &mut self,
+        working_directory: Option<PathBuf>,
+        task_shell: Option<Shell>,
+        window: AnyWindowHandle,
+        cx: &mut ModelContext<Self>,
     ) -> anyhow::Result<Model<Terminal>> {
         if self.is_remote() {
             return Err(anyhow::anyhow!(
@@ -28,7 +79,8 @@ impl Project {
         } else {
             let settings = TerminalSettings::get_global(cx);
             let python_settings = settings.detect_venv.clone();
-            let shell = settings.shell.clone();
+            let is_task = task_shell.is_some();
+            let shell = task_shell.unwrap_or_else(|| settings.shell.clone());
 
             let terminal = TerminalBuilder::new(
                 working_directory.clone(),
@@ -39,6 +91,11 @@ impl Project {
                 window,
             )
             .map(|builder| {
+                let builder = if is_task {
+                    builder.run_as_task()
+                } else {
+                    builder
+                };
                 let terminal_handle = cx.new_model(|cx| builder.subscribe(cx));
 
                 self.terminals
@@ -59,7 +116,7 @@ impl Project {
                 })
                 .detach();
 
-                if let Some(python_settings) = &python_settings.as_option() {
+                if let Some(python_settings) = &python_settings.as_option().filter(|_| !is_task) {
                     let activate_command = Project::get_activate_command(python_settings);
                     let activate_script_path =
                         self.find_activate_script_path(python_settings, working_directory);
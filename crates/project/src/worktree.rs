@@ -2105,6 +2105,17 @@ impl LocalSnapshot {
         Some((path, self.git_repositories.get(&repo.work_directory_id())?))
     }
 
+    /// Returns the repository containing the given path and its work directory. This is the
+    /// repository opened by the worktree, which also follows `.git` files, such as the ones of
+    /// linked worktrees and submodules, to the git directory they point to.
+    pub fn local_git_repo_for_path(
+        &self,
+        path: &Path,
+    ) -> Option<(RepositoryWorkDirectory, Arc<Mutex<dyn GitRepository>>)> {
+        let (path, repo) = self.local_repo_for_path(path)?;
+        Some((path, repo.repo_ptr.clone()))
+    }
+
     fn build_update(
         &self,
         project_id: u64,
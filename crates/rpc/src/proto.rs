@@ -174,6 +174,8 @@ messages!(
     (GetChannelMessagesResponse, Background),
     (GetCodeActions, Background),
     (GetCodeActionsResponse, Background),
+    (GetCodeLens, Background),
+    (GetCodeLensResponse, Background),
     (GetCompletions, Background),
     (GetCompletionsResponse, Background),
     (GetDefinition, Background),
@@ -310,6 +312,7 @@ request_messages!(
     (GetChannelMessages, GetChannelMessagesResponse),
     (GetChannelMessagesById, GetChannelMessagesResponse),
     (GetCodeActions, GetCodeActionsResponse),
+    (GetCodeLens, GetCodeLensResponse),
     (GetCompletions, GetCompletionsResponse),
     (GetDefinition, GetDefinitionResponse),
     (GetDocumentHighlights, GetDocumentHighlightsResponse),
@@ -386,6 +389,7 @@ entity_messages!(
     ExpandProjectEntry,
     FormatBuffers,
     GetCodeActions,
+    GetCodeLens,
     GetCompletions,
     GetDefinition,
     GetDocumentHighlights,
@@ -6,7 +6,8 @@ use crate::{
     search_bar::render_nav_button,
     ActivateRegexMode, ActivateTextMode, CycleMode, NextHistoryQuery, PreviousHistoryQuery,
     ReplaceAll, ReplaceNext, SearchOptions, SelectAllMatches, SelectNextMatch, SelectPrevMatch,
-    ToggleCaseSensitive, ToggleReplace, ToggleWholeWord,
+    ToggleCaseSensitive, ToggleCurrentExcerpt, TogglePreserveCase, TogglePreviewReplacements,
+    ToggleReplace, ToggleWholeWord,
 };
 use collections::HashMap;
 use editor::{
@@ -237,6 +238,14 @@ impl Render for BufferSearchBar {
                             SearchOptions::WHOLE_WORD,
                             cx.listener(|this, _, cx| this.toggle_whole_word(&ToggleWholeWord, cx)),
                         )
+                    }))
+                    .children(supported_options.excerpt.then(|| {
+                        self.render_search_option_button(
+                            SearchOptions::CURRENT_EXCERPT,
+                            cx.listener(|this, _, cx| {
+                                this.toggle_current_excerpt(&ToggleCurrentExcerpt, cx)
+                            }),
+                        )
                     })),
             )
             .child(
@@ -346,6 +355,18 @@ impl Render for BufferSearchBar {
                             &self.replacement_editor,
                             cx.theme().colors().text,
                             cx,
+                        ))
+                        .child(self.render_search_option_button(
+                            SearchOptions::PRESERVE_CASE,
+                            cx.listener(|this, _, cx| {
+                                this.toggle_preserve_case(&TogglePreserveCase, cx)
+                            }),
+                        ))
+                        .child(self.render_search_option_button(
+                            SearchOptions::PREVIEW_REPLACEMENTS,
+                            cx.listener(|this, _, cx| {
+                                this.toggle_preview_replacements(&TogglePreviewReplacements, cx)
+                            }),
                         )),
                 )
                 .child(
@@ -389,6 +410,8 @@ impl Render for BufferSearchBar {
             }))
             .when(self.supported_options().replacement, |this| {
                 this.on_action(cx.listener(Self::toggle_replace))
+                    .on_action(cx.listener(Self::toggle_preserve_case))
+                    .on_action(cx.listener(Self::toggle_preview_replacements))
                     .when(in_replace, |this| {
                         this.on_action(cx.listener(Self::replace_next))
                             .on_action(cx.listener(Self::replace_all))
@@ -400,6 +423,9 @@ impl Render for BufferSearchBar {
             .when(self.supported_options().word, |this| {
                 this.on_action(cx.listener(Self::toggle_whole_word))
             })
+            .when(self.supported_options().excerpt, |this| {
+                this.on_action(cx.listener(Self::toggle_current_excerpt))
+            })
             .gap_2()
             .child(
                 h_flex().child(search_line.w_full()).child(
@@ -475,6 +501,23 @@ impl BufferSearchBar {
                 this.toggle_whole_word(action, cx);
             }
         }));
+        registrar.register_handler(ForDeployed(|this, action: &ToggleCurrentExcerpt, cx| {
+            if this.supported_options().excerpt {
+                this.toggle_current_excerpt(action, cx);
+            }
+        }));
+        registrar.register_handler(ForDeployed(|this, action: &TogglePreserveCase, cx| {
+            if this.supported_options().replacement {
+                this.toggle_preserve_case(action, cx);
+            }
+        }));
+        registrar.register_handler(ForDeployed(
+            |this, action: &TogglePreviewReplacements, cx| {
+                if this.supported_options().replacement {
+                    this.toggle_preview_replacements(action, cx);
+                }
+            },
+        ));
         registrar.register_handler(ForDeployed(|this, action: &ToggleReplace, cx| {
             if this.supported_options().replacement {
                 this.toggle_replace(action, cx);
@@ -756,6 +799,11 @@ impl BufferSearchBar {
                     .get(&searchable_item.downgrade())
                 {
                     searchable_item.select_matches(matches, cx);
+                    if self.replace_enabled
+                        && self.search_options.contains(SearchOptions::PRESERVE_CASE)
+                    {
+                        searchable_item.preserve_case_in_selections(cx);
+                    }
                     self.focus_editor(&FocusEditor, cx);
                 }
             }
@@ -821,15 +869,52 @@ impl BufferSearchBar {
         &mut self,
         _: View<Editor>,
         event: &editor::EditorEvent,
-        _: &mut ViewContext<Self>,
+        cx: &mut ViewContext<Self>,
     ) {
         match event {
             editor::EditorEvent::Focused => self.replacement_editor_focused = true,
             editor::EditorEvent::Blurred => self.replacement_editor_focused = false,
+            editor::EditorEvent::Edited => self.update_replace_preview(cx),
             _ => {}
         }
     }
 
+    /// Shows what replacing each match of the active item would produce while replace and the
+    /// preview of replacements are enabled, and clears it otherwise.
+    fn update_replace_preview(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(searchable_item) = self.active_searchable_item.as_ref() else {
+            return;
+        };
+        let matches = self
+            .searchable_items_with_matches
+            .get(&searchable_item.downgrade());
+        let query = self
+            .active_search
+            .as_ref()
+            .filter(|_| {
+                self.replace_enabled
+                    && !self.dismissed
+                    && self
+                        .search_options
+                        .contains(SearchOptions::PREVIEW_REPLACEMENTS)
+            })
+            .map(|query| self.replacement_query(query, cx));
+        match (matches, query) {
+            (Some(matches), Some(query)) => {
+                searchable_item.preview_replacements(matches, Some(&query), cx)
+            }
+            _ => searchable_item.preview_replacements(&Vec::new(), None, cx),
+        }
+    }
+
+    /// Returns the given query with the replacement and replace options of the search bar.
+    fn replacement_query(&self, query: &SearchQuery, cx: &WindowContext) -> SearchQuery {
+        query
+            .clone()
+            .with_replacement(self.replacement(cx))
+            .with_preserve_case(self.search_options.contains(SearchOptions::PRESERVE_CASE))
+    }
+
     fn on_active_searchable_item_event(&mut self, event: &SearchEvent, cx: &mut ViewContext<Self>) {
         match event {
             SearchEvent::MatchesInvalidated => {
@@ -845,6 +930,19 @@ impl BufferSearchBar {
     fn toggle_whole_word(&mut self, _: &ToggleWholeWord, cx: &mut ViewContext<Self>) {
         self.toggle_search_option(SearchOptions::WHOLE_WORD, cx)
     }
+    fn toggle_current_excerpt(&mut self, _: &ToggleCurrentExcerpt, cx: &mut ViewContext<Self>) {
+        self.toggle_search_option(SearchOptions::CURRENT_EXCERPT, cx)
+    }
+    fn toggle_preserve_case(&mut self, _: &TogglePreserveCase, cx: &mut ViewContext<Self>) {
+        self.toggle_search_option(SearchOptions::PRESERVE_CASE, cx)
+    }
+    fn toggle_preview_replacements(
+        &mut self,
+        _: &TogglePreviewReplacements,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.toggle_search_option(SearchOptions::PREVIEW_REPLACEMENTS, cx)
+    }
 
     fn clear_active_searchable_item_matches(&mut self, cx: &mut WindowContext) {
         if let Some(active_searchable_item) = self.active_searchable_item.as_ref() {
@@ -924,6 +1022,10 @@ impl BufferSearchBar {
                 self.active_search = Some(query.clone());
                 let query_text = query.as_str().to_string();
 
+                active_searchable_item.toggle_search_within_excerpt(
+                    self.search_options.contains(SearchOptions::CURRENT_EXCERPT),
+                    cx,
+                );
                 let matches = active_searchable_item.find_matches(query, cx);
 
                 let active_searchable_item = active_searchable_item.downgrade();
@@ -945,6 +1047,7 @@ impl BufferSearchBar {
                                     .get(&active_searchable_item.downgrade())
                                     .unwrap();
                                 active_searchable_item.update_matches(matches, cx);
+                                this.update_replace_preview(cx);
                                 let _ = done_tx.send(());
                             }
                             cx.notify();
@@ -1026,6 +1129,7 @@ impl BufferSearchBar {
     fn toggle_replace(&mut self, _: &ToggleReplace, cx: &mut ViewContext<Self>) {
         if let Some(_) = &self.active_searchable_item {
             self.replace_enabled = !self.replace_enabled;
+            self.update_replace_preview(cx);
             let handle = if self.replace_enabled {
                 self.replacement_editor.focus_handle(cx)
             } else {
@@ -1045,10 +1149,7 @@ impl BufferSearchBar {
                         .get(&searchable_item.downgrade())
                     {
                         if let Some(active_index) = self.active_match_index {
-                            let query = query
-                                .as_ref()
-                                .clone()
-                                .with_replacement(self.replacement(cx));
+                            let query = self.replacement_query(query, cx);
                             searchable_item.replace(&matches[active_index], &query, cx);
                             self.select_next_match(&SelectNextMatch, cx);
                         }
@@ -1070,10 +1171,7 @@ impl BufferSearchBar {
                         .searchable_items_with_matches
                         .get(&searchable_item.downgrade())
                     {
-                        let query = query
-                            .as_ref()
-                            .clone()
-                            .with_replacement(self.replacement(cx));
+                        let query = self.replacement_query(query, cx);
                         for m in matches {
                             searchable_item.replace(m, &query, cx);
                         }
@@ -1816,6 +1914,71 @@ mod tests {
         });
     }
 
+    #[gpui::test]
+    async fn test_replace_preview(cx: &mut TestAppContext) {
+        let (editor, search_bar, cx) = init_test(cx);
+
+        search_bar.update(cx, |search_bar, cx| {
+            search_bar.toggle_replace(&ToggleReplace, cx)
+        });
+        search_bar
+            .update(cx, |search_bar, cx| {
+                search_bar.search("expression", Some(SearchOptions::PREVIEW_REPLACEMENTS), cx)
+            })
+            .await
+            .unwrap();
+        search_bar.update(cx, |search_bar, cx| {
+            search_bar.replacement_editor.update(cx, |editor, cx| {
+                editor.set_text("expr", cx);
+            });
+        });
+        cx.run_until_parked();
+
+        // Only the matches in the visible rows are previewed, which is the first row in an editor
+        // that wasn't laid out, and the buffer isn't edited.
+        let preview = editor.update(cx, |editor, cx| {
+            assert!(!editor.text(cx).contains("expressionexpr"));
+            editor.display_text(cx)
+        });
+        let mut lines = preview.lines();
+        assert_eq!(
+            lines.next(),
+            Some("A regular expressionexpr (shortened as regex or regexp;[1] also referred to as")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("rational expression[2][3]) is a sequence of characters that specifies a search")
+        );
+
+        // The preview is only shown while it's enabled.
+        search_bar.update(cx, |search_bar, cx| {
+            search_bar.toggle_preview_replacements(&TogglePreviewReplacements, cx)
+        });
+        cx.run_until_parked();
+        editor.update(cx, |editor, cx| {
+            assert_eq!(editor.display_text(cx), editor.text(cx));
+        });
+        search_bar.update(cx, |search_bar, cx| {
+            search_bar.toggle_preview_replacements(&TogglePreviewReplacements, cx)
+        });
+        cx.run_until_parked();
+
+        // Applying all the previewed replacements also replaces the matches that aren't visible.
+        editor.update(cx, |editor, cx| {
+            editor.apply_all_replace_previews(&editor::actions::ApplyAllReplacePreviews, cx)
+        });
+        assert_eq!(
+            editor.update(cx, |editor, cx| editor.text(cx)),
+            r#"
+        A regular expr (shortened as regex or regexp;[1] also referred to as
+        rational expr[2][3]) is a sequence of characters that specifies a search
+        pattern in text. Usually such patterns are used by string-searching algorithms
+        for "find" or "find and replace" operations on strings, or for input validation.
+        "#
+            .unindent()
+        );
+    }
+
     #[gpui::test]
     async fn test_replace_simple(cx: &mut TestAppContext) {
         let (editor, search_bar, cx) = init_test(cx);
@@ -1922,6 +2085,38 @@ mod tests {
         );
     }
 
+    #[gpui::test]
+    async fn test_replace_preserving_case(cx: &mut TestAppContext) {
+        let (editor, search_bar, cx) = init_test(cx);
+
+        search_bar
+            .update(cx, |search_bar, cx| {
+                search_bar.search(
+                    "a",
+                    Some(SearchOptions::WHOLE_WORD | SearchOptions::PRESERVE_CASE),
+                    cx,
+                )
+            })
+            .await
+            .unwrap();
+        search_bar.update(cx, |search_bar, cx| {
+            search_bar.replacement_editor.update(cx, |editor, cx| {
+                editor.set_text("the", cx);
+            });
+            search_bar.replace_all(&ReplaceAll, cx)
+        });
+        assert_eq!(
+            editor.update(cx, |this, cx| { this.text(cx) }),
+            r#"
+        The regular expression (shortened as regex or regexp;[1] also referred to as
+        rational expression[2][3]) is the sequence of characters that specifies the search
+        pattern in text. Usually such patterns are used by string-searching algorithms
+        for "find" or "find and replace" operations on strings, or for input validation.
+        "#
+            .unindent()
+        );
+    }
+
     #[gpui::test]
     async fn test_invalid_regexp_search_after_valid(cx: &mut TestAppContext) {
         let (editor, search_bar, cx) = init_test(cx);
@@ -26,6 +26,9 @@ actions!(
         ToggleWholeWord,
         ToggleCaseSensitive,
         ToggleIncludeIgnored,
+        ToggleCurrentExcerpt,
+        TogglePreserveCase,
+        TogglePreviewReplacements,
         ToggleReplace,
         SelectNextMatch,
         SelectPrevMatch,
@@ -43,10 +46,13 @@ actions!(
 bitflags! {
     #[derive(Default)]
     pub struct SearchOptions: u8 {
-        const NONE = 0b000;
-        const WHOLE_WORD = 0b001;
-        const CASE_SENSITIVE = 0b010;
-        const INCLUDE_IGNORED = 0b100;
+        const NONE = 0b000000;
+        const WHOLE_WORD = 0b000001;
+        const CASE_SENSITIVE = 0b000010;
+        const INCLUDE_IGNORED = 0b000100;
+        const CURRENT_EXCERPT = 0b001000;
+        const PRESERVE_CASE = 0b010000;
+        const PREVIEW_REPLACEMENTS = 0b100000;
     }
 }
 
@@ -56,6 +62,9 @@ impl SearchOptions {
             SearchOptions::WHOLE_WORD => "Match Whole Word",
             SearchOptions::CASE_SENSITIVE => "Match Case",
             SearchOptions::INCLUDE_IGNORED => "Include ignored",
+            SearchOptions::CURRENT_EXCERPT => "Search In Current Excerpt",
+            SearchOptions::PRESERVE_CASE => "Preserve Case",
+            SearchOptions::PREVIEW_REPLACEMENTS => "Preview Replacements",
             _ => panic!("{:?} is not a named SearchOption", self),
         }
     }
@@ -65,6 +74,9 @@ impl SearchOptions {
             SearchOptions::WHOLE_WORD => ui::IconName::WholeWord,
             SearchOptions::CASE_SENSITIVE => ui::IconName::CaseSensitive,
             SearchOptions::INCLUDE_IGNORED => ui::IconName::FileGit,
+            SearchOptions::CURRENT_EXCERPT => ui::IconName::Filter,
+            SearchOptions::PRESERVE_CASE => ui::IconName::PreserveCase,
+            SearchOptions::PREVIEW_REPLACEMENTS => ui::IconName::InlayHint,
             _ => panic!("{:?} is not a named SearchOption", self),
         }
     }
@@ -74,6 +86,9 @@ impl SearchOptions {
             SearchOptions::WHOLE_WORD => Box::new(ToggleWholeWord),
             SearchOptions::CASE_SENSITIVE => Box::new(ToggleCaseSensitive),
             SearchOptions::INCLUDE_IGNORED => Box::new(ToggleIncludeIgnored),
+            SearchOptions::CURRENT_EXCERPT => Box::new(ToggleCurrentExcerpt),
+            SearchOptions::PRESERVE_CASE => Box::new(TogglePreserveCase),
+            SearchOptions::PREVIEW_REPLACEMENTS => Box::new(TogglePreviewReplacements),
             _ => panic!("{:?} is not a named SearchOption", self),
         }
     }
@@ -87,6 +102,7 @@ impl SearchOptions {
         options.set(SearchOptions::WHOLE_WORD, query.whole_word());
         options.set(SearchOptions::CASE_SENSITIVE, query.case_sensitive());
         options.set(SearchOptions::INCLUDE_IGNORED, query.include_ignored());
+        options.set(SearchOptions::PRESERVE_CASE, query.preserve_case());
         options
     }
 
@@ -84,6 +84,15 @@ pub enum Event {
     SelectionsChanged,
     NewNavigationTarget(Option<MaybeNavigationTarget>),
     Open(MaybeNavigationTarget),
+    /// The program run by a task terminal exited.
+    TaskFinished,
+}
+
+/// Whether the program run by a task terminal, rather than an interactive shell, has exited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskStatus {
+    Running,
+    Completed,
 }
 
 #[derive(Clone, Debug)]
@@ -402,6 +411,7 @@ impl TerminalBuilder {
             hovered_word: false,
             url_regex,
             word_regex,
+            task_status: None,
         };
 
         Ok(TerminalBuilder {
@@ -410,6 +420,13 @@ impl TerminalBuilder {
         })
     }
 
+    /// Makes the terminal run its program as a task, which keeps the terminal open once the
+    /// program exits.
+    pub fn run_as_task(mut self) -> Self {
+        self.terminal.task_status = Some(TaskStatus::Running);
+        self
+    }
+
     pub fn subscribe(mut self, cx: &mut ModelContext<Terminal>) -> Terminal {
         //Event loop
         cx.spawn(|this, mut cx| async move {
@@ -558,6 +575,7 @@ pub struct Terminal {
     hovered_word: bool,
     url_regex: RegexSearch,
     word_regex: RegexSearch,
+    task_status: Option<TaskStatus>,
 }
 
 impl Terminal {
@@ -589,7 +607,13 @@ impl Terminal {
             AlacTermEvent::Bell => {
                 cx.emit(Event::Bell);
             }
-            AlacTermEvent::Exit => cx.emit(Event::CloseTerminal),
+            AlacTermEvent::Exit => match &mut self.task_status {
+                Some(status) => {
+                    *status = TaskStatus::Completed;
+                    cx.emit(Event::TaskFinished);
+                }
+                None => cx.emit(Event::CloseTerminal),
+            },
             AlacTermEvent::MouseCursorDirty => {
                 //NOOP, Handled in render
             }
@@ -607,6 +631,10 @@ impl Terminal {
         }
     }
 
+    pub fn task_status(&self) -> Option<TaskStatus> {
+        self.task_status
+    }
+
     pub fn selection_started(&self) -> bool {
         self.selection_phase == SelectionPhase::Selecting
     }
@@ -1,4 +1,8 @@
-use std::{ops::ControlFlow, path::PathBuf, sync::Arc};
+use std::{
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use crate::TerminalView;
 use db::kvp::KEY_VALUE_STORE;
@@ -12,7 +16,7 @@ use project::{Fs, ProjectEntryId};
 use search::{buffer_search::DivRegistrar, BufferSearchBar};
 use serde::{Deserialize, Serialize};
 use settings::Settings;
-use terminal::terminal_settings::{TerminalDockPosition, TerminalSettings};
+use terminal::terminal_settings::{Shell, TerminalDockPosition, TerminalSettings};
 use ui::{h_flex, ButtonCommon, Clickable, IconButton, IconSize, Selectable, Tooltip};
 use util::{ResultExt, TryFutureExt};
 use workspace::{
@@ -27,6 +31,9 @@ use anyhow::Result;
 
 const TERMINAL_PANEL_KEY: &'static str = "TerminalPanel";
 
+/// The shells that can run the POSIX shell scripts of tasks.
+const POSIX_SHELLS: &[&str] = &["sh", "bash", "zsh", "dash", "ksh"];
+
 actions!(terminal_panel, [ToggleFocus]);
 
 pub fn init(cx: &mut AppContext) {
@@ -34,6 +41,7 @@ pub fn init(cx: &mut AppContext) {
         |workspace: &mut Workspace, _: &mut ViewContext<Workspace>| {
             workspace.register_action(TerminalPanel::new_terminal);
             workspace.register_action(TerminalPanel::open_terminal);
+            workspace.register_action(TerminalPanel::run_in_terminal);
             workspace.register_action(|workspace, _: &ToggleFocus, cx| {
                 workspace.toggle_panel_focus::<TerminalPanel>(cx);
             });
@@ -75,7 +83,7 @@ impl TerminalPanel {
                             .icon_size(IconSize::Small)
                             .on_click(move |_, cx| {
                                 terminal_panel
-                                    .update(cx, |panel, cx| panel.add_terminal(None, cx))
+                                    .update(cx, |panel, cx| panel.add_terminal(None, None, cx))
                                     .log_err();
                             })
                             .tooltip(|cx| Tooltip::text("New Terminal", cx)),
@@ -266,7 +274,22 @@ impl TerminalPanel {
         };
 
         this.update(cx, |this, cx| {
-            this.add_terminal(Some(action.working_directory.clone()), cx)
+            this.add_terminal(Some(action.working_directory.clone()), None, cx)
+        })
+    }
+
+    /// Runs a program in a new terminal, such as a test run from the editor's gutter.
+    pub fn run_in_terminal(
+        workspace: &mut Workspace,
+        action: &workspace::RunInTerminal,
+        cx: &mut ViewContext<Workspace>,
+    ) {
+        let Some(this) = workspace.focus_panel::<Self>(cx) else {
+            return;
+        };
+
+        this.update(cx, |this, cx| {
+            this.add_terminal(action.working_directory.clone(), Some(action.clone()), cx)
         })
     }
 
@@ -280,10 +303,16 @@ impl TerminalPanel {
             return;
         };
 
-        this.update(cx, |this, cx| this.add_terminal(None, cx))
+        this.update(cx, |this, cx| this.add_terminal(None, None, cx))
     }
 
-    fn add_terminal(&mut self, working_directory: Option<PathBuf>, cx: &mut ViewContext<Self>) {
+    /// Adds a terminal, which runs the given task's program instead of a shell if there is one.
+    fn add_terminal(
+        &mut self,
+        working_directory: Option<PathBuf>,
+        task: Option<workspace::RunInTerminal>,
+        cx: &mut ViewContext<Self>,
+    ) {
         let workspace = self.workspace.clone();
         cx.spawn(|this, mut cx| async move {
             let pane = this.update(&mut cx, |this, _| this.pane.clone())?;
@@ -298,9 +327,25 @@ impl TerminalPanel {
 
                 let window = cx.window_handle();
                 if let Some(terminal) = workspace.project().update(cx, |project, cx| {
-                    project
-                        .create_terminal(working_directory, window, cx)
-                        .log_err()
+                    let terminal = if let Some(task) = task {
+                        let (command, args) = if task.is_shell_script {
+                            let shell = &TerminalSettings::get_global(cx).shell;
+                            shell_script_command(shell, task.command)
+                        } else {
+                            (task.command, task.args)
+                        };
+                        project.create_task_terminal(
+                            task.task_id,
+                            command,
+                            args,
+                            working_directory,
+                            window,
+                            cx,
+                        )
+                    } else {
+                        project.create_terminal(working_directory, window, cx)
+                    };
+                    terminal.log_err()
                 }) {
                     let terminal = Box::new(cx.new_view(|cx| {
                         TerminalView::new(
@@ -356,6 +401,26 @@ impl TerminalPanel {
     }
 }
 
+/// Returns the program and arguments that run the given POSIX shell script with the terminal's
+/// shell, or with `sh` if the terminal's shell can't run it.
+fn shell_script_command(shell: &Shell, script: String) -> (String, Vec<String>) {
+    let (program, mut args) = match shell {
+        Shell::System => (std::env::var("SHELL").unwrap_or_default(), Vec::new()),
+        Shell::Program(program) => (program.clone(), Vec::new()),
+        Shell::WithArguments { program, args } => (program.clone(), args.clone()),
+    };
+    let is_posix_shell = Path::new(&program)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map_or(false, |name| POSIX_SHELLS.contains(&name));
+    if !is_posix_shell {
+        return ("sh".into(), vec!["-c".into(), script]);
+    }
+    args.push("-c".into());
+    args.push(script);
+    (program, args)
+}
+
 fn add_paths_to_terminal(pane: &mut Pane, paths: &[PathBuf], cx: &mut ViewContext<'_, Pane>) {
     if let Some(terminal_view) = pane
         .active_item()
@@ -451,7 +516,7 @@ impl Panel for TerminalPanel {
 
     fn set_active(&mut self, active: bool, cx: &mut ViewContext<Self>) {
         if active && self.pane.read(cx).items_len() == 0 {
-            self.add_terminal(None, cx)
+            self.add_terminal(None, None, cx)
         }
     }
 
@@ -294,6 +294,7 @@ impl TerminalView {
             },
             Event::BreadcrumbsChanged => cx.emit(ItemEvent::UpdateBreadcrumbs),
             Event::CloseTerminal => cx.emit(ItemEvent::CloseItem),
+            Event::TaskFinished => cx.emit(ItemEvent::UpdateTab),
             Event::SelectionsChanged => cx.emit(SearchEvent::ActiveMatchChanged),
         })
         .detach();
@@ -878,6 +879,7 @@ impl SearchableItem for TerminalView {
             word: false,
             regex: true,
             replacement: false,
+            excerpt: false,
         }
     }
 
@@ -524,6 +524,37 @@ fn test_undo_redo() {
     assert_eq!(buffer.text(), "1234");
 }
 
+#[test]
+fn test_undo_in_ranges() {
+    let mut buffer = Buffer::new(0, BufferId::new(1).unwrap(), "abc def".into());
+    buffer.set_group_interval(Duration::from_secs(0));
+    assert!(buffer.undo_in_ranges(&[0..0]).is_none());
+
+    buffer.edit([(0..0, "1")]);
+    buffer.start_transaction();
+    buffer.edit([(4..4, "2")]);
+    buffer.edit([(9..9, "3")]);
+    buffer.end_transaction();
+    assert_eq!(buffer.text(), "1abc2 def3");
+
+    // Transactions after the one touching the range stay applied.
+    buffer.undo_in_ranges(&[0..1]).unwrap();
+    assert_eq!(buffer.text(), "abc2 def3");
+
+    // Only the edits of a transaction that touch the range are undone.
+    buffer.undo_in_ranges(&[8..8]).unwrap();
+    assert_eq!(buffer.text(), "abc2 def");
+    buffer.undo();
+    assert_eq!(buffer.text(), "abc def");
+
+    buffer.redo();
+    assert_eq!(buffer.text(), "abc2 def");
+    buffer.redo();
+    assert_eq!(buffer.text(), "abc2 def3");
+    buffer.redo();
+    assert_eq!(buffer.text(), "1abc2 def3");
+}
+
 #[test]
 fn test_history() {
     let mut now = Instant::now();
@@ -626,6 +657,112 @@ fn test_finalize_last_transaction() {
     assert_eq!(buffer.text(), "ab2cde6");
 }
 
+#[test]
+fn test_undo_tree() {
+    let mut buffer = Buffer::new(0, BufferId::new(1).unwrap(), "123456".into());
+
+    buffer.edit([(0..1, "a")]);
+    let transaction_1 = buffer.undo_tree().current().unwrap();
+    buffer.edit([(1..2, "b")]);
+    let transaction_2 = buffer.undo_tree().current().unwrap();
+    assert_eq!(buffer.text(), "ab3456");
+
+    // Editing after undoing starts a new branch instead of discarding the undone transaction.
+    buffer.undo();
+    assert_eq!(buffer.undo_tree().current(), Some(transaction_1));
+    buffer.edit([(2..3, "c")]);
+    let transaction_3 = buffer.undo_tree().current().unwrap();
+    assert_eq!(buffer.text(), "a2c456");
+    assert_eq!(
+        buffer
+            .undo_tree()
+            .children(Some(transaction_1))
+            .map(|node| node.transaction_id())
+            .collect::<Vec<_>>(),
+        vec![transaction_2, transaction_3]
+    );
+    assert!(buffer.redo().is_none());
+
+    // Jumping to another branch undoes and redoes the transactions in between.
+    buffer.go_to_undo_tree_node(Some(transaction_2));
+    assert_eq!(buffer.text(), "ab3456");
+    assert_eq!(buffer.undo_tree().current(), Some(transaction_2));
+    buffer.undo();
+    assert_eq!(buffer.text(), "a23456");
+    buffer.redo();
+    assert_eq!(buffer.text(), "ab3456");
+
+    // Redoing after jumping follows the most recent branch.
+    buffer.go_to_undo_tree_node(None);
+    assert_eq!(buffer.text(), "123456");
+    assert_eq!(buffer.undo_tree().current(), None);
+    buffer.redo();
+    buffer.redo();
+    assert_eq!(buffer.text(), "a2c456");
+    assert_eq!(buffer.undo_tree().current(), Some(transaction_3));
+    assert_eq!(
+        buffer.undo_tree().path_to(Some(transaction_3)),
+        vec![transaction_1, transaction_3]
+    );
+
+    // Restore points name states of the tree along with their text.
+    buffer.go_to_undo_tree_node(Some(transaction_2));
+    buffer.add_restore_point("two");
+    buffer.go_to_undo_tree_node(Some(transaction_3));
+    buffer.add_restore_point("three");
+    let restore_points = |buffer: &Buffer| {
+        buffer
+            .undo_tree()
+            .restore_points()
+            .iter()
+            .map(|point| (point.transaction_id(), point.text().to_string()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        restore_points(&buffer),
+        vec![
+            (Some(transaction_2), "ab3456".to_string()),
+            (Some(transaction_3), "a2c456".to_string())
+        ]
+    );
+
+    // Forgetting a transaction removes its node and restore points, leaving the nodes after it in
+    // place.
+    buffer.forget_transaction(transaction_2);
+    assert!(buffer.undo_tree().node(transaction_2).is_none());
+    assert_eq!(
+        buffer
+            .undo_tree()
+            .node(transaction_3)
+            .map(|node| node.parent()),
+        Some(Some(transaction_1))
+    );
+    assert_eq!(
+        restore_points(&buffer),
+        vec![(Some(transaction_3), "a2c456".to_string())]
+    );
+
+    // Transactions grouped into the previous one extend its node instead of adding their own.
+    let now = Instant::now();
+    buffer.set_group_interval(Duration::from_millis(300));
+    buffer.start_transaction_at(now);
+    buffer.edit([(3..4, "d")]);
+    let transaction_4 = buffer.end_transaction_at(now).unwrap().0;
+    buffer.start_transaction_at(now);
+    buffer.edit([(4..5, "e")]);
+    buffer.end_transaction_at(now);
+    assert_eq!(buffer.undo_tree().current(), Some(transaction_4));
+    assert_eq!(
+        buffer
+            .undo_tree()
+            .children(Some(transaction_3))
+            .map(|node| (node.transaction_id(), node.transaction().edit_ids.len()))
+            .collect::<Vec<_>>(),
+        vec![(transaction_4, 2)]
+    );
+    assert_eq!(buffer.undo_tree().nodes().count(), 3);
+}
+
 #[test]
 fn test_edited_ranges_for_transaction() {
     let now = Instant::now();
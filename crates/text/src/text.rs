@@ -9,6 +9,7 @@ pub mod subscription;
 #[cfg(test)]
 mod tests;
 mod undo_map;
+mod undo_tree;
 
 pub use anchor::*;
 use anyhow::{anyhow, Context as _, Result};
@@ -28,7 +29,7 @@ use std::{
     cmp::{self, Ordering, Reverse},
     fmt::Display,
     future::Future,
-    iter::Iterator,
+    iter::{self, Iterator},
     num::NonZeroU64,
     ops::{self, Deref, Range, Sub},
     str,
@@ -39,6 +40,7 @@ pub use subscription::*;
 pub use sum_tree::Bias;
 use sum_tree::{FilterCursor, SumTree, TreeMap};
 use undo_map::UndoMap;
+pub use undo_tree::{RestorePoint, UndoTree, UndoTreeNode};
 use util::ResultExt;
 
 #[cfg(any(test, feature = "test-support"))]
@@ -131,6 +133,7 @@ struct History {
     insertion_slices: HashMap<clock::Lamport, Vec<InsertionSlice>>,
     undo_stack: Vec<HistoryEntry>,
     redo_stack: Vec<HistoryEntry>,
+    undo_tree: UndoTree,
     transaction_depth: usize,
     group_interval: Duration,
 }
@@ -149,6 +152,7 @@ impl History {
             insertion_slices: Default::default(),
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
+            undo_tree: UndoTree::default(),
             transaction_depth: 0,
             // Don't group transactions in tests unless we opt in, because it's a footgun.
             #[cfg(any(test, feature = "test-support"))]
@@ -248,6 +252,7 @@ impl History {
         let new_len = self.undo_stack.len() - n;
         let (entries_to_keep, entries_to_merge) = self.undo_stack.split_at_mut(new_len);
         if let Some(last_entry) = entries_to_keep.last_mut() {
+            let edit_ix = last_entry.transaction.edit_ids.len();
             for entry in &*entries_to_merge {
                 for edit_id in &entry.transaction.edit_ids {
                     last_entry.transaction.edit_ids.push(*edit_id);
@@ -256,10 +261,14 @@ impl History {
 
             if let Some(entry) = entries_to_merge.last_mut() {
                 last_entry.last_edit_at = entry.last_edit_at;
+                self.undo_tree.append_edits(last_entry, edit_ix);
             }
         }
 
-        self.undo_stack.truncate(new_len);
+        for entry in self.undo_stack.drain(new_len..) {
+            self.undo_tree.remove(entry.transaction.id);
+        }
+        self.update_undo_tree();
         self.undo_stack.last().map(|e| e.transaction.id)
     }
 
@@ -279,6 +288,7 @@ impl History {
             suppress_grouping: false,
         });
         self.redo_stack.clear();
+        self.update_undo_tree();
     }
 
     fn push_undo(&mut self, op_id: clock::Lamport) {
@@ -293,6 +303,7 @@ impl History {
         assert_eq!(self.transaction_depth, 0);
         if let Some(entry) = self.undo_stack.pop() {
             self.redo_stack.push(entry);
+            self.update_undo_tree();
             self.redo_stack.last()
         } else {
             None
@@ -307,7 +318,13 @@ impl History {
             .iter()
             .rposition(|entry| entry.transaction.id == transaction_id)?;
         let entry = self.undo_stack.remove(entry_ix);
+        // Undoing a transaction other than the last one leaves the transactions after it applied,
+        // so it can no longer be part of the path to the current state in the undo tree.
+        if entry_ix < self.undo_stack.len() {
+            self.undo_tree.remove(transaction_id);
+        }
         self.redo_stack.push(entry);
+        self.update_undo_tree();
         self.redo_stack.last()
     }
 
@@ -323,11 +340,48 @@ impl History {
             self.redo_stack
                 .extend(self.undo_stack.drain(entry_ix..).rev());
         }
+        self.update_undo_tree();
         &self.redo_stack[redo_stack_start_len..]
     }
 
+    /// Moves the given edits of a transaction on the undo stack into a transaction of their own on
+    /// the redo stack, so that they can be undone while the rest of the transaction stays applied.
+    fn split_from_undo(
+        &mut self,
+        transaction_id: TransactionId,
+        edit_ids: &[clock::Lamport],
+        clock: &mut clock::Lamport,
+    ) -> Option<&HistoryEntry> {
+        assert_eq!(self.transaction_depth, 0);
+
+        let entry = self
+            .undo_stack
+            .iter_mut()
+            .rfind(|entry| entry.transaction.id == transaction_id)?;
+        entry
+            .transaction
+            .edit_ids
+            .retain(|edit_id| !edit_ids.contains(edit_id));
+        let split_entry = HistoryEntry {
+            transaction: Transaction {
+                id: clock.tick(),
+                edit_ids: edit_ids.to_vec(),
+                start: entry.transaction.start.clone(),
+            },
+            first_edit_at: entry.first_edit_at,
+            last_edit_at: entry.last_edit_at,
+            suppress_grouping: true,
+        };
+        // The transaction's node in the undo tree no longer matches the edits it contains.
+        self.undo_tree.remove(transaction_id);
+        self.redo_stack.push(split_entry);
+        self.update_undo_tree();
+        self.redo_stack.last()
+    }
+
     fn forget(&mut self, transaction_id: TransactionId) -> Option<Transaction> {
         assert_eq!(self.transaction_depth, 0);
+        self.undo_tree.remove(transaction_id);
         if let Some(entry_ix) = self
             .undo_stack
             .iter()
@@ -363,6 +417,14 @@ impl History {
             if let Some(destination) = self.transaction_mut(destination) {
                 destination.edit_ids.extend(transaction.edit_ids);
             }
+            if let Some(entry) = self
+                .undo_stack
+                .iter()
+                .chain(&self.redo_stack)
+                .find(|entry| entry.transaction.id == destination)
+            {
+                self.undo_tree.update(entry);
+            }
         }
     }
 
@@ -370,6 +432,7 @@ impl History {
         assert_eq!(self.transaction_depth, 0);
         if let Some(entry) = self.redo_stack.pop() {
             self.undo_stack.push(entry);
+            self.update_undo_tree();
             self.undo_stack.last()
         } else {
             None
@@ -388,8 +451,46 @@ impl History {
             self.undo_stack
                 .extend(self.redo_stack.drain(entry_ix..).rev());
         }
+        self.update_undo_tree();
         &self.undo_stack[undo_stack_start_len..]
     }
+
+    /// Records the transaction at the top of the undo stack as the current state of the undo
+    /// tree, applied on top of the transaction below it.
+    fn update_undo_tree(&mut self) {
+        let mut entries = self.undo_stack.iter().rev();
+        if let Some(entry) = entries.next() {
+            let parent = entries.next().map(|entry| entry.transaction.id);
+            self.undo_tree.record(entry, parent);
+        } else {
+            self.undo_tree.set_current(None);
+        }
+    }
+
+    /// Replaces the undo and redo stacks with the path to the given node of the undo tree and
+    /// its most recent descendants, respectively.
+    fn reset_stacks_to_undo_tree_node(&mut self, transaction_id: Option<TransactionId>) {
+        let entry = |node: &UndoTreeNode| HistoryEntry {
+            // Never group new transactions with the ones that were jumped to.
+            suppress_grouping: true,
+            ..node.entry.clone()
+        };
+        self.undo_stack = self
+            .undo_tree
+            .path_to(transaction_id)
+            .into_iter()
+            .filter_map(|transaction_id| self.undo_tree.node(transaction_id))
+            .map(entry)
+            .collect();
+        self.redo_stack.clear();
+        let mut parent = transaction_id;
+        while let Some(child) = self.undo_tree.children(parent).next_back() {
+            self.redo_stack.push(entry(child));
+            parent = Some(child.transaction_id());
+        }
+        self.redo_stack.reverse();
+        self.undo_tree.set_current(transaction_id);
+    }
 }
 
 struct Edits<'a, D: TextDimension, F: FnMut(&FragmentSummary) -> bool> {
@@ -1221,6 +1322,50 @@ impl Buffer {
         self.undo_or_redo(transaction).log_err()
     }
 
+    /// Undoes the edits of the most recent transaction that touch any of the given ranges, leaving
+    /// the other edits of that transaction and the transactions after it applied.
+    pub fn undo_in_ranges(
+        &mut self,
+        ranges: &[Range<usize>],
+    ) -> Option<(TransactionId, Operation)> {
+        let (transaction_id, edit_ids, whole_transaction) =
+            self.history.undo_stack.iter().rev().find_map(|entry| {
+                let edit_ids = entry
+                    .transaction
+                    .edit_ids
+                    .iter()
+                    .filter(|&edit_id| {
+                        self.edited_ranges_for_edit_ids::<usize>(iter::once(edit_id))
+                            .any(|edited| {
+                                ranges.iter().any(|range| {
+                                    edited.start <= range.end && range.start <= edited.end
+                                })
+                            })
+                    })
+                    .copied()
+                    .collect::<Vec<_>>();
+                let whole_transaction = edit_ids.len() == entry.transaction.edit_ids.len();
+                (!edit_ids.is_empty()).then_some((
+                    entry.transaction.id,
+                    edit_ids,
+                    whole_transaction,
+                ))
+            })?;
+
+        if whole_transaction {
+            let operation = self.undo_transaction(transaction_id)?;
+            return Some((transaction_id, operation));
+        }
+
+        let transaction = self
+            .history
+            .split_from_undo(transaction_id, &edit_ids, &mut self.lamport_clock)?
+            .transaction
+            .clone();
+        let operation = self.undo_or_redo(transaction).log_err()?;
+        Some((transaction_id, operation))
+    }
+
     pub fn undo_to_transaction(&mut self, transaction_id: TransactionId) -> Vec<Operation> {
         let transactions = self
             .history
@@ -1239,6 +1384,14 @@ impl Buffer {
         self.history.forget(transaction_id);
     }
 
+    /// Returns whether the given transaction is on the undo stack, i.e. it hasn't been undone.
+    pub fn is_transaction_undoable(&self, transaction_id: TransactionId) -> bool {
+        self.history
+            .undo_stack
+            .iter()
+            .any(|entry| entry.transaction.id == transaction_id)
+    }
+
     pub fn merge_transactions(&mut self, transaction: TransactionId, destination: TransactionId) {
         self.history.merge_transactions(transaction, destination);
     }
@@ -1254,6 +1407,52 @@ impl Buffer {
         }
     }
 
+    pub fn undo_tree(&self) -> &UndoTree {
+        &self.history.undo_tree
+    }
+
+    /// Names the current state of the buffer's undo tree, so that it can be returned to with
+    /// [`Buffer::go_to_undo_tree_node`]. The last transaction is finalized, so that later edits
+    /// are not grouped into the named state.
+    pub fn add_restore_point(&mut self, name: impl Into<Arc<str>>) {
+        self.history.finalize_last_transaction();
+        let text = self.visible_text.clone();
+        self.history.undo_tree.add_restore_point(name.into(), text);
+    }
+
+    /// Moves the buffer to the state of the given node of its undo tree, or to its original text
+    /// if `transaction_id` is `None`, by undoing the transactions from the current state up to
+    /// the closest common ancestor and redoing the ones from there down to the target.
+    pub fn go_to_undo_tree_node(
+        &mut self,
+        transaction_id: Option<TransactionId>,
+    ) -> Vec<Operation> {
+        let undo_tree = &self.history.undo_tree;
+        if transaction_id.map_or(false, |id| undo_tree.node(id).is_none()) {
+            return Vec::new();
+        }
+
+        let current_path = undo_tree.path_to(undo_tree.current());
+        let target_path = undo_tree.path_to(transaction_id);
+        let common_len = current_path
+            .iter()
+            .zip(&target_path)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let transactions = current_path[common_len..]
+            .iter()
+            .rev()
+            .chain(&target_path[common_len..])
+            .filter_map(|id| Some(undo_tree.node(*id)?.transaction().clone()))
+            .collect::<Vec<_>>();
+        self.history.reset_stacks_to_undo_tree_node(transaction_id);
+
+        transactions
+            .into_iter()
+            .map(|transaction| self.undo_or_redo(transaction).unwrap())
+            .collect()
+    }
+
     pub fn redo_to_transaction(&mut self, transaction_id: TransactionId) -> Vec<Operation> {
         let transactions = self
             .history
@@ -1295,13 +1494,23 @@ impl Buffer {
         &'a self,
         transaction: &'a Transaction,
     ) -> impl 'a + Iterator<Item = Range<D>>
+    where
+        D: TextDimension,
+    {
+        self.edited_ranges_for_edit_ids(transaction.edit_ids.iter())
+    }
+
+    pub fn edited_ranges_for_edit_ids<'a, D>(
+        &'a self,
+        edit_ids: impl Iterator<Item = &'a clock::Lamport>,
+    ) -> impl 'a + Iterator<Item = Range<D>>
     where
         D: TextDimension,
     {
         // get fragment ranges
         let mut cursor = self.fragments.cursor::<(Option<&Locator>, usize)>();
         let offset_ranges = self
-            .fragment_ids_for_edits(transaction.edit_ids.iter())
+            .fragment_ids_for_edits(edit_ids)
             .into_iter()
             .filter_map(move |fragment_id| {
                 cursor.seek_forward(&Some(fragment_id), Bias::Left, &None);
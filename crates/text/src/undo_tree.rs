@@ -0,0 +1,254 @@
+use crate::{HistoryEntry, Rope, Transaction, TransactionId};
+use collections::{BTreeMap, HashMap};
+use std::{mem, sync::Arc, time::SystemTime};
+use util::post_inc;
+
+/// The maximum number of restore points kept per buffer. The oldest ones are dropped first.
+const MAX_RESTORE_POINTS: usize = 50;
+
+/// The branching history of a buffer's transactions.
+///
+/// The undo and redo stacks only describe the states that can be reached by undoing and redoing
+/// in a line, and an edit made after undoing discards the redo stack. The undo tree keeps every
+/// state instead, so that an edit made after undoing starts a new branch.
+#[derive(Clone, Debug, Default)]
+pub struct UndoTree {
+    nodes: HashMap<TransactionId, UndoTreeNode>,
+    /// The transactions of the nodes, keyed by the order in which the nodes were created.
+    order: BTreeMap<usize, TransactionId>,
+    next_order: usize,
+    /// The children of the original text, in the order they were created.
+    root_children: Vec<TransactionId>,
+    current: Option<TransactionId>,
+    restore_points: Vec<RestorePoint>,
+}
+
+/// A state of the buffer in its [`UndoTree`], reached by applying a transaction to the state of
+/// its parent.
+#[derive(Clone, Debug)]
+pub struct UndoTreeNode {
+    pub(crate) entry: HistoryEntry,
+    parent: Option<TransactionId>,
+    /// The children of this node, in the order they were created.
+    children: Vec<TransactionId>,
+    order: usize,
+    timestamp: SystemTime,
+}
+
+impl UndoTreeNode {
+    pub fn transaction_id(&self) -> TransactionId {
+        self.entry.transaction.id
+    }
+
+    pub fn transaction(&self) -> &Transaction {
+        &self.entry.transaction
+    }
+
+    /// The node this node's transaction was applied to, or `None` if it was applied to the
+    /// buffer's original text.
+    pub fn parent(&self) -> Option<TransactionId> {
+        self.parent
+    }
+
+    /// When this node's transaction was first recorded.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+}
+
+/// A state of the buffer in its [`UndoTree`] that was given a name, so that it can be found and
+/// returned to later.
+#[derive(Clone, Debug)]
+pub struct RestorePoint {
+    transaction_id: Option<TransactionId>,
+    name: Arc<str>,
+    text: Rope,
+    timestamp: SystemTime,
+}
+
+impl RestorePoint {
+    /// The node of the state, or `None` for the buffer's original text.
+    pub fn transaction_id(&self) -> Option<TransactionId> {
+        self.transaction_id
+    }
+
+    pub fn name(&self) -> &Arc<str> {
+        &self.name
+    }
+
+    /// The text of the buffer in this state.
+    pub fn text(&self) -> &Rope {
+        &self.text
+    }
+
+    /// When the restore point was recorded.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+}
+
+impl UndoTree {
+    /// The nodes of the tree, in the order they were created.
+    pub fn nodes(&self) -> impl Iterator<Item = &UndoTreeNode> + '_ {
+        self.order.values().filter_map(move |id| self.nodes.get(id))
+    }
+
+    /// The node of the buffer's current state, or `None` if the buffer is at its original text.
+    pub fn current(&self) -> Option<TransactionId> {
+        self.current
+    }
+
+    pub fn node(&self, transaction_id: TransactionId) -> Option<&UndoTreeNode> {
+        self.nodes.get(&transaction_id)
+    }
+
+    /// The children of the given node, or of the original text if `parent` is `None`, in the
+    /// order they were created.
+    pub fn children(
+        &self,
+        parent: Option<TransactionId>,
+    ) -> impl DoubleEndedIterator<Item = &UndoTreeNode> + '_ {
+        let children = match parent {
+            Some(parent) => self
+                .nodes
+                .get(&parent)
+                .map_or(&[][..], |node| node.children.as_slice()),
+            None => self.root_children.as_slice(),
+        };
+        children.iter().filter_map(move |id| self.nodes.get(id))
+    }
+
+    fn children_mut(&mut self, parent: Option<TransactionId>) -> Option<&mut Vec<TransactionId>> {
+        match parent {
+            Some(parent) => Some(&mut self.nodes.get_mut(&parent)?.children),
+            None => Some(&mut self.root_children),
+        }
+    }
+
+    /// Adds the given nodes to the children of `parent`, keeping them in the order they were
+    /// created.
+    fn attach(&mut self, parent: Option<TransactionId>, children: &[TransactionId]) {
+        let mut siblings = self.children_mut(parent).map(mem::take).unwrap_or_default();
+        let order = |id: &TransactionId| self.nodes.get(id).map_or(0, |node| node.order);
+        // New nodes are always the most recent child, so only reattached ones need sorting.
+        let is_sorted = children.len() == 1
+            && siblings
+                .last()
+                .map_or(true, |last| order(last) < order(&children[0]));
+        siblings.extend_from_slice(children);
+        if !is_sorted {
+            siblings.sort_by_key(order);
+        }
+        if let Some(children) = self.children_mut(parent) {
+            *children = siblings;
+        }
+    }
+
+    fn detach(&mut self, parent: Option<TransactionId>, child: TransactionId) {
+        if let Some(children) = self.children_mut(parent) {
+            children.retain(|id| *id != child);
+        }
+    }
+
+    /// Returns the transactions leading from the original text to the given node, including the
+    /// node's own transaction.
+    pub fn path_to(&self, transaction_id: Option<TransactionId>) -> Vec<TransactionId> {
+        let mut path = Vec::new();
+        let mut next = transaction_id;
+        while let Some(transaction_id) = next {
+            path.push(transaction_id);
+            next = self.node(transaction_id).and_then(|node| node.parent);
+        }
+        path.reverse();
+        path
+    }
+
+    /// Records the state reached by applying the given entry's transaction to `parent`, and
+    /// makes it the current state. The node of a transaction that was already recorded keeps its
+    /// entry, which is kept up to date by [`UndoTree::update`] and [`UndoTree::append_edits`].
+    pub(crate) fn record(&mut self, entry: &HistoryEntry, parent: Option<TransactionId>) {
+        let transaction_id = entry.transaction.id;
+        if let Some(node) = self.nodes.get_mut(&transaction_id) {
+            let old_parent = mem::replace(&mut node.parent, parent);
+            if old_parent != parent {
+                self.detach(old_parent, transaction_id);
+                self.attach(parent, &[transaction_id]);
+            }
+        } else {
+            let order = post_inc(&mut self.next_order);
+            self.order.insert(order, transaction_id);
+            self.nodes.insert(
+                transaction_id,
+                UndoTreeNode {
+                    entry: entry.clone(),
+                    parent,
+                    children: Vec::new(),
+                    order,
+                    timestamp: SystemTime::now(),
+                },
+            );
+            self.attach(parent, &[transaction_id]);
+        }
+        self.current = Some(transaction_id);
+    }
+
+    /// Updates the node of the given entry's transaction after edits were merged into it.
+    pub(crate) fn update(&mut self, entry: &HistoryEntry) {
+        if let Some(node) = self.nodes.get_mut(&entry.transaction.id) {
+            node.entry = entry.clone();
+        }
+    }
+
+    /// Updates the node of the given entry's transaction after the edits from `edit_ix` onwards
+    /// were grouped into it, without copying the edits it already had.
+    pub(crate) fn append_edits(&mut self, entry: &HistoryEntry, edit_ix: usize) {
+        if let Some(node) = self.nodes.get_mut(&entry.transaction.id) {
+            let edit_ids = &entry.transaction.edit_ids[edit_ix..];
+            node.entry.transaction.edit_ids.extend_from_slice(edit_ids);
+            node.entry.last_edit_at = entry.last_edit_at;
+        }
+    }
+
+    pub(crate) fn set_current(&mut self, transaction_id: Option<TransactionId>) {
+        self.current = transaction_id;
+    }
+
+    /// The restore points of the tree, in the order they were recorded.
+    pub fn restore_points(&self) -> &[RestorePoint] {
+        &self.restore_points
+    }
+
+    /// Records the current state, whose text is given, as a restore point.
+    pub(crate) fn add_restore_point(&mut self, name: Arc<str>, text: Rope) {
+        self.restore_points.push(RestorePoint {
+            transaction_id: self.current,
+            name,
+            text,
+            timestamp: SystemTime::now(),
+        });
+        let excess = self.restore_points.len().saturating_sub(MAX_RESTORE_POINTS);
+        self.restore_points.drain(..excess);
+    }
+
+    /// Removes the node of the given transaction, attaching its children to its parent.
+    pub(crate) fn remove(&mut self, transaction_id: TransactionId) {
+        let Some(node) = self.nodes.remove(&transaction_id) else {
+            return;
+        };
+        self.order.remove(&node.order);
+        self.detach(node.parent, transaction_id);
+        for child in &node.children {
+            if let Some(child) = self.nodes.get_mut(child) {
+                child.parent = node.parent;
+            }
+        }
+        if !node.children.is_empty() {
+            self.attach(node.parent, &node.children);
+        }
+        if self.current == Some(transaction_id) {
+            self.current = node.parent;
+        }
+        self.restore_points
+            .retain(|point| point.transaction_id != Some(transaction_id));
+    }
+}
@@ -347,6 +347,14 @@ pub struct ThemeColorsContent {
     #[serde(rename = "editor.active_wrap_guide")]
     pub editor_active_wrap_guide: Option<String>,
 
+    /// Used for the vertical guides marking the indentation levels of the text.
+    #[serde(rename = "editor.indent_guide")]
+    pub editor_indent_guide: Option<String>,
+
+    /// Used for the indent guide of the innermost indented block containing the cursor.
+    #[serde(rename = "editor.indent_guide_active")]
+    pub editor_indent_guide_active: Option<String>,
+
     /// Read-access of a symbol, like reading a variable.
     ///
     /// A document highlight is a range inside a text document which deserves
@@ -707,6 +715,14 @@ impl ThemeColorsContent {
                 .editor_active_wrap_guide
                 .as_ref()
                 .and_then(|color| try_parse_color(&color).ok()),
+            editor_indent_guide: self
+                .editor_indent_guide
+                .as_ref()
+                .and_then(|color| try_parse_color(&color).ok()),
+            editor_indent_guide_active: self
+                .editor_indent_guide_active
+                .as_ref()
+                .and_then(|color| try_parse_color(&color).ok()),
             editor_document_highlight_read_background: self
                 .editor_document_highlight_read_background
                 .as_ref()
@@ -153,6 +153,10 @@ pub struct ThemeColors {
     pub editor_invisible: Hsla,
     pub editor_wrap_guide: Hsla,
     pub editor_active_wrap_guide: Hsla,
+    /// Used for the vertical guides marking the indentation levels of the text.
+    pub editor_indent_guide: Hsla,
+    /// Used for the indent guide of the innermost indented block containing the cursor.
+    pub editor_indent_guide_active: Hsla,
     /// Read-access of a symbol, like reading a variable.
     ///
     /// A document highlight is a range inside a text document which deserves
@@ -41,6 +41,7 @@ pub enum IconName {
     BellRing,
     BellDot,
     Bolt,
+    Bookmark,
     CaseSensitive,
     Check,
     ChevronDown,
@@ -93,7 +94,9 @@ pub enum IconName {
     Option,
     PageDown,
     PageUp,
+    Play,
     Plus,
+    PreserveCase,
     Public,
     Quote,
     Replace,
@@ -133,6 +136,7 @@ impl IconName {
             IconName::BellRing => "icons/bell_ring.svg",
             IconName::BellDot => "icons/bell_dot.svg",
             IconName::Bolt => "icons/bolt.svg",
+            IconName::Bookmark => "icons/bookmark.svg",
             IconName::CaseSensitive => "icons/case_insensitive.svg",
             IconName::Check => "icons/check.svg",
             IconName::ChevronDown => "icons/chevron_down.svg",
@@ -185,7 +189,9 @@ impl IconName {
             IconName::Option => "icons/option.svg",
             IconName::PageDown => "icons/page_down.svg",
             IconName::PageUp => "icons/page_up.svg",
+            IconName::Play => "icons/play.svg",
             IconName::Plus => "icons/plus.svg",
+            IconName::PreserveCase => "icons/preserve_case.svg",
             IconName::Public => "icons/public.svg",
             IconName::Quote => "icons/quote.svg",
             IconName::Replace => "icons/replace.svg",
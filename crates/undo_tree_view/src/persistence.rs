@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+
+use db::{define_connection, query, sqlez_macros::sql};
+use workspace::WorkspaceDb;
+
+define_connection! {
+    // Current schema shape using pseudo-rust syntax:
+    // undo_histories(
+    //   path: PathBuf,
+    //   states: String, // A JSON array of `PersistedUndoState`s
+    // )
+    pub static ref DB: UndoHistoryDb<WorkspaceDb> =
+        &[sql!(
+            CREATE TABLE undo_histories(
+                path BLOB NOT NULL PRIMARY KEY,
+                states TEXT NOT NULL
+            ) STRICT;
+        )];
+}
+
+impl UndoHistoryDb {
+    query! {
+        pub async fn get_undo_history(path: PathBuf) -> Result<Option<String>> {
+            SELECT states FROM undo_histories
+            WHERE path = ?
+        }
+    }
+
+    query! {
+        pub async fn save_undo_history(path: PathBuf, states: String) -> Result<()> {
+            INSERT OR REPLACE INTO undo_histories(path, states)
+            VALUES (?, ?)
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use anyhow;
+use schemars::JsonSchema;
+use serde_derive::{Deserialize, Serialize};
+use settings::Settings;
+
+#[derive(Deserialize, Debug)]
+pub struct UndoTreeSettings {
+    pub persist: bool,
+    pub max_persisted_states: usize,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize, JsonSchema, Debug)]
+pub struct UndoTreeSettingsContent {
+    /// Whether to save the undo history of files in the workspace database, so
+    /// that their states from previous sessions can be restored from the undo tree.
+    ///
+    /// Default: false
+    pub persist: Option<bool>,
+    /// The maximum number of states to save for each file.
+    ///
+    /// Default: 100
+    pub max_persisted_states: Option<usize>,
+}
+
+impl Settings for UndoTreeSettings {
+    const KEY: Option<&'static str> = Some("undo_tree");
+
+    type FileContent = UndoTreeSettingsContent;
+
+    fn load(
+        default_value: &Self::FileContent,
+        user_values: &[&Self::FileContent],
+        _: &mut gpui::AppContext,
+    ) -> anyhow::Result<Self> {
+        Self::load_via_json_merge(default_value, user_values)
+    }
+}
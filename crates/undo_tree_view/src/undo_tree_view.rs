@@ -0,0 +1,764 @@
+mod persistence;
+mod undo_tree_settings;
+
+use chrono::{DateTime, Local};
+use collections::HashMap;
+use editor::{scroll::Autoscroll, Editor, EditorMode};
+use gpui::{
+    actions, rems, AppContext, Context as _, DismissEvent, EntityId, EventEmitter, FocusHandle,
+    FocusableView, Global, Model, ModelContext, Render, Subscription, Task, View, ViewContext,
+    VisualContext, WeakModel, WeakView, WindowContext,
+};
+use language::Buffer;
+use persistence::DB;
+use picker::{Picker, PickerDelegate};
+use serde_derive::{Deserialize, Serialize};
+use settings::Settings;
+use std::{
+    hash::Hash,
+    ops::Range,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use text::{Rope, Transaction, TransactionId};
+use ui::{
+    prelude::*,
+    utils::{format_distance_from_now, DateTimeType},
+    ListItem, ListItemSpacing,
+};
+use undo_tree_settings::UndoTreeSettings;
+use util::ResultExt;
+use workspace::ModalView;
+
+actions!(undo_tree_view, [Toggle]);
+
+const SAVE_UNDO_HISTORY_DEBOUNCE: Duration = Duration::from_secs(1);
+const MAX_PREVIEW_LEN: usize = 60;
+
+pub fn init(cx: &mut AppContext) {
+    UndoTreeSettings::register(cx);
+    cx.observe_new_views(UndoTreeView::register).detach();
+}
+
+pub fn toggle(editor: View<Editor>, _: &Toggle, cx: &mut WindowContext) {
+    let Some(buffer) = editor.read(cx).buffer().read(cx).as_singleton() else {
+        return;
+    };
+    if let Some(workspace) = editor.read(cx).workspace() {
+        workspace.update(cx, |workspace, cx| {
+            workspace.toggle_modal(cx, |cx| UndoTreeView::new(editor, buffer, cx));
+        })
+    }
+}
+
+pub struct UndoTreeView {
+    picker: View<Picker<UndoTreeViewDelegate>>,
+}
+
+impl FocusableView for UndoTreeView {
+    fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
+        self.picker.focus_handle(cx)
+    }
+}
+
+impl EventEmitter<DismissEvent> for UndoTreeView {}
+impl ModalView for UndoTreeView {}
+
+impl Render for UndoTreeView {
+    fn render(&mut self, _cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex().w(rems(34.)).child(self.picker.clone())
+    }
+}
+
+impl UndoTreeView {
+    fn register(editor: &mut Editor, cx: &mut ViewContext<Editor>) {
+        if editor.mode() != EditorMode::Full {
+            return;
+        }
+
+        let handle = cx.view().downgrade();
+        editor.register_action(move |action, cx| {
+            if let Some(editor) = handle.upgrade() {
+                toggle(editor, action, cx);
+            }
+        });
+        if let Some(buffer) = editor.buffer().read(cx).as_singleton() {
+            UndoHistory::observe(buffer, cx);
+        }
+    }
+
+    fn new(editor: View<Editor>, buffer: Model<Buffer>, cx: &mut ViewContext<Self>) -> Self {
+        let delegate = UndoTreeViewDelegate::new(cx.view().downgrade(), editor, buffer, cx);
+        let picker = cx.new_view(|cx| Picker::new(delegate, cx).max_height(vh(0.75, cx)));
+        UndoTreeView { picker }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum UndoTreeTarget {
+    /// A state of the buffer's undo tree in this session, or its original text.
+    State(Option<TransactionId>),
+    /// A state saved by a previous session, given by its index.
+    PersistedState(usize),
+}
+
+struct UndoTreeEntry {
+    target: UndoTreeTarget,
+    label: String,
+    depth: usize,
+    timestamp: Option<SystemTime>,
+    is_current: bool,
+}
+
+struct UndoTreeViewDelegate {
+    undo_tree_view: WeakView<UndoTreeView>,
+    editor: View<Editor>,
+    buffer: Model<Buffer>,
+    entries: Vec<UndoTreeEntry>,
+    /// The number of entries that belong to this session, which come before the persisted ones.
+    session_entry_count: usize,
+    persisted_states: Arc<[PersistedUndoState]>,
+    matches: Vec<usize>,
+    selected_index: usize,
+}
+
+impl UndoTreeViewDelegate {
+    fn new(
+        undo_tree_view: WeakView<UndoTreeView>,
+        editor: View<Editor>,
+        buffer: Model<Buffer>,
+        cx: &mut ViewContext<UndoTreeView>,
+    ) -> Self {
+        let mut entries = session_entries(buffer.read(cx));
+        let session_entry_count = entries.len();
+        let persisted_states = cx
+            .try_global::<UndoHistories>()
+            .and_then(|histories| histories.0.get(&buffer.entity_id()))
+            .and_then(|history| history.read(cx).previous_states.clone())
+            .unwrap_or_default();
+        entries.extend(persisted_entries(&persisted_states));
+
+        let selected_index = entries
+            .iter()
+            .position(|entry| entry.is_current)
+            .unwrap_or(0);
+        Self {
+            undo_tree_view,
+            editor,
+            buffer,
+            matches: (0..entries.len()).collect(),
+            entries,
+            session_entry_count,
+            persisted_states,
+            selected_index,
+        }
+    }
+}
+
+impl PickerDelegate for UndoTreeViewDelegate {
+    type ListItem = ListItem;
+
+    fn placeholder_text(&self) -> Arc<str> {
+        "Search undo history...".into()
+    }
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn separators_after_indices(&self) -> Vec<usize> {
+        let session_match_count = self
+            .matches
+            .iter()
+            .take_while(|ix| **ix < self.session_entry_count)
+            .count();
+        if session_match_count > 0 && session_match_count < self.matches.len() {
+            vec![session_match_count - 1]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn set_selected_index(&mut self, ix: usize, _: &mut ViewContext<Picker<Self>>) {
+        self.selected_index = ix;
+    }
+
+    fn update_matches(&mut self, query: String, _: &mut ViewContext<Picker<Self>>) -> Task<()> {
+        let query = query.to_lowercase();
+        self.matches = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.label.to_lowercase().contains(&query))
+            .map(|(ix, _)| ix)
+            .collect();
+        self.selected_index = self
+            .matches
+            .iter()
+            .position(|ix| self.entries[*ix].is_current)
+            .unwrap_or(0);
+        Task::ready(())
+    }
+
+    fn confirm(&mut self, _: bool, cx: &mut ViewContext<Picker<Self>>) {
+        let Some(entry) = self
+            .matches
+            .get(self.selected_index)
+            .map(|ix| &self.entries[*ix])
+        else {
+            return;
+        };
+
+        match entry.target {
+            UndoTreeTarget::State(transaction_id) => {
+                self.buffer.update(cx, |buffer, cx| {
+                    buffer.go_to_undo_tree_node(transaction_id, cx);
+                });
+            }
+            UndoTreeTarget::PersistedState(ix) => {
+                let states = self.persisted_states.clone();
+                let buffer = self.buffer.clone();
+                let editor = self.editor.clone();
+                cx.spawn(|_, mut cx| async move {
+                    let text = cx
+                        .background_executor()
+                        .spawn(async move { state_text(&states, ix) })
+                        .await?;
+                    buffer
+                        .update(&mut cx, |buffer, cx| buffer.set_text(text, cx))
+                        .ok()?;
+                    editor
+                        .update(&mut cx, |editor, cx| {
+                            editor.request_autoscroll(Autoscroll::fit(), cx)
+                        })
+                        .ok()
+                })
+                .detach();
+            }
+        }
+        self.editor.update(cx, |editor, cx| {
+            editor.request_autoscroll(Autoscroll::fit(), cx);
+            editor.focus(cx);
+        });
+        self.dismissed(cx);
+    }
+
+    fn dismissed(&mut self, cx: &mut ViewContext<Picker<Self>>) {
+        self.undo_tree_view
+            .update(cx, |_, cx| cx.emit(DismissEvent))
+            .log_err();
+    }
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        _: &mut ViewContext<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let entry = &self.entries[*self.matches.get(ix)?];
+        let timestamp = entry.timestamp.map(|timestamp| {
+            format_distance_from_now(
+                DateTimeType::Local(DateTime::<Local>::from(timestamp)),
+                true,
+                true,
+                false,
+            )
+        });
+
+        Some(
+            ListItem::new(ix)
+                .inset(true)
+                .spacing(ListItemSpacing::Sparse)
+                .selected(selected)
+                .start_slot(Icon::new(IconName::Check).size(IconSize::Small).color(
+                    if entry.is_current {
+                        Color::Accent
+                    } else {
+                        Color::Hidden
+                    },
+                ))
+                .child(
+                    div()
+                        .pl(rems(entry.depth as f32))
+                        .child(Label::new(entry.label.clone())),
+                )
+                .end_slot(timestamp.map(|timestamp| {
+                    Label::new(timestamp)
+                        .size(LabelSize::Small)
+                        .color(Color::Muted)
+                })),
+        )
+    }
+}
+
+/// Returns the entries for the buffer's original text and the states of its undo tree.
+fn session_entries(buffer: &Buffer) -> Vec<UndoTreeEntry> {
+    let undo_tree = buffer.undo_tree();
+    let positions = undo_tree
+        .nodes()
+        .enumerate()
+        .map(|(ix, node)| (node.transaction_id(), (ix, node)))
+        .collect::<HashMap<_, _>>();
+    let nodes = undo_tree
+        .nodes()
+        .map(|node| (node.transaction_id(), node.parent()))
+        .collect::<Vec<_>>();
+
+    let mut entries = vec![UndoTreeEntry {
+        target: UndoTreeTarget::State(None),
+        label: "Original text".into(),
+        depth: 0,
+        timestamp: None,
+        is_current: undo_tree.current().is_none(),
+    }];
+    entries.extend(
+        layout_tree(&nodes)
+            .into_iter()
+            .map(|(transaction_id, depth)| {
+                let (ix, node) = positions[&transaction_id];
+                UndoTreeEntry {
+                    target: UndoTreeTarget::State(Some(transaction_id)),
+                    label: format!(
+                        "{}. {}",
+                        ix + 1,
+                        transaction_preview(buffer, node.transaction())
+                    ),
+                    depth,
+                    timestamp: Some(node.timestamp()),
+                    is_current: undo_tree.current() == Some(transaction_id),
+                }
+            }),
+    );
+    entries
+}
+
+fn persisted_entries(states: &[PersistedUndoState]) -> Vec<UndoTreeEntry> {
+    let nodes = states
+        .iter()
+        .enumerate()
+        .map(|(ix, state)| (ix, state.parent))
+        .collect::<Vec<_>>();
+    layout_tree(&nodes)
+        .into_iter()
+        .map(|(ix, depth)| UndoTreeEntry {
+            target: UndoTreeTarget::PersistedState(ix),
+            label: states[ix].preview.clone(),
+            depth,
+            timestamp: Some(states[ix].timestamp),
+            is_current: false,
+        })
+        .collect()
+}
+
+/// Orders the nodes of a tree for display, given as `(id, parent)` pairs in the order they were
+/// created. Each node is followed by its subtree, in which the node's most recent child continues
+/// at the node's depth and older children branch off one level deeper.
+fn layout_tree<T: Copy + Eq + Hash>(nodes: &[(T, Option<T>)]) -> Vec<(T, usize)> {
+    fn push_children<T: Copy + Eq + Hash>(
+        children: &HashMap<Option<T>, Vec<T>>,
+        parent: Option<T>,
+        depth: usize,
+        stack: &mut Vec<(T, usize)>,
+    ) {
+        if let Some((newest, older)) = children.get(&parent).and_then(|ids| ids.split_last()) {
+            stack.push((*newest, depth));
+            stack.extend(older.iter().rev().map(|id| (*id, depth + 1)));
+        }
+    }
+
+    let mut children = HashMap::<Option<T>, Vec<T>>::default();
+    for (id, parent) in nodes {
+        children.entry(*parent).or_default().push(*id);
+    }
+
+    let mut layout = Vec::with_capacity(nodes.len());
+    let mut stack = Vec::new();
+    push_children(&children, None, 0, &mut stack);
+    while let Some((id, depth)) = stack.pop() {
+        layout.push((id, depth));
+        push_children(&children, Some(id), depth, &mut stack);
+    }
+    layout
+}
+
+/// Describes a transaction by the first line of text it inserted.
+fn transaction_preview(buffer: &Buffer, transaction: &Transaction) -> String {
+    let inserted_text = transaction
+        .edit_ids
+        .iter()
+        .filter_map(|edit_id| match buffer.operations().get(edit_id)? {
+            text::Operation::Edit(edit) => Some(edit),
+            text::Operation::Undo(_) => None,
+        })
+        .flat_map(|edit| edit.new_text.iter())
+        .find_map(|text| text.lines().map(str::trim).find(|line| !line.is_empty()));
+    match inserted_text {
+        Some(line) if line.chars().count() > MAX_PREVIEW_LEN => {
+            let line = line.chars().take(MAX_PREVIEW_LEN).collect::<String>();
+            format!("{line}…")
+        }
+        Some(line) => line.to_string(),
+        None => "Deleted text".to_string(),
+    }
+}
+
+/// A state of a buffer saved to the database, so that it can be restored in later sessions.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct PersistedUndoState {
+    /// The index of the state this state was reached from.
+    parent: Option<usize>,
+    timestamp: SystemTime,
+    preview: String,
+    /// The index of the state whose text `edits` apply to, or `None` if they apply to an empty
+    /// text. States are always saved after their base.
+    base: Option<usize>,
+    /// The ranges of the base state's text that were replaced to reach this state, in order,
+    /// along with the text that replaced them.
+    edits: Vec<(Range<usize>, String)>,
+}
+
+/// Returns the text of the state with the given index, applying the edits of each state it was
+/// reached from in turn, or `None` if they don't fit together.
+fn state_text(states: &[PersistedUndoState], ix: usize) -> Option<String> {
+    let mut chain = vec![ix];
+    let mut next = states.get(ix)?.base;
+    while let Some(base) = next {
+        if base >= *chain.last()? {
+            return None;
+        }
+        chain.push(base);
+        next = states.get(base)?.base;
+    }
+
+    let mut text = String::new();
+    for ix in chain.into_iter().rev() {
+        for (range, new_text) in states[ix].edits.iter().rev() {
+            text.get(range.clone())?;
+            text.replace_range(range.clone(), new_text);
+        }
+    }
+    Some(text)
+}
+
+/// Removes all but the `max_len` most recent states, detaching the remaining states from the
+/// parents that were removed, and saving the whole text of the states whose base was removed.
+fn truncate_states(states: &mut Vec<PersistedUndoState>, max_len: usize) {
+    let excess = states.len().saturating_sub(max_len);
+    if excess == 0 {
+        return;
+    }
+    for ix in excess..states.len() {
+        if states[ix].base.map_or(false, |base| base < excess) {
+            let text = state_text(states, ix);
+            let state = &mut states[ix];
+            state.base = None;
+            state.edits = text.into_iter().map(|text| (0..0, text)).collect();
+        }
+    }
+    states.drain(..excess);
+    for state in states {
+        state.parent = state.parent.and_then(|parent| parent.checked_sub(excess));
+        state.base = state.base.map(|base| base - excess);
+    }
+}
+
+/// The undo histories being saved, by the buffers they belong to.
+#[derive(Default)]
+struct UndoHistories(HashMap<EntityId, Model<UndoHistory>>);
+
+impl Global for UndoHistories {}
+
+/// A state of a buffer's undo tree that it has been in during this session.
+#[derive(Clone)]
+struct RecordedState {
+    transaction_id: Option<TransactionId>,
+    /// The index of the state that `edits` apply to, or `None` for the state the buffer was in
+    /// when it was opened.
+    base: Option<usize>,
+    /// The version of the buffer when it was in the base state.
+    base_version: clock::Global,
+    /// The ranges of the base state's text that were replaced to reach this state, in order,
+    /// along with the text that replaced them.
+    edits: Arc<[(Range<usize>, String)]>,
+    timestamp: SystemTime,
+    preview: String,
+}
+
+/// Keeps the edits between the states of a buffer's undo tree, and saves them to the database
+/// along with the states saved by previous sessions.
+struct UndoHistory {
+    buffer: WeakModel<Buffer>,
+    path: PathBuf,
+    /// The text the buffer was opened with, which the edits of the other states build upon.
+    opened_text: Rope,
+    /// The states the buffer has been in during this session, in the order it first reached them.
+    states: Vec<RecordedState>,
+    state_ixs: HashMap<Option<TransactionId>, usize>,
+    /// The index of the state the buffer was last in, and the buffer's version at the time.
+    last_state: (usize, clock::Global),
+    /// The states saved by previous sessions, once they have been read from the database.
+    previous_states: Option<Arc<[PersistedUndoState]>>,
+    save_task: Option<Task<Option<()>>>,
+    _load_task: Task<Option<()>>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl UndoHistory {
+    fn observe(buffer: Model<Buffer>, cx: &mut AppContext) {
+        if !UndoTreeSettings::get_global(cx).persist
+            || cx
+                .default_global::<UndoHistories>()
+                .0
+                .contains_key(&buffer.entity_id())
+        {
+            return;
+        }
+        let Some(path) = buffer
+            .read(cx)
+            .file()
+            .and_then(|file| file.as_local())
+            .map(|file| file.abs_path(cx))
+        else {
+            return;
+        };
+
+        let history = cx.new_model(|cx| {
+            let buffer_id = buffer.entity_id();
+            let load_task = cx.spawn({
+                let path = path.clone();
+                |this, mut cx| async move {
+                    let states = DB.get_undo_history(path).await.log_err().flatten();
+                    let states = cx
+                        .background_executor()
+                        .spawn(async move {
+                            match states {
+                                Some(states) => serde_json::from_str(&states).log_err(),
+                                None => Some(Vec::new()),
+                            }
+                        })
+                        .await?;
+                    this.update(&mut cx, |this, cx| {
+                        this.previous_states = Some(states.into());
+                        if this.states.len() > 1 {
+                            this.schedule_save(cx);
+                        }
+                    })
+                    .ok()
+                }
+            });
+
+            let transaction_id = buffer.read(cx).undo_tree().current();
+            let version = buffer.read(cx).version();
+            Self {
+                buffer: buffer.downgrade(),
+                path,
+                opened_text: buffer.read(cx).as_rope().clone(),
+                states: vec![RecordedState {
+                    transaction_id,
+                    base: None,
+                    base_version: version.clone(),
+                    edits: Arc::from([]),
+                    timestamp: SystemTime::now(),
+                    preview: "Opened".into(),
+                }],
+                state_ixs: HashMap::from_iter([(transaction_id, 0)]),
+                last_state: (0, version),
+                previous_states: None,
+                save_task: None,
+                _load_task: load_task,
+                _subscriptions: vec![
+                    cx.subscribe(&buffer, Self::on_buffer_event),
+                    cx.observe_release(&buffer, move |_, _, cx| {
+                        cx.update_global(|histories: &mut UndoHistories, _| {
+                            histories.0.remove(&buffer_id);
+                        });
+                    }),
+                ],
+            }
+        });
+        cx.default_global::<UndoHistories>()
+            .0
+            .insert(buffer.entity_id(), history);
+    }
+
+    fn on_buffer_event(
+        &mut self,
+        buffer: Model<Buffer>,
+        event: &language::Event,
+        cx: &mut ModelContext<Self>,
+    ) {
+        if !matches!(event, language::Event::Edited) {
+            return;
+        }
+
+        let buffer = buffer.read(cx);
+        let transaction_id = buffer.undo_tree().current();
+        let edits_since = |version: &clock::Global| {
+            buffer
+                .edits_since::<usize>(version)
+                .map(|edit| (edit.old, buffer.text_for_range(edit.new).collect()))
+                .collect::<Arc<[_]>>()
+        };
+        match self.state_ixs.get(&transaction_id).copied() {
+            // Edits grouped into the transaction of the last state change that state's text.
+            Some(ix) if ix == self.last_state.0 => {
+                let state = &mut self.states[ix];
+                if state.base.is_some() {
+                    state.edits = edits_since(&state.base_version);
+                }
+            }
+            Some(_) => {}
+            None => {
+                let (base, base_version) = self.last_state.clone();
+                let preview = transaction_id
+                    .and_then(|transaction_id| buffer.undo_tree().node(transaction_id))
+                    .map_or_else(
+                        || "Original text".to_string(),
+                        |node| transaction_preview(buffer, node.transaction()),
+                    );
+                self.state_ixs.insert(transaction_id, self.states.len());
+                self.states.push(RecordedState {
+                    transaction_id,
+                    base: Some(base),
+                    edits: edits_since(&base_version),
+                    base_version,
+                    timestamp: SystemTime::now(),
+                    preview,
+                });
+            }
+        }
+        self.last_state = (self.state_ixs[&transaction_id], buffer.version());
+        self.schedule_save(cx);
+    }
+
+    /// Saves the states of this session and of previous sessions after a delay, once the states
+    /// of previous sessions have been read.
+    fn schedule_save(&mut self, cx: &mut ModelContext<Self>) {
+        self.save_task = Some(cx.spawn(|this, mut cx| async move {
+            cx.background_executor()
+                .timer(SAVE_UNDO_HISTORY_DEBOUNCE)
+                .await;
+            let (path, save_states) = this
+                .update(&mut cx, |this, cx| {
+                    Some((this.path.clone(), this.states_to_save(cx)?))
+                })
+                .ok()??;
+            let states = cx
+                .background_executor()
+                .spawn(async move { serde_json::to_string(&save_states()) })
+                .await
+                .log_err()?;
+            DB.save_undo_history(path, states).await.log_err()
+        }));
+    }
+
+    /// Returns a function building the states saved by previous sessions followed by the states
+    /// of this session, so that they can be built off the main thread.
+    fn states_to_save(
+        &self,
+        cx: &AppContext,
+    ) -> Option<impl FnOnce() -> Vec<PersistedUndoState> + Send + 'static> {
+        let previous_states = self.previous_states.clone()?;
+        let buffer = self.buffer.upgrade()?;
+        let undo_tree = buffer.read(cx).undo_tree();
+        // States are shown below the states they were reached from in the undo tree, or below
+        // their base if that state wasn't reached during this session.
+        let parents = self
+            .states
+            .iter()
+            .map(|state| {
+                let node = undo_tree.node(state.transaction_id?);
+                node.and_then(|node| self.state_ixs.get(&node.parent()).copied())
+                    .or(state.base)
+            })
+            .collect::<Vec<_>>();
+        let states = self.states.clone();
+        let opened_text = self.opened_text.clone();
+        let max_len = UndoTreeSettings::get_global(cx).max_persisted_states;
+
+        Some(move || {
+            let offset = previous_states.len();
+            let mut saved_states = previous_states.to_vec();
+            saved_states.extend(states.into_iter().zip(parents).map(|(state, parent)| {
+                let edits = if state.base.is_some() {
+                    state.edits.to_vec()
+                } else {
+                    vec![(0..0, opened_text.to_string())]
+                };
+                PersistedUndoState {
+                    parent: parent.map(|parent| parent + offset),
+                    timestamp: state.timestamp,
+                    preview: state.preview,
+                    base: state.base.map(|base| base + offset),
+                    edits,
+                }
+            }));
+            truncate_states(&mut saved_states, max_len);
+            saved_states
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_tree() {
+        // 1 ─ 2 ─ 3
+        //      └─ 4 ─ 5
+        //          └─ 6
+        let nodes = [
+            (1, None),
+            (2, Some(1)),
+            (3, Some(2)),
+            (4, Some(2)),
+            (5, Some(4)),
+            (6, Some(4)),
+        ];
+        assert_eq!(
+            layout_tree(&nodes),
+            vec![(1, 0), (2, 0), (3, 1), (4, 0), (5, 1), (6, 0)]
+        );
+    }
+
+    #[test]
+    fn test_truncate_states() {
+        let state = |base: Option<usize>, range: Range<usize>, text: &str| PersistedUndoState {
+            parent: base,
+            timestamp: SystemTime::UNIX_EPOCH,
+            preview: String::new(),
+            base,
+            edits: vec![(range, text.to_string())],
+        };
+        let mut states = vec![
+            state(None, 0..0, "one"),
+            state(Some(0), 3..3, " two"),
+            state(Some(1), 0..3, "1"),
+            state(Some(0), 0..0, "zero "),
+        ];
+        assert_eq!(state_text(&states, 2).as_deref(), Some("1 two"));
+        assert_eq!(state_text(&states, 3).as_deref(), Some("zero one"));
+
+        // The states whose base was removed keep their whole text instead.
+        truncate_states(&mut states, 3);
+        assert_eq!(
+            states,
+            vec![
+                state(None, 0..0, "one two"),
+                state(Some(0), 0..3, "1"),
+                state(None, 0..0, "zero one"),
+            ]
+        );
+        assert_eq!(state_text(&states, 1).as_deref(), Some("1 two"));
+
+        // Edits that don't fit the text of their base are ignored.
+        states[1].edits = vec![(5..20, String::new())];
+        assert_eq!(state_text(&states, 1), None);
+    }
+}
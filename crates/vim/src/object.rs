@@ -6,7 +6,7 @@ use editor::{
     Bias, DisplayPoint,
 };
 use gpui::{actions, impl_actions, ViewContext, WindowContext};
-use language::{char_kind, CharKind, Selection};
+use language::{char_kind, CharKind, Selection, TextObject};
 use serde::Deserialize;
 use workspace::Workspace;
 
@@ -27,6 +27,10 @@ pub enum Object {
     SquareBrackets,
     CurlyBrackets,
     AngleBrackets,
+    Function,
+    Class,
+    Argument,
+    StringContents,
 }
 
 #[derive(Clone, Deserialize, PartialEq)]
@@ -49,7 +53,11 @@ actions!(
         Parentheses,
         SquareBrackets,
         CurlyBrackets,
-        AngleBrackets
+        AngleBrackets,
+        Function,
+        Class,
+        Argument,
+        StringContents
     ]
 );
 
@@ -82,6 +90,14 @@ pub fn register(workspace: &mut Workspace, _: &mut ViewContext<Workspace>) {
     workspace.register_action(|_: &mut Workspace, _: &VerticalBars, cx: _| {
         object(Object::VerticalBars, cx)
     });
+    workspace
+        .register_action(|_: &mut Workspace, _: &Function, cx: _| object(Object::Function, cx));
+    workspace.register_action(|_: &mut Workspace, _: &Class, cx: _| object(Object::Class, cx));
+    workspace
+        .register_action(|_: &mut Workspace, _: &Argument, cx: _| object(Object::Argument, cx));
+    workspace.register_action(|_: &mut Workspace, _: &StringContents, cx: _| {
+        object(Object::StringContents, cx)
+    });
 }
 
 fn object(object: Object, cx: &mut WindowContext) {
@@ -101,12 +117,16 @@ impl Object {
             | Object::Quotes
             | Object::BackQuotes
             | Object::VerticalBars
-            | Object::DoubleQuotes => false,
+            | Object::DoubleQuotes
+            | Object::StringContents => false,
             Object::Sentence
             | Object::Parentheses
             | Object::AngleBrackets
             | Object::CurlyBrackets
-            | Object::SquareBrackets => true,
+            | Object::SquareBrackets
+            | Object::Function
+            | Object::Class
+            | Object::Argument => true,
         }
     }
 
@@ -120,7 +140,11 @@ impl Object {
             | Object::Parentheses
             | Object::SquareBrackets
             | Object::CurlyBrackets
-            | Object::AngleBrackets => true,
+            | Object::AngleBrackets
+            | Object::Function
+            | Object::Class
+            | Object::Argument
+            | Object::StringContents => true,
         }
     }
 
@@ -136,7 +160,11 @@ impl Object {
             | Object::Parentheses
             | Object::SquareBrackets
             | Object::CurlyBrackets
-            | Object::AngleBrackets => Mode::Visual,
+            | Object::AngleBrackets
+            | Object::Function
+            | Object::Class
+            | Object::Argument
+            | Object::StringContents => Mode::Visual,
         }
     }
 
@@ -179,6 +207,10 @@ impl Object {
             Object::AngleBrackets => {
                 surrounding_markers(map, relative_to, around, self.is_multiline(), '<', '>')
             }
+            Object::Function => syntax_object(map, relative_to, TextObject::Function, around),
+            Object::Class => syntax_object(map, relative_to, TextObject::Type, around),
+            Object::Argument => syntax_object(map, relative_to, TextObject::Argument, around),
+            Object::StringContents => syntax_object(map, relative_to, TextObject::String, around),
         }
     }
 
@@ -198,6 +230,21 @@ impl Object {
     }
 }
 
+/// Returns the range of the innermost text object of the given kind enclosing `relative_to`,
+/// found from the syntax tree.
+fn syntax_object(
+    map: &DisplaySnapshot,
+    relative_to: DisplayPoint,
+    object: TextObject,
+    around: bool,
+) -> Option<Range<DisplayPoint>> {
+    let offset = relative_to.to_offset(map, Bias::Left);
+    let range = map
+        .buffer_snapshot
+        .range_for_text_object(offset..offset, object, around)?;
+    Some(range.start.to_display_point(map)..range.end.to_display_point(map))
+}
+
 /// Returns a range that surrounds the word `relative_to` is in.
 ///
 /// If `relative_to` is at the start of a word, return the word.
@@ -1026,4 +1073,25 @@ mod test {
                 .await;
         }
     }
+
+    #[gpui::test]
+    async fn test_syntax_objects(cx: &mut gpui::TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+
+        cx.set_state("fn a() { b(c, ˇd); }", Mode::Normal);
+        cx.simulate_keystrokes(["d", "a", "a"]);
+        cx.assert_state("fn a() { b(cˇ); }", Mode::Normal);
+
+        cx.set_state("fn a() { b(\"cˇd\"); }", Mode::Normal);
+        cx.simulate_keystrokes(["d", "i", "q"]);
+        cx.assert_state("fn a() { b(\"ˇ\"); }", Mode::Normal);
+
+        cx.set_state("fn a() { b(cˇ); }", Mode::Normal);
+        cx.simulate_keystrokes(["d", "i", "f"]);
+        cx.assert_state("fn a() {ˇ}", Mode::Normal);
+
+        cx.set_state("struct A;\nimpl A {\n    fn ˇb() {}\n}", Mode::Normal);
+        cx.simulate_keystrokes(["d", "a", "c"]);
+        cx.assert_state("struct A;\nˇ", Mode::Normal);
+    }
 }
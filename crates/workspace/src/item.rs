@@ -2,7 +2,7 @@ use crate::{
     pane::{self, Pane},
     persistence::model::ItemId,
     searchable::SearchableItemHandle,
-    workspace_settings::{AutosaveSetting, WorkspaceSettings},
+    workspace_settings::AutosaveSetting,
     DelayedDebouncedEditAction, FollowableItemBuilders, ItemNavHistory, ToolbarItemLocation,
     ViewId, Workspace, WorkspaceId,
 };
@@ -106,6 +106,23 @@ pub trait Item: FocusableView + EventEmitter<Self::Event> {
     fn navigate(&mut self, _: Box<dyn Any>, _: &mut ViewContext<Self>) -> bool {
         false
     }
+    /// Converts navigation data this item pushed to its pane's navigation history into a string,
+    /// so that the history can be persisted with the workspace.
+    fn serialize_navigation_data(
+        &self,
+        _data: &(dyn Any + Send),
+        _cx: &AppContext,
+    ) -> Option<String> {
+        None
+    }
+    /// Converts a string returned by `serialize_navigation_data` back into navigation data.
+    fn deserialize_navigation_data(
+        &self,
+        _data: &str,
+        _cx: &AppContext,
+    ) -> Option<Box<dyn Any + Send>> {
+        None
+    }
     fn tab_tooltip_text(&self, _: &AppContext) -> Option<SharedString> {
         None
     }
@@ -149,6 +166,15 @@ pub trait Item: FocusableView + EventEmitter<Self::Event> {
     fn save(&mut self, _project: Model<Project>, _cx: &mut ViewContext<Self>) -> Task<Result<()>> {
         unimplemented!("save() must be implemented if can_save() returns true")
     }
+    /// Saves the item in response to an autosave trigger rather than an explicit request, which
+    /// may happen while the user is still editing it.
+    fn autosave(
+        &mut self,
+        project: Model<Project>,
+        cx: &mut ViewContext<Self>,
+    ) -> Task<Result<()>> {
+        self.save(project, cx)
+    }
     fn save_as(
         &mut self,
         _project: Model<Project>,
@@ -239,6 +265,7 @@ pub trait ItemHandle: 'static + Send {
     );
     fn is_singleton(&self, cx: &AppContext) -> bool;
     fn boxed_clone(&self) -> Box<dyn ItemHandle>;
+    fn downgrade_item(&self) -> Box<dyn WeakItemHandle>;
     fn clone_on_split(
         &self,
         workspace_id: WorkspaceId,
@@ -253,12 +280,20 @@ pub trait ItemHandle: 'static + Send {
     fn deactivated(&self, cx: &mut WindowContext);
     fn workspace_deactivated(&self, cx: &mut WindowContext);
     fn navigate(&self, data: Box<dyn Any>, cx: &mut WindowContext) -> bool;
+    fn serialize_navigation_data(&self, data: &(dyn Any + Send), cx: &AppContext)
+        -> Option<String>;
+    fn deserialize_navigation_data(
+        &self,
+        data: &str,
+        cx: &AppContext,
+    ) -> Option<Box<dyn Any + Send>>;
     fn item_id(&self) -> EntityId;
     fn to_any(&self) -> AnyView;
     fn is_dirty(&self, cx: &AppContext) -> bool;
     fn has_conflict(&self, cx: &AppContext) -> bool;
     fn can_save(&self, cx: &AppContext) -> bool;
     fn save(&self, project: Model<Project>, cx: &mut WindowContext) -> Task<Result<()>>;
+    fn autosave(&self, project: Model<Project>, cx: &mut WindowContext) -> Task<Result<()>>;
     fn save_as(
         &self,
         project: Model<Project>,
@@ -377,6 +412,10 @@ impl<T: Item> ItemHandle for View<T> {
         Box::new(self.clone())
     }
 
+    fn downgrade_item(&self) -> Box<dyn WeakItemHandle> {
+        Box::new(self.downgrade())
+    }
+
     fn clone_on_split(
         &self,
         workspace_id: WorkspaceId,
@@ -497,7 +536,7 @@ impl<T: Item> ItemHandle for View<T> {
                         }
 
                         ItemEvent::Edit => {
-                            let autosave = WorkspaceSettings::get_global(cx).autosave;
+                            let autosave = Pane::autosave_setting(&item, cx);
                             if let AutosaveSetting::AfterDelay { milliseconds } = autosave {
                                 let delay = Duration::from_millis(milliseconds);
                                 let item = item.clone();
@@ -512,8 +551,8 @@ impl<T: Item> ItemHandle for View<T> {
                 }));
 
             cx.on_blur(&self.focus_handle(cx), move |workspace, cx| {
-                if WorkspaceSettings::get_global(cx).autosave == AutosaveSetting::OnFocusChange {
-                    if let Some(item) = weak_item.upgrade() {
+                if let Some(item) = weak_item.upgrade() {
+                    if Pane::autosave_setting(&item, cx) == AutosaveSetting::OnFocusChange {
                         Pane::autosave_item(&item, workspace.project.clone(), cx)
                             .detach_and_log_err(cx);
                     }
@@ -546,6 +585,22 @@ impl<T: Item> ItemHandle for View<T> {
         self.update(cx, |this, cx| this.navigate(data, cx))
     }
 
+    fn serialize_navigation_data(
+        &self,
+        data: &(dyn Any + Send),
+        cx: &AppContext,
+    ) -> Option<String> {
+        self.read(cx).serialize_navigation_data(data, cx)
+    }
+
+    fn deserialize_navigation_data(
+        &self,
+        data: &str,
+        cx: &AppContext,
+    ) -> Option<Box<dyn Any + Send>> {
+        self.read(cx).deserialize_navigation_data(data, cx)
+    }
+
     fn item_id(&self) -> EntityId {
         self.entity_id()
     }
@@ -570,6 +625,10 @@ impl<T: Item> ItemHandle for View<T> {
         self.update(cx, |item, cx| item.save(project, cx))
     }
 
+    fn autosave(&self, project: Model<Project>, cx: &mut WindowContext) -> Task<Result<()>> {
+        self.update(cx, |item, cx| item.autosave(project, cx))
+    }
+
     fn save_as(
         &self,
         project: Model<Project>,
@@ -118,6 +118,7 @@ pub enum Event {
     RemoveItem { item_id: EntityId },
     Split(SplitDirection),
     ChangeItemTitle,
+    ChangeNavigationHistory,
     Focus,
     ZoomIn,
     ZoomOut,
@@ -144,6 +145,7 @@ impl fmt::Debug for Event {
                 .field("direction", direction)
                 .finish(),
             Event::ChangeItemTitle => f.write_str("ChangeItemTitle"),
+            Event::ChangeNavigationHistory => f.write_str("ChangeNavigationHistory"),
             Event::Focus => f.write_str("Focus"),
             Event::ZoomIn => f.write_str("ZoomIn"),
             Event::ZoomOut => f.write_str("ZoomOut"),
@@ -488,6 +490,7 @@ impl Pane {
 
     fn history_updated(&mut self, cx: &mut ViewContext<Self>) {
         self.toolbar.update(cx, |_, cx| cx.notify());
+        cx.emit(Event::ChangeNavigationHistory);
     }
 
     pub(crate) fn open_item(
@@ -1147,7 +1150,7 @@ impl Pane {
             if save_intent == SaveIntent::Close {
                 let will_autosave = cx.update(|cx| {
                     matches!(
-                        WorkspaceSettings::get_global(cx).autosave,
+                        Self::autosave_setting(&*item, cx),
                         AutosaveSetting::OnFocusChange | AutosaveSetting::OnWindowChange
                     ) && Self::can_autosave_item(&*item, cx)
                 })?;
@@ -1192,6 +1195,19 @@ impl Pane {
         Ok(true)
     }
 
+    /// Returns the autosave setting that applies to the given item, taking into account the
+    /// settings of the worktree that contains it.
+    pub fn autosave_setting(item: &dyn ItemHandle, cx: &AppContext) -> AutosaveSetting {
+        let project_path = item.project_path(cx);
+        let location = project_path.as_ref().map(|project_path| {
+            (
+                project_path.worktree_id.to_usize(),
+                project_path.path.as_ref(),
+            )
+        });
+        WorkspaceSettings::get(location, cx).autosave
+    }
+
     fn can_autosave_item(item: &dyn ItemHandle, cx: &AppContext) -> bool {
         let is_deleted = item.project_entry_ids(cx).is_empty();
         item.is_dirty(cx) && !item.has_conflict(cx) && item.can_save(cx) && !is_deleted
@@ -1203,7 +1219,7 @@ impl Pane {
         cx: &mut WindowContext,
     ) -> Task<Result<()>> {
         if Self::can_autosave_item(item, cx) {
-            item.save(project, cx)
+            item.autosave(project, cx)
         } else {
             Task::ready(Ok(()))
         }
@@ -2045,6 +2061,41 @@ impl NavHistory {
         state.did_update(cx);
     }
 
+    /// Calls the given function with each entry of the backward and forward stacks, from the
+    /// oldest to the newest, along with whether it's on the forward stack.
+    pub fn for_each_navigation_entry(&self, mut f: impl FnMut(&NavigationEntry, bool)) {
+        let state = self.0.lock();
+        for entry in &state.backward_stack {
+            f(entry, false);
+        }
+        for entry in &state.forward_stack {
+            f(entry, true);
+        }
+    }
+
+    /// Appends an entry that was persisted with the workspace to the backward or forward stack.
+    pub fn restore(
+        &mut self,
+        item: Arc<dyn WeakItemHandle>,
+        data: Box<dyn Any + Send>,
+        forward: bool,
+    ) {
+        let state = &mut *self.0.lock();
+        let stack = if forward {
+            &mut state.forward_stack
+        } else {
+            &mut state.backward_stack
+        };
+        if stack.len() >= MAX_NAVIGATION_HISTORY_LEN {
+            stack.pop_front();
+        }
+        stack.push_back(NavigationEntry {
+            item,
+            data: Some(data),
+            timestamp: state.next_timestamp.fetch_add(1, Ordering::SeqCst),
+        });
+    }
+
     pub fn remove_item(&mut self, item_id: EntityId) {
         let mut state = self.0.lock();
         state.paths_by_item.remove(&item_id);
@@ -17,8 +17,8 @@ use uuid::Uuid;
 use crate::WorkspaceId;
 
 use model::{
-    GroupId, PaneId, SerializedItem, SerializedPane, SerializedPaneGroup, SerializedWorkspace,
-    WorkspaceLocation,
+    GroupId, ItemId, PaneId, SerializedItem, SerializedNavigationEntry, SerializedPane,
+    SerializedPaneGroup, SerializedWorkspace, WorkspaceLocation,
 };
 
 use self::model::DockStructure;
@@ -291,6 +291,22 @@ define_connection! {
     // Add pane group flex data
     sql!(
         ALTER TABLE pane_groups ADD COLUMN flexes TEXT;
+    ),
+    // Add pane navigation history
+    sql!(
+        CREATE TABLE navigation_history(
+            workspace_id INTEGER NOT NULL,
+            pane_id INTEGER NOT NULL,
+            position INTEGER NOT NULL,
+            item_id INTEGER NOT NULL,
+            forward INTEGER NOT NULL, // Boolean
+            data TEXT NOT NULL,
+            FOREIGN KEY(workspace_id) REFERENCES workspaces(workspace_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE,
+            FOREIGN KEY(pane_id) REFERENCES panes(pane_id)
+            ON DELETE CASCADE
+        ) STRICT;
     )
     ];
 }
@@ -530,10 +546,10 @@ impl WorkspaceDb {
                     flexes,
                 })
             } else if let Some((pane_id, active)) = pane_id.zip(active) {
-                Ok(SerializedPaneGroup::Pane(SerializedPane::new(
-                    self.get_items(pane_id)?,
-                    active,
-                )))
+                Ok(SerializedPaneGroup::Pane(
+                    SerializedPane::new(self.get_items(pane_id)?, active)
+                        .with_navigation_history(self.get_navigation_history(pane_id)?),
+                ))
             } else {
                 bail!("Pane Group Child was neither a pane group or a pane");
             }
@@ -617,6 +633,8 @@ impl WorkspaceDb {
         ))?((pane_id, parent_id, order))?;
 
         Self::save_items(conn, workspace_id, pane_id, &pane.children).context("Saving items")?;
+        Self::save_navigation_history(conn, workspace_id, pane_id, &pane.navigation_history)
+            .context("Saving navigation history")?;
 
         Ok(pane_id)
     }
@@ -645,6 +663,44 @@ impl WorkspaceDb {
         Ok(())
     }
 
+    fn get_navigation_history(&self, pane_id: PaneId) -> Result<Vec<SerializedNavigationEntry>> {
+        Ok(self.select_bound::<PaneId, (ItemId, String, bool)>(sql!(
+            SELECT item_id, data, forward FROM navigation_history
+            WHERE pane_id = ?
+                ORDER BY position
+        ))?(pane_id)?
+        .into_iter()
+        .map(|(item_id, data, forward)| SerializedNavigationEntry {
+            item_id,
+            data,
+            forward,
+        })
+        .collect())
+    }
+
+    fn save_navigation_history(
+        conn: &Connection,
+        workspace_id: WorkspaceId,
+        pane_id: PaneId,
+        entries: &[SerializedNavigationEntry],
+    ) -> Result<()> {
+        let mut insert = conn.exec_bound(sql!(
+            INSERT INTO navigation_history(workspace_id, pane_id, position, item_id, forward, data) VALUES (?, ?, ?, ?, ?, ?)
+        )).context("Preparing insertion")?;
+        for (position, entry) in entries.iter().enumerate() {
+            insert((
+                workspace_id,
+                pane_id,
+                position,
+                entry.item_id,
+                entry.forward,
+                entry.data.as_str(),
+            ))?;
+        }
+
+        Ok(())
+    }
+
     query! {
         pub async fn update_timestamp(workspace_id: WorkspaceId) -> Result<()> {
             UPDATE workspaces
@@ -1088,4 +1144,50 @@ mod tests {
 
         assert_eq!(workspace.center_group, new_workspace.center_group);
     }
+
+    #[gpui::test]
+    async fn test_navigation_history_serialization() {
+        env_logger::try_init().ok();
+
+        let db = WorkspaceDb(open_test_db("test_navigation_history_serialization").await);
+
+        let navigation_history = vec![
+            SerializedNavigationEntry {
+                item_id: 1,
+                data: "3,4,0".into(),
+                forward: false,
+            },
+            SerializedNavigationEntry {
+                item_id: 2,
+                data: "10,0,5".into(),
+                forward: false,
+            },
+            SerializedNavigationEntry {
+                item_id: 1,
+                data: "20,2,15".into(),
+                forward: true,
+            },
+        ];
+        let center_pane = SerializedPaneGroup::Pane(
+            SerializedPane::new(
+                vec![
+                    SerializedItem::new("Terminal", 1, false),
+                    SerializedItem::new("Terminal", 2, true),
+                ],
+                true,
+            )
+            .with_navigation_history(navigation_history.clone()),
+        );
+
+        let id = &["/tmp"];
+        let workspace = default_workspace(id, &center_pane);
+        db.save_workspace(workspace.clone()).await;
+
+        let new_workspace = db.workspace_for_roots(id).unwrap();
+        assert_eq!(workspace.center_group, new_workspace.center_group);
+        let SerializedPaneGroup::Pane(pane) = new_workspace.center_group else {
+            panic!("expected a pane");
+        };
+        assert_eq!(pane.navigation_history, navigation_history);
+    }
 }
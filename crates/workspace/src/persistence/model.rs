@@ -142,6 +142,7 @@ impl Default for SerializedPaneGroup {
         Self::Pane(SerializedPane {
             children: vec![SerializedItem::default()],
             active: false,
+            navigation_history: Vec::new(),
         })
     }
 }
@@ -218,11 +219,24 @@ impl SerializedPaneGroup {
 pub struct SerializedPane {
     pub(crate) active: bool,
     pub(crate) children: Vec<SerializedItem>,
+    pub(crate) navigation_history: Vec<SerializedNavigationEntry>,
 }
 
 impl SerializedPane {
     pub fn new(children: Vec<SerializedItem>, active: bool) -> Self {
-        SerializedPane { children, active }
+        SerializedPane {
+            children,
+            active,
+            navigation_history: Vec::new(),
+        }
+    }
+
+    pub fn with_navigation_history(
+        mut self,
+        navigation_history: Vec<SerializedNavigationEntry>,
+    ) -> Self {
+        self.navigation_history = navigation_history;
+        self
     }
 
     pub async fn deserialize_to(
@@ -270,10 +284,41 @@ impl SerializedPane {
             })?;
         }
 
+        pane.update(cx, |pane, cx| {
+            for entry in &self.navigation_history {
+                let item = self
+                    .children
+                    .iter()
+                    .zip(&items)
+                    .find(|(serialized_item, _)| serialized_item.item_id == entry.item_id)
+                    .and_then(|(_, item)| item.as_ref());
+                let Some(item) = item else {
+                    continue;
+                };
+                if let Some(data) = item.deserialize_navigation_data(&entry.data, cx) {
+                    pane.nav_history_mut().restore(
+                        item.downgrade_item().into(),
+                        data,
+                        entry.forward,
+                    );
+                }
+            }
+        })?;
+
         anyhow::Ok(items)
     }
 }
 
+/// An entry of a pane's navigation history, which is restored along with the item it navigates
+/// within.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SerializedNavigationEntry {
+    pub item_id: ItemId,
+    pub data: String,
+    /// Whether the entry is on the forward stack rather than the backward one.
+    pub forward: bool,
+}
+
 pub type GroupId = i64;
 pub type PaneId = i64;
 pub type ItemId = u64;
@@ -30,6 +30,8 @@ pub struct SearchOptions {
     pub regex: bool,
     /// Specifies whether the item supports search & replace.
     pub replacement: bool,
+    /// Specifies whether the item can restrict matches to the excerpt under the cursor.
+    pub excerpt: bool,
 }
 
 pub trait SearchableItem: Item + EventEmitter<SearchEvent> {
@@ -41,6 +43,7 @@ pub trait SearchableItem: Item + EventEmitter<SearchEvent> {
             word: true,
             regex: true,
             replacement: true,
+            excerpt: false,
         }
     }
 
@@ -55,6 +58,15 @@ pub trait SearchableItem: Item + EventEmitter<SearchEvent> {
     );
     fn select_matches(&mut self, matches: Vec<Self::Match>, cx: &mut ViewContext<Self>);
     fn replace(&mut self, _: &Self::Match, _: &SearchQuery, _: &mut ViewContext<Self>);
+    fn toggle_search_within_excerpt(&mut self, _enabled: bool, _: &mut ViewContext<Self>) {}
+    fn preserve_case_in_selections(&mut self, _: &mut ViewContext<Self>) {}
+    fn preview_replacements(
+        &mut self,
+        _matches: &[Self::Match],
+        _query: Option<&SearchQuery>,
+        _: &mut ViewContext<Self>,
+    ) {
+    }
     fn match_index_for_direction(
         &mut self,
         matches: &Vec<Self::Match>,
@@ -107,6 +119,14 @@ pub trait SearchableItemHandle: ItemHandle {
     );
     fn select_matches(&self, matches: &Vec<Box<dyn Any + Send>>, cx: &mut WindowContext);
     fn replace(&self, _: &Box<dyn Any + Send>, _: &SearchQuery, _: &mut WindowContext);
+    fn toggle_search_within_excerpt(&self, enabled: bool, cx: &mut WindowContext);
+    fn preserve_case_in_selections(&self, cx: &mut WindowContext);
+    fn preview_replacements(
+        &self,
+        matches: &Vec<Box<dyn Any + Send>>,
+        query: Option<&SearchQuery>,
+        cx: &mut WindowContext,
+    );
     fn match_index_for_direction(
         &self,
         matches: &Vec<Box<dyn Any + Send>>,
@@ -213,6 +233,28 @@ impl<T: SearchableItem> SearchableItemHandle for View<T> {
         let matches = matches.downcast_ref().unwrap();
         self.update(cx, |this, cx| this.replace(matches, query, cx))
     }
+
+    fn toggle_search_within_excerpt(&self, enabled: bool, cx: &mut WindowContext) {
+        self.update(cx, |this, cx| {
+            this.toggle_search_within_excerpt(enabled, cx)
+        })
+    }
+
+    fn preserve_case_in_selections(&self, cx: &mut WindowContext) {
+        self.update(cx, |this, cx| this.preserve_case_in_selections(cx))
+    }
+
+    fn preview_replacements(
+        &self,
+        matches: &Vec<Box<dyn Any + Send>>,
+        query: Option<&SearchQuery>,
+        cx: &mut WindowContext,
+    ) {
+        let matches = downcast_matches(matches);
+        self.update(cx, |this, cx| {
+            this.preview_replacements(&matches, query, cx)
+        });
+    }
 }
 
 fn downcast_matches<T: Any + Clone>(matches: &Vec<Box<dyn Any + Send>>) -> Vec<T> {
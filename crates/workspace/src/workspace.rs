@@ -73,7 +73,10 @@ use uuid::Uuid;
 pub use workspace_settings::{AutosaveSetting, WorkspaceSettings};
 
 use crate::persistence::{
-    model::{DockData, DockStructure, SerializedItem, SerializedPane, SerializedPaneGroup},
+    model::{
+        DockData, DockStructure, SerializedItem, SerializedNavigationEntry, SerializedPane,
+        SerializedPaneGroup,
+    },
     SerializedAxis,
 };
 
@@ -165,6 +168,7 @@ impl_actions!(
         CloseAllItemsAndPanes,
         NewFileInDirection,
         OpenTerminal,
+        RunInTerminal,
         Save,
         SaveAll,
         SwapPaneInDirection,
@@ -221,6 +225,21 @@ pub struct OpenTerminal {
     pub working_directory: PathBuf,
 }
 
+/// Runs a program in a new terminal, which stays open once the program exits.
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+pub struct RunInTerminal {
+    pub command: String,
+    pub args: Vec<String>,
+    /// Whether `command` is a POSIX shell script to run with the terminal's shell, rather than a
+    /// program to run with `args`.
+    #[serde(default)]
+    pub is_shell_script: bool,
+    pub working_directory: Option<PathBuf>,
+    /// The id of the [`project::Event::TerminalTaskFinished`] event emitted once the program
+    /// exits, from [`Project::next_terminal_task_id`].
+    pub task_id: usize,
+}
+
 pub type WorkspaceId = i64;
 
 pub fn init_settings(cx: &mut AppContext) {
@@ -2280,6 +2299,10 @@ impl Workspace {
                 }
                 self.update_window_edited(cx);
             }
+            pane::Event::ChangeNavigationHistory => {
+                self.schedule_serialize(cx);
+                return;
+            }
             pane::Event::RemoveItem { item_id } => {
                 self.update_window_edited(cx);
                 if let hash_map::Entry::Occupied(entry) = self.panes_by_item.entry(*item_id) {
@@ -3060,11 +3083,11 @@ impl Workspace {
                     if let Some(item) = pane.active_item() {
                         item.workspace_deactivated(cx);
                     }
-                    if matches!(
-                        WorkspaceSettings::get_global(cx).autosave,
-                        AutosaveSetting::OnWindowChange | AutosaveSetting::OnFocusChange
-                    ) {
-                        for item in pane.items() {
+                    for item in pane.items() {
+                        if matches!(
+                            Pane::autosave_setting(item.as_ref(), cx),
+                            AutosaveSetting::OnWindowChange | AutosaveSetting::OnFocusChange
+                        ) {
                             Pane::autosave_item(item.as_ref(), self.project.clone(), cx)
                                 .detach_and_log_err(cx);
                         }
@@ -3167,7 +3190,32 @@ impl Workspace {
                 )
             };
 
-            SerializedPane::new(items, active)
+            let mut navigation_history = Vec::new();
+            pane_handle
+                .read(cx)
+                .nav_history()
+                .for_each_navigation_entry(|entry, forward| {
+                    let Some(item) = entry.item.upgrade() else {
+                        return;
+                    };
+                    let item_id = item.item_id().as_u64();
+                    if !items.iter().any(|serialized| serialized.item_id == item_id) {
+                        return;
+                    }
+                    let data = entry
+                        .data
+                        .as_ref()
+                        .and_then(|data| item.serialize_navigation_data(data.as_ref(), cx));
+                    if let Some(data) = data {
+                        navigation_history.push(SerializedNavigationEntry {
+                            item_id,
+                            data,
+                            forward,
+                        });
+                    }
+                });
+
+            SerializedPane::new(items, active).with_navigation_history(navigation_history)
         }
 
         fn build_serialized_pane_group(
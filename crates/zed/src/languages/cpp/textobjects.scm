@@ -0,0 +1,21 @@
+(function_definition
+    body: (_) @function.inside) @function.around
+
+(lambda_expression
+    body: (_) @function.inside) @function.around
+
+(struct_specifier
+    body: (_) @class.inside) @class.around
+
+(class_specifier
+    body: (_) @class.inside) @class.around
+
+(parameter_list
+    (_) @parameter.inside)
+
+(argument_list
+    (_) @parameter.inside)
+
+(string_literal) @string.around
+
+(raw_string_literal) @string.around
@@ -0,0 +1,5 @@
+(
+    (function_declaration
+        name: (_) @name) @run
+    (#match? @name "^Test")
+)
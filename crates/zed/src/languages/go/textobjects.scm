@@ -0,0 +1,20 @@
+(function_declaration
+    body: (_)? @function.inside) @function.around
+
+(method_declaration
+    body: (_)? @function.inside) @function.around
+
+(func_literal
+    body: (_) @function.inside) @function.around
+
+(type_declaration) @class.around
+
+(parameter_list
+    (_) @parameter.inside)
+
+(argument_list
+    (_) @parameter.inside)
+
+(interpreted_string_literal) @string.around
+
+(raw_string_literal) @string.around
@@ -0,0 +1,10 @@
+(
+    (attribute_item (attribute) @_attribute
+        (#match? @_attribute "^([a-z_]+::)?test([(].*)?$"))
+    .
+    (function_item
+        name: (_) @name) @run
+)
+
+(mod_item
+    name: (_) @scope_name) @scope